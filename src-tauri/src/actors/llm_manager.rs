@@ -4,13 +4,14 @@
 // 用消息传递替代锁机制，消除Arc<Mutex<LLMManager>>的锁竞争
 
 // ✅ 修改 1: 引入 OllamaConfig
-use crate::llm::{CodexConfig, LLMConfig, LLMManager, OllamaConfig, QwenConfig, SessionBrief, SessionSummary};
+use crate::llm::{AnalysisContext, CodexConfig, CostEstimate, ProgressSender, LLMConfig, LLMManager, MiddlewareConfig, OllamaConfig, ProviderProfile, QwenConfig, SessionBrief, SessionSummary, SummaryField};
 use anyhow::Result;
 use tokio::sync::{mpsc, oneshot};
 
-use crate::llm::{TimelineAnalysis, TimelineCard, VideoSegment};
+use crate::llm::{ProviderMetrics, ProviderStatus, TimelineAnalysis, TimelineCard, VideoSegment};
 use crate::storage::Database;
 use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 /// LLM管理器命令
@@ -34,12 +35,52 @@ pub enum LLMCommand {
         reply: oneshot::Sender<Result<()>>,
     },
 
+    /// 配置 provider 中间件
+    ConfigureMiddleware {
+        config: MiddlewareConfig,
+        reply: oneshot::Sender<Result<()>>,
+    },
+
+    /// 应用配置档文件中的一个配置档
+    ApplyProfile {
+        profile: Box<ProviderProfile>,
+        reply: oneshot::Sender<Result<()>>,
+    },
+
     /// 分析帧
     AnalyzeFrames {
         frames: Vec<String>,
         reply: oneshot::Sender<Result<SessionSummary>>,
     },
 
+    /// 结合附加上下文分析帧
+    AnalyzeFramesWithContext {
+        frames: Vec<String>,
+        context: AnalysisContext,
+        reply: oneshot::Sender<Result<SessionSummary>>,
+    },
+
+    /// 生成多个候选总结
+    AnalyzeFramesN {
+        frames: Vec<String>,
+        n: usize,
+        reply: oneshot::Sender<Result<Vec<SessionSummary>>>,
+    },
+
+    /// 重新生成总结的单个字段
+    RefineField {
+        frames: Vec<String>,
+        current: SessionSummary,
+        field: SummaryField,
+        reply: oneshot::Sender<Result<SessionSummary>>,
+    },
+
+    /// 分析纯文本会话
+    AnalyzeText {
+        content: String,
+        reply: oneshot::Sender<Result<SessionSummary>>,
+    },
+
     /// 获取配置
     GetConfig { reply: oneshot::Sender<LLMConfig> },
 
@@ -55,6 +96,12 @@ pub enum LLMCommand {
         reply: oneshot::Sender<()>,
     },
 
+    /// 设置流式分析进度通道
+    SetProgressSender {
+        sender: Option<ProgressSender>,
+        reply: oneshot::Sender<()>,
+    },
+
     /// 设置会话时间范围
     SetSessionWindow {
         start: Option<DateTime<Utc>>,
@@ -83,6 +130,12 @@ pub enum LLMCommand {
         reply: oneshot::Sender<Option<i64>>,
     },
 
+    /// 估算分析费用
+    EstimateCost {
+        frames: usize,
+        reply: oneshot::Sender<Option<CostEstimate>>,
+    },
+
     /// 生成时间线卡片
     GenerateTimeline {
         segments: Vec<VideoSegment>,
@@ -109,6 +162,12 @@ pub enum LLMCommand {
         reply: oneshot::Sender<Result<()>>,
     },
 
+    /// 预热已配置的 provider
+    StartProviders { reply: oneshot::Sender<Result<()>> },
+
+    /// 释放已配置的 provider 资源
+    StopProviders { reply: oneshot::Sender<Result<()>> },
+
     /// 健康检查（Ping）
     HealthCheck { reply: oneshot::Sender<()> },
 }
@@ -123,8 +182,13 @@ impl LLMManagerActor {
     /// 创建新的Actor
     pub fn new(manager: LLMManager) -> (Self, LLMHandle) {
         let (sender, receiver) = mpsc::channel(200); // 增加容量到200以支持高负载
+        let metrics = manager.metrics();
         let actor = Self { receiver, manager };
-        let handle = LLMHandle { sender };
+        let handle = LLMHandle {
+            sender,
+            pending_analyses: Arc::new(AtomicUsize::new(0)),
+            metrics,
+        };
         (actor, handle)
     }
 
@@ -152,11 +216,53 @@ impl LLMManagerActor {
                     let _ = reply.send(result);
                 }
 
+                LLMCommand::ConfigureMiddleware { config, reply } => {
+                    let result = self.manager.configure_middleware(config).await;
+                    let _ = reply.send(result);
+                }
+
+                LLMCommand::ApplyProfile { profile, reply } => {
+                    let result = self.manager.apply_profile(*profile).await;
+                    let _ = reply.send(result);
+                }
+
                 LLMCommand::AnalyzeFrames { frames, reply } => {
                     let result = self.manager.analyze_frames(frames).await;
                     let _ = reply.send(result);
                 }
 
+                LLMCommand::AnalyzeFramesWithContext {
+                    frames,
+                    context,
+                    reply,
+                } => {
+                    let result = self
+                        .manager
+                        .analyze_frames_with_context(frames, context)
+                        .await;
+                    let _ = reply.send(result);
+                }
+
+                LLMCommand::AnalyzeFramesN { frames, n, reply } => {
+                    let result = self.manager.analyze_frames_n(frames, n).await;
+                    let _ = reply.send(result);
+                }
+
+                LLMCommand::RefineField {
+                    frames,
+                    current,
+                    field,
+                    reply,
+                } => {
+                    let result = self.manager.refine_field(frames, current, field).await;
+                    let _ = reply.send(result);
+                }
+
+                LLMCommand::AnalyzeText { content, reply } => {
+                    let result = self.manager.analyze_text(content).await;
+                    let _ = reply.send(result);
+                }
+
                 LLMCommand::GetConfig { reply } => {
                     let config = self.manager.get_config().await;
                     let _ = reply.send(config);
@@ -175,6 +281,11 @@ impl LLMManagerActor {
                     let _ = reply.send(()); // 发送确认
                 }
 
+                LLMCommand::SetProgressSender { sender, reply } => {
+                    self.manager.set_progress_sender(sender);
+                    let _ = reply.send(());
+                }
+
                 LLMCommand::SetSessionWindow { start, end, reply } => {
                     self.manager.set_session_window(start, end);
                     let _ = reply.send(()); // 发送确认
@@ -207,6 +318,11 @@ impl LLMManagerActor {
                     let _ = reply.send(id);
                 }
 
+                LLMCommand::EstimateCost { frames, reply } => {
+                    let estimate = self.manager.estimate_cost(frames);
+                    let _ = reply.send(estimate);
+                }
+
                 LLMCommand::GenerateTimeline {
                     segments,
                     previous_cards,
@@ -238,6 +354,16 @@ impl LLMManagerActor {
                     let _ = reply.send(result);
                 }
 
+                LLMCommand::StartProviders { reply } => {
+                    let result = self.manager.start_providers().await;
+                    let _ = reply.send(result);
+                }
+
+                LLMCommand::StopProviders { reply } => {
+                    let result = self.manager.stop_providers().await;
+                    let _ = reply.send(result);
+                }
+
                 LLMCommand::HealthCheck { reply } => {
                     // 立即响应，表明Actor正常运行
                     let _ = reply.send(());
@@ -253,9 +379,34 @@ impl LLMManagerActor {
 #[derive(Clone)]
 pub struct LLMHandle {
     sender: mpsc::Sender<LLMCommand>,
+    /// 排队或执行中的分析请求数
+    pending_analyses: Arc<AtomicUsize>,
+    /// provider 指标（直接读取，不经过 Actor）
+    metrics: ProviderMetrics,
+}
+
+/// 分析请求计数守卫，请求结束（含失败、取消）时计数减一
+struct PendingAnalysis(Arc<AtomicUsize>);
+
+impl PendingAnalysis {
+    fn new(counter: &Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter.clone())
+    }
+}
+
+impl Drop for PendingAnalysis {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl LLMHandle {
+    /// 排队或执行中的分析请求数（为 0 时才适合释放 provider 资源）
+    pub fn pending_analyses(&self) -> usize {
+        self.pending_analyses.load(Ordering::SeqCst)
+    }
+
     /// 配置LLM (Qwen)
     pub async fn configure(&self, config: QwenConfig) -> Result<()> {
         let (reply, rx) = oneshot::channel();
@@ -285,8 +436,32 @@ impl LLMHandle {
         rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?
     }
 
+    /// 配置 provider 中间件（重试、限流、缓存）
+    pub async fn configure_middleware(&self, config: MiddlewareConfig) -> Result<()> {
+        let (reply, rx) = oneshot::channel();
+        self.sender
+            .send(LLMCommand::ConfigureMiddleware { config, reply })
+            .await
+            .map_err(|_| anyhow::anyhow!("Actor通道已关闭"))?;
+        rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?
+    }
+
+    /// 应用配置档
+    pub async fn apply_profile(&self, profile: ProviderProfile) -> Result<()> {
+        let (reply, rx) = oneshot::channel();
+        self.sender
+            .send(LLMCommand::ApplyProfile {
+                profile: Box::new(profile),
+                reply,
+            })
+            .await
+            .map_err(|_| anyhow::anyhow!("Actor通道已关闭"))?;
+        rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?
+    }
+
     /// 分析帧
     pub async fn analyze_frames(&self, frames: Vec<String>) -> Result<SessionSummary> {
+        let _pending = PendingAnalysis::new(&self.pending_analyses);
         let (reply, rx) = oneshot::channel();
         self.sender
             .send(LLMCommand::AnalyzeFrames { frames, reply })
@@ -295,6 +470,69 @@ impl LLMHandle {
         rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?
     }
 
+    /// 结合附加上下文（如音频转写）分析帧
+    pub async fn analyze_frames_with_context(
+        &self,
+        frames: Vec<String>,
+        context: AnalysisContext,
+    ) -> Result<SessionSummary> {
+        let _pending = PendingAnalysis::new(&self.pending_analyses);
+        let (reply, rx) = oneshot::channel();
+        self.sender
+            .send(LLMCommand::AnalyzeFramesWithContext {
+                frames,
+                context,
+                reply,
+            })
+            .await
+            .map_err(|_| anyhow::anyhow!("Actor通道已关闭"))?;
+        rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?
+    }
+
+    /// 生成多个候选总结供用户挑选
+    pub async fn analyze_frames_n(
+        &self,
+        frames: Vec<String>,
+        n: usize,
+    ) -> Result<Vec<SessionSummary>> {
+        let (reply, rx) = oneshot::channel();
+        self.sender
+            .send(LLMCommand::AnalyzeFramesN { frames, n, reply })
+            .await
+            .map_err(|_| anyhow::anyhow!("Actor通道已关闭"))?;
+        rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?
+    }
+
+    /// 结合已有总结重新生成单个字段（标签、关键时刻或评分）
+    pub async fn refine_field(
+        &self,
+        frames: Vec<String>,
+        current: SessionSummary,
+        field: SummaryField,
+    ) -> Result<SessionSummary> {
+        let (reply, rx) = oneshot::channel();
+        self.sender
+            .send(LLMCommand::RefineField {
+                frames,
+                current,
+                field,
+                reply,
+            })
+            .await
+            .map_err(|_| anyhow::anyhow!("Actor通道已关闭"))?;
+        rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?
+    }
+
+    /// 分析纯文本会话（无截图）
+    pub async fn analyze_text(&self, content: String) -> Result<SessionSummary> {
+        let (reply, rx) = oneshot::channel();
+        self.sender
+            .send(LLMCommand::AnalyzeText { content, reply })
+            .await
+            .map_err(|_| anyhow::anyhow!("Actor通道已关闭"))?;
+        rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?
+    }
+
     /// 获取配置
     pub async fn get_config(&self) -> Result<LLMConfig> {
         let (reply, rx) = oneshot::channel();
@@ -305,6 +543,11 @@ impl LLMHandle {
         Ok(rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?)
     }
 
+    /// 各 provider 的健康状态（直接读取指标，分析进行中也能立即返回）
+    pub fn provider_status(&self) -> Vec<ProviderStatus> {
+        self.metrics.provider_status()
+    }
+
     /// 设置视频路径
     pub async fn set_video_path(&self, video_path: Option<String>) -> Result<()> {
         let (reply, rx) = oneshot::channel();
@@ -330,6 +573,17 @@ impl LLMHandle {
         Ok(())
     }
 
+    /// 设置流式分析进度通道
+    pub async fn set_progress_sender(&self, sender: Option<ProgressSender>) -> Result<()> {
+        let (reply, rx) = oneshot::channel();
+        self.sender
+            .send(LLMCommand::SetProgressSender { sender, reply })
+            .await
+            .map_err(|_| anyhow::anyhow!("Actor通道已关闭"))?;
+        rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?;
+        Ok(())
+    }
+
     /// 设置会话时间范围
     pub async fn set_session_window(
         &self,
@@ -371,6 +625,7 @@ impl LLMHandle {
         duration: u32,
         previous_cards: Option<Vec<TimelineCard>>,
     ) -> Result<TimelineAnalysis> {
+        let _pending = PendingAnalysis::new(&self.pending_analyses);
         let (reply, rx) = oneshot::channel();
         self.sender
             .send(LLMCommand::SegmentVideoAndGenerateTimeline {
@@ -397,12 +652,23 @@ impl LLMHandle {
         rx.await.ok().flatten()
     }
 
+    /// 估算当前 provider 分析指定帧数的费用
+    pub async fn estimate_cost(&self, frames: usize) -> Result<Option<CostEstimate>> {
+        let (reply, rx) = oneshot::channel();
+        self.sender
+            .send(LLMCommand::EstimateCost { frames, reply })
+            .await
+            .map_err(|_| anyhow::anyhow!("Actor通道已关闭"))?;
+        rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))
+    }
+
     /// 生成时间线卡片
     pub async fn generate_timeline(
         &self,
         segments: Vec<VideoSegment>,
         previous_cards: Option<Vec<TimelineCard>>,
     ) -> Result<Vec<TimelineCard>> {
+        let _pending = PendingAnalysis::new(&self.pending_analyses);
         let (reply, rx) = oneshot::channel();
         self.sender
             .send(LLMCommand::GenerateTimeline {
@@ -456,6 +722,26 @@ impl LLMHandle {
         rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?
     }
 
+    /// 预热已配置的 provider（录制开始前）
+    pub async fn start_providers(&self) -> Result<()> {
+        let (reply, rx) = oneshot::channel();
+        self.sender
+            .send(LLMCommand::StartProviders { reply })
+            .await
+            .map_err(|_| anyhow::anyhow!("Actor通道已关闭"))?;
+        rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?
+    }
+
+    /// 释放已配置的 provider 资源（录制结束后）
+    pub async fn stop_providers(&self) -> Result<()> {
+        let (reply, rx) = oneshot::channel();
+        self.sender
+            .send(LLMCommand::StopProviders { reply })
+            .await
+            .map_err(|_| anyhow::anyhow!("Actor通道已关闭"))?;
+        rx.await.map_err(|_| anyhow::anyhow!("Actor已停止"))?
+    }
+
     /// 健康检查
     /// 返回true表示Actor正常运行，false表示Actor无响应或已停止
     /// 超时时间为5秒
@@ -59,4 +59,18 @@ mod tests {
         let is_healthy = handle.health_check().await;
         assert!(!is_healthy, "停止的Actor应该健康检查失败");
     }
+
+    #[tokio::test]
+    async fn test_llm_pending_analyses_released_after_reply() {
+        let manager = crate::llm::LLMManager::new(reqwest::Client::new());
+        let (actor, handle) = LLMManagerActor::new(manager);
+        tokio::spawn(async move {
+            actor.run().await;
+        });
+
+        assert_eq!(handle.pending_analyses(), 0);
+        // 未配置的 provider 分析失败，计数同样要归零，否则暂停截屏时永远不会释放模型
+        assert!(handle.analyze_frames(vec![]).await.is_err());
+        assert_eq!(handle.pending_analyses(), 0);
+    }
 }
@@ -159,6 +159,7 @@ pub async fn analyze_video_once(
             created_at: Some(now),
             device_name: Some(device_name),
             device_type: Some(device_type),
+            pinned: false,
         };
 
         match state
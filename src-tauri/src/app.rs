@@ -1064,6 +1064,7 @@ async fn analyze_video_once(
             created_at: Some(now),
             device_name: Some(device_name),
             device_type: Some(device_type),
+            pinned: false,
         };
 
         match state
@@ -0,0 +1,352 @@
+// 内容变化触发 - 画面或前台应用持续变化时切分会话
+//
+// 以最近若干稳定帧的感知哈希为基准，新帧与基准的平均汉明距离超过阈值（或前台应用改变）计为变化帧；
+// 连续多帧变化且彼此一致时确认切换，切分点为第一帧变化帧的时间。
+// 切分点只会把固定时长的会话切得更短，会话最长仍为配置的会话时长
+
+use crate::models::ActivityChangeSettings;
+use crate::video::frame_hash;
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::Mutex;
+use tokio::sync::Notify;
+use tracing::{debug, info};
+
+/// 灵敏度为 0 时的距离阈值（0-64）
+const MAX_DISTANCE_THRESHOLD: u32 = 32;
+
+/// 灵敏度为 1 时的距离阈值
+const MIN_DISTANCE_THRESHOLD: u32 = 8;
+
+/// 切分点保留时长（毫秒），更早的切分点对应的会话早已处理
+const BOUNDARY_RETENTION_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// 灵敏度（0-1）对应的哈希距离阈值：灵敏度越高阈值越低
+pub fn distance_threshold(sensitivity: f32) -> u32 {
+    let sensitivity = if sensitivity.is_finite() {
+        sensitivity.clamp(0.0, 1.0)
+    } else {
+        0.5
+    };
+    let span = (MAX_DISTANCE_THRESHOLD - MIN_DISTANCE_THRESHOLD) as f32;
+    (MAX_DISTANCE_THRESHOLD as f32 - sensitivity * span).round() as u32
+}
+
+/// 单帧观测：截图时间、感知哈希与前台应用
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameObservation {
+    pub timestamp_ms: i64,
+    pub hash: u64,
+    pub app: Option<String>,
+}
+
+/// 活动变化检测器
+pub struct ChangeDetector {
+    threshold: u32,
+    window_frames: usize,
+    confirm_frames: usize,
+    min_session_ms: i64,
+    session_ms: i64,
+    detect_app_switch: bool,
+    /// 最近的稳定帧哈希
+    baseline: VecDeque<u64>,
+    /// 最近稳定帧的前台应用
+    baseline_app: Option<String>,
+    /// 连续的变化帧
+    pending: Vec<FrameObservation>,
+    /// 当前会话开始时间（固定窗口起点或上一个切分点）
+    session_start_ms: Option<i64>,
+}
+
+impl ChangeDetector {
+    pub fn new(settings: &ActivityChangeSettings, session_duration_mins: u64) -> Self {
+        Self {
+            threshold: distance_threshold(settings.sensitivity),
+            window_frames: settings.window_frames.max(1),
+            confirm_frames: settings.confirm_frames.max(1),
+            min_session_ms: settings.min_session_secs as i64 * 1000,
+            session_ms: session_duration_mins.max(1) as i64 * 60_000,
+            detect_app_switch: settings.detect_app_switch,
+            baseline: VecDeque::new(),
+            baseline_app: None,
+            pending: Vec::new(),
+            session_start_ms: None,
+        }
+    }
+
+    /// 输入一帧；确认切换且当前会话已达到最短时长时返回切分点（毫秒）
+    pub fn observe(&mut self, frame: FrameObservation) -> Option<i64> {
+        // 进入新的固定窗口时，会话起点随之前移
+        let window_start = frame.timestamp_ms.div_euclid(self.session_ms) * self.session_ms;
+        if self
+            .session_start_ms
+            .is_none_or(|start| start < window_start)
+        {
+            self.session_start_ms = Some(window_start);
+        }
+
+        if self.baseline.is_empty() || !self.differs(&frame) {
+            self.pending.clear();
+            self.push_baseline(frame);
+            return None;
+        }
+
+        // 变化帧之间也要一致，画面来回跳动时重新计数
+        if let Some(first) = self.pending.first() {
+            if !self.consistent(first, &frame) {
+                self.pending.clear();
+            }
+        }
+        self.pending.push(frame);
+        if self.pending.len() < self.confirm_frames {
+            return None;
+        }
+
+        // 确认切换：以新画面作为基准
+        let pending = std::mem::take(&mut self.pending);
+        let boundary = pending[0].timestamp_ms;
+        self.baseline.clear();
+        self.baseline_app = None;
+        for frame in pending {
+            self.push_baseline(frame);
+        }
+
+        let session_start = self.session_start_ms.unwrap_or(boundary);
+        if boundary - session_start < self.min_session_ms {
+            debug!(
+                "内容变化发生在会话开始 {}s 内，不切分会话",
+                (boundary - session_start) / 1000
+            );
+            return None;
+        }
+        self.session_start_ms = Some(boundary);
+        Some(boundary)
+    }
+
+    /// 新帧是否与基准不同：与基准帧的平均距离达到阈值，或前台应用改变
+    fn differs(&self, frame: &FrameObservation) -> bool {
+        let total: u32 = self
+            .baseline
+            .iter()
+            .map(|hash| frame_hash::distance(*hash, frame.hash))
+            .sum();
+        let mean = total as f32 / self.baseline.len() as f32;
+        mean >= self.threshold as f32 || self.app_switched(self.baseline_app.as_deref(), frame)
+    }
+
+    /// 两帧变化帧是否属于同一新画面
+    fn consistent(&self, first: &FrameObservation, frame: &FrameObservation) -> bool {
+        frame_hash::distance(first.hash, frame.hash) < self.threshold
+            && !self.app_switched(first.app.as_deref(), frame)
+    }
+
+    fn app_switched(&self, previous: Option<&str>, frame: &FrameObservation) -> bool {
+        self.detect_app_switch
+            && matches!((previous, frame.app.as_deref()), (Some(a), Some(b)) if a != b)
+    }
+
+    fn push_baseline(&mut self, frame: FrameObservation) {
+        self.baseline.push_back(frame.hash);
+        while self.baseline.len() > self.window_frames {
+            self.baseline.pop_front();
+        }
+        if frame.app.is_some() {
+            self.baseline_app = frame.app;
+        }
+    }
+}
+
+/// 已确认的会话切分点（毫秒），由截屏任务写入、会话扫描任务读取
+pub struct SessionBoundaries {
+    points: Mutex<BTreeSet<i64>>,
+    notify: Notify,
+}
+
+impl SessionBoundaries {
+    pub fn new() -> Self {
+        Self {
+            points: Mutex::new(BTreeSet::new()),
+            notify: Notify::new(),
+        }
+    }
+
+    /// 记录切分点并唤醒会话扫描任务
+    pub fn insert(&self, at_ms: i64) {
+        {
+            let mut points = self.points.lock().unwrap();
+            points.insert(at_ms);
+            points.retain(|point| at_ms - *point <= BOUNDARY_RETENTION_MS);
+        }
+        info!("检测到活动变化，会话在 {} 处切分", at_ms);
+        self.notify.notify_one();
+    }
+
+    pub fn snapshot(&self) -> BTreeSet<i64> {
+        self.points.lock().unwrap().clone()
+    }
+
+    /// 等待新的切分点
+    pub async fn notified(&self) {
+        self.notify.notified().await;
+    }
+}
+
+impl Default for SessionBoundaries {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 帧所属的会话时间窗：固定时长窗口再按切分点细分
+///
+/// 返回 (开始, 结束, 是否由切分点结束)；由切分点结束的会话已确定关闭，无需等待写入缓冲
+pub fn session_window(
+    timestamp_ms: i64,
+    interval_ms: i64,
+    boundaries: &BTreeSet<i64>,
+) -> (i64, i64, bool) {
+    let window_start = timestamp_ms.div_euclid(interval_ms) * interval_ms;
+    let window_end = window_start + interval_ms;
+    let start = boundaries
+        .range(..=timestamp_ms)
+        .next_back()
+        .copied()
+        .filter(|point| *point > window_start)
+        .unwrap_or(window_start);
+    match boundaries
+        .range(timestamp_ms + 1..)
+        .next()
+        .copied()
+        .filter(|point| *point < window_end)
+    {
+        Some(end) => (start, end, true),
+        None => (start, window_end, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 15 分钟对齐的起始时间
+    const T0: i64 = 1_710_000_000_000;
+    const CODE: u64 = 0x0000_0000_ffff_ffff;
+    const MEETING: u64 = 0xffff_ffff_0000_0000;
+
+    fn frame(second: i64, hash: u64, app: Option<&str>) -> FrameObservation {
+        FrameObservation {
+            timestamp_ms: T0 + second * 1000,
+            hash,
+            app: app.map(str::to_string),
+        }
+    }
+
+    /// 依次输入 (秒, 哈希)，返回确认的切分点（相对 T0 的秒数）
+    fn run(detector: &mut ChangeDetector, frames: &[(i64, u64)]) -> Vec<i64> {
+        frames
+            .iter()
+            .filter_map(|(second, hash)| detector.observe(frame(*second, *hash, None)))
+            .map(|point| (point - T0) / 1000)
+            .collect()
+    }
+
+    #[test]
+    fn test_sustained_change_splits_once_and_ignores_popups() {
+        assert_eq!(distance_threshold(0.5), 20);
+        let mut detector = ChangeDetector::new(&ActivityChangeSettings::default(), 15);
+
+        // 每 2 秒一帧：编码画面带轻微噪声，第 120 秒弹出一次通知，第 200 秒切到会议
+        let frames: Vec<(i64, u64)> = (0..200)
+            .map(|i| {
+                let second = i * 2;
+                let hash = match second {
+                    120 => CODE ^ 0x00ff_ffff_0000_0000,
+                    s if s >= 200 => MEETING ^ (i as u64 % 3),
+                    _ => CODE ^ (i as u64 % 4),
+                };
+                (second, hash)
+            })
+            .collect();
+        assert_eq!(run(&mut detector, &frames), vec![200]);
+    }
+
+    #[test]
+    fn test_change_within_min_session_is_debounced() {
+        let mut detector = ChangeDetector::new(&ActivityChangeSettings::default(), 15);
+        // 第 60 秒的变化未达到最短会话时长，被并入当前会话；第 300 秒切回编码时才切分
+        let frames: Vec<(i64, u64)> = (0..200)
+            .map(|i| {
+                let second = i * 2;
+                let hash = if (60..300).contains(&second) {
+                    MEETING
+                } else {
+                    CODE
+                };
+                (second, hash)
+            })
+            .collect();
+        assert_eq!(run(&mut detector, &frames), vec![300]);
+
+        // 新的固定窗口重新开始计时：下一窗口开头的变化同样被防抖
+        let next_window: Vec<(i64, u64)> = (0..30)
+            .map(|i| (900 + i * 2, if i >= 10 { MEETING } else { CODE }))
+            .collect();
+        assert!(run(&mut detector, &next_window).is_empty());
+    }
+
+    #[test]
+    fn test_foreground_app_switch() {
+        let settings = ActivityChangeSettings {
+            min_session_secs: 0,
+            ..Default::default()
+        };
+        let observe = |detector: &mut ChangeDetector| -> Vec<Option<i64>> {
+            (0..10)
+                .map(|i| {
+                    let app = if i < 5 { "Code" } else { "Zoom" };
+                    detector.observe(frame(i * 2, CODE, Some(app)))
+                })
+                .collect()
+        };
+
+        let mut detector = ChangeDetector::new(&settings, 15);
+        let points: Vec<i64> = observe(&mut detector).into_iter().flatten().collect();
+        assert_eq!(points, vec![T0 + 10_000]);
+
+        let mut detector = ChangeDetector::new(
+            &ActivityChangeSettings {
+                detect_app_switch: false,
+                ..settings
+            },
+            15,
+        );
+        assert!(observe(&mut detector).iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn test_session_window_splits_fixed_windows_at_boundaries() {
+        let interval = 900_000;
+        let boundaries = BTreeSet::from([T0 + 200_000, T0 + 600_000, T0 + 900_000 + 5_000]);
+
+        assert_eq!(
+            session_window(T0 + 10_000, interval, &boundaries),
+            (T0, T0 + 200_000, true)
+        );
+        assert_eq!(
+            session_window(T0 + 200_000, interval, &boundaries),
+            (T0 + 200_000, T0 + 600_000, true)
+        );
+        assert_eq!(
+            session_window(T0 + 700_000, interval, &boundaries),
+            (T0 + 600_000, T0 + 900_000, false)
+        );
+        assert_eq!(
+            session_window(T0 + 901_000, interval, &boundaries),
+            (T0 + 900_000, T0 + 905_000, true)
+        );
+        // 没有切分点时与固定窗口一致
+        assert_eq!(
+            session_window(T0 + 10_000, interval, &BTreeSet::new()),
+            (T0, T0 + 900_000, false)
+        );
+    }
+}
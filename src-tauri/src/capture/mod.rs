@@ -18,7 +18,9 @@ use std::process::Command;
 #[cfg(not(target_os = "macos"))]
 use tracing::debug;
 
+pub mod change_trigger;
 pub mod scheduler;
+pub mod watchdog;
 
 /// 截屏帧数据结构
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -165,6 +167,26 @@ impl ScreenCapture {
         false
     }
 
+    /// 获取前台应用名称（阻塞调用）；不支持的平台返回 None
+    pub fn foreground_app() -> Option<String> {
+        #[cfg(target_os = "macos")]
+        {
+            let output = Command::new("osascript")
+                .arg("-e")
+                .arg("tell application \"System Events\" to get name of first application process whose frontmost is true")
+                .output()
+                .ok()?;
+            let name = String::from_utf8(output.stdout).ok()?;
+            let name = name.trim();
+            (output.status.success() && !name.is_empty()).then(|| name.to_string())
+        }
+
+        #[cfg(not(target_os = "macos"))]
+        {
+            None
+        }
+    }
+
     /// 检测图像是否为黑屏
     async fn is_black_screen(&self, img: &DynamicImage) -> bool {
         let settings = self.capture_settings.lock().await;
@@ -3,13 +3,18 @@
 // 使用事件驱动架构,通过EventBus发布SessionCompleted事件
 // 解耦调度器与业务逻辑处理
 
+use super::change_trigger::{self, ChangeDetector, FrameObservation, SessionBoundaries};
+use super::watchdog::{self, CaptureGapLog, CaptureHeartbeat};
 use super::ScreenCapture;
 use crate::event_bus::{AppEvent, EventBus};
+use crate::models::{ActivityChangeSettings, CaptureWatchdogSettings};
+use crate::video::frame_hash::{self, FrameHashAlgorithm};
 use anyhow::{anyhow, Result};
-use std::collections::{BTreeMap, HashSet, VecDeque};
-use std::sync::Arc;
+use std::collections::{BTreeMap, BTreeSet, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
 use tokio::time::{interval, Duration};
-use tracing::{debug, error, info, trace};
+use tracing::{debug, error, info, trace, warn};
 
 /// 窗口跟踪器 - 用于跟踪已处理的窗口，防止内存泄漏
 struct WindowTracker {
@@ -63,6 +68,18 @@ pub struct CaptureScheduler {
     capture_interval: u64,
     /// 会话时长（分钟）
     session_duration: u64,
+    /// 看门狗配置
+    watchdog: CaptureWatchdogSettings,
+    /// 截屏心跳
+    heartbeat: Arc<CaptureHeartbeat>,
+    /// 尚未入库的截屏缺口
+    capture_gaps: Arc<CaptureGapLog>,
+    /// 当前截屏任务，看门狗重启时中止
+    capture_task: Mutex<Option<JoinHandle<()>>>,
+    /// 内容变化触发配置
+    activity_change: ActivityChangeSettings,
+    /// 内容变化确认的会话切分点
+    boundaries: Arc<SessionBoundaries>,
 }
 
 impl CaptureScheduler {
@@ -72,6 +89,12 @@ impl CaptureScheduler {
             capture,
             capture_interval: 1,  // 默认1秒一次（1 FPS）
             session_duration: 15, // 默认15分钟一个会话
+            watchdog: CaptureWatchdogSettings::default(),
+            heartbeat: Arc::new(CaptureHeartbeat::new()),
+            capture_gaps: Arc::new(CaptureGapLog::new()),
+            capture_task: Mutex::new(None),
+            activity_change: ActivityChangeSettings::default(),
+            boundaries: Arc::new(SessionBoundaries::new()),
         }
     }
 
@@ -85,30 +108,63 @@ impl CaptureScheduler {
         );
     }
 
-    /// 启动截屏任务
+    /// 配置截屏看门狗
+    pub fn configure_watchdog(&mut self, settings: CaptureWatchdogSettings) {
+        info!(
+            "看门狗配置更新: 启用={}, 卡死阈值={}秒",
+            settings.enabled, settings.stall_threshold_secs
+        );
+        self.watchdog = settings;
+    }
+
+    /// 配置内容变化触发
+    pub fn configure_activity_change(&mut self, settings: ActivityChangeSettings) {
+        info!(
+            "内容变化触发配置更新: 启用={}, 灵敏度={}, 最短会话={}秒",
+            settings.enabled, settings.sensitivity, settings.min_session_secs
+        );
+        self.activity_change = settings;
+    }
+
+    /// 尚未入库的截屏缺口，会话分析时写入数据库
+    pub fn capture_gaps(&self) -> Arc<CaptureGapLog> {
+        self.capture_gaps.clone()
+    }
+
+    /// 启动截屏任务（已有任务时先中止）
     pub fn start_capture_task(self: Arc<Self>) {
         let capture = self.capture.clone();
+        let heartbeat = self.heartbeat.clone();
         let interval_secs = self.capture_interval;
+        let boundaries = self.boundaries.clone();
+        let activity_change = self.activity_change.clone();
+        let mut detector = activity_change
+            .enabled
+            .then(|| ChangeDetector::new(&activity_change, self.session_duration));
 
         info!("准备启动截屏任务，间隔: {}秒", interval_secs);
 
         // 直接在当前的异步上下文中生成任务
-        tokio::task::spawn(async move {
+        let handle = tokio::task::spawn(async move {
             info!("截屏任务已启动，间隔: {}秒", interval_secs);
             let mut interval = interval(Duration::from_secs(interval_secs));
 
             // 立即执行第一次截屏（检查锁屏状态）
+            // 锁屏与黑屏属于正常跳过，同样更新心跳，不计入卡死
             if super::ScreenCapture::is_screen_locked() {
                 trace!("系统锁屏中，跳过初始截屏");
+                heartbeat.beat();
             } else {
                 match capture.capture_frame().await {
                     Ok(frame) => {
                         trace!("初始截屏成功: {}", frame.timestamp);
+                        heartbeat.beat();
                     }
                     Err(e) => {
                         // 黑屏不是真正的错误，只记录trace级别日志
                         if e.to_string().contains("黑屏") {
                             debug!("初始截屏检测到黑屏，已跳过");
+                            heartbeat.beat();
                         } else {
                             error!("初始截屏失败: {}", e);
                         }
@@ -122,17 +178,45 @@ impl CaptureScheduler {
                 // 检查锁屏状态
                 if super::ScreenCapture::is_screen_locked() {
                     info!("系统锁屏中，跳过截屏");
+                    heartbeat.beat();
                     continue;
                 }
 
                 match capture.capture_frame().await {
                     Ok(frame) => {
                         trace!("自动截屏成功: {}", frame.timestamp);
+                        heartbeat.beat();
+
+                        if let Some(detector) = detector.as_mut() {
+                            let detect_app = activity_change.detect_app_switch;
+                            let path = frame.file_path.clone();
+                            let observed = tokio::task::spawn_blocking(move || {
+                                let hash = frame_hash::hash_frame(&path, FrameHashAlgorithm::DHash);
+                                let app = detect_app.then(ScreenCapture::foreground_app).flatten();
+                                (hash, app)
+                            })
+                            .await;
+                            match observed {
+                                Ok((Ok(hash), app)) => {
+                                    let observation = FrameObservation {
+                                        timestamp_ms: frame.timestamp.timestamp_millis(),
+                                        hash,
+                                        app,
+                                    };
+                                    if let Some(boundary) = detector.observe(observation) {
+                                        boundaries.insert(boundary);
+                                    }
+                                }
+                                Ok((Err(e), _)) => debug!("计算帧哈希失败: {}", e),
+                                Err(e) => debug!("内容变化检测任务失败: {}", e),
+                            }
+                        }
                     }
                     Err(e) => {
                         // 黑屏不是真正的错误，只记录trace级别日志
                         if e.to_string().contains("黑屏") {
                             trace!("跳过黑屏图像");
+                            heartbeat.beat();
                         } else {
                             error!("自动截屏失败: {}", e);
                         }
@@ -140,12 +224,43 @@ impl CaptureScheduler {
                 }
             }
         });
+
+        if let Some(previous) = self.capture_task.lock().unwrap().replace(handle) {
+            previous.abort();
+        }
+    }
+
+    /// 启动截屏看门狗：长时间没有新帧时发布事件并重启截屏任务
+    pub fn start_watchdog_task(self: Arc<Self>, event_bus: Arc<EventBus>) {
+        if !self.watchdog.enabled {
+            info!("截屏看门狗未启用");
+            return;
+        }
+
+        let threshold = watchdog::effective_threshold(&self.watchdog, self.capture_interval);
+        let check_interval = Duration::from_secs(self.watchdog.check_interval_secs.max(1));
+        let heartbeat = self.heartbeat.clone();
+        let gaps = self.capture_gaps.clone();
+
+        tokio::task::spawn(watchdog::supervise(
+            heartbeat,
+            threshold,
+            check_interval,
+            event_bus,
+            gaps,
+            move || {
+                // 卡在同步截屏调用中的任务无法被中止时，新任务仍会接替出帧
+                warn!("重启截屏任务");
+                self.clone().start_capture_task();
+            },
+        ));
     }
 
     /// 启动会话处理任务(事件驱动版本)
     pub fn start_session_task(self: Arc<Self>, event_bus: Arc<EventBus>) {
         let capture = self.capture.clone();
         let session_mins = self.session_duration;
+        let boundaries = self.boundaries.clone();
 
         tokio::task::spawn(async move {
             // 使用 WindowTracker 限制内存使用，最多保留 1000 个窗口记录
@@ -159,6 +274,7 @@ impl CaptureScheduler {
                     capture.clone(),
                     event_bus.clone(),
                     session_mins,
+                    &boundaries.snapshot(),
                     &mut processed_windows,
                 )
                 .await
@@ -166,7 +282,11 @@ impl CaptureScheduler {
                     error!("扫描待处理图片失败: {}", e);
                 }
 
-                tokio::time::sleep(check_interval).await;
+                // 内容变化切分会话时立即扫描，不必等到下一个周期
+                tokio::select! {
+                    _ = tokio::time::sleep(check_interval) => {}
+                    _ = boundaries.notified() => {}
+                }
             }
         });
     }
@@ -178,6 +298,9 @@ impl CaptureScheduler {
         // 启动截屏任务
         self.clone().start_capture_task();
 
+        // 启动截屏看门狗
+        self.clone().start_watchdog_task(event_bus.clone());
+
         // 启动会话处理任务
         self.start_session_task(event_bus);
 
@@ -188,6 +311,7 @@ impl CaptureScheduler {
         capture: Arc<ScreenCapture>,
         event_bus: Arc<EventBus>,
         session_duration: u64,
+        boundaries: &BTreeSet<i64>,
         processed_windows: &mut WindowTracker,
     ) -> Result<()> {
         use chrono::{TimeZone, Utc};
@@ -202,7 +326,8 @@ impl CaptureScheduler {
         }
 
         let interval_ms = session_duration as i64 * 60_000;
-        let mut grouped: BTreeMap<i64, Vec<super::ScreenFrame>> = BTreeMap::new();
+        // 会话开始时间 -> (结束时间, 是否由切分点结束, 帧)
+        let mut grouped: BTreeMap<i64, (i64, bool, Vec<super::ScreenFrame>)> = BTreeMap::new();
         let mut entries = tokio::fs::read_dir(&frames_dir).await?;
 
         while let Some(entry) = entries.next_entry().await? {
@@ -237,8 +362,13 @@ impl CaptureScheduler {
                 screen_id: 0,
             };
 
-            let bucket = (timestamp_ms / interval_ms) * interval_ms;
-            grouped.entry(bucket).or_default().push(frame);
+            let (start, end, closed) =
+                change_trigger::session_window(timestamp_ms, interval_ms, boundaries);
+            grouped
+                .entry(start)
+                .or_insert_with(|| (end, closed, Vec::new()))
+                .2
+                .push(frame);
         }
 
         if grouped.is_empty() {
@@ -249,9 +379,9 @@ impl CaptureScheduler {
         let now_ms = crate::storage::local_now().timestamp_millis();
         let cutoff_ms = now_ms - 30_000; // 留出缓冲，避免处理仍在写入的区间
 
-        for (bucket_start_ms, mut frames) in grouped.into_iter() {
-            let bucket_end_ms = bucket_start_ms + interval_ms;
-            if bucket_end_ms > cutoff_ms {
+        for (bucket_start_ms, (bucket_end_ms, closed, mut frames)) in grouped.into_iter() {
+            // 由切分点结束的会话后续不会再有新帧，无需等待缓冲
+            if bucket_end_ms > cutoff_ms && !closed {
                 continue;
             }
 
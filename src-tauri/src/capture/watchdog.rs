@@ -0,0 +1,394 @@
+// 截屏看门狗 - 截屏任务卡死（驱动异常、显示器休眠）时帧会静默中断，会话看起来像是空闲
+//
+// 截屏循环每轮写入心跳，监督任务定期检查距上次心跳的时长：超过阈值即发布
+// CaptureStalled 事件并重启截屏任务；重新出帧后把中断时段记为截屏缺口，随会话入库
+
+use crate::event_bus::{AppEvent, EventBus};
+use crate::models::CaptureWatchdogSettings;
+use chrono::{DateTime, TimeZone, Utc};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::time::Duration;
+use tracing::{info, warn};
+
+/// 内存中保留的缺口数量上限（缺口在会话分析时入库）
+const MAX_PENDING_GAPS: usize = 256;
+
+/// 未在等待恢复
+const NOT_WATCHING: i64 = -1;
+/// 等待卡死后的第一帧
+const WAITING: i64 = 0;
+
+/// 截屏心跳：截屏循环每轮（出帧、锁屏或黑屏跳过）更新，看门狗据此判断是否卡死
+pub struct CaptureHeartbeat {
+    last_beat_ms: AtomicI64,
+    /// 卡死后第一次心跳的时间，用于确定缺口结束时间
+    resumed_ms: AtomicI64,
+}
+
+impl CaptureHeartbeat {
+    pub fn new() -> Self {
+        Self {
+            last_beat_ms: AtomicI64::new(crate::storage::local_now().timestamp_millis()),
+            resumed_ms: AtomicI64::new(NOT_WATCHING),
+        }
+    }
+
+    /// 记录一次心跳
+    pub fn beat(&self) {
+        self.beat_at(crate::storage::local_now().timestamp_millis());
+    }
+
+    fn beat_at(&self, now_ms: i64) {
+        self.last_beat_ms.store(now_ms, Ordering::SeqCst);
+        let _ = self.resumed_ms.compare_exchange(
+            WAITING,
+            now_ms.max(WAITING + 1),
+            Ordering::SeqCst,
+            Ordering::SeqCst,
+        );
+    }
+
+    fn last_beat_ms(&self) -> i64 {
+        self.last_beat_ms.load(Ordering::SeqCst)
+    }
+
+    fn watch_for_resume(&self) {
+        self.resumed_ms.store(WAITING, Ordering::SeqCst);
+    }
+
+    fn take_resumed(&self) -> Option<i64> {
+        let resumed = self.resumed_ms.load(Ordering::SeqCst);
+        if resumed > WAITING {
+            self.resumed_ms.store(NOT_WATCHING, Ordering::SeqCst);
+            Some(resumed)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for CaptureHeartbeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 截屏缺口：截屏卡死期间没有截图的时段
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct CaptureGap {
+    /// 卡死前最后一帧的时间
+    pub start: DateTime<Utc>,
+    /// 恢复后第一帧的时间
+    pub end: DateTime<Utc>,
+    /// 恢复前的重启次数
+    pub restart_attempts: u32,
+}
+
+/// 尚未入库的截屏缺口
+#[derive(Default)]
+pub struct CaptureGapLog {
+    gaps: Mutex<VecDeque<CaptureGap>>,
+}
+
+impl CaptureGapLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, gap: CaptureGap) {
+        let mut gaps = self.gaps.lock().unwrap();
+        if gaps.len() >= MAX_PENDING_GAPS {
+            gaps.pop_front();
+        }
+        gaps.push_back(gap);
+    }
+
+    /// 与时间窗重叠的缺口（裁剪到时间窗内），跨越多个会话的缺口会在每个会话中各记一段
+    pub fn overlapping(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<CaptureGap> {
+        self.gaps
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|gap| gap.start < end && gap.end > start)
+            .map(|gap| CaptureGap {
+                start: gap.start.max(start),
+                end: gap.end.min(end),
+                restart_attempts: gap.restart_attempts,
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.gaps.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 一次检查的结果
+#[derive(Debug, Clone, PartialEq)]
+enum WatchdogAction {
+    /// 截屏正常
+    Healthy,
+    /// 截屏卡死，需要重启
+    Restart {
+        last_frame_ms: i64,
+        stalled_ms: i64,
+        attempt: u32,
+    },
+    /// 卡死后已恢复出帧
+    Recovered(CaptureGap),
+}
+
+/// 卡死检测状态
+struct StallDetector {
+    threshold_ms: i64,
+    /// 卡死前最后一帧的时间
+    stalled_since: Option<i64>,
+    last_restart_ms: Option<i64>,
+    restart_attempts: u32,
+}
+
+impl StallDetector {
+    fn new(threshold: Duration) -> Self {
+        Self {
+            threshold_ms: threshold.as_millis() as i64,
+            stalled_since: None,
+            last_restart_ms: None,
+            restart_attempts: 0,
+        }
+    }
+
+    fn check(&mut self, heartbeat: &CaptureHeartbeat, now_ms: i64) -> WatchdogAction {
+        let last_beat = heartbeat.last_beat_ms();
+
+        if let Some(since) = self.stalled_since {
+            if let Some(resumed) = heartbeat.take_resumed() {
+                let gap = CaptureGap {
+                    start: millis_to_datetime(since),
+                    end: millis_to_datetime(resumed),
+                    restart_attempts: self.restart_attempts,
+                };
+                self.stalled_since = None;
+                self.last_restart_ms = None;
+                self.restart_attempts = 0;
+                return WatchdogAction::Recovered(gap);
+            }
+        }
+
+        // 重启后给新任务一个完整阈值的时间出帧，避免连续重启
+        let reference = last_beat.max(self.last_restart_ms.unwrap_or(i64::MIN));
+        if now_ms - reference < self.threshold_ms {
+            return WatchdogAction::Healthy;
+        }
+
+        if self.stalled_since.is_none() {
+            self.stalled_since = Some(last_beat);
+            heartbeat.watch_for_resume();
+        }
+        self.last_restart_ms = Some(now_ms);
+        self.restart_attempts += 1;
+        WatchdogAction::Restart {
+            last_frame_ms: last_beat,
+            stalled_ms: now_ms - last_beat,
+            attempt: self.restart_attempts,
+        }
+    }
+}
+
+fn millis_to_datetime(ms: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(ms).single().unwrap_or_default()
+}
+
+/// 实际生效的卡死阈值：至少为截屏间隔的 3 倍，避免间隔较长时误判
+pub fn effective_threshold(settings: &CaptureWatchdogSettings, capture_interval: u64) -> Duration {
+    Duration::from_secs(
+        settings
+            .stall_threshold_secs
+            .max(capture_interval.saturating_mul(3))
+            .max(1),
+    )
+}
+
+/// 看门狗监督循环：按 check_interval 检查心跳，卡死时调用 restart 重启截屏任务（不会返回）
+pub async fn supervise<F>(
+    heartbeat: Arc<CaptureHeartbeat>,
+    threshold: Duration,
+    check_interval: Duration,
+    event_bus: Arc<EventBus>,
+    gaps: Arc<CaptureGapLog>,
+    mut restart: F,
+) where
+    F: FnMut() + Send,
+{
+    let mut detector = StallDetector::new(threshold);
+    let mut ticker = tokio::time::interval(check_interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    info!(
+        "截屏看门狗已启动：超过 {} 秒无新帧视为卡死，每 {} 毫秒检查一次",
+        threshold.as_secs_f64(),
+        check_interval.as_millis()
+    );
+
+    loop {
+        ticker.tick().await;
+        let now_ms = crate::storage::local_now().timestamp_millis();
+        match detector.check(&heartbeat, now_ms) {
+            WatchdogAction::Healthy => {}
+            WatchdogAction::Restart {
+                last_frame_ms,
+                stalled_ms,
+                attempt,
+            } => {
+                let last_frame_at = millis_to_datetime(last_frame_ms);
+                warn!(
+                    "截屏已 {} 秒没有新帧（最后一帧 {}），第 {} 次重启截屏任务",
+                    stalled_ms / 1000,
+                    last_frame_at,
+                    attempt
+                );
+                event_bus.publish(AppEvent::CaptureStalled {
+                    last_frame_at,
+                    stalled_secs: (stalled_ms / 1000) as u64,
+                    restart_attempt: attempt,
+                });
+                restart();
+            }
+            WatchdogAction::Recovered(gap) => {
+                info!(
+                    "截屏已恢复，缺口 {} - {}（重启 {} 次）",
+                    gap.start, gap.end, gap.restart_attempts
+                );
+                event_bus.publish(AppEvent::CaptureRecovered {
+                    gap_start: gap.start,
+                    gap_end: gap.end,
+                    restart_attempts: gap.restart_attempts,
+                });
+                gaps.record(gap);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicU32};
+
+    #[test]
+    fn test_detector_restarts_once_per_threshold_and_reports_gap() {
+        let heartbeat = CaptureHeartbeat::new();
+        heartbeat.beat_at(1_000);
+        let mut detector = StallDetector::new(Duration::from_secs(10));
+
+        assert_eq!(detector.check(&heartbeat, 5_000), WatchdogAction::Healthy);
+        assert_eq!(
+            detector.check(&heartbeat, 11_000),
+            WatchdogAction::Restart {
+                last_frame_ms: 1_000,
+                stalled_ms: 10_000,
+                attempt: 1,
+            }
+        );
+        // 重启后一个阈值内不再重复重启
+        assert_eq!(detector.check(&heartbeat, 15_000), WatchdogAction::Healthy);
+        assert!(matches!(
+            detector.check(&heartbeat, 21_000),
+            WatchdogAction::Restart { attempt: 2, .. }
+        ));
+
+        // 缺口结束于恢复后的第一帧，而不是检查时的最新一帧
+        heartbeat.beat_at(23_000);
+        heartbeat.beat_at(24_000);
+        assert_eq!(
+            detector.check(&heartbeat, 25_000),
+            WatchdogAction::Recovered(CaptureGap {
+                start: millis_to_datetime(1_000),
+                end: millis_to_datetime(23_000),
+                restart_attempts: 2,
+            })
+        );
+        assert_eq!(detector.check(&heartbeat, 26_000), WatchdogAction::Healthy);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_frame_source_triggers_restart() {
+        let heartbeat = Arc::new(CaptureHeartbeat::new());
+        let event_bus = Arc::new(EventBus::new(16));
+        let mut events = event_bus.subscribe();
+        let gaps = Arc::new(CaptureGapLog::new());
+
+        // 模拟帧源：出几帧后卡住，直到被重启
+        let producing = Arc::new(AtomicBool::new(true));
+        let source = {
+            let heartbeat = heartbeat.clone();
+            let producing = producing.clone();
+            tokio::spawn(async move {
+                for frame in 0.. {
+                    if producing.load(Ordering::SeqCst) {
+                        heartbeat.beat();
+                        if frame == 3 {
+                            producing.store(false, Ordering::SeqCst);
+                        }
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            })
+        };
+
+        let restarts = Arc::new(AtomicU32::new(0));
+        let watchdog = {
+            let restarts = restarts.clone();
+            let producing = producing.clone();
+            tokio::spawn(supervise(
+                heartbeat,
+                Duration::from_millis(200),
+                Duration::from_millis(20),
+                event_bus.clone(),
+                gaps.clone(),
+                move || {
+                    restarts.fetch_add(1, Ordering::SeqCst);
+                    producing.store(true, Ordering::SeqCst);
+                },
+            ))
+        };
+
+        let stalled = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            stalled,
+            AppEvent::CaptureStalled {
+                restart_attempt: 1,
+                ..
+            }
+        ));
+        let recovered = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        let AppEvent::CaptureRecovered {
+            gap_start, gap_end, ..
+        } = recovered
+        else {
+            panic!("应收到恢复事件");
+        };
+        assert!(gap_end - gap_start >= chrono::Duration::milliseconds(200));
+
+        assert_eq!(restarts.load(Ordering::SeqCst), 1);
+        assert_eq!(gaps.overlapping(gap_start, gap_end).len(), 1);
+        // 缺口之外的时间窗不受影响
+        assert!(gaps
+            .overlapping(gap_end, gap_end + chrono::Duration::seconds(1))
+            .is_empty());
+
+        watchdog.abort();
+        source.abort();
+    }
+}
@@ -43,6 +43,16 @@ pub async fn update_config(
             .map_err(|e| e.to_string())?;
     }
 
+    // 更新存储预算
+    if let Some(max_storage_bytes) = config.max_storage_bytes {
+        state
+            .storage_domain
+            .get_cleaner()
+            .await?
+            .set_max_storage_bytes(Some(max_storage_bytes))
+            .await;
+    }
+
     // 更新LLM配置（现在只有Qwen）
     if let Some(_llm_provider) = config.llm_provider {
         // 现在只支持Qwen，不需要切换provider
@@ -228,6 +238,7 @@ pub async fn configure_llm_provider(
 
     let update = models::AppConfig {
         retention_days: None,
+        max_storage_bytes: None,
         llm_provider: Some(provider.clone()),
         capture_interval: None,
         summary_interval: None,
@@ -238,6 +249,16 @@ pub async fn configure_llm_provider(
         logger_settings: None,
         database_config: None,
         notion_config: None,
+        frame_cleanup: None,
+        frame_storage: None,
+        session_thumbnail: None,
+        capture_watchdog: None,
+        analysis_concurrency: None,
+        activity_change: None,
+        metrics_exporter: None,
+        provider_profiles: None,
+        webhook: None,
+        deep_work: None,
     };
 
     state
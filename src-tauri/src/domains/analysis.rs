@@ -5,25 +5,57 @@
 // 使用Actor模式管理LLM状态，消除锁竞争
 
 use crate::actors::LLMHandle;
+use crate::llm::queue::MAX_INFERENCE_CONCURRENCY;
+use crate::llm::{AnalysisPipeline, AnalysisQueue};
+use crate::models::AnalysisConcurrencySettings;
 use crate::video::processor::VideoProcessor;
 use std::sync::Arc;
+use tracing::warn;
 
 /// 分析领域管理器 - 负责 LLM 分析和视频处理
 #[derive(Clone)]
 pub struct AnalysisDomain {
     llm_handle: LLMHandle,
     video_processor: Arc<VideoProcessor>,
+    analysis_queue: AnalysisQueue,
+    analysis_pipeline: AnalysisPipeline,
 }
 
 impl AnalysisDomain {
     /// 创建新的分析领域管理器
     pub fn new(llm_handle: LLMHandle, video_processor: Arc<VideoProcessor>) -> Self {
+        let analysis_queue = AnalysisQueue::default();
         Self {
             llm_handle,
             video_processor,
+            analysis_pipeline: AnalysisPipeline::new(
+                crate::llm::queue::DEFAULT_PREPARATION_CONCURRENCY,
+                analysis_queue.clone(),
+            ),
+            analysis_queue,
         }
     }
 
+    /// 按配置设置准备与推理并发（推理并发不超过 MAX_INFERENCE_CONCURRENCY）
+    pub fn with_concurrency(mut self, settings: &AnalysisConcurrencySettings) -> Self {
+        if settings.inference_concurrency > MAX_INFERENCE_CONCURRENCY {
+            warn!(
+                "推理并发 {} 超过上限，按 {} 执行",
+                settings.inference_concurrency, MAX_INFERENCE_CONCURRENCY
+            );
+        }
+        self.analysis_queue = AnalysisQueue::new(
+            settings
+                .inference_concurrency
+                .clamp(1, MAX_INFERENCE_CONCURRENCY),
+        );
+        self.analysis_pipeline = AnalysisPipeline::new(
+            settings.preparation_concurrency,
+            self.analysis_queue.clone(),
+        );
+        self
+    }
+
     /// 获取 LLM Handle
     pub fn get_llm_handle(&self) -> &LLMHandle {
         &self.llm_handle
@@ -33,4 +65,14 @@ impl AnalysisDomain {
     pub fn get_video_processor(&self) -> &Arc<VideoProcessor> {
         &self.video_processor
     }
+
+    /// 获取分析调度队列（所有分析入口共享并发名额）
+    pub fn get_analysis_queue(&self) -> &AnalysisQueue {
+        &self.analysis_queue
+    }
+
+    /// 获取后台分析流水线（推理阶段与分析调度队列共享名额）
+    pub fn get_analysis_pipeline(&self) -> &AnalysisPipeline {
+        &self.analysis_pipeline
+    }
 }
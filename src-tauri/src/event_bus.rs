@@ -27,6 +27,20 @@ pub enum AppEvent {
         window_end: DateTime<Utc>,
     },
 
+    /// 截屏卡死事件（看门狗检测到长时间没有新帧，即将重启截屏任务）
+    CaptureStalled {
+        last_frame_at: DateTime<Utc>,
+        stalled_secs: u64,
+        restart_attempt: u32,
+    },
+
+    /// 截屏恢复事件（卡死后重新出帧，gap_start 到 gap_end 之间没有截图）
+    CaptureRecovered {
+        gap_start: DateTime<Utc>,
+        gap_end: DateTime<Utc>,
+        restart_attempts: u32,
+    },
+
     // --- 分析事件 ---
     /// 分析开始事件
     AnalysisStarted { session_id: i64 },
@@ -34,7 +48,7 @@ pub enum AppEvent {
     /// 分析完成事件
     AnalysisCompleted {
         session_id: i64,
-        summary: SessionSummary,
+        summary: Box<SessionSummary>,
     },
 
     /// 分析失败事件
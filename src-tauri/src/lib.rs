@@ -10,12 +10,19 @@ pub mod logger;
 pub mod models;
 pub mod notion;
 pub mod settings;
+pub mod shutdown;
 pub mod storage;
+pub mod telemetry;
+pub mod time_mapper;
 pub mod video;
+pub mod webhook;
+
+#[cfg(test)]
+mod test_server;
 
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 // Actor模式不再需要Mutex和RwLock
 // use tokio::sync::{Mutex, RwLock};
 use tracing::{debug, error, info, warn};
@@ -54,6 +61,8 @@ pub struct AppState {
     pub system_domain: Arc<SystemDomain>,
     /// 事件总线
     pub event_bus: Arc<EventBus>,
+    /// 退出协调器
+    pub shutdown: Arc<shutdown::ShutdownCoordinator>,
 }
 
 /// 文件夹类型枚举（用于安全的路径访问）
@@ -95,6 +104,7 @@ async fn get_database_status(
 /// - `state`: 应用状态
 /// - `start_date`: 开始日期 (格式: YYYY-MM-DD)
 /// - `end_date`: 结束日期 (格式: YYYY-MM-DD)
+/// - `midnight_policy`: 跨午夜会话的归属（默认计入开始日）
 ///
 /// # 返回
 /// - `Ok(Vec<Activity>)`: 活动列表
@@ -104,24 +114,68 @@ async fn get_activities(
     state: tauri::State<'_, AppState>,
     start_date: String,
     end_date: String,
+    midnight_policy: Option<storage::day_grouping::MidnightPolicy>,
 ) -> Result<Vec<Activity>, String> {
     let db = state.storage_domain.get_db().await?;
-    db.get_activities(&start_date, &end_date)
+    let policy = midnight_policy.unwrap_or_default();
+    storage::day_grouping::activities(&db, &start_date, &end_date, policy)
         .await
         .map_err(|e| e.to_string())
 }
 
-/// 获取某天的会话列表
+/// 获取某天的会话列表（跨午夜的会话按 midnight_policy 归属，默认计入开始日）
 #[tauri::command]
 async fn get_day_sessions(
     state: tauri::State<'_, AppState>,
     date: String,
+    midnight_policy: Option<storage::day_grouping::MidnightPolicy>,
 ) -> Result<Vec<Session>, String> {
-    state
-        .storage_domain
-        .get_db()
-        .await?
-        .get_sessions_by_date(&date)
+    let db = state.storage_domain.get_db().await?;
+    let policy = midnight_policy.unwrap_or_default();
+    let sessions = storage::day_grouping::day_sessions(&db, &date, policy)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(sessions.into_iter().map(|(session, _)| session).collect())
+}
+
+/// 按类别路径汇总某天的会话时长（分钟）
+///
+/// # 参数
+/// * `date` - 日期 (YYYY-MM-DD)
+/// * `depth` - 汇总层级（1 为顶层类别，默认 1）；父类别的时长包含其全部子类别
+/// * `midnight_policy` - 跨午夜会话的归属（默认计入开始日）；切分时只统计当天的部分
+#[tauri::command]
+async fn get_category_minutes(
+    state: tauri::State<'_, AppState>,
+    date: String,
+    depth: Option<usize>,
+    midnight_policy: Option<storage::day_grouping::MidnightPolicy>,
+) -> Result<std::collections::BTreeMap<String, i64>, String> {
+    let db = state.storage_domain.get_db().await?;
+    let policy = midnight_policy.unwrap_or_default();
+    let sessions = storage::day_grouping::day_sessions(&db, &date, policy)
+        .await
+        .map_err(|e| format!("获取会话失败: {}", e))?;
+    let depth = depth.unwrap_or(1);
+    Ok(llm::taxonomy::allocated_category_minutes(
+        sessions
+            .iter()
+            .map(|(session, minutes)| (session, *minutes)),
+        depth,
+    ))
+}
+
+/// 获取某天的深度工作时段（与当天有重叠的时段，按开始时间排序）
+///
+/// # 参数
+/// * `date` - 日期 (YYYY-MM-DD)
+#[tauri::command]
+async fn get_deep_work_stretches(
+    state: tauri::State<'_, AppState>,
+    date: String,
+) -> Result<Vec<storage::DeepWorkRecord>, String> {
+    let db = state.storage_domain.get_db().await?;
+    storage::deep_work::day_stretches(&db, &date)
         .await
         .map_err(|e| e.to_string())
 }
@@ -192,6 +246,16 @@ async fn update_config(
             .map_err(|e| e.to_string())?;
     }
 
+    // 更新存储预算
+    if let Some(max_storage_bytes) = config.max_storage_bytes {
+        state
+            .storage_domain
+            .get_cleaner()
+            .await?
+            .set_max_storage_bytes(Some(max_storage_bytes))
+            .await;
+    }
+
     // 更新LLM配置（现在只有Qwen）
     if let Some(_llm_provider) = config.llm_provider {
         // 现在只支持Qwen，不需要切换provider
@@ -361,12 +425,30 @@ async fn toggle_capture(state: tauri::State<'_, AppState>, enabled: bool) -> Res
         .set_capturing(enabled)
         .await;
 
+    // provider 预热/释放可能要加载或卸载本地模型，放到后台执行，不阻塞切换
+    let llm_handle = state.analysis_domain.get_llm_handle().clone();
     if enabled {
         info!("恢复截屏");
         // TODO: 恢复调度器
+        tokio::spawn(async move {
+            if let Err(e) = llm_handle.start_providers().await {
+                warn!("LLM provider 预热失败: {}", e);
+            }
+        });
     } else {
         info!("暂停截屏");
         // TODO: 暂停调度器
+        tokio::spawn(async move {
+            // 仍有分析在排队或执行时保留模型，避免卸载后又立即重新加载
+            let pending = llm_handle.pending_analyses();
+            if pending > 0 {
+                info!("仍有 {} 个分析未完成，跳过 provider 资源释放", pending);
+                return;
+            }
+            if let Err(e) = llm_handle.stop_providers().await {
+                warn!("LLM provider 资源释放失败: {}", e);
+            }
+        });
     }
 
     Ok(())
@@ -425,11 +507,27 @@ async fn retry_session_analysis(
             .await
             .map_err(|e| e.to_string())?;
 
-        let video_path = session_detail
-            .session
-            .video_path
-            .clone()
-            .ok_or_else(|| "该会话没有关联视频，无法重新分析".to_string())?;
+        let video_path = match session_detail.session.video_path.clone() {
+            Some(path) => path,
+            None => {
+                // 原始帧已在分析后清理时，明确提示帧不可用
+                let pruned = state
+                    .storage_domain
+                    .get_db()
+                    .await?
+                    .get_frame_prune(session_id)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                return Err(match pruned {
+                    Some(record) => format!(
+                        "该会话的原始帧已于 {} 在分析后清理（保留 {} 帧），无法重新分析",
+                        record.pruned_at.format("%Y-%m-%d %H:%M:%S"),
+                        record.kept_count
+                    ),
+                    None => "该会话没有关联视频，无法重新分析".to_string(),
+                });
+            }
+        };
 
         let session_start = session_detail.session.start_time;
         let session_end = session_detail.session.end_time;
@@ -479,6 +577,7 @@ async fn retry_session_analysis(
             session_end,
             duration_minutes,
             Some(session_id),
+            llm::AnalysisPriority::Interactive,
         )
         .await?;
 
@@ -1001,6 +1100,533 @@ async fn refresh_device_info(state: tauri::State<'_, AppState>) -> Result<u64, S
         .map_err(|e| e.to_string())
 }
 
+/// 导出会话数据（JSON/CSV），用于迁移到其他设备
+#[tauri::command]
+async fn export_sessions(
+    state: tauri::State<'_, AppState>,
+    request: ExportRequest,
+) -> Result<usize, String> {
+    if !matches!(request.export_type, ExportType::Sessions) {
+        return Err("目前仅支持导出会话数据".to_string());
+    }
+    let output_path = request
+        .output_path
+        .ok_or_else(|| "未指定导出路径".to_string())?;
+    info!("导出会话到 {}", output_path);
+
+    let db = state.storage_domain.get_db().await?;
+    storage::transfer::export_sessions(
+        &db,
+        Path::new(&output_path),
+        &request.format,
+        Some(&request.date_range),
+    )
+    .await
+    .map_err(|e| format!("导出会话失败: {}", e))
+}
+
+/// 将会话导出为 Markdown 文本（便于粘贴进工作日志），可选内嵌会话封面
+#[tauri::command]
+async fn export_session_markdown(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+    include_cover: bool,
+) -> Result<String, String> {
+    validate_session_id(session_id)?;
+    let db = state.storage_domain.get_db().await?;
+    storage::markdown::render_session_markdown(&db, session_id, include_cover)
+        .await
+        .map_err(|e| format!("导出 Markdown 失败: {}", e))
+}
+
+/// 导入其他实例导出的会话数据，返回逐条导入结果
+#[tauri::command]
+async fn import_sessions(
+    state: tauri::State<'_, AppState>,
+    path: String,
+    format: ExportFormat,
+) -> Result<storage::transfer::ImportReport, String> {
+    info!("从 {} 导入会话", path);
+
+    let db = state.storage_domain.get_db().await?;
+    storage::transfer::import_sessions(&db, Path::new(&path), &format)
+        .await
+        .map_err(|e| format!("导入会话失败: {}", e))
+}
+
+/// 将已有会话的零散帧打包为归档（未指定会话时处理全部会话），返回 (会话数, 帧数)
+#[tauri::command]
+async fn pack_session_frames(
+    state: tauri::State<'_, AppState>,
+    session_ids: Option<Vec<i64>>,
+) -> Result<(usize, usize), String> {
+    info!("打包会话帧: {:?}", session_ids);
+
+    let db = state.storage_domain.get_db().await?;
+    storage::frame_pack::pack_existing_sessions(&db, session_ids)
+        .await
+        .map_err(|e| format!("打包会话帧失败: {}", e))
+}
+
+/// 读取帧图片（支持归档内的帧），返回 data URL 供前端显示
+#[tauri::command]
+async fn read_frame_data(path: String) -> Result<String, String> {
+    use base64::{engine::general_purpose, Engine as _};
+
+    let data = storage::frame_pack::read_frame(&path)
+        .await
+        .map_err(|e| format!("读取帧失败: {}", e))?;
+    let mime = if path.to_ascii_lowercase().ends_with(".png") {
+        "image/png"
+    } else {
+        "image/jpeg"
+    };
+    Ok(format!(
+        "data:{};base64,{}",
+        mime,
+        general_purpose::STANDARD.encode(data)
+    ))
+}
+
+/// 为会话生成多个候选总结供用户挑选（不修改已保存的总结）
+#[tauri::command]
+async fn generate_summary_candidates(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+    count: usize,
+) -> Result<Vec<llm::SessionSummary>, String> {
+    validate_session_id(session_id)?;
+    if count == 0 || count > llm::plugin::MAX_SUMMARY_CANDIDATES {
+        return Err(format!(
+            "候选数量必须在 1-{} 之间",
+            llm::plugin::MAX_SUMMARY_CANDIDATES
+        ));
+    }
+
+    let detail = state
+        .storage_domain
+        .get_db()
+        .await?
+        .get_session_detail(session_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let frames: Vec<String> = detail.frames.iter().map(|f| f.file_path.clone()).collect();
+    if frames.is_empty() {
+        return Err("该会话没有可用的帧，无法生成候选总结".to_string());
+    }
+
+    let _permit = state
+        .analysis_domain
+        .get_analysis_queue()
+        .acquire(llm::AnalysisPriority::Interactive)
+        .await;
+    let mut candidates = state
+        .analysis_domain
+        .get_llm_handle()
+        .analyze_frames_n(frames, count)
+        .await
+        .map_err(|e| format!("生成候选总结失败: {}", e))?;
+    // 候选总结的时间统一使用会话的真实时间
+    for candidate in &mut candidates {
+        candidate.start_time = detail.session.start_time;
+        candidate.end_time = detail.session.end_time;
+    }
+    Ok(candidates)
+}
+
+/// 只重新生成会话总结的单个字段（标签、关键时刻或评分），其余部分保持不变
+///
+/// 标签会写回会话记录；关键时刻与评分不单独持久化，仅返回合并后的总结
+#[tauri::command]
+async fn refine_field(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+    field: llm::SummaryField,
+) -> Result<llm::SessionSummary, String> {
+    validate_session_id(session_id)?;
+
+    let db = state.storage_domain.get_db().await?;
+    let detail = db
+        .get_session_detail(session_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let frames: Vec<String> = detail.frames.iter().map(|f| f.file_path.clone()).collect();
+    if frames.is_empty() {
+        return Err("该会话没有可用的帧，无法重新生成".to_string());
+    }
+
+    // 关键时刻与 build_session_summary 一致，取自视频分段
+    let key_moments = db
+        .get_video_segments_by_session(session_id)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|segment| llm::KeyMoment {
+            time: segment.start_timestamp,
+            description: segment.description,
+            importance: 3,
+        })
+        .collect();
+    let session = detail.session;
+    let current = llm::SessionSummary {
+        title: session.title.clone(),
+        summary: session.summary.clone(),
+        tags: detail.tags,
+        start_time: session.start_time,
+        end_time: session.end_time,
+        key_moments,
+        ..Default::default()
+    };
+
+    let _permit = state
+        .analysis_domain
+        .get_analysis_queue()
+        .acquire(llm::AnalysisPriority::Interactive)
+        .await;
+    let refined = state
+        .analysis_domain
+        .get_llm_handle()
+        .refine_field(frames, current, field)
+        .await
+        .map_err(|e| format!("重新生成失败: {}", e))?;
+
+    if field == llm::SummaryField::Tags {
+        let tags_json = serde_json::to_string(&refined.tags).map_err(|e| e.to_string())?;
+        db.update_session(
+            session_id,
+            &session.title,
+            &session.summary,
+            session.video_path.as_deref(),
+            &tags_json,
+        )
+        .await
+        .map_err(|e| format!("保存标签失败: {}", e))?;
+    }
+    Ok(refined)
+}
+
+/// 导出会话联系表（采样帧网格 + 时间标签 + 关键时刻标记），返回 PNG 路径
+#[tauri::command]
+async fn render_contact_sheet(
+    state: tauri::State<'_, AppState>,
+    app: tauri::AppHandle,
+    session_id: i64,
+    config: Option<video::contact_sheet::ContactSheetConfig>,
+) -> Result<String, String> {
+    validate_session_id(session_id)?;
+    let mut config = config.unwrap_or_default();
+    if config.sampling_interval.is_none() {
+        // 默认与分析时的采样间隔一致
+        let llm_config = state.analysis_domain.get_llm_handle().get_config().await;
+        config.sampling_interval = llm_config
+            .ok()
+            .map(|c| c.analysis_params.frame_sampling_interval as usize);
+    }
+
+    let output_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("获取应用目录失败: {}", e))?
+        .join("contact_sheets");
+    let db = state.storage_domain.get_db().await?;
+    video::contact_sheet::render_contact_sheet(&db, session_id, &output_dir, &config)
+        .await
+        .map(|path| path.to_string_lossy().to_string())
+        .map_err(|e| format!("生成联系表失败: {}", e))
+}
+
+/// 获取会话封面（data URL）；未生成封面或原始帧已清理时返回 None
+#[tauri::command]
+async fn get_session_thumbnail(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+) -> Result<Option<String>, String> {
+    use base64::{engine::general_purpose, Engine as _};
+
+    validate_session_id(session_id)?;
+    let db = state.storage_domain.get_db().await?;
+    let record = db
+        .get_session_thumbnail(session_id)
+        .await
+        .map_err(|e| format!("读取会话封面失败: {}", e))?;
+    Ok(record.map(|record| {
+        format!(
+            "data:image/jpeg;base64,{}",
+            general_purpose::STANDARD.encode(&record.image_data)
+        )
+    }))
+}
+
+/// 获取会话的截屏缺口（截屏卡死期间没有截图的时段）
+#[tauri::command]
+async fn get_session_capture_gaps(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+) -> Result<Vec<storage::CaptureGapRecord>, String> {
+    validate_session_id(session_id)?;
+    let db = state.storage_domain.get_db().await?;
+    db.get_capture_gaps(session_id)
+        .await
+        .map_err(|e| format!("读取截屏缺口失败: {}", e))
+}
+
+/// 获取会话分析实际使用的帧（需开启 Ollama 的 record_sampled_frames）；未记录时返回 null
+#[tauri::command]
+async fn get_session_frame_selection(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+) -> Result<Option<llm::FrameSelection>, String> {
+    validate_session_id(session_id)?;
+    let db = state.storage_domain.get_db().await?;
+    let Some(record) = db
+        .get_frame_selection(session_id)
+        .await
+        .map_err(|e| format!("读取分析所用帧失败: {}", e))?
+    else {
+        return Ok(None);
+    };
+    let frames = serde_json::from_str(&record.frames)
+        .map_err(|e| format!("分析所用帧记录格式无效: {}", e))?;
+    Ok(Some(llm::FrameSelection {
+        total_frames: record.total_frames.max(0) as usize,
+        frames,
+    }))
+}
+
+/// 获取会话的自定义总结字段（由 Ollama 配置的 extra_fields 生成）；未记录时返回空对象
+#[tauri::command]
+async fn get_session_extra_fields(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+) -> Result<std::collections::HashMap<String, serde_json::Value>, String> {
+    validate_session_id(session_id)?;
+    let db = state.storage_domain.get_db().await?;
+    let Some(record) = db
+        .get_session_extra_fields(session_id)
+        .await
+        .map_err(|e| format!("读取自定义字段失败: {}", e))?
+    else {
+        return Ok(Default::default());
+    };
+    serde_json::from_str(&record.fields).map_err(|e| format!("自定义字段记录格式无效: {}", e))
+}
+
+/// 设置会话置顶（收藏）状态；置顶会话不会被过期清理删除
+#[tauri::command]
+async fn set_session_pinned(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+    pinned: bool,
+) -> Result<(), String> {
+    validate_session_id(session_id)?;
+    let db = state.storage_domain.get_db().await?;
+    db.set_session_pinned(session_id, pinned)
+        .await
+        .map_err(|e| format!("设置会话置顶失败: {}", e))
+}
+
+/// 获取所有置顶会话（按开始时间倒序）
+#[tauri::command]
+async fn get_pinned_sessions(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<storage::Session>, String> {
+    let db = state.storage_domain.get_db().await?;
+    db.list_pinned_sessions()
+        .await
+        .map_err(|e| format!("读取置顶会话失败: {}", e))
+}
+
+/// 按日期范围、类别、关键词与评分范围筛选会话，分页返回（附符合条件的总数）
+#[tauri::command]
+async fn query_sessions(
+    state: tauri::State<'_, AppState>,
+    filter: storage::SessionFilter,
+) -> Result<storage::SessionPage, String> {
+    let db = state.storage_domain.get_db().await?;
+    db.query_sessions(&filter)
+        .await
+        .map_err(|e| format!("筛选会话失败: {}", e))
+}
+
+/// 列出死信队列：多次分析失败、不再自动重试的会话（含最近的错误与失败次数）
+#[tauri::command]
+async fn get_dead_letters(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<storage::AnalysisFailureRecord>, String> {
+    let db = state.storage_domain.get_db().await?;
+    storage::dead_letter::dead_letters(&db)
+        .await
+        .map_err(|e| format!("读取死信队列失败: {}", e))
+}
+
+/// 把死信队列中的会话重新入队，下一轮自动分析时重试
+#[tauri::command]
+async fn requeue_dead_letter(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+) -> Result<storage::AnalysisFailureRecord, String> {
+    validate_session_id(session_id)?;
+    let db = state.storage_domain.get_db().await?;
+    storage::dead_letter::requeue(&db, session_id)
+        .await
+        .map_err(|e| format!("重新入队失败: {}", e))
+}
+
+/// 为会话选择分析模式（如 work、study、entertainment），重新分析该会话时按此模式构造提示词
+#[tauri::command]
+async fn set_session_analysis_mode(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+    mode: String,
+) -> Result<(), String> {
+    validate_session_id(session_id)?;
+    let mode = mode.trim();
+    if mode.is_empty() {
+        return Err("分析模式不能为空".to_string());
+    }
+    let db = state.storage_domain.get_db().await?;
+    db.save_session_analysis_mode(&storage::SessionAnalysisModeRecord {
+        session_id,
+        mode: mode.to_string(),
+        updated_at: storage::local_now(),
+    })
+    .await
+    .map_err(|e| format!("保存分析模式失败: {}", e))
+}
+
+/// 获取会话的分析模式；未选择且未按模式分析过时返回 None
+#[tauri::command]
+async fn get_session_analysis_mode(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+) -> Result<Option<String>, String> {
+    validate_session_id(session_id)?;
+    let db = state.storage_domain.get_db().await?;
+    let record = db
+        .get_session_analysis_mode(session_id)
+        .await
+        .map_err(|e| format!("读取分析模式失败: {}", e))?;
+    Ok(record.map(|record| record.mode))
+}
+
+/// 人工更正会话的标题、摘要与标签；更正同时记录下来，开启 few_shot_examples 后作为相似会话的分析示例
+#[tauri::command]
+async fn correct_session_summary(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+    title: Option<String>,
+    summary: Option<String>,
+    tags: Vec<llm::ActivityTag>,
+) -> Result<(), String> {
+    validate_session_id(session_id)?;
+    let db = state.storage_domain.get_db().await?;
+    let session = db
+        .get_session(session_id)
+        .await
+        .map_err(|e| format!("读取会话失败: {}", e))?;
+
+    // 多次更正同一会话时保留模型最初的总结
+    let description = match db
+        .get_summary_correction(session_id)
+        .await
+        .map_err(|e| format!("读取人工更正失败: {}", e))?
+    {
+        Some(existing) => existing.description,
+        None => {
+            let original_tags: Vec<llm::ActivityTag> =
+                serde_json::from_str(&session.tags).unwrap_or_default();
+            llm::corrections::describe_summary(&session.title, &session.summary, &original_tags)
+        }
+    };
+
+    let non_empty =
+        |text: Option<String>| text.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+    let title = non_empty(title).unwrap_or(session.title);
+    let summary = non_empty(summary).unwrap_or(session.summary);
+    let tags_json = serde_json::to_string(&tags).map_err(|e| format!("序列化标签失败: {}", e))?;
+    db.update_session(
+        session_id,
+        &title,
+        &summary,
+        session.video_path.as_deref(),
+        &tags_json,
+    )
+    .await
+    .map_err(|e| format!("更新会话失败: {}", e))?;
+
+    let embedding = serde_json::to_string(&llm::corrections::embed_text(&description))
+        .map_err(|e| format!("序列化向量失败: {}", e))?;
+    let record = storage::SummaryCorrectionRecord {
+        session_id,
+        description,
+        corrected: llm::corrections::corrected_text(&title, &summary, &tags),
+        embedding,
+        created_at: storage::local_now(),
+    };
+    db.save_summary_correction(&record)
+        .await
+        .map_err(|e| format!("保存人工更正失败: {}", e))?;
+    info!("已记录会话 {} 的人工更正", session_id);
+    Ok(())
+}
+
+/// 合并相邻会话并重新分析，返回合并后的会话 ID；原始会话保存在合并记录中
+#[tauri::command]
+async fn merge_sessions(
+    state: tauri::State<'_, AppState>,
+    session_ids: Vec<i64>,
+) -> Result<i64, String> {
+    for session_id in &session_ids {
+        validate_session_id(*session_id)?;
+    }
+    let db = state.storage_domain.get_db().await?;
+
+    let _permit = state
+        .analysis_domain
+        .get_analysis_queue()
+        .acquire(llm::AnalysisPriority::Interactive)
+        .await;
+    llm::session_merge::merge_sessions(
+        &db,
+        state.analysis_domain.get_llm_handle(),
+        session_ids,
+        llm::session_merge::DEFAULT_CHUNK_FRAMES,
+    )
+    .await
+    .map_err(|e| format!("合并会话失败: {}", e))
+}
+
+/// 只分析会话中的一段时间，返回该时间段的总结
+///
+/// start/end 为相对会话开始的 MM:SS、HH:MM:SS 或 RFC3339 时间；overwrite 为 true 时替换会话的总结
+#[tauri::command]
+async fn analyze_time_range(
+    state: tauri::State<'_, AppState>,
+    session_id: i64,
+    start: String,
+    end: String,
+    overwrite: Option<bool>,
+) -> Result<llm::SessionSummary, String> {
+    validate_session_id(session_id)?;
+    let db = state.storage_domain.get_db().await?;
+
+    let _permit = state
+        .analysis_domain
+        .get_analysis_queue()
+        .acquire(llm::AnalysisPriority::Interactive)
+        .await;
+    llm::time_slice::analyze_time_range(
+        &db,
+        state.analysis_domain.get_llm_handle(),
+        session_id,
+        &start,
+        &end,
+        overwrite.unwrap_or(false),
+    )
+    .await
+    .map_err(|e| format!("时间段分析失败: {}", e))
+}
+
 /// 同步 SQLite 数据到 MariaDB
 #[tauri::command]
 async fn sync_data_to_mariadb(
@@ -1027,14 +1653,63 @@ async fn sync_data_to_mariadb(
 
     // 执行同步
     state
-        .storage_domain
-        .get_db()
-        .await?
-        .sync_from_sqlite_to_mariadb(&sqlite_db_path.to_string_lossy())
+        .storage_domain
+        .get_db()
+        .await?
+        .sync_from_sqlite_to_mariadb(&sqlite_db_path.to_string_lossy())
+        .await
+        .map_err(|e| format!("同步数据失败: {}", e))?;
+
+    Ok("数据同步成功".to_string())
+}
+
+/// 获取各 LLM provider 的健康状态（配置状态、最近成功/失败、平均延迟、成功率）
+#[tauri::command]
+async fn provider_status(
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<llm::ProviderStatus>, String> {
+    Ok(state.analysis_domain.get_llm_handle().provider_status())
+}
+
+/// 按设置读取配置档文件并取出要应用的配置档；name 为空时依次使用设置中的 active 与文件中的 default
+fn select_provider_profile(
+    app_dir: &std::path::Path,
+    settings: &models::ProviderProfilesSettings,
+    name: Option<&str>,
+) -> anyhow::Result<(String, llm::ProviderProfile)> {
+    // 绝对路径 join 后保持不变
+    let profiles = llm::ProviderProfiles::load(&app_dir.join(&settings.path))?;
+    let (name, profile) = profiles.select(name.or(settings.active.as_deref()))?;
+    Ok((name.to_string(), profile.clone()))
+}
+
+/// 应用配置档文件中的一个配置档（切换 provider 并写入其配置）
+#[tauri::command]
+async fn apply_provider_profile(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    name: Option<String>,
+) -> Result<String, String> {
+    let settings = state
+        .storage_domain
+        .get_settings()
+        .get()
+        .await
+        .provider_profiles
+        .ok_or_else(|| "未配置 provider 配置档文件".to_string())?;
+    let app_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("获取应用目录失败: {}", e))?;
+    let (name, profile) =
+        select_provider_profile(&app_dir, &settings, name.as_deref()).map_err(|e| e.to_string())?;
+    state
+        .analysis_domain
+        .get_llm_handle()
+        .apply_profile(profile)
         .await
-        .map_err(|e| format!("同步数据失败: {}", e))?;
-
-    Ok("数据同步成功".to_string())
+        .map_err(|e| format!("应用配置档 {} 失败: {}", name, e))?;
+    Ok(name)
 }
 
 /// 配置Qwen
@@ -1059,6 +1734,7 @@ async fn configure_llm_provider(
     config: serde_json::Value,
 ) -> Result<(), String> {
     info!("配置LLM提供商: {}", provider);
+    let middleware = middleware_override(&config)?;
 
     // 根据 provider 构建配置
     let llm_provider_config = match provider.as_str() {
@@ -1095,6 +1771,7 @@ async fn configure_llm_provider(
                     .get("video_path")
                     .and_then(|v| v.as_str())
                     .map(|s| s.to_string()),
+                pricing: pricing_override(&config)?,
             };
 
             models::LLMProviderConfig {
@@ -1104,6 +1781,9 @@ async fn configure_llm_provider(
                 use_video_mode: qwen_config.use_video_mode,
                 auth_token: String::new(), // Qwen 不使用 auth_token
                 codex_config: None,
+                ollama_config: None,
+                pricing: qwen_config.pricing,
+                middleware: middleware.clone(),
             }
         }
         "claude" => {
@@ -1147,6 +1827,9 @@ async fn configure_llm_provider(
                 use_video_mode: true, // Claude 支持视频模式
                 auth_token,           // 添加 auth_token 字段
                 codex_config: None,
+                ollama_config: None,
+                pricing: pricing_override(&config)?,
+                middleware: middleware.clone(),
             }
         }
         "codex" => {
@@ -1170,22 +1853,25 @@ async fn configure_llm_provider(
                 use_video_mode: false,
                 auth_token: String::new(),
                 codex_config: Some(stored),
+                ollama_config: None,
+                pricing: None, // Codex 的单价保存在 codex_config 中
+                middleware: middleware.clone(),
             }
         }
         // ✅ 新增以下 Ollama 分支
     "ollama" => {
-        let ollama_config = llm::OllamaConfig {
-            base_url: config
-                .get("base_url")
-                .and_then(|v| v.as_str())
-                .unwrap_or("http://100.82.18.91:11434")
-                .to_string(),
-            model: config
-                .get("model")
-                .and_then(|v| v.as_str())
-                .unwrap_or("qwen3-vl:32b")
-                .to_string(),
-        };
+        // 设置界面只发送部分字段，在当前配置上合并，避免重置其余开关
+        let ollama_config = state
+            .analysis_domain
+            .get_llm_handle()
+            .get_config()
+            .await
+            .map_err(|e| e.to_string())?
+            .ollama
+            .merged(config.clone())
+            .map_err(|e| e.to_string())?;
+        let stored = serde_json::to_value(&ollama_config)
+            .map_err(|e| format!("Ollama 配置序列化失败: {}", e))?;
 
         // 1. 更新内存中的 LLM Manager
         state
@@ -1203,6 +1889,9 @@ async fn configure_llm_provider(
             use_video_mode: true, // Ollama 通常逐帧分析，但也支持 vision
             auth_token: String::new(),
             codex_config: None,
+            ollama_config: Some(stored),
+            pricing: None,
+            middleware: middleware.clone(),
         }
     }
         _ => {
@@ -1212,6 +1901,7 @@ async fn configure_llm_provider(
 
     let update = models::AppConfig {
         retention_days: None,
+        max_storage_bytes: None,
         llm_provider: Some(provider.clone()),
         capture_interval: None,
         summary_interval: None,
@@ -1222,6 +1912,16 @@ async fn configure_llm_provider(
         logger_settings: None,
         database_config: None,
         notion_config: None,
+        frame_cleanup: None,
+        frame_storage: None,
+        session_thumbnail: None,
+        capture_watchdog: None,
+        analysis_concurrency: None,
+        activity_change: None,
+        metrics_exporter: None,
+        provider_profiles: None,
+        webhook: None,
+        deep_work: None,
     };
 
     state
@@ -1232,8 +1932,7 @@ async fn configure_llm_provider(
         .map_err(|e| format!("保存配置失败: {}", e))?;
 
     // 配置内存中的LLM管理器
-    // 注意：目前 LLM Manager 的 configure 方法只支持 QwenConfig
-    // Claude 的配置在切换 provider 时自动处理
+    // Codex / Ollama 已在上面配置；Claude 的认证信息已写入环境变量，这里下发模型与计费单价
     if provider == "openai" {
         let qwen_config = llm::QwenConfig {
             api_key: config
@@ -1259,6 +1958,7 @@ async fn configure_llm_provider(
                 .get("video_path")
                 .and_then(|v| v.as_str())
                 .map(|s| s.to_string()),
+            pricing: pricing_override(&config)?,
         };
 
         state
@@ -1267,13 +1967,63 @@ async fn configure_llm_provider(
             .configure(qwen_config)
             .await
             .map_err(|e| e.to_string())?;
+    } else if provider == "claude" {
+        state
+            .analysis_domain
+            .get_llm_handle()
+            .configure_claude(config)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    if let Some(middleware) = middleware {
+        state
+            .analysis_domain
+            .get_llm_handle()
+            .configure_middleware(middleware)
+            .await
+            .map_err(|e| e.to_string())?;
     }
-    // Claude 的配置会在应用启动时或切换 provider 时自动加载
 
     info!("LLM配置已保存并应用");
     Ok(())
 }
 
+/// 读取设置中的计费单价覆盖（未提供时使用 provider 内置单价）
+fn pricing_override(config: &serde_json::Value) -> Result<Option<llm::ProviderPricing>, String> {
+    match config.get("pricing") {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(pricing) => serde_json::from_value(pricing.clone())
+            .map(Some)
+            .map_err(|e| format!("计费单价配置解析失败: {}", e)),
+    }
+}
+
+/// 读取设置中的中间件配置（未提供时保留当前配置）
+fn middleware_override(
+    config: &serde_json::Value,
+) -> Result<Option<llm::MiddlewareConfig>, String> {
+    match config.get("middleware") {
+        None | Some(serde_json::Value::Null) => Ok(None),
+        Some(middleware) => serde_json::from_value(middleware.clone())
+            .map(Some)
+            .map_err(|e| format!("中间件配置解析失败: {}", e)),
+    }
+}
+
+/// 估算当前 LLM provider 分析指定帧数的费用（本地 provider 返回 None）
+#[tauri::command]
+async fn estimate_analysis_cost(
+    state: tauri::State<'_, AppState>,
+    frames: usize,
+) -> Result<Option<llm::CostEstimate>, String> {
+    state
+        .analysis_domain
+        .get_llm_handle()
+        .estimate_cost(frames)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// 测试截屏功能
 #[tauri::command]
 async fn test_capture(state: tauri::State<'_, AppState>) -> Result<String, String> {
@@ -1291,48 +2041,23 @@ async fn test_capture(state: tauri::State<'_, AppState>) -> Result<String, Strin
     }
 }
 
-/// 删除会话
+/// 删除会话：清理所有表中的相关记录（含人工更正）以及帧文件、帧归档和视频文件，返回删除清单
+///
+/// secure 为 true 时文件删除前先用零覆写
 #[tauri::command]
 async fn delete_session(
     state: tauri::State<'_, AppState>,
     session_id: i64,
-) -> Result<String, String> {
+    secure: Option<bool>,
+) -> Result<storage::SessionPurgeManifest, String> {
     validate_session_id(session_id)?;
-    info!("删除会话: {}", session_id);
-
-    // 获取会话详情
-    let session_detail = state
-        .storage_domain
-        .get_db()
-        .await?
-        .get_session_detail(session_id)
-        .await
-        .map_err(|e| format!("获取会话详情失败: {}", e))?;
-
-    // 删除视频文件（如果存在）
-    if let Some(video_path) = &session_detail.session.video_path {
-        if let Err(e) = tokio::fs::remove_file(video_path).await {
-            error!("删除视频文件失败: {}", e);
-        }
-    }
-
-    // 删除帧文件
-    for frame in &session_detail.frames {
-        if let Err(e) = tokio::fs::remove_file(&frame.file_path).await {
-            error!("删除帧文件失败: {}", e);
-        }
-    }
+    let secure = secure.unwrap_or(false);
+    info!("删除会话: {} (覆写文件: {})", session_id, secure);
 
-    // 删除数据库记录 - 使用 Database 提供的方法
-    state
-        .storage_domain
-        .get_db()
-        .await?
-        .delete_session(session_id)
+    let db = state.storage_domain.get_db().await?;
+    storage::cleaner::purge_session(&db, session_id, secure)
         .await
-        .map_err(|e| format!("删除会话失败: {}", e))?;
-
-    Ok("会话已成功删除".to_string())
+        .map_err(|e| format!("删除会话失败: {}", e))
 }
 
 /// 重新生成timeline
@@ -1426,7 +2151,12 @@ async fn regenerate_timeline(
             .await
             .map_err(|e| format!("清除旧时间线失败: {}", e))?;
 
-        // 使用LLM重新生成timeline
+        // 使用LLM重新生成timeline（用户触发，优先于后台分析）
+        let _permit = state
+            .analysis_domain
+            .get_analysis_queue()
+            .acquire(llm::AnalysisPriority::Interactive)
+            .await;
         let llm_handle = state.analysis_domain.get_llm_handle();
         // 设置当前的 session_id，以便 LLM 调用记录能正确关联
         llm_handle
@@ -2030,6 +2760,31 @@ async fn test_ollama_api(config: serde_json::Value) -> Result<String, String> {
 }
 
 
+/// 确认 Ollama 配置的模型已下载；pull_if_missing 为 true 时自动拉取，进度通过 model-pull-progress 事件推送
+#[tauri::command]
+async fn ensure_ollama_model(
+    app: tauri::AppHandle,
+    config: serde_json::Value,
+    pull_if_missing: bool,
+) -> Result<(), String> {
+    let mut provider = llm::OllamaProvider::new(reqwest::Client::new());
+    provider.configure(config).map_err(|e| e.to_string())?;
+
+    let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+    provider.set_progress_sender(Some(progress_tx));
+    let forward = tokio::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            let _ = app.emit("model-pull-progress", &progress);
+        }
+    });
+
+    let result = provider.ensure_model(pull_if_missing).await;
+    // 释放发送端，等待剩余进度推送完毕
+    drop(provider);
+    let _ = forward.await;
+    result.map_err(|e| e.to_string())
+}
+
 /// 测试 Notion API 连接
 #[tauri::command]
 async fn test_notion_connection(
@@ -2454,6 +3209,7 @@ pub fn run() {
 
             // 设置日志广播器的 app handle
             log_broadcaster.set_app_handle(app.handle().clone());
+            let progress_app_handle = app.handle().clone();
 
             let app_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
 
@@ -2537,6 +3293,12 @@ pub fn run() {
                     initial_config.capture_interval,
                     initial_config.summary_interval,
                 );
+                scheduler_inner.configure_watchdog(
+                    initial_config.capture_watchdog.clone().unwrap_or_default(),
+                );
+                scheduler_inner.configure_activity_change(
+                    initial_config.activity_change.clone().unwrap_or_default(),
+                );
                 let scheduler = Arc::new(scheduler_inner);
 
                 // 初始化系统状态（使用Actor模式，无需锁）
@@ -2561,10 +3323,12 @@ pub fn run() {
                     Arc::new(CaptureDomain::new(capture.clone(), scheduler.clone()));
 
                 // 创建分析领域（使用LLM Handle）
-                let analysis_domain = Arc::new(AnalysisDomain::new(
-                    llm_handle.clone(),
-                    video_processor.clone(),
-                ));
+                let analysis_domain = Arc::new(
+                    AnalysisDomain::new(llm_handle.clone(), video_processor.clone())
+                        .with_concurrency(
+                            &initial_config.analysis_concurrency.clone().unwrap_or_default(),
+                        ),
+                );
 
                 // 创建存储领域（数据库未初始化）
                 let storage_domain = Arc::new(StorageDomain::new_pending(settings.clone()));
@@ -2587,6 +3351,7 @@ pub fn run() {
                     storage_domain,
                     system_domain,
                     event_bus,
+                    shutdown: Arc::new(shutdown::ShutdownCoordinator::new()),
                 };
 
                 // 返回 AppState、两个 Actor、LLM provider、LLM 配置、数据库配置和目录路径
@@ -2612,6 +3377,18 @@ pub fn run() {
                     rt.block_on(async {
                         info!("启动后台任务...");
 
+                        // 启动指标导出（未启用时不监听端口）
+                        let metrics_settings = state_clone
+                            .storage_domain
+                            .get_settings()
+                            .get()
+                            .await
+                            .metrics_exporter
+                            .unwrap_or_default();
+                        if let Err(e) = telemetry::start_exporter(&metrics_settings).await {
+                            error!("启动指标导出失败: {}", e);
+                        }
+
                         // ========== 异步初始化数据库 ==========
                         info!("开始异步初始化数据库...");
                         let db_result = if let Some(mut db_config) = db_config_to_load {
@@ -2656,6 +3433,13 @@ pub fn run() {
                                 if let Err(e) = cleaner.set_retention_days(retention_days).await {
                                     error!("设置保留天数失败: {}", e);
                                 }
+                                let max_storage_bytes = state_clone
+                                    .storage_domain
+                                    .get_settings()
+                                    .get()
+                                    .await
+                                    .max_storage_bytes;
+                                cleaner.set_max_storage_bytes(max_storage_bytes).await;
 
                                 // 设置清理器到 StorageDomain
                                 state_clone.storage_domain.set_cleaner(cleaner).await;
@@ -2679,6 +3463,22 @@ pub fn run() {
                         tokio::spawn(status_actor.run());
                         info!("Actors 已启动");
 
+                        // 流式分析进度转发到前端（analysis-progress 事件）
+                        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+                        if let Err(e) = state_clone
+                            .analysis_domain
+                            .get_llm_handle()
+                            .set_progress_sender(Some(progress_tx))
+                            .await
+                        {
+                            error!("设置分析进度通道失败: {}", e);
+                        }
+                        tokio::spawn(async move {
+                            while let Some(progress) = progress_rx.recv().await {
+                                let _ = progress_app_handle.emit("analysis-progress", &progress);
+                            }
+                        });
+
                         // 配置 LLM（Actor 启动后才能配置）
                         // 1. 根据配置切换 provider
                         let provider = llm_provider_name.as_str();
@@ -2695,6 +3495,7 @@ pub fn run() {
 
                         // 2. 加载 provider 配置
                         if let Some(llm_config) = llm_config_to_load {
+                            let middleware = llm_config.middleware.clone();
                             match provider {
                                 "openai" => {
                                     // Qwen 配置 - 验证 API key 不为空
@@ -2707,6 +3508,7 @@ pub fn run() {
                                             base_url: llm_config.base_url,
                                             use_video_mode: llm_config.use_video_mode,
                                             video_path: None,
+                                            pricing: llm_config.pricing,
                                         };
 
                                         if let Err(e) = state_clone
@@ -2726,7 +3528,8 @@ pub fn run() {
                                     let claude_config = serde_json::json!({
                                         "model": llm_config.model,
                                         "auth_token": llm_config.auth_token,
-                                        "base_url": llm_config.base_url
+                                        "base_url": llm_config.base_url,
+                                        "pricing": llm_config.pricing
                                     });
 
                                     if let Err(e) = state_clone
@@ -2758,12 +3561,73 @@ pub fn run() {
                                         info!("已从配置文件加载 Codex 设置");
                                     }
                                 }
+                                "ollama" => {
+                                    let ollama_config =
+                                        llm::OllamaConfig::from_provider_config(&llm_config);
+
+                                    if let Err(e) = state_clone
+                                        .analysis_domain
+                                        .get_llm_handle()
+                                        .configure_ollama(ollama_config)
+                                        .await
+                                    {
+                                        error!("加载 Ollama 配置失败: {}", e);
+                                    } else {
+                                        info!("已从配置文件加载 Ollama 设置");
+                                    }
+                                }
                                 _ => {
                                     warn!("未知的 LLM provider: {}", provider);
                                 }
                             }
+
+                            // provider 配置完成后再应用中间件
+                            if let Some(middleware) = middleware {
+                                if let Err(e) = state_clone
+                                    .analysis_domain
+                                    .get_llm_handle()
+                                    .configure_middleware(middleware)
+                                    .await
+                                {
+                                    error!("加载中间件配置失败: {}", e);
+                                }
+                            }
+                        }
+
+                        // 配置档文件（可选）在设置之后应用，覆盖对应 provider 的参数
+                        let profiles_settings = state_clone
+                            .storage_domain
+                            .get_settings()
+                            .get()
+                            .await
+                            .provider_profiles;
+                        if let Some(profiles_settings) = profiles_settings {
+                            match select_provider_profile(&app_dir_clone, &profiles_settings, None) {
+                                Ok((name, profile)) => {
+                                    let provider = profile.provider_name();
+                                    if let Err(e) = state_clone
+                                        .analysis_domain
+                                        .get_llm_handle()
+                                        .apply_profile(profile)
+                                        .await
+                                    {
+                                        error!("应用配置档 {} 失败: {}", name, e);
+                                    } else {
+                                        info!("已应用配置档 {}（provider: {}）", name, provider);
+                                    }
+                                }
+                                Err(e) => error!("加载 provider 配置档失败: {}", e),
+                            }
                         }
 
+                        // 3. 后台预热 provider（本地模型加载较慢，失败不影响启动）
+                        let warmup_handle = state_clone.analysis_domain.get_llm_handle().clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = warmup_handle.start_providers().await {
+                                warn!("LLM provider 预热失败: {}", e);
+                            }
+                        });
+
                         // 初始化 Notion 集成
                         let config = state_clone.storage_domain.get_settings().get().await;
                         if let Some(notion_config) = config.notion_config {
@@ -2790,10 +3654,18 @@ pub fn run() {
                                 state_clone.analysis_domain.get_video_processor().clone(),
                                 state_clone.storage_domain.get_settings().clone(),
                                 state_clone.storage_domain.get_notion_manager().clone(),
+                            )
+                            .with_shutdown(state_clone.shutdown.clone())
+                            .with_analysis_pipeline(
+                                state_clone.analysis_domain.get_analysis_pipeline().clone(),
+                            )
+                            .with_capture_gaps(
+                                state_clone.capture_domain.get_scheduler().capture_gaps(),
                             ));
 
                             // 启动LLM处理器事件监听器
                             llm_processor
+                                .clone()
                                 .start_event_listener(
                                     state_clone.event_bus.clone(),
                                     state_clone.capture_domain.get_capture().clone(),
@@ -2802,6 +3674,11 @@ pub fn run() {
 
                             info!("LLM处理器事件监听器已启动");
 
+                            // 重新分析上次退出时未完成的会话
+                            llm_processor
+                                .requeue_pending_sessions(&state_clone.event_bus)
+                                .await;
+
                             // 启动调度器（事件驱动模式）
                             state_clone
                                 .capture_domain
@@ -2939,6 +3816,8 @@ pub fn run() {
             get_database_status,
             get_activities,
             get_day_sessions,
+            get_category_minutes,
+            get_deep_work_stretches,
             get_day_summary,
             get_session_detail,
             get_app_config,
@@ -2958,8 +3837,34 @@ pub fn run() {
             migrate_timezone_to_local,
             refresh_device_info,
             sync_data_to_mariadb,
+            export_sessions,
+            export_session_markdown,
+            import_sessions,
+            pack_session_frames,
+            read_frame_data,
+            generate_summary_candidates,
+            render_contact_sheet,
+            get_session_thumbnail,
+            get_session_capture_gaps,
+            get_session_frame_selection,
+            get_session_extra_fields,
+            set_session_pinned,
+            get_pinned_sessions,
+            query_sessions,
+            get_dead_letters,
+            requeue_dead_letter,
+            apply_provider_profile,
+            set_session_analysis_mode,
+            get_session_analysis_mode,
+            correct_session_summary,
+            ensure_ollama_model,
+            merge_sessions,
+            analyze_time_range,
+            provider_status,
+            refine_field,
             configure_qwen,
             configure_llm_provider,
+            estimate_analysis_cost,
             test_capture,
             test_llm_api,
             retry_session_analysis,
@@ -2973,8 +3878,45 @@ pub fn run() {
             search_notion_pages,
             create_notion_database,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app_handle, event| {
+            if let tauri::RunEvent::ExitRequested { .. } = event {
+                let state = app_handle.state::<AppState>();
+                // 退出请求可能多次触发，只处理第一次
+                if state.shutdown.token().is_cancelled() {
+                    return;
+                }
+
+                let state = state.inner().clone();
+                tauri::async_runtime::block_on(async move {
+                    info!("应用退出中，等待进行中的分析完成...");
+                    let report = state
+                        .shutdown
+                        .shutdown(shutdown::DEFAULT_DRAIN_TIMEOUT)
+                        .await;
+                    info!(
+                        "分析已排空: 完成 {} 个，重新排队 {} 个",
+                        report.drained,
+                        report.requeued.len()
+                    );
+
+                    // 刷新并关闭数据库连接，确保写入落盘
+                    if let Some(db) = state.storage_domain.try_get_db().await {
+                        // 未完成的分析留在分析队列中，退出打断的不计入分析次数
+                        for session in &report.requeued {
+                            if let Err(e) =
+                                storage::analysis_queue::release(&db, session.session_id).await
+                            {
+                                warn!("更新分析队列失败: session_id={}, {}", session.session_id, e);
+                            }
+                        }
+                        db.close().await;
+                        info!("数据库连接已关闭");
+                    }
+                });
+            }
+        });
 }
 #[derive(Default)]
 struct VideoAnalysisReport {
@@ -2992,6 +3934,36 @@ struct VideoAnalysisOutcome {
     summary: llm::SessionSummary,
 }
 
+/// 为待分析的视频创建临时会话，分析完成后由 analyze_video_once 更新标题与总结
+async fn insert_video_session(
+    db: &storage::Database,
+    video_path: &Path,
+    session_start: chrono::DateTime<chrono::Utc>,
+    session_end: chrono::DateTime<chrono::Utc>,
+) -> Result<i64, String> {
+    let file_stem = video_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("视频");
+    let (device_name, device_type) = storage::get_device_info();
+    let temp_session = storage::Session {
+        id: None,
+        start_time: session_start,
+        end_time: session_end,
+        title: format!("视频分析中: {}", file_stem),
+        summary: "正在分析...".to_string(),
+        video_path: Some(video_path.to_string_lossy().to_string()),
+        tags: "[]".to_string(),
+        created_at: Some(storage::local_now()),
+        device_name: Some(device_name),
+        device_type: Some(device_type),
+        pinned: false,
+    };
+    db.insert_session(&temp_session)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 async fn analyze_video_once(
     state: &AppState,
     video_path: &Path,
@@ -2999,12 +3971,40 @@ async fn analyze_video_once(
     session_end: chrono::DateTime<chrono::Utc>,
     duration_minutes: u32,
     reuse_session: Option<i64>,
+    priority: llm::AnalysisPriority,
 ) -> Result<VideoAnalysisOutcome, String> {
+    // 按优先级申请分析名额，用户触发的分析优先于批量分析
+    let _permit = state
+        .analysis_domain
+        .get_analysis_queue()
+        .acquire(priority)
+        .await;
+
+    let session_id =
+        prepare_video_analysis(state, video_path, session_start, session_end, reuse_session)
+            .await?;
+    run_video_analysis(
+        state,
+        video_path,
+        session_start,
+        session_end,
+        duration_minutes,
+        session_id,
+    )
+    .await
+}
+
+/// 分析视频前的准备：按当前 provider 配置 LLM、准备会话，返回会话 ID
+///
+/// 这一步还没有调用模型，失败时不会留下分析结果
+async fn prepare_video_analysis(
+    state: &AppState,
+    video_path: &Path,
+    session_start: chrono::DateTime<chrono::Utc>,
+    session_end: chrono::DateTime<chrono::Utc>,
+    reuse_session: Option<i64>,
+) -> Result<i64, String> {
     let video_path_str = video_path.to_string_lossy().to_string();
-    let file_stem = video_path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("视频");
 
     let persisted_config = state.storage_domain.get_settings().get().await;
     let llm_handle = state.analysis_domain.get_llm_handle();
@@ -3022,6 +4022,7 @@ async fn analyze_video_once(
                     base_url: llm_config.base_url,
                     use_video_mode: llm_config.use_video_mode,
                     video_path: Some(video_path_str.clone()),
+                    pricing: llm_config.pricing,
                 }
             } else {
                 let config = llm_handle.get_config().await.map_err(|e| e.to_string())?;
@@ -3031,6 +4032,7 @@ async fn analyze_video_once(
                     base_url: config.qwen.base_url.clone(),
                     use_video_mode: true,
                     video_path: Some(video_path_str.clone()),
+                    pricing: config.qwen.pricing.clone(),
                 }
             };
 
@@ -3050,16 +4052,10 @@ async fn analyze_video_once(
         // ✅ 新增以下 Ollama 分支
         "ollama" => {
             let ollama_cfg = if let Some(llm_config) = persisted_config.llm_config {
-                llm::OllamaConfig {
-                    base_url: llm_config.base_url,
-                    model: llm_config.model,
-                }
+                llm::OllamaConfig::from_provider_config(&llm_config)
             } else {
                 let config = llm_handle.get_config().await.map_err(|e| e.to_string())?;
-                llm::OllamaConfig {
-                    base_url: config.ollama.base_url.clone(),
-                    model: config.ollama.model.clone(),
-                }
+                config.ollama.clone()
             };
             
             // 配置 Ollama 并传入视频路径（如果 OllamaProvider 支持 video_path）
@@ -3088,8 +4084,6 @@ async fn analyze_video_once(
         return Err(e.to_string());
     }
 
-    let now = storage::local_now();
-
     // 准备会话
     let db = state.storage_domain.get_db().await?;
     let session_id = if let Some(existing_id) = reuse_session {
@@ -3105,31 +4099,11 @@ async fn analyze_video_once(
 
         existing_id
     } else {
-        let (device_name, device_type) = storage::get_device_info();
-        let temp_session = storage::Session {
-            id: None,
-            start_time: session_start,
-            end_time: session_end,
-            title: format!("视频分析中: {}", file_stem),
-            summary: "正在分析...".to_string(),
-            video_path: Some(video_path_str.clone()),
-            tags: "[]".to_string(),
-            created_at: Some(now),
-            device_name: Some(device_name),
-            device_type: Some(device_type),
-        };
-
-        match state
-            .storage_domain
-            .get_db()
-            .await?
-            .insert_session(&temp_session)
-            .await
-        {
+        match insert_video_session(&db, video_path, session_start, session_end).await {
             Ok(id) => id,
             Err(e) => {
                 let _ = llm_handle.set_video_path(None).await;
-                return Err(e.to_string());
+                return Err(e);
             }
         }
     };
@@ -3154,10 +4128,30 @@ async fn analyze_video_once(
         .await
         .map_err(|e| e.to_string())?;
 
-    let analysis = match llm_handle
+    Ok(session_id)
+}
+
+/// 调用模型分析已准备好的视频，保存分段、时间线卡片与会话总结
+async fn run_video_analysis(
+    state: &AppState,
+    video_path: &Path,
+    session_start: chrono::DateTime<chrono::Utc>,
+    session_end: chrono::DateTime<chrono::Utc>,
+    duration_minutes: u32,
+    session_id: i64,
+) -> Result<VideoAnalysisOutcome, String> {
+    let video_path_str = video_path.to_string_lossy().to_string();
+    let llm_handle = state.analysis_domain.get_llm_handle();
+    let db = state.storage_domain.get_db().await?;
+    let now = storage::local_now();
+
+    // 与帧分析一样记录分析次数、耗时与失败类型（指标导出）
+    let started = std::time::Instant::now();
+    let result = llm_handle
         .segment_video_and_generate_timeline(vec![], duration_minutes, None)
-        .await
-    {
+        .await;
+    telemetry::global().record_analysis(started.elapsed(), &result);
+    let analysis = match result {
         Ok(res) => res,
         Err(e) => {
             let _ = llm_handle.set_video_path(None).await;
@@ -3262,8 +4256,9 @@ async fn analyze_video_once(
         }
     }
 
-    let summary =
+    let mut summary =
         llm::build_session_summary(session_start, session_end, &segments, &timeline_cards);
+    summary.sampling_seed = analysis.sampling_seed;
 
     let tags_json = serde_json::to_string(&summary.tags).unwrap_or_else(|_| "[]".to_string());
     if let Err(e) = db
@@ -3278,6 +4273,54 @@ async fn analyze_video_once(
     {
         return Err(format!("更新会话信息失败: {}", e));
     }
+    let llm_config = llm_handle.get_config().await.map_err(|e| e.to_string())?;
+    if let Err(e) = llm::confidence::save_session_scores(
+        &db,
+        session_id,
+        &mut summary,
+        llm_config.min_confidence,
+    )
+    .await
+    {
+        warn!("保存会话 {} 的评分失败: {}", session_id, e);
+    }
+
+    // 按配置识别深度工作时段：重新分析的会话沿用已保存的帧时间，
+    // 导入的视频没有帧记录，由 refresh_session 按时间线卡片取样
+    let app_config = state.storage_domain.get_settings().get().await;
+    let deep_work = app_config.deep_work.clone().unwrap_or_default();
+    if deep_work.enabled {
+        let frame_times: Vec<chrono::DateTime<chrono::Utc>> =
+            match db.get_frames_by_session(session_id).await {
+                Ok(frames) => frames.iter().map(|frame| frame.timestamp).collect(),
+                Err(e) => {
+                    warn!(
+                        "读取会话 {} 的帧记录失败，按时间线卡片取样: {}",
+                        session_id, e
+                    );
+                    Vec::new()
+                }
+            };
+        if let Err(e) = storage::deep_work::refresh_session(
+            &db,
+            session_id,
+            &frame_times,
+            summary.focus_score,
+            &deep_work,
+        )
+        .await
+        {
+            warn!("识别会话 {} 的深度工作时段失败: {}", session_id, e);
+        }
+    }
+
+    // 后台推送分析完成通知（失败不影响分析）
+    webhook::dispatch(
+        app_config.webhook,
+        &llm_config.redaction,
+        session_id,
+        &summary,
+    );
 
     Ok(VideoAnalysisOutcome {
         _session_id: session_id,
@@ -3287,6 +4330,37 @@ async fn analyze_video_once(
     })
 }
 
+/// 记录自动分析的结果：成功时清除失败记录，失败时累计次数（达到上限后移入死信队列）
+///
+/// 只应传入调用模型之后的结果；视频过短的会话不会再分析，不计入失败
+async fn track_analysis_attempt(
+    db: &storage::Database,
+    session_id: i64,
+    result: &Result<VideoAnalysisOutcome, String>,
+) {
+    use storage::dead_letter;
+
+    match result {
+        Ok(_) => {
+            if let Err(e) = dead_letter::record_success(db, session_id).await {
+                warn!("清除会话 {} 的失败记录失败: {}", session_id, e);
+            }
+        }
+        Err(err) if err.contains("VIDEO_TOO_SHORT") => {}
+        Err(err) => {
+            let max_attempts = dead_letter::DEFAULT_MAX_ATTEMPTS;
+            match dead_letter::record_failure(db, session_id, err, max_attempts).await {
+                Ok(record) if record.dead_lettered_at.is_some() => warn!(
+                    "会话 {} 已连续分析失败 {} 次，移入死信队列，不再自动重试",
+                    session_id, record.attempts
+                ),
+                Ok(_) => {}
+                Err(e) => error!("记录会话 {} 的分析失败失败: {}", session_id, e),
+            }
+        }
+    }
+}
+
 async fn analyze_unprocessed_videos(
     state: &AppState,
     limit: Option<usize>,
@@ -3338,7 +4412,27 @@ async fn analyze_unprocessed_videos(
 
     unanalyzed_videos.sort();
 
-    let total_candidates = unanalyzed_videos.len();
+    // 之前分析失败、尚未移入死信队列的会话排在新视频之后，复用原会话重试
+    let db = state.storage_domain.get_db().await?;
+    let mut jobs: Vec<(PathBuf, Option<i64>)> = unanalyzed_videos
+        .into_iter()
+        .map(|path| (path, None))
+        .collect();
+    let retries = storage::dead_letter::retryable(&db)
+        .await
+        .map_err(|e| e.to_string())?;
+    for record in retries {
+        match db.get_session(record.session_id).await {
+            Ok(session) => {
+                if let Some(path) = session.video_path {
+                    jobs.push((PathBuf::from(path), Some(record.session_id)));
+                }
+            }
+            Err(e) => warn!("读取待重试的会话 {} 失败: {}", record.session_id, e),
+        }
+    }
+
+    let total_candidates = jobs.len();
     if total_candidates == 0 {
         return Ok(VideoAnalysisReport::default());
     }
@@ -3377,7 +4471,7 @@ async fn analyze_unprocessed_videos(
 
     let mut processing_error: Option<String> = None;
 
-    for (index, video_path) in unanalyzed_videos.iter().enumerate() {
+    for (index, (video_path, retry_session)) in jobs.iter().enumerate() {
         if index >= total_to_process {
             break;
         }
@@ -3402,16 +4496,48 @@ async fn analyze_unprocessed_videos(
             1
         };
 
-        match analyze_video_once(
-            state,
-            video_path,
-            session_start,
-            session_end,
-            duration_minutes,
-            None,
-        )
-        .await
-        {
+        let result = if !video_path.exists() {
+            let result = Err(format!("视频文件不存在: {}", video_path.display()));
+            // 待重试会话的视频已被删除时也计入失败，避免每轮都卡在这个会话上
+            if let Some(session_id) = retry_session {
+                track_analysis_attempt(&db, *session_id, &result).await;
+            }
+            result
+        } else {
+            let _permit = state
+                .analysis_domain
+                .get_analysis_queue()
+                .acquire(llm::AnalysisPriority::Background)
+                .await;
+
+            // 先配置 provider 再创建会话；配置缺失等调用模型之前的失败不计入重试次数
+            match prepare_video_analysis(
+                state,
+                video_path,
+                session_start,
+                session_end,
+                *retry_session,
+            )
+            .await
+            {
+                Ok(session_id) => {
+                    let result = run_video_analysis(
+                        state,
+                        video_path,
+                        session_start,
+                        session_end,
+                        duration_minutes,
+                        session_id,
+                    )
+                    .await;
+                    track_analysis_attempt(&db, session_id, &result).await;
+                    result
+                }
+                Err(e) => Err(e),
+            }
+        };
+
+        match result {
             Ok(outcome) => {
                 info!(
                     "视频分析成功: {} 个片段, {} 个卡片",
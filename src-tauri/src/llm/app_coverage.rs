@@ -0,0 +1,191 @@
+// 按前台应用覆盖采样 - 保证每个用过的应用至少有一帧进入采样
+//
+// 等间隔采样会漏掉短暂的应用切换（如中途看了一眼 Slack），总结里便不会提到这个应用。
+// 采集层提供前台应用记录时，先为每个应用（可按停留时长设下限）选一帧代表帧，
+// 再用剩余的帧预算等间隔补齐；应用数超过预算时按停留时长保留用得最多的应用
+
+use super::budget::frame_timestamp_ms;
+use super::plugin::ForegroundApp;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// 按前台应用覆盖采样的配置
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppCoverageConfig {
+    /// 停留时长（秒）不少于该值的应用才保证有一帧；0 表示每个出现过的应用都保证
+    pub min_app_secs: u64,
+}
+
+/// 在 budget 帧以内采样并保证应用覆盖，结果保持时间顺序
+///
+/// 帧文件名无法解析时间或没有前台应用记录时返回 None，由调用方回退为普通采样
+pub fn sample_with_app_coverage(
+    frames: &[String],
+    apps: &[ForegroundApp],
+    budget: usize,
+    config: &AppCoverageConfig,
+) -> Option<Vec<String>> {
+    if frames.len() <= budget {
+        return Some(frames.to_vec());
+    }
+    let mut apps: Vec<&ForegroundApp> = apps.iter().collect();
+    apps.sort_by_key(|app| app.timestamp);
+    let first = apps.first()?;
+    let times: Vec<i64> = frames
+        .iter()
+        .map(|frame| frame_timestamp_ms(frame))
+        .collect::<Option<_>>()?;
+
+    // 每帧所在的应用：取该帧之前最近一次记录的应用（首次记录之前的帧归入首个应用）
+    let frame_apps: Vec<&str> = times
+        .iter()
+        .map(|&ms| {
+            apps.iter()
+                .take_while(|app| app.timestamp.timestamp_millis() <= ms)
+                .last()
+                .unwrap_or(first)
+                .app
+                .as_str()
+        })
+        .collect();
+
+    // 各应用的停留时长：每段记录持续到下一次记录（最后一段持续到最后一帧）
+    let end_ms = times.iter().copied().max().unwrap_or_default();
+    let mut durations: HashMap<&str, i64> = HashMap::new();
+    for (index, app) in apps.iter().enumerate() {
+        let start = app.timestamp.timestamp_millis();
+        let end = apps
+            .get(index + 1)
+            .map_or(end_ms, |next| next.timestamp.timestamp_millis());
+        *durations.entry(app.app.as_str()).or_default() += (end - start).max(0);
+    }
+
+    // 每个应用的帧序号
+    let mut app_frames: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, app) in frame_apps.iter().copied().enumerate() {
+        app_frames.entry(app).or_default().push(index);
+    }
+
+    // 保证覆盖的应用按停留时长降序，超出预算时舍弃用得最少的应用
+    let min_ms = config.min_app_secs as i64 * 1000;
+    let mut covered: Vec<(&str, i64)> = app_frames
+        .keys()
+        .map(|app| (*app, durations.get(app).copied().unwrap_or_default()))
+        .filter(|(_, duration)| *duration >= min_ms)
+        .collect();
+    covered.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    covered.truncate(budget);
+
+    // 每个应用取其帧的中间一帧作为代表
+    let mut selected: BTreeSet<usize> = covered
+        .iter()
+        .map(|(app, _)| {
+            let indices = &app_frames[app];
+            indices[indices.len() / 2]
+        })
+        .collect();
+
+    // 剩余预算等间隔补齐：按目标位置取最近的未选帧
+    let remaining = budget - selected.len();
+    for slot in 0..remaining {
+        let target = (2 * slot + 1) * frames.len() / (2 * remaining);
+        let nearest = (0..frames.len())
+            .filter(|index| !selected.contains(index))
+            .min_by_key(|index| index.abs_diff(target));
+        if let Some(index) = nearest {
+            selected.insert(index);
+        }
+    }
+
+    Some(
+        selected
+            .into_iter()
+            .map(|index| frames[index].clone())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    const START_MS: i64 = 1_700_000_000_000;
+
+    /// 每 2 秒一帧
+    fn frames(count: usize) -> Vec<String> {
+        (0..count)
+            .map(|i| format!("/frames/{}.jpg", START_MS + i as i64 * 2000))
+            .collect()
+    }
+
+    fn app(offset_secs: i64, name: &str) -> ForegroundApp {
+        ForegroundApp {
+            timestamp: Utc
+                .timestamp_millis_opt(START_MS + offset_secs * 1000)
+                .unwrap(),
+            app: name.to_string(),
+        }
+    }
+
+    fn apps_of(sampled: &[String], apps: &[ForegroundApp]) -> Vec<String> {
+        sampled
+            .iter()
+            .map(|frame| {
+                let ms = frame_timestamp_ms(frame).unwrap();
+                apps.iter()
+                    .rev()
+                    .find(|app| app.timestamp.timestamp_millis() <= ms)
+                    .unwrap()
+                    .app
+                    .clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_brief_slack_visit_keeps_a_slack_frame() {
+        // 10 分钟的会话，中途切到 Slack 6 秒（3 帧）
+        let frames = frames(300);
+        let apps = vec![app(0, "VS Code"), app(250, "Slack"), app(256, "VS Code")];
+        let config = AppCoverageConfig::default();
+
+        let sampled = sample_with_app_coverage(&frames, &apps, 10, &config).unwrap();
+        assert_eq!(sampled.len(), 10);
+        assert!(apps_of(&sampled, &apps).contains(&"Slack".to_string()));
+        // 保持时间顺序、不重复
+        let times: Vec<i64> = sampled
+            .iter()
+            .filter_map(|f| frame_timestamp_ms(f))
+            .collect();
+        assert!(times.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // 停留时长低于下限的应用不保证覆盖
+        let config = AppCoverageConfig { min_app_secs: 30 };
+        let sampled = sample_with_app_coverage(&frames, &apps, 10, &config).unwrap();
+        assert!(!apps_of(&sampled, &apps).contains(&"Slack".to_string()));
+
+        // 没有应用记录或帧数在预算内时不做处理
+        assert!(sample_with_app_coverage(&frames, &[], 10, &config).is_none());
+        assert_eq!(
+            sample_with_app_coverage(&frames[..5], &apps, 10, &config).unwrap(),
+            frames[..5].to_vec()
+        );
+    }
+
+    #[test]
+    fn test_budget_drops_least_used_apps() {
+        let frames = frames(100);
+        let apps = vec![
+            app(0, "VS Code"),
+            app(100, "Slack"),
+            app(104, "Mail"),
+            app(110, "Chrome"),
+        ];
+        let sampled =
+            sample_with_app_coverage(&frames, &apps, 2, &AppCoverageConfig::default()).unwrap();
+        // VS Code（100 秒）与 Chrome（88 秒）保留，Slack 与 Mail 停留最短被舍弃
+        assert_eq!(apps_of(&sampled, &apps), vec!["VS Code", "Chrome"]);
+    }
+}
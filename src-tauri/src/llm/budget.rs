@@ -0,0 +1,304 @@
+// 请求预算 - 发送前按 provider 的真实限制（图片数、上下文长度）规划帧
+//
+// 各 provider 原先各自假定“采样到 30 帧”，上下文较小的模型仍会超限。
+// 规划按 capabilities() 进行：先在图片数上限内均匀采样；token 超出时缩小图片；
+// 缩到下限后单次请求仍放不下足够的帧时，改为分块分析再合并（map-reduce）。
+// token 为按单图固定开销的粗略估算，只用于规划，不替代 provider 自身的请求体裁剪
+
+use super::plugin::{LLMProvider, ProviderCapabilities};
+use crate::storage::Frame;
+use anyhow::{anyhow, Result};
+use chrono::{TimeZone, Utc};
+use tracing::warn;
+
+/// 提示词、上下文与输出预留的 token 数
+pub const RESERVED_TOKENS: usize = 4096;
+
+/// 缩小图片时边长的最小比例
+pub const MIN_IMAGE_SCALE: f32 = 0.5;
+
+/// 单次请求放不下这么多帧时改为分块分析
+pub const MIN_FRAMES_PER_REQUEST: usize = 8;
+
+/// 分块分析的最大块数（每块一次请求，另加一次合并请求）
+pub const MAX_MAP_CHUNKS: usize = 4;
+
+/// 缩小后重新编码使用的 JPEG 质量
+const SCALED_JPEG_QUALITY: u8 = 80;
+
+/// 发送方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetStrategy {
+    /// 一次请求发送全部帧
+    Single,
+    /// 按 chunk_frames 帧分块分析，再把各块总结合并为一份
+    MapReduce { chunk_frames: usize },
+}
+
+/// 预算规划结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetPlan {
+    /// 采样后发送的帧（时间顺序）
+    pub frames: Vec<String>,
+    /// 图片边长的缩放比例（1.0 表示原图）
+    pub image_scale: f32,
+    /// 单次请求估算的输入 token 数
+    pub estimated_tokens: usize,
+    pub strategy: BudgetStrategy,
+}
+
+impl BudgetPlan {
+    /// 相对输入是否做了调整（减少帧数、缩小图片或分块）
+    pub fn is_reduced(&self, input_frames: usize) -> bool {
+        self.frames.len() < input_frames
+            || self.image_scale < 1.0
+            || self.strategy != BudgetStrategy::Single
+    }
+
+    /// 单次请求发送的帧数
+    pub fn frames_per_request(&self) -> usize {
+        match self.strategy {
+            BudgetStrategy::Single => self.frames.len(),
+            BudgetStrategy::MapReduce { chunk_frames } => chunk_frames,
+        }
+    }
+}
+
+/// 按 provider 当前的能力（含已刷新的服务端报告）规划发送的帧
+pub fn fit_to_budget(frames: &[String], provider: &dyn LLMProvider) -> Result<BudgetPlan> {
+    fit_to_capabilities(frames, &provider.capabilities())
+}
+
+/// 按给定的能力规划发送的帧；上下文连一张缩小后的图片都放不下时返回错误
+pub fn fit_to_capabilities(
+    frames: &[String],
+    capabilities: &ProviderCapabilities,
+) -> Result<BudgetPlan> {
+    let max_images = capabilities.max_images.max(1);
+    let per_image = capabilities.tokens_per_image.max(1);
+    let available = capabilities
+        .max_input_tokens
+        .saturating_sub(RESERVED_TOKENS);
+    let min_per_image = scaled_tokens(per_image, MIN_IMAGE_SCALE);
+    let fits_scaled = available / min_per_image;
+    if fits_scaled == 0 {
+        return Err(anyhow!(
+            "模型上下文 {} tokens 放不下一张缩小后的图片（约 {} tokens）",
+            capabilities.max_input_tokens,
+            min_per_image
+        ));
+    }
+
+    let plan = |count: usize, scale: f32, strategy: BudgetStrategy| {
+        let mut plan = BudgetPlan {
+            frames: sample_evenly(frames, count),
+            image_scale: scale,
+            estimated_tokens: 0,
+            strategy,
+        };
+        plan.estimated_tokens =
+            RESERVED_TOKENS + plan.frames_per_request() * scaled_tokens(per_image, scale);
+        plan
+    };
+
+    // 1. 原图放得下：只按图片数上限采样
+    let wanted = frames.len().min(max_images);
+    if wanted * per_image <= available {
+        return Ok(plan(wanted, 1.0, BudgetStrategy::Single));
+    }
+
+    // 2. 缩小图片（token 数与面积成正比）使全部采样帧放得下
+    let scale = (available as f32 / (wanted * per_image) as f32).sqrt();
+    if scale >= MIN_IMAGE_SCALE {
+        return Ok(plan(wanted, scale, BudgetStrategy::Single));
+    }
+
+    // 3. 缩到下限后单次仍能放下足够多的帧：减少帧数
+    let per_request = fits_scaled.min(wanted);
+    if per_request >= MIN_FRAMES_PER_REQUEST {
+        return Ok(plan(per_request, MIN_IMAGE_SCALE, BudgetStrategy::Single));
+    }
+
+    // 4. 单次放下的帧太少：分块分析，总帧数不超过图片数上限
+    let total = wanted.min(per_request * MAX_MAP_CHUNKS);
+    let strategy = if total > per_request {
+        BudgetStrategy::MapReduce {
+            chunk_frames: per_request,
+        }
+    } else {
+        BudgetStrategy::Single
+    };
+    Ok(plan(total, MIN_IMAGE_SCALE, strategy))
+}
+
+/// 缩放后单张图片的 token 数
+fn scaled_tokens(per_image: usize, scale: f32) -> usize {
+    ((per_image as f32 * scale * scale) as usize).max(1)
+}
+
+/// 均匀取 count 帧，保留首帧与末帧
+fn sample_evenly(frames: &[String], count: usize) -> Vec<String> {
+    let len = frames.len();
+    if count >= len {
+        return frames.to_vec();
+    }
+    match count {
+        0 => Vec::new(),
+        1 => vec![frames[0].clone()],
+        _ => (0..count)
+            .map(|i| frames[i * (len - 1) / (count - 1)].clone())
+            .collect(),
+    }
+}
+
+/// 帧文件名（不含扩展名），归档内的帧取 `#` 之后的部分
+fn frame_stem(path: &str) -> Option<&str> {
+    let name = path.rsplit(['/', '\\', '#']).next()?;
+    std::path::Path::new(name).file_stem()?.to_str()
+}
+
+/// 帧文件名中的毫秒时间戳（零散文件与归档内的帧均可）
+pub fn frame_timestamp_ms(path: &str) -> Option<i64> {
+    frame_stem(path)?.parse().ok()
+}
+
+/// 由帧路径构造带时间的帧：帧文件名为毫秒时间戳，无法解析的帧跳过
+pub fn timed_frames(paths: &[String]) -> Vec<Frame> {
+    paths
+        .iter()
+        .filter_map(|path| {
+            let ms = frame_timestamp_ms(path)?;
+            Some(Frame {
+                id: None,
+                session_id: 0,
+                timestamp: Utc.timestamp_millis_opt(ms).single()?,
+                file_path: path.clone(),
+            })
+        })
+        .collect()
+}
+
+/// 缩小后的帧副本，保存在临时目录中，释放时一并删除
+pub struct ScaledFrames {
+    _dir: tempfile::TempDir,
+    pub paths: Vec<String>,
+}
+
+impl ScaledFrames {
+    /// 按比例缩小并写入临时目录；文件名保持不变（帧文件名为时间戳），单帧失败时沿用原图
+    pub async fn create(frames: &[String], scale: f32) -> Result<Self> {
+        let dir = tempfile::tempdir()?;
+        let mut paths = Vec::with_capacity(frames.len());
+        for (index, frame) in frames.iter().enumerate() {
+            let name = format!(
+                "{}.jpg",
+                frame_stem(frame).map_or_else(|| index.to_string(), str::to_string)
+            );
+            let target = dir.path().join(name);
+            match scale_frame(frame, scale, target.clone()).await {
+                Ok(()) => paths.push(target.to_string_lossy().into_owned()),
+                Err(e) => {
+                    warn!("缩小帧失败，使用原图 path={} err={}", frame, e);
+                    paths.push(frame.clone());
+                }
+            }
+        }
+        Ok(Self { _dir: dir, paths })
+    }
+}
+
+async fn scale_frame(frame: &str, scale: f32, target: std::path::PathBuf) -> Result<()> {
+    let bytes = crate::storage::frame_pack::read_frame(frame).await?;
+    tokio::task::spawn_blocking(move || write_scaled(&bytes, scale, &target)).await?
+}
+
+fn write_scaled(bytes: &[u8], scale: f32, target: &std::path::Path) -> Result<()> {
+    use image::codecs::jpeg::JpegEncoder;
+
+    let image = image::load_from_memory(bytes)?;
+    let width = ((image.width() as f32 * scale).round() as u32).max(1);
+    let height = ((image.height() as f32 * scale).round() as u32).max(1);
+    let resized = image
+        .resize(width, height, image::imageops::FilterType::Triangle)
+        .to_rgb8();
+    let mut buf = Vec::new();
+    JpegEncoder::new_with_quality(&mut buf, SCALED_JPEG_QUALITY).encode(
+        resized.as_raw(),
+        resized.width(),
+        resized.height(),
+        image::ColorType::Rgb8,
+    )?;
+    std::fs::write(target, buf)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames(count: usize) -> Vec<String> {
+        (0..count)
+            .map(|i| format!("/frames/{}.jpg", 1_700_000_000_000i64 + i as i64 * 2000))
+            .collect()
+    }
+
+    fn capabilities(max_input_tokens: usize) -> ProviderCapabilities {
+        ProviderCapabilities {
+            max_input_tokens,
+            max_images: 30,
+            tokens_per_image: 1000,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_small_context_triggers_aggressive_reduction() {
+        let input = frames(450);
+
+        // 大上下文：只按图片数上限采样，不缩小、不分块
+        let plan = fit_to_capabilities(&input, &capabilities(128_000)).unwrap();
+        assert_eq!(plan.strategy, BudgetStrategy::Single);
+        assert_eq!(plan.image_scale, 1.0);
+        assert_eq!(plan.frames.len(), 30);
+        assert_eq!(plan.frames.first(), input.first());
+        assert_eq!(plan.frames.last(), input.last());
+        assert!(plan.estimated_tokens <= 128_000);
+        // 帧数在上限内时原样发送
+        let short = fit_to_capabilities(&input[..10], &capabilities(128_000)).unwrap();
+        assert!(!short.is_reduced(10));
+
+        // 中等上下文：缩小图片后仍一次发送全部采样帧
+        let plan = fit_to_capabilities(&input, &capabilities(24_000)).unwrap();
+        assert_eq!(plan.strategy, BudgetStrategy::Single);
+        assert_eq!(plan.frames.len(), 30);
+        assert!(plan.image_scale < 1.0 && plan.image_scale >= MIN_IMAGE_SCALE);
+        assert!(plan.estimated_tokens <= 24_000);
+
+        // 小上下文：缩到下限并减少帧数
+        let plan = fit_to_capabilities(&input, &capabilities(8_192)).unwrap();
+        assert_eq!(plan.strategy, BudgetStrategy::Single);
+        assert_eq!(plan.image_scale, MIN_IMAGE_SCALE);
+        assert_eq!(plan.frames.len(), 16);
+        assert!(plan.estimated_tokens <= 8_192);
+
+        // 极小上下文：单次放不下足够的帧，改为分块分析
+        let plan = fit_to_capabilities(&input, &capabilities(6_000)).unwrap();
+        assert_eq!(plan.strategy, BudgetStrategy::MapReduce { chunk_frames: 7 });
+        assert_eq!(plan.frames.len(), 28);
+        assert!(plan.estimated_tokens <= 6_000);
+
+        assert!(fit_to_capabilities(&input, &capabilities(4_096)).is_err());
+    }
+
+    #[test]
+    fn test_timed_frames_parse_packed_and_plain_paths() {
+        let paths = vec![
+            "/frames/1700000000000.jpg".to_string(),
+            "/frames/packs/a.pack#1700000002000.jpg".to_string(),
+            "/frames/cover.jpg".to_string(),
+        ];
+        let frames = timed_frames(&paths);
+        assert_eq!(frames.len(), 2);
+        assert_eq!((frames[1].timestamp - frames[0].timestamp).num_seconds(), 2);
+    }
+}
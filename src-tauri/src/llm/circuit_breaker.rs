@@ -0,0 +1,233 @@
+// Provider 熔断器 - 连续失败后暂停调用，冷却结束后放行探测请求
+//
+// 失败按 provider 的最终结果计数（RetryLayer 的重试在 provider 内部完成），
+// 熔断期间直接返回 CircuitOpen，调用方可立即改用其他 provider 而不必等待超时
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// 熔断配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CircuitBreakerConfig {
+    /// 窗口内连续失败多少次后熔断，0 表示不启用
+    pub failure_threshold: u32,
+    /// 统计连续失败的时间窗口（秒），早于窗口的失败不再计数
+    pub window_secs: u64,
+    /// 熔断后的冷却时间（秒），结束后放行探测请求
+    pub cooldown_secs: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            window_secs: 300,
+            cooldown_secs: 60,
+        }
+    }
+}
+
+/// 熔断状态（状态接口中展示）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// 正常放行
+    Closed,
+    /// 熔断中，调用直接失败
+    Open,
+    /// 冷却结束，下一次调用作为探测
+    HalfOpen,
+}
+
+#[derive(Debug, Clone)]
+enum BreakerState {
+    Closed { failures: VecDeque<Instant> },
+    Open { until: Instant },
+    HalfOpen,
+}
+
+/// 单个 provider 的熔断器，时间由调用方传入便于测试
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    state: BreakerState,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            state: BreakerState::Closed {
+                failures: VecDeque::new(),
+            },
+        }
+    }
+}
+
+impl CircuitBreaker {
+    /// 调用前检查：熔断中返回剩余冷却时间；冷却结束时转为半开并放行本次调用
+    pub fn try_acquire(
+        &mut self,
+        config: &CircuitBreakerConfig,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        if config.failure_threshold == 0 {
+            return Ok(());
+        }
+        if let BreakerState::Open { until } = self.state {
+            if now < until {
+                return Err(until - now);
+            }
+            self.state = BreakerState::HalfOpen;
+        }
+        Ok(())
+    }
+
+    /// 记录一次调用结果
+    pub fn record(&mut self, config: &CircuitBreakerConfig, success: bool, now: Instant) {
+        if config.failure_threshold == 0 || success {
+            *self = Self::default();
+            return;
+        }
+        let window = Duration::from_secs(config.window_secs);
+        let cooldown = Duration::from_secs(config.cooldown_secs);
+        match &mut self.state {
+            BreakerState::Closed { failures } => {
+                failures.push_back(now);
+                while failures
+                    .front()
+                    .is_some_and(|at| now.saturating_duration_since(*at) > window)
+                {
+                    failures.pop_front();
+                }
+                if failures.len() >= config.failure_threshold as usize {
+                    self.state = BreakerState::Open {
+                        until: now + cooldown,
+                    };
+                }
+            }
+            // 探测失败，重新进入冷却
+            BreakerState::HalfOpen => {
+                self.state = BreakerState::Open {
+                    until: now + cooldown,
+                }
+            }
+            // 熔断前已放行的调用，结果不影响冷却
+            BreakerState::Open { .. } => {}
+        }
+    }
+
+    /// 当前状态；冷却已结束但尚未有调用时视为半开
+    pub fn state(&self, now: Instant) -> CircuitState {
+        match self.state {
+            BreakerState::Closed { .. } => CircuitState::Closed,
+            BreakerState::Open { until } if now < until => CircuitState::Open,
+            BreakerState::Open { .. } | BreakerState::HalfOpen => CircuitState::HalfOpen,
+        }
+    }
+
+    /// 熔断中的剩余冷却时间
+    pub fn retry_after(&self, now: Instant) -> Option<Duration> {
+        match self.state {
+            BreakerState::Open { until } if now < until => Some(until - now),
+            _ => None,
+        }
+    }
+}
+
+/// provider 处于熔断状态，未发起调用
+///
+/// 调用方可通过 `downcast_ref::<CircuitOpen>()` 识别并改用其他 provider
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircuitOpen {
+    pub provider: String,
+    /// 剩余冷却时间（秒，向上取整）
+    pub retry_after_secs: u64,
+}
+
+impl std::fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} 连续调用失败，已暂停调用（{} 秒后重试）",
+            self.provider, self.retry_after_secs
+        )
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CircuitBreakerConfig {
+        CircuitBreakerConfig {
+            failure_threshold: 3,
+            window_secs: 60,
+            cooldown_secs: 30,
+        }
+    }
+
+    #[test]
+    fn test_opens_after_consecutive_failures_and_recovers_through_half_open() {
+        let config = config();
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut breaker = CircuitBreaker::default();
+
+        // 中间的成功会清零连续失败计数
+        breaker.record(&config, false, at(0));
+        breaker.record(&config, false, at(1));
+        breaker.record(&config, true, at(2));
+        breaker.record(&config, false, at(3));
+        breaker.record(&config, false, at(4));
+        assert_eq!(breaker.state(at(4)), CircuitState::Closed);
+
+        breaker.record(&config, false, at(5));
+        assert_eq!(breaker.state(at(5)), CircuitState::Open);
+        assert_eq!(
+            breaker.try_acquire(&config, at(15)),
+            Err(Duration::from_secs(20))
+        );
+        assert_eq!(breaker.retry_after(at(15)), Some(Duration::from_secs(20)));
+
+        // 冷却结束后放行探测，探测失败重新熔断
+        assert_eq!(breaker.state(at(35)), CircuitState::HalfOpen);
+        assert_eq!(breaker.try_acquire(&config, at(35)), Ok(()));
+        breaker.record(&config, false, at(36));
+        assert_eq!(breaker.state(at(36)), CircuitState::Open);
+        assert!(breaker.try_acquire(&config, at(60)).is_err());
+
+        // 探测成功后恢复正常
+        assert_eq!(breaker.try_acquire(&config, at(66)), Ok(()));
+        breaker.record(&config, true, at(67));
+        assert_eq!(breaker.state(at(67)), CircuitState::Closed);
+        assert_eq!(breaker.retry_after(at(67)), None);
+    }
+
+    #[test]
+    fn test_failures_outside_window_do_not_count() {
+        let config = config();
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut breaker = CircuitBreaker::default();
+
+        breaker.record(&config, false, at(0));
+        breaker.record(&config, false, at(50));
+        // 第一次失败已超出 60 秒窗口
+        breaker.record(&config, false, at(100));
+        assert_eq!(breaker.state(at(100)), CircuitState::Closed);
+        breaker.record(&config, false, at(101));
+        assert_eq!(breaker.state(at(101)), CircuitState::Open);
+
+        // 阈值为 0 时不启用熔断
+        let disabled = CircuitBreakerConfig {
+            failure_threshold: 0,
+            ..config
+        };
+        assert_eq!(breaker.try_acquire(&disabled, at(102)), Ok(()));
+        breaker.record(&disabled, false, at(103));
+        assert_eq!(breaker.state(at(103)), CircuitState::Closed);
+    }
+}
@@ -22,6 +22,21 @@ use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use tracing::{debug, error, info, warn};
 
+/// 单次分析最多发送的帧数
+const MAX_FRAMES: usize = 30;
+
+/// 会话总结提示词（帧分析与纯文本分析共用）
+const SUMMARY_PROMPT: &str = r#"Analyze these screenshots and summarize the activity.
+Return JSON:
+{
+  "title": "Activity title (in Chinese)",
+  "summary": "Detailed description (in Chinese)",
+  "productivity_score": 75,
+  "focus_score": 80
+}
+
+Return ONLY the JSON object."#;
+
 /// 读取 Claude CLI 的会话令牌（Windows 平台）
 #[cfg(target_os = "windows")]
 pub(crate) fn read_claude_cli_session_token() -> Option<String> {
@@ -118,6 +133,8 @@ pub struct ClaudeProvider {
     /// 当前分析的绝对时间窗口（UTC）
     session_window_start: Option<DateTime<Utc>>,
     session_window_end: Option<DateTime<Utc>>,
+    /// 计费单价（用于费用估算）
+    pricing: ProviderPricing,
 }
 
 impl ClaudeProvider {
@@ -133,6 +150,13 @@ impl ClaudeProvider {
             session_video_path: None,
             session_window_start: None,
             session_window_end: None,
+            pricing: ProviderPricing {
+                currency: "USD".to_string(),
+                per_image: 0.0,
+                per_1k_input_tokens: 0.003,
+                tokens_per_image: 1600,
+                prompt_tokens: 2000,
+            },
         }
     }
 
@@ -180,7 +204,7 @@ impl ClaudeProvider {
 
     /// 将图片文件转换为 base64
     async fn image_to_base64(&self, path: &str) -> Result<String> {
-        let image_data = tokio::fs::read(path).await?;
+        let image_data = crate::storage::frame_pack::read_frame(path).await?;
         Ok(general_purpose::STANDARD.encode(&image_data))
     }
 
@@ -344,6 +368,54 @@ impl ClaudeProvider {
         }
     }
 
+    /// 发送会话总结请求并解析为 SessionSummary（帧分析与纯文本分析共用）
+    async fn request_session_summary(
+        &self,
+        user_content: Vec<Value>,
+        call_type: &str,
+    ) -> Result<SessionSummary> {
+        let system_prompt = "You are analyzing computer screen activity.".to_string();
+
+        let response = self
+            .call_claude_api_with_retry(system_prompt, user_content, call_type)
+            .await?;
+
+        let json_value = match Self::extract_json(&response) {
+            Ok(value) => value,
+            Err(err) => {
+                info!("Claude {} 原始响应: {}", call_type, response);
+                return Err(err);
+            }
+        };
+
+        let now = crate::storage::local_now();
+        Ok(SessionSummary {
+            title: json_value["title"]
+                .as_str()
+                .unwrap_or("未命名会话")
+                .to_string(),
+            summary: json_value["summary"].as_str().unwrap_or("").to_string(),
+            tags: vec![],
+            start_time: now - chrono::Duration::minutes(15),
+            end_time: now,
+            key_moments: vec![],
+            productivity_score: json_value["productivity_score"].as_f64().map(|v| v as f32),
+            focus_score: json_value["focus_score"].as_f64().map(|v| v as f32),
+            sampling_seed: None,
+            temperature: None,
+            language: None,
+            summary_auto_derived: false,
+            redacted: false,
+            idle: false,
+            recovered_stream: false,
+            low_confidence: false,
+            frame_selection: None,
+            reasoning: None,
+            served_by: None,
+            extra: HashMap::new(),
+        })
+    }
+
     /// 调用 Claude Agent SDK（带重试机制）
     async fn call_claude_api_with_retry(
         &self,
@@ -442,6 +514,8 @@ impl ClaudeProvider {
             }
         }
 
+        crate::telemetry::global().add_frames_encoded(image_count);
+
         let request_body_bytes = serde_json::to_string(&user_content)
             .map(|s| s.len())
             .unwrap_or(0);
@@ -1302,11 +1376,16 @@ impl LLMProvider for ClaudeProvider {
     async fn analyze_frames(&self, frames: Vec<String>) -> Result<SessionSummary> {
         info!("Claude 开始分析 {} 帧图像", frames.len());
 
-        // 采样帧（最多 30 张）
-        let sampled_frames = if frames.len() > 30 {
-            let step = (frames.len() as f32 / 30.0).ceil() as usize;
+        // 采样帧（最多 MAX_FRAMES 张）
+        let sampled_frames = if frames.len() > MAX_FRAMES {
+            let step = (frames.len() as f32 / MAX_FRAMES as f32).ceil() as usize;
             let step = step.max(1);
-            frames.iter().step_by(step).take(30).cloned().collect()
+            frames
+                .iter()
+                .step_by(step)
+                .take(MAX_FRAMES)
+                .cloned()
+                .collect()
         } else {
             frames
         };
@@ -1332,46 +1411,27 @@ impl LLMProvider for ClaudeProvider {
         // 添加文本提示
         user_content.push(json!({
             "type": "text",
-            "text": r#"Analyze these screenshots and summarize the activity.
-Return JSON:
-{
-  "title": "Activity title (in Chinese)",
-  "summary": "Detailed description (in Chinese)",
-  "productivity_score": 75,
-  "focus_score": 80
-}
-
-Return ONLY the JSON object."#
+            "text": SUMMARY_PROMPT
         }));
 
-        let system_prompt = "You are analyzing computer screen activity.".to_string();
+        self.request_session_summary(user_content, "analyze_frames")
+            .await
+    }
 
-        let response = self
-            .call_claude_api_with_retry(system_prompt, user_content, "analyze_frames")
-            .await?;
+    async fn analyze_text(&self, content: String) -> Result<SessionSummary> {
+        info!(
+            "Claude 开始分析纯文本会话 ({} 字符)",
+            content.chars().count()
+        );
 
-        let json_value = match Self::extract_json(&response) {
-            Ok(value) => value,
-            Err(err) => {
-                info!("Claude analyze_frames 原始响应: {}", response);
-                return Err(err);
-            }
-        };
+        let prompt = build_text_session_prompt(SUMMARY_PROMPT, &content, Default::default())?;
+        let user_content = vec![json!({
+            "type": "text",
+            "text": prompt
+        })];
 
-        let now = crate::storage::local_now();
-        Ok(SessionSummary {
-            title: json_value["title"]
-                .as_str()
-                .unwrap_or("未命名会话")
-                .to_string(),
-            summary: json_value["summary"].as_str().unwrap_or("").to_string(),
-            tags: vec![],
-            start_time: now - chrono::Duration::minutes(15),
-            end_time: now,
-            key_moments: vec![],
-            productivity_score: json_value["productivity_score"].as_f64().map(|v| v as f32),
-            focus_score: json_value["focus_score"].as_f64().map(|v| v as f32),
-        })
+        self.request_session_summary(user_content, "analyze_text")
+            .await
     }
 
     async fn segment_video(&self, frames: Vec<String>, duration: u32) -> Result<Vec<VideoSegment>> {
@@ -1391,11 +1451,15 @@ Return ONLY the JSON object."#
             );
         }
 
-        // 优先从视频文件提取帧（最多30帧）
+        // 优先从视频文件提取帧（最多 MAX_FRAMES 帧）
         let frames_to_use = if let Some(ref video_path) = self.session_video_path {
             info!("从视频文件提取帧: {}", video_path);
             match self
-                .extract_frames_from_video(video_path, 30, Some(duration.saturating_mul(60)))
+                .extract_frames_from_video(
+                    video_path,
+                    MAX_FRAMES,
+                    Some(duration.saturating_mul(60)),
+                )
                 .await
             {
                 Ok(extracted_frames) => {
@@ -1424,14 +1488,14 @@ Return ONLY the JSON object."#
             ));
         }
 
-        // 采样帧（最多 30 张）
-        let sampled_frames = if frames_to_use.len() > 30 {
-            let step = (frames_to_use.len() as f32 / 30.0).ceil() as usize;
+        // 采样帧（最多 MAX_FRAMES 张）
+        let sampled_frames = if frames_to_use.len() > MAX_FRAMES {
+            let step = (frames_to_use.len() as f32 / MAX_FRAMES as f32).ceil() as usize;
             let step = step.max(1);
             frames_to_use
                 .iter()
                 .step_by(step)
-                .take(30)
+                .take(MAX_FRAMES)
                 .cloned()
                 .collect()
         } else {
@@ -1651,6 +1715,7 @@ Return ONLY the JSON object."#
         self.api_key = None;
         self.auth_token = None;
         self.base_url = None;
+        self.pricing.apply_config(&config);
 
         if let Some(model) = config.get("model").and_then(|v| v.as_str()) {
             self.model = model.to_string();
@@ -1769,14 +1834,44 @@ Return ONLY the JSON object."#
                 "gif".to_string(),
                 "webp".to_string(),
             ],
+            audio_support: false,
+            url_context_support: false,
+            model_family: None,
+            max_images: MAX_FRAMES,
+            tokens_per_image: DEFAULT_TOKENS_PER_IMAGE,
         }
     }
+
+    fn estimate_cost(&self, frames: usize) -> Option<CostEstimate> {
+        Some(self.pricing.estimate(frames))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_pricing_override_is_applied_and_stored() {
+        let mut manager = crate::llm::LLMManager::new(reqwest::Client::new());
+        manager.switch_provider("claude").await.unwrap();
+        manager
+            .configure_claude(json!({
+                "model": "claude-sonnet-4-5",
+                "pricing": {"currency": "USD", "per_1k_input_tokens": 0.01, "tokens_per_image": 1000}
+            }))
+            .await
+            .unwrap();
+
+        // 未覆盖的 prompt_tokens 沿用内置的 2000
+        let estimate = manager.estimate_cost(10).unwrap();
+        assert_eq!(estimate.estimated_input_tokens, 12000);
+        assert!((estimate.amount - 0.12).abs() < 1e-9);
+        // 覆盖随配置保存，重启后恢复
+        let stored = manager.get_config().await.claude.pricing.unwrap();
+        assert_eq!(stored.tokens_per_image, 1000);
+    }
+
     #[test]
     fn test_extract_json_from_markdown_array() {
         // 测试从 markdown 代码块中提取 JSON 数组
@@ -36,6 +36,8 @@ pub struct CodexProvider {
     last_call_ids: Mutex<HashMap<String, i64>>,
     session_window_start: Option<DateTime<Utc>>,
     session_window_end: Option<DateTime<Utc>>,
+    /// 计费单价（用于费用估算）
+    pricing: ProviderPricing,
 }
 
 impl CodexProvider {
@@ -59,6 +61,13 @@ impl CodexProvider {
             last_call_ids: Mutex::new(HashMap::new()),
             session_window_start: None,
             session_window_end: None,
+            pricing: ProviderPricing {
+                currency: "USD".to_string(),
+                per_image: 0.0,
+                per_1k_input_tokens: 0.00125,
+                tokens_per_image: 800,
+                prompt_tokens: 2000,
+            },
         }
     }
 
@@ -211,6 +220,19 @@ impl CodexProvider {
         sampled
     }
 
+    /// Codex CLI 只接受真实文件路径，归档内的帧先解出到临时目录
+    fn unpack_frames(frames: &[String]) -> Result<(Option<tempfile::TempDir>, Vec<String>)> {
+        if !frames
+            .iter()
+            .any(|frame| crate::storage::frame_pack::is_packed_path(frame))
+        {
+            return Ok((None, frames.to_vec()));
+        }
+        let dir = tempfile::tempdir()?;
+        let frames = crate::storage::frame_pack::unpack_to_dir(frames, dir.path())?;
+        Ok((Some(dir), frames))
+    }
+
     fn canonicalize_paths(&self, frames: &[String]) -> Vec<String> {
         frames
             .iter()
@@ -268,6 +290,7 @@ impl CodexProvider {
         for image in images {
             command.arg("--image").arg(image);
         }
+        crate::telemetry::global().add_frames_encoded(images.len());
 
         command.arg(prompt);
         command.stdout(Stdio::piped());
@@ -381,6 +404,40 @@ impl CodexProvider {
         serde_json::from_value(value).map_err(|e| anyhow!("JSON 结构不符合预期: {}", e))
     }
 
+    /// 解析会话总结（帧分析与纯文本分析共用）
+    pub(crate) fn parse_session_summary(&self, raw: &str) -> Result<SessionSummary> {
+        let payload: CodexSummaryPayload = self.parse_json(raw)?;
+
+        let now = crate::storage::local_now();
+        let start = self
+            .session_window_start
+            .unwrap_or_else(|| now - ChronoDuration::minutes(15));
+        let end = self.session_window_end.unwrap_or(now);
+
+        Ok(SessionSummary {
+            title: payload.title.unwrap_or_else(|| "未命名会话".to_string()),
+            summary: payload.summary.unwrap_or_default(),
+            tags: self.map_tags(payload.tags),
+            start_time: start,
+            end_time: end,
+            key_moments: self.map_key_moments(payload.key_moments),
+            productivity_score: payload.productivity_score,
+            focus_score: payload.focus_score,
+            sampling_seed: None,
+            temperature: None,
+            language: None,
+            summary_auto_derived: false,
+            redacted: false,
+            idle: false,
+            recovered_stream: false,
+            low_confidence: false,
+            frame_selection: None,
+            reasoning: None,
+            served_by: None,
+            extra: HashMap::new(),
+        })
+    }
+
     fn map_tags(&self, raw: Vec<CodexTagPayload>) -> Vec<ActivityTag> {
         raw.into_iter()
             .filter_map(|tag| {
@@ -398,6 +455,8 @@ impl CodexProvider {
                     category: map_activity_category(&tag.category),
                     confidence: tag.confidence.unwrap_or(0.5).clamp(0.0, 1.0),
                     keywords,
+                    subcategories: Vec::new(),
+                    time_ranges: Vec::new(),
                 })
             })
             .collect()
@@ -457,6 +516,8 @@ impl LLMProvider for CodexProvider {
         }
 
         let sampled = self.sample_frames(&frames);
+        // 临时目录需存活到 CLI 执行结束
+        let (_unpacked_dir, sampled) = Self::unpack_frames(&sampled)?;
         let images = self.canonicalize_paths(&sampled);
         if images.is_empty() {
             return Err(anyhow!("采样后没有有效图片路径"));
@@ -466,24 +527,15 @@ impl LLMProvider for CodexProvider {
             .run_codex_exec(&self.summary_prompt(), &images, "analyze_frames")
             .await?;
 
-        let payload: CodexSummaryPayload = self.parse_json(&response)?;
+        self.parse_session_summary(&response)
+    }
 
-        let now = crate::storage::local_now();
-        let start = self
-            .session_window_start
-            .unwrap_or_else(|| now - ChronoDuration::minutes(15));
-        let end = self.session_window_end.unwrap_or(now);
+    async fn analyze_text(&self, content: String) -> Result<SessionSummary> {
+        let prompt =
+            build_text_session_prompt(&self.summary_prompt(), &content, Default::default())?;
+        let response = self.run_text_prompt(&prompt, "analyze_text").await?;
 
-        Ok(SessionSummary {
-            title: payload.title.unwrap_or_else(|| "未命名会话".to_string()),
-            summary: payload.summary.unwrap_or_default(),
-            tags: self.map_tags(payload.tags),
-            start_time: start,
-            end_time: end,
-            key_moments: self.map_key_moments(payload.key_moments),
-            productivity_score: payload.productivity_score,
-            focus_score: payload.focus_score,
-        })
+        self.parse_session_summary(&response)
     }
 
     async fn segment_video(&self, frames: Vec<String>, duration: u32) -> Result<Vec<VideoSegment>> {
@@ -492,6 +544,8 @@ impl LLMProvider for CodexProvider {
         }
 
         let sampled = self.sample_frames(&frames);
+        // 临时目录需存活到 CLI 执行结束
+        let (_unpacked_dir, sampled) = Self::unpack_frames(&sampled)?;
         let images = self.canonicalize_paths(&sampled);
         if images.is_empty() {
             return Err(anyhow!("采样后没有有效图片路径"));
@@ -553,6 +607,8 @@ impl LLMProvider for CodexProvider {
     }
 
     fn configure(&mut self, config: serde_json::Value) -> Result<()> {
+        self.pricing.apply_config(&config);
+
         if let Some(path) = config.get("binary_path").and_then(|v| v.as_str()) {
             if !path.trim().is_empty() {
                 self.binary_path = PathBuf::from(path);
@@ -642,9 +698,19 @@ impl LLMProvider for CodexProvider {
                 "gif".to_string(),
                 "webp".to_string(),
             ],
+            audio_support: false,
+            url_context_support: false,
+            model_family: None,
+            max_images: self.max_images,
+            tokens_per_image: DEFAULT_TOKENS_PER_IMAGE,
         }
     }
 
+    fn estimate_cost(&self, frames: usize) -> Option<CostEstimate> {
+        // 超过 max_images 的帧不会发送
+        Some(self.pricing.estimate(frames.min(self.max_images)))
+    }
+
     fn last_llm_call_id(&self, call_type: &str) -> Option<i64> {
         self.last_call_ids
             .lock()
@@ -0,0 +1,179 @@
+// 总结置信度 - 由标签置信度汇总整体置信度，低于阈值的总结标记为低质量
+//
+// 模型没看懂截图时常给出笼统的标题和一组低置信度标签。整体置信度按标签置信度自加权平均：
+// 高置信度标签权重更大，主要活动明确、只附带几个低置信度次要标签的总结不会被拉低。
+// 低于阈值的总结仍然保存，只标记 low_confidence，供换模型重新分析或人工复核；
+// 各 provider 的总结入库前都按 LLMConfig.min_confidence 检查
+
+use super::plugin::{ActivityTag, SessionSummary};
+use crate::storage::{Database, SessionScoresRecord};
+use tracing::warn;
+
+/// 整体置信度（0-1）：按置信度自加权的平均值；没有标签时返回 None
+pub fn aggregate_confidence(tags: &[ActivityTag]) -> Option<f32> {
+    let confidences: Vec<f32> = tags
+        .iter()
+        .map(|tag| tag.confidence)
+        .filter(|c| c.is_finite())
+        .map(|c| c.clamp(0.0, 1.0))
+        .collect();
+    if confidences.is_empty() {
+        return None;
+    }
+    let weight: f32 = confidences.iter().sum();
+    if weight == 0.0 {
+        return Some(0.0);
+    }
+    Some(confidences.iter().map(|c| c * c).sum::<f32>() / weight)
+}
+
+/// 校验阈值（0-1）
+pub fn validate_threshold(min_confidence: f32) -> anyhow::Result<()> {
+    if !(0.0..=1.0).contains(&min_confidence) {
+        return Err(anyhow::anyhow!(
+            "min_confidence 须在 0 到 1 之间，当前为 {}",
+            min_confidence
+        ));
+    }
+    Ok(())
+}
+
+/// 按阈值设置总结的 low_confidence 标记并返回；空闲会话与没有标签的总结不标记，
+/// 未设置阈值时不检查，保留已有标记
+pub fn flag_low_confidence(summary: &mut SessionSummary, min_confidence: Option<f32>) -> bool {
+    let Some(min_confidence) = min_confidence else {
+        return summary.low_confidence;
+    };
+    summary.low_confidence = !summary.idle
+        && aggregate_confidence(&summary.tags)
+            .is_some_and(|confidence| confidence < min_confidence);
+    summary.low_confidence
+}
+
+/// 总结入库前按阈值标记低置信度，并保存会话的评分与整体置信度（帧分析与视频分析共用）
+pub async fn save_session_scores(
+    db: &Database,
+    session_id: i64,
+    summary: &mut SessionSummary,
+    min_confidence: Option<f32>,
+) -> anyhow::Result<()> {
+    if flag_low_confidence(summary, min_confidence) {
+        warn!("会话 {} 的总结整体置信度低于阈值，已标记待复核", session_id);
+    }
+    let scores = SessionScoresRecord {
+        session_id,
+        productivity_score: summary.productivity_score.map(f64::from),
+        focus_score: summary.focus_score.map(f64::from),
+        confidence: aggregate_confidence(&summary.tags).map(f64::from),
+        low_confidence: summary.low_confidence,
+        updated_at: crate::storage::local_now(),
+    };
+    db.save_session_scores(&scores).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::plugin::ActivityCategory;
+
+    fn tag(category: ActivityCategory, confidence: f32) -> ActivityTag {
+        ActivityTag {
+            category,
+            confidence,
+            keywords: vec![],
+            subcategories: vec![],
+            time_ranges: vec![],
+        }
+    }
+
+    #[test]
+    fn test_uniformly_low_confidence_summary_is_flagged() {
+        let mut summary = SessionSummary {
+            title: "使用电脑".to_string(),
+            tags: vec![
+                tag(ActivityCategory::Work, 0.3),
+                tag(ActivityCategory::Personal, 0.25),
+                tag(ActivityCategory::Other, 0.2),
+            ],
+            ..Default::default()
+        };
+        assert!(aggregate_confidence(&summary.tags).unwrap() < 0.3);
+        assert!(flag_low_confidence(&mut summary, Some(0.5)));
+        assert!(summary.low_confidence);
+        // 标记随总结一起序列化保存
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap()["low_confidence"],
+            true
+        );
+
+        // 主要活动明确时，低置信度的次要标签不会拉低整体
+        summary.tags = vec![
+            tag(ActivityCategory::Work, 0.9),
+            tag(ActivityCategory::Communication, 0.2),
+        ];
+        assert!(!flag_low_confidence(&mut summary, Some(0.5)));
+
+        // 没有标签或空闲会话不标记
+        summary.tags.clear();
+        assert!(!flag_low_confidence(&mut summary, Some(0.5)));
+        // 未设置阈值时不检查
+        summary.low_confidence = true;
+        assert!(flag_low_confidence(&mut summary, None));
+        assert!(validate_threshold(1.5).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_low_confidence_flag_is_saved_with_scores() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let now = crate::storage::local_now();
+        let mut session_ids = Vec::new();
+        for _ in 0..2 {
+            let session = crate::storage::Session {
+                id: None,
+                start_time: now,
+                end_time: now,
+                title: "使用电脑".to_string(),
+                summary: String::new(),
+                video_path: None,
+                tags: "[]".to_string(),
+                created_at: Some(now),
+                device_name: None,
+                device_type: None,
+                pinned: false,
+            };
+            session_ids.push(db.insert_session(&session).await.unwrap());
+        }
+
+        // 任何 provider 给出的笼统总结都在入库前检查
+        let vague = || SessionSummary {
+            title: "使用电脑".to_string(),
+            tags: vec![
+                tag(ActivityCategory::Work, 0.3),
+                tag(ActivityCategory::Other, 0.2),
+            ],
+            ..Default::default()
+        };
+        let mut flagged = vague();
+        save_session_scores(&db, session_ids[0], &mut flagged, Some(0.5))
+            .await
+            .unwrap();
+        assert!(flagged.low_confidence);
+        // 未设置阈值时不标记，仍保存整体置信度
+        let mut unchecked = vague();
+        save_session_scores(&db, session_ids[1], &mut unchecked, None)
+            .await
+            .unwrap();
+        assert!(!unchecked.low_confidence);
+
+        let filter = crate::storage::SessionFilter {
+            low_confidence: Some(true),
+            ..Default::default()
+        };
+        let page = db.query_sessions(&filter).await.unwrap();
+        let ids: Vec<Option<i64>> = page.sessions.iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![Some(session_ids[0])]);
+    }
+}
@@ -0,0 +1,252 @@
+// 人工更正示例 - 用户修正过的会话总结作为后续分析的少样本示例
+//
+// 更正记录保存模型最初的描述与更正后的总结，并附带描述的向量。
+// 分析新会话前，用已知的上下文（网页标题、音频转写、紧邻的上一个会话）构造查询，
+// 取向量最相似的若干条更正注入提示词。向量为本地的字符 n-gram 哈希向量，不依赖额外的嵌入模型
+
+use super::plugin::{ActivityTag, AnalysisContext, FewShotExample};
+use crate::storage::{Session, SummaryCorrectionRecord};
+use serde_json::json;
+
+/// 向量维度
+pub const EMBEDDING_DIMS: usize = 256;
+
+/// 单次分析最多注入的示例数
+pub const MAX_FEW_SHOT_EXAMPLES: usize = 5;
+
+/// 参与检索的最近更正条数
+pub const MAX_CANDIDATE_CORRECTIONS: i64 = 500;
+
+/// 相似度低于该值的更正不作为示例
+const MIN_EXAMPLE_SIMILARITY: f32 = 0.3;
+
+/// 每条示例中描述与更正结果各自的最大字符数
+const MAX_EXAMPLE_CHARS: usize = 400;
+
+/// 查询中音频转写的最大字符数
+const MAX_QUERY_TRANSCRIPT_CHARS: usize = 500;
+
+/// 文本向量：小写后按字符二元组（中文）与单词（英文、数字）做特征哈希，再归一化
+pub fn embed_text(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0f32; EMBEDDING_DIMS];
+    let mut add = |feature: &str| {
+        let hash = fnv1a(feature.as_bytes());
+        let index = (hash % EMBEDDING_DIMS as u64) as usize;
+        vector[index] += if hash >> 63 == 0 { 1.0 } else { -1.0 };
+    };
+
+    let text = text.to_lowercase();
+    for word in text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+    {
+        let chars: Vec<char> = word.chars().collect();
+        if chars.iter().all(char::is_ascii) {
+            add(word);
+            continue;
+        }
+        if chars.len() == 1 {
+            add(word);
+        }
+        for pair in chars.windows(2) {
+            add(&pair.iter().collect::<String>());
+        }
+    }
+
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// 余弦相似度；维度不一致或为零向量时为 0
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm =
+        a.iter().map(|v| v * v).sum::<f32>().sqrt() * b.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        dot / norm
+    } else {
+        0.0
+    }
+}
+
+/// 模型给出的总结描述：标题、摘要与关键词
+pub fn describe_summary(title: &str, summary: &str, tags: &[ActivityTag]) -> String {
+    let keywords: Vec<&str> = tags
+        .iter()
+        .flat_map(|tag| tag.keywords.iter().map(String::as_str))
+        .collect();
+    let mut description = format!("{}\n{}", title.trim(), summary.trim());
+    if !keywords.is_empty() {
+        description.push_str(&format!("\n关键词：{}", keywords.join("、")));
+    }
+    description
+}
+
+/// 更正后的总结（紧凑 JSON，只保留类别与关键词）
+pub fn corrected_text(title: &str, summary: &str, tags: &[ActivityTag]) -> String {
+    let tags: Vec<_> = tags
+        .iter()
+        .map(|tag| json!({ "category": tag.category, "keywords": tag.keywords }))
+        .collect();
+    json!({ "title": title, "summary": summary, "tags": tags }).to_string()
+}
+
+/// 检索查询：网页标题、音频转写开头与紧邻的上一个会话（相邻会话通常是同类活动）
+pub fn query_text(context: &AnalysisContext, previous: Option<&Session>) -> String {
+    let mut parts = Vec::new();
+    for visit in &context.visited_urls {
+        let title = visit.title.trim();
+        parts.push(if title.is_empty() {
+            visit.url.trim().to_string()
+        } else {
+            title.to_string()
+        });
+    }
+    if let Some(transcript) = context.transcript.as_deref() {
+        parts.push(
+            transcript
+                .chars()
+                .take(MAX_QUERY_TRANSCRIPT_CHARS)
+                .collect(),
+        );
+    }
+    if let Some(session) = previous {
+        let tags: Vec<ActivityTag> = serde_json::from_str(&session.tags).unwrap_or_default();
+        parts.push(describe_summary(&session.title, &session.summary, &tags));
+    }
+    parts.retain(|part| !part.trim().is_empty());
+    parts.join("\n")
+}
+
+/// 取与查询最相似的至多 limit 条更正（相似度从高到低）
+pub fn select_examples(
+    query: &str,
+    records: &[SummaryCorrectionRecord],
+    limit: usize,
+) -> Vec<FewShotExample> {
+    let query = embed_text(query);
+    let mut scored: Vec<(f32, &SummaryCorrectionRecord)> = records
+        .iter()
+        .filter_map(|record| {
+            let embedding: Vec<f32> = serde_json::from_str(&record.embedding).ok()?;
+            let similarity = cosine(&query, &embedding);
+            (similarity >= MIN_EXAMPLE_SIMILARITY).then_some((similarity, record))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored
+        .into_iter()
+        .take(limit.min(MAX_FEW_SHOT_EXAMPLES))
+        .map(|(_, record)| FewShotExample {
+            description: record.description.clone(),
+            corrected: record.corrected.clone(),
+        })
+        .collect()
+}
+
+/// 提示词中的示例段落；没有示例时返回 None
+pub fn build_examples_note(examples: &[FewShotExample]) -> Option<String> {
+    if examples.is_empty() {
+        return None;
+    }
+    let clip = |text: &str| -> String {
+        let text = text.trim();
+        let mut clipped: String = text.chars().take(MAX_EXAMPLE_CHARS).collect();
+        if clipped.len() < text.len() {
+            clipped.push('…');
+        }
+        clipped
+    };
+    let mut note = String::from(
+        "以下是用户对相似会话总结的人工更正，请参考其中的分类与措辞习惯（只作参考，内容以本次截图为准）：",
+    );
+    for (i, example) in examples.iter().take(MAX_FEW_SHOT_EXAMPLES).enumerate() {
+        note.push_str(&format!(
+            "\n示例 {}：\n原始总结：{}\n更正为：{}",
+            i + 1,
+            clip(&example.description),
+            clip(&example.corrected)
+        ));
+    }
+    Some(note)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ActivityCategory;
+
+    fn record(session_id: i64, description: &str) -> SummaryCorrectionRecord {
+        SummaryCorrectionRecord {
+            session_id,
+            description: description.to_string(),
+            corrected: format!("更正 {}", session_id),
+            embedding: serde_json::to_string(&embed_text(description)).unwrap(),
+            created_at: crate::storage::local_now(),
+        }
+    }
+
+    #[test]
+    fn test_embedding_similarity_ranks_related_text_first() {
+        let code = embed_text("在 VS Code 中编写 Rust 代码并运行 cargo test");
+        assert!((cosine(&code, &code) - 1.0).abs() < 1e-5);
+        assert!(
+            cosine(&code, &embed_text("用 VS Code 调试 Rust 项目的 cargo 构建"))
+                > cosine(&code, &embed_text("在视频网站观看烹饪节目"))
+        );
+        assert_eq!(cosine(&embed_text(""), &code), 0.0);
+    }
+
+    #[test]
+    fn test_select_examples_is_bounded_and_filtered() {
+        let records = vec![
+            record(1, "观看烹饪视频\n在视频网站浏览菜谱"),
+            record(2, "编写 Rust 代码\n在编辑器中修改 cargo 项目并运行测试"),
+            record(3, "编写 Rust 代码\n调试 Rust 项目"),
+            SummaryCorrectionRecord {
+                embedding: "invalid".to_string(),
+                ..record(4, "编写 Rust 代码")
+            },
+        ];
+        let examples = select_examples("调试 Rust 代码并运行 cargo 测试", &records, 10);
+        let corrected: Vec<&str> = examples.iter().map(|e| e.corrected.as_str()).collect();
+        assert_eq!(corrected.len(), 2);
+        assert!(!corrected.contains(&"更正 1"));
+        assert_eq!(select_examples("调试 Rust 代码", &records, 1).len(), 1);
+
+        let note = build_examples_note(&examples).unwrap();
+        assert!(note.contains("示例 2："));
+        assert!(build_examples_note(&[]).is_none());
+    }
+
+    #[test]
+    fn test_corrected_text_keeps_category_and_keywords() {
+        let tags = vec![ActivityTag {
+            category: ActivityCategory::Learning,
+            confidence: 0.4,
+            keywords: vec!["Rust".to_string()],
+            subcategories: Vec::new(),
+            time_ranges: Vec::new(),
+        }];
+        let value: serde_json::Value =
+            serde_json::from_str(&corrected_text("学习 Rust", "阅读文档", &tags)).unwrap();
+        assert_eq!(value["tags"][0]["category"], "learning");
+        assert!(value["tags"][0].get("confidence").is_none());
+        assert_eq!(
+            describe_summary("学习 Rust", "阅读文档", &tags),
+            "学习 Rust\n阅读文档\n关键词：Rust"
+        );
+    }
+}
@@ -0,0 +1,521 @@
+// 组合 provider - 同时调用多个 provider，合并各自的总结以提高可靠性
+//
+// 标签取并集并平均置信度，评分取中位数，关键时刻按时间合并去重；
+// 标题与摘要取与合并后标签最一致的成员结果。单个成员失败不影响其余成员
+
+use super::plugin::*;
+use crate::time_mapper::TimeMapper;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use futures_util::future::{BoxFuture, FutureExt};
+use futures_util::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// 时间相差不超过该秒数的关键时刻视为同一时刻
+const KEY_MOMENT_MERGE_SECS: i64 = 30;
+
+/// 组合 provider 配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnsembleConfig {
+    /// 同时调用的成员数上限（本地模型较多时调小，避免抢占显存）
+    pub max_concurrency: usize,
+}
+
+impl Default for EnsembleConfig {
+    fn default() -> Self {
+        Self { max_concurrency: 2 }
+    }
+}
+
+/// 单个成员的调用结果
+#[derive(Debug, Clone, Serialize)]
+pub struct MemberResult {
+    pub provider: String,
+    pub elapsed_ms: u64,
+    pub summary: Option<SessionSummary>,
+    pub error: Option<String>,
+}
+
+/// 组合多个 provider 的总结；排在前面的成员优先级更高
+pub struct EnsembleProvider {
+    members: Vec<Box<dyn LLMProvider>>,
+    config: EnsembleConfig,
+    /// 最近一次调用中各成员的结果
+    last_results: Mutex<Vec<MemberResult>>,
+}
+
+impl EnsembleProvider {
+    pub fn new(members: Vec<Box<dyn LLMProvider>>) -> Self {
+        Self {
+            members,
+            config: EnsembleConfig::default(),
+            last_results: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 最近一次调用中各成员的结果（按成员顺序）
+    pub fn last_member_results(&self) -> Vec<MemberResult> {
+        self.last_results.lock().unwrap().clone()
+    }
+
+    fn primary(&self) -> Result<&dyn LLMProvider> {
+        self.members
+            .first()
+            .map(|member| member.as_ref())
+            .ok_or_else(|| anyhow!("组合 provider 没有成员"))
+    }
+
+    /// 并发（有上限）调用全部成员并合并结果；全部失败时返回第一个错误
+    async fn run_members<'a, F>(&'a self, call: F) -> Result<SessionSummary>
+    where
+        F: Fn(&'a dyn LLMProvider) -> BoxFuture<'a, Result<SessionSummary>>,
+    {
+        if self.members.is_empty() {
+            return Err(anyhow!("组合 provider 没有成员"));
+        }
+        // 先装箱再交给 buffered，避免 async_trait 要求 Send 时闭包的生命周期推断失败
+        let calls: Vec<BoxFuture<'a, (String, u64, Result<SessionSummary>)>> = self
+            .members
+            .iter()
+            .map(|member| {
+                let future = call(member.as_ref());
+                async move {
+                    let started = Instant::now();
+                    let result = future.await;
+                    let elapsed_ms = started.elapsed().as_millis() as u64;
+                    (member.name().to_string(), elapsed_ms, result)
+                }
+                .boxed()
+            })
+            .collect();
+        let results: Vec<(String, u64, Result<SessionSummary>)> = stream::iter(calls)
+            .buffered(self.config.max_concurrency.max(1))
+            .collect()
+            .await;
+
+        let mut reports = Vec::with_capacity(results.len());
+        let mut summaries = Vec::new();
+        let mut first_error = None;
+        for (provider, elapsed_ms, result) in results {
+            match result {
+                Ok(summary) => {
+                    summaries.push(summary.clone());
+                    reports.push(MemberResult {
+                        provider,
+                        elapsed_ms,
+                        summary: Some(summary),
+                        error: None,
+                    });
+                }
+                Err(e) => {
+                    warn!("组合 provider 成员 {} 失败: {}", provider, e);
+                    reports.push(MemberResult {
+                        provider,
+                        elapsed_ms,
+                        summary: None,
+                        error: Some(e.to_string()),
+                    });
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        info!(
+            "组合 provider: {}/{} 个成员成功",
+            summaries.len(),
+            reports.len()
+        );
+        *self.last_results.lock().unwrap() = reports;
+
+        match merge_summaries(&summaries) {
+            Some(merged) => Ok(merged),
+            None => Err(first_error.unwrap_or_else(|| anyhow!("组合 provider 没有可用结果"))),
+        }
+    }
+}
+
+/// 中位数（偶数个时取中间两个的平均值）
+fn median(values: impl Iterator<Item = Option<f32>>) -> Option<f32> {
+    let mut values: Vec<f32> = values.flatten().collect();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    })
+}
+
+/// 标签取并集：同类别的置信度按给出该类别的成员平均，关键词去重合并
+fn merge_tags(summaries: &[SessionSummary]) -> Vec<ActivityTag> {
+    let mut merged: Vec<(ActivityTag, usize)> = Vec::new();
+    for tag in summaries.iter().flat_map(|s| &s.tags) {
+        match merged.iter_mut().find(|(t, _)| t.category == tag.category) {
+            Some((existing, count)) => {
+                existing.confidence += tag.confidence;
+                *count += 1;
+                for keyword in &tag.keywords {
+                    if !existing.keywords.contains(keyword) {
+                        existing.keywords.push(keyword.clone());
+                    }
+                }
+            }
+            None => merged.push((tag.clone(), 1)),
+        }
+    }
+    let mut tags: Vec<ActivityTag> = merged
+        .into_iter()
+        .map(|(mut tag, count)| {
+            tag.confidence /= count as f32;
+            tag
+        })
+        .collect();
+    tags.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    tags
+}
+
+/// 关键时刻按时间排序，相近的时刻只保留重要性最高的一条；无法解析时间的排在最后
+fn merge_key_moments(summaries: &[SessionSummary]) -> Vec<KeyMoment> {
+    let mut timed: Vec<(i64, KeyMoment)> = Vec::new();
+    let mut untimed: Vec<KeyMoment> = Vec::new();
+    for moment in summaries.iter().flat_map(|s| &s.key_moments) {
+        match TimeMapper::parse_offset(&moment.time) {
+            Some(offset) => timed.push((offset.num_seconds(), moment.clone())),
+            None => {
+                if !untimed.iter().any(|m| m.description == moment.description) {
+                    untimed.push(moment.clone());
+                }
+            }
+        }
+    }
+    timed.sort_by_key(|(seconds, _)| *seconds);
+
+    let mut merged: Vec<(i64, KeyMoment)> = Vec::new();
+    for (seconds, moment) in timed {
+        match merged.last_mut() {
+            Some((last_seconds, last)) if seconds - *last_seconds <= KEY_MOMENT_MERGE_SECS => {
+                if moment.importance > last.importance {
+                    *last = moment;
+                    *last_seconds = seconds;
+                }
+            }
+            _ => merged.push((seconds, moment)),
+        }
+    }
+    merged
+        .into_iter()
+        .map(|(_, moment)| moment)
+        .chain(untimed)
+        .collect()
+}
+
+/// 合并多个成员的总结；没有结果时返回 None
+///
+/// 标题与摘要取标签与合并结果最一致的成员（按合并后的置信度累加），相同时取排在前面的成员
+pub fn merge_summaries(summaries: &[SessionSummary]) -> Option<SessionSummary> {
+    let tags = merge_tags(summaries);
+    let agreement = |summary: &SessionSummary| -> f32 {
+        summary
+            .tags
+            .iter()
+            .filter_map(|tag| tags.iter().find(|t| t.category == tag.category))
+            .map(|t| t.confidence)
+            .sum()
+    };
+    let representative = summaries
+        .iter()
+        .enumerate()
+        .max_by(|(ia, a), (ib, b)| agreement(a).total_cmp(&agreement(b)).then(ib.cmp(ia)))
+        .map(|(_, summary)| summary)?;
+
+    Some(SessionSummary {
+        tags,
+        key_moments: merge_key_moments(summaries),
+        productivity_score: median(summaries.iter().map(|s| s.productivity_score)),
+        focus_score: median(summaries.iter().map(|s| s.focus_score)),
+        sampling_seed: None,
+        temperature: None,
+        redacted: summaries.iter().any(|s| s.redacted),
+        ..representative.clone()
+    })
+}
+
+#[async_trait]
+impl LLMProvider for EnsembleProvider {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    async fn analyze_frames(&self, frames: Vec<String>) -> Result<SessionSummary> {
+        self.run_members(|member| member.analyze_frames(frames.clone()))
+            .await
+    }
+
+    async fn analyze_text(&self, content: String) -> Result<SessionSummary> {
+        self.run_members(|member| member.analyze_text(content.clone()))
+            .await
+    }
+
+    async fn analyze_frames_with_context(
+        &self,
+        frames: Vec<String>,
+        context: AnalysisContext,
+    ) -> Result<SessionSummary> {
+        self.run_members(|member| {
+            member.analyze_frames_with_context(frames.clone(), context.clone())
+        })
+        .await
+    }
+
+    // 以下调用的结果无法逐项合并，交给优先级最高的成员
+
+    async fn refine_field(
+        &self,
+        frames: Vec<String>,
+        current: SessionSummary,
+        field: SummaryField,
+    ) -> Result<SessionSummary> {
+        self.primary()?.refine_field(frames, current, field).await
+    }
+
+    async fn segment_video(&self, frames: Vec<String>, duration: u32) -> Result<Vec<VideoSegment>> {
+        self.primary()?.segment_video(frames, duration).await
+    }
+
+    async fn generate_timeline(
+        &self,
+        segments: Vec<VideoSegment>,
+        previous_cards: Option<Vec<TimelineCard>>,
+    ) -> Result<Vec<TimelineCard>> {
+        self.primary()?
+            .generate_timeline(segments, previous_cards)
+            .await
+    }
+
+    async fn generate_day_summary(&self, date: &str, sessions: &[SessionBrief]) -> Result<String> {
+        self.primary()?.generate_day_summary(date, sessions).await
+    }
+
+    fn set_session_window(
+        &mut self,
+        start: Option<chrono::DateTime<chrono::Utc>>,
+        end: Option<chrono::DateTime<chrono::Utc>>,
+    ) {
+        for member in &mut self.members {
+            member.set_session_window(start, end);
+        }
+    }
+
+    async fn on_start(&self) -> Result<()> {
+        for member in &self.members {
+            member.on_start().await?;
+        }
+        Ok(())
+    }
+
+    async fn on_stop(&self) -> Result<()> {
+        for member in &self.members {
+            if let Err(e) = member.on_stop().await {
+                warn!("释放 {} 资源失败: {}", member.name(), e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn refresh_capabilities(&mut self) -> Result<()> {
+        for member in &mut self.members {
+            member.refresh_capabilities().await?;
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "ensemble"
+    }
+
+    /// 配置组合方式；各成员需在加入前单独配置
+    fn configure(&mut self, config: serde_json::Value) -> Result<()> {
+        self.config =
+            serde_json::from_value(config).map_err(|e| anyhow!("组合 provider 配置无效: {}", e))?;
+        Ok(())
+    }
+
+    fn is_configured(&self) -> bool {
+        self.members.iter().any(|member| member.is_configured())
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        self.members
+            .first()
+            .map(|member| member.capabilities())
+            .unwrap_or_default()
+    }
+
+    fn estimate_cost(&self, frames: usize) -> Option<CostEstimate> {
+        let mut estimates = self
+            .members
+            .iter()
+            .filter_map(|member| member.estimate_cost(frames));
+        let first = estimates.next()?;
+        // 币种不同时无法累加
+        estimates.try_fold(first, |mut total, estimate| {
+            (estimate.currency == total.currency).then(|| {
+                total.image_count += estimate.image_count;
+                total.estimated_input_tokens += estimate.estimated_input_tokens;
+                total.amount += estimate.amount;
+                total
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(category: ActivityCategory, confidence: f32, keywords: &[&str]) -> ActivityTag {
+        ActivityTag {
+            category,
+            confidence,
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            subcategories: Vec::new(),
+            time_ranges: Vec::new(),
+        }
+    }
+
+    fn moment(time: &str, description: &str, importance: u8) -> KeyMoment {
+        KeyMoment {
+            time: time.to_string(),
+            description: description.to_string(),
+            importance,
+        }
+    }
+
+    fn summaries() -> (SessionSummary, SessionSummary) {
+        let first = SessionSummary {
+            title: "浏览新闻".to_string(),
+            summary: "阅读新闻网站".to_string(),
+            tags: vec![tag(ActivityCategory::Personal, 0.6, &["新闻"])],
+            key_moments: vec![moment("02:00", "打开新闻", 2)],
+            productivity_score: Some(40.0),
+            focus_score: None,
+            ..Default::default()
+        };
+        let second = SessionSummary {
+            title: "编写 Rust 代码".to_string(),
+            summary: "实现组合 provider".to_string(),
+            tags: vec![
+                tag(ActivityCategory::Work, 0.9, &["rust"]),
+                tag(ActivityCategory::Personal, 0.2, &["新闻", "天气"]),
+            ],
+            key_moments: vec![
+                moment("02:20", "切换到编辑器", 4),
+                moment("10:00", "运行测试", 3),
+                moment("稍后", "提交代码", 3),
+            ],
+            productivity_score: Some(90.0),
+            focus_score: Some(70.0),
+            ..Default::default()
+        };
+        (first, second)
+    }
+
+    #[test]
+    fn test_merge_two_summaries() {
+        let (first, second) = summaries();
+        let merged = merge_summaries(&[first, second]).unwrap();
+
+        // 标签并集，置信度按给出该类别的成员平均，按置信度排序
+        let categories: Vec<_> = merged.tags.iter().map(|t| t.category.clone()).collect();
+        assert_eq!(
+            categories,
+            vec![ActivityCategory::Work, ActivityCategory::Personal]
+        );
+        assert!((merged.tags[1].confidence - 0.4).abs() < 1e-6);
+        assert_eq!(merged.tags[1].keywords, vec!["新闻", "天气"]);
+
+        // 两个数取平均，只有一个时取该值
+        assert_eq!(merged.productivity_score, Some(65.0));
+        assert_eq!(merged.focus_score, Some(70.0));
+
+        // 02:00 与 02:20 合并为重要性更高的一条，无法解析时间的排在最后
+        let moments: Vec<&str> = merged
+            .key_moments
+            .iter()
+            .map(|m| m.description.as_str())
+            .collect();
+        assert_eq!(moments, vec!["切换到编辑器", "运行测试", "提交代码"]);
+
+        // 第二个成员的标签与合并结果更一致
+        assert_eq!(merged.title, "编写 Rust 代码");
+        assert_eq!(merged.summary, "实现组合 provider");
+
+        assert!(merge_summaries(&[]).is_none());
+    }
+
+    /// 返回固定结果的成员
+    struct FixedProvider {
+        name: &'static str,
+        summary: Option<SessionSummary>,
+    }
+
+    #[async_trait]
+    impl LLMProvider for FixedProvider {
+        fn as_any(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+
+        async fn analyze_frames(&self, _frames: Vec<String>) -> Result<SessionSummary> {
+            self.summary.clone().ok_or_else(|| anyhow!("模型不可用"))
+        }
+
+        async fn analyze_text(&self, _content: String) -> Result<SessionSummary> {
+            Err(anyhow!("模型不可用"))
+        }
+
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn configure(&mut self, _config: serde_json::Value) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_configured(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_member_results_are_reported() {
+        let (first, _) = summaries();
+        let ensemble = EnsembleProvider::new(vec![
+            Box::new(FixedProvider {
+                name: "down",
+                summary: None,
+            }),
+            Box::new(FixedProvider {
+                name: "up",
+                summary: Some(first),
+            }),
+        ]);
+
+        let summary = ensemble.analyze_frames(vec![]).await.unwrap();
+        assert_eq!(summary.title, "浏览新闻");
+        let results = ensemble.last_member_results();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].provider, "down");
+        assert_eq!(results[0].error.as_deref(), Some("模型不可用"));
+        assert!(results[1].summary.is_some() && results[1].error.is_none());
+
+        // 全部失败时返回成员的错误
+        let err = ensemble.analyze_text(String::new()).await.unwrap_err();
+        assert_eq!(err.to_string(), "模型不可用");
+    }
+}
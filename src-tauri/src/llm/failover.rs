@@ -0,0 +1,283 @@
+// Provider 故障转移 - 当前 provider 因配额、限流或欠费失败时改用后备 provider
+//
+// 付费的云端 provider 用完额度或被限流时，整个会话分析失败；同时配置了本地 Ollama 的用户
+// 更希望直接改用本地模型。与 Ollama 的模型回退（同一 provider 内换模型）不同，这里按配置的
+// provider 链依次尝试其他 provider，只有配置的错误类型才触发，其余错误照常返回。
+// 实际生成总结的 provider 记录在总结的 served_by 中
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// 触发故障转移的错误类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailoverTrigger {
+    /// 额度用完（如 insufficient_quota）
+    Quota,
+    /// 请求过多被限流（HTTP 429）
+    RateLimit,
+    /// 欠费或需要付费（HTTP 402）
+    Payment,
+}
+
+impl std::fmt::Display for FailoverTrigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Quota => "额度用完",
+            Self::RateLimit => "请求被限流",
+            Self::Payment => "账户欠费",
+        })
+    }
+}
+
+/// 故障转移配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FailoverConfig {
+    /// 按顺序尝试的后备 provider（如 ["ollama"]）；为空时不转移
+    pub providers: Vec<String>,
+    /// 触发转移的错误类型
+    pub triggers: Vec<FailoverTrigger>,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            providers: Vec::new(),
+            triggers: vec![
+                FailoverTrigger::Quota,
+                FailoverTrigger::RateLimit,
+                FailoverTrigger::Payment,
+            ],
+        }
+    }
+}
+
+impl FailoverConfig {
+    pub fn validate(&self) -> Result<()> {
+        for (index, name) in self.providers.iter().enumerate() {
+            if !super::metrics::REGISTERED_PROVIDERS.contains(&name.as_str()) {
+                return Err(anyhow!("failover.providers 中的 provider {} 不存在", name));
+            }
+            if self.providers[..index].contains(name) {
+                return Err(anyhow!("failover.providers 中的 provider {} 重复", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// 错误属于配置的触发类型时返回该类型
+    pub fn trigger_for(&self, error: &anyhow::Error) -> Option<FailoverTrigger> {
+        classify_error(error).filter(|trigger| self.triggers.contains(trigger))
+    }
+}
+
+/// 按错误信息判断错误类型（各 provider 的错误信息包含 HTTP 状态码与接口返回的错误内容）
+pub fn classify_error(error: &anyhow::Error) -> Option<FailoverTrigger> {
+    let message = format!("{:#}", error).to_lowercase();
+    let contains_any = |keywords: &[&str]| keywords.iter().any(|k| message.contains(k));
+    // 402 与 429 只按独立的数字匹配，避免命中请求 ID 等长数字
+    let has_status = |code: &str| {
+        message
+            .split(|c: char| !c.is_ascii_digit())
+            .any(|part| part == code)
+    };
+
+    if has_status("402")
+        || contains_any(&[
+            "payment required",
+            "arrearage",
+            "billing",
+            "余额不足",
+            "欠费",
+        ])
+    {
+        Some(FailoverTrigger::Payment)
+    } else if contains_any(&[
+        "insufficient_quota",
+        "exceeded your current quota",
+        "quota exceeded",
+        "allocationquota",
+        "credit balance",
+        "配额",
+        "额度",
+    ]) {
+        Some(FailoverTrigger::Quota)
+    } else if has_status("429")
+        || contains_any(&[
+            "too many requests",
+            "rate limit",
+            "rate_limit",
+            "throttl",
+            "限流",
+        ])
+    {
+        Some(FailoverTrigger::RateLimit)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::plugin::{LLMProvider, SessionSummary};
+    use crate::llm::LLMManager;
+    use async_trait::async_trait;
+
+    /// 总是返回 429 的云端 provider
+    struct ThrottledProvider;
+
+    #[async_trait]
+    impl LLMProvider for ThrottledProvider {
+        fn as_any(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+
+        async fn analyze_frames(&self, _frames: Vec<String>) -> Result<SessionSummary> {
+            Err(anyhow!(
+                "Qwen API调用失败 HTTP 429: {{\"error\":{{\"code\":\"Throttling.RateQuota\"}}}}"
+            ))
+        }
+
+        async fn analyze_text(&self, _content: String) -> Result<SessionSummary> {
+            Err(anyhow!("不支持文本分析"))
+        }
+
+        fn name(&self) -> &str {
+            "Qwen"
+        }
+
+        fn configure(&mut self, _config: serde_json::Value) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_configured(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_hosted_provider_fails_over_to_local() {
+        let content = serde_json::json!({
+            "title": "编写代码",
+            "summary": "在编辑器中实现故障转移",
+            "tags": [{"category": "work", "confidence": 0.9, "keywords": ["Rust"]}]
+        })
+        .to_string();
+        let url = crate::test_server::serve_routes(vec![(
+            "/api/chat",
+            serde_json::json!({ "message": { "content": content } }).to_string(),
+        )])
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<String> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.jpg", 1_700_000_000_000i64 + i));
+                image::RgbImage::from_fn(64, 48, |x, y| {
+                    image::Rgb([(x * 4) as u8, (y * 5) as u8, (i * 40) as u8])
+                })
+                .save(&path)
+                .unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+
+        let db = std::sync::Arc::new(
+            crate::storage::Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let session_id = db
+            .insert_session(&crate::storage::Session {
+                id: None,
+                start_time: chrono::Utc::now(),
+                end_time: chrono::Utc::now(),
+                title: String::new(),
+                summary: String::new(),
+                video_path: None,
+                tags: "[]".to_string(),
+                created_at: None,
+                device_name: None,
+                device_type: None,
+                pinned: false,
+            })
+            .await
+            .unwrap();
+
+        let mut manager = LLMManager::new(reqwest::Client::new());
+        manager.provider = crate::llm::Layered::new(Box::new(ThrottledProvider));
+        manager.set_provider_database(db.clone(), Some(session_id));
+        let mut config = manager.get_config().await;
+        config.ollama.base_url = url;
+        config.ollama.detect_idle_frames = false;
+        config.failover.providers = vec!["ollama".to_string()];
+        manager.update_config(config.clone()).await.unwrap();
+
+        // 分段与时间线两个阶段都由后备 provider 完成
+        let analysis = manager
+            .segment_video_and_generate_timeline(frames.clone(), 1, None)
+            .await
+            .unwrap();
+        assert_eq!(analysis.served_by, "ollama");
+        assert_eq!(analysis.segments[0].description, "在编辑器中实现故障转移");
+        assert_eq!(analysis.timeline_cards.len(), 1);
+        let record = db.get_session_analysis(session_id).await.unwrap().unwrap();
+        assert_eq!(record.served_by.as_deref(), Some("ollama"));
+        // 两个 provider 的调用都计入各自的指标
+        let statuses = manager.metrics().provider_status();
+        let status = |name: &str| {
+            statuses
+                .iter()
+                .find(|s| s.provider == name)
+                .unwrap()
+                .clone()
+        };
+        assert!(status("qwen").last_error.unwrap().contains("HTTP 429"));
+        assert_eq!(status("ollama").success_rate, Some(1.0));
+
+        let summary = manager.analyze_frames(frames.clone()).await.unwrap();
+        assert_eq!(summary.title, "编写代码");
+        assert_eq!(summary.served_by.as_deref(), Some("ollama"));
+
+        // 未配置限流触发时照常返回原错误
+        config.failover.triggers = vec![FailoverTrigger::Quota];
+        manager.update_config(config).await.unwrap();
+        let err = manager
+            .segment_video_and_generate_timeline(frames, 1, None)
+            .await
+            .err()
+            .expect("未配置限流触发时应返回原错误");
+        assert!(err.to_string().contains("HTTP 429"));
+    }
+
+    #[test]
+    fn test_errors_are_classified() {
+        let classify = |message: &str| classify_error(&anyhow!(message.to_string()));
+        assert_eq!(
+            classify("Ollama 请求失败 HTTP 429: too many requests"),
+            Some(FailoverTrigger::RateLimit)
+        );
+        assert_eq!(
+            classify("You exceeded your current quota (insufficient_quota)"),
+            Some(FailoverTrigger::Quota)
+        );
+        assert_eq!(
+            classify("Qwen API调用失败 HTTP 400: Arrearage"),
+            Some(FailoverTrigger::Payment)
+        );
+        assert_eq!(
+            classify("Throttling.RateQuota: Requests rate limit exceeded"),
+            Some(FailoverTrigger::RateLimit)
+        );
+        assert_eq!(classify("request 14290 timed out"), None);
+        assert_eq!(classify("连接被重置"), None);
+
+        let config = FailoverConfig {
+            providers: vec!["ollama".to_string(), "ollama".to_string()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,172 @@
+// 黄金文件测试 - 录制的模型原始输出须解析为保存的预期总结
+//
+// 每个用例是 tests/golden/<用例名>/ 下的三个文件：input.json（provider、帧路径与 provider 配置）、
+// response.txt（模型的原始输出）、expected.json（预期的总结，解析失败时为 {"error": 错误信息}）。
+// 修改提示词或解析逻辑导致输出变化时，设置 UPDATE_GOLDEN=1 运行测试重写 expected.json，审阅差异后再提交
+
+use super::budget::timed_frames;
+use super::codex::CodexProvider;
+use super::ollama::OllamaProvider;
+use super::plugin::{LLMProvider, SessionSummary};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+
+/// 设置后重写预期文件而不是比较
+const UPDATE_ENV: &str = "UPDATE_GOLDEN";
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum GoldenProvider {
+    Ollama,
+    Codex,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CaseInput {
+    provider: GoldenProvider,
+    /// 录制时的帧路径，文件名为毫秒时间戳；首末帧的时间作为会话时间
+    frames: Vec<String>,
+    /// provider 配置（与 configure 的参数相同），缺省时使用默认配置
+    #[serde(default)]
+    config: Option<Value>,
+}
+
+/// 已加载的用例
+struct GoldenCase {
+    name: String,
+    dir: PathBuf,
+    input: CaseInput,
+    response: String,
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+}
+
+/// 全部用例名（按名称排序）
+fn case_names() -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(golden_dir())? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn load_case(name: &str) -> Result<GoldenCase> {
+    let dir = golden_dir().join(name);
+    let input = std::fs::read_to_string(dir.join("input.json"))
+        .with_context(|| format!("读取用例 {} 的 input.json 失败", name))?;
+    let response = std::fs::read_to_string(dir.join("response.txt"))
+        .with_context(|| format!("读取用例 {} 的 response.txt 失败", name))?;
+    Ok(GoldenCase {
+        name: name.to_string(),
+        input: serde_json::from_str(&input)
+            .with_context(|| format!("用例 {} 的 input.json 格式错误", name))?,
+        dir,
+        response,
+    })
+}
+
+/// 按用例的 provider 解析录制的输出：成功时为总结的 JSON，解析失败时为 {"error": 错误信息}
+///
+/// 用例本身有误（帧路径无法解析出时间、配置无效）时返回错误
+fn run_case(case: &GoldenCase) -> Result<Value> {
+    let frames = timed_frames(&case.input.frames);
+    let start = frames.iter().map(|frame| frame.timestamp).min();
+    let end = frames.iter().map(|frame| frame.timestamp).max();
+    if start.is_none() {
+        return Err(anyhow!("用例 {} 没有可解析出时间的帧", case.name));
+    }
+
+    let parsed = match case.input.provider {
+        GoldenProvider::Ollama => {
+            let mut provider = OllamaProvider::new(reqwest::Client::new());
+            if let Some(config) = &case.input.config {
+                provider.configure(config.clone())?;
+            }
+            provider.set_session_window(start, end);
+            provider.parse_session_summary(provider.extract_json_text(&case.response))
+        }
+        GoldenProvider::Codex => {
+            let mut provider = CodexProvider::new();
+            if let Some(config) = &case.input.config {
+                provider.configure(config.clone())?;
+            }
+            provider.set_session_window(start, end);
+            provider.parse_session_summary(&case.response)
+        }
+    };
+    match parsed {
+        Ok(summary) => Ok(serde_json::to_value(&summary)?),
+        Err(e) => Ok(json!({ "error": e.to_string() })),
+    }
+}
+
+/// 经 SessionSummary 往返一次，使手写的预期文件与序列化结果可比（如 f32 精度、时间格式）
+fn normalize(value: Value) -> Result<Value> {
+    if value.get("error").is_some() {
+        return Ok(value);
+    }
+    let summary: SessionSummary = serde_json::from_value(value)?;
+    Ok(serde_json::to_value(&summary)?)
+}
+
+/// 运行用例并与 expected.json 比较；设置了 UPDATE_GOLDEN 时改为重写 expected.json
+fn check_case(case: &GoldenCase) -> Result<()> {
+    let actual = run_case(case)?;
+    let path = case.dir.join("expected.json");
+    if std::env::var_os(UPDATE_ENV).is_some() {
+        std::fs::write(&path, serde_json::to_string_pretty(&actual)? + "\n")?;
+        return Ok(());
+    }
+
+    let expected = std::fs::read_to_string(&path).with_context(|| {
+        format!(
+            "读取 {} 失败（可设置 {}=1 生成）",
+            path.display(),
+            UPDATE_ENV
+        )
+    })?;
+    let expected: Value = serde_json::from_str(&expected)?;
+    let (expected, actual) = (normalize(expected)?, normalize(actual)?);
+    if expected != actual {
+        return Err(anyhow!(
+            "输出与预期不一致\n预期: {}\n实际: {}",
+            serde_json::to_string_pretty(&expected)?,
+            serde_json::to_string_pretty(&actual)?
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_golden_cases() {
+    let names = case_names().unwrap();
+    assert!(!names.is_empty(), "{} 下没有用例", golden_dir().display());
+
+    let failures: Vec<String> = names
+        .iter()
+        .filter_map(|name| {
+            load_case(name)
+                .and_then(|case| check_case(&case))
+                .err()
+                .map(|e| format!("[{}] {:#}", name, e))
+        })
+        .collect();
+    assert!(
+        failures.is_empty(),
+        "{} 个黄金用例失败（确认为预期变化时设置 {}=1 重新生成）：\n{}",
+        failures.len(),
+        UPDATE_ENV,
+        failures.join("\n\n")
+    );
+}
@@ -0,0 +1,164 @@
+// 空闲会话识别 - 会话中没有有意义的活动（离开、锁屏、黑屏）时直接给出空闲总结
+//
+// 本地启发式：采样帧中绝大多数为黑屏或与前一帧几乎相同，即认为无人操作，不调用模型。
+// 只看采样帧，长时间静止阅读同一页面也可能被判为空闲，因此阈值偏保守
+
+use super::plugin::{ActivityCategory, ActivityTag, SessionSummary};
+use crate::video::frame_hash::{self, FrameHashAlgorithm};
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+/// 空闲总结的标题
+pub const IDLE_TITLE: &str = "无有效活动";
+
+/// 空闲总结的默认摘要
+pub const IDLE_SUMMARY: &str =
+    "这段时间没有检测到有意义的屏幕活动（可能处于空闲、离开或锁屏状态）。";
+
+/// 平均亮度低于该值的帧视为黑屏（0-255）
+const BLACK_FRAME_LUMA: u8 = 16;
+
+/// 与前一帧差异哈希距离不超过该值视为同一画面
+const STATIC_FRAME_DISTANCE: u32 = 1;
+
+/// 空闲帧占比达到该值时判定为空闲会话
+const MIN_IDLE_RATIO: f32 = 0.95;
+
+/// 至少需要的帧数，帧数更少时不做判断
+const MIN_FRAMES: usize = 3;
+
+/// 模型只给出空闲标签且置信度达到该值时视为空闲会话
+const MIN_IDLE_TAG_CONFIDENCE: f32 = 0.9;
+
+/// 单帧特征：平均亮度与差异哈希
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameSignal {
+    pub luma: u8,
+    pub hash: u64,
+}
+
+impl FrameSignal {
+    pub fn from_image(image: &DynamicImage) -> Self {
+        let small = image
+            .grayscale()
+            .resize_exact(32, 32, FilterType::Triangle)
+            .to_luma8();
+        let total: u64 = small.pixels().map(|p| u64::from(p[0])).sum();
+        Self {
+            luma: (total / (32 * 32)) as u8,
+            hash: frame_hash::hash_image(image, FrameHashAlgorithm::DHash),
+        }
+    }
+
+    /// 读取帧（支持帧包）并计算特征（阻塞调用）；读取或解码失败时返回 None
+    pub fn from_frame(path: &str) -> Option<Self> {
+        let bytes = crate::storage::frame_pack::read_frame_blocking(path).ok()?;
+        let image = image::load_from_memory(&bytes).ok()?;
+        Some(Self::from_image(&image))
+    }
+}
+
+/// 按帧特征判断是否为空闲会话
+///
+/// 黑屏帧、或与前一帧几乎相同的帧计为空闲帧；无法读取的帧不参与统计
+pub fn is_idle(signals: &[Option<FrameSignal>]) -> bool {
+    let signals: Vec<FrameSignal> = signals.iter().flatten().copied().collect();
+    if signals.len() < MIN_FRAMES {
+        return false;
+    }
+    let idle = signals
+        .iter()
+        .enumerate()
+        .filter(|(i, signal)| {
+            signal.luma < BLACK_FRAME_LUMA
+                || i.checked_sub(1).is_some_and(|prev| {
+                    frame_hash::distance(signals[prev].hash, signal.hash) <= STATIC_FRAME_DISTANCE
+                })
+        })
+        .count();
+    idle as f32 / signals.len() as f32 >= MIN_IDLE_RATIO
+}
+
+/// 读取帧并判断是否为空闲会话（阻塞调用）
+pub fn detect_idle(frames: &[String]) -> bool {
+    let signals: Vec<Option<FrameSignal>> =
+        frames.iter().map(|f| FrameSignal::from_frame(f)).collect();
+    is_idle(&signals)
+}
+
+impl SessionSummary {
+    /// 空闲总结：标注 idle 并只带一个高置信度的空闲标签，界面可折叠显示
+    pub fn idle(summary: Option<String>) -> Self {
+        Self {
+            title: IDLE_TITLE.to_string(),
+            summary: summary
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| IDLE_SUMMARY.to_string()),
+            tags: vec![ActivityTag {
+                category: ActivityCategory::Idle,
+                confidence: 1.0,
+                keywords: vec![],
+                subcategories: Vec::new(),
+                time_ranges: Vec::new(),
+            }],
+            idle: true,
+            ..Default::default()
+        }
+    }
+
+    /// 标签只有空闲类别且置信度足够高
+    pub fn has_only_idle_tags(&self) -> bool {
+        !self.tags.is_empty()
+            && self.tags.iter().all(|tag| {
+                tag.category == ActivityCategory::Idle && tag.confidence >= MIN_IDLE_TAG_CONFIDENCE
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn image(f: impl Fn(u32, u32) -> u8) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(160, 120, |x, y| {
+            let v = f(x, y);
+            Rgb([v, v, v])
+        }))
+    }
+
+    #[test]
+    fn test_black_and_static_frames_are_idle() {
+        let black = FrameSignal::from_image(&image(|_, _| 3));
+        assert!(black.luma < BLACK_FRAME_LUMA);
+        let desktop = FrameSignal::from_image(&image(|x, y| ((x + y) % 200) as u8 + 40));
+
+        // 全部黑屏
+        assert!(is_idle(&[Some(black); 10]));
+        // 锁屏前一帧桌面，之后画面一直不变
+        let mut frames = vec![Some(desktop); 30];
+        frames.push(None);
+        assert!(is_idle(&frames));
+        // 帧数过少不做判断
+        assert!(!is_idle(&[Some(black), Some(black)]));
+    }
+
+    #[test]
+    fn test_changing_frames_are_not_idle() {
+        // 明暗方向交替的渐变，相邻两帧的差异哈希完全不同
+        let rising = FrameSignal::from_image(&image(|x, _| 40 + x as u8));
+        let falling = FrameSignal::from_image(&image(|x, _| 200 - x as u8));
+        let frames: Vec<Option<FrameSignal>> = (0..10)
+            .map(|i| Some(if i % 2 == 0 { rising } else { falling }))
+            .collect();
+        assert!(!is_idle(&frames));
+
+        let summary = SessionSummary::idle(Some("  ".to_string()));
+        assert!(summary.idle);
+        assert_eq!(summary.title, IDLE_TITLE);
+        assert_eq!(summary.summary, IDLE_SUMMARY);
+        assert_eq!(summary.tags[0].category, ActivityCategory::Idle);
+        assert!(summary.validate().is_ok());
+    }
+}
@@ -0,0 +1,276 @@
+// JSON 修复 - 严格解析失败后修复模型输出中常见的 JSON 错误
+//
+// 只处理几类确定性的问题：尾随逗号、单引号字符串、未加引号的键、输出被截断（未闭合的字符串与括号）。
+// 修复结果仍需调用方按目标结构校验，修复不保证语义正确
+
+/// 容器类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Container {
+    /// expect_key：当前位置应为键（刚读到 `{` 或 `,`）
+    Object {
+        expect_key: bool,
+    },
+    Array,
+}
+
+/// 修复常见的 JSON 错误；括号不匹配等无法修复的情况返回 None
+///
+/// 返回的文本不一定是合法 JSON（例如截断在字面量中间），调用方仍需解析
+pub fn repair(input: &str) -> Option<String> {
+    let chars: Vec<char> = input.trim().chars().collect();
+    let mut out = String::with_capacity(input.len() + 16);
+    let mut stack: Vec<Container> = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if let Some(q) = quote {
+            match c {
+                '\\' => {
+                    // 截断在转义符上时丢弃转义符
+                    if let Some(&next) = chars.get(i + 1) {
+                        if q == '\'' && next == '\'' {
+                            out.push('\'');
+                        } else {
+                            out.push('\\');
+                            out.push(next);
+                        }
+                    }
+                    i += 2;
+                    continue;
+                }
+                c if c == q => {
+                    out.push('"');
+                    quote = None;
+                }
+                '"' => out.push_str("\\\""),
+                '\n' => out.push_str("\\n"),
+                _ => out.push(c),
+            }
+            i += 1;
+            continue;
+        }
+
+        match c {
+            '"' | '\'' => {
+                quote = Some(c);
+                out.push('"');
+            }
+            '{' => {
+                stack.push(Container::Object { expect_key: true });
+                out.push(c);
+            }
+            '[' => {
+                stack.push(Container::Array);
+                out.push(c);
+            }
+            '}' | ']' => {
+                let expected = if c == '}' {
+                    matches!(stack.last(), Some(Container::Object { .. }))
+                } else {
+                    matches!(stack.last(), Some(Container::Array))
+                };
+                if !expected {
+                    return None;
+                }
+                stack.pop();
+                trim_trailing_comma(&mut out);
+                out.push(c);
+            }
+            ',' => {
+                set_expect_key(&mut stack, true);
+                out.push(c);
+            }
+            ':' => {
+                set_expect_key(&mut stack, false);
+                out.push(c);
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let start = i;
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '$' | '-'))
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                // 键位置上的裸词补上引号；值位置的 true/false/null 原样保留
+                if matches!(stack.last(), Some(Container::Object { expect_key: true })) {
+                    out.push('"');
+                    out.push_str(&word);
+                    out.push('"');
+                } else {
+                    out.push_str(&word);
+                }
+                continue;
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+
+    // 输出被截断：闭合字符串，补齐缺失的值，再按嵌套顺序闭合括号
+    if quote.is_some() {
+        out.push('"');
+    }
+    let trimmed_len = out.trim_end().len();
+    out.truncate(trimmed_len);
+    if out.ends_with(',') {
+        out.pop();
+    } else if out.ends_with(':') {
+        out.push_str("null");
+    } else if out.ends_with('"')
+        && matches!(stack.last(), Some(Container::Object { expect_key: true }))
+    {
+        // 截断在键上
+        out.push_str(":null");
+    }
+    while let Some(container) = stack.pop() {
+        out.push(match container {
+            Container::Object { .. } => '}',
+            Container::Array => ']',
+        });
+    }
+    Some(out)
+}
+
+fn set_expect_key(stack: &mut [Container], value: bool) {
+    if let Some(Container::Object { expect_key }) = stack.last_mut() {
+        *expect_key = value;
+    }
+}
+
+/// 去掉末尾（忽略空白）的逗号
+fn trim_trailing_comma(out: &mut String) {
+    let trimmed = out.trim_end();
+    if trimmed.ends_with(',') {
+        let len = trimmed.len() - 1;
+        out.truncate(len);
+    }
+}
+
+/// 按出现顺序切出文本中完整的顶层 JSON 对象（忽略对象之外的说明文字与 code fence）
+///
+/// 只按括号与字符串配对切分，不校验对象内容；末尾未闭合的对象不计入
+pub fn top_level_objects(input: &str) -> Vec<&str> {
+    let mut objects = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (i, c) in input.char_indices() {
+        if depth == 0 {
+            if c == '{' {
+                depth = 1;
+                start = i;
+            }
+            continue;
+        }
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    objects.push(&input[start..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    objects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    /// 严格解析失败、修复后解析为期望值
+    fn assert_repairs(input: &str, expected: Value) {
+        assert!(
+            serde_json::from_str::<Value>(input).is_err(),
+            "严格解析应失败: {input}"
+        );
+        let repaired = repair(input).unwrap();
+        let value: Value = serde_json::from_str(&repaired)
+            .unwrap_or_else(|e| panic!("修复结果仍不合法: {repaired} ({e})"));
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn test_trailing_commas() {
+        assert_repairs(
+            r#"{"title": "编码", "tags": ["rust", "json",], }"#,
+            json!({"title": "编码", "tags": ["rust", "json"]}),
+        );
+    }
+
+    #[test]
+    fn test_single_quotes() {
+        assert_repairs(
+            r#"{'title': '阅读 "Rust" 文档', 'summary': 'it\'s fine'}"#,
+            json!({"title": "阅读 \"Rust\" 文档", "summary": "it's fine"}),
+        );
+    }
+
+    #[test]
+    fn test_unquoted_keys() {
+        assert_repairs(
+            r#"{title: "编码", focus_score: 80, done: true, key_moments: [{time: "01:00"}]}"#,
+            json!({"title": "编码", "focus_score": 80, "done": true, "key_moments": [{"time": "01:00"}]}),
+        );
+    }
+
+    #[test]
+    fn test_truncated_output() {
+        assert_repairs(
+            r#"{"title": "编码", "tags": [{"category": "work", "keywords": ["ru"#,
+            json!({"title": "编码", "tags": [{"category": "work", "keywords": ["ru"]}]}),
+        );
+        assert_repairs(
+            r#"{"title": "编码", "summary":"#,
+            json!({"title": "编码", "summary": null}),
+        );
+        assert_repairs(
+            r#"{"title": "编码", "summ"#,
+            json!({"title": "编码", "summ": null}),
+        );
+    }
+
+    #[test]
+    fn test_unrepairable_input() {
+        assert_eq!(repair(r#"{"tags": [1, 2}"#), None);
+        // 截断在字面量中间：修复结果仍不合法，由调用方拒绝
+        let repaired = repair(r#"{"done": tr"#).unwrap();
+        assert!(serde_json::from_str::<Value>(&repaired).is_err());
+    }
+
+    #[test]
+    fn test_top_level_objects() {
+        let text = r#"思考：{"thought": "括号 } 在字符串中", "nested": {"a": 1}}
+```json
+{"title": "编码"}
+```
+{"title": "截断"#;
+        assert_eq!(
+            top_level_objects(text),
+            vec![
+                r#"{"thought": "括号 } 在字符串中", "nested": {"a": 1}}"#,
+                r#"{"title": "编码"}"#,
+            ]
+        );
+        assert!(top_level_objects("没有 JSON").is_empty());
+    }
+}
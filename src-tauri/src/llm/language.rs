@@ -0,0 +1,151 @@
+// 摘要语言检测 - 按文字系统统计判断标题/摘要的语言
+
+/// 置信度低于该值时视为无法判断
+const MIN_CONFIDENCE: f32 = 0.6;
+/// 可计数单元（汉字/假名/谚文按字，拉丁/西里尔按词）少于该值时不做判断
+const MIN_UNITS: usize = 3;
+
+/// 检测结果
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DetectedLanguage {
+    /// ISO 639-1 语言代码
+    pub code: &'static str,
+    /// 0-1，主导文字系统在全部计数单元中的占比
+    pub confidence: f32,
+}
+
+#[derive(Default)]
+struct ScriptCounts {
+    han: usize,
+    kana: usize,
+    hangul: usize,
+    latin_words: usize,
+    cyrillic_words: usize,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum WordScript {
+    Latin,
+    Cyrillic,
+}
+
+fn word_script(c: char) -> Option<WordScript> {
+    if c.is_ascii_alphabetic() || ('\u{00C0}'..='\u{024F}').contains(&c) {
+        Some(WordScript::Latin)
+    } else if ('\u{0400}'..='\u{04FF}').contains(&c) {
+        Some(WordScript::Cyrillic)
+    } else {
+        None
+    }
+}
+
+fn count_scripts(text: &str) -> ScriptCounts {
+    let mut counts = ScriptCounts::default();
+    let mut current_word: Option<WordScript> = None;
+
+    for c in text.chars() {
+        match c {
+            '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' => counts.han += 1,
+            '\u{3040}'..='\u{30FF}' => counts.kana += 1,
+            '\u{AC00}'..='\u{D7AF}' | '\u{1100}'..='\u{11FF}' => counts.hangul += 1,
+            _ => {}
+        }
+
+        // 拉丁/西里尔字母按连续字母组成的词计数，避免英文应用名压过中文正文
+        let script = word_script(c);
+        if script.is_some() && script != current_word {
+            match script {
+                Some(WordScript::Latin) => counts.latin_words += 1,
+                Some(WordScript::Cyrillic) => counts.cyrillic_words += 1,
+                None => {}
+            }
+        }
+        current_word = script;
+    }
+    counts
+}
+
+/// 检测文本语言，文本过短或置信度不足时返回 None
+pub fn detect_language(text: &str) -> Option<DetectedLanguage> {
+    let counts = count_scripts(text);
+    let total =
+        counts.han + counts.kana + counts.hangul + counts.latin_words + counts.cyrillic_words;
+    if total < MIN_UNITS {
+        return None;
+    }
+
+    // 日文混用汉字与假名，假名占比达到一成即判为日文
+    let japanese = if counts.kana > 0 && counts.kana * 10 >= counts.han + counts.kana {
+        counts.han + counts.kana
+    } else {
+        0
+    };
+    let candidates = [
+        ("ja", japanese),
+        ("zh", counts.han),
+        ("ko", counts.hangul),
+        ("en", counts.latin_words),
+        ("ru", counts.cyrillic_words),
+    ];
+    let (code, units) = candidates
+        .iter()
+        .copied()
+        .max_by_key(|(_, units)| *units)
+        .filter(|(_, units)| *units > 0)?;
+
+    let confidence = units as f32 / total as f32;
+    (confidence >= MIN_CONFIDENCE).then_some(DetectedLanguage { code, confidence })
+}
+
+/// 检测语言，置信度不足时使用配置的输出语言
+pub fn resolve_language(text: &str, fallback: Option<&str>) -> Option<String> {
+    detect_language(text)
+        .map(|detected| detected.code.to_string())
+        .or_else(|| {
+            fallback
+                .map(str::trim)
+                .filter(|lang| !lang.is_empty())
+                .map(str::to_string)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_common_languages() {
+        let zh = detect_language("在 VS Code 中编写 Rust 代码并调试接口").unwrap();
+        assert_eq!(zh.code, "zh");
+        assert_eq!(
+            detect_language("Writing Rust code in the editor")
+                .unwrap()
+                .code,
+            "en"
+        );
+        assert_eq!(
+            detect_language("エディタでコードを書いている")
+                .unwrap()
+                .code,
+            "ja"
+        );
+        assert_eq!(detect_language("코드를 작성하는 중").unwrap().code, "ko");
+    }
+
+    #[test]
+    fn test_low_confidence_uses_fallback() {
+        // 过短或中英各半的文本无法判断
+        assert!(detect_language("OK").is_none());
+        assert!(detect_language("周会 sprint review 讨论 demo").is_none());
+
+        assert_eq!(
+            resolve_language("周会 sprint review 讨论 demo", Some("zh")),
+            Some("zh".to_string())
+        );
+        assert_eq!(resolve_language("OK", None), None);
+        assert_eq!(
+            resolve_language("Reviewing pull requests", Some("zh")),
+            Some("en".to_string())
+        );
+    }
+}
@@ -0,0 +1,300 @@
+// Provider 健康指标 - 记录每个 provider 最近的调用结果，供状态接口查询
+//
+// 指标保存在共享内存中，查询不经过 LLM Actor，分析进行中也能立即返回；
+// 各 provider 的熔断器随指标一起保存，调用结果同时用于熔断判断
+
+use super::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitOpen, CircuitState};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 已注册的 provider（与配置中的 provider 名称一致）
+pub const REGISTERED_PROVIDERS: [&str; 4] = ["qwen", "claude", "codex", "ollama"];
+
+/// 滚动窗口保留的最近调用数
+const METRICS_WINDOW: usize = 50;
+
+/// 单次调用样本
+#[derive(Debug, Clone, Copy)]
+struct CallSample {
+    latency_ms: u64,
+    success: bool,
+}
+
+/// 最近 N 次调用的滚动窗口
+#[derive(Debug, Clone)]
+pub struct RollingWindow {
+    samples: VecDeque<CallSample>,
+    capacity: usize,
+}
+
+impl RollingWindow {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn push(&mut self, latency: Duration, success: bool) {
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(CallSample {
+            latency_ms: latency.as_millis() as u64,
+            success,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// 窗口内调用的平均延迟（毫秒），无样本时为 None
+    pub fn average_latency_ms(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let total: u64 = self.samples.iter().map(|s| s.latency_ms).sum();
+        Some(total as f64 / self.samples.len() as f64)
+    }
+
+    /// 窗口内调用的成功率（0-1），无样本时为 None
+    pub fn success_rate(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let succeeded = self.samples.iter().filter(|s| s.success).count();
+        Some(succeeded as f64 / self.samples.len() as f64)
+    }
+}
+
+impl Default for RollingWindow {
+    fn default() -> Self {
+        Self::new(METRICS_WINDOW)
+    }
+}
+
+#[derive(Debug, Default)]
+struct ProviderStats {
+    configured: bool,
+    last_success_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    last_error_at: Option<DateTime<Utc>>,
+    window: RollingWindow,
+    breaker: CircuitBreaker,
+}
+
+#[derive(Debug, Default)]
+struct MetricsState {
+    active: Option<String>,
+    breaker_config: CircuitBreakerConfig,
+    providers: HashMap<String, ProviderStats>,
+}
+
+/// 单个 provider 的健康状态
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderStatus {
+    pub provider: String,
+    /// 是否为当前使用的 provider
+    pub active: bool,
+    pub configured: bool,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+    /// 最近调用的平均延迟（毫秒）
+    pub avg_latency_ms: Option<f64>,
+    /// 最近调用的成功率（0-1）
+    pub success_rate: Option<f64>,
+    /// 参与统计的调用数
+    pub recent_calls: usize,
+    /// 熔断状态
+    pub circuit: CircuitState,
+    /// 熔断中的剩余冷却时间（秒）
+    pub circuit_retry_after_secs: Option<u64>,
+}
+
+/// Provider 指标（可克隆，所有副本共享同一份数据）
+#[derive(Clone, Default)]
+pub struct ProviderMetrics {
+    state: Arc<Mutex<MetricsState>>,
+}
+
+impl ProviderMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次 provider 调用
+    pub fn record(&self, provider: &str, latency: Duration, error: Option<String>) {
+        let mut state = self.lock();
+        let config = state.breaker_config.clone();
+        let stats = state.providers.entry(provider.to_string()).or_default();
+        stats.window.push(latency, error.is_none());
+        stats
+            .breaker
+            .record(&config, error.is_none(), Instant::now());
+        match error {
+            None => stats.last_success_at = Some(Utc::now()),
+            Some(error) => {
+                stats.last_error = Some(error);
+                stats.last_error_at = Some(Utc::now());
+            }
+        }
+    }
+
+    /// 更新 provider 的配置状态
+    pub fn set_configured(&self, provider: &str, configured: bool) {
+        let mut state = self.lock();
+        state
+            .providers
+            .entry(provider.to_string())
+            .or_default()
+            .configured = configured;
+    }
+
+    /// 调用 provider 前检查熔断状态，熔断中返回 CircuitOpen
+    pub fn try_acquire(&self, provider: &str) -> Result<(), CircuitOpen> {
+        let mut state = self.lock();
+        let config = state.breaker_config.clone();
+        let stats = state.providers.entry(provider.to_string()).or_default();
+        stats
+            .breaker
+            .try_acquire(&config, Instant::now())
+            .map_err(|remaining| CircuitOpen {
+                provider: provider.to_string(),
+                retry_after_secs: remaining.as_secs_f64().ceil() as u64,
+            })
+    }
+
+    /// 更新熔断配置（已有的熔断状态保留）
+    pub fn set_circuit_breaker(&self, config: CircuitBreakerConfig) {
+        self.lock().breaker_config = config;
+    }
+
+    /// 设置当前使用的 provider
+    pub fn set_active(&self, provider: &str) {
+        self.lock().active = Some(provider.to_string());
+    }
+
+    /// 所有已注册 provider 的健康状态
+    pub fn provider_status(&self) -> Vec<ProviderStatus> {
+        let state = self.lock();
+        let now = Instant::now();
+        REGISTERED_PROVIDERS
+            .iter()
+            .map(|name| {
+                let stats = state.providers.get(*name);
+                ProviderStatus {
+                    provider: name.to_string(),
+                    active: state.active.as_deref() == Some(*name),
+                    configured: stats.is_some_and(|s| s.configured),
+                    last_success_at: stats.and_then(|s| s.last_success_at),
+                    last_error: stats.and_then(|s| s.last_error.clone()),
+                    last_error_at: stats.and_then(|s| s.last_error_at),
+                    avg_latency_ms: stats.and_then(|s| s.window.average_latency_ms()),
+                    success_rate: stats.and_then(|s| s.window.success_rate()),
+                    recent_calls: stats.map_or(0, |s| s.window.len()),
+                    circuit: stats.map_or(CircuitState::Closed, |s| s.breaker.state(now)),
+                    circuit_retry_after_secs: stats
+                        .and_then(|s| s.breaker.retry_after(now))
+                        .map(|remaining| remaining.as_secs_f64().ceil() as u64),
+                }
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MetricsState> {
+        // 持锁期间不会 panic，锁中毒时沿用内部数据
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rolling_average_evicts_oldest_samples() {
+        let mut window = RollingWindow::new(3);
+        assert_eq!(window.average_latency_ms(), None);
+        assert_eq!(window.success_rate(), None);
+
+        window.push(Duration::from_millis(100), true);
+        window.push(Duration::from_millis(200), false);
+        assert_eq!(window.average_latency_ms(), Some(150.0));
+        assert_eq!(window.success_rate(), Some(0.5));
+
+        // 超出容量后最早的样本被淘汰：(200 + 300 + 700) / 3
+        window.push(Duration::from_millis(300), true);
+        window.push(Duration::from_millis(700), true);
+        assert_eq!(window.len(), 3);
+        assert_eq!(window.average_latency_ms(), Some(400.0));
+        assert_eq!(window.success_rate(), Some(2.0 / 3.0));
+    }
+
+    #[test]
+    fn test_provider_status_reports_every_registered_provider() {
+        let metrics = ProviderMetrics::new();
+        metrics.set_active("ollama");
+        metrics.set_configured("ollama", true);
+        metrics.record("ollama", Duration::from_millis(800), None);
+        metrics.record(
+            "ollama",
+            Duration::from_millis(1200),
+            Some("超时".to_string()),
+        );
+
+        let status = metrics.provider_status();
+        assert_eq!(status.len(), REGISTERED_PROVIDERS.len());
+
+        let ollama = status.iter().find(|s| s.provider == "ollama").unwrap();
+        assert!(ollama.active && ollama.configured);
+        assert!(ollama.last_success_at.is_some());
+        assert_eq!(ollama.last_error.as_deref(), Some("超时"));
+        assert_eq!(ollama.avg_latency_ms, Some(1000.0));
+        assert_eq!(ollama.success_rate, Some(0.5));
+
+        let qwen = status.iter().find(|s| s.provider == "qwen").unwrap();
+        assert!(!qwen.active && !qwen.configured);
+        assert_eq!(qwen.recent_calls, 0);
+        assert_eq!(qwen.circuit, CircuitState::Closed);
+    }
+
+    #[test]
+    fn test_consecutive_failures_open_circuit_in_status() {
+        let metrics = ProviderMetrics::new();
+        metrics.set_circuit_breaker(CircuitBreakerConfig {
+            failure_threshold: 2,
+            window_secs: 60,
+            cooldown_secs: 30,
+        });
+        assert!(metrics.try_acquire("ollama").is_ok());
+        for _ in 0..2 {
+            metrics.record(
+                "ollama",
+                Duration::from_millis(10),
+                Some("连接失败".to_string()),
+            );
+        }
+
+        let err = metrics.try_acquire("ollama").unwrap_err();
+        assert_eq!(err.provider, "ollama");
+        assert!(err.retry_after_secs > 0 && err.retry_after_secs <= 30);
+
+        let status = metrics.provider_status();
+        let ollama = status.iter().find(|s| s.provider == "ollama").unwrap();
+        assert_eq!(ollama.circuit, CircuitState::Open);
+        assert!(ollama.circuit_retry_after_secs.is_some());
+        // 其他 provider 不受影响，可以立即切换
+        assert!(metrics.try_acquire("qwen").is_ok());
+    }
+}
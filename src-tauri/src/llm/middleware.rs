@@ -0,0 +1,1121 @@
+// Provider 中间件 - 在 LLMProvider 外层叠加重试、限流、缓存等通用能力
+//
+// 中间件拦截所有调用模型的请求（帧分析、候选总结、文本分析、字段重生成、视频分段、时间线），
+// 其余调用原样转发给内层 provider，provider 本身只负责与模型通信。
+// LLMManager 按配置（LLMConfig.middleware）为当前 provider 组装中间件。
+//
+// 叠加顺序：ProviderBuilder 中先添加的中间件位于最外层，请求由外向内经过，结果由内向外返回。
+// LLMManager 使用的顺序为 cache → retry → empty_response → downscale → rate_limit → reconnect：
+// - 缓存命中时直接返回，不会触发重试与限流；
+// - 每次重试（包括附加提醒的空内容重试、缩小图片的重试）都重新经过限流，重试不会突破调用频率上限；
+// - 断线重连位于最内层，统计每一次实际发出的请求；
+// - 只有最终成功的结果会被缓存。
+//
+// 重试类中间件无法修改 provider 内部的提示词，改为通过 RetryHints 提示内层 provider
+// （如附加提醒、缩小图片），provider 发送请求时用 retry_hints() 读取。
+
+use super::ollama::{OllamaImageTooLarge, OllamaUnreachable};
+use super::plugin::*;
+use crate::video::frame_hash::{self, FrameHashConfig};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+/// 空内容最多附加提醒重试的次数
+pub const MAX_EMPTY_RESPONSE_RETRIES: u32 = 3;
+
+/// 中间件配置，LLMManager 据此为 provider 组装中间件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MiddlewareConfig {
+    /// 调用失败后的重试次数（0 表示不重试）
+    pub max_retries: u32,
+    /// 首次重试前的等待（毫秒），之后每次翻倍
+    pub retry_backoff_ms: u64,
+    /// 每分钟最多发起的调用次数（0 表示不限流）
+    pub max_calls_per_minute: usize,
+    /// 缓存的帧分析结果数（0 表示不缓存）
+    pub cache_capacity: usize,
+    /// 缓存按帧画面匹配时使用的哈希算法与阈值
+    pub frame_hash: FrameHashConfig,
+    /// 连续多少次无法连接模型服务后重建连接（0 表示不重建）
+    pub reconnect_after_failures: u32,
+}
+
+impl Default for MiddlewareConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            retry_backoff_ms: 1000,
+            max_calls_per_minute: 0,
+            cache_capacity: 0,
+            frame_hash: FrameHashConfig::default(),
+            reconnect_after_failures: 2,
+        }
+    }
+}
+
+/// 中间件拦截的模型调用
+#[derive(Debug, Clone)]
+pub enum LLMRequest {
+    /// 帧分析；context 为 None 时走 analyze_frames
+    Frames {
+        frames: Vec<String>,
+        context: Option<AnalysisContext>,
+    },
+    /// 生成多个候选总结
+    Candidates { frames: Vec<String>, n: usize },
+    /// 纯文本分析
+    Text { content: String },
+    /// 结合已有总结重新生成单个字段
+    Refine {
+        frames: Vec<String>,
+        current: SessionSummary,
+        field: SummaryField,
+    },
+    /// 视频分段
+    Segment { frames: Vec<String>, duration: u32 },
+    /// 生成时间线卡片
+    Timeline {
+        segments: Vec<VideoSegment>,
+        previous_cards: Option<Vec<TimelineCard>>,
+    },
+}
+
+/// 模型调用的结果，与 LLMRequest 的变体对应
+#[derive(Debug, Clone)]
+pub enum LLMResponse {
+    /// Frames、Text、Refine 的结果（装箱，避免整个枚举按最大的变体占用空间）
+    Summary(Box<SessionSummary>),
+    /// Candidates 的结果
+    Summaries(Vec<SessionSummary>),
+    /// Segment 的结果
+    Segments(Vec<VideoSegment>),
+    /// Timeline 的结果
+    Cards(Vec<TimelineCard>),
+}
+
+impl LLMResponse {
+    fn mismatch(&self) -> anyhow::Error {
+        anyhow!("中间件返回了与请求不匹配的结果: {:?}", self)
+    }
+
+    pub fn into_summary(self) -> Result<SessionSummary> {
+        match self {
+            Self::Summary(summary) => Ok(*summary),
+            other => Err(other.mismatch()),
+        }
+    }
+
+    pub fn into_summaries(self) -> Result<Vec<SessionSummary>> {
+        match self {
+            Self::Summaries(summaries) => Ok(summaries),
+            other => Err(other.mismatch()),
+        }
+    }
+
+    pub fn into_segments(self) -> Result<Vec<VideoSegment>> {
+        match self {
+            Self::Segments(segments) => Ok(segments),
+            other => Err(other.mismatch()),
+        }
+    }
+
+    pub fn into_cards(self) -> Result<Vec<TimelineCard>> {
+        match self {
+            Self::Cards(cards) => Ok(cards),
+            other => Err(other.mismatch()),
+        }
+    }
+}
+
+/// 重试类中间件对内层 provider 的提示
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RetryHints {
+    /// 上一次模型返回了空内容：在提示词末尾附加提醒
+    pub nudge: bool,
+    /// 上一次请求的图片被服务端以过大拒绝：将图片缩小后再发送
+    pub downscale: bool,
+}
+
+tokio::task_local! {
+    static RETRY_HINTS: RetryHints;
+}
+
+/// 当前请求的重试提示；不在重试中时为默认值
+pub fn retry_hints() -> RetryHints {
+    RETRY_HINTS.try_with(|hints| *hints).unwrap_or_default()
+}
+
+/// 带着重试提示执行请求
+async fn with_hints<F: std::future::Future>(hints: RetryHints, request: F) -> F::Output {
+    RETRY_HINTS.scope(hints, request).await
+}
+
+/// 调用链中的下一环（内层中间件或最终的 provider）
+#[derive(Clone, Copy)]
+pub struct Next<'a> {
+    middlewares: &'a [Arc<dyn Middleware>],
+    provider: &'a dyn LLMProvider,
+}
+
+impl<'a> Next<'a> {
+    /// 调用链末端的 provider（用于重建连接等不经过中间件的操作）
+    pub fn provider(self) -> &'a dyn LLMProvider {
+        self.provider
+    }
+
+    /// 把请求交给下一环，可多次调用（如重试）
+    pub async fn run(self, request: LLMRequest) -> Result<LLMResponse> {
+        match self.middlewares.split_first() {
+            Some((middleware, rest)) => {
+                let next = Next {
+                    middlewares: rest,
+                    provider: self.provider,
+                };
+                middleware.call(request, next).await
+            }
+            None => call_provider(self.provider, request).await,
+        }
+    }
+}
+
+async fn call_provider(provider: &dyn LLMProvider, request: LLMRequest) -> Result<LLMResponse> {
+    Ok(match request {
+        LLMRequest::Frames {
+            frames,
+            context: Some(context),
+        } => LLMResponse::Summary(Box::new(
+            provider
+                .analyze_frames_with_context(frames, context)
+                .await?,
+        )),
+        LLMRequest::Frames {
+            frames,
+            context: None,
+        } => LLMResponse::Summary(Box::new(provider.analyze_frames(frames).await?)),
+        LLMRequest::Candidates { frames, n } => {
+            LLMResponse::Summaries(provider.analyze_frames_n(frames, n).await?)
+        }
+        LLMRequest::Text { content } => {
+            LLMResponse::Summary(Box::new(provider.analyze_text(content).await?))
+        }
+        LLMRequest::Refine {
+            frames,
+            current,
+            field,
+        } => LLMResponse::Summary(Box::new(
+            provider.refine_field(frames, current, field).await?,
+        )),
+        LLMRequest::Segment { frames, duration } => {
+            LLMResponse::Segments(provider.segment_video(frames, duration).await?)
+        }
+        LLMRequest::Timeline {
+            segments,
+            previous_cards,
+        } => LLMResponse::Cards(provider.generate_timeline(segments, previous_cards).await?),
+    })
+}
+
+/// Provider 中间件
+#[async_trait]
+pub trait Middleware: Send + Sync + 'static {
+    /// 处理一次模型调用：可在调用 next 前后做预处理/后处理，也可以不调用 next 直接返回
+    async fn call(&self, request: LLMRequest, next: Next<'_>) -> Result<LLMResponse>;
+}
+
+/// 按顺序叠加中间件，先添加的位于最外层
+#[derive(Default)]
+pub struct ProviderBuilder {
+    middlewares: Vec<Arc<dyn Middleware>>,
+}
+
+impl ProviderBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在已添加的中间件内侧再加一层
+    pub fn layer(mut self, middleware: impl Middleware) -> Self {
+        self.middlewares.push(Arc::new(middleware));
+        self
+    }
+
+    /// 包装 provider；未添加中间件时原样返回
+    pub fn build(self, provider: Box<dyn LLMProvider>) -> Box<dyn LLMProvider> {
+        if self.middlewares.is_empty() {
+            return provider;
+        }
+        Box::new(self.wrap(provider))
+    }
+
+    /// 包装为 Layered，之后可用 set_layers 替换中间件
+    pub fn wrap(self, provider: Box<dyn LLMProvider>) -> Layered {
+        Layered {
+            middlewares: self.middlewares,
+            inner: provider,
+        }
+    }
+}
+
+/// 叠加了中间件的 provider：模型调用经过中间件链，其余调用原样转发
+pub struct Layered {
+    middlewares: Vec<Arc<dyn Middleware>>,
+    inner: Box<dyn LLMProvider>,
+}
+
+impl Layered {
+    /// 包装 provider，暂不添加中间件
+    pub fn new(provider: Box<dyn LLMProvider>) -> Self {
+        ProviderBuilder::new().wrap(provider)
+    }
+
+    /// 替换全部中间件（原中间件的缓存、限流窗口等状态随之丢弃）
+    pub fn set_layers(&mut self, layers: ProviderBuilder) {
+        self.middlewares = layers.middlewares;
+    }
+
+    async fn run(&self, request: LLMRequest) -> Result<LLMResponse> {
+        Next {
+            middlewares: &self.middlewares,
+            provider: self.inner.as_ref(),
+        }
+        .run(request)
+        .await
+    }
+}
+
+#[async_trait]
+impl LLMProvider for Layered {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        // 透传给内部提供商，保证按具体类型向下转型的逻辑不受包装影响
+        self.inner.as_any()
+    }
+
+    async fn analyze_frames(&self, frames: Vec<String>) -> Result<SessionSummary> {
+        self.run(LLMRequest::Frames {
+            frames,
+            context: None,
+        })
+        .await?
+        .into_summary()
+    }
+
+    async fn analyze_frames_with_context(
+        &self,
+        frames: Vec<String>,
+        context: AnalysisContext,
+    ) -> Result<SessionSummary> {
+        self.run(LLMRequest::Frames {
+            frames,
+            context: Some(context),
+        })
+        .await?
+        .into_summary()
+    }
+
+    async fn analyze_frames_n(&self, frames: Vec<String>, n: usize) -> Result<Vec<SessionSummary>> {
+        self.run(LLMRequest::Candidates { frames, n })
+            .await?
+            .into_summaries()
+    }
+
+    async fn analyze_text(&self, content: String) -> Result<SessionSummary> {
+        self.run(LLMRequest::Text { content }).await?.into_summary()
+    }
+
+    async fn refine_field(
+        &self,
+        frames: Vec<String>,
+        current: SessionSummary,
+        field: SummaryField,
+    ) -> Result<SessionSummary> {
+        self.run(LLMRequest::Refine {
+            frames,
+            current,
+            field,
+        })
+        .await?
+        .into_summary()
+    }
+
+    async fn segment_video(&self, frames: Vec<String>, duration: u32) -> Result<Vec<VideoSegment>> {
+        self.run(LLMRequest::Segment { frames, duration })
+            .await?
+            .into_segments()
+    }
+
+    async fn generate_timeline(
+        &self,
+        segments: Vec<VideoSegment>,
+        previous_cards: Option<Vec<TimelineCard>>,
+    ) -> Result<Vec<TimelineCard>> {
+        self.run(LLMRequest::Timeline {
+            segments,
+            previous_cards,
+        })
+        .await?
+        .into_cards()
+    }
+
+    async fn generate_day_summary(&self, date: &str, sessions: &[SessionBrief]) -> Result<String> {
+        self.inner.generate_day_summary(date, sessions).await
+    }
+
+    fn set_session_window(&mut self, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) {
+        self.inner.set_session_window(start, end);
+    }
+
+    async fn on_start(&self) -> Result<()> {
+        self.inner.on_start().await
+    }
+
+    async fn on_stop(&self) -> Result<()> {
+        self.inner.on_stop().await
+    }
+
+    async fn reconnect(&self) -> Result<()> {
+        self.inner.reconnect().await
+    }
+
+    fn set_progress_sender(&mut self, sender: Option<ProgressSender>) {
+        self.inner.set_progress_sender(sender);
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn configure(&mut self, config: serde_json::Value) -> Result<()> {
+        self.inner.configure(config)
+    }
+
+    fn is_configured(&self) -> bool {
+        self.inner.is_configured()
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn refresh_capabilities(&mut self) -> Result<()> {
+        self.inner.refresh_capabilities().await
+    }
+
+    fn estimate_cost(&self, frames: usize) -> Option<CostEstimate> {
+        self.inner.estimate_cost(frames)
+    }
+
+    fn last_llm_call_id(&self, call_type: &str) -> Option<i64> {
+        self.inner.last_llm_call_id(call_type)
+    }
+
+    fn last_sampling_seed(&self) -> Option<u64> {
+        self.inner.last_sampling_seed()
+    }
+}
+
+/// 空内容重试：provider 返回 EmptyResponse 时带着附加提醒的提示重新请求
+///
+/// HTTP 成功但内容为空不是格式错误，重试后仍为空时返回包含总请求次数的 EmptyResponse
+pub struct EmptyResponseLayer {
+    retries: u32,
+}
+
+impl EmptyResponseLayer {
+    /// retries 最多为 MAX_EMPTY_RESPONSE_RETRIES
+    pub fn new(retries: u32) -> Self {
+        Self {
+            retries: retries.min(MAX_EMPTY_RESPONSE_RETRIES),
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for EmptyResponseLayer {
+    async fn call(&self, request: LLMRequest, next: Next<'_>) -> Result<LLMResponse> {
+        let attempts = self.retries + 1;
+        let mut hints = retry_hints();
+        for attempt in 0..attempts {
+            match with_hints(hints, next.run(request.clone())).await {
+                Err(e) if e.downcast_ref::<EmptyResponse>().is_some() => {
+                    warn!("模型返回空内容（第 {}/{} 次）", attempt + 1, attempts);
+                    hints.nudge = true;
+                }
+                result => return result,
+            }
+        }
+        Err(EmptyResponse { attempts }.into())
+    }
+}
+
+/// 图片过大重试：provider 返回 OllamaImageTooLarge 时带着缩小图片的提示重新请求一次
+///
+/// 服务端不会指出是哪张图片超限，由 provider 按 retry_hints().downscale 统一缩小全部图片
+pub struct DownscaleLayer;
+
+impl DownscaleLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for DownscaleLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Middleware for DownscaleLayer {
+    async fn call(&self, request: LLMRequest, next: Next<'_>) -> Result<LLMResponse> {
+        let hints = retry_hints();
+        match next.run(request.clone()).await {
+            Err(e) if !hints.downscale && e.downcast_ref::<OllamaImageTooLarge>().is_some() => {
+                warn!("{}，缩小图片后重试一次", e);
+                let hints = RetryHints {
+                    downscale: true,
+                    ..hints
+                };
+                with_hints(hints, next.run(request)).await
+            }
+            result => result,
+        }
+    }
+}
+
+/// 断线重连：连续多次无法连接模型服务时让 provider 重建连接
+///
+/// 切换网络后连接池中的连接可能已失效、服务地址也可能解析到新的 IP，重建后的连接会重新解析主机。
+/// 请求到达服务端（无论成功与否）即清零计数；本次调用仍返回原错误，由外层的重试再次请求
+pub struct ReconnectLayer {
+    threshold: u32,
+    failures: AtomicU32,
+}
+
+impl ReconnectLayer {
+    /// threshold 为触发重建的连续连接失败次数（至少为 1）
+    pub fn new(threshold: u32) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            failures: AtomicU32::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for ReconnectLayer {
+    async fn call(&self, request: LLMRequest, next: Next<'_>) -> Result<LLMResponse> {
+        let result = next.run(request).await;
+        let unreachable =
+            matches!(&result, Err(e) if e.downcast_ref::<OllamaUnreachable>().is_some());
+        if !unreachable {
+            self.failures.store(0, Ordering::Relaxed);
+            return result;
+        }
+        let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= self.threshold {
+            self.failures.store(0, Ordering::Relaxed);
+            match next.provider().reconnect().await {
+                Ok(()) => info!("连续 {} 次无法连接模型服务，已重建连接", failures),
+                Err(e) => warn!("重建连接失败: {}", e),
+            }
+        }
+        result
+    }
+}
+
+/// 失败重试：按指数退避重试，帧数过少等确定性错误不重试
+pub struct RetryLayer {
+    max_retries: u32,
+    backoff: Duration,
+}
+
+impl RetryLayer {
+    /// backoff 为首次重试前的等待时间，之后每次翻倍
+    pub fn new(max_retries: u32, backoff: Duration) -> Self {
+        Self {
+            max_retries,
+            backoff,
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for RetryLayer {
+    async fn call(&self, request: LLMRequest, next: Next<'_>) -> Result<LLMResponse> {
+        let mut attempt = 0;
+        loop {
+            match next.run(request.clone()).await {
+                // 帧数不足或帧文件缺失时重试也无济于事
+                Err(e)
+                    if attempt < self.max_retries
+                        && e.downcast_ref::<TooFewFrames>().is_none()
+                        && e.downcast_ref::<FramesUnavailable>().is_none() =>
+                {
+                    let delay = self.backoff * 2u32.saturating_pow(attempt);
+                    attempt += 1;
+                    warn!(
+                        "调用失败，{:?} 后第 {}/{} 次重试: {}",
+                        delay, attempt, self.max_retries, e
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// 限流：任意 per 时间窗口内最多发起 max_calls 次调用，超出时排队等待
+pub struct RateLimitLayer {
+    max_calls: usize,
+    per: Duration,
+    /// 窗口内各次调用的开始时间（异步锁：等待期间后来者按先来先服务排队）
+    recent: tokio::sync::Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimitLayer {
+    pub fn new(max_calls: usize, per: Duration) -> Self {
+        Self {
+            max_calls: max_calls.max(1),
+            per,
+            recent: tokio::sync::Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// 等待直到可以发起下一次调用，并登记本次调用
+    async fn acquire(&self) {
+        let mut recent = self.recent.lock().await;
+        loop {
+            let now = Instant::now();
+            while recent
+                .front()
+                .is_some_and(|t| now.duration_since(*t) >= self.per)
+            {
+                recent.pop_front();
+            }
+            if recent.len() < self.max_calls {
+                recent.push_back(now);
+                return;
+            }
+            // 窗口已满，等最早的一次调用移出窗口
+            let wait_until = recent[0] + self.per;
+            debug!("调用频率达到上限，等待 {:?}", wait_until - now);
+            tokio::time::sleep_until(wait_until).await;
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for RateLimitLayer {
+    async fn call(&self, request: LLMRequest, next: Next<'_>) -> Result<LLMResponse> {
+        self.acquire().await;
+        next.run(request).await
+    }
+}
+
+/// 缓存：相同帧与上下文的成功结果直接返回，不再调用模型
+///
+/// 帧按解码后像素的感知哈希比较，重新编码、缩放过的同一画面也能命中；无法读取的帧按路径比较。
+/// 超出容量时淘汰最早写入的结果；失败结果不缓存
+pub struct CacheLayer {
+    capacity: usize,
+    frame_hash: FrameHashConfig,
+    entries: Mutex<VecDeque<CacheEntry>>,
+}
+
+/// 单帧的缓存键
+#[derive(Debug, Clone, PartialEq)]
+enum FrameKey {
+    Hash(u64),
+    Path(String),
+}
+
+struct CacheEntry {
+    /// 序列化后的附加上下文，需完全一致
+    context: String,
+    frames: Vec<FrameKey>,
+    summary: SessionSummary,
+}
+
+impl CacheEntry {
+    fn matches(&self, context: &str, frames: &[FrameKey], config: &FrameHashConfig) -> bool {
+        self.context == context
+            && self.frames.len() == frames.len()
+            && self.frames.iter().zip(frames).all(|pair| match pair {
+                (FrameKey::Hash(a), FrameKey::Hash(b)) => config.matches(*a, *b),
+                (a, b) => a == b,
+            })
+    }
+}
+
+impl CacheLayer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            frame_hash: FrameHashConfig::default(),
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// 设置帧哈希算法与匹配阈值
+    pub fn with_frame_hash(mut self, config: FrameHashConfig) -> Self {
+        self.frame_hash = config;
+        self
+    }
+
+    /// 计算每帧的缓存键（读取并解码图片，在阻塞线程中执行）
+    async fn frame_keys(&self, frames: &[String]) -> Result<Vec<FrameKey>> {
+        let frames = frames.to_vec();
+        let algorithm = self.frame_hash.algorithm;
+        let keys = tokio::task::spawn_blocking(move || {
+            frames
+                .into_iter()
+                .map(|path| match frame_hash::hash_frame(&path, algorithm) {
+                    Ok(hash) => FrameKey::Hash(hash),
+                    Err(_) => FrameKey::Path(path),
+                })
+                .collect()
+        })
+        .await?;
+        Ok(keys)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<CacheEntry>> {
+        // 持锁期间不会 panic，锁中毒时沿用内部数据
+        self.entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl Middleware for CacheLayer {
+    async fn call(&self, request: LLMRequest, next: Next<'_>) -> Result<LLMResponse> {
+        // 只缓存帧分析，其余调用原样转发
+        let LLMRequest::Frames {
+            frames: paths,
+            context,
+        } = &request
+        else {
+            return next.run(request).await;
+        };
+        let context = serde_json::to_string(context)?;
+        let frames = self.frame_keys(paths).await?;
+        let cached = self
+            .lock()
+            .iter()
+            .find(|entry| entry.matches(&context, &frames, &self.frame_hash))
+            .map(|entry| entry.summary.clone());
+        if let Some(summary) = cached {
+            debug!("分析结果命中缓存（{} 帧）", paths.len());
+            return Ok(LLMResponse::Summary(Box::new(summary)));
+        }
+
+        let response = next.run(request).await?;
+        if let LLMResponse::Summary(summary) = &response {
+            let mut entries = self.lock();
+            // 并发的相同请求只保留最新的结果
+            entries.retain(|entry| !entry.matches(&context, &frames, &self.frame_hash));
+            if entries.len() >= self.capacity {
+                entries.pop_front();
+            }
+            entries.push_back(CacheEntry {
+                context,
+                frames,
+                summary: summary.as_ref().clone(),
+            });
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 前 failures 次调用失败，之后返回以调用序号为标题的总结
+    struct FlakyProvider {
+        calls: Arc<AtomicUsize>,
+        failures: usize,
+    }
+
+    impl FlakyProvider {
+        fn new(failures: usize) -> (Self, Arc<AtomicUsize>) {
+            let calls = Arc::new(AtomicUsize::new(0));
+            (
+                Self {
+                    calls: calls.clone(),
+                    failures,
+                },
+                calls,
+            )
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for FlakyProvider {
+        fn as_any(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+
+        async fn analyze_frames(&self, _frames: Vec<String>) -> Result<SessionSummary> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call < self.failures {
+                return Err(anyhow!("连接被重置"));
+            }
+            Ok(SessionSummary {
+                title: format!("第 {} 次调用", call + 1),
+                ..Default::default()
+            })
+        }
+
+        async fn analyze_text(&self, _content: String) -> Result<SessionSummary> {
+            Err(anyhow!("不支持文本分析"))
+        }
+
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn configure(&mut self, _config: serde_json::Value) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_configured(&self) -> bool {
+            true
+        }
+    }
+
+    /// 记录经过顺序的中间件
+    struct Trace {
+        name: &'static str,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Middleware for Trace {
+        async fn call(&self, request: LLMRequest, next: Next<'_>) -> Result<LLMResponse> {
+            self.log.lock().unwrap().push(format!("{} 前", self.name));
+            let result = next.run(request).await;
+            self.log.lock().unwrap().push(format!("{} 后", self.name));
+            result
+        }
+    }
+
+    fn frames() -> Vec<String> {
+        vec!["frame_0001.jpg".to_string(), "frame_0002.jpg".to_string()]
+    }
+
+    #[tokio::test]
+    async fn test_first_added_layer_is_outermost() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (inner, _) = FlakyProvider::new(0);
+        let provider = ProviderBuilder::new()
+            .layer(Trace {
+                name: "外层",
+                log: log.clone(),
+            })
+            .layer(Trace {
+                name: "内层",
+                log: log.clone(),
+            })
+            .build(Box::new(inner));
+
+        provider.analyze_frames(frames()).await.unwrap();
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["外层 前", "内层 前", "内层 后", "外层 后"]
+        );
+        // 非帧分析调用原样转发
+        assert_eq!(provider.name(), "flaky");
+    }
+
+    #[tokio::test]
+    async fn test_layered_intercepts_text_calls_and_replaces_layers() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (inner, _) = FlakyProvider::new(0);
+        let mut provider = Layered::new(Box::new(inner));
+        provider.set_layers(ProviderBuilder::new().layer(Trace {
+            name: "追踪",
+            log: log.clone(),
+        }));
+
+        // 文本分析同样经过中间件
+        assert!(provider.analyze_text("会议纪要".to_string()).await.is_err());
+        assert_eq!(*log.lock().unwrap(), vec!["追踪 前", "追踪 后"]);
+
+        // 替换中间件后旧的中间件不再生效
+        provider.set_layers(ProviderBuilder::new());
+        provider.analyze_frames(frames()).await.unwrap();
+        assert_eq!(log.lock().unwrap().len(), 2);
+        // 向下转型透传给内层 provider
+        assert!(provider.as_any().downcast_mut::<FlakyProvider>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_retry_layer() {
+        let (inner, calls) = FlakyProvider::new(2);
+        let provider = ProviderBuilder::new()
+            .layer(RetryLayer::new(2, Duration::from_millis(1)))
+            .build(Box::new(inner));
+        let summary = provider.analyze_frames(frames()).await.unwrap();
+        assert_eq!(summary.title, "第 3 次调用");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // 重试次数用尽后返回最后一次的错误
+        let (inner, calls) = FlakyProvider::new(5);
+        let provider = ProviderBuilder::new()
+            .layer(RetryLayer::new(1, Duration::from_millis(1)))
+            .build(Box::new(inner));
+        assert!(provider.analyze_frames(frames()).await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_layer_spaces_calls() {
+        let (inner, _) = FlakyProvider::new(0);
+        let provider = ProviderBuilder::new()
+            .layer(RateLimitLayer::new(2, Duration::from_millis(200)))
+            .build(Box::new(inner));
+
+        let started = Instant::now();
+        for _ in 0..3 {
+            provider.analyze_frames(frames()).await.unwrap();
+        }
+        // 前两次立即执行，第三次等到第一次移出窗口
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[tokio::test]
+    async fn test_cache_layer_outside_retry() {
+        let (inner, calls) = FlakyProvider::new(1);
+        let provider = ProviderBuilder::new()
+            .layer(CacheLayer::new(8))
+            .layer(RetryLayer::new(1, Duration::ZERO))
+            .build(Box::new(inner));
+
+        let first = provider.analyze_frames(frames()).await.unwrap();
+        let second = provider.analyze_frames(frames()).await.unwrap();
+        assert_eq!(first.title, second.title);
+        // 首次失败一次后重试成功，第二次命中缓存
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // 上下文不同视为不同请求
+        provider
+            .analyze_frames_with_context(
+                frames(),
+                AnalysisContext {
+                    transcript: Some("会议讨论".to_string()),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    /// 记录每次调用收到的重试提示，依次返回给定的错误（用尽后成功）
+    struct HintedProvider {
+        hints: Arc<Mutex<Vec<RetryHints>>>,
+        errors: Mutex<VecDeque<anyhow::Error>>,
+        reconnects: Arc<AtomicUsize>,
+    }
+
+    impl HintedProvider {
+        fn new(errors: Vec<anyhow::Error>) -> Self {
+            Self {
+                hints: Arc::default(),
+                errors: Mutex::new(errors.into()),
+                reconnects: Arc::default(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl LLMProvider for HintedProvider {
+        fn as_any(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+
+        async fn analyze_frames(&self, _frames: Vec<String>) -> Result<SessionSummary> {
+            self.hints.lock().unwrap().push(retry_hints());
+            match self.errors.lock().unwrap().pop_front() {
+                Some(e) => Err(e),
+                None => Ok(SessionSummary::default()),
+            }
+        }
+
+        async fn analyze_text(&self, _content: String) -> Result<SessionSummary> {
+            Err(anyhow!("不支持文本分析"))
+        }
+
+        async fn reconnect(&self) -> Result<()> {
+            self.reconnects.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "hinted"
+        }
+
+        fn configure(&mut self, _config: serde_json::Value) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_configured(&self) -> bool {
+            true
+        }
+    }
+
+    fn empty() -> anyhow::Error {
+        EmptyResponse { attempts: 1 }.into()
+    }
+
+    fn unreachable() -> anyhow::Error {
+        OllamaUnreachable {
+            base_url: "http://127.0.0.1:11434".to_string(),
+            connect_timeout_secs: 5,
+        }
+        .into()
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_layer_rebuilds_after_consecutive_failures() {
+        // 无法连接 → 其他错误（请求已到达服务端，清零）→ 连续两次无法连接 → 重建
+        let inner = HintedProvider::new(vec![
+            unreachable(),
+            anyhow!("HTTP 500"),
+            unreachable(),
+            unreachable(),
+            unreachable(),
+        ]);
+        let reconnects = inner.reconnects.clone();
+        let provider = ProviderBuilder::new()
+            .layer(ReconnectLayer::new(2))
+            .build(Box::new(inner));
+        let mut counts = Vec::new();
+        for _ in 0..5 {
+            assert!(provider.analyze_frames(frames()).await.is_err());
+            counts.push(reconnects.load(Ordering::SeqCst));
+        }
+        assert_eq!(counts, vec![0, 0, 0, 1, 1]);
+
+        // 成功后计数清零
+        provider.analyze_frames(frames()).await.unwrap();
+        assert_eq!(reconnects.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_empty_response_layer_retries_with_nudge() {
+        // 第一次为空，附加提醒后成功；视频分段同样经过中间件
+        let inner = HintedProvider::new(vec![empty()]);
+        let hints = inner.hints.clone();
+        let provider = ProviderBuilder::new()
+            .layer(EmptyResponseLayer::new(1))
+            .build(Box::new(inner));
+        provider.segment_video(frames(), 1).await.unwrap();
+        let nudges: Vec<bool> = hints.lock().unwrap().iter().map(|h| h.nudge).collect();
+        assert_eq!(nudges, vec![false, true]);
+
+        // 重试后仍为空：返回包含总请求次数的 EmptyResponse
+        let inner = HintedProvider::new(vec![empty(), empty()]);
+        let provider = ProviderBuilder::new()
+            .layer(EmptyResponseLayer::new(1))
+            .build(Box::new(inner));
+        let err = provider.analyze_frames(frames()).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<EmptyResponse>(),
+            Some(&EmptyResponse { attempts: 2 })
+        );
+
+        // 其他错误直接返回，不重试
+        let inner = HintedProvider::new(vec![anyhow!("连接被拒绝")]);
+        let hints = inner.hints.clone();
+        let provider = ProviderBuilder::new()
+            .layer(EmptyResponseLayer::new(1))
+            .build(Box::new(inner));
+        assert!(provider.analyze_frames(frames()).await.is_err());
+        assert_eq!(hints.lock().unwrap().len(), 1);
+
+        // 重试次数超过上限时截断
+        let inner = HintedProvider::new((0..10).map(|_| empty()).collect());
+        let provider = ProviderBuilder::new()
+            .layer(EmptyResponseLayer::new(10))
+            .build(Box::new(inner));
+        let err = provider.analyze_frames(frames()).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<EmptyResponse>(),
+            Some(&EmptyResponse {
+                attempts: MAX_EMPTY_RESPONSE_RETRIES + 1
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cache_layer_evicts_oldest() {
+        let (inner, calls) = FlakyProvider::new(0);
+        let provider = ProviderBuilder::new()
+            .layer(CacheLayer::new(1))
+            .build(Box::new(inner));
+
+        let other = vec!["frame_0003.jpg".to_string()];
+        provider.analyze_frames(frames()).await.unwrap();
+        provider.analyze_frames(other.clone()).await.unwrap();
+        provider.analyze_frames(other).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        // 最早的结果已被淘汰
+        provider.analyze_frames(frames()).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_cache_layer_matches_reencoded_frames() {
+        use image::codecs::jpeg::JpegEncoder;
+        use image::{imageops::FilterType, Rgb, RgbImage};
+
+        let dir = tempfile::tempdir().unwrap();
+        let write_jpeg = |name: &str, image: &RgbImage, quality: u8| {
+            let path = dir.path().join(name);
+            let mut bytes = Vec::new();
+            JpegEncoder::new_with_quality(&mut bytes, quality)
+                .encode_image(image)
+                .unwrap();
+            std::fs::write(&path, bytes).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+        let image = RgbImage::from_fn(320, 240, |x, y| {
+            let block = if (x / 80 + y / 60) % 2 == 0 { 100 } else { 0 };
+            let value = (x * 120 / 320) as u8 + block;
+            Rgb([value, value, value])
+        });
+        let original = write_jpeg("original.jpg", &image, 90);
+        let resized = image::imageops::resize(&image, 160, 120, FilterType::Triangle);
+        let reencoded = write_jpeg("reencoded.jpg", &resized, 40);
+        let flipped = write_jpeg("flipped.jpg", &image::imageops::flip_horizontal(&image), 90);
+
+        let (inner, calls) = FlakyProvider::new(0);
+        let provider = ProviderBuilder::new()
+            .layer(CacheLayer::new(8))
+            .build(Box::new(inner));
+
+        let first = provider.analyze_frames(vec![original]).await.unwrap();
+        // 重新编码的同一画面命中缓存
+        let second = provider.analyze_frames(vec![reencoded]).await.unwrap();
+        assert_eq!(first.title, second.title);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 不同画面重新调用模型
+        provider.analyze_frames(vec![flipped]).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,38 +1,94 @@
 // LLM模块 - 管理AI分析服务
 
+pub mod app_coverage;
+pub mod budget;
+pub mod circuit_breaker;
 pub mod claude;
 pub mod codex;
+pub mod confidence;
+pub mod corrections;
+pub mod ensemble;
+pub mod failover;
+pub mod idle;
+pub mod json_repair;
+pub mod language;
+pub mod metrics;
+pub mod middleware;
+pub mod modes;
+pub mod mosaic;
+pub mod output_schema;
 pub mod plugin;
+pub mod profiles;
+pub mod provider_profiles;
+pub mod queue;
 pub mod qwen;
+pub mod reasoning;
+pub mod recording;
+pub mod redaction;
+pub mod sanitize;
+pub mod scene_sampling;
+pub mod session_merge;
+pub mod taxonomy;
+pub mod time_slice;
 pub mod ollama;
+
+#[cfg(test)]
+mod golden;
+pub use circuit_breaker::{CircuitBreakerConfig, CircuitOpen, CircuitState};
+pub use ensemble::EnsembleProvider;
+pub use failover::{FailoverConfig, FailoverTrigger};
+pub use metrics::{ProviderMetrics, ProviderStatus};
+pub use middleware::{
+    retry_hints, CacheLayer, DownscaleLayer, EmptyResponseLayer, LLMRequest, LLMResponse, Layered,
+    Middleware, MiddlewareConfig, ProviderBuilder, RateLimitLayer, ReconnectLayer, RetryHints,
+    RetryLayer,
+};
 pub use ollama::OllamaProvider;
+pub use queue::{AnalysisPermit, AnalysisPipeline, AnalysisPriority, AnalysisQueue};
+pub use recording::{RecordingProvider, ReplayProvider};
+pub use redaction::RedactionConfig;
+pub use sanitize::SanitizeLevel;
 
 
 pub use claude::ClaudeProvider;
 pub use codex::CodexProvider;
 pub use plugin::{
-    ActivityCategory, ActivityTag, AppSites, Distraction, KeyMoment, LLMProvider, SessionBrief,
-    SessionSummary, TimelineCard, VideoSegment,
+    ActivityCategory, ActivityTag, AnalysisContext, AnalysisProgress, AppSites, CostEstimate,
+    Distraction, EmptyResponse, FewShotExample, ForegroundApp, FrameSelection, FramesUnavailable,
+    InputActivity, InputActivityMinute, KeyMoment, LLMProvider, ProgressSender, ProviderPricing,
+    SampledFrame, SessionBrief, SessionSummary, SessionSummaryBuilder, SummaryField, TagTimeRange,
+    TimelineCard, TooFewFrames, ValidationError, VideoSegment, VisitedUrl,
 };
+pub use profiles::PromptProfile;
+pub use provider_profiles::{ProviderProfile, ProviderProfiles};
 pub use qwen::QwenProvider;
 
-use crate::capture::scheduler::SessionProcessor;
 use crate::settings::SettingsManager;
+use crate::video::frame_quality::FrameQualityConfig;
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use serde_json::Value;
+use std::collections::BTreeMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{error, info, warn};
 
 /// LLM管理器
 pub struct LLMManager {
-    /// 当前使用的提供商
-    provider: Box<dyn LLMProvider>,
+    /// 当前使用的提供商（外层叠加了按配置组装的中间件）
+    provider: Layered,
     /// 配置锁
     config_lock: Arc<RwLock<LLMConfig>>,
     /// HTTP 客户端（用于 Qwen provider）
     http_client: Option<reqwest::Client>,
+    /// 流式分析进度通道（切换 provider 后重新下发）
+    progress_tx: Option<plugin::ProgressSender>,
+    /// 当前关联的数据库与会话（用于保存会话分析记录）
+    provider_database: Option<(Arc<crate::storage::Database>, Option<i64>)>,
+    /// 各 provider 的调用指标（健康状态接口）
+    metrics: ProviderMetrics,
+    /// 当前会话时间范围（故障转移时下发给后备 provider）
+    session_window: (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
 }
 
 /// LLM配置
@@ -54,25 +110,357 @@ pub struct LLMConfig {
     pub codex: CodexConfig,
     /// 分析参数
     pub analysis_params: AnalysisParams,
+    /// Provider 中间件（重试、限流、缓存）配置
+    #[serde(default)]
+    pub middleware: MiddlewareConfig,
+    /// Provider 熔断配置
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// 总结入库前的敏感内容脱敏
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    /// 总结整体置信度（按标签置信度汇总，0-1）的下限，所有 provider 的总结入库前检查，
+    /// 低于时标记为 low_confidence；未设置时不检查
+    #[serde(default)]
+    pub min_confidence: Option<f32>,
+    /// 配额、限流或欠费时改用的后备 provider
+    #[serde(default)]
+    pub failover: FailoverConfig,
 }
 
 fn default_provider() -> String {
     "qwen".to_string()
 }
 
+/// 按配置为当前 provider 组装中间件，顺序为 cache → retry → empty_response → downscale → rate_limit → reconnect（见 middleware 模块说明）
+///
+/// 空内容重试与图片过大重试只对 Ollama 生效，分别由 OllamaConfig.empty_response_retries
+/// 与 OllamaConfig.downscale_on_size_error 控制
+fn provider_layers(config: &LLMConfig) -> ProviderBuilder {
+    let mw = &config.middleware;
+    let mut layers = ProviderBuilder::new();
+    if mw.cache_capacity > 0 {
+        layers = layers.layer(CacheLayer::new(mw.cache_capacity).with_frame_hash(mw.frame_hash));
+    }
+    if mw.max_retries > 0 {
+        layers = layers.layer(RetryLayer::new(
+            mw.max_retries,
+            std::time::Duration::from_millis(mw.retry_backoff_ms),
+        ));
+    }
+    if config.provider == "ollama" && config.ollama.empty_response_retries > 0 {
+        layers = layers.layer(EmptyResponseLayer::new(
+            config.ollama.empty_response_retries,
+        ));
+    }
+    if config.provider == "ollama" && config.ollama.downscale_on_size_error {
+        layers = layers.layer(DownscaleLayer::new());
+    }
+    if mw.max_calls_per_minute > 0 {
+        layers = layers.layer(RateLimitLayer::new(
+            mw.max_calls_per_minute,
+            std::time::Duration::from_secs(60),
+        ));
+    }
+    if mw.reconnect_after_failures > 0 {
+        layers = layers.layer(ReconnectLayer::new(mw.reconnect_after_failures));
+    }
+    layers
+}
+
 /// Claude配置（使用 Agent SDK，可通过配置传入 API key）
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Default)]
 pub struct ClaudeConfig {
     pub api_key: Option<String>,
     pub model: Option<String>,
+    /// 计费单价覆盖（未设置时使用内置单价）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<plugin::ProviderPricing>,
 }
 
+/// Ollama 配置
+///
+/// 所有字段都有默认值，旧配置缺字段时可直接兼容；未知字段（如拼错的 "basel_url"）会被拒绝
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct OllamaConfig {
     #[serde(default = "default_ollama_base_url")]
     pub base_url: String,
     #[serde(default = "default_ollama_model")]
     pub model: String,
+    /// 在提示词中说明图片按时间先后排列
+    #[serde(default = "default_true")]
+    pub frame_order_hint: bool,
+    /// 在提示词中为每张图片列出带序号的说明
+    #[serde(default)]
+    pub frame_index_captions: bool,
+    /// 是否采信模型返回的 start_time/end_time（默认使用真实会话时间）
+    #[serde(default)]
+    pub trust_model_times: bool,
+    /// 使用流式响应（可推送生成进度）
+    #[serde(default)]
+    pub stream: bool,
+    /// 最大生成 token 数（Ollama options.num_predict），用于估算进度
+    #[serde(default)]
+    pub num_predict: Option<u32>,
+    /// 在每个采样区间内随机取帧（默认取区间首帧）
+    #[serde(default)]
+    pub sample_jitter: bool,
+    /// 随机采样种子；未设置时由帧路径推导，保证同一会话采样结果一致
+    #[serde(default)]
+    pub sampling_seed: Option<u64>,
+    /// 请求体大小上限（字节），超出时按相似度丢帧或降低画质
+    #[serde(default)]
+    pub max_payload_bytes: Option<usize>,
+    /// 非流式请求期间轮询 /api/ps 的间隔（秒），用于推送心跳并在模型意外消失时提前失败
+    #[serde(default)]
+    pub keep_alive_poll_secs: Option<u64>,
+    /// 转写、会话文本等不可信内容拼入提示词前的过滤强度（off/standard/strict）
+    #[serde(default)]
+    pub prompt_sanitization: SanitizeLevel,
+    /// 摘要的输出语言（如 zh、en）；未设置时由模型自行决定，语言检测置信度不足时作为结果语言
+    #[serde(default)]
+    pub output_language: Option<String>,
+    /// 最小分析帧数，输入帧数更少时返回 TooFewFrames 而不调用模型（默认 1，即不限制）
+    #[serde(default = "default_min_frames_for_analysis")]
+    pub min_frames_for_analysis: usize,
+    /// 带标签的参考截图（如 "我的 IDE"），作为少样本示例放在截图之前，帮助模型识别常用应用
+    #[serde(default)]
+    pub reference_images: Vec<ReferenceImage>,
+    /// 模型返回空摘要但给出了标签时，由标签与关键时刻推导摘要；关闭后空摘要视为解析失败
+    #[serde(default = "default_true")]
+    pub derive_empty_summary: bool,
+    /// 按帧数选择模型：取帧数不超过 max_frames 的最小阈值对应的模型，都不满足时使用 model
+    #[serde(default)]
+    pub model_rules: Vec<ModelRule>,
+    /// 模型返回空内容时附加提醒重试的次数（默认 1，0 表示不重试，最多 3 次），由 EmptyResponseLayer 执行
+    #[serde(default = "default_empty_response_retries")]
+    pub empty_response_retries: u32,
+    /// 按模型名或家族覆盖内置的提示词配置（如 "llava" 或 "llava:13b"），完整模型名优先
+    #[serde(default)]
+    pub prompt_profiles: BTreeMap<String, PromptProfileOverride>,
+    /// 建立连接的超时（秒），服务不可达时尽快失败
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    /// 等待模型返回的超时（秒）；流式响应时为两次输出之间的最长间隔
+    #[serde(default = "default_read_timeout_secs")]
+    pub read_timeout_secs: u64,
+    /// 在分析结果中记录实际发送给模型的帧（序号与路径），便于查看总结依据了哪些帧
+    #[serde(default)]
+    pub record_sampled_frames: bool,
+    /// 采样帧几乎都是黑屏或静止画面时直接判定为空闲会话，不调用模型
+    #[serde(default = "default_true")]
+    pub detect_idle_frames: bool,
+    /// 附加到每个请求的请求头（如鉴权网关要求的 API Key），日志中不输出疑似密钥的值
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// 按会话时长采样：每分钟采样的帧数，未设置时按固定上限采样；采样数仍不超过模型的图片上限
+    #[serde(default)]
+    pub frames_per_minute: Option<f32>,
+    /// 注入提示词的人工更正示例数（按与当前会话的相似度选取，0 表示不使用，最多 5 条）
+    #[serde(default)]
+    pub few_shot_examples: usize,
+    /// 自定义总结字段：追加到提示词的 JSON schema，解析结果放入 SessionSummary.extra
+    #[serde(default)]
+    pub extra_fields: Vec<ExtraFieldSpec>,
+    /// 服务端以图片过大拒绝请求时，将图片缩小一半后重试一次（默认开启），由 DownscaleLayer 执行
+    #[serde(default = "default_true")]
+    pub downscale_on_size_error: bool,
+    /// 默认分析模式（内置 work、study、entertainment）；会话单独选择的模式优先，未设置时不附加模式说明
+    #[serde(default)]
+    pub analysis_mode: Option<String>,
+    /// 自定义分析模式，同名时覆盖内置模式
+    #[serde(default)]
+    pub analysis_modes: BTreeMap<String, AnalysisModeConfig>,
+    /// 发送给模型的帧顺序：chronological（默认）或 reverse（最近的帧在前，总结侧重最近的活动）
+    #[serde(default)]
+    pub order: FrameOrder,
+    /// 模型输出多个顶层 JSON 对象（如先输出推理过程再输出答案）时的选取方式：last（默认）或 first
+    #[serde(default)]
+    pub json_selection: JsonSelection,
+    /// 分类体系：按顶层类别（work、communication 等）配置多级子类别，模型以 work/coding/frontend 的形式输出完整路径；
+    /// 未配置时只使用顶层类别
+    #[serde(default)]
+    pub taxonomy: BTreeMap<String, Vec<TaxonomyNode>>,
+    /// 反序列化前按 JSON Schema 校验模型输出：off（默认）、coerce（先修正字符串形式的数字等类型偏差）或 strict
+    #[serde(default)]
+    pub schema_validation: SchemaValidation,
+    /// 拼图模式：把采样帧拼成网格图片发送（每格标注时间），同样的图片数可覆盖更多帧；未设置时逐帧发送
+    #[serde(default)]
+    pub mosaic: Option<mosaic::MosaicConfig>,
+    /// 以 gzip 压缩 /api/chat 请求体（Content-Encoding: gzip），适合经慢速链路访问远程服务；
+    /// 服务端不支持（415，或无法解析压缩后的请求体）时改为不压缩重发，之后不再压缩
+    #[serde(default)]
+    pub compress_request: bool,
+    /// 按前台应用覆盖采样：分析上下文带有前台应用记录时，保证每个应用至少有一帧，再等间隔补齐；未设置时等间隔采样
+    #[serde(default)]
+    pub app_coverage: Option<app_coverage::AppCoverageConfig>,
+    /// 按场景采样：以相邻帧感知哈希的跳变为场景切换，每个场景取切换后的第一帧（预算不足时保留持续最久的场景）；
+    /// 同时开启按前台应用覆盖采样且有前台应用记录时，优先按应用覆盖采样
+    #[serde(default)]
+    pub scene_aware: Option<scene_sampling::SceneAwareConfig>,
+    /// 保留模型在 JSON 之外输出的思考过程（<think> 块或 JSON 之前的说明），存为总结的 reasoning 字段；
+    /// 关闭时同样从解析内容中去掉，只是不保存
+    #[serde(default)]
+    pub capture_reasoning: bool,
+    /// 分析后立即检查的整体置信度下限（0-1），低于时标记为 low_confidence，配合 low_confidence_model 换模型重新分析；
+    /// 入库前的检查由 LLMConfig.min_confidence 决定，对所有 provider 生效
+    #[serde(default)]
+    pub min_confidence: Option<f32>,
+    /// 总结被标记为低置信度时改用该模型重新分析一次，取置信度较高的结果；未设置时只标记
+    #[serde(default)]
+    pub low_confidence_model: Option<String>,
+    /// 同时保存在内存中的已编码图片数上限，超出的帧在写出请求体时才读取并编码（逐张写出后释放）；
+    /// 未设置时全部预先编码
+    #[serde(default)]
+    pub max_encoded_frames_in_memory: Option<usize>,
+    /// 单次分析的帧数硬上限，超出时按时间分块分别分析，再合并为一份总结；未设置时不分块
+    #[serde(default)]
+    pub max_frames_hard_limit: Option<usize>,
+}
+
+/// 发送给模型的帧顺序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameOrder {
+    /// 按时间先后，最早的帧在前
+    #[default]
+    Chronological,
+    /// 时间倒序，最近的帧在前；提示词中的时间仍为相对最早一帧的真实偏移
+    Reverse,
+}
+
+/// 模型输出多个顶层 JSON 对象时选用哪一个：按顺序取第一个能解析为会话总结的对象
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonSelection {
+    /// 从后往前找（推理型模型通常最后输出答案）
+    #[default]
+    Last,
+    /// 从前往后找
+    First,
+}
+
+/// 模型输出的 JSON Schema 校验方式，校验失败时列出每一处违规
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaValidation {
+    /// 不校验，只做反序列化
+    #[default]
+    Off,
+    /// 先把 Schema 要求为数字、布尔值的字符串（如 "80"）转换为对应类型，再校验
+    Coerce,
+    /// 按原样校验，类型不符即报错
+    Strict,
+}
+
+/// 模型选择规则：帧数不超过 max_frames 的会话使用 model
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelRule {
+    pub max_frames: usize,
+    pub model: String,
+}
+
+/// 提示词配置覆盖：未设置的字段沿用内置配置
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptProfileOverride {
+    /// 系统提示词，空字符串表示不使用
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// 是否要求服务端以 JSON 格式输出
+    #[serde(default)]
+    pub json_format: Option<bool>,
+    /// 单次请求最多发送的图片数
+    #[serde(default)]
+    pub max_images: Option<usize>,
+}
+
+/// 分析模式配置：按使用场景调整提示词的侧重点
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AnalysisModeConfig {
+    /// 显示名，未设置时使用模式名
+    #[serde(default)]
+    pub label: String,
+    /// 追加到提示词的侧重说明
+    pub instructions: String,
+    /// 需要重点区分与细化的活动类别
+    #[serde(default)]
+    pub focus_categories: Vec<ActivityCategory>,
+}
+
+/// 分类体系中的子类别
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaxonomyNode {
+    /// 子类别名（写入标签路径，不能含 / 或 >）
+    pub name: String,
+    /// 写入提示词的说明
+    #[serde(default)]
+    pub description: String,
+    /// 下一级子类别
+    #[serde(default)]
+    pub children: Vec<TaxonomyNode>,
+}
+
+/// 自定义总结字段
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExtraFieldSpec {
+    /// 字段名（小写字母开头，只含小写字母、数字与下划线）
+    pub name: String,
+    #[serde(default)]
+    pub kind: ExtraFieldKind,
+    /// 写入提示词的字段说明
+    #[serde(default)]
+    pub description: String,
+}
+
+/// 自定义字段的取值类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExtraFieldKind {
+    #[default]
+    String,
+    /// 字符串数组
+    Array,
+}
+
+impl ExtraFieldSpec {
+    /// 校验字段名：格式合法、不与内置字段冲突、彼此不重复
+    pub fn validate_all(fields: &[ExtraFieldSpec]) -> Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        for field in fields {
+            let name = field.name.as_str();
+            let valid = name.starts_with(|c: char| c.is_ascii_lowercase())
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+            if !valid {
+                return Err(anyhow!(
+                    "自定义字段名 {:?} 无效：须以小写字母开头，只含小写字母、数字与下划线",
+                    name
+                ));
+            }
+            if plugin::BUILTIN_SUMMARY_FIELDS.contains(&name) {
+                return Err(anyhow!("自定义字段 {} 与内置字段重名", name));
+            }
+            if !seen.insert(name) {
+                return Err(anyhow!("自定义字段 {} 重复", name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 参考截图：应用名称与示例截图路径
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ReferenceImage {
+    pub label: String,
+    pub path: String,
 }
 
 impl Default for OllamaConfig {
@@ -80,10 +468,109 @@ impl Default for OllamaConfig {
         Self {
             base_url: default_ollama_base_url(),
             model: default_ollama_model(),
+            frame_order_hint: true,
+            frame_index_captions: false,
+            trust_model_times: false,
+            stream: false,
+            num_predict: None,
+            sample_jitter: false,
+            sampling_seed: None,
+            max_payload_bytes: None,
+            keep_alive_poll_secs: None,
+            prompt_sanitization: SanitizeLevel::default(),
+            output_language: None,
+            min_frames_for_analysis: default_min_frames_for_analysis(),
+            reference_images: Vec::new(),
+            derive_empty_summary: true,
+            model_rules: Vec::new(),
+            empty_response_retries: default_empty_response_retries(),
+            prompt_profiles: BTreeMap::new(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+            read_timeout_secs: default_read_timeout_secs(),
+            record_sampled_frames: false,
+            detect_idle_frames: true,
+            headers: BTreeMap::new(),
+            frames_per_minute: None,
+            few_shot_examples: 0,
+            extra_fields: Vec::new(),
+            downscale_on_size_error: true,
+            analysis_mode: None,
+            analysis_modes: BTreeMap::new(),
+            order: FrameOrder::Chronological,
+            json_selection: JsonSelection::Last,
+            taxonomy: BTreeMap::new(),
+            schema_validation: SchemaValidation::Off,
+            mosaic: None,
+            compress_request: false,
+            app_coverage: None,
+            scene_aware: None,
+            capture_reasoning: false,
+            min_confidence: None,
+            low_confidence_model: None,
+            max_encoded_frames_in_memory: None,
+            max_frames_hard_limit: None,
         }
     }
 }
 
+impl OllamaConfig {
+    /// 从 JSON 解析完整配置，字段类型错误或存在未知字段时返回明确的错误
+    pub fn from_value(value: serde_json::Value) -> Result<Self> {
+        serde_json::from_value(value).map_err(|e| anyhow!("Ollama 配置无效: {}", e))
+    }
+
+    /// 把 patch 中提供的字段覆盖到当前配置上，未提供的字段保持不变；合并后整体校验，未知字段照样报错
+    pub fn merged(&self, patch: serde_json::Value) -> Result<Self> {
+        let serde_json::Value::Object(patch) = patch else {
+            return Err(anyhow!("Ollama 配置无效: 配置须为 JSON 对象"));
+        };
+        let mut value = serde_json::to_value(self)?;
+        if let Some(current) = value.as_object_mut() {
+            current.extend(patch);
+        }
+        Self::from_value(value)
+    }
+
+    /// 从持久化的 provider 配置恢复（优先使用保存的完整配置）
+    pub fn from_provider_config(config: &crate::models::LLMProviderConfig) -> Self {
+        config
+            .ollama_config
+            .clone()
+            .and_then(|raw| match Self::from_value(raw) {
+                Ok(cfg) => Some(cfg),
+                Err(e) => {
+                    warn!("已保存的 Ollama 配置无法解析，使用默认值: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_else(|| Self {
+                base_url: config.base_url.clone(),
+                model: config.model.clone(),
+                ..Default::default()
+            })
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_min_frames_for_analysis() -> usize {
+    1
+}
+
+fn default_empty_response_retries() -> u32 {
+    1
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    5
+}
+
+fn default_read_timeout_secs() -> u64 {
+    300
+}
+
 fn default_ollama_base_url() -> String {
     "http://100.82.18.91:11434".to_string()
 }
@@ -119,6 +606,8 @@ pub struct CodexConfig {
     pub timeline_prompt: Option<String>,
     #[serde(default)]
     pub day_summary_prompt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<plugin::ProviderPricing>,
 }
 
 /// Qwen配置
@@ -133,6 +622,9 @@ pub struct QwenConfig {
     pub use_video_mode: bool, // 是否使用视频模式处理多张图片
     #[serde(skip_serializing_if = "Option::is_none")]
     pub video_path: Option<String>, // 可选的视频文件路径
+    /// 计费单价覆盖（未设置时使用内置单价）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pricing: Option<plugin::ProviderPricing>,
 }
 
 fn default_model() -> String {
@@ -158,6 +650,9 @@ pub struct AnalysisParams {
     pub include_detailed_description: bool,
     /// 置信度阈值
     pub confidence_threshold: f32,
+    /// 采样前剔除全黑、纯色帧
+    #[serde(default)]
+    pub frame_quality: FrameQualityConfig,
 }
 
 impl Default for AnalysisParams {
@@ -167,6 +662,7 @@ impl Default for AnalysisParams {
             max_frames_per_analysis: 30, // 最多30帧
             include_detailed_description: true,
             confidence_threshold: 0.5,
+            frame_quality: FrameQualityConfig::default(),
         }
     }
 }
@@ -175,26 +671,108 @@ impl LLMManager {
     /// 创建新的LLM管理器（接受共享的HTTP客户端以复用连接池）
     pub fn new(client: reqwest::Client) -> Self {
         // 默认使用 Qwen provider
-        let provider: Box<dyn LLMProvider> = Box::new(QwenProvider::new(client.clone()));
+        let provider = Layered::new(Box::new(QwenProvider::new(client.clone())));
+        let config = LLMConfig {
+            provider: default_provider(),
+            qwen: QwenConfig {
+                api_key: String::new(),
+                model: default_model(),
+                base_url: default_base_url(),
+                use_video_mode: default_video_mode(),
+                video_path: None,
+                pricing: None,
+            },
+            claude: ClaudeConfig::default(),
+            codex: CodexConfig::default(),
+            ollama: OllamaConfig::default(),
+            analysis_params: AnalysisParams::default(),
+            middleware: MiddlewareConfig::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+            redaction: RedactionConfig::default(),
+            min_confidence: None,
+            failover: FailoverConfig::default(),
+        };
 
-        Self {
+        let manager = Self {
             provider,
-            config_lock: Arc::new(RwLock::new(LLMConfig {
-                provider: default_provider(),
-                qwen: QwenConfig {
-                    api_key: String::new(),
-                    model: default_model(),
-                    base_url: default_base_url(),
-                    use_video_mode: default_video_mode(),
-                    video_path: None,
-                },
-                claude: ClaudeConfig::default(),
-                codex: CodexConfig::default(),
-                ollama: OllamaConfig::default(),
-                analysis_params: AnalysisParams::default(),
-            })),
+            config_lock: Arc::new(RwLock::new(config.clone())),
             http_client: Some(client),
+            progress_tx: None,
+            provider_database: None,
+            metrics: ProviderMetrics::new(),
+            session_window: (None, None),
+        };
+        manager.sync_provider_status(&config);
+        manager
+    }
+
+    /// 获取 provider 指标（可克隆，查询不经过 Actor）
+    pub fn metrics(&self) -> ProviderMetrics {
+        self.metrics.clone()
+    }
+
+    /// 当前 provider 在指标中的名称（与配置中的 provider 名称一致）
+    fn provider_key(&self) -> String {
+        self.provider.name().to_lowercase()
+    }
+
+    /// 同步各 provider 的配置状态：当前 provider 以实例状态为准，其余按已保存的配置判断
+    fn sync_provider_status(&self, config: &LLMConfig) {
+        for name in metrics::REGISTERED_PROVIDERS {
+            let configured = match name {
+                "qwen" => !config.qwen.api_key.trim().is_empty(),
+                "ollama" => !config.ollama.base_url.trim().is_empty(),
+                // Claude / Codex 使用本机 SDK 或 CLI 的登录状态，无需额外配置
+                _ => true,
+            };
+            self.metrics.set_configured(name, configured);
+        }
+        let active = self.provider_key();
+        self.metrics
+            .set_configured(&active, self.provider.is_configured());
+        self.metrics.set_active(&active);
+        self.metrics
+            .set_circuit_breaker(config.circuit_breaker.clone());
+    }
+
+    /// 调用当前 provider 前检查熔断状态
+    fn ensure_circuit_closed(&self) -> Result<()> {
+        self.metrics
+            .try_acquire(&self.provider_key())
+            .map_err(|open| {
+                warn!("{}", open);
+                open.into()
+            })
+    }
+
+    /// 按配置脱敏模型返回的总结
+    async fn redact(&self, summary: &mut SessionSummary) {
+        let config = self.config_lock.read().await;
+        redaction::redact_summary(&config.redaction, summary);
+    }
+
+    /// 记录一次 provider 调用的耗时与结果
+    ///
+    /// 帧数不足或帧文件缺失时未调用模型，不计入指标
+    fn record_call<T>(&self, started: std::time::Instant, result: &Result<T>) {
+        self.record_call_for(&self.provider_key(), started, result);
+    }
+
+    /// 记录指定 provider 的一次调用（故障转移时后备 provider 的调用计入其自身）
+    fn record_call_for<T>(&self, provider: &str, started: std::time::Instant, result: &Result<T>) {
+        if let Err(e) = result {
+            if e.downcast_ref::<TooFewFrames>().is_some()
+                || e.downcast_ref::<FramesUnavailable>().is_some()
+            {
+                return;
+            }
         }
+        crate::telemetry::global().record_provider_request(provider, result.is_ok());
+        self.metrics.record(
+            provider,
+            started.elapsed(),
+            result.as_ref().err().map(|e| e.to_string()),
+        );
     }
 
     /// 配置 LLM（支持多 provider）
@@ -224,6 +802,7 @@ impl LLMManager {
                 // 更新配置锁
                 let mut current_config = self.config_lock.write().await;
                 current_config.qwen = config;
+                self.sync_provider_status(&current_config);
 
                 info!("Qwen 配置已更新成功");
             }
@@ -264,13 +843,13 @@ impl LLMManager {
                     .http_client
                     .clone()
                     .ok_or_else(|| anyhow!("无法切换到 Qwen provider: HTTP 客户端未初始化"))?;
-                self.provider = Box::new(QwenProvider::new(client));
+                self.provider = Layered::new(Box::new(QwenProvider::new(client)));
             }
             "claude" => {
-                self.provider = Box::new(ClaudeProvider::new());
+                self.provider = Layered::new(Box::new(ClaudeProvider::new()));
             }
             "codex" => {
-                self.provider = Box::new(CodexProvider::new());
+                self.provider = Layered::new(Box::new(CodexProvider::new()));
             }
             // "ollama" => {
             //     self.provider = Box::new(OllamaProvider::new());
@@ -280,7 +859,7 @@ impl LLMManager {
                     .http_client
                     .clone()
                     .ok_or_else(|| anyhow!("无法切换到 Ollama provider: HTTP 客户端未初始化"))?;
-                self.provider = Box::new(OllamaProvider::new(client));
+                self.provider = Layered::new(Box::new(OllamaProvider::new(client)));
 
                 // 可选：应用已保存的 ollama 配置
                 let cfg = { self.config_lock.read().await.ollama.clone() };
@@ -291,9 +870,13 @@ impl LLMManager {
             }
         }
 
+        self.provider.set_progress_sender(self.progress_tx.clone());
+
         // 更新配置中的 provider
         let mut config = self.config_lock.write().await;
         config.provider = provider_name.to_string();
+        self.provider.set_layers(provider_layers(&config));
+        self.sync_provider_status(&config);
 
         info!("已切换到 provider: {}", provider_name);
         Ok(())
@@ -303,8 +886,11 @@ impl LLMManager {
     pub async fn configure_claude(&mut self, config: serde_json::Value) -> Result<()> {
         info!("配置 Claude provider");
 
-        // 更新 provider 配置
-        self.provider.configure(config.clone())?;
+        if let Some(provider) = self.provider.as_any().downcast_mut::<ClaudeProvider>() {
+            provider.configure(config.clone())?;
+        } else {
+            warn!("当前 provider 不是 Claude，暂存配置待切换后生效");
+        }
 
         // 更新配置锁中的 Claude 配置
         let mut current_config = self.config_lock.write().await;
@@ -314,6 +900,13 @@ impl LLMManager {
         if let Some(model) = config.get("model").and_then(|v| v.as_str()) {
             current_config.claude.model = Some(model.to_string());
         }
+        if let Some(pricing) = config.get("pricing") {
+            current_config.claude.pricing = serde_json::from_value(pricing.clone())
+                .map_err(|e| warn!("忽略无效的 Claude pricing 配置: {}", e))
+                .ok()
+                .flatten();
+        }
+        self.sync_provider_status(&current_config);
 
         info!("Claude 配置已更新");
         Ok(())
@@ -326,15 +919,22 @@ impl LLMManager {
             p.configure(cfg_json)?;
         } else {
             // 如果当前 provider 不是 ollama，但你想切换过去：
-            self.provider = Box::new(OllamaProvider::new(
+            self.provider = Layered::new(Box::new(OllamaProvider::new(
                 self.http_client.clone().ok_or_else(|| anyhow!("HTTP client missing"))?,
-            ));
+            )));
             self.provider.configure(cfg_json)?;
+            self.provider.set_progress_sender(self.progress_tx.clone());
+        }
+        // 查询失败时沿用静态默认能力，不影响配置
+        if let Err(e) = self.refresh_capabilities().await {
+            warn!("查询 Ollama 模型能力失败，使用默认能力: {}", e);
         }
 
         let mut current = self.config_lock.write().await;
         current.provider = "ollama".to_string();
         current.ollama = config;
+        self.provider.set_layers(provider_layers(&current));
+        self.sync_provider_status(&current);
         Ok(())
     }
 
@@ -352,11 +952,32 @@ impl LLMManager {
 
         let mut current_config = self.config_lock.write().await;
         current_config.codex = config;
+        self.sync_provider_status(&current_config);
 
         info!("Codex 配置已更新");
         Ok(())
     }
 
+    /// 应用配置档文件中的一个配置档：切换到对应的 provider 并写入其配置
+    pub async fn apply_profile(&mut self, profile: ProviderProfile) -> Result<()> {
+        match profile {
+            ProviderProfile::Qwen(config) => {
+                self.switch_provider("qwen").await?;
+                self.configure(config).await
+            }
+            ProviderProfile::Claude(config) => {
+                self.switch_provider("claude").await?;
+                self.configure_claude(serde_json::to_value(config)?).await
+            }
+            // configure_ollama 会自行切换到 Ollama provider
+            ProviderProfile::Ollama(config) => self.configure_ollama(*config).await,
+            ProviderProfile::Codex(config) => {
+                self.switch_provider("codex").await?;
+                self.configure_codex(config).await
+            }
+        }
+    }
+
     pub fn set_video_path(&mut self, video_path: Option<String>) {
         if let Some(provider) = self.provider.as_any().downcast_mut::<QwenProvider>() {
             provider.set_video_path(video_path.clone());
@@ -375,8 +996,91 @@ impl LLMManager {
         }
     }
 
+    /// 预热所有已配置的 provider（录制开始前调用）
+    pub async fn start_providers(&self) -> Result<()> {
+        let standby = self.standby_providers().await;
+        let mut failures = Vec::new();
+        let current: &dyn LLMProvider = &self.provider;
+        for provider in std::iter::once(current).chain(standby.iter().map(|p| p.as_ref())) {
+            info!("预热 LLM provider: {}", provider.name());
+            if let Err(e) = provider.on_start().await {
+                failures.push(format!("{}: {}", provider.name(), e));
+            }
+        }
+        Self::lifecycle_result("预热", failures)
+    }
+
+    /// 释放所有已配置的 provider 的资源（录制结束后调用）
+    pub async fn stop_providers(&self) -> Result<()> {
+        let standby = self.standby_providers().await;
+        let mut failures = Vec::new();
+        let current: &dyn LLMProvider = &self.provider;
+        for provider in std::iter::once(current).chain(standby.iter().map(|p| p.as_ref())) {
+            info!("释放 LLM provider 资源: {}", provider.name());
+            if let Err(e) = provider.on_stop().await {
+                failures.push(format!("{}: {}", provider.name(), e));
+            }
+        }
+        Self::lifecycle_result("资源释放", failures)
+    }
+
+    /// 除当前 provider 外、保存了凭据的其他 provider（按保存的配置临时创建，供生命周期钩子使用）
+    ///
+    /// Ollama 没有凭据可判断是否在用，只在作为当前 provider 时处理，避免把用不到的模型加载进显存
+    async fn standby_providers(&self) -> Vec<Box<dyn LLMProvider>> {
+        let config = self.config_lock.read().await.clone();
+        let current = config.provider.as_str();
+        let mut standby: Vec<Box<dyn LLMProvider>> = Vec::new();
+
+        if !matches!(current, "qwen" | "openai") && !config.qwen.api_key.trim().is_empty() {
+            if let Some(client) = self.http_client.clone() {
+                let mut provider = QwenProvider::new(client);
+                match serde_json::to_value(&config.qwen).map(|c| provider.configure(c)) {
+                    Ok(Ok(())) => standby.push(Box::new(provider)),
+                    _ => warn!("Qwen 配置无效，跳过生命周期钩子"),
+                }
+            }
+        }
+        let claude_key = config.claude.api_key.as_deref().map(str::trim);
+        if current != "claude" && claude_key.is_some_and(|key| !key.is_empty()) {
+            let mut provider = ClaudeProvider::new();
+            let claude_config = serde_json::json!({
+                "api_key": claude_key,
+                "model": config.claude.model,
+            });
+            match provider.configure(claude_config) {
+                Ok(()) => standby.push(Box::new(provider)),
+                Err(e) => warn!("Claude 配置无效，跳过生命周期钩子: {}", e),
+            }
+        }
+        if current != "codex" && config.codex.binary_path.is_some() {
+            let mut provider = CodexProvider::new();
+            match serde_json::to_value(&config.codex).map(|c| provider.configure(c)) {
+                Ok(Ok(())) => standby.push(Box::new(provider)),
+                _ => warn!("Codex 配置无效，跳过生命周期钩子"),
+            }
+        }
+
+        standby
+    }
+
+    fn lifecycle_result(stage: &str, failures: Vec<String>) -> Result<()> {
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("provider {}失败: {}", stage, failures.join("; ")))
+        }
+    }
+
+    /// 设置流式分析进度通道
+    pub fn set_progress_sender(&mut self, sender: Option<plugin::ProgressSender>) {
+        self.progress_tx = sender.clone();
+        self.provider.set_progress_sender(sender);
+    }
+
     /// 设置会话时间范围（用于提示词中的绝对时间）
     pub fn set_session_window(&mut self, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) {
+        self.session_window = (start, end);
         self.provider.set_session_window(start, end);
     }
 
@@ -388,8 +1092,361 @@ impl LLMManager {
         };
         info!("使用 {} 分析 {} 帧", provider_name, frames.len());
 
-        match self.provider.analyze_frames(frames).await {
-            Ok(summary) => {
+        self.ensure_circuit_closed()?;
+        let started = std::time::Instant::now();
+        let result = self
+            .analyze_within_budget(&self.provider, frames.clone(), None)
+            .await;
+        self.record_call(started, &result);
+        let manager: &Self = self;
+        let result = manager
+            .fail_over(result, |provider| {
+                let frames = frames.clone();
+                async move {
+                    manager
+                        .analyze_within_budget(provider.as_ref(), frames, None)
+                        .await
+                }
+            })
+            .await;
+        match result {
+            Ok(Served {
+                value: mut summary,
+                provider,
+                ..
+            }) => {
+                summary.served_by = Some(provider);
+                self.redact(&mut summary).await;
+                info!("分析成功: {}", summary.title);
+                Ok(summary)
+            }
+            Err(e) => {
+                error!("分析失败: {}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// 结合附加上下文（如音频转写）分析帧数据
+    pub async fn analyze_frames_with_context(
+        &mut self,
+        frames: Vec<String>,
+        context: AnalysisContext,
+    ) -> Result<SessionSummary> {
+        if context.transcript.is_some() && !self.provider.capabilities().audio_support {
+            warn!(
+                "{} 不支持音频转写上下文，将忽略转写内容",
+                self.provider.name()
+            );
+        }
+        if !context.visited_urls.is_empty() && !self.provider.capabilities().url_context_support {
+            warn!(
+                "{} 不支持网页访问上下文，将忽略 {} 条访问记录",
+                self.provider.name(),
+                context.visited_urls.len()
+            );
+        }
+        info!(
+            "使用 {} 结合上下文分析 {} 帧",
+            self.provider.name(),
+            frames.len()
+        );
+
+        self.ensure_circuit_closed()?;
+        let started = std::time::Instant::now();
+        let result = self
+            .analyze_within_budget(&self.provider, frames.clone(), Some(context.clone()))
+            .await;
+        self.record_call(started, &result);
+        let manager: &Self = self;
+        let result = manager
+            .fail_over(result, |provider| {
+                let frames = frames.clone();
+                let context = Some(context.clone());
+                async move {
+                    manager
+                        .analyze_within_budget(provider.as_ref(), frames, context)
+                        .await
+                }
+            })
+            .await;
+        match result {
+            Ok(Served {
+                value: mut summary,
+                provider,
+                ..
+            }) => {
+                summary.served_by = Some(provider);
+                self.redact(&mut summary).await;
+                info!("分析成功: {}", summary.title);
+                Ok(summary)
+            }
+            Err(e) => {
+                error!("分析失败: {}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// 按 provider 的图片数与上下文限制规划后分析：采样、缩小图片，单次放不下时分块分析再合并
+    async fn analyze_within_budget(
+        &self,
+        provider: &dyn LLMProvider,
+        frames: Vec<String>,
+        context: Option<AnalysisContext>,
+    ) -> Result<SessionSummary> {
+        let input_frames = frames.len();
+        let plan = budget::fit_to_budget(&frames, provider)?;
+        if plan.is_reduced(input_frames) {
+            info!(
+                "按 {} 的限制调整输入: {} -> {} 帧, 图片缩放 {:.2}, 单次 {} 帧（约 {} tokens）",
+                provider.name(),
+                input_frames,
+                plan.frames.len(),
+                plan.image_scale,
+                plan.frames_per_request(),
+                plan.estimated_tokens
+            );
+        }
+        // 缩小后的副本在分析结束前保持存在
+        let scaled = if plan.image_scale < 1.0 {
+            match budget::ScaledFrames::create(&plan.frames, plan.image_scale).await {
+                Ok(scaled) => Some(scaled),
+                Err(e) => {
+                    warn!("缩小图片失败，使用原图: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let paths = scaled
+            .as_ref()
+            .map_or_else(|| plan.frames.clone(), |scaled| scaled.paths.clone());
+
+        let budget::BudgetStrategy::MapReduce { chunk_frames } = plan.strategy else {
+            return Self::analyze_once(provider, paths, context.as_ref()).await;
+        };
+        let mut chunks = Vec::new();
+        for (index, (chunk, originals)) in paths
+            .chunks(chunk_frames)
+            .zip(plan.frames.chunks(chunk_frames))
+            .enumerate()
+        {
+            info!("分块分析：第 {} 块（{} 帧）", index + 1, chunk.len());
+            let partial = Self::analyze_once(provider, chunk.to_vec(), context.as_ref()).await?;
+            chunks.push((budget::timed_frames(originals), partial));
+        }
+        let partials: Vec<SessionSummary> = chunks.iter().map(|(_, p)| p.clone()).collect();
+        let reduced = match provider
+            .analyze_text(session_merge::build_reduce_content(&partials))
+            .await
+        {
+            Ok(summary) => Some(summary),
+            Err(e) => {
+                warn!("合并各块总结失败，改为直接拼接: {}", e);
+                None
+            }
+        };
+        // 与单次分析一致，关键时刻相对第一帧；会话时间沿用各块给出的范围
+        let start = chunks
+            .iter()
+            .find_map(|(frames, _)| frames.first())
+            .map_or(partials[0].start_time, |frame| frame.timestamp);
+        let mut summary = session_merge::combine_chunks(reduced, &chunks, start);
+        summary.start_time = partials.iter().map(|p| p.start_time).min().unwrap();
+        summary.end_time = partials.iter().map(|p| p.end_time).max().unwrap();
+        summary.clamp_tag_time_ranges();
+        Ok(summary)
+    }
+
+    async fn analyze_once(
+        provider: &dyn LLMProvider,
+        frames: Vec<String>,
+        context: Option<&AnalysisContext>,
+    ) -> Result<SessionSummary> {
+        match context {
+            Some(context) => {
+                provider
+                    .analyze_frames_with_context(frames, context.clone())
+                    .await
+            }
+            None => provider.analyze_frames(frames).await,
+        }
+    }
+
+    /// 当前 provider 因配置的错误类型（配额、限流、欠费）失败时，按顺序用后备 provider 重新执行 call
+    ///
+    /// 成功时一并返回实际提供服务的 provider；后备 provider 也失败时返回原错误
+    async fn fail_over<T, F, Fut>(&self, result: Result<T>, call: F) -> Result<Served<T>>
+    where
+        F: Fn(Arc<dyn LLMProvider>) -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let primary = self.provider_key();
+        let error = match result {
+            Ok(value) => {
+                return Ok(Served {
+                    value,
+                    provider: primary,
+                    fallback: None,
+                })
+            }
+            Err(e) => e,
+        };
+        let config = self.config_lock.read().await.clone();
+        let Some(trigger) = config.failover.trigger_for(&error) else {
+            return Err(error);
+        };
+        let fallbacks = config.failover.providers.iter();
+        for name in fallbacks.filter(|name| **name != primary) {
+            if let Err(open) = self.metrics.try_acquire(name) {
+                warn!("{}", open);
+                continue;
+            }
+            let provider: Arc<dyn LLMProvider> = match self.build_fallback_provider(name, &config) {
+                Ok(provider) if provider.is_configured() => provider.into(),
+                Ok(_) => {
+                    warn!("后备 provider {} 未配置，跳过", name);
+                    continue;
+                }
+                Err(e) => {
+                    warn!("创建后备 provider {} 失败: {}", name, e);
+                    continue;
+                }
+            };
+            warn!(
+                "{} {}（{}），改用后备 provider {}",
+                primary, trigger, error, name
+            );
+            let started = std::time::Instant::now();
+            let result = call(provider.clone()).await;
+            self.record_call_for(name, started, &result);
+            match result {
+                Ok(value) => {
+                    return Ok(Served {
+                        value,
+                        provider: name.clone(),
+                        fallback: Some(provider),
+                    })
+                }
+                Err(e) => match config.failover.trigger_for(&e) {
+                    Some(trigger) => warn!("后备 provider {} 同样{}: {}", name, trigger, e),
+                    None => {
+                        warn!("后备 provider {} 分析失败: {}", name, e);
+                        break;
+                    }
+                },
+            }
+        }
+        Err(error)
+    }
+
+    /// 按已保存的配置创建后备 provider，并下发会话时间范围、进度通道与数据库连接
+    fn build_fallback_provider(
+        &self,
+        name: &str,
+        config: &LLMConfig,
+    ) -> Result<Box<dyn LLMProvider>> {
+        let client = || {
+            self.http_client
+                .clone()
+                .ok_or_else(|| anyhow!("无法创建 {} provider: HTTP 客户端未初始化", name))
+        };
+        let mut provider: Box<dyn LLMProvider> = match name {
+            "qwen" => Box::new(QwenProvider::new(client()?)),
+            "claude" => Box::new(ClaudeProvider::new()),
+            "codex" => Box::new(CodexProvider::new()),
+            "ollama" => Box::new(OllamaProvider::new(client()?)),
+            _ => return Err(anyhow!("不支持的 provider: {}", name)),
+        };
+        let saved = match name {
+            "qwen" => serde_json::to_value(&config.qwen)?,
+            "claude" => serde_json::to_value(&config.claude)?,
+            "codex" => serde_json::to_value(&config.codex)?,
+            _ => serde_json::to_value(&config.ollama)?,
+        };
+        provider.configure(saved)?;
+        provider.set_session_window(self.session_window.0, self.session_window.1);
+        provider.set_progress_sender(self.progress_tx.clone());
+        if let Some((db, session_id)) = &self.provider_database {
+            attach_database(provider.as_mut(), db.clone(), *session_id);
+        }
+        Ok(provider)
+    }
+
+    /// 生成多个候选总结供用户挑选
+    pub async fn analyze_frames_n(
+        &mut self,
+        frames: Vec<String>,
+        n: usize,
+    ) -> Result<Vec<SessionSummary>> {
+        info!(
+            "使用 {} 为 {} 帧生成 {} 个候选总结",
+            self.provider.name(),
+            frames.len(),
+            n
+        );
+
+        self.ensure_circuit_closed()?;
+        let started = std::time::Instant::now();
+        let result = self.provider.analyze_frames_n(frames, n).await;
+        self.record_call(started, &result);
+        match result {
+            Ok(mut candidates) => {
+                for candidate in &mut candidates {
+                    self.redact(candidate).await;
+                }
+                info!("生成了 {} 个不同的候选总结", candidates.len());
+                Ok(candidates)
+            }
+            Err(e) => {
+                error!("候选总结全部生成失败: {}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// 结合已有总结重新生成单个字段
+    pub async fn refine_field(
+        &mut self,
+        frames: Vec<String>,
+        current: SessionSummary,
+        field: SummaryField,
+    ) -> Result<SessionSummary> {
+        info!(
+            "使用 {} 为 {} 帧重新生成 {:?}",
+            self.provider.name(),
+            frames.len(),
+            field
+        );
+
+        self.ensure_circuit_closed()?;
+        let started = std::time::Instant::now();
+        let mut result = self.provider.refine_field(frames, current, field).await;
+        self.record_call(started, &result);
+        match &mut result {
+            Ok(summary) => self.redact(summary).await,
+            Err(e) => error!("重新生成 {:?} 失败: {}", field, e),
+        }
+        result
+    }
+
+    /// 分析纯文本会话（终端输出、剪贴板历史等，无截图）
+    pub async fn analyze_text(&mut self, content: String) -> Result<SessionSummary> {
+        info!(
+            "使用 {} 分析纯文本会话 ({} 字符)",
+            self.provider.name(),
+            content.chars().count()
+        );
+
+        self.ensure_circuit_closed()?;
+        let started = std::time::Instant::now();
+        let result = self.provider.analyze_text(content).await;
+        self.record_call(started, &result);
+        match result {
+            Ok(mut summary) => {
+                self.redact(&mut summary).await;
                 info!("分析成功: {}", summary.title);
                 Ok(summary)
             }
@@ -400,14 +1457,40 @@ impl LLMManager {
         }
     }
 
+    /// 从服务端刷新当前 provider 的模型能力
+    pub async fn refresh_capabilities(&mut self) -> Result<()> {
+        self.provider.refresh_capabilities().await
+    }
+
+    /// 估算当前 provider 分析指定帧数的费用（本地 provider 返回 None）
+    pub fn estimate_cost(&self, frames: usize) -> Option<CostEstimate> {
+        self.provider.estimate_cost(frames)
+    }
+
     /// 更新配置
-    pub async fn update_config(&self, config: LLMConfig) -> Result<()> {
+    pub async fn update_config(&mut self, config: LLMConfig) -> Result<()> {
+        redaction::Redactor::new(&config.redaction)?;
+        if let Some(min_confidence) = config.min_confidence {
+            confidence::validate_threshold(min_confidence)?;
+        }
+        config.failover.validate()?;
         let mut current_config = self.config_lock.write().await;
         *current_config = config;
+        self.provider.set_layers(provider_layers(&current_config));
+        self.sync_provider_status(&current_config);
         info!("LLM配置已更新");
         Ok(())
     }
 
+    /// 配置 provider 中间件，立即对当前 provider 生效
+    pub async fn configure_middleware(&mut self, middleware: MiddlewareConfig) -> Result<()> {
+        let mut current = self.config_lock.write().await;
+        current.middleware = middleware;
+        self.provider.set_layers(provider_layers(&current));
+        info!("Provider 中间件配置已更新: {:?}", current.middleware);
+        Ok(())
+    }
+
     /// 获取当前配置
     pub async fn get_config(&self) -> LLMConfig {
         self.config_lock.read().await.clone()
@@ -419,6 +1502,8 @@ impl LLMManager {
         db: Arc<crate::storage::Database>,
         session_id: Option<i64>,
     ) {
+        self.provider_database = Some((db.clone(), session_id));
+
         // Qwen provider
         if let Some(provider) = self.provider.as_any().downcast_mut::<QwenProvider>() {
             provider.set_database(db.clone());
@@ -466,9 +1551,14 @@ impl LLMManager {
         segments: Vec<VideoSegment>,
         previous_cards: Option<Vec<TimelineCard>>,
     ) -> Result<Vec<TimelineCard>> {
-        self.provider
+        self.ensure_circuit_closed()?;
+        let started = std::time::Instant::now();
+        let result = self
+            .provider
             .generate_timeline(segments, previous_cards)
-            .await
+            .await;
+        self.record_call(started, &result);
+        result
     }
 
     /// 获取最后一次LLM调用的ID
@@ -482,7 +1572,11 @@ impl LLMManager {
         date: &str,
         sessions: &[SessionBrief],
     ) -> Result<String> {
-        self.provider.generate_day_summary(date, sessions).await
+        self.ensure_circuit_closed()?;
+        let started = std::time::Instant::now();
+        let result = self.provider.generate_day_summary(date, sessions).await;
+        self.record_call(started, &result);
+        result
     }
 
     /// 分析视频并生成时间线（两阶段处理）
@@ -505,10 +1599,24 @@ impl LLMManager {
         );
 
         // 第一阶段：分段视频
-        let segments = match self.provider.segment_video(frames.clone(), duration).await {
-            Ok(segs) => {
-                info!("视频分段成功: {} 个segment", segs.len());
-                segs
+        self.ensure_circuit_closed()?;
+        let started = std::time::Instant::now();
+        let result = self.provider.segment_video(frames.clone(), duration).await;
+        self.record_call(started, &result);
+        let result = self
+            .fail_over(result, |provider| {
+                let frames = frames.clone();
+                async move { provider.segment_video(frames, duration).await }
+            })
+            .await;
+        let segmented = match result {
+            Ok(segmented) => {
+                info!(
+                    "视频分段成功: {} 个segment（{}）",
+                    segmented.value.len(),
+                    segmented.provider
+                );
+                segmented
             }
             Err(e) => {
                 error!("视频分段失败: {}", e);
@@ -519,16 +1627,42 @@ impl LLMManager {
                 return Err(e);
             }
         };
-
-        // 第二阶段：生成时间线
-        let timeline_cards = match self
-            .provider
-            .generate_timeline(segments.clone(), previous_cards)
-            .await
-        {
-            Ok(cards) => {
-                info!("时间线生成成功: {} 个卡片", cards.len());
-                cards
+        let segments = segmented.value.clone();
+
+        // 第二阶段：生成时间线（分段已由后备 provider 完成时沿用同一 provider）
+        let result = match &segmented.fallback {
+            Some(provider) => {
+                let started = std::time::Instant::now();
+                let result = provider
+                    .generate_timeline(segments.clone(), previous_cards)
+                    .await;
+                self.record_call_for(&segmented.provider, started, &result);
+                result.map(|value| Served {
+                    value,
+                    provider: segmented.provider.clone(),
+                    fallback: Some(provider.clone()),
+                })
+            }
+            None => {
+                self.ensure_circuit_closed()?;
+                let started = std::time::Instant::now();
+                let result = self
+                    .provider
+                    .generate_timeline(segments.clone(), previous_cards.clone())
+                    .await;
+                self.record_call(started, &result);
+                self.fail_over(result, |provider| {
+                    let segments = segments.clone();
+                    let previous_cards = previous_cards.clone();
+                    async move { provider.generate_timeline(segments, previous_cards).await }
+                })
+                .await
+            }
+        };
+        let timeline = match result {
+            Ok(timeline) => {
+                info!("时间线生成成功: {} 个卡片", timeline.value.len());
+                timeline
             }
             Err(e) => {
                 error!("时间线生成失败: {}", e);
@@ -536,16 +1670,87 @@ impl LLMManager {
             }
         };
 
-        let segment_call_id = self.provider.last_llm_call_id("segment_video");
-        let timeline_call_id = self.provider.last_llm_call_id("generate_timeline");
+        let segment_source = segmented.source(&self.provider);
+        let segment_call_id = segment_source.last_llm_call_id("segment_video");
+        let sampling_seed = segment_source.last_sampling_seed();
+        let timeline_call_id = timeline
+            .source(&self.provider)
+            .last_llm_call_id("generate_timeline");
+        // 会话总结由时间线卡片生成，记录生成时间线的 provider
+        let served_by = timeline.provider.clone();
+        self.save_analysis_record(&served_by, sampling_seed).await;
 
         Ok(TimelineAnalysis {
             segments,
-            timeline_cards,
+            timeline_cards: timeline.value,
             segment_call_id,
             timeline_call_id,
+            sampling_seed,
+            served_by,
         })
     }
+
+    /// 将实际使用的分析参数写入当前会话的分析记录；未关联会话时跳过，写入失败只记录告警
+    async fn save_analysis_record(&self, served_by: &str, sampling_seed: Option<u64>) {
+        let Some((db, Some(session_id))) = &self.provider_database else {
+            return;
+        };
+        let record = crate::storage::SessionAnalysisRecord {
+            session_id: *session_id,
+            served_by: Some(served_by.to_string()),
+            sampling_seed: sampling_seed.map(|seed| seed as i64),
+            created_at: crate::storage::local_now(),
+        };
+        if let Err(e) = db.save_session_analysis(&record).await {
+            warn!("保存会话 {} 的分析记录失败: {}", session_id, e);
+        }
+    }
+}
+
+/// 经故障转移得到的结果
+struct Served<T> {
+    value: T,
+    /// 实际提供服务的 provider 名称
+    provider: String,
+    /// 由后备 provider 提供服务时的实例
+    fallback: Option<Arc<dyn LLMProvider>>,
+}
+
+impl<T> Served<T> {
+    /// 实际提供服务的 provider 实例（用于查询其调用记录与采样种子）
+    fn source<'a>(&'a self, primary: &'a dyn LLMProvider) -> &'a dyn LLMProvider {
+        self.fallback.as_deref().unwrap_or(primary)
+    }
+}
+
+/// 为后备 provider 设置数据库连接与会话 ID（用于保存 LLM 调用记录）
+fn attach_database(
+    provider: &mut dyn LLMProvider,
+    db: Arc<crate::storage::Database>,
+    session_id: Option<i64>,
+) {
+    let any = provider.as_any();
+    if let Some(provider) = any.downcast_mut::<QwenProvider>() {
+        provider.set_database(db);
+        if let Some(sid) = session_id {
+            provider.set_session_id(sid);
+        }
+    } else if let Some(provider) = any.downcast_mut::<ClaudeProvider>() {
+        provider.set_database(db);
+        if let Some(sid) = session_id {
+            provider.set_session_id(sid);
+        }
+    } else if let Some(provider) = any.downcast_mut::<CodexProvider>() {
+        provider.set_database(db);
+        if let Some(sid) = session_id {
+            provider.set_session_id(sid);
+        }
+    } else if let Some(provider) = any.downcast_mut::<OllamaProvider>() {
+        provider.set_database(db);
+        if let Some(sid) = session_id {
+            provider.set_session_id(sid);
+        }
+    }
 }
 
 pub fn build_session_summary(
@@ -613,6 +1818,8 @@ pub fn build_session_summary(
                 category,
                 confidence: *weight, // 使用时间占比作为confidence
                 keywords,
+                subcategories: Vec::new(),
+                time_ranges: Vec::new(),
             });
         }
     }
@@ -624,6 +1831,8 @@ pub fn build_session_summary(
             category: map_category(&first_card.category),
             confidence: 1.0,
             keywords: vec![first_card.subcategory.clone()],
+            subcategories: Vec::new(),
+            time_ranges: Vec::new(),
         });
     }
 
@@ -661,23 +1870,20 @@ pub fn build_session_summary(
             })
     };
 
-    SessionSummary {
-        title,
-        summary,
-        tags,
-        start_time: window_start,
-        end_time: window_end,
-        key_moments: segments
-            .iter()
-            .map(|seg| KeyMoment {
-                time: seg.start_timestamp.clone(),
-                description: seg.description.clone(),
-                importance: 3,
-            })
-            .collect(),
-        productivity_score: Some(75.0),
-        focus_score: Some(80.0),
-    }
+    // 关键时刻取自模型输出的视频分段，时间格式由调用方的校验处理
+    let mut summary = segments
+        .iter()
+        .fold(SessionSummary::builder(), |builder, seg| {
+            builder.key_moment(seg.start_timestamp.clone(), seg.description.clone(), 3)
+        })
+        .title(title)
+        .summary(summary)
+        .tags(tags)
+        .time_range(window_start, window_end)
+        .scores(Some(75.0), Some(80.0))
+        .build_unchecked();
+    summary.detect_language(None);
+    summary
 }
 
 // 辅助函数：解析持续时间（MM:SS格式）
@@ -701,7 +1907,7 @@ fn parse_duration(start: &str, end: &str) -> Option<f32> {
 }
 
 // 辅助函数：映射类别
-fn map_category(category_str: &str) -> ActivityCategory {
+pub(crate) fn map_category(category_str: &str) -> ActivityCategory {
     match category_str.to_lowercase().as_str() {
         "work" | "coding" | "writing" | "design" | "planning" | "data_analysis" => {
             ActivityCategory::Work
@@ -797,6 +2003,24 @@ pub struct LLMProcessor {
     video_processor: Option<Arc<crate::video::VideoProcessor>>,
     settings: Arc<SettingsManager>,
     notion_manager: Option<Arc<crate::notion::NotionManager>>,
+    /// 退出协调器：退出时不再开始新分析，未完成的会话重新排队
+    shutdown: Option<Arc<crate::shutdown::ShutdownCoordinator>>,
+    /// 分析流水线：准备与推理分别限流，推理与用户触发的分析共享并发名额
+    analysis_pipeline: Option<AnalysisPipeline>,
+    /// 截屏看门狗记录的缺口，随会话入库
+    capture_gaps: Option<Arc<crate::capture::watchdog::CaptureGapLog>>,
+}
+
+/// 准备阶段的产物：筛帧、采样与视频生成的结果，交给推理阶段使用
+struct PreparedSession {
+    config: LLMConfig,
+    frames: Vec<crate::capture::ScreenFrame>,
+    window: crate::capture::scheduler::SessionWindow,
+    /// 采样后交给模型的帧
+    frame_paths: Vec<String>,
+    duration_minutes: u32,
+    video_path: Option<String>,
+    should_persist_frames: bool,
 }
 
 /// LLM两阶段分析的聚合结果
@@ -805,6 +2029,10 @@ pub struct TimelineAnalysis {
     pub timeline_cards: Vec<TimelineCard>,
     pub segment_call_id: Option<i64>,
     pub timeline_call_id: Option<i64>,
+    /// 分段时实际使用的随机采样种子（未开启随机采样时为空）
+    pub sampling_seed: Option<u64>,
+    /// 生成时间线的 provider（当前 provider 因配额、限流或欠费失败时可能是后备 provider）
+    pub served_by: String,
 }
 
 impl LLMProcessor {
@@ -819,6 +2047,9 @@ impl LLMProcessor {
             video_processor: None,
             settings,
             notion_manager: None,
+            shutdown: None,
+            analysis_pipeline: None,
+            capture_gaps: None,
         }
     }
 
@@ -834,6 +2065,9 @@ impl LLMProcessor {
             video_processor: Some(video_processor),
             settings,
             notion_manager: None,
+            shutdown: None,
+            analysis_pipeline: None,
+            capture_gaps: None,
         }
     }
 
@@ -851,6 +2085,105 @@ impl LLMProcessor {
             video_processor: Some(video_processor),
             settings,
             notion_manager: Some(notion_manager),
+            shutdown: None,
+            analysis_pipeline: None,
+            capture_gaps: None,
+        }
+    }
+
+    /// 接入退出协调器
+    pub fn with_shutdown(mut self, shutdown: Arc<crate::shutdown::ShutdownCoordinator>) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// 接入分析流水线，定时会话以后台优先级排队推理
+    pub fn with_analysis_pipeline(mut self, pipeline: AnalysisPipeline) -> Self {
+        self.analysis_pipeline = Some(pipeline);
+        self
+    }
+
+    /// 经由分析流水线执行：准备与推理分别限流；未接入流水线时依次执行
+    async fn run_in_pipeline<P>(&self, label: &str, prepare: P) -> Result<()>
+    where
+        P: std::future::Future<Output = Result<PreparedSession>>,
+    {
+        match &self.analysis_pipeline {
+            Some(pipeline) => {
+                pipeline
+                    .run(label, AnalysisPriority::Background, prepare, |prepared| {
+                        self.analyze_prepared(prepared)
+                    })
+                    .await
+            }
+            None => self.analyze_prepared(prepare.await?).await,
+        }
+    }
+
+    /// 接入截屏缺口记录，会话入库时写入与时间窗重叠的缺口
+    pub fn with_capture_gaps(mut self, gaps: Arc<crate::capture::watchdog::CaptureGapLog>) -> Self {
+        self.capture_gaps = Some(gaps);
+        self
+    }
+
+    /// 保存与会话时间窗重叠的截屏缺口
+    async fn save_capture_gaps(
+        &self,
+        session_id: i64,
+        window: &crate::capture::scheduler::SessionWindow,
+    ) {
+        let Some(log) = &self.capture_gaps else {
+            return;
+        };
+        let gaps: Vec<crate::storage::CaptureGapRecord> = log
+            .overlapping(window.start, window.end)
+            .into_iter()
+            .map(|gap| crate::storage::CaptureGapRecord {
+                id: None,
+                session_id,
+                gap_start: gap.start,
+                gap_end: gap.end,
+                restart_attempts: gap.restart_attempts as i64,
+            })
+            .collect();
+        if gaps.is_empty() {
+            return;
+        }
+        match self.db.save_capture_gaps(&gaps).await {
+            Ok(()) => warn!("会话 {} 中有 {} 段截屏缺口", session_id, gaps.len()),
+            Err(e) => error!("保存截屏缺口失败: {}", e),
+        }
+    }
+
+    /// 重新分析上次退出时未完成的会话：从数据库的分析队列恢复（含排队中与被中断的会话），
+    /// 删除残留的临时会话后重新发布会话完成事件
+    ///
+    /// 需在事件监听器启动后调用
+    pub async fn requeue_pending_sessions(&self, event_bus: &crate::event_bus::EventBus) {
+        use crate::storage::analysis_queue;
+
+        let pending =
+            match analysis_queue::rehydrate(&self.db, analysis_queue::DEFAULT_MAX_RESUME_ATTEMPTS)
+                .await
+            {
+                Ok(records) => records,
+                Err(e) => {
+                    error!("恢复分析队列失败: {}", e);
+                    return;
+                }
+            };
+
+        for record in pending {
+            info!(
+                "重新分析上次退出时未完成的会话: {} - {}",
+                record.window_start, record.window_end
+            );
+            event_bus.publish(crate::event_bus::AppEvent::SessionCompleted {
+                session_id: record.session_key,
+                frame_count: record.frame_count as usize,
+                window_start: record.window_start,
+                window_end: record.window_end,
+            });
         }
     }
 
@@ -878,62 +2211,112 @@ impl LLMProcessor {
                             session_id, frame_count, window_start, window_end
                         );
 
-                        // 发布分析开始事件
-                        event_bus
-                            .publish(crate::event_bus::AppEvent::AnalysisStarted { session_id });
-
-                        // 读取该时间段的所有frames
-                        let frames_result = Self::load_frames_for_window(
-                            &capture,
+                        // 先写入持久化的分析队列，崩溃后重启时恢复
+                        if let Err(e) = crate::storage::analysis_queue::enqueue(
+                            &self.db,
                             session_id,
+                            frame_count,
                             window_start,
                             window_end,
+                            AnalysisPriority::Background,
                         )
-                        .await;
+                        .await
+                        {
+                            warn!("写入分析队列失败: session_id={}, {}", session_id, e);
+                        }
 
-                        let frames = match frames_result {
-                            Ok(f) => f,
-                            Err(e) => {
-                                error!("读取frames失败: {}", e);
-                                event_bus.publish(crate::event_bus::AppEvent::AnalysisFailed {
+                        // 登记进行中的分析；应用正在退出时会话留在分析队列中，留待下次启动
+                        let _in_flight = match &self.shutdown {
+                            Some(shutdown) => {
+                                let pending = crate::shutdown::PendingSession {
                                     session_id,
-                                    error: e.to_string(),
-                                });
-                                continue;
+                                    frame_count,
+                                    window_start,
+                                    window_end,
+                                };
+                                match shutdown.begin(pending) {
+                                    Some(guard) => Some(guard),
+                                    None => continue,
+                                }
                             }
+                            None => None,
                         };
 
-                        if frames.is_empty() {
-                            warn!("该时间段没有有效frames，跳过分析");
-                            event_bus.publish(crate::event_bus::AppEvent::AnalysisFailed {
+                        // 每个会话独立执行：推理进行时后续会话可以同时准备
+                        let processor = self.clone();
+                        let event_bus = event_bus.clone();
+                        let capture = capture.clone();
+                        tokio::spawn(async move {
+                            let _in_flight = _in_flight;
+                            if let Err(e) = crate::storage::analysis_queue::mark_started(
+                                &processor.db,
                                 session_id,
-                                error: "没有有效frames".to_string(),
-                            });
-                            continue;
-                        }
+                            )
+                            .await
+                            {
+                                warn!("更新分析队列失败: session_id={}, {}", session_id, e);
+                            }
 
-                        // 构建SessionWindow
-                        let window = crate::capture::scheduler::SessionWindow {
-                            start: window_start,
-                            end: window_end,
-                        };
+                            // 发布分析开始事件
+                            event_bus.publish(crate::event_bus::AppEvent::AnalysisStarted {
+                                session_id,
+                            });
 
-                        // 执行分析
-                        match self.process_session(frames, window).await {
-                            Ok(_) => {
-                                info!("会话分析完成: session_id={}", session_id);
-                                // 注意：AnalysisCompleted事件将在未来由独立的分析流程发布
-                                // 当前process_session包含了完整的处理，包括视频生成
-                                // 这里暂时不发布AnalysisCompleted，避免重复处理
-                            }
-                            Err(e) => {
-                                error!("会话分析失败: session_id={}, 错误: {}", session_id, e);
-                                event_bus.publish(crate::event_bus::AppEvent::AnalysisFailed {
+                            let window = crate::capture::scheduler::SessionWindow {
+                                start: window_start,
+                                end: window_end,
+                            };
+                            let label = format!(
+                                "会话 {} - {}",
+                                window_start.format("%H:%M"),
+                                window_end.format("%H:%M")
+                            );
+                            let prepare = async {
+                                // 读取该时间段的所有frames
+                                let frames = Self::load_frames_for_window(
+                                    &capture,
                                     session_id,
-                                    error: e.to_string(),
-                                });
+                                    window_start,
+                                    window_end,
+                                )
+                                .await
+                                .map_err(|e| anyhow!("读取frames失败: {}", e))?;
+                                if frames.is_empty() {
+                                    return Err(anyhow!("该时间段没有有效frames"));
+                                }
+                                processor.prepare_session(frames, window).await
+                            };
+
+                            // 执行分析
+                            match processor.run_in_pipeline(&label, prepare).await {
+                                Ok(_) => {
+                                    info!("会话分析完成: session_id={}", session_id);
+                                    // 注意：AnalysisCompleted事件将在未来由独立的分析流程发布
+                                    // 当前process_session包含了完整的处理，包括视频生成
+                                    // 这里暂时不发布AnalysisCompleted，避免重复处理
+                                }
+                                // 帧数过少是预期内的跳过，不作为分析失败上报
+                                Err(e) if e.downcast_ref::<TooFewFrames>().is_some() => {
+                                    info!(
+                                        "会话帧数过少，跳过分析: session_id={}, {}",
+                                        session_id, e
+                                    );
+                                }
+                                Err(e) => {
+                                    error!("会话分析失败: session_id={}, 错误: {}", session_id, e);
+                                    event_bus.publish(crate::event_bus::AppEvent::AnalysisFailed {
+                                        session_id,
+                                        error: e.to_string(),
+                                    });
+                                }
                             }
-                        }
+                            if let Err(e) =
+                                crate::storage::analysis_queue::complete(&processor.db, session_id)
+                                    .await
+                            {
+                                warn!("移出分析队列失败: session_id={}, {}", session_id, e);
+                            }
+                        });
                     }
                     _ => {}
                 }
@@ -1017,12 +2400,33 @@ impl crate::capture::scheduler::SessionProcessor for LLMProcessor {
         frames: Vec<crate::capture::ScreenFrame>,
         window: crate::capture::scheduler::SessionWindow,
     ) -> Result<()> {
+        let label = format!(
+            "会话 {} - {}",
+            window.start.format("%H:%M"),
+            window.end.format("%H:%M")
+        );
+        self.run_in_pipeline(&label, self.prepare_session(frames, window))
+            .await
+    }
+}
+
+impl LLMProcessor {
+    /// 准备阶段（I/O 密集）：筛帧、采样并按配置生成会话视频
+    async fn prepare_session(
+        &self,
+        frames: Vec<crate::capture::ScreenFrame>,
+        window: crate::capture::scheduler::SessionWindow,
+    ) -> Result<PreparedSession> {
         // 获取配置
         let config = self.llm_handle.get_config().await?;
         let params = &config.analysis_params;
 
-        // 采样帧
-        let sampled_frames = self.sample_frames(&frames, params.frame_sampling_interval as usize);
+        // 剔除全黑、纯色帧后再采样
+        let content_frames = self
+            .filter_blank_frames(&frames, &params.frame_quality)
+            .await;
+        let sampled_frames =
+            self.sample_frames(&content_frames, params.frame_sampling_interval as usize);
 
         // 提取文件路径
         let frame_paths: Vec<String> = sampled_frames.iter().map(|f| f.file_path.clone()).collect();
@@ -1102,6 +2506,37 @@ impl crate::capture::scheduler::SessionProcessor for LLMProcessor {
             return Err(anyhow!("没有找到截图帧，无法创建会话"));
         }
 
+        Ok(PreparedSession {
+            config,
+            frames,
+            window,
+            frame_paths,
+            duration_minutes,
+            video_path,
+            should_persist_frames,
+        })
+    }
+
+    /// 推理阶段：调用模型分析并保存结果（在推理名额内执行）
+    /// 执行分析并记录分析次数、耗时与失败类型
+    async fn analyze_prepared(&self, prepared: PreparedSession) -> Result<()> {
+        let started = std::time::Instant::now();
+        let result = self.run_analysis(prepared).await;
+        crate::telemetry::global().record_analysis(started.elapsed(), &result);
+        result
+    }
+
+    async fn run_analysis(&self, prepared: PreparedSession) -> Result<()> {
+        let PreparedSession {
+            config,
+            frames,
+            window,
+            frame_paths,
+            duration_minutes,
+            video_path,
+            should_persist_frames,
+        } = prepared;
+
         // 先创建会话获取session_id（用于关联LLM调用记录）
         let (device_name, device_type) = crate::storage::get_device_info();
         let temp_session = crate::storage::Session {
@@ -1115,10 +2550,21 @@ impl crate::capture::scheduler::SessionProcessor for LLMProcessor {
             created_at: None,
             device_name: Some(device_name),
             device_type: Some(device_type),
+            pinned: false,
         };
 
         let session_id = self.db.insert_session(&temp_session).await?;
         info!("创建临时会话: ID={}", session_id);
+        if let Err(e) = crate::storage::analysis_queue::attach_temp_session(
+            &self.db,
+            window.start.timestamp_millis(),
+            session_id,
+        )
+        .await
+        {
+            warn!("记录临时会话到分析队列失败: {}", e);
+        }
+        self.save_capture_gaps(session_id, &window).await;
 
         // 记录视频路径，用于错误清理
         let video_path_for_cleanup = video_path.clone();
@@ -1145,9 +2591,11 @@ impl crate::capture::scheduler::SessionProcessor for LLMProcessor {
             {
                 Ok(result) => result,
                 Err(e) => {
-                    // 如果是视频过短错误，清理已创建的资源
-                    if e.to_string().contains("VIDEO_TOO_SHORT") {
-                        error!("检测到视频过短错误，开始清理资源...");
+                    // 如果是视频过短或帧数过少，清理已创建的资源
+                    if e.to_string().contains("VIDEO_TOO_SHORT")
+                        || e.downcast_ref::<TooFewFrames>().is_some()
+                    {
+                        error!("检测到视频过短或帧数过少，开始清理资源...");
 
                         // 1. 删除数据库中的会话记录
                         if let Err(del_err) = self.db.delete_session(session_id).await {
@@ -1176,6 +2624,8 @@ impl crate::capture::scheduler::SessionProcessor for LLMProcessor {
             mut timeline_cards,
             segment_call_id,
             timeline_call_id,
+            sampling_seed,
+            served_by: _,
         } = analysis;
 
         for segment in &mut segments {
@@ -1278,7 +2728,12 @@ impl crate::capture::scheduler::SessionProcessor for LLMProcessor {
         }
 
         // 从timeline卡片生成总结（使用第一个卡片的信息）
-        let summary = build_session_summary(window.start, window.end, &segments, &timeline_cards);
+        let mut summary =
+            build_session_summary(window.start, window.end, &segments, &timeline_cards);
+        summary.sampling_seed = sampling_seed;
+        redaction::redact_summary(&config.redaction, &mut summary);
+        self.redact_stored_extra_fields(session_id, &config.redaction)
+            .await;
 
         // 更新会话信息（之前已经创建了临时会话）
         self.db
@@ -1290,6 +2745,16 @@ impl crate::capture::scheduler::SessionProcessor for LLMProcessor {
                 &serde_json::to_string(&summary.tags)?,
             )
             .await?;
+        if let Err(e) = confidence::save_session_scores(
+            &self.db,
+            session_id,
+            &mut summary,
+            config.min_confidence,
+        )
+        .await
+        {
+            warn!("保存会话 {} 的评分失败: {}", session_id, e);
+        }
 
         // 保存帧数据（如果没有生成视频则保存路径，否则路径已被删除）
         if should_persist_frames {
@@ -1311,6 +2776,75 @@ impl crate::capture::scheduler::SessionProcessor for LLMProcessor {
             session_id, summary.title
         );
 
+        // 按配置识别深度工作时段（派生记录，失败不影响分析）
+        let deep_work = app_config.deep_work.clone().unwrap_or_default();
+        if deep_work.enabled {
+            let frame_times: Vec<DateTime<Utc>> = frames.iter().map(|f| f.timestamp).collect();
+            if let Err(e) = crate::storage::deep_work::refresh_session(
+                &self.db,
+                session_id,
+                &frame_times,
+                summary.focus_score,
+                &deep_work,
+            )
+            .await
+            {
+                warn!("识别会话 {} 的深度工作时段失败: {}", session_id, e);
+            }
+        }
+
+        // 按配置生成会话封面（须在清理原始帧之前，代表帧可能被清理）
+        let thumbnail = app_config.session_thumbnail.clone().unwrap_or_default();
+        if thumbnail.enabled && should_persist_frames {
+            if let Err(e) = crate::video::session_thumbnail::generate_session_thumbnail(
+                &self.db,
+                session_id,
+                &summary.key_moments,
+                &thumbnail,
+            )
+            .await
+            {
+                error!("生成会话 {} 的封面失败: {}", session_id, e);
+            }
+        }
+
+        // 分析成功后按配置清理原始帧（未得到时间线卡片的占位总结不清理）
+        let cleanup = app_config.frame_cleanup.clone().unwrap_or_default();
+        if cleanup.delete_frames_after_analysis
+            && should_persist_frames
+            && !timeline_cards.is_empty()
+        {
+            let keep_times: Vec<DateTime<Utc>> = if cleanup.keep_key_moment_frames {
+                summary
+                    .key_moments
+                    .iter()
+                    .filter_map(|moment| DateTime::parse_from_rfc3339(&moment.time).ok())
+                    .map(|time| time.naive_local().and_utc())
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            if let Err(e) =
+                crate::storage::cleaner::prune_session_frames(&self.db, session_id, &keep_times)
+                    .await
+            {
+                error!("清理会话 {} 的原始帧失败: {}", session_id, e);
+            }
+        }
+
+        // 按配置将剩余的零散帧打包为单个归档（无损，读取时透明还原）
+        let pack_frames = app_config
+            .frame_storage
+            .as_ref()
+            .is_some_and(|storage| storage.pack_frames_after_analysis);
+        if pack_frames && should_persist_frames {
+            if let Err(e) =
+                crate::storage::frame_pack::pack_session_frames(&self.db, session_id).await
+            {
+                error!("打包会话 {} 的帧失败: {}", session_id, e);
+            }
+        }
+
         // 异步同步到 Notion（不阻塞主流程）
         if let Some(notion_manager) = &self.notion_manager {
             if notion_manager.is_enabled().await {
@@ -1322,6 +2856,14 @@ impl crate::capture::scheduler::SessionProcessor for LLMProcessor {
             }
         }
 
+        // 后台推送分析完成通知（失败不影响分析）
+        crate::webhook::dispatch(
+            app_config.webhook.clone(),
+            &config.redaction,
+            session_id,
+            &summary,
+        );
+
         // 清理provider的视频路径，避免影响后续会话
         self.llm_handle.set_video_path(None).await?;
         Ok(())
@@ -1329,29 +2871,86 @@ impl crate::capture::scheduler::SessionProcessor for LLMProcessor {
 }
 
 impl LLMProcessor {
+    /// 脱敏 provider 已保存的自定义字段；没有记录或无需遮盖时不改写
+    async fn redact_stored_extra_fields(&self, session_id: i64, config: &RedactionConfig) {
+        let record = match self.db.get_session_extra_fields(session_id).await {
+            Ok(Some(record)) => record,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("读取会话 {} 的自定义字段失败: {}", session_id, e);
+                return;
+            }
+        };
+        let mut extra: std::collections::HashMap<String, serde_json::Value> =
+            serde_json::from_str(&record.fields).unwrap_or_default();
+        if redaction::redact_extra_fields(config, &mut extra) == 0 {
+            return;
+        }
+        let record = crate::storage::SessionExtraFieldsRecord {
+            fields: serde_json::to_string(&extra).unwrap_or_default(),
+            ..record
+        };
+        if let Err(e) = self.db.save_session_extra_fields(&record).await {
+            warn!("保存会话 {} 脱敏后的自定义字段失败: {}", session_id, e);
+        }
+    }
+
     /// 采样帧数据
     fn sample_frames(
         &self,
         frames: &[crate::capture::ScreenFrame],
         interval: usize,
     ) -> Vec<crate::capture::ScreenFrame> {
-        if frames.is_empty() {
-            return vec![];
-        }
-
-        let mut sampled = vec![frames[0].clone()]; // 始终包含第一帧
+        sample_frame_indices(frames.len(), interval)
+            .into_iter()
+            .map(|i| frames[i].clone())
+            .collect()
+    }
 
-        for i in (interval..frames.len()).step_by(interval) {
-            sampled.push(frames[i].clone());
+    /// 剔除全黑、纯色帧；全部被剔除时保留原始帧，仍由模型给出总结
+    async fn filter_blank_frames(
+        &self,
+        frames: &[crate::capture::ScreenFrame],
+        config: &FrameQualityConfig,
+    ) -> Vec<crate::capture::ScreenFrame> {
+        let paths: Vec<String> = frames.iter().map(|f| f.file_path.clone()).collect();
+        let config = config.clone();
+        let kept = match tokio::task::spawn_blocking(move || {
+            crate::video::frame_quality::content_frame_indices(&paths, &config)
+        })
+        .await
+        {
+            Ok(kept) => kept,
+            Err(e) => {
+                warn!("帧质量过滤失败，使用全部帧: {}", e);
+                return frames.to_vec();
+            }
+        };
+        if kept.is_empty() {
+            warn!(
+                "全部 {} 帧均为全黑或纯色画面，保留原始帧进行分析",
+                frames.len()
+            );
+            return frames.to_vec();
         }
+        kept.into_iter().map(|i| frames[i].clone()).collect()
+    }
+}
 
-        // 如果最后一帧没有被包含，添加它
-        if sampled.last().unwrap().timestamp != frames.last().unwrap().timestamp {
-            sampled.push(frames.last().unwrap().clone());
-        }
+/// 按固定间隔采样帧，返回被选中的帧下标（始终包含首帧和末帧）
+pub fn sample_frame_indices(len: usize, interval: usize) -> Vec<usize> {
+    if len == 0 {
+        return vec![];
+    }
+
+    let mut sampled: Vec<usize> = (0..len).step_by(interval.max(1)).collect();
 
-        sampled
+    // 如果最后一帧没有被包含，添加它
+    if sampled.last() != Some(&(len - 1)) {
+        sampled.push(len - 1);
     }
+
+    sampled
 }
 
 /// 清理 request_body 中的图片 base64 数据，避免数据库膨胀
@@ -0,0 +1,137 @@
+// 分析模式 - 按使用场景调整提示词的侧重点
+//
+// 同一份提示词难以兼顾不同场景：工作复盘关心产出与效率，学习记录关心学了什么，
+// 娱乐时段只需简要记录。模式只在提示词中追加侧重说明与重点类别，输出仍按同一 JSON schema 解析。
+// 内置 work、study、entertainment 三种模式；用户可在 OllamaConfig.analysis_modes 中新增或覆盖同名模式
+
+use super::plugin::ActivityCategory;
+use super::AnalysisModeConfig;
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+
+/// 生效的分析模式
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisMode {
+    /// 模式名（配置与会话中记录的名称）
+    pub name: String,
+    /// 显示名
+    pub label: String,
+    /// 追加到提示词的侧重说明
+    pub instructions: String,
+    /// 需要重点区分与细化的活动类别
+    pub focus_categories: Vec<ActivityCategory>,
+}
+
+/// 内置模式
+fn builtin_mode(name: &str) -> Option<AnalysisMode> {
+    let mode = |label: &str, instructions: &str, focus: Vec<ActivityCategory>| AnalysisMode {
+        name: name.to_string(),
+        label: label.to_string(),
+        instructions: instructions.to_string(),
+        focus_categories: focus,
+    };
+    let mode = match name {
+        "work" => mode(
+            "工作复盘",
+            "侧重工作产出与效率：说明完成了哪些任务、推进到哪一步、被什么打断；summary 以可用于日报的事实为主，productivity_score 与 focus_score 要反映实际投入程度。",
+            vec![ActivityCategory::Work, ActivityCategory::Communication],
+        ),
+        "study" => mode(
+            "学习记录",
+            "侧重学习内容：记录学习的主题、资料（书名、课程、文档、视频标题）与掌握到的知识点；keywords 优先写具体的概念和技术名词，key_moments 标出理解上的关键步骤。",
+            vec![ActivityCategory::Learning],
+        ),
+        "entertainment" => mode(
+            "娱乐时段",
+            "侧重简要记录：说明在看什么、玩什么或浏览了哪些内容即可，不要评价效率，也不要把娱乐内容归为工作或学习。",
+            vec![ActivityCategory::Personal],
+        ),
+        _ => return None,
+    };
+    Some(mode)
+}
+
+/// 按名称查找模式：配置中的同名模式优先于内置模式
+pub fn resolve_mode(
+    name: &str,
+    overrides: &BTreeMap<String, AnalysisModeConfig>,
+) -> Option<AnalysisMode> {
+    let name = name.trim();
+    if let Some(config) = overrides.get(name) {
+        return Some(AnalysisMode {
+            name: name.to_string(),
+            label: Some(config.label.trim())
+                .filter(|label| !label.is_empty())
+                .unwrap_or(name)
+                .to_string(),
+            instructions: config.instructions.trim().to_string(),
+            focus_categories: config.focus_categories.clone(),
+        });
+    }
+    builtin_mode(name)
+}
+
+/// 校验配置：自定义模式须有侧重说明，默认模式须能找到
+pub fn validate_modes(
+    default_mode: Option<&str>,
+    overrides: &BTreeMap<String, AnalysisModeConfig>,
+) -> Result<()> {
+    for (name, config) in overrides {
+        if name.trim().is_empty() {
+            return Err(anyhow!("分析模式名不能为空"));
+        }
+        if config.instructions.trim().is_empty() {
+            return Err(anyhow!("分析模式 {} 缺少 instructions", name));
+        }
+    }
+    if let Some(name) = default_mode {
+        if resolve_mode(name, overrides).is_none() {
+            return Err(anyhow!("未知的分析模式 {}", name));
+        }
+    }
+    Ok(())
+}
+
+/// 提示词中的模式说明
+pub fn build_mode_note(mode: &AnalysisMode) -> String {
+    let mut note = format!("\n本次为「{}」模式：{}", mode.label, mode.instructions);
+    if !mode.focus_categories.is_empty() {
+        let categories: Vec<String> = mode
+            .focus_categories
+            .iter()
+            .map(|category| format!("{}（{}）", category.as_str(), category.to_chinese()))
+            .collect();
+        note.push_str(&format!(
+            "\n重点关注 {} 类活动，为它们给出更细的 keywords 与 key_moments；其他活动照常简要记录，category 按实际内容判断，不要为了迎合模式而改变分类。",
+            categories.join("、")
+        ));
+    }
+    note
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_take_precedence_over_builtin_modes() {
+        let overrides = BTreeMap::from([(
+            "study".to_string(),
+            AnalysisModeConfig {
+                label: String::new(),
+                instructions: "  记录背过的单词  ".to_string(),
+                focus_categories: vec![ActivityCategory::Learning],
+            },
+        )]);
+        let study = resolve_mode("study", &overrides).unwrap();
+        assert_eq!(study.label, "study");
+        assert_eq!(study.instructions, "记录背过的单词");
+        assert_eq!(resolve_mode("work", &overrides).unwrap().label, "工作复盘");
+        assert!(resolve_mode("travel", &overrides).is_none());
+
+        assert!(validate_modes(Some("entertainment"), &overrides).is_ok());
+        assert!(validate_modes(Some("travel"), &overrides).is_err());
+        let empty = BTreeMap::from([("travel".to_string(), AnalysisModeConfig::default())]);
+        assert!(validate_modes(None, &empty).is_err());
+    }
+}
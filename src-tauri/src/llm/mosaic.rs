@@ -0,0 +1,321 @@
+// 拼图模式 - 把采样帧拼成少数几张网格图片发送，而不是逐帧发送
+//
+// 部分模型单次能接收的图片数有限，或多图时上下文很快耗尽；把多帧缩小后拼成一张网格图，
+// 同样的图片数可以覆盖更多帧。每格左上角标注该帧相对最早一帧的时间（MM:SS），
+// 提示词中说明网格的排列方式，模型据此填写 key_moments 与 time_ranges。
+// 标注使用内置的点阵数字，不依赖字体文件
+
+use super::budget::frame_timestamp_ms;
+use anyhow::{anyhow, Result};
+use image::{DynamicImage, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// 拼图的 JPEG 质量
+const MOSAIC_JPEG_QUALITY: u8 = 80;
+
+/// 网格的最大行数与列数
+const MAX_GRID_SIDE: u32 = 6;
+
+/// 每格宽度的范围（像素）
+const TILE_WIDTH_RANGE: std::ops::RangeInclusive<u32> = 64..=2048;
+
+/// 拼图配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MosaicConfig {
+    /// 每张拼图的列数
+    pub columns: u32,
+    /// 每张拼图的行数（最后一张拼图只保留用到的行）
+    pub rows: u32,
+    /// 每格的宽度（像素），高度按第一帧的宽高比计算
+    pub tile_width: u32,
+}
+
+impl Default for MosaicConfig {
+    fn default() -> Self {
+        Self {
+            columns: 3,
+            rows: 3,
+            tile_width: 512,
+        }
+    }
+}
+
+impl MosaicConfig {
+    /// 每张拼图的格数
+    pub fn tiles(&self) -> usize {
+        (self.columns * self.rows) as usize
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=MAX_GRID_SIDE).contains(&self.columns) || !(1..=MAX_GRID_SIDE).contains(&self.rows)
+        {
+            return Err(anyhow!(
+                "mosaic 的 columns 与 rows 须在 1-{} 之间",
+                MAX_GRID_SIDE
+            ));
+        }
+        if !TILE_WIDTH_RANGE.contains(&self.tile_width) {
+            return Err(anyhow!(
+                "mosaic.tile_width 须在 {}-{} 之间",
+                TILE_WIDTH_RANGE.start(),
+                TILE_WIDTH_RANGE.end()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 帧数对应的拼图张数
+pub fn composite_count(frames: usize, config: &MosaicConfig) -> usize {
+    frames.div_ceil(config.tiles().max(1))
+}
+
+/// 提示词中的拼图说明
+pub fn build_mosaic_note(frames: usize, config: &MosaicConfig, reverse: bool) -> String {
+    let order = if reverse {
+        "按时间倒序排列（第一格最晚）"
+    } else {
+        "按时间先后排列（第一格最早）"
+    };
+    format!(
+        "以下 {} 张图片是由 {} 张截图缩小后拼成的网格（每行 {} 格），每张图片内从左到右、从上到下{}，多张图片依次衔接。\n每格左上角标注该截图相对最早一张截图的时间（MM:SS），填写 key_moments 与 time_ranges 时以这些时间为准；请把每一格当作一张独立的截图，不要把相邻格的内容混为一个画面。",
+        composite_count(frames, config),
+        frames,
+        config.columns,
+        order
+    )
+}
+
+/// 生成的拼图，保存在临时目录中，释放时一并删除
+pub struct MosaicImages {
+    _dir: tempfile::TempDir,
+    /// 拼图文件路径（按顺序）
+    pub paths: Vec<String>,
+    /// 实际拼入的帧（读取失败的帧跳过），顺序与格子一致
+    pub frames: Vec<String>,
+}
+
+impl MosaicImages {
+    /// 按给定顺序把帧拼成网格图片；没有可读取的帧时返回错误
+    pub async fn create(frames: &[String], config: &MosaicConfig) -> Result<Self> {
+        let frames = frames.to_vec();
+        let config = config.clone();
+        tokio::task::spawn_blocking(move || Self::create_blocking(&frames, &config)).await?
+    }
+
+    fn create_blocking(frames: &[String], config: &MosaicConfig) -> Result<Self> {
+        let mut images = Vec::new();
+        let mut included = Vec::new();
+        for frame in frames {
+            let decoded = crate::storage::frame_pack::read_frame_blocking(frame)
+                .and_then(|bytes| Ok(image::load_from_memory(&bytes)?));
+            match decoded {
+                Ok(image) => {
+                    images.push(image);
+                    included.push(frame.clone());
+                }
+                Err(e) => warn!("拼图: 读取帧失败，跳过 path={} err={}", frame, e),
+            }
+        }
+        let first = images
+            .first()
+            .ok_or_else(|| anyhow!("没有可用的图片帧用于拼图"))?;
+        let tile_width = config.tile_width;
+        let tile_height =
+            ((first.height() as u64 * tile_width as u64 / first.width().max(1) as u64) as u32)
+                .max(1);
+
+        let first_ms = included.iter().filter_map(|f| frame_timestamp_ms(f)).min();
+        let labels: Vec<String> = included
+            .iter()
+            .enumerate()
+            .map(
+                |(index, frame)| match first_ms.zip(frame_timestamp_ms(frame)) {
+                    Some((first, ts)) => {
+                        let secs = (ts - first).max(0) / 1000;
+                        format!("{:02}:{:02}", secs / 60, secs % 60)
+                    }
+                    None => (index + 1).to_string(),
+                },
+            )
+            .collect();
+
+        let dir = tempfile::tempdir()?;
+        let mut paths = Vec::new();
+        let tiles = config.tiles().max(1);
+        for (index, (chunk, chunk_labels)) in
+            images.chunks(tiles).zip(labels.chunks(tiles)).enumerate()
+        {
+            let canvas = render_grid(chunk, chunk_labels, config.columns, tile_width, tile_height);
+            let path = dir.path().join(format!("mosaic_{}.jpg", index + 1));
+            write_jpeg(&canvas, &path)?;
+            paths.push(path.to_string_lossy().into_owned());
+        }
+        Ok(Self {
+            _dir: dir,
+            paths,
+            frames: included,
+        })
+    }
+}
+
+/// 把一组帧画到网格上：每帧等比缩放后居中放入格子，左上角绘制时间标注
+fn render_grid(
+    images: &[DynamicImage],
+    labels: &[String],
+    columns: u32,
+    tile_width: u32,
+    tile_height: u32,
+) -> RgbImage {
+    let columns = columns.min(images.len() as u32).max(1);
+    let rows = (images.len() as u32).div_ceil(columns);
+    let mut canvas = RgbImage::new(columns * tile_width, rows * tile_height);
+    let label_scale = (tile_width / 160).max(2);
+    for (index, (image, label)) in images.iter().zip(labels).enumerate() {
+        let x = (index as u32 % columns) * tile_width;
+        let y = (index as u32 / columns) * tile_height;
+        let tile = image
+            .resize(
+                tile_width,
+                tile_height,
+                image::imageops::FilterType::Triangle,
+            )
+            .to_rgb8();
+        let offset_x = x + (tile_width - tile.width()) / 2;
+        let offset_y = y + (tile_height - tile.height()) / 2;
+        image::imageops::overlay(&mut canvas, &tile, offset_x as i64, offset_y as i64);
+        draw_label(&mut canvas, x, y, label, label_scale);
+    }
+    canvas
+}
+
+/// 3x5 点阵字形（每行低 3 位有效，高位在左），支持数字与冒号
+fn glyph(c: char) -> Option<[u8; 5]> {
+    let rows = match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        _ => return None,
+    };
+    Some(rows)
+}
+
+/// 在 (x, y) 处绘制黑底白字的标注，超出画布的部分裁掉
+fn draw_label(canvas: &mut RgbImage, x: u32, y: u32, text: &str, scale: u32) {
+    let glyphs: Vec<[u8; 5]> = text.chars().filter_map(glyph).collect();
+    if glyphs.is_empty() {
+        return;
+    }
+    let padding = scale;
+    let advance = 4 * scale;
+    let width = glyphs.len() as u32 * advance - scale + 2 * padding;
+    let height = 5 * scale + 2 * padding;
+    let mut put = |px: u32, py: u32, color: Rgb<u8>| {
+        if px < canvas.width() && py < canvas.height() {
+            canvas.put_pixel(px, py, color);
+        }
+    };
+    for dy in 0..height {
+        for dx in 0..width {
+            put(x + dx, y + dy, Rgb([0, 0, 0]));
+        }
+    }
+    for (index, rows) in glyphs.iter().enumerate() {
+        let left = x + padding + index as u32 * advance;
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..3u32 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                for sy in 0..scale {
+                    for sx in 0..scale {
+                        put(
+                            left + col * scale + sx,
+                            y + padding + row as u32 * scale + sy,
+                            Rgb([255, 255, 255]),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn write_jpeg(canvas: &RgbImage, path: &std::path::Path) -> Result<()> {
+    use image::codecs::jpeg::JpegEncoder;
+
+    let mut buf = Vec::new();
+    JpegEncoder::new_with_quality(&mut buf, MOSAIC_JPEG_QUALITY).encode(
+        canvas.as_raw(),
+        canvas.width(),
+        canvas.height(),
+        image::ColorType::Rgb8,
+    )?;
+    std::fs::write(path, buf)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mosaic_produces_expected_composites() {
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<String> = (0..11i64)
+            .map(|i| {
+                let path = dir
+                    .path()
+                    .join(format!("{}.png", 1_700_000_000_000i64 + i * 65_000));
+                RgbImage::from_pixel(320, 180, Rgb([200, 60, 60]))
+                    .save(&path)
+                    .unwrap();
+                path.to_string_lossy().into_owned()
+            })
+            .collect();
+        let config = MosaicConfig {
+            columns: 2,
+            rows: 2,
+            tile_width: 160,
+        };
+        assert!(config.validate().is_ok());
+        assert_eq!(composite_count(frames.len(), &config), 3);
+        assert_eq!(composite_count(4, &config), 1);
+
+        let mosaic = MosaicImages::create(&frames, &config).await.unwrap();
+        assert_eq!(mosaic.paths.len(), 3);
+        assert_eq!(mosaic.frames, frames);
+        // 2×2 网格，每格 160×90；最后一张只有 3 帧，仍占两行
+        let full = image::open(&mosaic.paths[0]).unwrap();
+        assert_eq!((full.width(), full.height()), (320, 180));
+        let last = image::open(&mosaic.paths[2]).unwrap();
+        assert_eq!((last.width(), last.height()), (320, 180));
+
+        // 只有一帧时网格收缩为一格；左上角为黑底白字的标注，其余为帧内容
+        let frame = DynamicImage::ImageRgb8(RgbImage::from_pixel(320, 180, Rgb([200, 60, 60])));
+        let grid = render_grid(&[frame], &["01:05".to_string()], 2, 160, 90);
+        assert_eq!(grid.dimensions(), (160, 90));
+        assert_eq!(*grid.get_pixel(0, 0), Rgb([0, 0, 0]));
+        assert_eq!(*grid.get_pixel(2, 2), Rgb([255, 255, 255]));
+        assert!(grid.get_pixel(80, 60)[0] > 150);
+
+        let note = build_mosaic_note(frames.len(), &config, false);
+        assert!(note.contains("以下 3 张图片是由 11 张截图"));
+        assert!(MosaicConfig {
+            columns: 0,
+            ..MosaicConfig::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
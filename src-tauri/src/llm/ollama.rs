@@ -1,35 +1,221 @@
 // src-tauri/src/llm/ollama.rs
 
 use super::plugin::*;
+use super::sanitize::SanitizeLevel;
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use base64::{engine::general_purpose, Engine as _};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tracing::{debug, info, warn};
+use tracing::{debug, info, info_span, warn, Instrument};
 use std::sync::Arc;
 
+use candidates::CandidateSampling;
+
+mod analysis_mode;
+mod body;
+mod bounded;
+mod candidates;
+mod compress;
+mod confidence;
+mod config;
+mod downscale;
+mod empty_retry;
+mod extra_fields;
+mod frame_files;
+mod few_shot;
+mod frame_selection;
+mod headers;
+mod idle;
+mod keep_alive;
+mod lifecycle;
+mod model_select;
+mod mosaic;
+mod parse;
+mod payload;
+mod profile;
+mod prompt;
+mod pull;
+mod reconnect;
+mod reference;
+mod refine;
+mod sampling;
+mod schema;
+mod show;
+mod stream;
+mod taxonomy;
+mod text;
+mod times;
+mod timeouts;
+
+pub use downscale::OllamaImageTooLarge;
+pub use pull::{OllamaModelMissing, OllamaPullFailed};
+pub use stream::OllamaStreamInterrupted;
+pub use timeouts::{OllamaReadTimeout, OllamaUnreachable};
+
 pub struct OllamaProvider {
-    client: Client,
+    /// 连续无法连接时由 ReconnectLayer 触发重建，见 reconnect 模块
+    client: std::sync::RwLock<Client>,
+    /// 重建连接时沿用的附加请求头
+    client_headers: reqwest::header::HeaderMap,
     base_url: String, // e.g. http://localhost:11434
     model: String,    // e.g. qwen3-vl:32b
     configured: bool,
     // 新增：用于记录 LLM 调用、写库等（先放着也行）
     db: Option<Arc<crate::storage::Database>>,
     session_id: Option<i64>,
+    /// 提示词中说明图片按时间顺序排列
+    frame_order_hint: bool,
+    /// 提示词中逐帧列出 [序号] 说明
+    frame_index_captions: bool,
+    /// 是否采信模型返回的 start_time/end_time
+    trust_model_times: bool,
+    /// 当前分析的真实会话时间范围（UTC）
+    session_window_start: Option<DateTime<Utc>>,
+    session_window_end: Option<DateTime<Utc>>,
+    /// 使用流式响应
+    stream: bool,
+    /// 最大生成 token 数（options.num_predict）
+    num_predict: Option<u32>,
+    /// 流式进度通道
+    progress_tx: Option<ProgressSender>,
+    /// 在每个采样区间内随机取帧
+    sample_jitter: bool,
+    /// 随机采样种子（未设置时由帧路径推导）
+    sampling_seed: Option<u64>,
+    /// 最后一次分析实际使用的采样种子
+    last_sampling_seed: std::sync::Mutex<Option<u64>>,
+    /// 请求体大小上限（字节）
+    max_payload_bytes: Option<usize>,
+    /// 非流式请求期间轮询 /api/ps 的间隔（秒）
+    keep_alive_poll_secs: Option<u64>,
+    /// 拼入提示词的不可信文本（转写、会话文本）的过滤强度
+    prompt_sanitization: SanitizeLevel,
+    /// 最近一次应用的完整配置，部分更新时在此基础上合并
+    config: super::OllamaConfig,
+    /// 摘要的输出语言
+    output_language: Option<String>,
+    /// 最小分析帧数
+    min_frames_for_analysis: usize,
+    /// 参考截图（少样本示例）
+    reference_images: Vec<super::ReferenceImage>,
+    /// 空摘要时由标签推导摘要（关闭时视为解析失败）
+    derive_empty_summary: bool,
+    /// 按帧数选择模型的规则（按 max_frames 升序）
+    model_rules: Vec<super::ModelRule>,
+    /// 用户对内置提示词配置的覆盖（按模型名或家族）
+    prompt_profiles: std::collections::BTreeMap<String, super::PromptProfileOverride>,
+    /// 最近一次从 /api/show 查询到的模型能力
+    reported_capabilities: Option<show::ReportedCapabilities>,
+    /// 建立连接的超时（秒）
+    connect_timeout_secs: u64,
+    /// 等待模型返回的超时（秒）
+    read_timeout_secs: u64,
+    /// 在总结中记录实际发送的帧
+    record_sampled_frames: bool,
+    /// 本地判定空闲会话（黑屏、静止画面）
+    detect_idle_frames: bool,
+    /// 每分钟采样帧数（None 表示按固定上限采样）
+    frames_per_minute: Option<f32>,
+    /// 注入的人工更正示例数（0 表示不使用）
+    few_shot_examples: usize,
+    /// 自定义总结字段
+    extra_fields: Vec<super::ExtraFieldSpec>,
+    /// 默认分析模式
+    analysis_mode: Option<String>,
+    /// 自定义分析模式（覆盖同名内置模式）
+    analysis_modes: std::collections::BTreeMap<String, super::AnalysisModeConfig>,
+    /// 发送给模型的帧顺序
+    frame_order: super::FrameOrder,
+    /// 输出多个 JSON 对象时的选取方式
+    json_selection: super::JsonSelection,
+    /// 分类体系（顶层类别下的子类别）
+    taxonomy: super::taxonomy::Taxonomy,
+    /// 模型输出的 JSON Schema 校验方式
+    schema_validation: super::SchemaValidation,
+    /// 拼图模式配置（未设置时逐帧发送）
+    mosaic: Option<super::mosaic::MosaicConfig>,
+    /// 以 gzip 压缩 /api/chat 请求体
+    compress_request: bool,
+    /// 按前台应用覆盖采样（未设置时按时间均匀采样）
+    app_coverage: Option<super::app_coverage::AppCoverageConfig>,
+    /// 按场景采样（未设置时不启用）
+    scene_aware: Option<super::scene_sampling::SceneAwareConfig>,
+    /// 保留模型的思考过程
+    capture_reasoning: bool,
+    /// 总结整体置信度的下限
+    min_confidence: Option<f32>,
+    /// 低置信度时重新分析使用的模型
+    low_confidence_model: Option<String>,
+    /// 同时保存在内存中的已编码图片数上限
+    max_encoded_frames_in_memory: Option<usize>,
+    /// 单次分析的帧数硬上限，超出时分块分析后合并
+    max_frames_hard_limit: Option<usize>,
+    /// 服务端已拒绝过压缩的请求体，之后不再压缩（重新配置时清除）
+    gzip_rejected: std::sync::atomic::AtomicBool,
 }
 
 impl OllamaProvider {
     pub fn new(client: Client) -> Self {
+        debug_assert!(
+            schema::validate_schema_example().is_ok(),
+            "Ollama 提示词示例与 SessionSummary 不一致: {:?}",
+            schema::validate_schema_example().err()
+        );
         Self {
-            client,
+            client: std::sync::RwLock::new(client),
+            client_headers: reqwest::header::HeaderMap::new(),
             base_url: "http://100.82.18.91:11434".to_string(),
             model: "qwen3-vl:32b".to_string(),
             configured: true, // Ollama 通常不需要 key；有 base_url 就算可用
             db: None,
             session_id: None,
+            frame_order_hint: true,
+            frame_index_captions: false,
+            trust_model_times: false,
+            session_window_start: None,
+            session_window_end: None,
+            stream: false,
+            num_predict: None,
+            progress_tx: None,
+            sample_jitter: false,
+            sampling_seed: None,
+            last_sampling_seed: std::sync::Mutex::new(None),
+            max_payload_bytes: None,
+            keep_alive_poll_secs: None,
+            prompt_sanitization: SanitizeLevel::default(),
+            config: super::OllamaConfig::default(),
+            output_language: None,
+            min_frames_for_analysis: 1,
+            reference_images: Vec::new(),
+            derive_empty_summary: true,
+            model_rules: Vec::new(),
+            prompt_profiles: std::collections::BTreeMap::new(),
+            reported_capabilities: None,
+            connect_timeout_secs: 5,
+            read_timeout_secs: 300,
+            record_sampled_frames: false,
+            detect_idle_frames: true,
+            frames_per_minute: None,
+            few_shot_examples: 0,
+            extra_fields: Vec::new(),
+            analysis_mode: None,
+            analysis_modes: std::collections::BTreeMap::new(),
+            frame_order: super::FrameOrder::Chronological,
+            json_selection: super::JsonSelection::Last,
+            taxonomy: Default::default(),
+            schema_validation: super::SchemaValidation::Off,
+            mosaic: None,
+            compress_request: false,
+            app_coverage: None,
+            scene_aware: None,
+            capture_reasoning: false,
+            min_confidence: None,
+            low_confidence_model: None,
+            max_encoded_frames_in_memory: None,
+            max_frames_hard_limit: None,
+            gzip_rejected: Default::default(),
         }
     }
 
@@ -41,129 +227,387 @@ impl OllamaProvider {
         self.session_id = Some(session_id);
     }
     
-    fn sample_frames(&self, frames: &[String], max_frames: usize) -> Vec<String> {
-        if frames.len() <= max_frames {
-            return frames.to_vec();
-        }
-        let step = (frames.len() / max_frames).max(1);
-        frames.iter().step_by(step).take(max_frames).cloned().collect()
-    }
-
     async fn image_to_base64(&self, path: &str) -> Result<String> {
-        let bytes = tokio::fs::read(path).await?;
+        let bytes = crate::storage::frame_pack::read_frame(path).await?;
         Ok(general_purpose::STANDARD.encode(bytes))
     }
 
-    fn build_prompt(&self) -> String {
+    fn build_prompt(&self, context: &AnalysisContext, frames: &[String]) -> String {
         // 建议沿用你们 Qwen/Claude 的结构化输出要求，确保可解析为 SessionSummary
-        r#"
-请分析这些屏幕截图，识别用户的活动并输出 严格 JSON（不要多余文本，不要 markdown）。
-
-JSON schema:
-{
-  "title": "10字以内",
-  "summary": "50-100字",
-  "tags": [
-    {"category":"work|communication|learning|personal|idle|other","confidence":0.0,"keywords":["..."]}
-  ],
-  "key_moments": [
-    {"time":"MM:SS","description":"...","importance":1}
-  ],
-  "productivity_score": 0,
-  "focus_score": 0
-}
-
-只返回 JSON。
-"#.trim().to_string()
-    }
-
-    async fn call_ollama_chat(&self, images_b64: Vec<String>) -> Result<String> {
-        let url = format!("{}/api/chat", self.base_url.trim_end_matches('/'));
+        let mut prompt = String::new();
+        if let Some(note) = self.layout_note(frames) {
+            prompt.push_str(&note);
+            prompt.push_str("\n\n");
+        }
+        prompt.push_str(
+            "请分析这些屏幕截图，识别用户的活动并输出 严格 JSON（不要多余文本，不要 markdown）。\n\nJSON schema:\n",
+        );
+        prompt.push_str(schema::PROMPT_SCHEMA_EXAMPLE);
+        prompt.push_str(
+            "\n其中 category 取值 work|communication|learning|personal|idle|other，confidence 为 0-1，importance 为 1-5，评分为 0-100。",
+        );
+        prompt.push_str(prompt::time_ranges_note(self.frame_order));
+        if let Some(note) = self.taxonomy_note() {
+            prompt.push_str(&note);
+        }
+        if let Some(mode) = self.analysis_mode_for(context) {
+            prompt.push_str(&super::modes::build_mode_note(&mode));
+        }
+        if let Some(note) = self.extra_fields_note() {
+            prompt.push_str(&note);
+        }
+        prompt.push_str(
+            "\n如果截图中没有任何有意义的活动（空闲、离开、锁屏或黑屏），不要编造内容，只输出 {\"empty\": true, \"summary\": \"一句话说明\"}。",
+        );
+        if let Some(language) = &self.output_language {
+            prompt.push_str(&format!(
+                "\ntitle、summary、keywords、description 等文本字段请使用 {} 语言输出。",
+                language
+            ));
+        }
 
-        #[derive(Serialize)]
-        struct Msg {
-            role: String,
-            content: String,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            images: Option<Vec<String>>,
+        if let Some(section) = prompt::transcript_section(context.transcript.as_deref(), self.prompt_sanitization) {
+            prompt.push_str(&section);
         }
-        #[derive(Serialize)]
-        struct Req {
-            model: String,
-            stream: bool,
-            messages: Vec<Msg>,
+
+        if let Some(section) = prompt::visited_urls_section(&context.visited_urls, self.prompt_sanitization) {
+            prompt.push_str(&section);
         }
-        #[derive(Deserialize)]
-        struct Resp {
-            message: RespMsg,
+
+        if let Some(section) = prompt::input_activity_section(context.input_activity.as_ref()) {
+            prompt.push_str(&section);
         }
-        #[derive(Deserialize)]
-        struct RespMsg {
-            content: String,
+
+        // 用户对相似会话的人工更正，帮助模型沿用用户习惯的分类
+        if let Some(note) = super::corrections::build_examples_note(&context.examples) {
+            prompt.push_str("\n\n");
+            prompt.push_str(&note);
         }
 
-        let req = Req {
-            model: self.model.clone(),
-            stream: false,
-            messages: vec![Msg {
-                role: "user".to_string(),
-                content: self.build_prompt(),
-                images: Some(images_b64),
-            }],
-        };
+        prompt.push_str("\n\n只返回 JSON。");
+        prompt
+    }
+
+    /// 调用 /api/chat；模型返回空内容（或只有空白）时返回 EmptyResponse
+    ///
+    /// 空内容与图片过大的重试由 EmptyResponseLayer、DownscaleLayer 负责，这里只按 retry_hints() 附加提醒或缩小图片
+    async fn call_ollama_chat(
+        &self,
+        model: &str,
+        prompt: String,
+        images: Vec<body::ChatImage>,
+        candidate: Option<CandidateSampling>,
+    ) -> Result<String> {
+        let prompt = empty_retry::nudged_prompt(prompt);
+        let images = Self::downscaled_images(images).await?;
+        self.check_payload_size(&prompt, &images)?;
 
-        let resp: Resp = self
-            .client
-            .post(url)
-            .json(&req)
-            .send()
-            .await?
-            .error_for_status()?
-            .json()
+        // 图片以共享切片交给请求体，按需逐张写出
+        let content = self
+            .send_chat(model, &prompt, images.into(), candidate)
             .await?;
+        empty_retry::non_empty(content)
+    }
 
-        Ok(resp.message.content)
+    /// 发送单次 /api/chat 请求，返回模型输出的原始内容
+    async fn send_chat(
+        &self,
+        model: &str,
+        prompt: &str,
+        images: Arc<[body::ChatImage]>,
+        candidate: Option<CandidateSampling>,
+    ) -> Result<String> {
+        let url = format!("{}/api/chat", self.base_url.trim_end_matches('/'));
+        let profile = self.prompt_profile(model);
+        let mut req = body::ChatRequest::user(model, prompt)
+            .with_system(profile.system_prompt.as_deref());
+        req.format = profile.json_format.then_some("json");
+        req.stream = self.stream;
+        req.options = (self.num_predict.is_some() || candidate.is_some()).then(|| {
+            body::ChatOptions {
+                num_predict: self.num_predict,
+                seed: candidate.map(|c| c.seed),
+                temperature: candidate.map(|c| c.temperature),
+            }
+        });
+        // 请求体逐块写出，图片不会在内存中再拼出一份完整的 JSON；不压缩重发时重新生成
+        let body = || body::ChatBody::new(&req, images.clone());
+
+        if self.stream {
+            // 流式响应不限制总时长，只限制等待响应头与两次输出之间的间隔
+            let send = self.send_chat_body(&url, None, body);
+            let resp = tokio::time::timeout(self.read_timeout(), send)
+                .await
+                .map_err(|_| self.read_timeout_error())??;
+            let resp = downscale::check_chat_status(resp).await?;
+            return self.read_chat_stream(resp).await;
+        }
+
+        let request = async {
+            let resp = self
+                .send_chat_body(&url, Some(self.read_timeout()), body)
+                .await?;
+            let resp = downscale::check_chat_status(resp).await?;
+            // 容忍代理附加的 BOM 与非法 UTF-8 字节，不直接使用 resp.json()
+            let bytes = resp.bytes().await.map_err(|e| self.map_request_error(e))?;
+            let resp: body::ChatResponse = parse::decode_lossy(&bytes)?;
+            Ok::<_, anyhow::Error>(resp.message.content)
+        };
+        match self.keep_alive_poll_secs {
+            Some(poll_secs) => self.with_keep_alive_poll(request, poll_secs, model).await,
+            None => request.await,
+        }
     }
 
-    fn extract_json_text<'a>(&self, raw: &'a str) -> &'a str {
+    pub(crate) fn extract_json_text<'a>(&self, raw: &'a str) -> &'a str {
         // 兼容模型偶尔返回 ```json ... ``` 的情况
-        let s = raw.trim();
+        let s = parse::trim_noise(raw);
         if let Some(pos) = s.find("```") {
             // 简单剥离 code fence（够用）
             let s2 = s[pos..].trim_start_matches("```json").trim_start_matches("```");
             if let Some(end) = s2.find("```") {
-                return s2[..end].trim();
+                return parse::trim_noise(&s2[..end]);
             }
         }
         s
     }
 
-    fn parse_session_summary(&self, raw: &str) -> Result<SessionSummary> {
+    /// 解析模型输出：先分离思考过程，再从其余内容中提取总结
+    pub(crate) fn parse_session_summary(&self, raw: &str) -> Result<SessionSummary> {
+        let (reasoning, body) = super::reasoning::split_reasoning(raw);
+        let mut summary = self.parse_summary_body(&body)?;
+        if self.capture_reasoning {
+            summary.reasoning = reasoning;
+        }
+        Ok(summary)
+    }
+
+    fn parse_summary_body(&self, raw: &str) -> Result<SessionSummary> {
+        if let Some(summary) = self.select_json_object(raw) {
+            return Ok(summary);
+        }
         let json_text = self.extract_json_text(raw);
         // 将 JSON 解析为 Value，以便我们可以在转换 Struct 之前修改它
-        let mut v: Value = serde_json::from_str(json_text)
-            .map_err(|e| anyhow!("Ollama 返回不是合法 JSON: {e}; raw={}", raw))?;
+        let v = self.parse_json_or_repair(json_text, raw)?;
+        self.summary_from_value(v, raw)
+    }
+
+    /// 由解析出的 JSON 构造总结：校验必需字段、补齐可选段落并决定会话时间
+    fn summary_from_value(&self, mut v: Value, raw: &str) -> Result<SessionSummary> {
+        // 模型明确表示没有有意义的活动：不要求其余字段，直接给出空闲总结
+        if let Some(summary) = self.model_flagged_idle(&v) {
+            return Ok(summary);
+        }
 
-        // ✅ 关键修复：手动注入缺失的时间字段
-        // LLM 不知道绝对时间，所以我们在这里给一个默认值（当前时间）
-        // 后续业务逻辑通常会用真实的会话时间覆盖它
+        // 补默认值之前按 Schema 校验原始输出，违规时一次列出全部位置
+        self.check_output_schema(&mut v, raw)?;
+
+        // title / summary 是必需字段，其余可选段落缺失时补默认值
+        parse::check_required_fields(&v, raw)?;
         if let Some(obj) = v.as_object_mut() {
-            let now = Utc::now();
-            if !obj.contains_key("start_time") {
-                obj.insert("start_time".to_string(), serde_json::to_value(now)?);
-            }
-            if !obj.contains_key("end_time") {
-                obj.insert("end_time".to_string(), serde_json::to_value(now)?);
+            let defaulted = parse::fill_missing_optional_fields(obj);
+            if !defaulted.is_empty() {
+                warn!(
+                    "Ollama: 模型输出缺少字段 {:?}，已使用默认值（模型 {} 可能表现不佳）",
+                    defaulted, self.model
+                );
             }
         }
 
+        // 区分“模型给出的时间”与“需要注入的时间”
+        // 模型给出的时间先取出暂存，解析后再决定是否采信
+        let model_times = v.as_object_mut().and_then(|obj| {
+            let start = obj.remove("start_time");
+            let end = obj.remove("end_time");
+            let parse = |t: Option<Value>| {
+                t.and_then(|t| serde_json::from_value::<DateTime<Utc>>(t).ok())
+            };
+            parse(start).zip(parse(end))
+        });
+        if let Some(obj) = v.as_object_mut() {
+            // 先占位，解析后由 resolve_times 覆盖
+            let now = serde_json::to_value(Utc::now())?;
+            obj.insert("start_time".to_string(), now.clone());
+            obj.insert("end_time".to_string(), now);
+        }
+
+        let extra = v
+            .as_object_mut()
+            .map(|obj| self.take_extra_fields(obj))
+            .unwrap_or_default();
+        self.normalize_tag_paths(&mut v);
+
         // 现在再转换为 SessionSummary Struct
         let mut summary: SessionSummary = serde_json::from_value(v)?;
-        
-        let now = Utc::now();
-        if summary.start_time > summary.end_time {
-            summary.start_time = now;
-            summary.end_time = now;
+        summary.extra = extra;
+        let (start, end) = self.resolve_times(model_times);
+        summary.start_time = start;
+        summary.end_time = end;
+        let adjusted = summary.clamp_tag_time_ranges();
+        if adjusted > 0 {
+            warn!(
+                "Ollama: {} 个标签时间段超出会话范围或格式无效，已调整",
+                adjusted
+            );
+        }
+
+        // 模型偶尔只给出标签和评分而摘要为空，时间线上会出现空白条目
+        if summary.summary.trim().is_empty() {
+            if !self.derive_empty_summary {
+                return Err(anyhow!("Ollama 返回的 summary 为空; raw={}", raw));
+            }
+            if summary.derive_missing_summary() {
+                warn!(
+                    "Ollama: 模型返回的摘要为空，已由标签推导: {}",
+                    summary.summary
+                );
+            }
+        }
+        summary.detect_language(self.output_language.as_deref());
+        summary.idle = summary.has_only_idle_tags();
+
+        // 不变量校验只记录告警，不影响返回（与缺失字段补默认值的降级策略一致）
+        if let Err(violations) = summary.validate() {
+            let details: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+            warn!("Ollama: 模型输出不符合约束: {}", details.join("; "));
+        }
+        Ok(summary)
+    }
+
+    /// 分析主体（由 analyze_frames_with_context 包裹在 tracing span 中执行）
+    async fn analyze_in_span(
+        &self,
+        frames: Vec<String>,
+        context: AnalysisContext,
+        candidate: Option<CandidateSampling>,
+        model: &str,
+    ) -> Result<SessionSummary> {
+        if !self.configured {
+            return Err(anyhow!("Ollama provider 未配置"));
+        }
+        // 帧数过少的会话生成的总结没有价值，直接跳过，不占用模型
+        if frames.len() < self.min_frames_for_analysis {
+            return Err(TooFewFrames {
+                frames: frames.len(),
+                min_frames: self.min_frames_for_analysis,
+            }
+            .into());
+        }
+        let frames = self.drop_missing_frames(frames).await?;
+
+        let profile = self.prompt_profile(model);
+        info!(
+            "Ollama: 开始分析 {} 帧 profile={} max_images={}",
+            frames.len(),
+            profile.name,
+            profile.max_images
+        );
+
+        // 参考截图排在会话截图之前，并占用同一图片预算
+        let (mut reference_labels, mut reference_images) = self.encode_reference_images().await;
+        // 图片预算容不下参考截图时放弃参考截图，保证至少发送一张会话截图
+        if !reference_images.is_empty() && reference_images.len() >= profile.max_images {
+            warn!(
+                "Ollama: 模型 {} 单次最多 {} 张图片，不发送参考截图",
+                model, profile.max_images
+            );
+            reference_labels.clear();
+            reference_images.clear();
+        }
+        let reference_note = Self::build_reference_note(&reference_labels);
+
+        // 采样：图片数上限由模型的提示词配置决定（通用配置为 30），扣除参考截图；
+        // 配置了 frames_per_minute 时按会话时长取帧，仍不超过该上限
+        // 多候选时开启随机采样的会话按候选种子取帧，使不同候选看到不同的帧
+        let seed = match candidate {
+            Some(c) if self.sample_jitter => Some(c.seed),
+            _ => self.effective_seed(&frames),
+        };
+        // 拼图模式下每张图片容纳多帧
+        let frame_budget = self.frame_budget(
+            &frames,
+            (profile.max_images - reference_images.len()) * self.frames_per_image(),
+        );
+        let sampled = self
+            .sample_for_context(&frames, &context, frame_budget, seed)
+            .await;
+        debug!("Ollama: 采样后 {} 帧 seed={:?}", sampled.len(), seed);
+        if let Some(summary) = self.idle_summary_for_frames(&sampled, seed).await {
+            return Ok(summary);
+        }
+
+        let (context, mode) = self.with_analysis_mode(context).await;
+        let context = self.with_few_shot_examples(context, &frames).await;
+
+        // 编码（只保留编码成功的帧，保证提示词中的序号与图片一一对应）；拼图模式下多帧拼成一张图片
+        let ordered = self.order_frames(sampled);
+        // 已编码的图片数达到 max_encoded_frames_in_memory 后，其余帧在写出请求体时才编码
+        let (encoded_frames, images) = match self.encode_mosaic(&ordered).await? {
+            Some((frames, images)) => (
+                frames,
+                images.into_iter().map(body::ChatImage::Ready).collect(),
+            ),
+            None => {
+                let mut images = Vec::new();
+                let mut encoded_frames = Vec::new();
+                let mut resident = 0;
+                for path in ordered {
+                    match self.chat_image(&path, &mut resident).await {
+                        Ok(image) => {
+                            images.push(image);
+                            encoded_frames.push(path);
+                        }
+                        Err(e) => warn!("Ollama: 编码失败 path={} err={}", path, e),
+                    }
+                }
+                (encoded_frames, images)
+            }
+        };
+        if images.is_empty() {
+            return Err(anyhow!("没有可用的图片帧用于分析"));
+        }
+
+        // 帧越少提示词越短，按全部帧估算的提示词长度即为上界；参考截图不参与裁剪
+        let build_prompt = |frames: &[String]| match &reference_note {
+            Some(note) => format!("{}\n\n{}", note, self.build_prompt(&context, frames)),
+            None => self.build_prompt(&context, frames),
+        };
+        let reserved = build_prompt(&encoded_frames).len()
+            + reference_images.iter().map(String::len).sum::<usize>();
+        let max_payload_bytes = self.max_payload_bytes;
+        // 拼图与帧不是一一对应，不按帧裁剪；请求体仍由 call_ollama_chat 兜底检查
+        let (encoded_frames, images) = if self.mosaic.is_some() {
+            (encoded_frames, images)
+        } else {
+            tokio::task::spawn_blocking(move || {
+                Self::fit_payload(encoded_frames, images, reserved, max_payload_bytes)
+            })
+            .await??
+        };
+        crate::telemetry::global().add_frames_encoded(images.len());
+
+        // 调用
+        let prompt = build_prompt(&encoded_frames);
+        let images = reference_images
+            .into_iter()
+            .map(body::ChatImage::Ready)
+            .chain(images)
+            .collect();
+        let result = self
+            .call_ollama_chat(model, prompt, images, candidate)
+            .await;
+        let mut summary = self.summary_from_chat(result)?;
+        summary.sampling_seed = candidate.map(|c| c.seed).or(seed);
+        summary.temperature = candidate.map(|c| c.temperature);
+        self.record_frame_selection(&mut summary, &frames, &encoded_frames, candidate)
+            .await;
+        // 多候选时各候选的种子不同，只随总结返回
+        if candidate.is_none() {
+            self.record_sampling_seed(seed);
+            self.save_extra_fields(&summary.extra).await;
+            if let Some(mode) = &mode {
+                self.save_analysis_mode(&mode.name).await;
+            }
         }
         Ok(summary)
     }
@@ -176,31 +620,102 @@ impl LLMProvider for OllamaProvider {
     }
 
     async fn analyze_frames(&self, frames: Vec<String>) -> Result<SessionSummary> {
+        self.analyze_frames_with_context(frames, AnalysisContext::default())
+            .await
+    }
+
+    async fn analyze_frames_with_context(
+        &self,
+        frames: Vec<String>,
+        context: AnalysisContext,
+    ) -> Result<SessionSummary> {
+        // 为本次分析建立 span，编码、请求、流式读取、解析中的日志都带上同一组上下文
+        let selection = self.select_model(frames.len());
+        let span = info_span!(
+            "ollama_analyze",
+            request_id = %uuid::Uuid::new_v4(),
+            session_id = ?self.session_id,
+            model = %selection.model,
+            model_reason = %selection.reason,
+            frame_count = frames.len()
+        );
+        async {
+            info!(
+                "Ollama: 选用模型 {}（{}）",
+                selection.model, selection.reason
+            );
+            let retry = self.low_confidence_retry(selection.model, &frames, &context);
+            let summary = self
+                .analyze_bounded(frames, context, selection.model)
+                .await?;
+            self.check_confidence(summary, retry).await
+        }
+        .instrument(span)
+        .await
+    }
+
+    async fn analyze_frames_n(&self, frames: Vec<String>, n: usize) -> Result<Vec<SessionSummary>> {
+        self.analyze_candidates(frames, n).await
+    }
+
+    async fn analyze_text(&self, content: String) -> Result<SessionSummary> {
         if !self.configured {
             return Err(anyhow!("Ollama provider 未配置"));
         }
 
-        info!("Ollama: 开始分析 {} 帧", frames.len());
-
-        // 采样：沿用你们 analysis_params 的默认策略（最多 30）
-        let sampled = self.sample_frames(&frames, 30);
-        debug!("Ollama: 采样后 {} 帧", sampled.len());
+        let span = info_span!(
+            "ollama_analyze_text",
+            request_id = %uuid::Uuid::new_v4(),
+            session_id = ?self.session_id,
+            model = %self.model,
+            text_chars = content.chars().count()
+        );
+        self.analyze_session_text(content).instrument(span).await
+    }
 
-        // 编码
-        let mut images_b64 = Vec::new();
-        for path in sampled {
-            match self.image_to_base64(&path).await {
-                Ok(b64) => images_b64.push(b64),
-                Err(e) => warn!("Ollama: 编码失败 path={} err={}", path, e),
-            }
-        }
-        if images_b64.is_empty() {
-            return Err(anyhow!("没有可用的图片帧用于分析"));
+    async fn refine_field(
+        &self,
+        frames: Vec<String>,
+        current: SessionSummary,
+        field: SummaryField,
+    ) -> Result<SessionSummary> {
+        if !self.configured {
+            return Err(anyhow!("Ollama provider 未配置"));
         }
 
-        // 调用
-        let raw = self.call_ollama_chat(images_b64).await?;
-        self.parse_session_summary(&raw)
+        let selection = self.select_model(frames.len());
+        let span = info_span!(
+            "ollama_refine_field",
+            request_id = %uuid::Uuid::new_v4(),
+            session_id = ?self.session_id,
+            model = %selection.model,
+            field = ?field,
+            frame_count = frames.len()
+        );
+        self.refine_summary_field(frames, current, field, selection.model)
+            .instrument(span)
+            .await
+    }
+
+    fn set_session_window(&mut self, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) {
+        self.session_window_start = start;
+        self.session_window_end = end;
+    }
+
+    fn set_progress_sender(&mut self, sender: Option<ProgressSender>) {
+        self.progress_tx = sender;
+    }
+
+    async fn on_start(&self) -> Result<()> {
+        self.warm_up().await
+    }
+
+    async fn on_stop(&self) -> Result<()> {
+        self.unload().await
+    }
+
+    async fn reconnect(&self) -> Result<()> {
+        self.rebuild_client()
     }
 
     fn name(&self) -> &str {
@@ -208,14 +723,39 @@ impl LLMProvider for OllamaProvider {
     }
 
     fn configure(&mut self, config: serde_json::Value) -> Result<()> {
-        if let Some(base_url) = config.get("base_url").and_then(|v| v.as_str()) {
-            self.base_url = base_url.to_string();
+        // 只更新提供的字段（设置界面只发送 base_url 与 model），合并后整体反序列化：
+        // 拼错的字段名或错误的类型直接报错，而不是静默使用默认值
+        let config = self.config.merged(config)?;
+        super::ExtraFieldSpec::validate_all(&config.extra_fields)
+            .map_err(|e| anyhow!("Ollama 配置无效: {}", e))?;
+        super::taxonomy::validate_taxonomy(&config.taxonomy)
+            .map_err(|e| anyhow!("Ollama 配置无效: {}", e))?;
+        if let Some(min_confidence) = config.min_confidence {
+            super::confidence::validate_threshold(min_confidence)
+                .map_err(|e| anyhow!("Ollama 配置无效: {}", e))?;
         }
-        if let Some(model) = config.get("model").and_then(|v| v.as_str()) {
-            self.model = model.to_string();
+        if let Some(mosaic) = &config.mosaic {
+            mosaic
+                .validate()
+                .map_err(|e| anyhow!("Ollama 配置无效: {}", e))?;
         }
-        // base_url 至少要有
-        self.configured = !self.base_url.trim().is_empty();
+        if let Some(scene_aware) = &config.scene_aware {
+            scene_aware
+                .validate()
+                .map_err(|e| anyhow!("Ollama 配置无效: {}", e))?;
+        }
+        Self::validate_hard_limit(&config).map_err(|e| anyhow!("Ollama 配置无效: {}", e))?;
+        let analysis_mode = Self::default_analysis_mode(&config);
+        super::modes::validate_modes(analysis_mode.as_deref(), &config.analysis_modes)
+            .map_err(|e| anyhow!("Ollama 配置无效: {}", e))?;
+        let headers = Self::build_headers(&config.headers)?;
+        self.apply_config(config);
+        if !headers.is_empty() {
+            info!("Ollama: 附加请求头 {}", Self::describe_headers(&headers));
+        }
+        self.client =
+            std::sync::RwLock::new(Self::build_client(self.connect_timeout_secs, headers.clone())?);
+        self.client_headers = headers;
         Ok(())
     }
 
@@ -224,12 +764,38 @@ impl LLMProvider for OllamaProvider {
     }
 
     fn capabilities(&self) -> ProviderCapabilities {
-        ProviderCapabilities {
+        let mut capabilities = ProviderCapabilities {
             vision_support: true,
             batch_analysis: true,
-            streaming: false,
+            streaming: true,
             max_input_tokens: 128000,
             supported_image_formats: vec!["jpg".to_string(), "jpeg".to_string(), "png".to_string()],
-        }
+            // 转写文本以纯文本形式拼入提示词，任意模型都可使用
+            audio_support: true,
+            url_context_support: true,
+            model_family: None,
+            max_images: self.prompt_profile(&self.model).max_images,
+            tokens_per_image: DEFAULT_TOKENS_PER_IMAGE,
+        };
+        // 拼图模式下按帧计：每帧占一格，约为一张图片的 1/格数
+        let tiles = self.frames_per_image();
+        capabilities.max_images *= tiles;
+        capabilities.tokens_per_image = (DEFAULT_TOKENS_PER_IMAGE / tiles).max(1);
+        // 服务端报告的字段覆盖静态默认值
+        self.apply_reported_capabilities(&mut capabilities);
+        capabilities
+    }
+
+    async fn refresh_capabilities(&mut self) -> Result<()> {
+        self.refresh_reported_capabilities().await
+    }
+
+    fn estimate_cost(&self, _frames: usize) -> Option<CostEstimate> {
+        // 本地模型不产生费用
+        None
+    }
+
+    fn last_sampling_seed(&self) -> Option<u64> {
+        self.last_sampling_seed.lock().ok().and_then(|last| *last)
     }
 }
\ No newline at end of file
@@ -0,0 +1,207 @@
+// Ollama 分析模式 - 按会话选择的模式或默认模式在提示词中追加侧重说明
+//
+// 会话单独选择的模式记录在数据库中，重新分析时沿用；未选择时使用默认模式并写回会话
+
+use super::OllamaProvider;
+use crate::llm::modes::{self, AnalysisMode};
+use crate::llm::plugin::AnalysisContext;
+use tracing::warn;
+
+impl OllamaProvider {
+    /// 本次分析的模式：上下文指定的模式优先，其次为默认模式；找不到对应模式时不附加模式说明
+    pub(super) fn analysis_mode_for(&self, context: &AnalysisContext) -> Option<AnalysisMode> {
+        let name = context.mode.as_deref().or(self.analysis_mode.as_deref())?;
+        modes::resolve_mode(name, &self.analysis_modes)
+    }
+
+    /// 上下文未指定模式时补充会话记录的模式，返回本次生效的模式
+    pub(super) async fn with_analysis_mode(
+        &self,
+        mut context: AnalysisContext,
+    ) -> (AnalysisContext, Option<AnalysisMode>) {
+        // 会话单独选择的模式优先于默认模式
+        if context.mode.is_none() {
+            context.mode = self.stored_analysis_mode().await;
+        }
+        let mode = self.analysis_mode_for(&context);
+        if let Some(name) = context.mode.as_deref().filter(|_| mode.is_none()) {
+            warn!("Ollama: 未知的分析模式 {}，使用默认提示词", name);
+        }
+        (context, mode)
+    }
+
+    /// 当前会话记录的分析模式；未关联数据库或会话、读取失败时为空
+    async fn stored_analysis_mode(&self) -> Option<String> {
+        let (Some(db), Some(session_id)) = (&self.db, self.session_id) else {
+            return None;
+        };
+        match db.get_session_analysis_mode(session_id).await {
+            Ok(record) => record.map(|r| r.mode),
+            Err(e) => {
+                warn!("Ollama: 读取分析模式失败 session={}: {}", session_id, e);
+                None
+            }
+        }
+    }
+
+    /// 将分析使用的模式写入当前会话，重新分析时沿用
+    pub(super) async fn save_analysis_mode(&self, mode: &str) {
+        let (Some(db), Some(session_id)) = (&self.db, self.session_id) else {
+            return;
+        };
+        let record = crate::storage::SessionAnalysisModeRecord {
+            session_id,
+            mode: mode.to_string(),
+            updated_at: crate::storage::local_now(),
+        };
+        if let Err(e) = db.save_session_analysis_mode(&record).await {
+            warn!("Ollama: 保存分析模式失败 session={}: {}", session_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{AnalysisContext, LLMProvider};
+    use crate::test_server::serve_routes;
+    use chrono::Utc;
+    use reqwest::Client;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_build_prompt_for_analysis_mode() {
+        let mut provider = OllamaProvider::new(Client::new());
+        // 未选择模式时不附加模式说明
+        assert!(!provider
+            .build_prompt(&AnalysisContext::default(), &[])
+            .contains("模式："));
+
+        provider
+            .configure(serde_json::json!({
+                "analysis_mode": "work",
+                "analysis_modes": {
+                    "reading": {
+                        "label": "读书笔记",
+                        "instructions": "记录书名与章节",
+                        "focus_categories": ["learning", "personal"]
+                    }
+                }
+            }))
+            .unwrap();
+        let with_mode = |mode: Option<&str>| {
+            provider.build_prompt(
+                &AnalysisContext {
+                    mode: mode.map(str::to_string),
+                    ..Default::default()
+                },
+                &[],
+            )
+        };
+
+        let work = with_mode(None);
+        assert!(work.contains("本次为「工作复盘」模式：侧重工作产出与效率"));
+        assert!(work.contains("重点关注 work（工作）、communication（沟通） 类活动"));
+        assert!(work.ends_with("只返回 JSON。"));
+
+        // 会话选择的模式优先于默认模式
+        let study = with_mode(Some("study"));
+        assert!(study.contains("本次为「学习记录」模式"));
+        assert!(study.contains("重点关注 learning（学习） 类活动"));
+        assert!(!study.contains("工作复盘"));
+
+        let reading = with_mode(Some("reading"));
+        assert!(reading.contains("本次为「读书笔记」模式：记录书名与章节"));
+        assert!(reading.contains("learning（学习）、personal（个人）"));
+        // 未知模式不附加模式说明
+        assert!(!with_mode(Some("travel")).contains("模式："));
+
+        assert!(provider
+            .configure(serde_json::json!({ "analysis_mode": "travel" }))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_analysis_mode_is_stored_with_session() {
+        let content = serde_json::json!({
+            "title": "阅读文档",
+            "summary": "阅读 Rust 异步编程文档",
+            "tags": [{"category": "learning", "confidence": 0.9, "keywords": ["Rust"]}]
+        })
+        .to_string();
+        let url = serve_routes(vec![(
+            "/api/chat",
+            serde_json::json!({ "message": { "content": content } }).to_string(),
+        )])
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<String> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.jpg", 1_700_000_000_000i64 + i));
+                image::RgbImage::from_fn(64, 48, |x, y| {
+                    image::Rgb([(x * 4) as u8, (y * 5) as u8, (i * 40) as u8])
+                })
+                .save(&path)
+                .unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+        let db = Arc::new(
+            crate::storage::Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let mut session_ids = Vec::new();
+        for _ in 0..2 {
+            let session = crate::storage::Session {
+                id: None,
+                start_time: Utc::now(),
+                end_time: Utc::now(),
+                title: String::new(),
+                summary: String::new(),
+                video_path: None,
+                tags: "[]".to_string(),
+                created_at: None,
+                device_name: None,
+                device_type: None,
+                pinned: false,
+            };
+            session_ids.push(db.insert_session(&session).await.unwrap());
+        }
+        // 第一个会话手动选择了学习模式
+        db.save_session_analysis_mode(&crate::storage::SessionAnalysisModeRecord {
+            session_id: session_ids[0],
+            mode: "study".to_string(),
+            updated_at: crate::storage::local_now(),
+        })
+        .await
+        .unwrap();
+
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({
+                "base_url": url,
+                "detect_idle_frames": false,
+                "analysis_mode": "work"
+            }))
+            .unwrap();
+        provider.set_database(db.clone());
+        for session_id in &session_ids {
+            provider.set_session_id(*session_id);
+            provider.analyze_frames(frames.clone()).await.unwrap();
+        }
+
+        let stored = |session_id: i64| {
+            let db = db.clone();
+            async move {
+                db.get_session_analysis_mode(session_id)
+                    .await
+                    .unwrap()
+                    .map(|record| record.mode)
+            }
+        };
+        assert_eq!(stored(session_ids[0]).await.as_deref(), Some("study"));
+        assert_eq!(stored(session_ids[1]).await.as_deref(), Some("work"));
+    }
+}
@@ -0,0 +1,272 @@
+// Ollama /api/chat 请求与响应结构
+//
+// Ollama 的 images 字段只接受 base64 编码的图片内容，不能传文件路径：
+// 即使服务运行在本机，也必须把帧读出并编码后放进请求体。
+// 请求体由 ChatBody 逐块写出，每张图片单独一块；延迟编码的图片在写出时才读取并编码
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// /api/chat 请求中的消息（图片由 ChatBody 写入最后一条消息）
+#[derive(Serialize)]
+pub(super) struct ChatMessage<'a> {
+    pub(super) role: &'a str,
+    pub(super) content: &'a str,
+}
+
+/// 生成参数（options）
+#[derive(Serialize)]
+pub(super) struct ChatOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) temperature: Option<f32>,
+}
+
+/// 请求字段只借用提示词，消息与图片由 ChatBody 逐块写出
+#[derive(Serialize)]
+pub(super) struct ChatRequest<'a> {
+    pub(super) model: &'a str,
+    pub(super) stream: bool,
+    /// 要求服务端输出的格式（"json"）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) format: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) options: Option<ChatOptions>,
+    #[serde(skip)]
+    pub(super) messages: Vec<ChatMessage<'a>>,
+}
+
+impl<'a> ChatRequest<'a> {
+    /// 单条用户消息的非流式请求
+    pub(super) fn user(model: &'a str, prompt: &'a str) -> Self {
+        Self {
+            model,
+            stream: false,
+            format: None,
+            options: None,
+            messages: vec![ChatMessage {
+                role: "user",
+                content: prompt,
+            }],
+        }
+    }
+
+    /// 在用户消息之前插入系统提示词（None 时不变）
+    pub(super) fn with_system(mut self, system_prompt: Option<&'a str>) -> Self {
+        if let Some(content) = system_prompt {
+            self.messages.insert(
+                0,
+                ChatMessage {
+                    role: "system",
+                    content,
+                },
+            );
+        }
+        self
+    }
+}
+
+/// 请求中的一张图片
+///
+/// Ready 为已编码的 base64；Deferred 只记录帧路径与编码后的长度，
+/// 写出请求体时才读取并编码，写出后即释放
+#[derive(Debug, Clone)]
+pub(super) enum ChatImage {
+    Ready(String),
+    Deferred { path: String, encoded_len: usize },
+}
+
+impl ChatImage {
+    /// 写入请求体的图片字节数（用于请求体大小检查）
+    pub(super) fn encoded_len(&self) -> usize {
+        match self {
+            Self::Ready(image) => image.len(),
+            Self::Deferred { encoded_len, .. } => *encoded_len,
+        }
+    }
+
+    /// base64 内容；Deferred 在此时读取帧并编码
+    fn encoded(&self) -> std::io::Result<Cow<'_, str>> {
+        match self {
+            Self::Ready(image) => Ok(image.into()),
+            Self::Deferred { path, .. } => crate::storage::frame_pack::read_frame_blocking(path)
+                .map(|bytes| general_purpose::STANDARD.encode(bytes).into())
+                .map_err(|e| std::io::Error::other(format!("读取帧 {} 失败: {}", path, e))),
+        }
+    }
+}
+
+/// 逐块写出的 /api/chat 请求体
+///
+/// 先写出请求字段与消息，再逐张把图片转成 JSON 字符串写出，
+/// 大会话的请求体不会在内存中完整拼出一份；延迟编码的图片在写出时才编码，同时只有一张在内存中
+pub(super) struct ChatBody {
+    head: Option<Vec<u8>>,
+    images: Arc<[ChatImage]>,
+    next_image: usize,
+    tail: Option<&'static [u8]>,
+}
+
+impl ChatBody {
+    /// 图片挂在最后一条消息上；纯文本分析不写 images 字段
+    pub(super) fn new(request: &ChatRequest<'_>, images: Arc<[ChatImage]>) -> Result<Self> {
+        let (last, earlier) = request
+            .messages
+            .split_last()
+            .ok_or_else(|| anyhow!("请求中没有消息"))?;
+        // 序列化后的对象去掉末尾的 '}'，在其后续写字段
+        let mut head = serde_json::to_vec(request)?;
+        head.pop();
+        head.extend_from_slice(b",\"messages\":[");
+        for message in earlier {
+            serde_json::to_writer(&mut head, message)?;
+            head.push(b',');
+        }
+        serde_json::to_writer(&mut head, last)?;
+        head.pop();
+        let tail: &'static [u8] = if images.is_empty() {
+            b"}]}"
+        } else {
+            head.extend_from_slice(b",\"images\":[");
+            b"]}]}"
+        };
+        Ok(Self {
+            head: Some(head),
+            images,
+            next_image: 0,
+            tail: Some(tail),
+        })
+    }
+
+    pub(super) fn into_body(self) -> reqwest::Body {
+        reqwest::Body::wrap_stream(futures_util::stream::iter(self))
+    }
+}
+
+impl Iterator for ChatBody {
+    type Item = std::io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(head) = self.head.take() {
+            return Some(Ok(head));
+        }
+        if let Some(image) = self.images.get(self.next_image) {
+            let mut chunk = Vec::with_capacity(image.encoded_len() + 3);
+            if self.next_image > 0 {
+                chunk.push(b',');
+            }
+            self.next_image += 1;
+            let image = match image.encoded() {
+                Ok(image) => image,
+                Err(e) => return Some(Err(e)),
+            };
+            return Some(
+                serde_json::to_writer(&mut chunk, image.as_ref())
+                    .map(|_| chunk)
+                    .map_err(std::io::Error::from),
+            );
+        }
+        self.tail.take().map(|tail| Ok(tail.to_vec()))
+    }
+}
+
+#[derive(Deserialize)]
+pub(super) struct ChatResponse {
+    pub(super) message: ChatResponseMessage,
+}
+
+#[derive(Deserialize)]
+pub(super) struct ChatResponseMessage {
+    pub(super) content: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ollama::OllamaProvider;
+    use reqwest::Client;
+    use serde_json::Value;
+
+    fn collect(body: ChatBody) -> Value {
+        let bytes: Vec<u8> = body.flat_map(|chunk| chunk.unwrap()).collect();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_body_carries_base64_images() {
+        let provider = OllamaProvider::new(Client::new());
+        let dir = tempfile::tempdir().unwrap();
+        let frame = dir.path().join("1700000000000.jpg");
+        std::fs::write(&frame, b"fake-jpeg").unwrap();
+
+        let image = provider
+            .image_to_base64(frame.to_str().unwrap())
+            .await
+            .unwrap();
+        let request = ChatRequest::user("qwen3-vl:32b", "prompt");
+        let bytes: Vec<u8> = ChatBody::new(&request, vec![ChatImage::Ready(image)].into())
+            .unwrap()
+            .flat_map(|chunk| chunk.unwrap())
+            .collect();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+
+        // 请求体里是图片内容的 base64，而不是帧文件路径
+        let images = body["messages"][0]["images"].as_array().unwrap();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0], general_purpose::STANDARD.encode(b"fake-jpeg"));
+        assert!(!String::from_utf8(bytes)
+            .unwrap()
+            .contains("1700000000000.jpg"));
+        assert_eq!(body["stream"], false);
+    }
+
+    #[test]
+    fn test_streamed_chat_body_matches_serialized_request() {
+        let mut request =
+            ChatRequest::user("llava:13b", "分析\n\"截图\"").with_system(Some("只输出 JSON"));
+        request.format = Some("json");
+        request.options = Some(ChatOptions {
+            num_predict: Some(512),
+            seed: None,
+            temperature: Some(0.5),
+        });
+        let encoded = ["aGVsbG8=".to_string(), r#"C:\frames\"1".jpg"#.to_string()];
+        let images: Arc<[ChatImage]> = encoded.iter().cloned().map(ChatImage::Ready).collect();
+
+        // 与一次性序列化的请求体逐字段一致
+        let streamed = collect(ChatBody::new(&request, images.clone()).unwrap());
+        let expected = serde_json::json!({
+            "model": "llava:13b",
+            "stream": false,
+            "format": "json",
+            "options": { "num_predict": 512, "temperature": 0.5 },
+            "messages": [
+                { "role": "system", "content": "只输出 JSON" },
+                { "role": "user", "content": "分析\n\"截图\"", "images": encoded }
+            ]
+        });
+        assert_eq!(streamed, expected);
+        // 每张图片单独一块，不与其他内容拼在一起
+        assert_eq!(ChatBody::new(&request, images).unwrap().count(), 4);
+
+        // 纯文本请求不带 images 字段
+        let mut request = ChatRequest::user("qwen3-vl:32b", "分析");
+        request.stream = true;
+        assert_eq!(
+            collect(ChatBody::new(&request, Vec::new().into()).unwrap()),
+            serde_json::json!({
+                "model": "qwen3-vl:32b",
+                "stream": true,
+                "messages": [{ "role": "user", "content": "分析" }]
+            })
+        );
+        request.messages.clear();
+        assert!(ChatBody::new(&request, Vec::new().into()).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+// Ollama 超大帧列表的内存上限 - 延迟编码图片，帧数超过硬上限时分块分析
+//
+// 两项上限默认都不启用：max_encoded_frames_in_memory 限制同时在内存中的已编码图片数，
+// max_frames_hard_limit 限制单次分析的帧数，超出时按时间分块分析后合并为一份总结
+
+use super::body::ChatImage;
+use super::OllamaProvider;
+use crate::llm::plugin::{AnalysisContext, LLMProvider, SessionSummary};
+use crate::llm::OllamaConfig;
+use anyhow::{anyhow, Result};
+use tracing::{debug, info, warn};
+
+impl OllamaProvider {
+    /// 生成请求中的一张图片：已编码的图片数达到 max_encoded_frames_in_memory 后，
+    /// 后续帧只读取一次得到编码后的长度，写出请求体时再编码（resident 为已编码的图片数）
+    pub(super) async fn chat_image(&self, path: &str, resident: &mut usize) -> Result<ChatImage> {
+        if self
+            .max_encoded_frames_in_memory
+            .is_some_and(|max| *resident >= max)
+        {
+            let bytes = crate::storage::frame_pack::read_frame(path).await?;
+            return Ok(ChatImage::Deferred {
+                path: path.to_string(),
+                encoded_len: bytes.len().div_ceil(3) * 4,
+            });
+        }
+        let image = self.image_to_base64(path).await?;
+        *resident += 1;
+        Ok(ChatImage::Ready(image))
+    }
+
+    /// 每块都要满足最小分析帧数，否则分块后全部被跳过
+    pub(super) fn validate_hard_limit(config: &OllamaConfig) -> Result<()> {
+        let min_frames = config.min_frames_for_analysis.max(1);
+        match config.max_frames_hard_limit {
+            Some(limit) if limit < min_frames => Err(anyhow!(
+                "max_frames_hard_limit ({}) 不能小于最小分析帧数 ({})",
+                limit,
+                min_frames
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 帧数超过 max_frames_hard_limit 时按时间分块依次分析，再合并为一份总结；否则直接分析
+    ///
+    /// 各块依次分析，同一时刻只有一块的图片在内存中。标签与评分按各块合并，
+    /// 标题与摘要交给模型按各块总结重写，失败时取与合并标签最一致的一块
+    pub(super) async fn analyze_bounded(
+        &self,
+        frames: Vec<String>,
+        context: AnalysisContext,
+        model: &str,
+    ) -> Result<SessionSummary> {
+        let Some(limit) = self
+            .max_frames_hard_limit
+            .filter(|&limit| frames.len() > limit)
+        else {
+            return self.analyze_in_span(frames, context, None, model).await;
+        };
+        let chunks = split_frames(frames, limit);
+        info!(
+            "Ollama: 帧数超过硬上限 {}，分为 {} 块分别分析后合并",
+            limit,
+            chunks.len()
+        );
+        let total = chunks.len();
+        let mut partials = Vec::with_capacity(total);
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            debug!(
+                "Ollama: 分析第 {}/{} 块（{} 帧）",
+                index + 1,
+                total,
+                chunk.len()
+            );
+            partials.push(
+                self.analyze_in_span(chunk, context.clone(), None, model)
+                    .await?,
+            );
+        }
+
+        let mut summary = crate::llm::ensemble::merge_summaries(&partials)
+            .ok_or_else(|| anyhow!("没有可用的图片帧用于分析"))?;
+        if summary.idle {
+            return Ok(summary);
+        }
+        let content = crate::llm::session_merge::build_reduce_content(&partials);
+        match self.analyze_text(content).await {
+            Ok(reduced) => {
+                summary.title = reduced.title;
+                summary.summary = reduced.summary;
+            }
+            Err(e) => warn!("Ollama: 合并各块总结失败，使用标签最一致的一块: {}", e),
+        }
+        Ok(summary)
+    }
+}
+
+/// 按时间顺序把帧均分为若干块，每块不超过 limit 帧
+fn split_frames(mut frames: Vec<String>, limit: usize) -> Vec<Vec<String>> {
+    let total = frames.len();
+    let count = total.div_ceil(limit.max(1));
+    let mut chunks: Vec<Vec<String>> = (0..count)
+        .rev()
+        .map(|i| frames.split_off(i * total / count))
+        .collect();
+    chunks.reverse();
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ollama::body::{ChatBody, ChatRequest};
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_large_frame_list_keeps_encoded_frames_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let files: Vec<String> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("{i}.jpg"));
+                std::fs::write(&path, vec![i as u8; 3000]).unwrap();
+                path.to_string_lossy().into_owned()
+            })
+            .collect();
+        let frames: Vec<String> = (0..20_000).map(|i| files[i % 3].clone()).collect();
+
+        // 超过硬上限的帧按时间顺序均分，每块不超过上限
+        let chunks = split_frames(frames.clone(), 6000);
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks.iter().all(|chunk| chunk.len() == 5000));
+        assert_eq!(chunks.concat(), frames);
+        let mut provider = OllamaProvider::new(Client::new());
+        let config =
+            serde_json::json!({ "min_frames_for_analysis": 10, "max_frames_hard_limit": 5 });
+        assert!(provider.configure(config).is_err());
+
+        // 只有前 4 张预先编码，其余在写出请求体时逐张编码
+        provider.max_encoded_frames_in_memory = Some(4);
+        let mut resident = 0;
+        let mut images = Vec::new();
+        for path in chunks[0].iter().take(30) {
+            images.push(provider.chat_image(path, &mut resident).await.unwrap());
+        }
+        let encoded_len = 4000;
+        let ready: usize = images
+            .iter()
+            .filter_map(|image| match image {
+                ChatImage::Ready(image) => Some(image.len()),
+                ChatImage::Deferred { .. } => None,
+            })
+            .sum();
+        assert_eq!((resident, ready), (4, 4 * encoded_len));
+        assert!(images
+            .iter()
+            .all(|image| image.encoded_len() == encoded_len));
+
+        let request = ChatRequest::user("llava", "hi");
+        let mut body = ChatBody::new(&request, images.into()).unwrap();
+        body.next().unwrap().unwrap();
+        // 请求体的每一块最多一张图片：峰值缓冲 = 预先编码的图片 + 当前写出的一块
+        let mut streamed = 0;
+        let mut peak_chunk = 0;
+        for chunk in body {
+            let chunk = chunk.unwrap();
+            streamed += chunk.len();
+            peak_chunk = peak_chunk.max(chunk.len());
+        }
+        assert!(streamed > 30 * encoded_len);
+        assert!(peak_chunk <= encoded_len + 3, "{peak_chunk}");
+        assert!(ready + peak_chunk <= 5 * (encoded_len + 3));
+    }
+}
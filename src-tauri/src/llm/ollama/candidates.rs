@@ -0,0 +1,97 @@
+// Ollama 多候选总结 - 同一组帧按不同种子与温度生成多个总结供用户挑选
+//
+// 每个候选的种子与温度都记录在结果中，可按相同参数复现
+
+use super::OllamaProvider;
+use crate::llm::plugin::{
+    collect_candidates, AnalysisContext, SessionSummary, MAX_SUMMARY_CANDIDATES,
+};
+use anyhow::Result;
+use tracing::{info, info_span, Instrument};
+
+/// 多候选总结依次使用的采样温度（超过数组长度时循环）
+const CANDIDATE_TEMPERATURES: [f32; 4] = [0.2, 0.5, 0.8, 1.0];
+
+/// 单个候选的采样参数（同时用于帧采样与模型采样，记录在结果中以便复现）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct CandidateSampling {
+    pub(super) seed: u64,
+    pub(super) temperature: f32,
+}
+
+impl OllamaProvider {
+    /// 依次生成 n 个候选：本地模型同一时间只服务一个请求，非流式请求仍受 keep_alive 轮询保护
+    pub(super) async fn analyze_candidates(
+        &self,
+        frames: Vec<String>,
+        n: usize,
+    ) -> Result<Vec<SessionSummary>> {
+        let n = n.clamp(1, MAX_SUMMARY_CANDIDATES);
+        let selection = self.select_model(frames.len());
+        info!(
+            "Ollama: 选用模型 {}（{}）",
+            selection.model, selection.reason
+        );
+        let mut results = Vec::with_capacity(n);
+        for index in 0..n {
+            let candidate = self.candidate_sampling(&frames, index);
+            let span = info_span!(
+                "ollama_analyze_candidate",
+                request_id = %uuid::Uuid::new_v4(),
+                session_id = ?self.session_id,
+                model = %selection.model,
+                candidate = index,
+                seed = candidate.seed,
+                temperature = candidate.temperature
+            );
+            let result = self
+                .analyze_in_span(
+                    frames.clone(),
+                    AnalysisContext::default(),
+                    Some(candidate),
+                    selection.model,
+                )
+                .instrument(span)
+                .await;
+            results.push(result);
+        }
+        collect_candidates(results)
+    }
+
+    /// 第 index 个候选的采样参数：种子从配置种子（或帧指纹）递增，温度按 CANDIDATE_TEMPERATURES 轮换
+    fn candidate_sampling(&self, frames: &[String], index: usize) -> CandidateSampling {
+        let base = self
+            .sampling_seed
+            .unwrap_or_else(|| Self::frames_fingerprint(frames));
+        CandidateSampling {
+            seed: base.wrapping_add(index as u64),
+            temperature: CANDIDATE_TEMPERATURES[index % CANDIDATE_TEMPERATURES.len()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CANDIDATE_TEMPERATURES;
+    use crate::llm::ollama::OllamaProvider;
+    use reqwest::Client;
+
+    #[test]
+    fn test_candidate_sampling_is_reproducible() {
+        let mut provider = OllamaProvider::new(Client::new());
+        let frames = vec!["a.jpg".to_string(), "b.jpg".to_string()];
+
+        let first = provider.candidate_sampling(&frames, 0);
+        assert_eq!(first, provider.candidate_sampling(&frames, 0));
+        let second = provider.candidate_sampling(&frames, 1);
+        assert_eq!(second.seed, first.seed.wrapping_add(1));
+        assert_ne!(second.temperature, first.temperature);
+
+        provider.sampling_seed = Some(42);
+        assert_eq!(provider.candidate_sampling(&frames, 4).seed, 46);
+        assert_eq!(
+            provider.candidate_sampling(&frames, 4).temperature,
+            CANDIDATE_TEMPERATURES[0]
+        );
+    }
+}
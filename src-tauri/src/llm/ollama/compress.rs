@@ -0,0 +1,171 @@
+// Ollama 请求体压缩 - 开启 compress_request 时以 gzip 发送 /api/chat 请求体
+//
+// 适用于在 Ollama 前面加了支持解压的反向代理、带宽有限的远程服务；
+// Ollama 自身不解压请求体，被拒绝后不压缩重发，之后的请求不再压缩
+
+use super::body::ChatBody;
+use super::OllamaProvider;
+use anyhow::Result;
+use flate2::write::GzEncoder;
+use std::io::Write;
+use std::sync::atomic::Ordering;
+use tracing::{info, warn};
+
+impl ChatBody {
+    /// gzip 压缩后的请求体，仍逐块压缩写出
+    pub(super) fn into_gzip_body(self) -> reqwest::Body {
+        reqwest::Body::wrap_stream(futures_util::stream::iter(GzipChunks::new(self)))
+    }
+}
+
+/// 逐块压缩请求体：每个输入块写入压缩器后取出已产生的输出，输入结束时写出 gzip 尾部
+struct GzipChunks {
+    inner: ChatBody,
+    encoder: Option<GzEncoder<Vec<u8>>>,
+}
+
+impl GzipChunks {
+    fn new(inner: ChatBody) -> Self {
+        Self {
+            inner,
+            encoder: Some(GzEncoder::new(Vec::new(), flate2::Compression::default())),
+        }
+    }
+}
+
+impl Iterator for GzipChunks {
+    type Item = std::io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let encoder = self.encoder.as_mut()?;
+            match self.inner.next() {
+                Some(Ok(chunk)) => {
+                    if let Err(e) = encoder.write_all(&chunk) {
+                        return Some(Err(e));
+                    }
+                    let output = std::mem::take(encoder.get_mut());
+                    // 压缩器可能暂不输出，继续读取下一块
+                    if !output.is_empty() {
+                        return Some(Ok(output));
+                    }
+                }
+                Some(Err(e)) => return Some(Err(e)),
+                None => return self.encoder.take().map(GzEncoder::finish),
+            }
+        }
+    }
+}
+
+impl OllamaProvider {
+    /// 发送 /api/chat 请求；开启 compress_request 时以 gzip 发送请求体，
+    /// 服务端以 415 或 400（Ollama 自身不解压，会把压缩数据当作非法 JSON）拒绝时不压缩重发一次，
+    /// 重发成功说明服务端不支持压缩，之后的请求不再压缩
+    pub(super) async fn send_chat_body(
+        &self,
+        url: &str,
+        timeout: Option<std::time::Duration>,
+        body: impl Fn() -> Result<ChatBody>,
+    ) -> Result<reqwest::Response> {
+        let body = &body;
+        let send = |gzip: bool| async move {
+            let body = body()?;
+            let mut request = self
+                .client()
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json");
+            request = if gzip {
+                request
+                    .header(reqwest::header::CONTENT_ENCODING, "gzip")
+                    .body(body.into_gzip_body())
+            } else {
+                request.body(body.into_body())
+            };
+            if let Some(timeout) = timeout {
+                request = request.timeout(timeout);
+            }
+            request.send().await.map_err(|e| self.map_request_error(e))
+        };
+
+        let gzip = self.compress_request && !self.gzip_rejected.load(Ordering::Relaxed);
+        let resp = send(gzip).await?;
+        let rejected = matches!(
+            resp.status(),
+            reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE | reqwest::StatusCode::BAD_REQUEST
+        );
+        if !gzip || !rejected {
+            return Ok(resp);
+        }
+        warn!(
+            "Ollama: 服务端拒绝了 gzip 压缩的请求体（HTTP {}），改为不压缩重发",
+            resp.status()
+        );
+        let resp = send(false).await?;
+        if resp.status().is_success() {
+            self.gzip_rejected.store(true, Ordering::Relaxed);
+            info!("Ollama: {} 不支持压缩请求体，之后不再压缩", self.base_url);
+        }
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GzipChunks;
+    use crate::llm::ollama::body::{ChatBody, ChatImage, ChatRequest};
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::LLMProvider;
+    use crate::test_server::{serve, Response};
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_compressed_request_falls_back_to_plain_when_unsupported() {
+        // 第 1 次请求以 415 拒绝，之后成功；记录每次请求是否为 gzip 压缩
+        let (tx, mut compressed) = tokio::sync::mpsc::unbounded_channel();
+        let mut attempt = 0;
+        let url = serve(move |request| {
+            attempt += 1;
+            let gzip_header = request.header("content-encoding") == Some("gzip");
+            let gzip_body = request.body.starts_with(&[0x1f, 0x8b]);
+            assert_eq!(gzip_header, gzip_body, "第 {} 次请求", attempt);
+            let _ = tx.send(gzip_header);
+            if attempt == 1 {
+                Response::with_status("415 Unsupported Media Type", "unsupported content encoding")
+            } else {
+                Response::ok(r#"{"message":{"content":"ok"}}"#)
+            }
+        })
+        .await;
+
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url, "compress_request": true }))
+            .unwrap();
+        for _ in 0..2 {
+            let content = provider
+                .call_ollama_chat("llava", "hi".to_string(), Vec::new(), None)
+                .await
+                .unwrap();
+            assert_eq!(content, "ok");
+        }
+        // 压缩请求被拒后不压缩重发，之后的请求不再压缩
+        let mut seen = Vec::new();
+        while let Ok(gzip) = compressed.try_recv() {
+            seen.push(gzip);
+        }
+        assert_eq!(seen, vec![true, false, false]);
+
+        // 压缩后的请求体解压即为原请求体
+        let chunks = || {
+            let req = ChatRequest::user("llava", "hi");
+            let images = vec![ChatImage::Ready("aGVsbG8=".to_string())];
+            ChatBody::new(&req, images.into()).unwrap()
+        };
+        let plain: Vec<u8> = chunks().flat_map(Result::unwrap).collect();
+        let gzip: Vec<u8> = GzipChunks::new(chunks()).flat_map(Result::unwrap).collect();
+        let mut decoded = Vec::new();
+        std::io::Read::read_to_end(&mut flate2::read::GzDecoder::new(&gzip[..]), &mut decoded)
+            .unwrap();
+        assert_eq!(decoded, plain);
+    }
+}
@@ -0,0 +1,141 @@
+// Ollama 低置信度处理 - 分析后按阈值标记，配置了备用模型时换模型重新分析一次
+//
+// 两次结果取整体置信度较高的一个；重新分析失败时保留原总结
+
+use super::OllamaProvider;
+use crate::llm::confidence;
+use crate::llm::plugin::{AnalysisContext, SessionSummary};
+use anyhow::Result;
+use tracing::{info, warn};
+
+/// 换模型重新分析所需的输入：(模型, 帧, 上下文)
+pub(super) type ConfidenceRetry<'a> = (&'a str, Vec<String>, AnalysisContext);
+
+impl OllamaProvider {
+    /// 低置信度时换模型重新分析需要同样的输入；未开启检查或备用模型与本次相同时为 None
+    pub(super) fn low_confidence_retry(
+        &self,
+        model: &str,
+        frames: &[String],
+        context: &AnalysisContext,
+    ) -> Option<ConfidenceRetry<'_>> {
+        self.min_confidence
+            .and(self.low_confidence_model.as_deref())
+            .filter(|retry_model| *retry_model != model)
+            .map(|retry_model| (retry_model, frames.to_vec(), context.clone()))
+    }
+
+    /// 按阈值标记低置信度的总结；给出了重新分析的模型时再分析一次，取整体置信度较高的结果
+    pub(super) async fn check_confidence(
+        &self,
+        mut summary: SessionSummary,
+        retry: Option<ConfidenceRetry<'_>>,
+    ) -> Result<SessionSummary> {
+        if !confidence::flag_low_confidence(&mut summary, self.min_confidence) {
+            return Ok(summary);
+        }
+        let original = confidence::aggregate_confidence(&summary.tags).unwrap_or(0.0);
+        warn!(
+            "Ollama: 总结整体置信度 {:.2} 低于阈值，已标记为低置信度",
+            original
+        );
+        let Some((model, frames, context)) = retry else {
+            return Ok(summary);
+        };
+        info!("Ollama: 改用模型 {} 重新分析低置信度的会话", model);
+        match self.analyze_bounded(frames, context, model).await {
+            Ok(mut retried) => {
+                confidence::flag_low_confidence(&mut retried, self.min_confidence);
+                let retried_confidence =
+                    confidence::aggregate_confidence(&retried.tags).unwrap_or(0.0);
+                info!(
+                    "Ollama: 重新分析的整体置信度 {:.2}（原 {:.2}）",
+                    retried_confidence, original
+                );
+                Ok(if retried_confidence > original {
+                    retried
+                } else {
+                    summary
+                })
+            }
+            Err(e) => {
+                warn!("Ollama: 改用模型 {} 重新分析失败，保留原总结: {}", model, e);
+                Ok(summary)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::LLMProvider;
+    use crate::test_server::{serve, Response};
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_low_confidence_summary_is_retried_with_fallback_model() {
+        // 默认模型给出笼统的低置信度总结，备用模型给出明确的总结
+        let url = serve(|request| {
+            let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+            let content = if body["model"] == "llava:34b" {
+                serde_json::json!({
+                    "title": "编写代码",
+                    "summary": "在编辑器中实现新功能",
+                    "tags": [{"category": "work", "confidence": 0.9, "keywords": []}]
+                })
+            } else {
+                serde_json::json!({
+                    "title": "使用电脑",
+                    "summary": "在电脑上操作",
+                    "tags": [
+                        {"category": "work", "confidence": 0.3, "keywords": []},
+                        {"category": "other", "confidence": 0.2, "keywords": []}
+                    ]
+                })
+            };
+            Response::ok(
+                serde_json::json!({ "message": { "content": content.to_string() } }).to_string(),
+            )
+        })
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<String> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.jpg", 1_700_000_000_000i64 + i));
+                image::RgbImage::from_fn(64, 48, |x, y| {
+                    image::Rgb([(x * 4) as u8, (y * 5) as u8, (i * 40) as u8])
+                })
+                .save(&path)
+                .unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({
+                "base_url": url,
+                "model": "llava",
+                "detect_idle_frames": false,
+                "min_confidence": 0.5
+            }))
+            .unwrap();
+        // 只标记，不重新分析
+        let summary = provider.analyze_frames(frames.clone()).await.unwrap();
+        assert_eq!(summary.title, "使用电脑");
+        assert!(summary.low_confidence);
+
+        provider
+            .configure(serde_json::json!({ "low_confidence_model": " llava:34b " }))
+            .unwrap();
+        let summary = provider.analyze_frames(frames).await.unwrap();
+        assert_eq!(summary.title, "编写代码");
+        assert!(!summary.low_confidence);
+
+        assert!(provider
+            .configure(serde_json::json!({ "min_confidence": 1.5 }))
+            .is_err());
+    }
+}
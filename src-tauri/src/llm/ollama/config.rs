@@ -0,0 +1,188 @@
+// Ollama 配置应用 - 把校验后的 OllamaConfig 写入 provider 各字段
+//
+// 配置整体反序列化为 OllamaConfig（拒绝未知字段），部分更新在最近一次应用的配置上合并
+
+use super::OllamaProvider;
+use crate::llm::OllamaConfig;
+
+impl OllamaProvider {
+    /// 应用完整配置，并记录下来作为之后部分更新的合并基础
+    pub(super) fn apply_config(&mut self, config: OllamaConfig) {
+        self.base_url = config.base_url.clone();
+        self.model = config.model.clone();
+        self.frame_order_hint = config.frame_order_hint;
+        self.frame_index_captions = config.frame_index_captions;
+        self.trust_model_times = config.trust_model_times;
+        self.stream = config.stream;
+        self.num_predict = config.num_predict;
+        self.sample_jitter = config.sample_jitter;
+        self.sampling_seed = config.sampling_seed;
+        self.max_payload_bytes = config.max_payload_bytes;
+        self.keep_alive_poll_secs = config.keep_alive_poll_secs.filter(|n| *n > 0);
+        self.prompt_sanitization = config.prompt_sanitization;
+        self.output_language = config
+            .output_language
+            .as_deref()
+            .map(str::trim)
+            .filter(|lang| !lang.is_empty())
+            .map(str::to_string);
+        self.min_frames_for_analysis = config.min_frames_for_analysis.max(1);
+        self.derive_empty_summary = config.derive_empty_summary;
+        self.model_rules = Self::sorted_model_rules(config.model_rules.clone());
+        self.prompt_profiles = config.prompt_profiles.clone();
+        self.reference_images = Self::bounded_reference_images(config.reference_images.clone());
+        self.connect_timeout_secs = config.connect_timeout_secs.max(1);
+        self.read_timeout_secs = config.read_timeout_secs.max(1);
+        self.record_sampled_frames = config.record_sampled_frames;
+        self.detect_idle_frames = config.detect_idle_frames;
+        self.frames_per_minute = config
+            .frames_per_minute
+            .filter(|n| n.is_finite() && *n > 0.0);
+        self.few_shot_examples = config
+            .few_shot_examples
+            .min(crate::llm::corrections::MAX_FEW_SHOT_EXAMPLES);
+        self.extra_fields = config.extra_fields.clone();
+        self.analysis_mode = Self::default_analysis_mode(&config);
+        self.analysis_modes = config.analysis_modes.clone();
+        self.frame_order = config.order;
+        self.json_selection = config.json_selection;
+        self.taxonomy = config.taxonomy.clone();
+        self.schema_validation = config.schema_validation;
+        self.mosaic = config.mosaic.clone();
+        self.compress_request = config.compress_request;
+        self.app_coverage = config.app_coverage.clone();
+        self.scene_aware = config.scene_aware.clone();
+        self.capture_reasoning = config.capture_reasoning;
+        self.min_confidence = config.min_confidence;
+        self.low_confidence_model = config
+            .low_confidence_model
+            .as_deref()
+            .map(str::trim)
+            .filter(|model| !model.is_empty())
+            .map(str::to_string);
+        self.max_encoded_frames_in_memory = config.max_encoded_frames_in_memory;
+        self.max_frames_hard_limit = config.max_frames_hard_limit;
+        *self.gzip_rejected.get_mut() = false;
+        // base_url 至少要有
+        self.configured = !self.base_url.trim().is_empty();
+        self.config = config;
+    }
+
+    /// 配置的默认分析模式（空白视为未配置）
+    pub(super) fn default_analysis_mode(config: &OllamaConfig) -> Option<String> {
+        config
+            .analysis_mode
+            .as_deref()
+            .map(str::trim)
+            .filter(|mode| !mode.is_empty())
+            .map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{FramesUnavailable, LLMProvider, TooFewFrames};
+    use reqwest::Client;
+
+    #[test]
+    fn test_configure_validates_fields() {
+        let mut provider = OllamaProvider::new(Client::new());
+
+        // 缺省字段使用默认值
+        provider
+            .configure(serde_json::json!({ "model": "llava:13b", "stream": true }))
+            .unwrap();
+        assert_eq!(provider.model, "llava:13b");
+        assert!(provider.stream);
+        assert!(provider.is_configured());
+
+        // 拼错的字段名与错误的类型都应报错
+        let err = provider
+            .configure(serde_json::json!({ "basel_url": "http://localhost:11434" }))
+            .unwrap_err();
+        assert!(err.to_string().contains("basel_url"), "{err}");
+        assert!(provider
+            .configure(serde_json::json!({ "num_predict": "many" }))
+            .is_err());
+    }
+
+    #[test]
+    fn test_partial_configure_keeps_earlier_settings() {
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({
+                "base_url": "http://127.0.0.1:11434",
+                "frame_order_hint": false,
+                "stream": true,
+                "max_payload_bytes": 4_000_000,
+                "keep_alive_poll_secs": 5
+            }))
+            .unwrap();
+
+        // 设置界面只发送 base_url 与 model，其余开关保持不变
+        provider
+            .configure(serde_json::json!({
+                "base_url": "http://192.168.1.2:11434",
+                "model": "llava:13b"
+            }))
+            .unwrap();
+        assert_eq!(provider.base_url, "http://192.168.1.2:11434");
+        assert_eq!(provider.model, "llava:13b");
+        assert!(!provider.frame_order_hint);
+        assert!(provider.stream);
+        assert_eq!(provider.max_payload_bytes, Some(4_000_000));
+        assert_eq!(provider.keep_alive_poll_secs, Some(5));
+
+        // 合并后的配置仍拒绝未知字段，失败时不影响已应用的配置
+        assert!(provider
+            .configure(serde_json::json!({ "strem": false }))
+            .is_err());
+        provider.configure(serde_json::json!({})).unwrap();
+        assert!(provider.stream);
+
+        // 持久化时同样在已保存的配置上合并
+        let stored = crate::llm::OllamaConfig {
+            stream: true,
+            ..Default::default()
+        };
+        let merged = stored
+            .merged(serde_json::json!({ "model": "llava:7b" }))
+            .unwrap();
+        assert!(merged.stream);
+        assert_eq!(merged.model, "llava:7b");
+        assert!(stored.merged(serde_json::json!(["model"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_min_frames_boundary() {
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "min_frames_for_analysis": 3 }))
+            .unwrap();
+
+        fn frames(n: usize) -> Vec<String> {
+            (0..n).map(|i| format!("/nonexistent/{i}.jpg")).collect()
+        }
+        let err = provider.analyze_frames(frames(2)).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<TooFewFrames>(),
+            Some(&TooFewFrames {
+                frames: 2,
+                min_frames: 3
+            })
+        );
+
+        // 恰好达到阈值时进入分析流程（帧文件不存在，在帧文件检查时报错）
+        let err = provider.analyze_frames(frames(3)).await.unwrap_err();
+        assert!(err.downcast_ref::<TooFewFrames>().is_none());
+        assert_eq!(
+            err.downcast_ref::<FramesUnavailable>(),
+            Some(&FramesUnavailable {
+                total: 3,
+                missing: 3,
+                min_frames: 3
+            })
+        );
+    }
+}
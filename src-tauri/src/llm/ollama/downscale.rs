@@ -0,0 +1,205 @@
+// Ollama 图片过大处理 - 服务端以图片尺寸或体积过大拒绝请求时返回 OllamaImageTooLarge
+//
+// 重试由 DownscaleLayer 负责，重试请求按 retry_hints() 将全部图片缩小一半后发送
+
+use super::body::ChatImage;
+use super::OllamaProvider;
+use crate::llm::middleware::retry_hints;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::Value;
+use tracing::warn;
+
+/// 服务端以图片尺寸或体积过大拒绝请求
+///
+/// 开启 downscale_on_size_error 时由 DownscaleLayer 缩小图片后重试一次
+#[derive(Clone, Debug, PartialEq)]
+pub struct OllamaImageTooLarge {
+    pub status: u16,
+    pub message: String,
+}
+
+impl std::fmt::Display for OllamaImageTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Ollama 拒绝了过大的图片（HTTP {}）：{}",
+            self.status, self.message
+        )
+    }
+}
+
+impl std::error::Error for OllamaImageTooLarge {}
+
+/// 服务端错误信息是否表示图片尺寸或体积超限（各模型的措辞不同，按关键词判断）
+fn is_image_size_rejection(message: &str) -> bool {
+    let message = message.to_lowercase();
+    let about_image = ["image", "img", "picture", "pixel"]
+        .iter()
+        .any(|word| message.contains(word));
+    let too_large = [
+        "too large",
+        "too big",
+        "exceed",
+        "dimension",
+        "max size",
+        "maximum size",
+    ]
+    .iter()
+    .any(|word| message.contains(word));
+    about_image && too_large
+}
+
+/// 检查 /api/chat 的响应状态；失败时读取错误信息，图片过大的拒绝返回 OllamaImageTooLarge
+pub(super) async fn check_chat_status(resp: reqwest::Response) -> Result<reqwest::Response> {
+    let status = resp.status();
+    if status.is_success() {
+        return Ok(resp);
+    }
+    let body = resp.text().await.unwrap_or_default();
+    // 错误响应体通常为 {"error": "..."}
+    let message = serde_json::from_str::<Value>(super::parse::trim_noise(&body))
+        .ok()
+        .and_then(|v| v.get("error").and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string());
+    if is_image_size_rejection(&message) {
+        return Err(OllamaImageTooLarge {
+            status: status.as_u16(),
+            message,
+        }
+        .into());
+    }
+    Err(anyhow!("Ollama 请求失败 HTTP {}: {}", status, message))
+}
+
+impl OllamaProvider {
+    /// DownscaleLayer 重试时将图片缩小一半；无法缩小的图片原样保留（延迟编码的图片直接从帧缩小）
+    pub(super) async fn downscaled_images(images: Vec<ChatImage>) -> Result<Vec<ChatImage>> {
+        if !retry_hints().downscale || images.is_empty() {
+            return Ok(images);
+        }
+        let original_bytes: usize = images.iter().map(ChatImage::encoded_len).sum();
+        let (images, downscaled) = tokio::task::spawn_blocking(move || {
+            let mut downscaled = 0;
+            let images: Vec<ChatImage> = images
+                .into_iter()
+                .map(|image| {
+                    let smaller = match &image {
+                        ChatImage::Ready(image) => Self::downscale_reference(image),
+                        ChatImage::Deferred { path, .. } => Self::reencode_base64(path),
+                    };
+                    match smaller {
+                        Some(smaller) => {
+                            downscaled += 1;
+                            ChatImage::Ready(smaller)
+                        }
+                        None => image,
+                    }
+                })
+                .collect();
+            (images, downscaled)
+        })
+        .await?;
+        warn!(
+            "Ollama: 已将 {}/{} 张图片缩小一半后重试（{} -> {} 字节）",
+            downscaled,
+            images.len(),
+            original_bytes,
+            images.iter().map(ChatImage::encoded_len).sum::<usize>()
+        );
+        Ok(images)
+    }
+
+    /// 将已生成的图片引用（base64 或文件路径）缩小一半重新编码为 base64
+    fn downscale_reference(image: &str) -> Option<String> {
+        let bytes = match general_purpose::STANDARD.decode(image) {
+            Ok(bytes) => bytes,
+            Err(_) => crate::storage::frame_pack::read_frame_blocking(image).ok()?,
+        };
+        Self::reencode_bytes(&bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_image_size_rejection, OllamaImageTooLarge};
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::LLMProvider;
+    use crate::llm::{DownscaleLayer, ProviderBuilder};
+    use crate::test_server::{serve, Response};
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_image_size_rejection_retries_with_smaller_images() {
+        // 只有第 2 次请求成功，其余都以图片过大拒绝；记录每次请求体的字节数
+        let content = r#"{"title":"编写代码","summary":"在编辑器中修改代码"}"#;
+        let (tx, mut sizes) = tokio::sync::mpsc::unbounded_channel();
+        let mut attempt = 0;
+        let url = serve(move |request| {
+            attempt += 1;
+            let _ = tx.send(request.body.len());
+            if attempt == 2 {
+                Response::ok(serde_json::json!({ "message": { "content": content } }).to_string())
+            } else {
+                Response::with_status(
+                    "400 Bad Request",
+                    r#"{"error":"image too large: exceeds maximum dimension 1024"}"#,
+                )
+            }
+        })
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<String> = (0..5)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.png", 1_700_000_000_000i64 + i));
+                image::RgbImage::from_fn(320, 240, |x, y| {
+                    image::Rgb([
+                        (x % 256) as u8,
+                        (y % 256) as u8,
+                        ((x * y + i as u32) % 256) as u8,
+                    ])
+                })
+                .save(&path)
+                .unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({
+                "base_url": url,
+                "detect_idle_frames": false
+            }))
+            .unwrap();
+        let provider = ProviderBuilder::new()
+            .layer(DownscaleLayer::new())
+            .build(Box::new(provider));
+        let summary = provider.analyze_frames(frames.clone()).await.unwrap();
+        assert_eq!(summary.title, "编写代码");
+        let first = sizes.recv().await.unwrap();
+        let second = sizes.recv().await.unwrap();
+        assert!(
+            second < first / 2,
+            "重试的请求应更小: {} -> {}",
+            first,
+            second
+        );
+
+        // 缩小后仍被拒绝时不再重试，返回可识别的错误
+        let err = provider.analyze_frames(frames).await.unwrap_err();
+        assert!(err.downcast_ref::<OllamaImageTooLarge>().is_some(), "{err}");
+        assert_eq!(sizes.len(), 2);
+    }
+
+    #[test]
+    fn test_is_image_size_rejection() {
+        assert!(is_image_size_rejection(
+            "Image dimensions exceed the model limit"
+        ));
+        assert!(is_image_size_rejection("image too large"));
+        assert!(!is_image_size_rejection("model 'llava' not found"));
+        assert!(!is_image_size_rejection("context length exceeded"));
+    }
+}
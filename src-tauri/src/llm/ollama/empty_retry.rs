@@ -0,0 +1,81 @@
+// Ollama 空内容处理 - 模型偶尔在 HTTP 成功时只返回空白
+//
+// 空内容返回 EmptyResponse，而不是交给 JSON 解析报错；重试由 EmptyResponseLayer 负责，
+// 重试请求按 retry_hints() 在提示词末尾附加提醒
+
+use crate::llm::middleware::retry_hints;
+use crate::llm::plugin::EmptyResponse;
+use anyhow::Result;
+
+/// 模型返回空内容后重试时附加在提示词末尾的提醒
+pub(super) const EMPTY_RESPONSE_NUDGE: &str =
+    "\n\n注意：上一次回复为空。请直接输出符合上述格式的 JSON，不要只输出空白。";
+
+/// EmptyResponseLayer 重试时在提示词末尾附加提醒
+pub(super) fn nudged_prompt(prompt: String) -> String {
+    if retry_hints().nudge {
+        prompt + EMPTY_RESPONSE_NUDGE
+    } else {
+        prompt
+    }
+}
+
+/// 内容为空（或只有空白）时返回 EmptyResponse
+pub(super) fn non_empty(content: String) -> Result<String> {
+    if content.trim().is_empty() {
+        return Err(EmptyResponse { attempts: 1 }.into());
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EMPTY_RESPONSE_NUDGE;
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{EmptyResponse, LLMProvider};
+    use crate::llm::{EmptyResponseLayer, ProviderBuilder};
+    use crate::test_server::{serve, Response};
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_empty_content_is_retried_with_nudge() {
+        // 第一次只返回空白，EmptyResponseLayer 附加提醒后重试成功
+        let content = r#"{"title":"编写代码","summary":"在编辑器中修改代码"}"#;
+        let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        let mut attempt = 0;
+        let url = serve(move |request| {
+            attempt += 1;
+            let _ = tx.send(request.text());
+            let reply = if attempt == 1 { " \n\t" } else { content };
+            Response::ok(serde_json::json!({ "message": { "content": reply } }).to_string())
+        })
+        .await;
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url }))
+            .unwrap();
+        let provider = ProviderBuilder::new()
+            .layer(EmptyResponseLayer::new(1))
+            .build(Box::new(provider));
+        let summary = provider.analyze_text("写代码".to_string()).await.unwrap();
+        assert_eq!(summary.title, "编写代码");
+        let nudge = EMPTY_RESPONSE_NUDGE.trim();
+        assert!(!requests.try_recv().unwrap().contains(nudge));
+        assert!(requests.try_recv().unwrap().contains(nudge));
+
+        // 没有中间件时只请求一次：返回可识别的 EmptyResponse，而不是 JSON 解析错误
+        let url = serve(|_| Response::ok(r#"{"message":{"content":"   "}}"#)).await;
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url }))
+            .unwrap();
+        let err = provider
+            .analyze_text("写代码".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<EmptyResponse>(),
+            Some(&EmptyResponse { attempts: 1 })
+        );
+    }
+}
@@ -0,0 +1,202 @@
+// Ollama 自定义总结字段 - 按配置要求模型在 JSON 顶层额外输出字段，并写入会话记录
+//
+// 只接受配置中声明的字段，模型自行输出的 extra 会被丢弃
+
+use super::OllamaProvider;
+use crate::llm::ExtraFieldKind;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use tracing::{debug, warn};
+
+impl OllamaProvider {
+    /// 自定义字段说明：要求模型在 JSON 顶层额外输出的字段
+    pub(super) fn extra_fields_note(&self) -> Option<String> {
+        if self.extra_fields.is_empty() {
+            return None;
+        }
+        let mut note = String::from("\n另外请在 JSON 顶层输出以下字段，无法判断时省略：");
+        for field in &self.extra_fields {
+            let kind = match field.kind {
+                ExtraFieldKind::String => "字符串",
+                ExtraFieldKind::Array => "字符串数组",
+            };
+            note.push_str(&format!("\n- {}（{}）", field.name, kind));
+            let description = field.description.trim();
+            if !description.is_empty() {
+                note.push_str(&format!("：{}", description));
+            }
+        }
+        Some(note)
+    }
+
+    /// 取出配置的自定义字段并按类型规整，空值或类型无法识别的值丢弃；模型自行输出的 extra 一并移除
+    pub(super) fn take_extra_fields(&self, obj: &mut Map<String, Value>) -> HashMap<String, Value> {
+        fn text(value: &Value) -> Option<String> {
+            let text = match value {
+                Value::String(s) => s.trim().to_string(),
+                Value::Number(n) => n.to_string(),
+                Value::Bool(b) => b.to_string(),
+                _ => return None,
+            };
+            (!text.is_empty()).then_some(text)
+        }
+
+        obj.remove("extra");
+        let mut extra = HashMap::new();
+        for field in &self.extra_fields {
+            let Some(value) = obj.remove(&field.name) else {
+                continue;
+            };
+            let items: Vec<String> = match &value {
+                Value::Array(items) => items.iter().filter_map(text).collect(),
+                other => text(other).into_iter().collect(),
+            };
+            if items.is_empty() {
+                debug!(
+                    "Ollama: 自定义字段 {} 的值为空或无法识别: {}",
+                    field.name, value
+                );
+                continue;
+            }
+            let value = match field.kind {
+                ExtraFieldKind::String => Value::String(items.join("、")),
+                ExtraFieldKind::Array => {
+                    Value::Array(items.into_iter().map(Value::String).collect())
+                }
+            };
+            extra.insert(field.name.clone(), value);
+        }
+        extra
+    }
+
+    /// 将自定义字段写入当前会话；未配置自定义字段、未关联数据库或会话时跳过，写入失败只记录告警
+    ///
+    /// 配置了自定义字段时总是写入，重新分析得到空结果时也覆盖旧值
+    pub(super) async fn save_extra_fields(&self, extra: &HashMap<String, Value>) {
+        if self.extra_fields.is_empty() {
+            return;
+        }
+        let (Some(db), Some(session_id)) = (&self.db, self.session_id) else {
+            return;
+        };
+        let record = crate::storage::SessionExtraFieldsRecord {
+            session_id,
+            fields: Value::Object(extra.clone().into_iter().collect()).to_string(),
+            created_at: crate::storage::local_now(),
+        };
+        if let Err(e) = db.save_session_extra_fields(&record).await {
+            warn!("Ollama: 保存自定义字段失败 session={}: {}", session_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{AnalysisContext, LLMProvider};
+    use crate::test_server::serve_routes;
+    use chrono::Utc;
+    use reqwest::Client;
+    use serde_json::Value;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_extra_fields_round_trip_through_storage() {
+        let content = serde_json::json!({
+            "title": "评审合同",
+            "summary": "与客户开会讨论合同条款",
+            "tags": [{"category": "work", "confidence": 0.9, "keywords": ["合同"]}],
+            "project": "  Apollo  ",
+            "meeting_participants": "Alice",
+            "extra": {"injected": true}
+        })
+        .to_string();
+        let url = serve_routes(vec![(
+            "/api/chat",
+            serde_json::json!({ "message": { "content": content } }).to_string(),
+        )])
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<String> = (0..5)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.jpg", 1_700_000_000_000i64 + i));
+                image::RgbImage::from_fn(64, 48, |x, y| {
+                    image::Rgb([(x * 4) as u8, (y * 5) as u8, (i * 40) as u8])
+                })
+                .save(&path)
+                .unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+        let db = Arc::new(
+            crate::storage::Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let session_id = db
+            .insert_session(&crate::storage::Session {
+                id: None,
+                start_time: Utc::now(),
+                end_time: Utc::now(),
+                title: String::new(),
+                summary: String::new(),
+                video_path: None,
+                tags: "[]".to_string(),
+                created_at: None,
+                device_name: None,
+                device_type: None,
+                pinned: false,
+            })
+            .await
+            .unwrap();
+
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({
+                "base_url": url,
+                "detect_idle_frames": false,
+                "extra_fields": [
+                    { "name": "project", "description": "所属项目" },
+                    { "name": "meeting_participants", "kind": "array" },
+                    { "name": "ticket" }
+                ]
+            }))
+            .unwrap();
+        provider.set_database(db.clone());
+        provider.set_session_id(session_id);
+        assert!(provider
+            .build_prompt(&AnalysisContext::default(), &[])
+            .contains("- project（字符串）：所属项目\n- meeting_participants（字符串数组）"));
+
+        let summary = provider.analyze_frames(frames).await.unwrap();
+        let expected = HashMap::from([
+            ("project".to_string(), serde_json::json!("Apollo")),
+            (
+                "meeting_participants".to_string(),
+                serde_json::json!(["Alice"]),
+            ),
+        ]);
+        assert_eq!(summary.extra, expected);
+
+        let stored = db
+            .get_session_extra_fields(session_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let stored: HashMap<String, Value> = serde_json::from_str(&stored.fields).unwrap();
+        assert_eq!(stored, expected);
+
+        // 与内置字段重名或重复的字段在配置时报错
+        for fields in [
+            serde_json::json!([{ "name": "title" }]),
+            serde_json::json!([{ "name": "project" }, { "name": "project" }]),
+            serde_json::json!([{ "name": "Project Name" }]),
+        ] {
+            assert!(provider
+                .configure(serde_json::json!({ "extra_fields": fields }))
+                .is_err());
+        }
+    }
+}
@@ -0,0 +1,114 @@
+// Ollama 人工更正示例 - 检索与当前会话相似的人工更正，作为少样本示例注入提示词
+//
+// 调用方已提供示例时不再检索；未开启 few_shot_examples 或未关联数据库时不注入
+
+use super::prompt::frame_timestamp_ms;
+use super::OllamaProvider;
+use crate::llm::corrections;
+use crate::llm::plugin::{AnalysisContext, FewShotExample};
+use chrono::{DateTime, Utc};
+use tracing::{debug, warn};
+
+impl OllamaProvider {
+    /// 上下文中没有示例时补充检索到的人工更正
+    pub(super) async fn with_few_shot_examples(
+        &self,
+        mut context: AnalysisContext,
+        frames: &[String],
+    ) -> AnalysisContext {
+        if context.examples.is_empty() {
+            context.examples = self.load_few_shot_examples(&context, frames).await;
+        }
+        context
+    }
+
+    /// 检索与当前会话相似的人工更正作为示例；未开启、未关联数据库或读取失败时为空
+    async fn load_few_shot_examples(
+        &self,
+        context: &AnalysisContext,
+        frames: &[String],
+    ) -> Vec<FewShotExample> {
+        let Some(db) = self.db.as_ref().filter(|_| self.few_shot_examples > 0) else {
+            return Vec::new();
+        };
+        // 会话开始时间优先取会话窗口，否则取最早一帧的时间
+        let start = self.session_window_start.or_else(|| {
+            frames
+                .iter()
+                .filter_map(|f| frame_timestamp_ms(f))
+                .min()
+                .and_then(DateTime::from_timestamp_millis)
+        });
+        let previous = match start {
+            Some(start) => self.previous_session(db, start).await,
+            None => None,
+        };
+        let query = corrections::query_text(context, previous.as_ref());
+        if query.is_empty() {
+            debug!("Ollama: 没有可用于检索更正示例的上下文");
+            return Vec::new();
+        }
+        let records = match db
+            .get_recent_summary_corrections(corrections::MAX_CANDIDATE_CORRECTIONS)
+            .await
+        {
+            Ok(records) => records,
+            Err(e) => {
+                warn!("Ollama: 读取人工更正失败: {}", e);
+                return Vec::new();
+            }
+        };
+        let examples = corrections::select_examples(&query, &records, self.few_shot_examples);
+        debug!(
+            "Ollama: 从 {} 条人工更正中选取 {} 条示例",
+            records.len(),
+            examples.len()
+        );
+        examples
+    }
+
+    /// 紧邻当前会话之前（间隔不超过 30 分钟）且已完成分析的会话
+    async fn previous_session(
+        &self,
+        db: &crate::storage::Database,
+        start: DateTime<Utc>,
+    ) -> Option<crate::storage::Session> {
+        let date = start.format("%Y-%m-%d").to_string();
+        let sessions = db.get_sessions_by_date(&date).await.ok()?;
+        sessions
+            .into_iter()
+            .filter(|s| s.id != self.session_id && s.end_time <= start)
+            .filter(|s| start - s.end_time <= chrono::Duration::minutes(30))
+            .filter(|s| s.tags != "[]")
+            .max_by_key(|s| s.end_time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{AnalysisContext, FewShotExample};
+    use reqwest::Client;
+
+    #[test]
+    fn test_build_prompt_with_correction_examples() {
+        let provider = OllamaProvider::new(Client::new());
+        let context = AnalysisContext {
+            examples: vec![FewShotExample {
+                description: "浏览网页\n在浏览器中阅读文章".to_string(),
+                corrected: r#"{"title":"阅读 Rust 文档","tags":[{"category":"learning"}]}"#
+                    .to_string(),
+            }],
+            ..Default::default()
+        };
+        let prompt = provider.build_prompt(&context, &[]);
+
+        assert!(prompt.contains("示例 1：\n原始总结：浏览网页"));
+        assert!(prompt.contains(r#"更正为：{"title":"阅读 Rust 文档""#));
+        assert!(prompt.ends_with("只返回 JSON。"));
+        // 没有示例时不出现示例段落
+        assert!(!provider
+            .build_prompt(&AnalysisContext::default(), &[])
+            .contains("人工更正"));
+    }
+}
@@ -0,0 +1,93 @@
+// Ollama 帧文件检查 - 重新分析时帧可能已被清理或移动
+//
+// 剩余帧数低于最小分析帧数时返回 FramesUnavailable，避免用残缺的帧生成低质量总结
+
+use super::OllamaProvider;
+use crate::llm::plugin::FramesUnavailable;
+use anyhow::Result;
+use tracing::warn;
+
+impl OllamaProvider {
+    /// 剔除已不存在的帧文件；剩余帧数不足时返回 FramesUnavailable
+    pub(super) async fn drop_missing_frames(&self, frames: Vec<String>) -> Result<Vec<String>> {
+        let total = frames.len();
+        let mut available = Vec::with_capacity(total);
+        for path in frames {
+            let storage = crate::storage::frame_pack::storage_path(&path);
+            if tokio::fs::try_exists(storage).await.unwrap_or(false) {
+                available.push(path);
+            }
+        }
+        let missing = total - available.len();
+        if missing == 0 {
+            return Ok(available);
+        }
+        let min_frames = self.min_frames_for_analysis.max(1);
+        if available.len() < min_frames {
+            return Err(FramesUnavailable {
+                total,
+                missing,
+                min_frames,
+            }
+            .into());
+        }
+        warn!(
+            "Ollama: {}/{} 帧文件已不存在，使用剩余 {} 帧分析",
+            missing,
+            total,
+            available.len()
+        );
+        Ok(available)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{FramesUnavailable, LLMProvider};
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_missing_frame_files_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        // 一半帧文件已被清理
+        let frames: Vec<String> = (0..8)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.jpg", 1_700_000_000_000i64 + i));
+                if i % 2 == 0 {
+                    image::RgbImage::from_pixel(64, 48, image::Rgb([120, 80, 40]))
+                        .save(&path)
+                        .unwrap();
+                }
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({
+                "base_url": "http://127.0.0.1:9",
+                "min_frames_for_analysis": 6,
+                "detect_idle_frames": false
+            }))
+            .unwrap();
+        let err = provider.analyze_frames(frames.clone()).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<FramesUnavailable>(),
+            Some(&FramesUnavailable {
+                total: 8,
+                missing: 4,
+                min_frames: 6
+            })
+        );
+        assert!(err.to_string().starts_with("4/8 帧文件已不存在"));
+
+        // 剩余帧足够时只分析存在的帧（不可达的地址：到达调用模型一步即失败）
+        provider.min_frames_for_analysis = 4;
+        let available = provider.drop_missing_frames(frames.clone()).await.unwrap();
+        assert_eq!(available.len(), 4);
+        assert!(available.iter().all(|p| std::path::Path::new(p).exists()));
+        let err = provider.analyze_frames(frames).await.unwrap_err();
+        assert!(err.downcast_ref::<FramesUnavailable>().is_none());
+    }
+}
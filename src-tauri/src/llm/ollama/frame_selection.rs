@@ -0,0 +1,84 @@
+// Ollama 分析所用帧记录 - 开启 record_sampled_frames 后记下实际发送给模型的帧
+//
+// 帧随总结返回，并写入当前会话（多候选时各候选的帧不同，只随总结返回）
+
+use super::candidates::CandidateSampling;
+use super::OllamaProvider;
+use crate::llm::plugin::{FrameSelection, SessionSummary};
+use tracing::warn;
+
+impl OllamaProvider {
+    /// 按配置在总结中记录实际发送的帧（frames 为采样前的全部帧，encoded 为编码成功后发送的帧）
+    pub(super) async fn record_frame_selection(
+        &self,
+        summary: &mut SessionSummary,
+        frames: &[String],
+        encoded: &[String],
+        candidate: Option<CandidateSampling>,
+    ) {
+        if !self.record_sampled_frames {
+            return;
+        }
+        let selection = FrameSelection::from_paths(frames, encoded);
+        if candidate.is_none() {
+            self.save_frame_selection(&selection).await;
+        }
+        summary.frame_selection = Some(selection);
+    }
+
+    /// 将分析所用的帧写入当前会话；未关联数据库或会话时跳过，写入失败只记录告警
+    async fn save_frame_selection(&self, selection: &FrameSelection) {
+        let (Some(db), Some(session_id)) = (&self.db, self.session_id) else {
+            return;
+        };
+        let frames = match serde_json::to_string(&selection.frames) {
+            Ok(frames) => frames,
+            Err(e) => {
+                warn!("Ollama: 序列化分析所用帧失败: {}", e);
+                return;
+            }
+        };
+        let record = crate::storage::FrameSelectionRecord {
+            session_id,
+            total_frames: selection.total_frames as i64,
+            frames,
+            created_at: crate::storage::local_now(),
+        };
+        if let Err(e) = db.save_frame_selection(&record).await {
+            warn!("Ollama: 保存分析所用帧失败 session={}: {}", session_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{FrameSelection, SampledFrame};
+    use reqwest::Client;
+
+    #[test]
+    fn test_frame_selection_matches_sampled_frames() {
+        let mut provider = OllamaProvider::new(Client::new());
+        provider.sample_jitter = true;
+        let frames: Vec<String> = (0..4000)
+            .map(|i| format!("/tmp/frames/{}.jpg", 1_700_000_000_000i64 + i * 1000))
+            .collect();
+        let sampled = provider.sample_frames(&frames, 30, Some(42));
+        // 模拟编码失败丢掉一帧
+        let encoded: Vec<String> = sampled.iter().skip(1).cloned().collect();
+
+        let selection = FrameSelection::from_paths(&frames, &encoded);
+        assert_eq!(selection.total_frames, 4000);
+        assert_eq!(selection.frames.len(), 29);
+        for frame in &selection.frames {
+            assert_eq!(frames[frame.index], frame.path);
+        }
+        let paths: Vec<String> = selection.frames.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(paths, encoded);
+
+        // 写入数据库的 JSON 可还原出相同的帧
+        let stored = serde_json::to_string(&selection.frames).unwrap();
+        let restored: Vec<SampledFrame> = serde_json::from_str(&stored).unwrap();
+        assert_eq!(restored, selection.frames);
+    }
+}
@@ -0,0 +1,112 @@
+// Ollama 附加请求头 - 鉴权网关等场景下为每个请求附加配置的请求头
+//
+// 疑似密钥的值标记为敏感：日志与 Debug 输出中以 *** 代替
+
+use super::OllamaProvider;
+use anyhow::{anyhow, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use std::collections::BTreeMap;
+
+/// 请求头名称包含这些片段时视为密钥，日志中不输出其值
+const SECRET_HEADER_HINTS: [&str; 6] = ["auth", "cookie", "key", "token", "secret", "password"];
+
+impl OllamaProvider {
+    /// 校验并转换配置的请求头；疑似密钥的值标记为敏感，不出现在 Debug 输出中
+    pub(super) fn build_headers(headers: &BTreeMap<String, String>) -> Result<HeaderMap> {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            let header_name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| anyhow!("无效的请求头名称: {:?}", name))?;
+            let mut header_value = HeaderValue::from_str(value.trim())
+                .map_err(|_| anyhow!("请求头 {} 的值包含非法字符", header_name))?;
+            header_value.set_sensitive(Self::is_secret_header(&header_name, value));
+            map.insert(header_name, header_value);
+        }
+        Ok(map)
+    }
+
+    /// 按名称或值的形式判断请求头是否可能包含密钥
+    fn is_secret_header(name: &HeaderName, value: &str) -> bool {
+        let name = name.as_str();
+        let value = value.trim().to_ascii_lowercase();
+        SECRET_HEADER_HINTS.iter().any(|hint| name.contains(hint))
+            || value.starts_with("bearer ")
+            || value.starts_with("basic ")
+    }
+
+    /// 用于日志的请求头说明：敏感值以 *** 代替
+    pub(super) fn describe_headers(headers: &HeaderMap) -> String {
+        headers
+            .iter()
+            .map(|(name, value)| {
+                let shown = match value.to_str() {
+                    Ok(v) if !value.is_sensitive() => v,
+                    _ => "***",
+                };
+                format!("{}={}", name, shown)
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::LLMProvider;
+    use crate::test_server::{serve, Response};
+    use reqwest::Client;
+    use std::collections::BTreeMap;
+
+    #[tokio::test]
+    async fn test_configured_headers_are_sent_and_redacted() {
+        let (tx, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        let url = serve(move |request| {
+            let _ = tx.send(request);
+            let content = r#"{"title":"编写代码","summary":"在编辑器中修改代码"}"#;
+            Response::ok(serde_json::json!({ "message": { "content": content } }).to_string())
+        })
+        .await;
+
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({
+                "base_url": url,
+                "headers": {
+                    "X-Api-Key": "sk-live-123",
+                    "Proxy-Authorization": "Bearer abc",
+                    "X-Team": "vision"
+                }
+            }))
+            .unwrap();
+        provider.analyze_text("写代码".to_string()).await.unwrap();
+        let request = requests.try_recv().unwrap();
+        assert_eq!(request.header("x-api-key"), Some("sk-live-123"));
+        assert_eq!(request.header("proxy-authorization"), Some("Bearer abc"));
+        assert_eq!(request.header("x-team"), Some("vision"));
+
+        // 日志只输出非敏感值
+        let headers = OllamaProvider::build_headers(&BTreeMap::from([
+            ("X-Api-Key".to_string(), "sk-live-123".to_string()),
+            ("X-Forwarded-User".to_string(), "Bearer abc".to_string()),
+            ("X-Team".to_string(), "vision".to_string()),
+        ]))
+        .unwrap();
+        let described = OllamaProvider::describe_headers(&headers);
+        assert_eq!(
+            described,
+            "x-api-key=***, x-forwarded-user=***, x-team=vision"
+        );
+        assert!(!format!("{:?}", headers).contains("sk-live-123"));
+
+        // 非法名称或值在配置时报错
+        assert!(OllamaProvider::build_headers(&BTreeMap::from([(
+            "Bad Header".to_string(),
+            "v".to_string()
+        )]))
+        .is_err());
+        assert!(provider
+            .configure(serde_json::json!({ "headers": { "X-Token": "a\nb" } }))
+            .is_err());
+    }
+}
@@ -0,0 +1,104 @@
+// Ollama 空闲会话 - 本地判定黑屏、静止画面，并接受模型给出的 {"empty": true}
+//
+// 本地判定为空闲时不调用模型；模型明确表示没有活动时不要求其余字段
+
+use super::OllamaProvider;
+use crate::llm::plugin::SessionSummary;
+use serde_json::Value;
+use tracing::info;
+
+impl OllamaProvider {
+    /// 采样帧几乎都是黑屏或静止画面时返回空闲总结（关闭 detect_idle_frames 时不判定）
+    pub(super) async fn idle_summary_for_frames(
+        &self,
+        sampled: &[String],
+        seed: Option<u64>,
+    ) -> Option<SessionSummary> {
+        if !self.detect_idle_frames {
+            return None;
+        }
+        let paths = sampled.to_vec();
+        let idle = tokio::task::spawn_blocking(move || crate::llm::idle::detect_idle(&paths))
+            .await
+            .unwrap_or(false);
+        if !idle {
+            return None;
+        }
+        info!("Ollama: 采样帧几乎都是黑屏或静止画面，按空闲会话处理，不调用模型");
+        let mut summary = self.idle_summary(None);
+        summary.sampling_seed = seed;
+        Some(summary)
+    }
+
+    /// 模型输出 {"empty": true} 时返回空闲总结
+    pub(super) fn model_flagged_idle(&self, v: &Value) -> Option<SessionSummary> {
+        if v.get("empty").and_then(Value::as_bool) != Some(true) {
+            return None;
+        }
+        let note = v.get("summary").and_then(Value::as_str).map(str::to_string);
+        Some(self.idle_summary(note))
+    }
+
+    fn idle_summary(&self, note: Option<String>) -> SessionSummary {
+        let mut summary = SessionSummary::idle(note);
+        (summary.start_time, summary.end_time) = self.resolve_times(None);
+        summary.detect_language(self.output_language.as_deref());
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{ActivityCategory, LLMProvider};
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_black_frames_are_idle_without_calling_model() {
+        let mut provider = OllamaProvider::new(Client::new());
+        // 不可达的地址：若调用模型则以连接错误失败
+        provider
+            .configure(serde_json::json!({ "base_url": "http://127.0.0.1:9" }))
+            .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<String> = (0..5)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.jpg", 1_700_000_000_000i64 + i));
+                image::RgbImage::from_pixel(64, 48, image::Rgb([2, 2, 2]))
+                    .save(&path)
+                    .unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+
+        let summary = provider.analyze_frames(frames.clone()).await.unwrap();
+        assert!(summary.idle);
+        assert_eq!(summary.title, crate::llm::idle::IDLE_TITLE);
+
+        // 关闭本地判定后照常调用模型
+        provider
+            .configure(serde_json::json!({
+                "base_url": "http://127.0.0.1:9",
+                "detect_idle_frames": false
+            }))
+            .unwrap();
+        assert!(provider.analyze_frames(frames).await.is_err());
+    }
+
+    #[test]
+    fn test_parse_model_flagged_empty_session() {
+        let provider = OllamaProvider::new(Client::new());
+        let summary = provider
+            .parse_session_summary(r#"{"empty": true, "summary": "屏幕一直处于锁屏状态"}"#)
+            .unwrap();
+        assert!(summary.idle);
+        assert_eq!(summary.summary, "屏幕一直处于锁屏状态");
+        assert_eq!(summary.tags[0].category, ActivityCategory::Idle);
+
+        // 只给出高置信度空闲标签也视为空闲；混有其他类别时不是
+        let raw = r#"{"title":"离开","summary":"无人操作","tags":[{"category":"idle","confidence":0.95,"keywords":[]}]}"#;
+        assert!(provider.parse_session_summary(raw).unwrap().idle);
+        let raw = r#"{"title":"a","summary":"b","empty":false,"tags":[{"category":"idle","confidence":0.95,"keywords":[]},{"category":"work","confidence":0.3,"keywords":[]}]}"#;
+        assert!(!provider.parse_session_summary(raw).unwrap().idle);
+    }
+}
@@ -0,0 +1,224 @@
+// Ollama 非流式请求保活 - 请求期间轮询 /api/ps
+//
+// 模型仍在运行时推送心跳；曾经出现过的模型从列表中消失（服务重启、被卸载）时立即失败，
+// 不再等待完整的请求超时
+
+use super::sampling::SplitMix64;
+use super::OllamaProvider;
+use crate::llm::plugin::{AnalysisProgress, LLMProvider};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use tracing::debug;
+
+/// /api/ps 轮询间隔的上下限（秒）
+const KEEP_ALIVE_POLL_MIN_SECS: u64 = 1;
+const KEEP_ALIVE_POLL_MAX_SECS: u64 = 300;
+
+/// /api/ps 响应：当前已加载到内存中的模型（/api/tags 的本地模型列表结构相同）
+#[derive(Deserialize)]
+pub(super) struct PsResponse {
+    #[serde(default)]
+    models: Vec<PsModel>,
+}
+
+#[derive(Deserialize)]
+struct PsModel {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    model: String,
+}
+
+impl PsResponse {
+    /// 是否包含指定模型（未写标签的模型名按 :latest 比较）
+    pub(super) fn lists_model(&self, model: &str) -> bool {
+        let normalize = |name: &str| {
+            if name.contains(':') {
+                name.to_string()
+            } else {
+                format!("{name}:latest")
+            }
+        };
+        let target = normalize(model);
+        self.models
+            .iter()
+            .any(|m| normalize(&m.name) == target || normalize(&m.model) == target)
+    }
+}
+
+impl OllamaProvider {
+    /// 非流式请求期间定期查询 /api/ps：模型在运行时推送心跳，
+    /// 曾经出现过的模型从列表消失时立即失败，不再等待完整超时
+    pub(super) async fn with_keep_alive_poll<T>(
+        &self,
+        request: impl std::future::Future<Output = Result<T>>,
+        poll_secs: u64,
+        model: &str,
+    ) -> Result<T> {
+        tokio::pin!(request);
+        let started = std::time::Instant::now();
+        let mut rng = SplitMix64(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default(),
+        );
+        let mut seen_running = false;
+
+        loop {
+            let delay = Self::jittered_poll_interval(poll_secs, rng.next_u64());
+            tokio::select! {
+                result = &mut request => return result,
+                _ = tokio::time::sleep(delay) => {}
+            }
+
+            match self.fetch_running_models().await {
+                Ok(ps) if ps.lists_model(model) => {
+                    seen_running = true;
+                    self.emit_progress(AnalysisProgress::Heartbeat {
+                        provider: self.name().to_string(),
+                        model: model.to_string(),
+                        elapsed_secs: started.elapsed().as_secs_f32(),
+                    });
+                }
+                Ok(_) if seen_running => {
+                    return Err(anyhow!(
+                        "Ollama 模型 {} 已从 /api/ps 中消失，请求可能已中断（已等待 {:.0}s）",
+                        model,
+                        started.elapsed().as_secs_f32()
+                    ));
+                }
+                // 模型可能仍在加载，继续等待
+                Ok(_) => debug!("Ollama: 模型 {} 尚未出现在 /api/ps 中", model),
+                // 查询失败不影响主请求
+                Err(e) => debug!("Ollama: 查询 /api/ps 失败: {}", e),
+            }
+        }
+    }
+
+    /// 查询 /api/ps 获取当前运行中的模型
+    async fn fetch_running_models(&self) -> Result<PsResponse> {
+        let url = format!("{}/api/ps", self.base_url.trim_end_matches('/'));
+        let ps = self
+            .client()
+            .get(url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(ps)
+    }
+
+    /// 轮询间隔：限制在上下限内，并加入 ±20% 抖动避免多个请求同时轮询
+    fn jittered_poll_interval(poll_secs: u64, random: u64) -> std::time::Duration {
+        let base = poll_secs.clamp(KEEP_ALIVE_POLL_MIN_SECS, KEEP_ALIVE_POLL_MAX_SECS) as f64;
+        let factor = 0.8 + 0.4 * (random as f64 / u64::MAX as f64);
+        std::time::Duration::from_secs_f64(base * factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PsResponse;
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{AnalysisProgress, LLMProvider};
+    use crate::test_server::{serve, Response};
+    use reqwest::Client;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_jittered_poll_interval_is_capped() {
+        for (secs, min, max) in [(0, 0.8, 1.2), (10, 8.0, 12.0), (10_000, 240.0, 360.0)] {
+            for random in [0, u64::MAX / 2, u64::MAX] {
+                let delay = OllamaProvider::jittered_poll_interval(secs, random).as_secs_f64();
+                assert!((min..=max).contains(&delay), "{secs}s -> {delay}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_ps_lists_model() {
+        let ps: PsResponse = serde_json::from_str(
+            r#"{"models":[{"name":"llava:latest","model":"llava:latest"},{"name":"qwen3-vl:32b","model":"qwen3-vl:32b"}]}"#,
+        )
+        .unwrap();
+        assert!(ps.lists_model("qwen3-vl:32b"));
+        assert!(ps.lists_model("llava"));
+        assert!(!ps.lists_model("qwen3-vl:8b"));
+        assert!(!PsResponse { models: vec![] }.lists_model("llava"));
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_poll_returns_request_result() {
+        let provider = OllamaProvider::new(Client::new());
+        let result = provider
+            .with_keep_alive_poll(async { Ok("done") }, 1, "qwen3-vl:32b")
+            .await
+            .unwrap();
+        assert_eq!(result, "done");
+    }
+
+    /// 模拟 /api/ps：前 running_polls 次查询列出模型，之后返回空列表
+    async fn provider_with_ps(running_polls: usize) -> OllamaProvider {
+        let polls = Arc::new(AtomicUsize::new(0));
+        let url = serve(move |_| {
+            let models = if polls.fetch_add(1, Ordering::SeqCst) < running_polls {
+                serde_json::json!([{ "name": "llava:latest", "model": "llava:latest" }])
+            } else {
+                serde_json::json!([])
+            };
+            Response::ok(serde_json::json!({ "models": models }).to_string())
+        })
+        .await;
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url, "model": "llava" }))
+            .unwrap();
+        provider
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_poll_fails_fast_when_model_disappears() {
+        let provider = provider_with_ps(1).await;
+
+        // 主请求永远不返回：模型从 /api/ps 中消失后应立即失败
+        let err = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            provider.with_keep_alive_poll(std::future::pending::<anyhow::Result<()>>(), 1, "llava"),
+        )
+        .await
+        .expect("模型消失后应提前失败")
+        .unwrap_err();
+        assert!(err.to_string().contains("已从 /api/ps 中消失"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_poll_emits_heartbeat_while_running() {
+        let mut provider = provider_with_ps(usize::MAX).await;
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        provider.set_progress_sender(Some(tx));
+
+        // 首次轮询最迟在 1.2s 后发生，主请求 1.5s 后返回
+        let request = async {
+            tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+            Ok("done")
+        };
+        let result = provider
+            .with_keep_alive_poll(request, 1, "llava")
+            .await
+            .unwrap();
+        assert_eq!(result, "done");
+
+        match rx.try_recv().expect("应至少推送一次心跳") {
+            AnalysisProgress::Heartbeat {
+                provider, model, ..
+            } => {
+                assert_eq!(provider, "ollama");
+                assert_eq!(model, "llava");
+            }
+            other => panic!("unexpected progress: {other:?}"),
+        }
+    }
+}
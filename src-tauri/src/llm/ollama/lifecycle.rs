@@ -0,0 +1,41 @@
+// Ollama 模型加载与卸载 - 录制开始前预热模型，结束后释放显存
+//
+// 两者都通过 /api/generate 完成：空 prompt 触发加载，keep_alive=0 触发卸载
+
+use super::OllamaProvider;
+use anyhow::Result;
+use serde_json::Value;
+use tracing::info;
+
+impl OllamaProvider {
+    /// 空 prompt 的 generate 请求会让 Ollama 提前把模型加载进显存
+    pub(super) async fn warm_up(&self) -> Result<()> {
+        self.post_generate(serde_json::json!({ "model": self.model, "prompt": "" }))
+            .await?;
+        info!("Ollama: 模型 {} 已预热", self.model);
+        Ok(())
+    }
+
+    /// keep_alive=0 让 Ollama 立即卸载模型
+    pub(super) async fn unload(&self) -> Result<()> {
+        self.post_generate(serde_json::json!({ "model": self.model, "keep_alive": 0 }))
+            .await?;
+        info!("Ollama: 模型 {} 已卸载", self.model);
+        Ok(())
+    }
+
+    /// 调用 /api/generate（仅用于模型加载/卸载，不关心返回内容）
+    async fn post_generate(&self, mut body: Value) -> Result<()> {
+        if let Some(obj) = body.as_object_mut() {
+            obj.insert("stream".to_string(), Value::Bool(false));
+        }
+        let url = format!("{}/api/generate", self.base_url.trim_end_matches('/'));
+        self.client()
+            .post(url)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+// Ollama 模型选择 - 按会话帧数选用不同规模的模型
+//
+// 帧数少的会话用小模型即可，帧数多时再使用默认的大模型
+
+use super::OllamaProvider;
+use crate::llm::ModelRule;
+
+/// 按帧数选出的模型及选择原因（写入分析 span 与日志）
+#[derive(Debug, PartialEq)]
+pub(super) struct ModelSelection<'a> {
+    pub(super) model: &'a str,
+    pub(super) reason: String,
+}
+
+impl OllamaProvider {
+    /// 忽略模型名为空的规则，并按 max_frames 升序排列
+    pub(super) fn sorted_model_rules(rules: Vec<ModelRule>) -> Vec<ModelRule> {
+        let mut rules: Vec<ModelRule> = rules
+            .into_iter()
+            .filter(|rule| !rule.model.trim().is_empty())
+            .collect();
+        rules.sort_by_key(|rule| rule.max_frames);
+        rules
+    }
+
+    /// 按帧数选择模型：取帧数不超过 max_frames 的最小阈值，都不满足时使用默认模型
+    pub(super) fn select_model(&self, frame_count: usize) -> ModelSelection<'_> {
+        match self
+            .model_rules
+            .iter()
+            .find(|rule| frame_count <= rule.max_frames)
+        {
+            Some(rule) => ModelSelection {
+                model: &rule.model,
+                reason: format!("{} 帧 ≤ {}", frame_count, rule.max_frames),
+            },
+            None if self.model_rules.is_empty() => ModelSelection {
+                model: &self.model,
+                reason: "未配置模型选择规则".to_string(),
+            },
+            None => ModelSelection {
+                model: &self.model,
+                reason: format!("{} 帧超过所有规则阈值", frame_count),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::LLMProvider;
+    use reqwest::Client;
+
+    #[test]
+    fn test_model_selection_boundaries() {
+        let mut provider = OllamaProvider::new(Client::new());
+        assert_eq!(provider.select_model(3).model, "qwen3-vl:32b");
+
+        provider
+            .configure(serde_json::json!({
+                "model": "qwen3-vl:32b",
+                "model_rules": [
+                    { "max_frames": 20, "model": "qwen3-vl:8b" },
+                    { "max_frames": 5, "model": "qwen3-vl:2b" },
+                    { "max_frames": 50, "model": " " }
+                ]
+            }))
+            .unwrap();
+
+        // 规则按阈值升序匹配，阈值本身包含在内；模型名为空的规则被忽略
+        let selected: Vec<&str> = [1, 5, 6, 20, 21, 40]
+            .into_iter()
+            .map(|n| provider.select_model(n).model)
+            .collect();
+        assert_eq!(
+            selected,
+            vec![
+                "qwen3-vl:2b",
+                "qwen3-vl:2b",
+                "qwen3-vl:8b",
+                "qwen3-vl:8b",
+                "qwen3-vl:32b",
+                "qwen3-vl:32b"
+            ]
+        );
+        assert_eq!(provider.select_model(5).reason, "5 帧 ≤ 5");
+    }
+}
@@ -0,0 +1,111 @@
+// Ollama 拼图模式 - 把采样帧拼成网格图片发送，同样的图片数覆盖更多帧
+//
+// 拼图与帧不是一一对应，提示词中的帧说明换成网格说明，也不按帧裁剪请求体
+
+use super::{prompt, OllamaProvider};
+use crate::llm::mosaic::{self, MosaicImages};
+use crate::llm::FrameOrder;
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use tracing::debug;
+
+impl OllamaProvider {
+    /// 每张图片容纳的帧数（未开启拼图时为 1）
+    pub(super) fn frames_per_image(&self) -> usize {
+        self.mosaic.as_ref().map_or(1, |mosaic| mosaic.tiles())
+    }
+
+    /// 提示词开头的图片说明：拼图模式下说明网格排列，否则按配置附加帧顺序说明
+    pub(super) fn layout_note(&self, frames: &[String]) -> Option<String> {
+        match &self.mosaic {
+            Some(config) if !frames.is_empty() => Some(mosaic::build_mosaic_note(
+                frames.len(),
+                config,
+                self.frame_order == FrameOrder::Reverse,
+            )),
+            _ if self.frame_order_hint => {
+                prompt::order_note(frames, self.frame_index_captions, self.frame_order)
+            }
+            _ => None,
+        }
+    }
+
+    /// 拼图模式下把帧拼成网格并编码，返回 (实际拼入的帧, 拼图的 base64)；未开启时为 None
+    pub(super) async fn encode_mosaic(
+        &self,
+        frames: &[String],
+    ) -> Result<Option<(Vec<String>, Vec<String>)>> {
+        let Some(config) = &self.mosaic else {
+            return Ok(None);
+        };
+        // 拼图文件在编码完成后随临时目录删除
+        let mosaic = MosaicImages::create(frames, config).await?;
+        let mut images = Vec::with_capacity(mosaic.paths.len());
+        for path in &mosaic.paths {
+            let bytes = tokio::fs::read(path).await?;
+            images.push(general_purpose::STANDARD.encode(bytes));
+        }
+        debug!(
+            "Ollama: {} 帧拼成 {} 张图片",
+            mosaic.frames.len(),
+            images.len()
+        );
+        Ok(Some((mosaic.frames, images)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{AnalysisContext, LLMProvider};
+    use reqwest::Client;
+
+    #[test]
+    fn test_mosaic_mode_adjusts_prompt_and_capabilities() {
+        let mut provider = OllamaProvider::new(Client::new());
+        let single = provider.capabilities().max_images;
+        provider
+            .configure(serde_json::json!({ "mosaic": { "columns": 2, "rows": 2 } }))
+            .unwrap();
+        assert_eq!(provider.capabilities().max_images, single * 4);
+
+        let frames: Vec<String> = (0..5i64)
+            .map(|i| format!("/frames/{}.jpg", 1_700_000_000_000i64 + i * 1000))
+            .collect();
+        let prompt = provider.build_prompt(&AnalysisContext::default(), &frames);
+        assert!(prompt.contains("以下 2 张图片是由 5 张截图"));
+        assert!(!prompt.contains("以下 5 张图片"));
+        assert!(provider
+            .configure(serde_json::json!({ "mosaic": { "columns": 9 } }))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_encode_mosaic_tiles_frames() {
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<String> = (0..5)
+            .map(|i| {
+                let path = dir
+                    .path()
+                    .join(format!("{}.jpg", 1_700_000_000_000i64 + i * 1000));
+                image::RgbImage::from_fn(64, 48, |x, y| {
+                    image::Rgb([(x * 4) as u8, (y * 5) as u8, (i * 40) as u8])
+                })
+                .save(&path)
+                .unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+
+        let mut provider = OllamaProvider::new(Client::new());
+        assert!(provider.encode_mosaic(&frames).await.unwrap().is_none());
+        provider
+            .configure(serde_json::json!({
+                "mosaic": { "columns": 2, "rows": 2, "tile_width": 64 }
+            }))
+            .unwrap();
+        let (encoded, images) = provider.encode_mosaic(&frames).await.unwrap().unwrap();
+        assert_eq!(encoded, frames);
+        assert_eq!(images.len(), 2);
+    }
+}
@@ -0,0 +1,336 @@
+// Ollama 输出解析辅助 - 在转换为 SessionSummary 之前校验和补齐模型返回的 JSON
+//
+// 小模型经常漏掉可选段落，这里只强制 title / summary，其余字段缺失时补默认值
+
+use super::OllamaProvider;
+use crate::llm::plugin::SessionSummary;
+use crate::llm::JsonSelection;
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use tracing::{debug, warn};
+
+/// 模型未给出评分时使用的中性分值
+pub(super) const NEUTRAL_SCORE: f32 = 50.0;
+
+/// 校验 title / summary 这两个必需字段是否为字符串
+pub(super) fn check_required_fields(v: &Value, raw: &str) -> Result<()> {
+    for required in ["title", "summary"] {
+        if !v.get(required).map(|f| f.is_string()).unwrap_or(false) {
+            return Err(anyhow!("Ollama 返回缺少必需字段 {required}; raw={}", raw));
+        }
+    }
+    Ok(())
+}
+
+/// 去掉首尾空白、BOM、零宽字符与控制字符（代理或模型偶尔在 JSON 前后附加）
+pub(super) fn trim_noise(s: &str) -> &str {
+    s.trim_matches(|c: char| {
+        c.is_whitespace() || c.is_control() || matches!(c, '\u{feff}' | '\u{200b}' | '\u{fffd}')
+    })
+}
+
+/// 按有损 UTF-8 解码响应体并去掉首尾噪声后解析 JSON
+pub(super) fn decode_lossy<T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    let body = String::from_utf8_lossy(bytes);
+    serde_json::from_str(trim_noise(&body)).map_err(|e| anyhow!("Ollama 响应解析失败: {e}"))
+}
+
+impl OllamaProvider {
+    /// 按配置的方式校验模型输出的 JSON Schema；coerce 模式下修正的字段记录告警
+    pub(super) fn check_output_schema(&self, v: &mut Value, raw: &str) -> Result<()> {
+        let coerced = crate::llm::output_schema::check(v, self.schema_validation)
+            .map_err(|e| anyhow!("{}; raw={}", e, raw))?;
+        if !coerced.is_empty() {
+            warn!(
+                "Ollama: 模型输出的字段类型有误，已转换: {}（模型 {}）",
+                coerced.join(", "),
+                self.model
+            );
+        }
+        Ok(())
+    }
+
+    /// 严格解析失败时修复常见错误后再试，修复结果须带有必需字段，否则报告原始解析错误
+    pub(super) fn parse_json_or_repair(&self, json_text: &str, raw: &str) -> Result<Value> {
+        let strict_err = match serde_json::from_str::<Value>(json_text) {
+            Ok(v) => return Ok(v),
+            Err(e) => e,
+        };
+
+        let repaired = crate::llm::json_repair::repair(json_text)
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+            .filter(|v| check_required_fields(v, raw).is_ok());
+        match repaired {
+            Some(v) => {
+                warn!(
+                    "Ollama: 模型输出不是合法 JSON（{}），已自动修复（模型 {}）",
+                    strict_err, self.model
+                );
+                Ok(v)
+            }
+            None => Err(anyhow!(
+                "Ollama 返回不是合法 JSON: {strict_err}; raw={}",
+                raw
+            )),
+        }
+    }
+
+    /// 输出含多个顶层 JSON 对象时，按配置的方向取第一个能解析为总结的对象；只有一个对象或都无法解析时返回 None
+    pub(super) fn select_json_object(&self, raw: &str) -> Option<SessionSummary> {
+        let objects = crate::llm::json_repair::top_level_objects(raw);
+        if objects.len() < 2 {
+            return None;
+        }
+        let mut candidates: Vec<(usize, &str)> = objects.iter().copied().enumerate().collect();
+        if self.json_selection == JsonSelection::Last {
+            candidates.reverse();
+        }
+        let (index, summary) = candidates.into_iter().find_map(|(index, text)| {
+            let value = serde_json::from_str::<Value>(text).ok()?;
+            Some((index, self.summary_from_value(value, raw).ok()?))
+        })?;
+        debug!(
+            "Ollama: 输出包含 {} 个 JSON 对象，使用第 {} 个",
+            objects.len(),
+            index + 1
+        );
+        Some(summary)
+    }
+}
+
+/// 为缺失（或为 null）的可选段落补默认值，返回被补齐的字段名
+pub(super) fn fill_missing_optional_fields(obj: &mut Map<String, Value>) -> Vec<&'static str> {
+    let defaults = [
+        ("tags", Value::Array(vec![])),
+        ("key_moments", Value::Array(vec![])),
+        ("productivity_score", serde_json::json!(NEUTRAL_SCORE)),
+        ("focus_score", serde_json::json!(NEUTRAL_SCORE)),
+    ];
+    let mut defaulted = Vec::new();
+    for (key, default) in defaults {
+        if obj.get(key).map(|f| f.is_null()).unwrap_or(true) {
+            obj.insert(key.to_string(), default);
+            defaulted.push(key);
+        }
+    }
+    defaulted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::LLMProvider;
+    use crate::test_server::serve_routes;
+    use reqwest::Client;
+
+    #[test]
+    fn test_parse_bare_title_and_summary() {
+        let provider = OllamaProvider::new(Client::new());
+        let raw = r#"{"title":"编写代码","summary":"在 IDE 中实现新功能"}"#;
+        let summary = provider.parse_session_summary(raw).unwrap();
+
+        assert_eq!(summary.title, "编写代码");
+        assert_eq!(summary.summary, "在 IDE 中实现新功能");
+        assert!(summary.tags.is_empty());
+        assert!(summary.key_moments.is_empty());
+        assert_eq!(summary.productivity_score, Some(NEUTRAL_SCORE));
+        assert_eq!(summary.focus_score, Some(NEUTRAL_SCORE));
+    }
+
+    #[test]
+    fn test_parse_repairs_malformed_json() {
+        let provider = OllamaProvider::new(Client::new());
+        let raw = r#"{title: '写代码', summary: "实现 JSON 修复", tags: [],}"#;
+        let summary = provider.parse_session_summary(raw).unwrap();
+        assert_eq!(summary.title, "写代码");
+        assert_eq!(summary.summary, "实现 JSON 修复");
+
+        // 修复后仍缺少必需字段时报告原始解析错误
+        let err = provider
+            .parse_session_summary(r#"{title: '只有标题',}"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("不是合法 JSON"));
+    }
+
+    #[test]
+    fn test_parse_detects_language() {
+        let mut provider = OllamaProvider::new(Client::new());
+        let raw = r#"{"title":"编写代码","summary":"在 IDE 中实现新功能"}"#;
+        let summary = provider.parse_session_summary(raw).unwrap();
+        assert_eq!(summary.language.as_deref(), Some("zh"));
+
+        // 文本过短无法判断时使用配置的输出语言
+        provider.output_language = Some("en".to_string());
+        let raw = r#"{"title":"OK","summary":""}"#;
+        let summary = provider.parse_session_summary(raw).unwrap();
+        assert_eq!(summary.language.as_deref(), Some("en"));
+    }
+
+    #[test]
+    fn test_reasoning_is_captured_without_affecting_parsing() {
+        let raw = concat!(
+            "<think>\n截图里一直是 VS Code，偶尔切到终端。草稿：{\"title\": \"草稿\"}\n</think>\n",
+            r#"{"title":"编写代码","summary":"在 IDE 中实现新功能"}"#
+        );
+
+        let mut provider = OllamaProvider::new(Client::new());
+        let summary = provider.parse_session_summary(raw).unwrap();
+        assert_eq!(summary.title, "编写代码");
+        assert!(summary.reasoning.is_none());
+
+        provider
+            .configure(serde_json::json!({ "capture_reasoning": true }))
+            .unwrap();
+        let summary = provider.parse_session_summary(raw).unwrap();
+        assert_eq!(summary.title, "编写代码");
+        assert_eq!(summary.summary, "在 IDE 中实现新功能");
+        let reasoning = summary.reasoning.as_deref().unwrap();
+        assert!(reasoning.starts_with("截图里一直是 VS Code"));
+        assert!(!reasoning.contains("<think>"));
+        // 不作为自定义字段或模型输出字段
+        assert!(summary.extra.is_empty());
+    }
+
+    #[test]
+    fn test_empty_summary_is_derived_from_tags() {
+        let raw = r#"{
+            "title": "开发与沟通",
+            "summary": "  ",
+            "tags": [
+                {"category": "communication", "confidence": 0.6, "keywords": ["Slack"]},
+                {"category": "work", "confidence": 0.9, "keywords": ["VS Code", "Rust"]}
+            ],
+            "key_moments": [
+                {"time": "01:00", "description": "回复消息", "importance": 2},
+                {"time": "05:00", "description": "提交代码", "importance": 4}
+            ]
+        }"#;
+
+        let mut provider = OllamaProvider::new(Client::new());
+        let summary = provider.parse_session_summary(raw).unwrap();
+        assert!(summary.summary_auto_derived);
+        assert_eq!(
+            summary.summary,
+            "工作、沟通会话，涉及 VS Code、Rust、Slack。关键时刻：提交代码；回复消息"
+        );
+
+        // 严格模式下空摘要视为解析失败
+        provider.derive_empty_summary = false;
+        assert!(provider.parse_session_summary(raw).is_err());
+        let raw = r#"{"title":"编写代码","summary":"实现新功能"}"#;
+        let summary = provider.parse_session_summary(raw).unwrap();
+        assert!(!summary.summary_auto_derived);
+    }
+
+    #[test]
+    fn test_parse_requires_title_and_summary() {
+        let provider = OllamaProvider::new(Client::new());
+        assert!(provider
+            .parse_session_summary(r#"{"title":"只有标题"}"#)
+            .is_err());
+        assert!(provider
+            .parse_session_summary(r#"{"summary":"只有摘要"}"#)
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_keeps_provided_fields() {
+        let provider = OllamaProvider::new(Client::new());
+        let raw = r#"{"title":"开会","summary":"讨论需求","tags":null,"focus_score":80}"#;
+        let summary = provider.parse_session_summary(raw).unwrap();
+
+        // null 视为缺失，已给出的评分保持不变
+        assert!(summary.tags.is_empty());
+        assert_eq!(summary.focus_score, Some(80.0));
+        assert_eq!(summary.productivity_score, Some(NEUTRAL_SCORE));
+    }
+
+    #[test]
+    fn test_multiple_json_objects_select_valid_summary() {
+        let mut provider = OllamaProvider::new(Client::new());
+        // 推理过程对象在前且不是合法总结，随后是草稿与最终答案
+        let raw = r#"{"thought": "先看编辑器 {窗口}，再看终端"}
+{"title": "草稿", "summary": "初步判断"}
+```json
+{"title": "编写 Rust 代码", "summary": "在编辑器中修改解析逻辑"}
+```"#;
+        let summary = provider.parse_session_summary(raw).unwrap();
+        assert_eq!(summary.title, "编写 Rust 代码");
+
+        provider
+            .configure(serde_json::json!({ "json_selection": "first" }))
+            .unwrap();
+        assert_eq!(provider.parse_session_summary(raw).unwrap().title, "草稿");
+
+        // 只有推理对象时仍按原逻辑报错
+        assert!(provider
+            .parse_session_summary(r#"{"thought": "a"} {"plan": "b"}"#)
+            .is_err());
+    }
+
+    #[test]
+    fn test_schema_validation_coerces_or_reports_string_scores() {
+        let mut provider = OllamaProvider::new(Client::new());
+        let raw = r#"{"title": "写代码", "summary": "实现解析器", "productivity_score": "80", "focus_score": "很高"}"#;
+        // 未开启时仍是反序列化错误
+        assert!(provider.parse_session_summary(raw).is_err());
+
+        provider
+            .configure(serde_json::json!({ "schema_validation": "strict" }))
+            .unwrap();
+        let err = provider.parse_session_summary(raw).unwrap_err().to_string();
+        assert!(err.contains("（2 处）"), "{}", err);
+        assert!(err.contains("\n- /productivity_score: "), "{}", err);
+        assert!(err.contains("\n- /focus_score: "), "{}", err);
+
+        provider
+            .configure(serde_json::json!({ "schema_validation": "coerce" }))
+            .unwrap();
+        let err = provider.parse_session_summary(raw).unwrap_err().to_string();
+        assert!(err.contains("（1 处）"), "{}", err);
+        let summary = provider
+            .parse_session_summary(&raw.replace("很高", "65.5"))
+            .unwrap();
+        assert_eq!(summary.productivity_score, Some(80.0));
+        assert_eq!(summary.focus_score, Some(65.5));
+    }
+
+    #[test]
+    fn test_parse_ignores_bom_and_control_characters() {
+        let provider = OllamaProvider::new(Client::new());
+        let json = r#"{"title":"写代码","summary":"调试解析","tags":[]}"#;
+
+        let bom = format!("\u{feff}{json}");
+        assert_eq!(
+            provider.parse_session_summary(&bom).unwrap().title,
+            "写代码"
+        );
+
+        let wrapped = format!("\u{0}\u{1b}\u{200b} {json}\r\n\u{0}\u{7f}");
+        assert_eq!(provider.extract_json_text(&wrapped), json);
+        assert_eq!(
+            provider.parse_session_summary(&wrapped).unwrap().summary,
+            "调试解析"
+        );
+
+        let fenced = format!("\u{feff}```json\n\u{feff}{json}\n```\u{0}");
+        assert_eq!(provider.extract_json_text(&fenced), json);
+    }
+
+    #[tokio::test]
+    async fn test_chat_response_with_bom_is_decoded() {
+        let body = "\u{feff}{\"message\":{\"content\":\"ok\"}}\n".to_string();
+        let url = serve_routes(vec![("/api/chat", body)]).await;
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url }))
+            .unwrap();
+        let content = provider
+            .call_ollama_chat("llava", "hi".to_string(), Vec::new(), None)
+            .await
+            .unwrap();
+        assert_eq!(content, "ok");
+    }
+}
@@ -0,0 +1,232 @@
+// Ollama 请求体大小控制 - 超过 max_payload_bytes 时丢弃相似帧或降低画质
+//
+// 计算哈希与重新编码都需要解码图片，属于 CPU 密集操作，调用方应放到阻塞线程中执行
+
+use super::body::ChatImage;
+use super::OllamaProvider;
+use crate::video::frame_hash::{self, FrameHashAlgorithm};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use tracing::{info, warn};
+
+/// 请求体超限时至少保留的帧数
+const MIN_PAYLOAD_FRAMES: usize = 3;
+
+/// 请求体超限时重新编码使用的 JPEG 质量
+const REENCODE_JPEG_QUALITY: u8 = 60;
+
+impl OllamaProvider {
+    /// 请求体超过 max_payload_bytes 时裁剪：先丢弃与相邻帧最相似的帧，再降低剩余帧的画质
+    ///
+    /// reserved 为提示词等非图片部分占用的字节数；计算哈希与重新编码需解码图片，须在阻塞线程中调用
+    pub(super) fn fit_payload(
+        frames: Vec<String>,
+        images: Vec<ChatImage>,
+        reserved: usize,
+        max_payload_bytes: Option<usize>,
+    ) -> Result<(Vec<String>, Vec<ChatImage>)> {
+        let Some(max_bytes) = max_payload_bytes else {
+            return Ok((frames, images));
+        };
+        let original_size = reserved + images.iter().map(ChatImage::encoded_len).sum::<usize>();
+        if original_size <= max_bytes {
+            return Ok((frames, images));
+        }
+
+        let size_of = |images: &[ChatImage], kept: &[usize]| {
+            reserved + kept.iter().map(|&i| images[i].encoded_len()).sum::<usize>()
+        };
+        let mut images = images;
+        let mut kept: Vec<usize> = (0..frames.len()).collect();
+
+        // 1. 按差异哈希丢弃最不具区分度的帧
+        let hashes: Vec<Option<u64>> = frames
+            .iter()
+            .map(|f| frame_hash::hash_frame(f, FrameHashAlgorithm::DHash).ok())
+            .collect();
+        let mut dropped = Vec::new();
+        while kept.len() > MIN_PAYLOAD_FRAMES && size_of(&images, &kept) > max_bytes {
+            let pos = Self::least_distinct(&kept, &hashes);
+            dropped.push(frames[kept.remove(pos)].clone());
+        }
+        if !dropped.is_empty() {
+            warn!(
+                "Ollama: 请求体 {} 字节超过上限 {}，丢弃 {} 个相似帧: {:?}",
+                original_size,
+                max_bytes,
+                dropped.len(),
+                dropped
+            );
+        }
+
+        // 2. 仍然超限则缩小并降低画质重新编码（仅在结果更小时替换）
+        if size_of(&images, &kept) > max_bytes {
+            let mut reencoded = 0;
+            for &i in &kept {
+                if let Some(smaller) =
+                    Self::reencode_base64(&frames[i]).filter(|s| s.len() < images[i].encoded_len())
+                {
+                    images[i] = ChatImage::Ready(smaller);
+                    reencoded += 1;
+                }
+            }
+            warn!(
+                "Ollama: 请求体仍超过上限，已降低 {} 帧的画质（JPEG 质量 {}）",
+                reencoded, REENCODE_JPEG_QUALITY
+            );
+        }
+
+        let final_size = size_of(&images, &kept);
+        if final_size > max_bytes {
+            return Err(anyhow!(
+                "Ollama 请求体过大（payload too large）：保留 {} 帧并降低画质后仍有 {} 字节，超过上限 {} 字节",
+                kept.len(),
+                final_size,
+                max_bytes
+            ));
+        }
+
+        info!(
+            "Ollama: 请求体已从 {} 字节裁剪到 {} 字节（{} -> {} 帧）",
+            original_size,
+            final_size,
+            frames.len(),
+            kept.len()
+        );
+        let frames = kept.iter().map(|&i| frames[i].clone()).collect();
+        let images = kept.iter().map(|&i| images[i].clone()).collect();
+        Ok((frames, images))
+    }
+
+    /// 最终兜底：超限时给出明确错误，而不是让服务端返回难以理解的失败
+    pub(super) fn check_payload_size(&self, prompt: &str, images: &[ChatImage]) -> Result<()> {
+        let Some(max_bytes) = self.max_payload_bytes else {
+            return Ok(());
+        };
+        let payload = prompt.len() + images.iter().map(ChatImage::encoded_len).sum::<usize>();
+        if payload > max_bytes {
+            return Err(anyhow!(
+                "Ollama 请求体过大（payload too large）：{} 字节超过上限 {} 字节",
+                payload,
+                max_bytes
+            ));
+        }
+        Ok(())
+    }
+
+    /// 找出与相邻保留帧最相似的帧在 kept 中的位置（无法计算哈希的帧视为独特）
+    fn least_distinct(kept: &[usize], hashes: &[Option<u64>]) -> usize {
+        let distance = |a: usize, b: usize| match (hashes[a], hashes[b]) {
+            (Some(x), Some(y)) => frame_hash::distance(x, y),
+            _ => u32::MAX,
+        };
+        (0..kept.len())
+            .min_by_key(|&pos| {
+                let prev = pos.checked_sub(1).map(|p| distance(kept[p], kept[pos]));
+                let next = kept.get(pos + 1).map(|&n| distance(kept[pos], n));
+                prev.into_iter().chain(next).min().unwrap_or(u32::MAX)
+            })
+            .unwrap_or(0)
+    }
+
+    /// 将帧缩小一半并以较低 JPEG 质量重新编码为 base64
+    pub(super) fn reencode_base64(path: &str) -> Option<String> {
+        let bytes = crate::storage::frame_pack::read_frame_blocking(path).ok()?;
+        Self::reencode_bytes(&bytes)
+    }
+
+    /// 将图片缩小一半并以较低 JPEG 质量重新编码为 base64
+    pub(super) fn reencode_bytes(bytes: &[u8]) -> Option<String> {
+        use image::codecs::jpeg::JpegEncoder;
+
+        let img = image::load_from_memory(bytes).ok()?;
+        let resized = img
+            .resize(
+                (img.width() / 2).max(1),
+                (img.height() / 2).max(1),
+                image::imageops::FilterType::Triangle,
+            )
+            .to_rgb8();
+        let mut buf = Vec::new();
+        JpegEncoder::new_with_quality(&mut buf, REENCODE_JPEG_QUALITY)
+            .encode(
+                resized.as_raw(),
+                resized.width(),
+                resized.height(),
+                image::ColorType::Rgb8,
+            )
+            .ok()?;
+        Some(general_purpose::STANDARD.encode(buf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::body::ChatImage;
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::LLMProvider;
+    use crate::test_server::serve_routes;
+    use reqwest::Client;
+
+    #[test]
+    fn test_least_distinct_picks_near_duplicate() {
+        let hashes = [Some(0), Some(0xFF), Some(0xFF), Some(0xFFFF_0000)];
+        // 帧 1 与帧 2 完全相同，取先出现的一个
+        assert_eq!(OllamaProvider::least_distinct(&[0, 1, 2, 3], &hashes), 1);
+        // 无法计算哈希的帧不会被优先丢弃
+        let hashes = [None, Some(0), Some(1), None];
+        assert_eq!(OllamaProvider::least_distinct(&[0, 1, 2, 3], &hashes), 1);
+    }
+
+    #[test]
+    fn test_fit_payload() {
+        let frames: Vec<String> = (0..5).map(|i| format!("/nonexistent/{i}.jpg")).collect();
+        let images = vec![ChatImage::Ready("A".repeat(100)); 5];
+        let fit = |max_bytes: Option<usize>| {
+            OllamaProvider::fit_payload(frames.clone(), images.clone(), 0, max_bytes)
+        };
+
+        // 未设置上限或未超限时原样返回
+        assert_eq!(fit(None).unwrap().0.len(), 5);
+        assert_eq!(fit(Some(500)).unwrap().0.len(), 5);
+
+        // 超限时丢帧直到满足，且保持原有顺序
+        let (kept, kept_images) = fit(Some(350)).unwrap();
+        assert_eq!(kept.len(), 3);
+        assert_eq!(kept_images.len(), 3);
+        assert!(kept.windows(2).all(|w| w[0] < w[1]));
+
+        // 保留最少帧数且无法重新编码时报错
+        let err = fit(Some(250)).unwrap_err();
+        assert!(err.to_string().contains("payload too large"));
+    }
+
+    #[tokio::test]
+    async fn test_completed_analysis_counts_encoded_frames() {
+        let content = r#"{"title":"编写代码","summary":"在编辑器中修改代码"}"#;
+        let url = serve_routes(vec![(
+            "/api/chat",
+            serde_json::json!({ "message": { "content": content } }).to_string(),
+        )])
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<String> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.jpg", 1_700_000_000_000i64 + i));
+                image::RgbImage::from_pixel(64, 48, image::Rgb([200, 120, 40]))
+                    .save(&path)
+                    .unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url, "detect_idle_frames": false }))
+            .unwrap();
+        let before = crate::telemetry::global().frames_encoded();
+        provider.analyze_frames(frames).await.unwrap();
+        // 其他测试可能并发累加，只要求至少增加本次发送的帧数
+        assert!(crate::telemetry::global().frames_encoded() - before >= 3);
+    }
+}
@@ -0,0 +1,75 @@
+// Ollama 提示词配置 - 按模型家族选择系统提示词、JSON 输出格式与单次图片上限
+//
+// 内置配置见 llm::profiles，用户可在 OllamaConfig.prompt_profiles 中按模型名或家族覆盖
+
+use super::OllamaProvider;
+use crate::llm::profiles::{resolve_profile, PromptProfile};
+
+impl OllamaProvider {
+    /// 模型对应的提示词配置（内置家族配置叠加用户覆盖）
+    pub(super) fn prompt_profile(&self, model: &str) -> PromptProfile {
+        resolve_profile(model, &self.prompt_profiles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::body::{ChatBody, ChatImage, ChatRequest};
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::LLMProvider;
+    use reqwest::Client;
+
+    #[test]
+    fn test_prompt_profile_follows_model_and_overrides() {
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({
+                "model": "llava:13b",
+                "prompt_profiles": { "llava:13b": { "json_format": false } }
+            }))
+            .unwrap();
+
+        // 已知模型使用内置配置，完整模型名的覆盖只改指定字段
+        let profile = provider.prompt_profile("llava:13b");
+        assert!(!profile.json_format);
+        assert_eq!(profile.max_images, 4);
+        assert!(profile.system_prompt.is_some());
+        assert!(provider.prompt_profile("llava:7b").json_format);
+        assert_eq!(provider.prompt_profile("qwen3-vl:32b").max_images, 30);
+
+        assert!(provider
+            .configure(serde_json::json!({ "prompt_profiles": { "llava": { "max_image": 2 } } }))
+            .is_err());
+    }
+
+    #[test]
+    fn test_request_body_follows_profile() {
+        let provider = OllamaProvider::new(Client::new());
+        let profile = provider.prompt_profile("llava:7b");
+        let body = |request: &ChatRequest<'_>| -> serde_json::Value {
+            let bytes: Vec<u8> = ChatBody::new(
+                request,
+                vec![ChatImage::Ready("aW1hZ2U=".to_string())].into(),
+            )
+            .unwrap()
+            .flat_map(|chunk| chunk.unwrap())
+            .collect();
+            serde_json::from_slice(&bytes).unwrap()
+        };
+
+        // 系统提示词排在用户消息之前，且不携带图片
+        let mut request =
+            ChatRequest::user("llava:7b", "prompt").with_system(profile.system_prompt.as_deref());
+        request.format = profile.json_format.then_some("json");
+        let json = body(&request);
+        assert_eq!(json["messages"][0]["role"], "system");
+        assert!(json["messages"][0].get("images").is_none());
+        assert_eq!(json["messages"][1]["role"], "user");
+        assert_eq!(json["format"], "json");
+
+        // 没有系统提示词、不要求 JSON 时请求体保持原样
+        let json = body(&ChatRequest::user("m", "prompt").with_system(None));
+        assert_eq!(json["messages"].as_array().unwrap().len(), 1);
+        assert!(json.get("format").is_none());
+    }
+}
@@ -0,0 +1,295 @@
+// Ollama 模型拉取 - 分析前确认配置的模型已下载，缺失时流式调用 /api/pull
+//
+// 下载进度通过进度通道推送；缺失模型与拉取失败分别返回可识别的错误类型
+
+use super::keep_alive::PsResponse;
+use super::show::SHOW_TIMEOUT_SECS;
+use super::OllamaProvider;
+use crate::llm::plugin::{AnalysisProgress, LLMProvider};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use tracing::{debug, info};
+
+/// 本地没有配置的模型，且未要求自动拉取
+///
+/// 调用方可通过 `downcast_ref::<OllamaModelMissing>()` 识别并提示用户拉取模型
+#[derive(Clone, Debug, PartialEq)]
+pub struct OllamaModelMissing {
+    pub model: String,
+}
+
+impl std::fmt::Display for OllamaModelMissing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Ollama 本地没有模型 {}，可拉取后再分析（ollama pull {}）",
+            self.model, self.model
+        )
+    }
+}
+
+impl std::error::Error for OllamaModelMissing {}
+
+/// 拉取模型失败（服务端返回错误或下载中断）
+///
+/// 调用方可通过 `downcast_ref::<OllamaPullFailed>()` 识别，reason 为服务端原始错误
+#[derive(Clone, Debug, PartialEq)]
+pub struct OllamaPullFailed {
+    pub model: String,
+    pub reason: String,
+}
+
+impl OllamaPullFailed {
+    /// 按服务端错误给出处理建议
+    fn hint(&self) -> &'static str {
+        let reason = self.reason.to_lowercase();
+        if reason.contains("no space") || reason.contains("disk") {
+            "磁盘空间不足，请清理后重试"
+        } else if reason.contains("does not exist")
+            || reason.contains("not found")
+            || reason.contains("invalid model name")
+        {
+            "模型名称不存在，请检查模型名与标签"
+        } else {
+            "请检查网络与 Ollama 服务日志"
+        }
+    }
+}
+
+impl std::fmt::Display for OllamaPullFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "拉取模型 {} 失败：{}（{}）",
+            self.model,
+            self.hint(),
+            self.reason
+        )
+    }
+}
+
+impl std::error::Error for OllamaPullFailed {}
+
+/// /api/pull 流式响应中的单行
+#[derive(Deserialize)]
+struct PullChunk {
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    total: Option<u64>,
+    #[serde(default)]
+    completed: Option<u64>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl OllamaProvider {
+    /// 确认配置的模型已下载；缺失时按 pull_if_missing 拉取（通过进度通道推送下载进度）或返回 OllamaModelMissing
+    pub async fn ensure_model(&self, pull_if_missing: bool) -> Result<()> {
+        let model = self.model.clone();
+        if self.fetch_local_models().await?.lists_model(&model) {
+            debug!("Ollama: 模型 {} 已存在", model);
+            return Ok(());
+        }
+        if !pull_if_missing {
+            return Err(OllamaModelMissing { model }.into());
+        }
+
+        info!("Ollama: 本地没有模型 {}，开始拉取", model);
+        let started = std::time::Instant::now();
+        self.pull_model(&model).await?;
+        info!(
+            "Ollama: 模型 {} 拉取完成，用时 {:.0}s",
+            model,
+            started.elapsed().as_secs_f32()
+        );
+        Ok(())
+    }
+
+    /// 查询 /api/tags 获取本地已下载的模型
+    async fn fetch_local_models(&self) -> Result<PsResponse> {
+        let url = format!("{}/api/tags", self.base_url.trim_end_matches('/'));
+        let tags = self
+            .client()
+            .get(url)
+            .timeout(std::time::Duration::from_secs(SHOW_TIMEOUT_SECS))
+            .send()
+            .await
+            .map_err(|e| self.map_request_error(e))?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(tags)
+    }
+
+    /// 流式调用 /api/pull，逐行推送下载进度；不设读取超时（大模型下载耗时较长）
+    async fn pull_model(&self, model: &str) -> Result<()> {
+        let url = format!("{}/api/pull", self.base_url.trim_end_matches('/'));
+        let mut resp = self
+            .client()
+            .post(url)
+            .json(&serde_json::json!({ "model": model, "stream": true }))
+            .send()
+            .await
+            .map_err(|e| self.map_request_error(e))?;
+        let failed = |reason: String| OllamaPullFailed {
+            model: model.to_string(),
+            reason,
+        };
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            // 错误响应体通常为 {"error": "..."}
+            let reason = serde_json::from_str::<PullChunk>(&body)
+                .ok()
+                .and_then(|chunk| chunk.error)
+                .unwrap_or_else(|| format!("HTTP {}: {}", status, body.trim()));
+            return Err(failed(reason).into());
+        }
+
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut succeeded = false;
+        while let Some(bytes) = resp
+            .chunk()
+            .await
+            .map_err(|e| failed(format!("下载中断: {}", e)))?
+        {
+            buffer.extend_from_slice(&bytes);
+            while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=pos).collect();
+                succeeded |= self.handle_pull_line(model, &line)?;
+            }
+        }
+        if !buffer.is_empty() {
+            succeeded |= self.handle_pull_line(model, &buffer)?;
+        }
+        if !succeeded {
+            return Err(failed("服务端未确认下载完成".to_string()).into());
+        }
+        Ok(())
+    }
+
+    /// 处理 /api/pull 的一行输出，返回是否为完成标记
+    fn handle_pull_line(&self, model: &str, line: &[u8]) -> Result<bool> {
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(false);
+        }
+        let chunk: PullChunk = serde_json::from_str(line)
+            .map_err(|e| anyhow!("Ollama 拉取响应解析失败: {e}; line={}", line))?;
+        if let Some(reason) = chunk.error {
+            return Err(OllamaPullFailed {
+                model: model.to_string(),
+                reason,
+            }
+            .into());
+        }
+        let percent = chunk
+            .total
+            .zip(chunk.completed)
+            .filter(|(total, _)| *total > 0)
+            .map(|(total, completed)| (completed as f64 / total as f64 * 100.0) as f32);
+        self.emit_progress(AnalysisProgress::ModelPull {
+            provider: self.name().to_string(),
+            model: model.to_string(),
+            status: chunk.status.clone(),
+            completed: chunk.completed,
+            total: chunk.total,
+            percent,
+        });
+        Ok(chunk.status == "success")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OllamaModelMissing, OllamaPullFailed};
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{AnalysisProgress, LLMProvider};
+    use crate::test_server::serve_routes;
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_ensure_model_pulls_missing_model_with_progress() {
+        let tags = r#"{"models":[{"name":"qwen3-vl:8b","model":"qwen3-vl:8b"}]}"#.to_string();
+        let pull = [
+            r#"{"status":"pulling manifest"}"#,
+            r#"{"status":"pulling abc","total":200,"completed":50}"#,
+            r#"{"status":"pulling abc","total":200,"completed":200}"#,
+            r#"{"status":"success"}"#,
+        ]
+        .join("\n");
+        let url = serve_routes(vec![("/api/tags", tags.clone()), ("/api/pull", pull)]).await;
+
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url, "model": "llava:7b" }))
+            .unwrap();
+        let err = provider.ensure_model(false).await.unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<OllamaModelMissing>(),
+            Some(&OllamaModelMissing {
+                model: "llava:7b".to_string()
+            })
+        );
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        provider.set_progress_sender(Some(tx));
+        provider.ensure_model(true).await.unwrap();
+        drop(provider);
+        let mut percents = Vec::new();
+        let mut statuses = Vec::new();
+        while let Some(event) = rx.recv().await {
+            if let AnalysisProgress::ModelPull {
+                status, percent, ..
+            } = event
+            {
+                statuses.push(status);
+                percents.extend(percent);
+            }
+        }
+        assert_eq!(
+            statuses.first().map(String::as_str),
+            Some("pulling manifest")
+        );
+        assert_eq!(statuses.last().map(String::as_str), Some("success"));
+        assert_eq!(percents, vec![25.0, 100.0]);
+
+        // 已存在的模型不会触发拉取
+        let url = serve_routes(vec![("/api/tags", tags)]).await;
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url, "model": "qwen3-vl:8b" }))
+            .unwrap();
+        provider.ensure_model(false).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ensure_model_reports_pull_errors() {
+        let tags = r#"{"models":[]}"#.to_string();
+        let pull = [
+            r#"{"status":"pulling abc","total":200,"completed":50}"#,
+            r#"{"error":"write /models/blobs/abc: no space left on device"}"#,
+        ]
+        .join("\n");
+        let url = serve_routes(vec![("/api/tags", tags.clone()), ("/api/pull", pull)]).await;
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url, "model": "llava:7b" }))
+            .unwrap();
+        let err = provider.ensure_model(true).await.unwrap_err();
+        let failed = err.downcast_ref::<OllamaPullFailed>().unwrap();
+        assert_eq!(failed.model, "llava:7b");
+        assert!(err.to_string().contains("磁盘空间不足"));
+
+        let pull = r#"{"error":"pull model manifest: file does not exist"}"#.to_string();
+        let url = serve_routes(vec![("/api/tags", tags), ("/api/pull", pull)]).await;
+        provider
+            .configure(serde_json::json!({ "base_url": url, "model": "no-such-model" }))
+            .unwrap();
+        let err = provider.ensure_model(true).await.unwrap_err();
+        assert!(err.downcast_ref::<OllamaPullFailed>().is_some());
+        assert!(err.to_string().contains("模型名称不存在"));
+    }
+}
@@ -0,0 +1,86 @@
+// Ollama 重建连接 - 连续无法连接时由 ReconnectLayer 触发，替换 HTTP 客户端
+//
+// 切换网络或 Tailscale 重连后，连接池中的连接可能已失效，服务地址也可能解析到新的 IP；
+// 新客户端不复用旧连接，沿用配置的连接超时与附加请求头
+
+use super::OllamaProvider;
+use anyhow::Result;
+use reqwest::Client;
+use std::sync::PoisonError;
+use tracing::info;
+
+impl OllamaProvider {
+    /// 当前使用的 HTTP 客户端（Client 内部为引用计数，克隆开销很小）
+    pub(super) fn client(&self) -> Client {
+        self.client
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// 丢弃连接池，按当前配置重建 HTTP 客户端；进行中的请求继续使用旧客户端
+    pub(super) fn rebuild_client(&self) -> Result<()> {
+        let client = Self::build_client(self.connect_timeout_secs, self.client_headers.clone())?;
+        *self.client.write().unwrap_or_else(PoisonError::into_inner) = client;
+        info!("Ollama: 已重建与 {} 的连接", self.base_url);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::{OllamaProvider, OllamaUnreachable};
+    use crate::llm::plugin::LLMProvider;
+    use crate::llm::{ProviderBuilder, ReconnectLayer};
+    use crate::test_server::{serve_listener, Response};
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_reconnect_after_service_comes_back() {
+        // 先占用一个端口再释放，模拟服务暂时不可用
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<String> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.jpg", 1_700_000_000_000i64 + i));
+                image::RgbImage::from_fn(64, 48, |x, y| {
+                    image::Rgb([(x * 4) as u8, (y * 5) as u8, (i * 40) as u8])
+                })
+                .save(&path)
+                .unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({
+                "base_url": format!("http://{}", addr),
+                "detect_idle_frames": false,
+                "headers": { "X-Token": "abc" }
+            }))
+            .unwrap();
+        let provider = ProviderBuilder::new()
+            .layer(ReconnectLayer::new(2))
+            .build(Box::new(provider));
+        for _ in 0..2 {
+            let err = provider.analyze_frames(frames.clone()).await.unwrap_err();
+            assert!(err.downcast_ref::<OllamaUnreachable>().is_some(), "{err}");
+        }
+
+        // 服务恢复后，重建的客户端仍带着配置的请求头
+        let (tx, mut tokens) = tokio::sync::mpsc::unbounded_channel();
+        let content = r#"{"title":"编写代码","summary":"在编辑器中修改代码"}"#;
+        let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+        tokio::spawn(serve_listener(listener, move |request| {
+            let _ = tx.send(request.header("x-token").map(str::to_string));
+            Response::ok(serde_json::json!({ "message": { "content": content } }).to_string())
+        }));
+        let summary = provider.analyze_frames(frames).await.unwrap();
+        assert_eq!(summary.title, "编写代码");
+        assert_eq!(tokens.recv().await.unwrap().as_deref(), Some("abc"));
+    }
+}
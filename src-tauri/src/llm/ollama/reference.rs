@@ -0,0 +1,94 @@
+// Ollama 参考截图 - 带标签的少样本示例，帮助模型识别用户常用的应用
+//
+// 参考截图排在会话截图之前发送，并与会话截图共用同一图片预算
+
+use super::OllamaProvider;
+use crate::llm::ReferenceImage;
+use tracing::warn;
+
+/// 参考截图数量上限
+pub(super) const MAX_REFERENCE_IMAGES: usize = 4;
+
+impl OllamaProvider {
+    /// 过滤标签或路径为空的参考截图，超过上限时只保留前几张
+    pub(super) fn bounded_reference_images(references: Vec<ReferenceImage>) -> Vec<ReferenceImage> {
+        let references: Vec<ReferenceImage> = references
+            .into_iter()
+            .filter(|r| !r.label.trim().is_empty() && !r.path.trim().is_empty())
+            .collect();
+        if references.len() > MAX_REFERENCE_IMAGES {
+            warn!(
+                "Ollama: 参考截图 {} 张超过上限 {}，只使用前 {} 张",
+                references.len(),
+                MAX_REFERENCE_IMAGES,
+                MAX_REFERENCE_IMAGES
+            );
+        }
+        references.into_iter().take(MAX_REFERENCE_IMAGES).collect()
+    }
+
+    /// 参考截图说明：告知模型前几张图片是带标签的示例而非会话截图
+    pub(super) fn build_reference_note(labels: &[&str]) -> Option<String> {
+        if labels.is_empty() {
+            return None;
+        }
+        let mut note = format!(
+            "前 {} 张图片是参考示例，仅用于帮助识别用户常用的应用，不属于本次会话：",
+            labels.len()
+        );
+        for (idx, label) in labels.iter().enumerate() {
+            note.push_str(&format!("\n[参考 {}] {}", idx + 1, label));
+        }
+        note.push_str("\n截图中出现相同界面时，请使用对应的应用名称填写 keywords；之后的图片才是需要分析的会话截图。");
+        Some(note)
+    }
+
+    /// 编码参考截图，读取失败的跳过（返回标签与 base64 图片）
+    pub(super) async fn encode_reference_images(&self) -> (Vec<&str>, Vec<String>) {
+        let mut labels = Vec::new();
+        let mut images = Vec::new();
+        for reference in &self.reference_images {
+            match self.image_to_base64(&reference.path).await {
+                Ok(image) => {
+                    labels.push(reference.label.as_str());
+                    images.push(image);
+                }
+                Err(e) => warn!(
+                    "Ollama: 参考截图读取失败 label={} path={} err={}",
+                    reference.label, reference.path, e
+                ),
+            }
+        }
+        (labels, images)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MAX_REFERENCE_IMAGES;
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::LLMProvider;
+    use reqwest::Client;
+    use serde_json::Value;
+
+    #[test]
+    fn test_reference_images_are_bounded() {
+        let mut provider = OllamaProvider::new(Client::new());
+        let references: Vec<Value> = (0..MAX_REFERENCE_IMAGES + 2)
+            .map(|i| serde_json::json!({ "label": format!("应用{i}"), "path": format!("/refs/{i}.png") }))
+            .chain(std::iter::once(
+                serde_json::json!({ "label": " ", "path": "/refs/x.png" }),
+            ))
+            .collect();
+        provider
+            .configure(serde_json::json!({ "reference_images": references }))
+            .unwrap();
+        assert_eq!(provider.reference_images.len(), MAX_REFERENCE_IMAGES);
+        assert_eq!(provider.reference_images[0].label, "应用0");
+
+        assert!(OllamaProvider::build_reference_note(&[]).is_none());
+        let note = OllamaProvider::build_reference_note(&["我的 IDE", "内部工单系统"]).unwrap();
+        assert!(note.starts_with("前 2 张图片是参考示例"));
+        assert!(note.contains("[参考 1] 我的 IDE\n[参考 2] 内部工单系统"));
+    }
+}
@@ -0,0 +1,92 @@
+// Ollama 单字段重新生成 - 总结大体准确时只重新生成标签、关键时刻或评分
+//
+// 提示词附上当前总结，只采用模型输出中的指定字段，其余字段保持不变
+
+use super::OllamaProvider;
+use crate::llm::plugin::{build_refine_prompt, merge_refined_field, SessionSummary, SummaryField};
+use anyhow::{anyhow, Result};
+use tracing::{info, warn};
+
+impl OllamaProvider {
+    /// 单字段重新生成主体（由 refine_field 包裹在 tracing span 中执行）
+    pub(super) async fn refine_summary_field(
+        &self,
+        frames: Vec<String>,
+        current: SessionSummary,
+        field: SummaryField,
+        model: &str,
+    ) -> Result<SessionSummary> {
+        info!("Ollama: 重新生成 {:?}", field);
+        // 与完整分析使用相同的采样，只发送会话截图，不附参考截图
+        let max_images = self.prompt_profile(model).max_images;
+        let frame_budget = self.frame_budget(&frames, max_images);
+        let sampled = self.sample_frames(&frames, frame_budget, current.sampling_seed);
+        let mut images = Vec::new();
+        let mut resident = 0;
+        for path in sampled {
+            match self.chat_image(&path, &mut resident).await {
+                Ok(image) => images.push(image),
+                Err(e) => warn!("Ollama: 编码失败 path={} err={}", path, e),
+            }
+        }
+        if images.is_empty() {
+            return Err(anyhow!("没有可用的图片帧用于分析"));
+        }
+
+        let prompt = build_refine_prompt(&current, field)?;
+        let raw = self.call_ollama_chat(model, prompt, images, None).await?;
+        let (_, body) = crate::llm::reasoning::split_reasoning(&raw);
+        merge_refined_field(&current, field, self.extract_json_text(&body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{LLMProvider, SessionSummary, SummaryField};
+    use crate::test_server::serve_routes;
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_refine_field_keeps_other_fields() {
+        let content = r#"```json
+{"productivity_score": 90, "focus_score": 85, "title": "不应采用"}
+```"#;
+        let url = serve_routes(vec![(
+            "/api/chat",
+            serde_json::json!({ "message": { "content": content } }).to_string(),
+        )])
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let frame = dir.path().join("1700000000000.jpg");
+        std::fs::write(&frame, [0xFF, 0xD8, 0xFF]).unwrap();
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url }))
+            .unwrap();
+
+        let current: SessionSummary = serde_json::from_value(serde_json::json!({
+            "title": "编写代码",
+            "summary": "在编辑器中实现新功能",
+            "tags": [],
+            "key_moments": [],
+            "productivity_score": 20,
+            "focus_score": 30,
+            "start_time": "2025-01-01T09:00:00Z",
+            "end_time": "2025-01-01T09:15:00Z"
+        }))
+        .unwrap();
+        let refined = provider
+            .refine_field(
+                vec![frame.to_str().unwrap().to_string()],
+                current,
+                SummaryField::Scores,
+            )
+            .await
+            .unwrap();
+        assert_eq!(refined.title, "编写代码");
+        assert_eq!(refined.productivity_score, Some(90.0));
+        assert_eq!(refined.focus_score, Some(85.0));
+    }
+}
@@ -0,0 +1,311 @@
+// Ollama 帧采样 - 等间隔采样与可复现的随机采样
+//
+// 开启 sample_jitter 后在每个采样区间内随机取一帧；种子固定时结果可复现，
+// 实际使用的种子会随总结返回并写入会话分析记录。
+// 按应用覆盖采样优先于按场景采样，两者都不适用时回退为上述采样
+
+use super::prompt::frame_timestamp_ms;
+use super::OllamaProvider;
+use crate::llm::plugin::AnalysisContext;
+use crate::llm::{app_coverage, scene_sampling, FrameOrder};
+use crate::video::frame_hash::{self, FrameHashAlgorithm};
+use tracing::{debug, warn};
+
+/// 带种子的伪随机数生成器（SplitMix64），结果不随平台或标准库版本变化
+pub(super) struct SplitMix64(pub(super) u64);
+
+impl SplitMix64 {
+    pub(super) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+impl OllamaProvider {
+    /// 按配置的顺序排列采样帧（采样结果为时间先后顺序）
+    pub(super) fn order_frames(&self, mut frames: Vec<String>) -> Vec<String> {
+        if self.frame_order == FrameOrder::Reverse {
+            frames.reverse();
+        }
+        frames
+    }
+
+    /// 采样帧：无种子时等间隔取帧；有种子时在每个区间内随机取一帧（同一种子结果一致）
+    pub(super) fn sample_frames(
+        &self,
+        frames: &[String],
+        max_frames: usize,
+        seed: Option<u64>,
+    ) -> Vec<String> {
+        if frames.len() <= max_frames {
+            return frames.to_vec();
+        }
+        let Some(seed) = seed else {
+            let step = (frames.len() / max_frames).max(1);
+            return frames
+                .iter()
+                .step_by(step)
+                .take(max_frames)
+                .cloned()
+                .collect();
+        };
+
+        // 将帧均分为 max_frames 个区间，每个区间取一帧，保持时间顺序
+        let mut rng = SplitMix64(seed);
+        (0..max_frames)
+            .map(|i| {
+                let start = i * frames.len() / max_frames;
+                let end = (i + 1) * frames.len() / max_frames;
+                let offset = (rng.next_u64() % (end - start) as u64) as usize;
+                frames[start + offset].clone()
+            })
+            .collect()
+    }
+
+    /// 按上下文采样：配置了应用覆盖且有前台应用记录时保证每个应用至少有一帧，
+    /// 其次按场景采样，都不适用时普通采样
+    pub(super) async fn sample_for_context(
+        &self,
+        frames: &[String],
+        context: &AnalysisContext,
+        max_frames: usize,
+        seed: Option<u64>,
+    ) -> Vec<String> {
+        let mut sampled = self.app_coverage.as_ref().and_then(|config| {
+            app_coverage::sample_with_app_coverage(
+                frames,
+                &context.foreground_apps,
+                max_frames,
+                config,
+            )
+        });
+        if sampled.is_none() {
+            sampled = self.sample_by_scene(frames, max_frames).await;
+        }
+        sampled.unwrap_or_else(|| self.sample_frames(frames, max_frames, seed))
+    }
+
+    /// 按场景采样（在阻塞线程中计算哈希）；未开启、帧数在预算内或无法计算哈希时返回 None
+    async fn sample_by_scene(&self, frames: &[String], budget: usize) -> Option<Vec<String>> {
+        let config = self.scene_aware.clone()?;
+        if frames.len() <= budget {
+            return None;
+        }
+        let candidates = scene_sampling::scan_candidates(frames, budget, &config);
+        let sampled = tokio::task::spawn_blocking(move || {
+            scene_sampling::sample_by_scene(&candidates, budget, &config, |path| {
+                frame_hash::hash_frame(path, FrameHashAlgorithm::DHash).ok()
+            })
+        })
+        .await
+        .ok()
+        .flatten();
+        if sampled.is_none() {
+            warn!("Ollama: 无法计算帧的感知哈希，按场景采样回退为等间隔采样");
+        }
+        sampled
+    }
+
+    /// 本次采样实际使用的种子；未开启随机采样时为 None
+    pub(super) fn effective_seed(&self, frames: &[String]) -> Option<u64> {
+        if !self.sample_jitter {
+            return None;
+        }
+        let seed = self
+            .sampling_seed
+            .unwrap_or_else(|| Self::frames_fingerprint(frames));
+        Some(seed)
+    }
+
+    /// 帧路径的 FNV-1a 哈希（DefaultHasher 不保证跨版本稳定，不能用于复现）
+    pub(super) fn frames_fingerprint(frames: &[String]) -> u64 {
+        frames
+            .iter()
+            .flat_map(|f| f.as_bytes().iter().chain(std::iter::once(&0u8)))
+            .fold(0xcbf2_9ce4_8422_2325u64, |hash, b| {
+                (hash ^ u64::from(*b)).wrapping_mul(0x0000_0100_0000_01b3)
+            })
+    }
+
+    /// 会话时长（秒）：优先使用会话时间窗口，否则按帧文件名中的时间戳推算
+    fn session_duration_secs(&self, frames: &[String]) -> Option<f64> {
+        if let Some((start, end)) = self.session_window_start.zip(self.session_window_end) {
+            return Some((end - start).num_milliseconds().max(0) as f64 / 1000.0);
+        }
+        let first = frames.first().and_then(|f| frame_timestamp_ms(f))?;
+        let last = frames.last().and_then(|f| frame_timestamp_ms(f))?;
+        Some((last - first).max(0) as f64 / 1000.0)
+    }
+
+    /// 采样帧数：配置了 frames_per_minute 时按会话时长计算（至少 1 帧），不超过 hard_max
+    pub(super) fn frame_budget(&self, frames: &[String], hard_max: usize) -> usize {
+        let Some(per_minute) = self.frames_per_minute else {
+            return hard_max;
+        };
+        let Some(duration_secs) = self.session_duration_secs(frames) else {
+            debug!("Ollama: 无法确定会话时长，按固定上限 {} 帧采样", hard_max);
+            return hard_max;
+        };
+        let budget = (duration_secs / 60.0 * per_minute as f64).ceil() as usize;
+        let budget = budget.clamp(1, hard_max.max(1));
+        debug!(
+            "Ollama: 会话时长 {:.0}s × {}/分钟 → 采样 {} 帧（上限 {}）",
+            duration_secs, per_minute, budget, hard_max
+        );
+        budget
+    }
+
+    /// 记录本次分析实际使用的采样种子，供 last_sampling_seed 查询
+    pub(super) fn record_sampling_seed(&self, seed: Option<u64>) {
+        if let Ok(mut last) = self.last_sampling_seed.lock() {
+            *last = seed;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::LLMProvider;
+    use crate::test_server::serve_routes;
+    use chrono::Utc;
+    use reqwest::Client;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_frame_budget_scales_with_duration_up_to_hard_max() {
+        // 每 2 秒一帧，文件名为毫秒时间戳
+        let frames = |minutes: i64| -> Vec<String> {
+            (0..=minutes * 30)
+                .map(|i| format!("/frames/{}.jpg", 1_700_000_000_000 + i * 2000))
+                .collect()
+        };
+        let mut provider = OllamaProvider::new(Client::new());
+        assert_eq!(provider.frame_budget(&frames(1), 30), 30);
+
+        provider
+            .configure(serde_json::json!({ "frames_per_minute": 4.0 }))
+            .unwrap();
+        assert_eq!(provider.frame_budget(&frames(1), 30), 4);
+        assert_eq!(provider.frame_budget(&frames(5), 30), 20);
+        // 3 小时会话受图片上限约束
+        assert_eq!(provider.frame_budget(&frames(180), 30), 30);
+        assert_eq!(provider.sample_frames(&frames(180), 30, None).len(), 30);
+        // 极短会话至少采样 1 帧；无法确定时长时按固定上限
+        assert_eq!(provider.frame_budget(&frames(0), 30), 1);
+        let unnamed = vec!["a.jpg".to_string(), "b.jpg".to_string()];
+        assert_eq!(provider.frame_budget(&unnamed, 30), 30);
+
+        // 会话时间窗口优先于帧时间戳
+        let start = Utc::now();
+        provider.set_session_window(Some(start), Some(start + chrono::Duration::minutes(2)));
+        assert_eq!(provider.frame_budget(&frames(180), 30), 8);
+
+        provider
+            .configure(serde_json::json!({ "frames_per_minute": 0.0 }))
+            .unwrap();
+        assert_eq!(provider.frame_budget(&frames(1), 30), 30);
+    }
+
+    #[test]
+    fn test_seeded_sampling_is_reproducible() {
+        let mut provider = OllamaProvider::new(Client::new());
+        let frames: Vec<String> = (0..300)
+            .map(|i| format!("/tmp/frames/{}.jpg", 1_700_000_000_000i64 + i * 1000))
+            .collect();
+
+        // 未开启随机采样时不使用也不记录种子
+        assert_eq!(provider.effective_seed(&frames), None);
+
+        provider.sample_jitter = true;
+        provider.sampling_seed = Some(42);
+        let seed = provider.effective_seed(&frames);
+        assert_eq!(seed, Some(42));
+
+        let first = provider.sample_frames(&frames, 30, seed);
+        let second = provider.sample_frames(&frames, 30, seed);
+        assert_eq!(first.len(), 30);
+        assert_eq!(first, second);
+        // 随机采样仍保持时间顺序
+        assert!(first.windows(2).all(|w| w[0] < w[1]));
+        assert_ne!(first, provider.sample_frames(&frames, 30, Some(7)));
+
+        // 未配置种子时由帧路径推导，同一会话两次运行结果一致
+        provider.sampling_seed = None;
+        let derived = provider.effective_seed(&frames);
+        assert_eq!(derived, provider.effective_seed(&frames.clone()));
+        assert_eq!(
+            provider.sample_frames(&frames, 30, derived),
+            provider.sample_frames(&frames, 30, derived)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sampling_seed_is_stored_with_session_analysis() {
+        let content = serde_json::json!({
+            "title": "编写代码",
+            "summary": "在编辑器中实现随机采样"
+        })
+        .to_string();
+        let url = serve_routes(vec![(
+            "/api/chat",
+            serde_json::json!({ "message": { "content": content } }).to_string(),
+        )])
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let frames: Vec<String> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.jpg", 1_700_000_000_000i64 + i));
+                std::fs::write(&path, [0xFF, 0xD8, i as u8]).unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect();
+        let db = Arc::new(
+            crate::storage::Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let session_id = db
+            .insert_session(&crate::storage::Session {
+                id: None,
+                start_time: Utc::now(),
+                end_time: Utc::now(),
+                title: String::new(),
+                summary: String::new(),
+                video_path: None,
+                tags: "[]".to_string(),
+                created_at: None,
+                device_name: None,
+                device_type: None,
+                pinned: false,
+            })
+            .await
+            .unwrap();
+
+        // 超出 i64 范围的种子按位存储
+        let seed = 0xDEAD_BEEF_DEAD_BEEF_u64;
+        let mut manager = crate::llm::LLMManager::new(Client::new());
+        manager
+            .configure_ollama(crate::llm::OllamaConfig {
+                base_url: url,
+                sample_jitter: true,
+                sampling_seed: Some(seed),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        manager.set_provider_database(db.clone(), Some(session_id));
+        let analysis = manager
+            .segment_video_and_generate_timeline(frames, 1, None)
+            .await
+            .unwrap();
+        assert_eq!(analysis.sampling_seed, Some(seed));
+
+        let record = db.get_session_analysis(session_id).await.unwrap().unwrap();
+        assert_eq!(record.sampling_seed.map(|seed| seed as u64), Some(seed));
+    }
+}
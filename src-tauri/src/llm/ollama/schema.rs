@@ -0,0 +1,108 @@
+// Ollama 提示词中的 JSON 示例 - 与 SessionSummary 的字段保持一致
+//
+// 示例写错字段时模型会照着输出，解析器再静默丢弃；这里在构造时和测试中校验两者不漂移
+
+use crate::llm::plugin::SessionSummary;
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde_json::Value;
+
+/// 提示词中的 JSON 示例，必须能被解析为 SessionSummary（见 validate_schema_example）
+pub(super) const PROMPT_SCHEMA_EXAMPLE: &str = r#"{
+  "title": "10字以内",
+  "summary": "50-100字",
+  "tags": [
+    {"category":"work","confidence":0.0,"keywords":["..."],"time_ranges":[{"start":"MM:SS","end":"MM:SS"}]}
+  ],
+  "key_moments": [
+    {"time":"MM:SS","description":"...","importance":1}
+  ],
+  "productivity_score": 0,
+  "focus_score": 0
+}"#;
+
+/// 由解析器注入、不需要出现在示例中的字段
+const PARSER_INJECTED_FIELDS: [&str; 2] = ["start_time", "end_time"];
+
+/// 校验提示词示例可被解析为 SessionSummary，且重新序列化后字段集合一致
+pub(super) fn validate_schema_example() -> Result<()> {
+    let example: Value = serde_json::from_str(PROMPT_SCHEMA_EXAMPLE)?;
+    let mut with_times = example.clone();
+    if let Some(obj) = with_times.as_object_mut() {
+        let now = serde_json::to_value(Utc::now())?;
+        for field in PARSER_INJECTED_FIELDS {
+            obj.insert(field.to_string(), now.clone());
+        }
+    }
+    let summary: SessionSummary = serde_json::from_value(with_times)
+        .map_err(|e| anyhow!("示例无法解析为 SessionSummary: {e}"))?;
+    let mut round_trip = serde_json::to_value(&summary)?;
+    if let Some(obj) = round_trip.as_object_mut() {
+        for field in PARSER_INJECTED_FIELDS {
+            obj.remove(field);
+        }
+    }
+
+    let mut drift = Vec::new();
+    collect_field_drift(&example, &round_trip, "", &mut drift);
+    if drift.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("字段不一致: {}", drift.join(", ")))
+    }
+}
+
+/// 递归比较两个 JSON 的字段集合（数组只比较首个元素）
+fn collect_field_drift(example: &Value, parsed: &Value, path: &str, drift: &mut Vec<String>) {
+    match (example, parsed) {
+        (Value::Object(a), Value::Object(b)) => {
+            for key in a.keys().filter(|k| !b.contains_key(*k)) {
+                drift.push(format!("{path}{key}（解析器忽略）"));
+            }
+            for key in b.keys().filter(|k| !a.contains_key(*k)) {
+                drift.push(format!("{path}{key}（示例缺失）"));
+            }
+            for (key, value) in a {
+                if let Some(other) = b.get(key) {
+                    collect_field_drift(value, other, &format!("{path}{key}."), drift);
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            if let (Some(x), Some(y)) = (a.first(), b.first()) {
+                let item_path = format!("{}[0].", path.trim_end_matches('.'));
+                collect_field_drift(x, y, &item_path, drift);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::AnalysisContext;
+    use reqwest::Client;
+
+    #[test]
+    fn test_schema_example_matches_session_summary() {
+        validate_schema_example().unwrap();
+
+        let provider = OllamaProvider::new(Client::new());
+        let prompt = provider.build_prompt(&AnalysisContext::default(), &[]);
+        assert!(prompt.contains(PROMPT_SCHEMA_EXAMPLE));
+    }
+
+    #[test]
+    fn test_collect_field_drift_reports_both_directions() {
+        let example = serde_json::json!({"title": "a", "extra": 1, "tags": [{"category": "work"}]});
+        let parsed = serde_json::json!({"title": "a", "summary": "b", "tags": [{"category": "work", "confidence": 0.5}]});
+        let mut drift = Vec::new();
+        collect_field_drift(&example, &parsed, "", &mut drift);
+
+        assert!(drift.contains(&"extra（解析器忽略）".to_string()));
+        assert!(drift.contains(&"summary（示例缺失）".to_string()));
+        assert!(drift.contains(&"tags[0].confidence（示例缺失）".to_string()));
+    }
+}
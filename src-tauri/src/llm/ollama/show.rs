@@ -0,0 +1,201 @@
+// Ollama 模型能力查询 - 从 /api/show 读取视觉能力、上下文长度与模型家族
+//
+// 服务端报告的字段覆盖 capabilities() 的静态默认值；只对查询时的模型生效，切换模型后失效
+
+use super::OllamaProvider;
+use crate::llm::plugin::ProviderCapabilities;
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{info, warn};
+
+/// /api/show 查询的超时（秒），服务端不可达时不阻塞配置流程
+pub(super) const SHOW_TIMEOUT_SECS: u64 = 10;
+
+/// /api/show 响应中与能力相关的字段（旧版本服务端可能缺少 capabilities 与 model_info）
+#[derive(Deserialize)]
+struct ShowResponse {
+    #[serde(default)]
+    capabilities: Option<Vec<String>>,
+    #[serde(default)]
+    details: Option<ShowDetails>,
+    #[serde(default)]
+    model_info: Option<serde_json::Map<String, Value>>,
+}
+
+#[derive(Deserialize)]
+struct ShowDetails {
+    #[serde(default)]
+    family: Option<String>,
+    #[serde(default)]
+    families: Option<Vec<String>>,
+}
+
+/// 服务端报告的模型能力，未报告的字段为 None，沿用静态默认值
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct ReportedCapabilities {
+    /// 查询时的模型名，切换模型后不再适用
+    model: String,
+    vision: Option<bool>,
+    context_length: Option<usize>,
+    family: Option<String>,
+}
+
+impl ShowResponse {
+    fn into_reported(self, model: &str) -> ReportedCapabilities {
+        let families = self
+            .details
+            .as_ref()
+            .and_then(|d| d.families.clone())
+            .unwrap_or_default();
+        let vision = match &self.capabilities {
+            Some(capabilities) => Some(capabilities.iter().any(|c| c == "vision")),
+            // 旧版本没有 capabilities 字段：带 clip 投影层的模型支持图片，否则无法判断
+            None => families.iter().any(|f| f == "clip").then_some(true),
+        };
+
+        let context_length = self.model_info.as_ref().and_then(|info| {
+            let architecture = info.get("general.architecture").and_then(Value::as_str);
+            let key = architecture.map(|arch| format!("{arch}.context_length"));
+            key.and_then(|key| info.get(&key))
+                .or_else(|| {
+                    info.iter()
+                        .find(|(key, _)| key.ends_with(".context_length"))
+                        .map(|(_, value)| value)
+                })
+                .and_then(Value::as_u64)
+                .map(|length| length as usize)
+        });
+
+        let family = self
+            .details
+            .and_then(|d| d.family)
+            .or_else(|| families.into_iter().next())
+            .filter(|family| !family.trim().is_empty());
+
+        ReportedCapabilities {
+            model: model.to_string(),
+            vision,
+            context_length,
+            family,
+        }
+    }
+}
+
+impl OllamaProvider {
+    /// 查询 /api/show 并记录当前模型的能力
+    pub(super) async fn refresh_reported_capabilities(&mut self) -> Result<()> {
+        let model = self.model.clone();
+        let reported = self.fetch_model_info(&model).await?.into_reported(&model);
+        info!(
+            "Ollama: 模型 {} 能力 vision={:?} context_length={:?} family={:?}",
+            model, reported.vision, reported.context_length, reported.family
+        );
+        if reported.vision == Some(false) {
+            warn!("Ollama: 模型 {} 不支持图片输入，截图分析将失败", model);
+        }
+        self.reported_capabilities = Some(reported);
+        Ok(())
+    }
+
+    /// 用服务端报告的字段覆盖静态默认值（查询的不是当前模型时不生效）
+    pub(super) fn apply_reported_capabilities(&self, capabilities: &mut ProviderCapabilities) {
+        let Some(reported) = self
+            .reported_capabilities
+            .as_ref()
+            .filter(|reported| reported.model == self.model)
+        else {
+            return;
+        };
+        if let Some(vision) = reported.vision {
+            capabilities.vision_support = vision;
+        }
+        if let Some(context_length) = reported.context_length {
+            capabilities.max_input_tokens = context_length;
+        }
+        capabilities.model_family = reported.family.clone();
+    }
+
+    /// 查询 /api/show 获取模型信息
+    async fn fetch_model_info(&self, model: &str) -> Result<ShowResponse> {
+        let url = format!("{}/api/show", self.base_url.trim_end_matches('/'));
+        let show = self
+            .client()
+            .post(url)
+            .timeout(std::time::Duration::from_secs(SHOW_TIMEOUT_SECS))
+            .json(&serde_json::json!({ "model": model }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(show)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::plugin::LLMProvider;
+    use crate::test_server::serve_routes;
+    use reqwest::Client;
+
+    /// 录制自 Ollama 0.12 的 /api/show 响应（省略 modelfile、template 等无关字段）
+    const RECORDED_SHOW: &str = r#"{
+        "license": "Apache License 2.0",
+        "parameters": "temperature 0.7\ntop_p 0.8",
+        "details": {
+            "parent_model": "",
+            "format": "gguf",
+            "family": "qwen3vl",
+            "families": ["qwen3vl"],
+            "parameter_size": "33.4B",
+            "quantization_level": "Q4_K_M"
+        },
+        "model_info": {
+            "general.architecture": "qwen3vl",
+            "general.parameter_count": 33354678272,
+            "qwen3vl.block_count": 64,
+            "qwen3vl.context_length": 262144,
+            "qwen3vl.embedding_length": 5120
+        },
+        "capabilities": ["completion", "vision", "tools", "thinking"],
+        "modified_at": "2025-10-30T10:12:45.118402+08:00"
+    }"#;
+
+    #[tokio::test]
+    async fn test_refreshed_capabilities_override_static_defaults() {
+        let url = serve_routes(vec![("/api/show", RECORDED_SHOW.to_string())]).await;
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url, "model": "qwen3-vl:32b" }))
+            .unwrap();
+        provider.refresh_capabilities().await.unwrap();
+
+        let capabilities = provider.capabilities();
+        assert!(capabilities.vision_support);
+        assert_eq!(capabilities.max_input_tokens, 262144);
+        assert_eq!(capabilities.model_family.as_deref(), Some("qwen3vl"));
+
+        // 纯文本模型：服务端未报告 vision
+        let text_only: ShowResponse = serde_json::from_str(
+            r#"{"details":{"family":"llama"},"capabilities":["completion"],"model_info":{"general.architecture":"llama","llama.context_length":8192}}"#,
+        )
+        .unwrap();
+        provider.reported_capabilities = Some(text_only.into_reported("qwen3-vl:32b"));
+        assert!(!provider.capabilities().vision_support);
+        assert_eq!(provider.capabilities().max_input_tokens, 8192);
+
+        // 旧版本服务端只有 details：按 clip 判断视觉能力，上下文长度沿用默认值
+        let legacy: ShowResponse =
+            serde_json::from_str(r#"{"details":{"family":"llama","families":["llama","clip"]}}"#)
+                .unwrap();
+        let reported = legacy.into_reported("llava:13b");
+        assert_eq!(reported.vision, Some(true));
+        assert_eq!(reported.context_length, None);
+        provider.reported_capabilities = Some(reported);
+        // 查询的不是当前模型，不生效
+        assert_eq!(provider.capabilities().max_input_tokens, 128000);
+        assert_eq!(provider.capabilities().model_family, None);
+    }
+}
@@ -0,0 +1,447 @@
+// Ollama 流式响应 - 逐行读取 /api/chat 的流式输出，拼接内容并推送生成进度
+//
+// 流式输出每块通常只有一个 token，文本事件合并后再推送，进度快照按间隔节流。
+// 输出在完成前中断时携带已接收的内容返回，分析时改为用 JSON 修复解析已接收的内容
+
+use super::OllamaProvider;
+use crate::llm::plugin::{AnalysisProgress, LLMProvider, SessionSummary};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+/// 流式进度推送的最小间隔
+const PROGRESS_REPORT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// 流式输出文本合并推送的最小间隔，期间收到的文本攒到下次推送
+const TEXT_REPORT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 攒下的文本达到该字节数时不等间隔立即推送
+const TEXT_REPORT_BYTES: usize = 512;
+
+/// 流式输出在完成前中断（连接断开、读取超时或最后一行被截断），携带已接收的内容
+///
+/// 作为底层错误的上下文附加，原错误类型仍可通过 downcast_ref 识别。
+/// Ollama 无法从断点继续生成，分析时改为用 JSON 修复解析已接收的内容
+#[derive(Clone, Debug, PartialEq)]
+pub struct OllamaStreamInterrupted {
+    pub partial: String,
+}
+
+impl std::fmt::Display for OllamaStreamInterrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Ollama 流式输出中断（已接收 {} 字符）",
+            self.partial.chars().count()
+        )
+    }
+}
+
+impl std::error::Error for OllamaStreamInterrupted {}
+
+/// 流式响应中的单行
+#[derive(Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    message: Option<StreamMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    eval_count: Option<u64>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StreamMessage {
+    #[serde(default)]
+    content: String,
+}
+
+/// 流式生成进度统计
+pub(super) struct StreamProgress {
+    started: Instant,
+    last_report: Option<Instant>,
+    eval_count: u64,
+    num_predict: Option<u64>,
+    /// 尚未推送的输出文本
+    pending_text: String,
+    last_text: Option<Instant>,
+}
+
+impl StreamProgress {
+    pub(super) fn new(num_predict: Option<u64>) -> Self {
+        Self {
+            started: Instant::now(),
+            last_report: None,
+            eval_count: 0,
+            num_predict,
+            pending_text: String::new(),
+            last_text: None,
+        }
+    }
+
+    /// 攒下一段输出文本；距上次推送超过间隔或攒够字节数时返回合并后的文本（第一段立即返回）
+    fn push_text(&mut self, delta: &str) -> Option<String> {
+        self.pending_text.push_str(delta);
+        let now = Instant::now();
+        let due = self.pending_text.len() >= TEXT_REPORT_BYTES
+            || match self.last_text {
+                Some(last) => now.duration_since(last) >= TEXT_REPORT_INTERVAL,
+                None => true,
+            };
+        if !due {
+            return None;
+        }
+        self.last_text = Some(now);
+        Some(std::mem::take(&mut self.pending_text))
+    }
+
+    /// 取出尚未推送的文本（输出结束时调用）
+    fn take_text(&mut self) -> Option<String> {
+        (!self.pending_text.is_empty()).then(|| std::mem::take(&mut self.pending_text))
+    }
+
+    fn record(&mut self, tokens: u64) {
+        self.eval_count += tokens;
+    }
+
+    fn elapsed_secs(&self) -> f32 {
+        self.started.elapsed().as_secs_f32()
+    }
+
+    /// 节流：距上次推送超过间隔才返回 true
+    fn should_report(&mut self) -> bool {
+        let now = Instant::now();
+        match self.last_report {
+            Some(last) if now.duration_since(last) < PROGRESS_REPORT_INTERVAL => false,
+            _ => {
+                self.last_report = Some(now);
+                true
+            }
+        }
+    }
+
+    /// 生成进度快照：有 num_predict 时给出百分比与剩余时间，否则给出耗时与速度
+    fn snapshot(&self, provider: &str, elapsed_secs: f32) -> AnalysisProgress {
+        let tokens_per_sec = if elapsed_secs > 0.0 {
+            self.eval_count as f32 / elapsed_secs
+        } else {
+            0.0
+        };
+        match self.num_predict.filter(|n| *n > 0) {
+            Some(num_predict) => {
+                let percent = (self.eval_count as f32 / num_predict as f32 * 100.0).min(100.0);
+                let remaining = num_predict.saturating_sub(self.eval_count) as f32;
+                let eta_secs = (tokens_per_sec > 0.0).then(|| remaining / tokens_per_sec);
+                AnalysisProgress::Tokens {
+                    provider: provider.to_string(),
+                    eval_count: self.eval_count,
+                    num_predict,
+                    percent,
+                    tokens_per_sec,
+                    eta_secs,
+                }
+            }
+            None => AnalysisProgress::Throughput {
+                provider: provider.to_string(),
+                eval_count: self.eval_count,
+                elapsed_secs,
+                tokens_per_sec,
+            },
+        }
+    }
+}
+
+impl OllamaProvider {
+    /// 读取流式响应（按行分隔的 JSON），拼接内容并推送进度
+    ///
+    /// 输出在完成前中断且已收到内容时，返回附带 OllamaStreamInterrupted 上下文的错误
+    pub(super) async fn read_chat_stream(&self, mut resp: reqwest::Response) -> Result<String> {
+        let mut progress = StreamProgress::new(self.num_predict.map(u64::from));
+        let mut content = String::new();
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut done = false;
+
+        let interrupted = loop {
+            let bytes = match tokio::time::timeout(self.read_timeout(), resp.chunk()).await {
+                Ok(Ok(Some(bytes))) => bytes,
+                Ok(Ok(None)) => {
+                    // 连接结束时残留的半行视为被截断的输出
+                    if !buffer.is_empty() {
+                        match self.handle_stream_line(&buffer, &mut content, &mut progress) {
+                            Ok(tail_done) => done |= tail_done,
+                            Err(e) => break Some(e),
+                        }
+                    }
+                    break (!done).then(|| anyhow!("Ollama 流式响应在完成前结束"));
+                }
+                Ok(Err(e)) => break Some(self.map_request_error(e)),
+                Err(_) => break Some(self.read_timeout_error()),
+            };
+            buffer.extend_from_slice(&bytes);
+            while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=pos).collect();
+                done |= self.handle_stream_line(&line, &mut content, &mut progress)?;
+            }
+        };
+        if let Some(e) = interrupted {
+            if content.trim().is_empty() {
+                return Err(e);
+            }
+            warn!(
+                "Ollama: 流式输出中断（已接收 {} 字符）: {}",
+                content.chars().count(),
+                e
+            );
+            return Err(e.context(OllamaStreamInterrupted { partial: content }));
+        }
+
+        if let Some(delta) = progress.take_text() {
+            self.emit_text(delta);
+        }
+        let elapsed = progress.elapsed_secs();
+        self.emit_progress(progress.snapshot(self.name(), elapsed));
+        self.emit_progress(AnalysisProgress::Done {
+            provider: self.name().to_string(),
+            eval_count: progress.eval_count,
+            elapsed_secs: elapsed,
+        });
+        debug!(
+            "Ollama: 流式输出完成 tokens={} 用时 {:.1}s",
+            progress.eval_count, elapsed
+        );
+        Ok(content)
+    }
+
+    /// 处理一行流式输出，返回是否为结束块
+    pub(super) fn handle_stream_line(
+        &self,
+        line: &[u8],
+        content: &mut String,
+        progress: &mut StreamProgress,
+    ) -> Result<bool> {
+        let line = String::from_utf8_lossy(line);
+        let line = super::parse::trim_noise(&line);
+        if line.is_empty() {
+            return Ok(false);
+        }
+        let chunk: StreamChunk = serde_json::from_str(line)
+            .map_err(|e| anyhow!("Ollama 流式响应解析失败: {e}; line={}", line))?;
+        if let Some(err) = chunk.error {
+            return Err(anyhow!("Ollama 流式响应错误: {}", err));
+        }
+
+        if let Some(delta) = chunk.message.map(|m| m.content).filter(|c| !c.is_empty()) {
+            content.push_str(&delta);
+            // 中间块不带 eval_count，按每块约一个 token 累计
+            progress.record(1);
+            // 每块通常只有一个 token，合并后再推送，避免长输出刷满进度通道
+            if let Some(delta) = progress.push_text(&delta) {
+                self.emit_text(delta);
+            }
+        }
+        if let Some(eval_count) = chunk.eval_count {
+            progress.eval_count = eval_count;
+        }
+        if chunk.done {
+            if let Some(delta) = progress.take_text() {
+                self.emit_text(delta);
+            }
+        }
+        if !chunk.done && progress.should_report() {
+            let elapsed = progress.elapsed_secs();
+            self.emit_progress(progress.snapshot(self.name(), elapsed));
+        }
+        Ok(chunk.done)
+    }
+
+    /// 解析模型输出；流式输出中断时改用已接收的内容（经 JSON 修复）得到总结，仍无法解析时返回原错误
+    pub(super) fn summary_from_chat(&self, result: Result<String>) -> Result<SessionSummary> {
+        let err = match result {
+            Ok(raw) => return self.parse_session_summary(&raw),
+            Err(e) => e,
+        };
+        let Some(interrupted) = err.downcast_ref::<OllamaStreamInterrupted>() else {
+            return Err(err);
+        };
+        match self.parse_session_summary(&interrupted.partial) {
+            Ok(mut summary) => {
+                warn!(
+                    "Ollama: 流式输出中断，已用已接收的 {} 字符恢复出总结（模型 {}）",
+                    interrupted.partial.chars().count(),
+                    self.model
+                );
+                summary.recovered_stream = true;
+                Ok(summary)
+            }
+            Err(parse_err) => {
+                debug!("Ollama: 中断的流式输出无法恢复: {}", parse_err);
+                Err(err)
+            }
+        }
+    }
+
+    pub(super) fn emit_progress(&self, event: AnalysisProgress) {
+        if let Some(tx) = &self.progress_tx {
+            let _ = tx.send(event);
+        }
+    }
+
+    fn emit_text(&self, delta: String) {
+        self.emit_progress(AnalysisProgress::Text {
+            provider: self.name().to_string(),
+            delta,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::serve_routes;
+    use reqwest::Client;
+
+    #[test]
+    fn test_stream_progress_snapshot() {
+        let mut progress = StreamProgress::new(Some(200));
+        progress.record(50);
+        match progress.snapshot("ollama", 5.0) {
+            AnalysisProgress::Tokens {
+                percent,
+                tokens_per_sec,
+                eta_secs,
+                ..
+            } => {
+                assert_eq!(percent, 25.0);
+                assert_eq!(tokens_per_sec, 10.0);
+                assert_eq!(eta_secs, Some(15.0));
+            }
+            other => panic!("应为 Tokens 进度: {:?}", other),
+        }
+
+        // 未设置 num_predict 时只报告耗时与速度
+        let mut progress = StreamProgress::new(None);
+        progress.record(30);
+        assert!(matches!(
+            progress.snapshot("ollama", 3.0),
+            AnalysisProgress::Throughput { eval_count: 30, .. }
+        ));
+    }
+
+    #[test]
+    fn test_handle_stream_lines() {
+        let mut provider = OllamaProvider::new(Client::new());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        provider.set_progress_sender(Some(tx));
+
+        let mut content = String::new();
+        let mut progress = StreamProgress::new(Some(100));
+        for line in [
+            r#"{"message":{"role":"assistant","content":"{\"title\""},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":":\"a\"}"},"done":false}"#,
+            r#"{"message":{"role":"assistant","content":""},"done":true,"eval_count":7}"#,
+        ] {
+            provider
+                .handle_stream_line(line.as_bytes(), &mut content, &mut progress)
+                .unwrap();
+        }
+
+        assert_eq!(content, r#"{"title":"a"}"#);
+        // done 块的 eval_count 覆盖按块累计的近似值
+        assert_eq!(progress.eval_count, 7);
+        assert!(matches!(rx.try_recv(), Ok(AnalysisProgress::Text { .. })));
+
+        let err = provider.handle_stream_line(
+            br#"{"error":"model not found"}"#,
+            &mut content,
+            &mut progress,
+        );
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_streamed_text_is_coalesced() {
+        let mut provider = OllamaProvider::new(Client::new());
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        provider.set_progress_sender(Some(tx));
+
+        let mut content = String::new();
+        let mut progress = StreamProgress::new(None);
+        let token = r#"{"message":{"role":"assistant","content":"字"},"done":false}"#;
+        for _ in 0..2000 {
+            provider
+                .handle_stream_line(token.as_bytes(), &mut content, &mut progress)
+                .unwrap();
+        }
+        provider
+            .handle_stream_line(
+                br#"{"done":true,"eval_count":2000}"#,
+                &mut content,
+                &mut progress,
+            )
+            .unwrap();
+
+        let mut texts = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let AnalysisProgress::Text { delta, .. } = event {
+                texts.push(delta);
+            }
+        }
+        // 2000 个块合并为少量事件，结束时推送剩余文本，拼接后与完整输出一致
+        assert!(texts.len() < 50, "{}", texts.len());
+        assert_eq!(texts.concat(), content);
+        assert_eq!(content.chars().count(), 2000);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_stream_is_recovered_from_partial_content() {
+        // 连接在最后一行中途断开：没有结束块，已接收的 JSON 缺少结尾
+        let stream = |deltas: &[&str]| -> String {
+            let mut body: String = deltas
+                .iter()
+                .map(|delta| {
+                    format!(
+                        "{}\n",
+                        serde_json::json!({ "message": { "content": delta } })
+                    )
+                })
+                .collect();
+            body.push_str(r#"{"message":{"content":"中"#);
+            body
+        };
+        let url = serve_routes(vec![(
+            "/api/chat",
+            stream(&[r#"{"title":"写代码","#, r#""summary":"调试流式解析"#]),
+        )])
+        .await;
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url, "stream": true }))
+            .unwrap();
+        let result = provider
+            .call_ollama_chat("llava", "hi".to_string(), Vec::new(), None)
+            .await;
+        let interrupted = result.as_ref().unwrap_err();
+        assert!(interrupted
+            .downcast_ref::<OllamaStreamInterrupted>()
+            .is_some());
+
+        let summary = provider.summary_from_chat(result).unwrap();
+        assert!(summary.recovered_stream);
+        assert_eq!(summary.title, "写代码");
+        assert_eq!(summary.summary, "调试流式解析");
+
+        // 已接收的内容修复后仍缺少必需字段时返回原错误
+        let url = serve_routes(vec![("/api/chat", stream(&[r#"{"title":"写"#]))]).await;
+        provider
+            .configure(serde_json::json!({ "base_url": url, "stream": true }))
+            .unwrap();
+        let result = provider
+            .call_ollama_chat("llava", "hi".to_string(), Vec::new(), None)
+            .await;
+        let err = provider.summary_from_chat(result).unwrap_err();
+        assert!(err.downcast_ref::<OllamaStreamInterrupted>().is_some());
+    }
+}
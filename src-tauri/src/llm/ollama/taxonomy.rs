@@ -0,0 +1,26 @@
+// Ollama 分类体系 - 在提示词中列出顶层类别下配置的子类别，并规整模型输出的类别路径
+//
+// 未配置子类别时不附加说明；模型输出未配置的子类别时截断到已知的父类别
+
+use super::OllamaProvider;
+use crate::llm::taxonomy;
+use serde_json::Value;
+use tracing::warn;
+
+impl OllamaProvider {
+    /// 子类别说明；未配置子类别时为 None
+    pub(super) fn taxonomy_note(&self) -> Option<String> {
+        taxonomy::build_taxonomy_note(&self.taxonomy)
+    }
+
+    /// 将标签的类别路径截断到配置中已知的类别
+    pub(super) fn normalize_tag_paths(&self, v: &mut Value) {
+        let truncated = taxonomy::normalize_tag_paths(v, &self.taxonomy);
+        if truncated > 0 {
+            warn!(
+                "Ollama: {} 个标签的类别路径含有未配置的子类别，已截断到已知的父类别",
+                truncated
+            );
+        }
+    }
+}
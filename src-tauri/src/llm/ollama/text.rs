@@ -0,0 +1,73 @@
+// Ollama 纯文本会话分析 - 终端输出、剪贴板历史等没有截图的会话
+//
+// 提示词由共享的 build_text_session_prompt 生成，请求中不携带 images 字段
+
+use super::OllamaProvider;
+use crate::llm::plugin::{build_text_session_prompt, AnalysisContext, SessionSummary};
+use anyhow::Result;
+use tracing::info;
+
+impl OllamaProvider {
+    /// 纯文本分析主体（由 analyze_text 包裹在 tracing span 中执行）
+    pub(super) async fn analyze_session_text(&self, content: String) -> Result<SessionSummary> {
+        info!("Ollama: 开始分析纯文本会话");
+        let summary_prompt = self.build_prompt(&AnalysisContext::default(), &[]);
+        let prompt =
+            build_text_session_prompt(&summary_prompt, &content, self.prompt_sanitization)?;
+        let result = self
+            .call_ollama_chat(&self.model, prompt, Vec::new(), None)
+            .await;
+        self.summary_from_chat(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::{build_text_session_prompt, AnalysisContext, LLMProvider};
+    use crate::test_server::{serve, Response};
+    use reqwest::Client;
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_analyze_text_sends_shared_text_prompt_without_images() {
+        let content = serde_json::json!({
+            "title": "构建项目",
+            "summary": "在终端中编译并修复报错"
+        })
+        .to_string();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+        let url = serve(move |request| {
+            seen.lock().unwrap().push(request);
+            Response::ok(serde_json::json!({ "message": { "content": content } }).to_string())
+        })
+        .await;
+
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({ "base_url": url }))
+            .unwrap();
+        let summary = provider
+            .analyze_text("$ cargo build\nerror[E0308]: mismatched types".to_string())
+            .await
+            .unwrap();
+        assert_eq!(summary.title, "构建项目");
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/api/chat");
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        let message = &body["messages"][0];
+        assert!(message.get("images").is_none());
+
+        // 提示词与共享的纯文本提示词构建结果一致
+        let expected = build_text_session_prompt(
+            &provider.build_prompt(&AnalysisContext::default(), &[]),
+            "$ cargo build\nerror[E0308]: mismatched types",
+            provider.prompt_sanitization,
+        )
+        .unwrap();
+        assert_eq!(message["content"], expected);
+    }
+}
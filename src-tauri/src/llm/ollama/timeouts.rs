@@ -0,0 +1,153 @@
+// Ollama 超时 - 区分连接失败与等待模型返回超时
+//
+// 连接超时设置在 HTTP 客户端上，读取超时按请求设置；流式响应限制的是两次输出之间的间隔
+
+use super::OllamaProvider;
+use anyhow::Result;
+use reqwest::header::HeaderMap;
+use reqwest::Client;
+
+/// 无法连接 Ollama 服务（连接被拒绝或超时），请求未到达服务端
+///
+/// 调用方可通过 `downcast_ref::<OllamaUnreachable>()` 识别并提示检查服务
+#[derive(Clone, Debug, PartialEq)]
+pub struct OllamaUnreachable {
+    pub base_url: String,
+    pub connect_timeout_secs: u64,
+}
+
+impl std::fmt::Display for OllamaUnreachable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "无法连接 Ollama 服务 {}（{} 秒内未能建立连接），请确认 ollama serve 已启动且地址正确",
+            self.base_url, self.connect_timeout_secs
+        )
+    }
+}
+
+impl std::error::Error for OllamaUnreachable {}
+
+/// 已连接 Ollama，但模型在读取超时内没有返回结果
+///
+/// 调用方可通过 `downcast_ref::<OllamaReadTimeout>()` 识别并提示调大超时或减少帧数
+#[derive(Clone, Debug, PartialEq)]
+pub struct OllamaReadTimeout {
+    pub read_timeout_secs: u64,
+}
+
+impl std::fmt::Display for OllamaReadTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Ollama 模型 {} 秒内没有返回结果，可能仍在推理；可调大 read_timeout_secs 或减少分析帧数",
+            self.read_timeout_secs
+        )
+    }
+}
+
+impl std::error::Error for OllamaReadTimeout {}
+
+impl OllamaProvider {
+    /// 创建带连接超时与附加请求头的 HTTP 客户端（reqwest 只支持在客户端级别设置连接超时）
+    pub(super) fn build_client(connect_timeout_secs: u64, headers: HeaderMap) -> Result<Client> {
+        Ok(Client::builder()
+            .connect_timeout(std::time::Duration::from_secs(connect_timeout_secs))
+            .pool_max_idle_per_host(10)
+            .default_headers(headers)
+            .build()?)
+    }
+
+    pub(super) fn read_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.read_timeout_secs)
+    }
+
+    /// 区分连接失败与读取超时，便于界面给出对应的建议
+    pub(super) fn map_request_error(&self, e: reqwest::Error) -> anyhow::Error {
+        if e.is_connect() {
+            OllamaUnreachable {
+                base_url: self.base_url.clone(),
+                connect_timeout_secs: self.connect_timeout_secs,
+            }
+            .into()
+        } else if e.is_timeout() {
+            self.read_timeout_error()
+        } else {
+            e.into()
+        }
+    }
+
+    pub(super) fn read_timeout_error(&self) -> anyhow::Error {
+        OllamaReadTimeout {
+            read_timeout_secs: self.read_timeout_secs,
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OllamaReadTimeout, OllamaUnreachable};
+    use crate::llm::ollama::OllamaProvider;
+    use crate::llm::plugin::LLMProvider;
+    use reqwest::Client;
+
+    #[tokio::test]
+    async fn test_connect_and_read_timeouts_map_to_distinct_errors() {
+        // 端口已关闭：连接失败
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed_url = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+        let mut provider = OllamaProvider::new(Client::new());
+        provider
+            .configure(serde_json::json!({
+                "base_url": closed_url,
+                "connect_timeout_secs": 1,
+                "read_timeout_secs": 1,
+            }))
+            .unwrap();
+        let err = provider
+            .analyze_text("写代码".to_string())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<OllamaUnreachable>(),
+            Some(&OllamaUnreachable {
+                base_url: closed_url,
+                connect_timeout_secs: 1,
+            })
+        );
+
+        // 接受连接但不响应：读取超时（非流式与流式）
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let silent_url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+        for stream in [false, true] {
+            provider
+                .configure(serde_json::json!({
+                    "base_url": silent_url,
+                    "connect_timeout_secs": 5,
+                    "read_timeout_secs": 1,
+                    "stream": stream,
+                }))
+                .unwrap();
+            let err = provider
+                .analyze_text("写代码".to_string())
+                .await
+                .unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<OllamaReadTimeout>(),
+                Some(&OllamaReadTimeout {
+                    read_timeout_secs: 1
+                }),
+                "stream={stream}: {err}"
+            );
+        }
+        server.abort();
+    }
+}
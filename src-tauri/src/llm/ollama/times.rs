@@ -0,0 +1,143 @@
+// Ollama 会话时间 - 决定摘要最终使用的开始/结束时间
+//
+// 模型看不到真实时间，给出的 start_time/end_time 往往是编造的；默认只用调用方提供的会话时间
+
+use super::OllamaProvider;
+use chrono::{DateTime, Utc};
+use tracing::{debug, warn};
+
+impl OllamaProvider {
+    /// 决定最终使用的会话时间
+    ///
+    /// 优先使用调用方提供的真实会话时间；仅在 trust_model_times 开启时采信模型给出的时间，
+    /// 且顺序颠倒时自动交换、超出真实会话范围时视为幻觉而丢弃
+    pub(super) fn resolve_times(
+        &self,
+        model_times: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    ) -> (DateTime<Utc>, DateTime<Utc>) {
+        let window = self.session_window_start.zip(self.session_window_end);
+        let fallback = || {
+            window.unwrap_or_else(|| {
+                let now = Utc::now();
+                (now, now)
+            })
+        };
+
+        let Some((start, end)) = model_times else {
+            return fallback();
+        };
+        if !self.trust_model_times {
+            debug!("Ollama: 未开启 trust_model_times，忽略模型给出的时间");
+            return fallback();
+        }
+
+        let (start, end) = if start > end {
+            warn!("Ollama: 模型给出的开始时间晚于结束时间，已交换");
+            (end, start)
+        } else {
+            (start, end)
+        };
+        if let Some((window_start, window_end)) = window {
+            if start < window_start || end > window_end {
+                warn!(
+                    "Ollama: 模型给出的时间 {} - {} 超出会话范围，使用会话时间",
+                    start, end
+                );
+                return (window_start, window_end);
+            }
+        }
+        (start, end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::plugin::LLMProvider;
+    use reqwest::Client;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_tag_time_ranges_are_optional_and_clamped() {
+        let mut provider = OllamaProvider::new(Client::new());
+        provider.set_session_window(
+            Some(utc("2025-01-01T09:00:00Z")),
+            Some(utc("2025-01-01T09:10:00Z")),
+        );
+
+        let raw = r#"{"title":"a","summary":"b","tags":[{"category":"work","confidence":0.9,"keywords":[]}]}"#;
+        let summary = provider.parse_session_summary(raw).unwrap();
+        assert!(summary.tags[0].time_ranges.is_empty());
+
+        // 越界的截断到 10:00，完全越界、颠倒或无法解析的丢弃，其余按开始时间排序
+        let raw = r#"{"title":"a","summary":"b","tags":[{"category":"communication","confidence":0.5,"keywords":[],
+            "time_ranges":[{"start":"08:00","end":"12:00"},{"start":"01:00","end":"02:30"},
+            {"start":"11:00","end":"12:00"},{"start":"05:00","end":"04:00"},{"start":"开头","end":"03:00"}]}]}"#;
+        let summary = provider.parse_session_summary(raw).unwrap();
+        let ranges: Vec<(&str, &str)> = summary.tags[0]
+            .time_ranges
+            .iter()
+            .map(|r| (r.start.as_str(), r.end.as_str()))
+            .collect();
+        assert_eq!(ranges, vec![("01:00", "02:30"), ("08:00", "10:00")]);
+    }
+
+    #[test]
+    fn test_times_prefer_session_window() {
+        let mut provider = OllamaProvider::new(Client::new());
+        provider.set_session_window(
+            Some(utc("2025-01-01T09:00:00Z")),
+            Some(utc("2025-01-01T09:10:00Z")),
+        );
+
+        // 模型未给出时间：使用会话时间而非 now
+        let summary = provider
+            .parse_session_summary(r#"{"title":"a","summary":"b"}"#)
+            .unwrap();
+        assert_eq!(summary.start_time, utc("2025-01-01T09:00:00Z"));
+        assert_eq!(summary.end_time, utc("2025-01-01T09:10:00Z"));
+
+        // 默认不采信模型给出的时间
+        let raw = r#"{"title":"a","summary":"b","start_time":"2025-01-01T09:02:00Z","end_time":"2025-01-01T09:05:00Z"}"#;
+        let summary = provider.parse_session_summary(raw).unwrap();
+        assert_eq!(summary.start_time, utc("2025-01-01T09:00:00Z"));
+    }
+
+    #[test]
+    fn test_trusted_swapped_times_are_reordered() {
+        let mut provider = OllamaProvider::new(Client::new());
+        provider.trust_model_times = true;
+        provider.set_session_window(
+            Some(utc("2025-01-01T09:00:00Z")),
+            Some(utc("2025-01-01T09:10:00Z")),
+        );
+
+        let raw = r#"{"title":"a","summary":"b","start_time":"2025-01-01T09:05:00Z","end_time":"2025-01-01T09:02:00Z"}"#;
+        let summary = provider.parse_session_summary(raw).unwrap();
+        assert_eq!(summary.start_time, utc("2025-01-01T09:02:00Z"));
+        assert_eq!(summary.end_time, utc("2025-01-01T09:05:00Z"));
+    }
+
+    #[test]
+    fn test_trusted_hallucinated_times_fall_back() {
+        let mut provider = OllamaProvider::new(Client::new());
+        provider.trust_model_times = true;
+        provider.set_session_window(
+            Some(utc("2025-01-01T09:00:00Z")),
+            Some(utc("2025-01-01T09:10:00Z")),
+        );
+
+        // 超出会话范围的时间视为幻觉
+        let raw = r#"{"title":"a","summary":"b","start_time":"2023-06-01T00:00:00Z","end_time":"2023-06-01T01:00:00Z"}"#;
+        let summary = provider.parse_session_summary(raw).unwrap();
+        assert_eq!(summary.start_time, utc("2025-01-01T09:00:00Z"));
+        assert_eq!(summary.end_time, utc("2025-01-01T09:10:00Z"));
+
+        // 无法解析的时间不会导致整体失败
+        let raw = r#"{"title":"a","summary":"b","start_time":"上午九点","end_time":"十点"}"#;
+        assert!(provider.parse_session_summary(raw).is_ok());
+    }
+}
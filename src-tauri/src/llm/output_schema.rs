@@ -0,0 +1,268 @@
+// 输出校验 - 反序列化前按 JSON Schema 校验模型输出
+//
+// 转换为 SessionSummary 只能发现字段缺失或类型无法转换，错误信息只有第一处问题。
+// 开启后先按内置的 Schema 校验提取出的 JSON，一次列出全部不符合的位置；
+// coerce 模式下先修正常见的类型偏差（"80" 写成字符串的数字、"true" 写成字符串的布尔值）再校验。
+// Schema 描述的是模型的原始输出：title、summary 之外的段落可以缺失（由解析器补默认值），也允许自定义字段
+
+use super::SchemaValidation;
+use serde_json::{json, Number, Value};
+use std::sync::OnceLock;
+
+/// 模型输出的 JSON Schema（与提示词中的示例对应）
+fn summary_schema() -> &'static Value {
+    static SCHEMA: OnceLock<Value> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        let score = json!({ "type": ["number", "null"] });
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "required": ["title", "summary"],
+            "properties": {
+                "title": { "type": "string" },
+                "summary": { "type": "string" },
+                "empty": { "type": "boolean" },
+                "start_time": { "type": "string" },
+                "end_time": { "type": "string" },
+                "tags": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["category", "confidence", "keywords"],
+                        "properties": {
+                            "category": { "type": "string" },
+                            "confidence": { "type": "number" },
+                            "keywords": { "type": "array", "items": { "type": "string" } },
+                            "subcategories": { "type": "array", "items": { "type": "string" } },
+                            "time_ranges": {
+                                "type": "array",
+                                "items": {
+                                    "type": "object",
+                                    "required": ["start", "end"],
+                                    "properties": {
+                                        "start": { "type": "string" },
+                                        "end": { "type": "string" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                },
+                "key_moments": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "required": ["time", "description", "importance"],
+                        "properties": {
+                            "time": { "type": "string" },
+                            "description": { "type": "string" },
+                            "importance": { "type": "integer", "minimum": 0, "maximum": 255 }
+                        }
+                    }
+                },
+                "productivity_score": score.clone(),
+                "focus_score": score
+            }
+        })
+    })
+}
+
+fn validator() -> &'static jsonschema::Validator {
+    static VALIDATOR: OnceLock<jsonschema::Validator> = OnceLock::new();
+    VALIDATOR.get_or_init(|| {
+        jsonschema::validator_for(summary_schema()).expect("内置的总结 JSON Schema 无效")
+    })
+}
+
+/// 模型输出不符合 JSON Schema
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaViolations {
+    /// 每处违规为“位置: 说明”，位置为 JSON Pointer
+    pub violations: Vec<String>,
+}
+
+impl std::fmt::Display for SchemaViolations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "模型输出不符合 JSON Schema（{} 处）",
+            self.violations.len()
+        )?;
+        for violation in &self.violations {
+            write!(f, "\n- {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for SchemaViolations {}
+
+/// 按 Schema 校验，返回全部违规
+pub fn validate(value: &Value) -> Result<(), SchemaViolations> {
+    let violations: Vec<String> = validator()
+        .iter_errors(value)
+        .map(|error| {
+            let path = error.instance_path.to_string();
+            let path = if path.is_empty() { "/" } else { path.as_str() };
+            format!("{}: {}", path, error)
+        })
+        .collect();
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(SchemaViolations { violations })
+    }
+}
+
+/// 修正 Schema 要求为数字或布尔值、实际却是字符串的字段，返回修正的位置
+pub fn coerce_types(value: &mut Value) -> Vec<String> {
+    let mut fixed = Vec::new();
+    coerce_at(value, summary_schema(), String::new(), &mut fixed);
+    fixed.sort();
+    fixed
+}
+
+/// Schema 的 type 是否允许该类型
+fn allows(schema: &Value, ty: &str) -> bool {
+    match schema.get("type") {
+        Some(Value::String(t)) => t == ty,
+        Some(Value::Array(types)) => types.iter().any(|t| t == ty),
+        _ => false,
+    }
+}
+
+fn coerce_at(value: &mut Value, schema: &Value, path: String, fixed: &mut Vec<String>) {
+    match value {
+        Value::String(text) if !allows(schema, "string") => {
+            if let Some(coerced) = coerce_string(text.trim(), schema) {
+                fixed.push(if path.is_empty() {
+                    "/".to_string()
+                } else {
+                    path
+                });
+                *value = coerced;
+            }
+        }
+        Value::Object(obj) => {
+            let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                return;
+            };
+            for (key, field) in obj.iter_mut() {
+                if let Some(sub) = properties.get(key) {
+                    coerce_at(field, sub, format!("{}/{}", path, key), fixed);
+                }
+            }
+        }
+        Value::Array(items) => {
+            let Some(sub) = schema.get("items") else {
+                return;
+            };
+            for (index, item) in items.iter_mut().enumerate() {
+                coerce_at(item, sub, format!("{}/{}", path, index), fixed);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 字符串按 Schema 允许的类型转换，无法转换时返回 None
+fn coerce_string(text: &str, schema: &Value) -> Option<Value> {
+    // 整数形式的文本保持为整数（"80" → 80），与模型直接输出数字时一致
+    if allows(schema, "integer") || allows(schema, "number") {
+        if let Ok(n) = text.parse::<i64>() {
+            return Some(Value::from(n));
+        }
+    }
+    if allows(schema, "number") {
+        if let Some(n) = text.parse::<f64>().ok().and_then(Number::from_f64) {
+            return Some(Value::Number(n));
+        }
+    }
+    if allows(schema, "boolean") {
+        match text.to_lowercase().as_str() {
+            "true" => return Some(Value::Bool(true)),
+            "false" => return Some(Value::Bool(false)),
+            _ => {}
+        }
+    }
+    if allows(schema, "null") && text.eq_ignore_ascii_case("null") {
+        return Some(Value::Null);
+    }
+    None
+}
+
+/// 按配置校验（必要时先修正类型），返回修正的位置；关闭时不做任何处理
+pub fn check(value: &mut Value, mode: SchemaValidation) -> Result<Vec<String>, SchemaViolations> {
+    let fixed = match mode {
+        SchemaValidation::Off => return Ok(Vec::new()),
+        SchemaValidation::Coerce => coerce_types(value),
+        SchemaValidation::Strict => Vec::new(),
+    };
+    validate(value)?;
+    Ok(fixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary() -> Value {
+        json!({
+            "title": "写代码",
+            "summary": "实现解析器",
+            "tags": [{"category": "work", "confidence": "0.9", "keywords": ["rust"]}],
+            "key_moments": [{"time": "01:00", "description": "提交", "importance": "3"}],
+            "productivity_score": "80",
+            "focus_score": null,
+            "reviewer": "自定义字段"
+        })
+    }
+
+    #[test]
+    fn test_strict_mode_lists_each_violation() {
+        let mut value = summary();
+        let err = check(&mut value, SchemaValidation::Strict).unwrap_err();
+        assert_eq!(err.violations.len(), 3);
+        for path in [
+            "/tags/0/confidence",
+            "/key_moments/0/importance",
+            "/productivity_score",
+        ] {
+            assert!(err
+                .violations
+                .iter()
+                .any(|v| v.starts_with(&format!("{}: ", path))));
+        }
+        assert!(err.to_string().contains("（3 处）"));
+
+        let mut missing = json!({"title": 1});
+        let err = validate(&missing).unwrap_err();
+        assert_eq!(err.violations.len(), 2);
+        // 字符串字段不做转换
+        assert!(coerce_types(&mut missing).is_empty());
+        assert_eq!(check(&mut missing, SchemaValidation::Off), Ok(Vec::new()));
+    }
+
+    #[test]
+    fn test_coerce_mode_fixes_numeric_strings() {
+        let mut value = summary();
+        let fixed = check(&mut value, SchemaValidation::Coerce).unwrap();
+        assert_eq!(
+            fixed,
+            vec![
+                "/key_moments/0/importance",
+                "/productivity_score",
+                "/tags/0/confidence"
+            ]
+        );
+        assert_eq!(value["productivity_score"], json!(80));
+        assert_eq!(value["key_moments"][0]["importance"], json!(3));
+        assert_eq!(value["reviewer"], "自定义字段");
+
+        // 无法转换的字符串仍报告为违规
+        let mut value = json!({"title": "t", "summary": "s", "focus_score": "很高"});
+        let err = check(&mut value, SchemaValidation::Coerce).unwrap_err();
+        assert_eq!(err.violations.len(), 1);
+        assert!(err.violations[0].starts_with("/focus_score: "));
+    }
+}
@@ -8,6 +8,7 @@ use serde::{
     Deserialize, Serialize,
 };
 use serde_json::{self, Value};
+use std::collections::HashMap;
 use std::mem;
 
 /// 活动标签
@@ -19,10 +20,33 @@ pub struct ActivityTag {
     pub confidence: f32,
     /// 关键词
     pub keywords: Vec<String>,
+    /// 分类体系中 category 之下的子类别路径（如 ["coding", "frontend"]），未细分时为空
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subcategories: Vec<String>,
+    /// 该活动出现的时间段（可选，旧模型或旧数据中缺失时为空）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub time_ranges: Vec<TagTimeRange>,
+}
+
+impl ActivityTag {
+    /// 完整的类别路径（如 work/coding/frontend），没有子类别时为顶层类别名
+    pub fn path(&self) -> String {
+        std::iter::once(self.category.as_str())
+            .chain(self.subcategories.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// 标签的时间段：相对会话开始的 MM:SS / HH:MM:SS
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TagTimeRange {
+    pub start: String,
+    pub end: String,
 }
 
 /// 活动类别（精简为6类，便于人工和AI标注）
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ActivityCategory {
     Work,          // 工作（编程、写作、设计、数据分析、会议、规划等）
@@ -449,6 +473,246 @@ mod tests {
         assert!(app_sites.secondary.is_none());
     }
 
+    #[test]
+    fn test_pricing_estimate_and_override() {
+        let mut pricing = ProviderPricing {
+            currency: "USD".to_string(),
+            per_image: 0.0,
+            per_1k_input_tokens: 0.003,
+            tokens_per_image: 1000,
+            prompt_tokens: 2000,
+        };
+
+        let estimate = pricing.estimate(10);
+        assert_eq!(estimate.image_count, 10);
+        assert_eq!(estimate.estimated_input_tokens, 12000);
+        assert!((estimate.amount - 0.036).abs() < 1e-9);
+
+        // 只覆盖给出的字段
+        pricing.apply_config(&json!({"pricing": {"per_image": 0.01}}));
+        assert!((pricing.per_image - 0.01).abs() < 1e-9);
+        assert_eq!(pricing.tokens_per_image, 1000);
+
+        // 无效配置被忽略
+        pricing.apply_config(&json!({"pricing": {"tokens_per_image": "many"}}));
+        assert_eq!(pricing.tokens_per_image, 1000);
+    }
+
+    #[test]
+    fn test_build_text_session_prompt() {
+        use crate::llm::sanitize::SanitizeLevel;
+
+        let level = SanitizeLevel::Standard;
+        let prompt = build_text_session_prompt("返回 JSON", "  $ cargo build\n  ", level).unwrap();
+        assert!(prompt.contains("返回 JSON"));
+        assert!(prompt.contains("<session_text>\n> $ cargo build\n</session_text>"));
+
+        let long = "字".repeat(MAX_SESSION_TEXT_CHARS + 10);
+        let prompt = build_text_session_prompt("返回 JSON", &long, level).unwrap();
+        assert!(prompt.contains("（文本过长，已截断）"));
+        assert!(!prompt.contains(&"字".repeat(MAX_SESSION_TEXT_CHARS + 1)));
+
+        assert!(build_text_session_prompt("返回 JSON", " \n ", level).is_err());
+    }
+
+    #[test]
+    fn test_collect_candidates_dedupes_and_tolerates_failures() {
+        let with_seed = |seed: u64, title: &str| SessionSummary {
+            title: title.to_string(),
+            sampling_seed: Some(seed),
+            temperature: Some(0.5),
+            ..valid_summary()
+        };
+        let results = vec![
+            Ok(with_seed(1, "编写代码")),
+            Err(anyhow::anyhow!("超时")),
+            Ok(with_seed(2, "编写代码")),
+            Ok(with_seed(3, "代码评审")),
+        ];
+        let candidates = collect_candidates(results).unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(candidates[0].sampling_seed, Some(1));
+        assert_eq!(candidates[1].title, "代码评审");
+
+        let all_failed = vec![Err(anyhow::anyhow!("超时")), Err(anyhow::anyhow!("断开"))];
+        let err = collect_candidates(all_failed).unwrap_err();
+        assert_eq!(err.to_string(), "超时");
+    }
+
+    fn valid_summary() -> SessionSummary {
+        SessionSummary::builder()
+            .title("编写代码")
+            .summary("实现新功能")
+            .tag(ActivityTag {
+                category: ActivityCategory::Work,
+                confidence: 0.9,
+                keywords: vec!["rust".to_string()],
+                subcategories: Vec::new(),
+                time_ranges: Vec::new(),
+            })
+            .time_range(
+                "2025-01-01T09:00:00Z".parse().unwrap(),
+                "2025-01-01T09:10:00Z".parse().unwrap(),
+            )
+            .key_moment("03:15", "提交代码", 3)
+            .scores(Some(80.0), Some(70.0))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_validate_accepts_valid_summary() {
+        assert!(valid_summary().validate().is_ok());
+        assert!(SessionSummary::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_builder_defaults_pass_validation() {
+        let summary = SessionSummary::builder().build().unwrap();
+        assert!(summary.validate().is_ok());
+        assert!(summary.tags.is_empty() && summary.key_moments.is_empty());
+        assert_eq!(summary.start_time, summary.end_time);
+        assert!(summary.productivity_score.is_none() && summary.focus_score.is_none());
+
+        // build() 一次返回全部违规项，build_unchecked() 原样返回
+        let start: DateTime<Utc> = "2025-01-01T09:00:00Z".parse().unwrap();
+        let invalid = SessionSummary::builder()
+            .time_range(start, start - chrono::Duration::minutes(1))
+            .key_moment("上午九点", "开会", 9)
+            .scores(Some(120.0), None);
+        assert_eq!(invalid.clone().build().unwrap_err().len(), 4);
+        assert_eq!(invalid.build_unchecked().productivity_score, Some(120.0));
+    }
+
+    #[test]
+    fn test_validate_score_range() {
+        let mut summary = valid_summary();
+        summary.productivity_score = Some(120.0);
+        summary.focus_score = Some(f32::NAN);
+        let errors = summary.validate().unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0],
+            ValidationError::ScoreOutOfRange {
+                field: "productivity_score",
+                ..
+            }
+        ));
+        assert!(matches!(
+            errors[1],
+            ValidationError::ScoreOutOfRange {
+                field: "focus_score",
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_validate_tags() {
+        let mut summary = valid_summary();
+        summary.tags.push(ActivityTag {
+            category: ActivityCategory::Work,
+            confidence: 1.5,
+            keywords: vec![],
+            subcategories: Vec::new(),
+            time_ranges: Vec::new(),
+        });
+        let errors = summary.validate().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                ValidationError::ConfidenceOutOfRange {
+                    tag_index: 1,
+                    value: 1.5
+                },
+                ValidationError::DuplicateCategory {
+                    category: ActivityCategory::Work
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_key_moments() {
+        let mut summary = valid_summary();
+        for time in ["1:02:03", "2025-01-01T09:05:00+08:00"] {
+            summary.key_moments[0].time = time.to_string();
+            assert!(summary.validate().is_ok(), "{time} 应为合法时间");
+        }
+        for time in ["12:75", "上午九点", "1:2:3:4", ""] {
+            summary.key_moments[0].time = time.to_string();
+            assert!(summary.validate().is_err(), "{time} 应为非法时间");
+        }
+
+        let mut summary = valid_summary();
+        summary.key_moments[0].importance = 0;
+        assert_eq!(
+            summary.validate().unwrap_err(),
+            vec![ValidationError::ImportanceOutOfRange {
+                moment_index: 0,
+                value: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn test_validate_time_order_and_collects_all() {
+        let mut summary = valid_summary();
+        std::mem::swap(&mut summary.start_time, &mut summary.end_time);
+        summary.focus_score = Some(-1.0);
+        summary.key_moments[0].time = "bad".to_string();
+
+        // 一次返回全部违规项
+        let errors = summary.validate().unwrap_err();
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(
+            errors.last(),
+            Some(ValidationError::StartAfterEnd { .. })
+        ));
+    }
+
+    #[test]
+    fn test_refine_prompt_requests_only_the_field() {
+        let prompt = build_refine_prompt(&valid_summary(), SummaryField::Scores).unwrap();
+        assert!(prompt.contains("实现新功能"));
+        assert!(prompt.contains("productivity_score、focus_score"));
+        assert!(prompt.ends_with(SummaryField::Scores.example()));
+    }
+
+    #[test]
+    fn test_merge_refined_field_keeps_other_fields() {
+        let current = valid_summary();
+        // 模型多输出的字段被忽略
+        let merged = merge_refined_field(
+            &current,
+            SummaryField::Tags,
+            r#"{"title": "被忽略", "tags": [{"category": "communication", "confidence": 0.8, "keywords": ["Slack"]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(merged.title, current.title);
+        assert_eq!(merged.tags.len(), 1);
+        assert_eq!(merged.tags[0].category, ActivityCategory::Communication);
+        assert_eq!(merged.productivity_score, current.productivity_score);
+
+        // 缺少请求的字段
+        assert!(
+            merge_refined_field(&current, SummaryField::Scores, r#"{"focus_score": 60}"#).is_err()
+        );
+        // 合并后违规
+        assert!(merge_refined_field(
+            &current,
+            SummaryField::KeyMoments,
+            r#"{"key_moments": [{"time": "12:75", "description": "x", "importance": 3}]}"#,
+        )
+        .is_err());
+
+        // 其他字段的既有违规项不阻止合并
+        let mut broken = valid_summary();
+        broken.focus_score = Some(150.0);
+        let merged = merge_refined_field(&broken, SummaryField::Tags, r#"{"tags": []}"#).unwrap();
+        assert!(merged.tags.is_empty());
+    }
+
     #[test]
     fn test_parse_string_distraction() {
         let json = r#"[
@@ -479,6 +743,18 @@ mod tests {
 }
 
 impl ActivityCategory {
+    /// 序列化时使用的类别名
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Work => "work",
+            Self::Communication => "communication",
+            Self::Learning => "learning",
+            Self::Personal => "personal",
+            Self::Idle => "idle",
+            Self::Other => "other",
+        }
+    }
+
     /// 获取类别的中文名称
     pub fn to_chinese(&self) -> &str {
         match self {
@@ -547,6 +823,108 @@ pub struct SessionSummary {
     pub productivity_score: Option<f32>,
     /// 专注度评分（0-100）
     pub focus_score: Option<f32>,
+    /// 实际生效的帧采样种子（仅随机采样时记录，用于复现分析输入）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling_seed: Option<u64>,
+    /// 生成该总结时使用的采样温度（仅多候选生成时记录）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    /// 标题与摘要的语言（ISO 639-1，如 zh、en），用于按语言筛选与全文检索分词
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// 摘要由标签与关键时刻自动推导（模型返回的摘要为空）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub summary_auto_derived: bool,
+    /// 标题、摘要等文本中的敏感内容已按脱敏规则遮盖
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub redacted: bool,
+    /// 会话中没有有意义的活动（模型标记为空或本地判定为空闲），界面可折叠显示
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub idle: bool,
+    /// 流式输出中断，总结由已接收的部分内容修复得到（可能缺少结尾的段落）
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub recovered_stream: bool,
+    /// 整体置信度低于配置的阈值（模型可能在猜测），总结照常保存，待换模型重新分析或人工复核
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub low_confidence: bool,
+    /// 实际发送给模型的帧（开启记录采样帧时由 provider 填写）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_selection: Option<FrameSelection>,
+    /// 模型在 JSON 之外输出的思考过程（开启 capture_reasoning 时由 provider 填写）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+    /// 实际生成该总结的 provider（当前 provider 因配额、限流或欠费失败时可能是后备 provider）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub served_by: Option<String>,
+    /// 配置的自定义字段（字段名 -> 字符串或字符串数组），字段名不会与内置字段重复
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extra: HashMap<String, Value>,
+}
+
+/// 总结的内置字段名（含模型输出中的约定字段），自定义字段不能使用
+pub const BUILTIN_SUMMARY_FIELDS: [&str; 21] = [
+    "title",
+    "summary",
+    "tags",
+    "start_time",
+    "end_time",
+    "key_moments",
+    "productivity_score",
+    "focus_score",
+    "sampling_seed",
+    "temperature",
+    "language",
+    "summary_auto_derived",
+    "redacted",
+    "idle",
+    "recovered_stream",
+    "low_confidence",
+    "frame_selection",
+    "reasoning",
+    "served_by",
+    "extra",
+    "empty",
+];
+
+/// 一次分析实际使用的帧：采样、编码与请求体裁剪之后留下的帧
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameSelection {
+    /// 输入帧总数
+    pub total_frames: usize,
+    /// 按时间顺序排列的所选帧
+    pub frames: Vec<SampledFrame>,
+}
+
+/// 所选帧：在输入帧中的序号（从 0 开始）与文件路径
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SampledFrame {
+    pub index: usize,
+    pub path: String,
+}
+
+impl FrameSelection {
+    /// 按路径在输入帧中查找所选帧的序号；不在输入帧中的路径忽略
+    pub fn from_paths(frames: &[String], selected: &[String]) -> Self {
+        let index_of: std::collections::HashMap<&str, usize> = frames
+            .iter()
+            .enumerate()
+            .map(|(index, path)| (path.as_str(), index))
+            .collect();
+        let mut selected: Vec<SampledFrame> = selected
+            .iter()
+            .filter_map(|path| {
+                index_of.get(path.as_str()).map(|&index| SampledFrame {
+                    index,
+                    path: path.clone(),
+                })
+            })
+            .collect();
+        selected.sort_by_key(|frame| frame.index);
+        Self {
+            total_frames: frames.len(),
+            frames: selected,
+        }
+    }
 }
 
 impl Default for SessionSummary {
@@ -561,10 +939,602 @@ impl Default for SessionSummary {
             key_moments: vec![],
             productivity_score: None,
             focus_score: None,
+            sampling_seed: None,
+            temperature: None,
+            language: None,
+            summary_auto_derived: false,
+            redacted: false,
+            idle: false,
+            recovered_stream: false,
+            low_confidence: false,
+            frame_selection: None,
+            reasoning: None,
+            served_by: None,
+            extra: HashMap::new(),
         }
     }
 }
 
+/// 推导摘要时最多列出的关键词数
+const DERIVED_SUMMARY_KEYWORDS: usize = 3;
+/// 推导摘要时最多列出的关键时刻数
+const DERIVED_SUMMARY_MOMENTS: usize = 2;
+
+/// 由标签与关键时刻拼出摘要，如 "工作、沟通会话，涉及 VS Code、Slack。关键时刻：提交代码"
+fn derive_summary(tags: &[ActivityTag], key_moments: &[KeyMoment]) -> Option<String> {
+    // 按置信度从高到低排列类别与关键词
+    let mut tags: Vec<&ActivityTag> = tags.iter().collect();
+    tags.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    let mut categories: Vec<&str> = Vec::new();
+    let mut keywords: Vec<&str> = Vec::new();
+    for tag in &tags {
+        let category = tag.category.to_chinese();
+        if !categories.contains(&category) {
+            categories.push(category);
+        }
+        for keyword in tag.keywords.iter().map(|k| k.trim()) {
+            if !keyword.is_empty() && !keywords.contains(&keyword) {
+                keywords.push(keyword);
+            }
+        }
+    }
+    keywords.truncate(DERIVED_SUMMARY_KEYWORDS);
+
+    // 关键时刻按重要性从高到低，重要性相同时保持原有顺序
+    let mut moments: Vec<&KeyMoment> = key_moments
+        .iter()
+        .filter(|m| !m.description.trim().is_empty())
+        .collect();
+    moments.sort_by_key(|m| std::cmp::Reverse(m.importance));
+    let moments: Vec<&str> = moments
+        .iter()
+        .take(DERIVED_SUMMARY_MOMENTS)
+        .map(|m| m.description.trim())
+        .collect();
+
+    let mut parts = Vec::new();
+    if !categories.is_empty() {
+        let mut sentence = format!("{}会话", categories.join("、"));
+        if !keywords.is_empty() {
+            sentence.push_str(&format!("，涉及 {}", keywords.join("、")));
+        }
+        parts.push(sentence);
+    }
+    if !moments.is_empty() {
+        parts.push(format!("关键时刻：{}", moments.join("；")));
+    }
+    (!parts.is_empty()).then(|| parts.join("。"))
+}
+
+/// SessionSummary 校验失败项
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationError {
+    /// 评分不在 0-100 范围内
+    ScoreOutOfRange { field: &'static str, value: f32 },
+    /// 标签置信度不在 0-1 范围内
+    ConfidenceOutOfRange { tag_index: usize, value: f32 },
+    /// 同一类别在标签中重复出现
+    DuplicateCategory { category: ActivityCategory },
+    /// 关键时刻的时间无法识别（支持 MM:SS、HH:MM:SS、RFC3339）
+    InvalidMomentTime { moment_index: usize, time: String },
+    /// 关键时刻重要性不在 1-5 范围内
+    ImportanceOutOfRange { moment_index: usize, value: u8 },
+    /// 开始时间晚于结束时间
+    StartAfterEnd {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ScoreOutOfRange { field, value } => {
+                write!(f, "{} 超出 0-100 范围: {}", field, value)
+            }
+            Self::ConfidenceOutOfRange { tag_index, value } => {
+                write!(f, "tags[{}].confidence 超出 0-1 范围: {}", tag_index, value)
+            }
+            Self::DuplicateCategory { category } => {
+                write!(f, "类别重复: {}", category.to_chinese())
+            }
+            Self::InvalidMomentTime { moment_index, time } => {
+                write!(f, "key_moments[{}].time 格式无效: {}", moment_index, time)
+            }
+            Self::ImportanceOutOfRange {
+                moment_index,
+                value,
+            } => {
+                write!(
+                    f,
+                    "key_moments[{}].importance 超出 1-5 范围: {}",
+                    moment_index, value
+                )
+            }
+            Self::StartAfterEnd { start, end } => {
+                write!(f, "开始时间 {} 晚于结束时间 {}", start, end)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// 输入帧数低于配置的最小分析帧数，未调用模型
+///
+/// 调用方可通过 `downcast_ref::<TooFewFrames>()` 识别并静默跳过该会话
+#[derive(Clone, Debug, PartialEq)]
+pub struct TooFewFrames {
+    pub frames: usize,
+    pub min_frames: usize,
+}
+
+impl std::fmt::Display for TooFewFrames {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "帧数过少（{} 帧，至少需要 {} 帧），跳过分析",
+            self.frames, self.min_frames
+        )
+    }
+}
+
+impl std::error::Error for TooFewFrames {}
+
+/// 帧文件大量缺失（已清理或移动），剩余帧数低于最小分析帧数，未调用模型
+///
+/// 与 TooFewFrames 不同，会话本身的帧数足够，需要检查帧目录后重新分析
+#[derive(Clone, Debug, PartialEq)]
+pub struct FramesUnavailable {
+    pub total: usize,
+    pub missing: usize,
+    pub min_frames: usize,
+}
+
+impl std::fmt::Display for FramesUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{} 帧文件已不存在，剩余 {} 帧少于所需的 {} 帧，请检查帧目录是否被清理或移动",
+            self.missing,
+            self.total,
+            self.total - self.missing,
+            self.min_frames
+        )
+    }
+}
+
+impl std::error::Error for FramesUnavailable {}
+
+/// 模型请求成功但返回的内容为空（或只有空白），重试后仍然为空
+///
+/// 与 JSON 解析失败区分开，调用方可通过 `downcast_ref::<EmptyResponse>()` 识别
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmptyResponse {
+    /// 包含重试在内的请求次数
+    pub attempts: u32,
+}
+
+impl std::fmt::Display for EmptyResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "模型连续 {} 次返回空内容", self.attempts)
+    }
+}
+
+impl std::error::Error for EmptyResponse {}
+
+impl SessionSummary {
+    /// 摘要为空时由标签与关键时刻推导一段简短摘要，返回是否进行了推导
+    ///
+    /// 标签与关键时刻都为空时无从推导，保持原样
+    pub fn derive_missing_summary(&mut self) -> bool {
+        if !self.summary.trim().is_empty() {
+            return false;
+        }
+        let Some(derived) = derive_summary(&self.tags, &self.key_moments) else {
+            return false;
+        };
+        self.summary = derived;
+        self.summary_auto_derived = true;
+        true
+    }
+
+    /// 将标签时间段限制在会话时长内，返回被调整或丢弃的时间段数量
+    ///
+    /// 无法解析、起止颠倒或完全落在会话之外的时间段丢弃；部分越界的截断到会话范围，
+    /// 同一标签的时间段按开始时间排序。会话时长未知（开始等于结束）时只做格式校验
+    pub fn clamp_tag_time_ranges(&mut self) -> usize {
+        use crate::time_mapper::TimeMapper;
+
+        let duration = (self.end_time > self.start_time).then(|| self.end_time - self.start_time);
+        let mut adjusted = 0;
+        for tag in &mut self.tags {
+            let mut ranges = Vec::with_capacity(tag.time_ranges.len());
+            for range in &tag.time_ranges {
+                let parsed = TimeMapper::parse_offset(range.start.trim())
+                    .zip(TimeMapper::parse_offset(range.end.trim()))
+                    .filter(|(s, e)| s < e && duration.is_none_or(|d| *s < d));
+                let Some((start, end)) = parsed else {
+                    adjusted += 1;
+                    continue;
+                };
+                let end = duration.map_or(end, |d| end.min(d));
+                let clamped = TagTimeRange {
+                    start: TimeMapper::format_offset(start),
+                    end: TimeMapper::format_offset(end),
+                };
+                if clamped != *range {
+                    adjusted += 1;
+                }
+                ranges.push((start, clamped));
+            }
+            ranges.sort_by_key(|(start, _)| *start);
+            tag.time_ranges = ranges.into_iter().map(|(_, range)| range).collect();
+        }
+        adjusted
+    }
+
+    /// 根据标题与摘要检测语言，置信度不足时使用配置的输出语言
+    pub fn detect_language(&mut self, fallback: Option<&str>) {
+        let text = format!("{}\n{}", self.title, self.summary);
+        self.language = super::language::resolve_language(&text, fallback);
+    }
+
+    /// 校验当前版本的不变量，一次返回全部违规项（便于批量修复旧版本或导入的数据）
+    ///
+    /// 类别取值本身由 ActivityCategory 反序列化保证，这里只检查重复
+    pub fn validate(&self) -> std::result::Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+
+        for (field, score) in [
+            ("productivity_score", self.productivity_score),
+            ("focus_score", self.focus_score),
+        ] {
+            if let Some(value) = score.filter(|v| !(0.0..=100.0).contains(v)) {
+                errors.push(ValidationError::ScoreOutOfRange { field, value });
+            }
+        }
+
+        let mut seen: Vec<&ActivityCategory> = Vec::new();
+        for (tag_index, tag) in self.tags.iter().enumerate() {
+            if !(0.0..=1.0).contains(&tag.confidence) {
+                errors.push(ValidationError::ConfidenceOutOfRange {
+                    tag_index,
+                    value: tag.confidence,
+                });
+            }
+            if seen.contains(&&tag.category) {
+                errors.push(ValidationError::DuplicateCategory {
+                    category: tag.category.clone(),
+                });
+            } else {
+                seen.push(&tag.category);
+            }
+        }
+
+        for (moment_index, moment) in self.key_moments.iter().enumerate() {
+            if !is_valid_moment_time(&moment.time) {
+                errors.push(ValidationError::InvalidMomentTime {
+                    moment_index,
+                    time: moment.time.clone(),
+                });
+            }
+            if !(1..=5).contains(&moment.importance) {
+                errors.push(ValidationError::ImportanceOutOfRange {
+                    moment_index,
+                    value: moment.importance,
+                });
+            }
+        }
+
+        if self.start_time > self.end_time {
+            errors.push(ValidationError::StartAfterEnd {
+                start: self.start_time,
+                end: self.end_time,
+            });
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// SessionSummary 构造器
+///
+/// 未设置的字段取默认值：起止时间为当前时间、没有标签与关键时刻、不给评分；
+/// build() 按 SessionSummary::validate 校验，兜底与合成总结不必各自手写全部字段
+#[derive(Clone, Debug, Default)]
+pub struct SessionSummaryBuilder {
+    summary: SessionSummary,
+}
+
+impl SessionSummary {
+    pub fn builder() -> SessionSummaryBuilder {
+        SessionSummaryBuilder::default()
+    }
+}
+
+impl SessionSummaryBuilder {
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.summary.title = title.into();
+        self
+    }
+
+    pub fn summary(mut self, summary: impl Into<String>) -> Self {
+        self.summary.summary = summary.into();
+        self
+    }
+
+    /// 会话起止时间
+    pub fn time_range(mut self, start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        self.summary.start_time = start;
+        self.summary.end_time = end;
+        self
+    }
+
+    pub fn tag(mut self, tag: ActivityTag) -> Self {
+        self.summary.tags.push(tag);
+        self
+    }
+
+    pub fn tags(mut self, tags: Vec<ActivityTag>) -> Self {
+        self.summary.tags = tags;
+        self
+    }
+
+    pub fn key_moment(
+        mut self,
+        time: impl Into<String>,
+        description: impl Into<String>,
+        importance: u8,
+    ) -> Self {
+        self.summary.key_moments.push(KeyMoment {
+            time: time.into(),
+            description: description.into(),
+            importance,
+        });
+        self
+    }
+
+    pub fn key_moments(mut self, key_moments: Vec<KeyMoment>) -> Self {
+        self.summary.key_moments = key_moments;
+        self
+    }
+
+    /// 生产力与专注度评分（0-100）
+    pub fn scores(mut self, productivity: Option<f32>, focus: Option<f32>) -> Self {
+        self.summary.productivity_score = productivity;
+        self.summary.focus_score = focus;
+        self
+    }
+
+    pub fn language(mut self, language: impl Into<String>) -> Self {
+        self.summary.language = Some(language.into());
+        self
+    }
+
+    pub fn extra(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.summary.extra.insert(name.into(), value);
+        self
+    }
+
+    /// 校验后返回总结，违规时一次返回全部违规项
+    pub fn build(self) -> std::result::Result<SessionSummary, Vec<ValidationError>> {
+        self.summary.validate()?;
+        Ok(self.summary)
+    }
+
+    /// 不校验直接返回：内容来自模型或旧数据、由调用方稍后统一校验时使用
+    pub fn build_unchecked(self) -> SessionSummary {
+        self.summary
+    }
+}
+
+/// 关键时刻时间：相对时间 MM:SS / HH:MM:SS，或转换后的 RFC3339 绝对时间
+fn is_valid_moment_time(time: &str) -> bool {
+    DateTime::parse_from_rfc3339(time).is_ok()
+        || crate::time_mapper::TimeMapper::parse_offset(time).is_some()
+}
+
+/// 纯文本会话单次提交给模型的最大字符数
+pub(crate) const MAX_SESSION_TEXT_CHARS: usize = 20000;
+
+/// 构建纯文本会话的提示词
+///
+/// 沿用截图分析的提示词与 JSON 结构，只把图片换成文本记录（终端输出、剪贴板历史等）；
+/// 文本内容不可信，按 level 过滤后作为数据块拼入
+pub(crate) fn build_text_session_prompt(
+    summary_prompt: &str,
+    content: &str,
+    level: super::sanitize::SanitizeLevel,
+) -> Result<String> {
+    let content = content.trim();
+    if content.is_empty() {
+        return Err(anyhow::anyhow!("会话文本为空，无法分析"));
+    }
+
+    let mut truncated: String = content.chars().take(MAX_SESSION_TEXT_CHARS).collect();
+    if truncated.len() < content.len() {
+        truncated.push_str("\n（文本过长，已截断）");
+    }
+    let mut prompt = String::from(
+        "本次会话以文本形式记录（如终端输出、剪贴板历史），没有屏幕截图；下文提到的截图即指这段文本。\n\n",
+    );
+    prompt.push_str(summary_prompt);
+    prompt.push_str("\n\n");
+    prompt.push_str(&super::sanitize::wrap_untrusted_block(
+        "session_text",
+        &truncated,
+        level,
+    ));
+    Ok(prompt)
+}
+
+/// 单次最多生成的候选总结数
+pub const MAX_SUMMARY_CANDIDATES: usize = 8;
+
+/// 汇总多候选结果：丢弃失败的候选并去重（忽略种子与温度比较内容），全部失败时返回第一个错误
+pub(crate) fn collect_candidates(
+    results: Vec<Result<SessionSummary>>,
+) -> Result<Vec<SessionSummary>> {
+    let mut candidates: Vec<SessionSummary> = Vec::new();
+    let mut seen: Vec<Value> = Vec::new();
+    let mut first_error = None;
+
+    for (index, result) in results.into_iter().enumerate() {
+        let summary = match result {
+            Ok(summary) => summary,
+            Err(e) => {
+                tracing::warn!("第 {} 个候选总结生成失败: {}", index + 1, e);
+                first_error.get_or_insert(e);
+                continue;
+            }
+        };
+
+        let mut key = summary.clone();
+        key.sampling_seed = None;
+        key.temperature = None;
+        key.frame_selection = None;
+        let key = serde_json::to_value(&key)?;
+        if !seen.contains(&key) {
+            seen.push(key);
+            candidates.push(summary);
+        }
+    }
+
+    match first_error {
+        Some(e) if candidates.is_empty() => Err(e),
+        _ => Ok(candidates),
+    }
+}
+
+/// 可单独重新生成的总结字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SummaryField {
+    /// 活动标签
+    Tags,
+    /// 关键时刻
+    KeyMoments,
+    /// 生产力与专注度评分
+    Scores,
+}
+
+impl SummaryField {
+    /// 该字段在 JSON 中对应的键
+    pub fn keys(&self) -> &'static [&'static str] {
+        match self {
+            Self::Tags => &["tags"],
+            Self::KeyMoments => &["key_moments"],
+            Self::Scores => &["productivity_score", "focus_score"],
+        }
+    }
+
+    /// 提示词中的输出示例
+    fn example(&self) -> &'static str {
+        match self {
+            Self::Tags => {
+                r#"{"tags": [{"category": "work", "confidence": 0.9, "keywords": ["VS Code", "Rust"]}]}"#
+            }
+            Self::KeyMoments => {
+                r#"{"key_moments": [{"time": "05:30", "description": "提交代码", "importance": 4}]}"#
+            }
+            Self::Scores => r#"{"productivity_score": 80, "focus_score": 75}"#,
+        }
+    }
+
+    /// 校验失败项是否属于该字段
+    fn owns(&self, error: &ValidationError) -> bool {
+        matches!(
+            (self, error),
+            (Self::Scores, ValidationError::ScoreOutOfRange { .. })
+                | (Self::Tags, ValidationError::ConfidenceOutOfRange { .. })
+                | (Self::Tags, ValidationError::DuplicateCategory { .. })
+                | (Self::KeyMoments, ValidationError::InvalidMomentTime { .. })
+                | (
+                    Self::KeyMoments,
+                    ValidationError::ImportanceOutOfRange { .. }
+                )
+        )
+    }
+}
+
+/// 构建单字段重新生成的提示词：附上当前总结，只要求输出指定字段
+pub(crate) fn build_refine_prompt(current: &SessionSummary, field: SummaryField) -> Result<String> {
+    let mut prompt = String::from(
+        "下面是根据这些截图生成的会话总结，其中大部分内容已经准确，只有指定字段需要重新生成。\n\n",
+    );
+    prompt.push_str("当前总结：\n");
+    prompt.push_str(&serde_json::to_string_pretty(current)?);
+    prompt.push_str(&format!(
+        "\n\n请结合截图重新生成 {} 字段，其余字段保持不变、不要输出。\n",
+        field.keys().join("、")
+    ));
+    match field {
+        SummaryField::Tags => prompt.push_str(
+            "category 只能是 work、communication、learning、personal、idle、other 之一，\
+             同一类别只出现一次，confidence 取 0-1。\n",
+        ),
+        SummaryField::KeyMoments => {
+            prompt.push_str("time 使用视频内的相对时间 MM:SS 或 HH:MM:SS，importance 取 1-5。\n")
+        }
+        SummaryField::Scores => prompt.push_str("评分取 0-100。\n"),
+    }
+    prompt.push_str("只输出如下格式的 JSON，不要输出其他内容：\n");
+    prompt.push_str(field.example());
+    Ok(prompt)
+}
+
+/// 解析单字段输出并合并回当前总结
+///
+/// 只采用指定字段（模型多输出的字段忽略），合并后该字段存在违规项时拒绝合并
+pub(crate) fn merge_refined_field(
+    current: &SessionSummary,
+    field: SummaryField,
+    json_text: &str,
+) -> Result<SessionSummary> {
+    let value: Value = serde_json::from_str(json_text)
+        .map_err(|e| anyhow::anyhow!("模型返回不是合法 JSON: {e}; raw={}", json_text))?;
+    let take = |key: &str| {
+        value
+            .get(key)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("模型返回缺少字段 {key}; raw={}", json_text))
+    };
+
+    let mut merged = current.clone();
+    match field {
+        SummaryField::Tags => merged.tags = serde_json::from_value(take("tags")?)?,
+        SummaryField::KeyMoments => {
+            merged.key_moments = serde_json::from_value(take("key_moments")?)?
+        }
+        SummaryField::Scores => {
+            merged.productivity_score = serde_json::from_value(take("productivity_score")?)?;
+            merged.focus_score = serde_json::from_value(take("focus_score")?)?;
+        }
+    }
+
+    // 其他字段的既有违规项与本次重新生成无关，不阻止合并
+    if let Err(violations) = merged.validate() {
+        let details: Vec<String> = violations
+            .iter()
+            .filter(|v| field.owns(v))
+            .map(|v| v.to_string())
+            .collect();
+        if !details.is_empty() {
+            return Err(anyhow::anyhow!(
+                "重新生成的字段不符合约束: {}",
+                details.join("; ")
+            ));
+        }
+    }
+    Ok(merged)
+}
+
 /// LLM提供商接口
 #[async_trait]
 pub trait LLMProvider: Send + Sync + std::any::Any {
@@ -579,6 +1549,51 @@ pub trait LLMProvider: Send + Sync + std::any::Any {
     /// * 会话总结
     async fn analyze_frames(&self, frames: Vec<String>) -> Result<SessionSummary>;
 
+    /// 生成多个候选总结供用户挑选
+    ///
+    /// 依次执行 n 次分析（不并发，避免抢占本地模型），单个候选失败不影响其余候选，
+    /// 内容相同的候选只保留一个；全部失败时返回第一个错误。
+    /// 默认实现不控制种子与温度，支持的提供商应覆盖并在结果中记录两者以便复现
+    async fn analyze_frames_n(&self, frames: Vec<String>, n: usize) -> Result<Vec<SessionSummary>> {
+        let mut results = Vec::with_capacity(n);
+        for _ in 0..n.clamp(1, MAX_SUMMARY_CANDIDATES) {
+            results.push(self.analyze_frames(frames.clone()).await);
+        }
+        collect_candidates(results)
+    }
+
+    /// 分析纯文本会话（无截图，如终端输出、剪贴板历史）
+    ///
+    /// 使用与帧分析相同的提示词与 JSON 结构，只发送文本，不支持视觉的提供商也需实现
+    async fn analyze_text(&self, content: String) -> Result<SessionSummary>;
+
+    /// 结合附加上下文分析帧（如音频转写文本）
+    ///
+    /// 默认实现忽略上下文，直接走纯帧分析路径
+    async fn analyze_frames_with_context(
+        &self,
+        frames: Vec<String>,
+        _context: AnalysisContext,
+    ) -> Result<SessionSummary> {
+        self.analyze_frames(frames).await
+    }
+
+    /// 结合已有总结重新生成单个字段（标签、关键时刻或评分），其余字段保持不变
+    ///
+    /// 默认不支持，需要提供商实现针对性的重新提问
+    async fn refine_field(
+        &self,
+        _frames: Vec<String>,
+        _current: SessionSummary,
+        field: SummaryField,
+    ) -> Result<SessionSummary> {
+        Err(anyhow::anyhow!(
+            "{} 不支持单独重新生成 {:?}",
+            self.name(),
+            field
+        ))
+    }
+
     /// 分析视频并分段
     ///
     /// # 参数
@@ -640,6 +1655,24 @@ pub trait LLMProvider: Send + Sync + std::any::Any {
     ) {
     }
 
+    /// 录制会话开始前的预热（如加载本地模型、校验鉴权），默认无操作
+    async fn on_start(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// 录制会话结束后释放资源（如卸载本地模型），默认无操作
+    async fn on_stop(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// 重建与模型服务的连接（如 HTTP 连接池），连续无法连接时由 ReconnectLayer 调用；默认无操作
+    async fn reconnect(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// 设置流式分析进度通道（不支持流式的提供商忽略）
+    fn set_progress_sender(&mut self, _sender: Option<ProgressSender>) {}
+
     /// 获取提供商名称
     fn name(&self) -> &str;
 
@@ -657,11 +1690,30 @@ pub trait LLMProvider: Send + Sync + std::any::Any {
         ProviderCapabilities::default()
     }
 
+    /// 从服务端查询当前模型的能力，更新 capabilities() 的结果
+    ///
+    /// 服务端未报告的字段沿用静态默认值；默认无操作
+    async fn refresh_capabilities(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// 估算分析指定帧数的费用
+    ///
+    /// 本地免费的提供商返回 None，托管提供商按配置的单价计算
+    fn estimate_cost(&self, _frames: usize) -> Option<CostEstimate> {
+        None
+    }
+
     /// 获取最后一次 LLM 调用的数据库 ID（可选，用于追踪）
     fn last_llm_call_id(&self, _call_type: &str) -> Option<i64> {
         None // 默认实现返回 None
     }
 
+    /// 获取最后一次分析实际使用的随机采样种子（可选，用于复现分析结果）
+    fn last_sampling_seed(&self) -> Option<u64> {
+        None
+    }
+
     /// 生成每日总结文本
     ///
     /// # 参数
@@ -701,8 +1753,34 @@ pub struct ProviderCapabilities {
     pub max_input_tokens: usize,
     /// 支持的图片格式
     pub supported_image_formats: Vec<String>,
+    /// 是否支持结合音频转写文本分析
+    #[serde(default)]
+    pub audio_support: bool,
+    /// 是否支持结合访问过的网页分析
+    #[serde(default)]
+    pub url_context_support: bool,
+    /// 模型家族（由服务端报告，未知时为 None）
+    #[serde(default)]
+    pub model_family: Option<String>,
+    /// 单次请求最多的图片数
+    #[serde(default = "default_max_images")]
+    pub max_images: usize,
+    /// 单张图片折算的输入 token 数（原图，粗略估算）
+    #[serde(default = "default_tokens_per_image")]
+    pub tokens_per_image: usize,
+}
+
+fn default_max_images() -> usize {
+    super::profiles::DEFAULT_MAX_IMAGES
+}
+
+fn default_tokens_per_image() -> usize {
+    DEFAULT_TOKENS_PER_IMAGE
 }
 
+/// 未知模型的单图 token 估算（约 1280x720 的截图）
+pub const DEFAULT_TOKENS_PER_IMAGE: usize = 1200;
+
 impl Default for ProviderCapabilities {
     fn default() -> Self {
         Self {
@@ -717,10 +1795,206 @@ impl Default for ProviderCapabilities {
                 "gif".to_string(),
                 "webp".to_string(),
             ],
+            audio_support: false,
+            url_context_support: false,
+            model_family: None,
+            max_images: default_max_images(),
+            tokens_per_image: DEFAULT_TOKENS_PER_IMAGE,
+        }
+    }
+}
+
+/// 托管提供商的计费单价（费率经常调整，需可通过 configure 的 `pricing` 字段覆盖）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderPricing {
+    /// 货币单位（如 USD、CNY）
+    pub currency: String,
+    /// 每张图片的固定费用
+    #[serde(default)]
+    pub per_image: f64,
+    /// 每千输入 token 的费用
+    #[serde(default)]
+    pub per_1k_input_tokens: f64,
+    /// 单张图片折算的 token 数
+    #[serde(default)]
+    pub tokens_per_image: usize,
+    /// 提示词等固定开销的 token 数
+    #[serde(default)]
+    pub prompt_tokens: usize,
+}
+
+impl ProviderPricing {
+    /// 从 configure 的 JSON 中读取 `pricing` 覆盖（未提供的字段沿用当前值）
+    pub fn apply_config(&mut self, config: &serde_json::Value) {
+        let Some(pricing) = config.get("pricing") else {
+            return;
+        };
+        let mut merged = serde_json::to_value(&*self).unwrap_or_default();
+        if let (Some(base), Some(overrides)) = (merged.as_object_mut(), pricing.as_object()) {
+            for (key, value) in overrides {
+                base.insert(key.clone(), value.clone());
+            }
         }
+        match serde_json::from_value(merged) {
+            Ok(parsed) => *self = parsed,
+            Err(e) => tracing::warn!("忽略无效的 pricing 配置: {}", e),
+        }
+    }
+
+    /// 按图片数量估算费用
+    pub fn estimate(&self, images: usize) -> CostEstimate {
+        let input_tokens = self.prompt_tokens + images * self.tokens_per_image;
+        let amount =
+            images as f64 * self.per_image + input_tokens as f64 / 1000.0 * self.per_1k_input_tokens;
+        CostEstimate {
+            currency: self.currency.clone(),
+            image_count: images,
+            estimated_input_tokens: input_tokens,
+            amount,
+        }
+    }
+}
+
+/// 分析费用估算
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostEstimate {
+    /// 货币单位
+    pub currency: String,
+    /// 实际会发送的图片数
+    pub image_count: usize,
+    /// 估算的输入 token 数
+    pub estimated_input_tokens: usize,
+    /// 估算金额
+    pub amount: f64,
+}
+
+/// 流式分析进度事件（通过进度通道推送给前端）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AnalysisProgress {
+    /// 收到一段流式输出文本
+    Text { provider: String, delta: String },
+    /// 已设置 num_predict：按 token 数估算完成百分比
+    Tokens {
+        provider: String,
+        eval_count: u64,
+        num_predict: u64,
+        percent: f32,
+        tokens_per_sec: f32,
+        eta_secs: Option<f32>,
+    },
+    /// 未设置 num_predict：只报告已用时间与速度
+    Throughput {
+        provider: String,
+        eval_count: u64,
+        elapsed_secs: f32,
+        tokens_per_sec: f32,
+    },
+    /// 非流式请求进行中：/api/ps 确认模型仍在运行
+    Heartbeat {
+        provider: String,
+        model: String,
+        elapsed_secs: f32,
+    },
+    /// 流式输出结束
+    Done {
+        provider: String,
+        eval_count: u64,
+        elapsed_secs: f32,
+    },
+    /// 正在拉取模型：status 为服务端的阶段说明，下载层时带已下载与总字节数
+    ModelPull {
+        provider: String,
+        model: String,
+        status: String,
+        completed: Option<u64>,
+        total: Option<u64>,
+        percent: Option<f32>,
+    },
+}
+
+/// 进度通道发送端
+pub type ProgressSender = tokio::sync::mpsc::UnboundedSender<AnalysisProgress>;
+
+/// 分析附加上下文（帧之外的可选补充信息）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalysisContext {
+    /// 同一时间段的音频转写文本
+    #[serde(default)]
+    pub transcript: Option<String>,
+    /// 同一时间段访问过的网页（由采集层记录，可为空）
+    #[serde(default)]
+    pub visited_urls: Vec<VisitedUrl>,
+    /// 相似会话的人工更正示例（为空时由 provider 按配置检索）
+    #[serde(default)]
+    pub examples: Vec<FewShotExample>,
+    /// 同一时间段的输入活跃度（由采集层按分钟汇总，可为空）
+    #[serde(default)]
+    pub input_activity: Option<InputActivity>,
+    /// 分析模式（如 work、study）；为空时使用会话记录的模式或配置的默认模式
+    #[serde(default)]
+    pub mode: Option<String>,
+    /// 同一时间段的前台应用记录（由采集层在应用切换时记录，可为空），用于按应用覆盖采样
+    #[serde(default)]
+    pub foreground_apps: Vec<ForegroundApp>,
+}
+
+/// 前台应用记录：从 timestamp 起前台为 app，直到下一条记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForegroundApp {
+    pub timestamp: DateTime<Utc>,
+    pub app: String,
+}
+
+/// 输入活跃度：按分钟汇总的输入次数，只有计数，不记录任何按键内容
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputActivity {
+    /// 每分钟的计数，按时间先后排列
+    pub minutes: Vec<InputActivityMinute>,
+}
+
+/// 一分钟内的输入次数
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct InputActivityMinute {
+    /// 该分钟的开始时间
+    pub minute: DateTime<Utc>,
+    /// 按键次数
+    #[serde(default)]
+    pub keystrokes: u32,
+    /// 滚动次数
+    #[serde(default)]
+    pub scrolls: u32,
+    /// 鼠标点击次数
+    #[serde(default)]
+    pub clicks: u32,
+}
+
+impl InputActivityMinute {
+    pub fn total(&self) -> u32 {
+        self.keystrokes
+            .saturating_add(self.scrolls)
+            .saturating_add(self.clicks)
     }
 }
 
+/// 少样本示例：模型最初的总结与用户更正后的结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FewShotExample {
+    pub description: String,
+    pub corrected: String,
+}
+
+/// 访问过的网页
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VisitedUrl {
+    /// 访问时间
+    pub timestamp: DateTime<Utc>,
+    pub url: String,
+    /// 网页标题
+    #[serde(default)]
+    pub title: String,
+}
+
 /// 分析请求
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AnalysisRequest {
@@ -0,0 +1,189 @@
+// 模型提示词配置 - 按模型家族调整系统提示词、JSON 输出模式与图片数量
+//
+// 不同视觉模型稳定输出 JSON 所需的写法不同：内置配置记录已验证过的调整，
+// 未知模型使用通用配置；用户可在 OllamaConfig.prompt_profiles 中按模型名或家族覆盖
+
+use super::PromptProfileOverride;
+use std::collections::BTreeMap;
+
+/// 通用配置单次最多发送的图片数（参考截图占用同一预算）
+pub const DEFAULT_MAX_IMAGES: usize = 30;
+
+/// 要求只输出 JSON 的系统提示词（小模型容易在 JSON 前后附加解释）
+const JSON_ONLY_SYSTEM_PROMPT: &str =
+    "你是屏幕活动分析助手。只输出一个 JSON 对象，不要输出 Markdown 代码块或任何解释文字。";
+
+/// 生效的提示词配置
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptProfile {
+    /// 匹配到的配置名（内置家族名或 "generic"）
+    pub name: String,
+    /// 系统提示词（作为 system 消息放在用户消息之前）
+    pub system_prompt: Option<String>,
+    /// 请求中设置 format: "json"，由服务端约束输出为合法 JSON
+    pub json_format: bool,
+    /// 单次请求最多发送的图片数
+    pub max_images: usize,
+}
+
+impl Default for PromptProfile {
+    fn default() -> Self {
+        Self {
+            name: "generic".to_string(),
+            system_prompt: None,
+            json_format: false,
+            max_images: DEFAULT_MAX_IMAGES,
+        }
+    }
+}
+
+impl PromptProfile {
+    fn apply(&mut self, over: &PromptProfileOverride) {
+        if let Some(system_prompt) = &over.system_prompt {
+            // 配置为空字符串表示不使用系统提示词
+            self.system_prompt =
+                Some(system_prompt.trim().to_string()).filter(|prompt| !prompt.is_empty());
+        }
+        if let Some(json_format) = over.json_format {
+            self.json_format = json_format;
+        }
+        if let Some(max_images) = over.max_images {
+            self.max_images = max_images.max(1);
+        }
+    }
+}
+
+/// 内置的模型家族配置（按家族名前缀匹配）
+fn builtin_profile(family: &str) -> Option<PromptProfile> {
+    let profile = |name: &str, system: bool, json_format: bool, max_images: usize| PromptProfile {
+        name: name.to_string(),
+        system_prompt: system.then(|| JSON_ONLY_SYSTEM_PROMPT.to_string()),
+        json_format,
+        max_images,
+    };
+    // 越具体的前缀越靠前
+    let profile = match family {
+        // Qwen 视觉模型能按提示词输出 JSON；format: json 会干扰其思考输出，不开启
+        f if f.starts_with("qwen3-vl") => profile("qwen3-vl", false, false, DEFAULT_MAX_IMAGES),
+        f if f.starts_with("qwen2.5vl") || f.starts_with("qwen2.5-vl") => {
+            profile("qwen2.5vl", false, false, DEFAULT_MAX_IMAGES)
+        }
+        // llama3.2-vision 每条消息只支持一张图片
+        f if f.starts_with("llama3.2-vision") => profile("llama3.2-vision", true, true, 1),
+        // LLaVA 上下文短、多图时容易混淆，少量图片并强制 JSON
+        f if f.starts_with("llava") || f.starts_with("bakllava") => profile("llava", true, true, 4),
+        f if f.starts_with("minicpm-v") => profile("minicpm-v", true, true, 8),
+        f if f.starts_with("gemma3") => profile("gemma3", true, true, 8),
+        _ => return None,
+    };
+    Some(profile)
+}
+
+/// 模型家族：去掉仓库前缀与标签，如 "library/llava:13b" -> "llava"
+pub fn model_family(model: &str) -> String {
+    let name = model.trim().rsplit('/').next().unwrap_or_default();
+    name.split(':').next().unwrap_or_default().to_lowercase()
+}
+
+/// 选出模型的提示词配置
+///
+/// 依次叠加：通用配置 → 内置家族配置 → 按家族前缀的用户覆盖（取最长前缀）→ 按完整模型名的用户覆盖
+pub fn resolve_profile(
+    model: &str,
+    overrides: &BTreeMap<String, PromptProfileOverride>,
+) -> PromptProfile {
+    let family = model_family(model);
+    let mut profile = builtin_profile(&family).unwrap_or_default();
+
+    let family_override = overrides
+        .iter()
+        .filter(|(key, _)| {
+            let key = key.trim().to_lowercase();
+            !key.is_empty() && !key.contains(':') && family.starts_with(&key)
+        })
+        .max_by_key(|(key, _)| key.trim().len());
+    if let Some((key, over)) = family_override {
+        profile.apply(over);
+        profile.name = key.trim().to_lowercase();
+    }
+    if let Some((key, over)) = overrides
+        .iter()
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(model.trim()))
+    {
+        profile.apply(over);
+        profile.name = key.trim().to_string();
+    }
+    profile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_models_select_builtin_profiles() {
+        let none = BTreeMap::new();
+        let llava = resolve_profile("llava:13b", &none);
+        assert_eq!(llava.name, "llava");
+        assert!(llava.json_format);
+        assert_eq!(llava.max_images, 4);
+        assert!(llava.system_prompt.is_some());
+
+        assert_eq!(resolve_profile("llava-llama3:8b", &none).name, "llava");
+        assert_eq!(resolve_profile("MiniCPM-V:8b", &none).name, "minicpm-v");
+        assert_eq!(
+            resolve_profile("library/llama3.2-vision:11b", &none).max_images,
+            1
+        );
+        assert!(!resolve_profile("qwen3-vl:32b", &none).json_format);
+
+        // 未知模型使用通用配置
+        assert_eq!(
+            resolve_profile("my-custom-vlm:latest", &none),
+            PromptProfile::default()
+        );
+    }
+
+    #[test]
+    fn test_overrides_apply_over_builtin_profile() {
+        let overrides = BTreeMap::from([
+            (
+                "llava".to_string(),
+                PromptProfileOverride {
+                    max_images: Some(2),
+                    ..Default::default()
+                },
+            ),
+            (
+                "llava:34b".to_string(),
+                PromptProfileOverride {
+                    system_prompt: Some(String::new()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "my-vlm".to_string(),
+                PromptProfileOverride {
+                    json_format: Some(true),
+                    max_images: Some(0),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        // 家族覆盖只改指定字段，其余沿用内置配置
+        let family = resolve_profile("llava:13b", &overrides);
+        assert_eq!(family.max_images, 2);
+        assert!(family.json_format && family.system_prompt.is_some());
+
+        // 完整模型名的覆盖叠加在家族覆盖之上
+        let exact = resolve_profile("llava:34b", &overrides);
+        assert_eq!(exact.max_images, 2);
+        assert_eq!(exact.system_prompt, None);
+
+        // 未知模型也可以通过覆盖配置，图片数至少为 1
+        let custom = resolve_profile("my-vlm:7b", &overrides);
+        assert!(custom.json_format);
+        assert_eq!(custom.max_images, 1);
+    }
+}
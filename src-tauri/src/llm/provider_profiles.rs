@@ -0,0 +1,230 @@
+// Provider 配置档 - 启动时从 TOML/JSON 文件加载各 provider 的默认参数
+//
+// 配置文件可放进版本控制，按名称定义多套 provider 配置（如本地 Ollama 与云端 Qwen）：
+// 每个配置档只有一个以 provider 名命名的子表（[profiles.local.ollama]），子表直接反序列化为
+// 对应的配置结构，再经由 LLMManager 的 configure 系列方法应用。
+// 按扩展名选择格式（.json 为 JSON，其余按 TOML），解析错误附带出错的行
+
+use super::{ClaudeConfig, CodexConfig, OllamaConfig, QwenConfig};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// 单个配置档：唯一的键为 provider 名，值为该 provider 的配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderProfile {
+    Qwen(QwenConfig),
+    Claude(ClaudeConfig),
+    Ollama(Box<OllamaConfig>),
+    Codex(CodexConfig),
+}
+
+impl ProviderProfile {
+    /// 对应的 provider 名（与 switch_provider 的参数一致）
+    pub fn provider_name(&self) -> &'static str {
+        match self {
+            Self::Qwen(_) => "qwen",
+            Self::Claude(_) => "claude",
+            Self::Ollama(_) => "ollama",
+            Self::Codex(_) => "codex",
+        }
+    }
+}
+
+/// 配置档文件
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderProfiles {
+    /// 未指定配置档名时使用的配置档
+    #[serde(default)]
+    pub default: Option<String>,
+    pub profiles: BTreeMap<String, ProviderProfile>,
+}
+
+impl ProviderProfiles {
+    /// 读取并校验配置档文件
+    pub fn load(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("读取配置档文件 {} 失败: {}", path.display(), e))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        Self::parse(&source, is_json).map_err(|e| anyhow!("配置档文件 {} {}", path.display(), e))
+    }
+
+    /// 解析配置档内容；错误信息带行号与出错行的原文
+    pub fn parse(source: &str, is_json: bool) -> Result<Self> {
+        let profiles: Self = if is_json {
+            serde_json::from_str(source).map_err(|e| {
+                let message = e.to_string();
+                // serde_json 的错误信息末尾自带 " at line X column Y"
+                let message = message
+                    .rfind(" at line ")
+                    .map_or(message.as_str(), |end| &message[..end]);
+                located_error(source, e.line(), e.column(), message)
+            })?
+        } else {
+            toml::from_str(source).map_err(|e| match e.span() {
+                Some(span) => {
+                    let (line, column) = line_column(source, span.start);
+                    located_error(source, line, column, e.message())
+                }
+                None => anyhow!("解析失败: {}", e.message()),
+            })?
+        };
+        profiles.validate()?;
+        Ok(profiles)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.profiles.is_empty() {
+            return Err(anyhow!("没有定义任何配置档"));
+        }
+        if let Some(name) = self.profiles.keys().find(|name| name.trim().is_empty()) {
+            return Err(anyhow!("配置档名 {:?} 无效", name));
+        }
+        if let Some(default) = &self.default {
+            if !self.profiles.contains_key(default) {
+                return Err(anyhow!("默认配置档 {} 不存在", default));
+            }
+        }
+        Ok(())
+    }
+
+    /// 按名称取配置档；未指定名称时使用 default，只有一个配置档时直接使用它
+    pub fn select(&self, name: Option<&str>) -> Result<(&str, &ProviderProfile)> {
+        let name = match name.or(self.default.as_deref()) {
+            Some(name) => name,
+            None if self.profiles.len() == 1 => self.profiles.keys().next().unwrap(),
+            None => {
+                return Err(anyhow!(
+                    "存在多个配置档但未指定使用哪一个（可选: {}）",
+                    self.names().join(", ")
+                ))
+            }
+        };
+        self.profiles
+            .get_key_value(name)
+            .map(|(name, profile)| (name.as_str(), profile))
+            .ok_or_else(|| {
+                anyhow!(
+                    "配置档 {} 不存在（可选: {}）",
+                    name,
+                    self.names().join(", ")
+                )
+            })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.profiles.keys().map(String::as_str).collect()
+    }
+}
+
+/// 字节偏移对应的行号与列号（均从 1 开始，列按字符计）
+fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// 带出错行原文的错误，如：
+/// 第 3 行第 12 列: invalid type
+///     3 | model = 12
+///       |         ^
+fn located_error(source: &str, line: usize, column: usize, message: &str) -> anyhow::Error {
+    let Some(text) = source.lines().nth(line.saturating_sub(1)) else {
+        return anyhow!("第 {} 行: {}", line, message);
+    };
+    let gutter = line.to_string().len();
+    anyhow!(
+        "第 {} 行第 {} 列: {}\n{:>width$} | {}\n{:>width$} | {}^",
+        line,
+        column,
+        message,
+        line,
+        text,
+        "",
+        " ".repeat(column.saturating_sub(1)),
+        width = gutter + 4
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::LLMManager;
+
+    const PROFILES: &str = r#"
+default = "local"
+
+[profiles.local.ollama]
+base_url = "http://127.0.0.1:9"
+model = "qwen3-vl:8b"
+min_frames_for_analysis = 3
+
+[profiles.cloud.qwen]
+api_key = "sk-test"
+model = "qwen-vl-plus"
+use_video_mode = false
+"#;
+
+    #[tokio::test]
+    async fn test_two_profiles_are_loaded_and_applied() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("providers.toml");
+        std::fs::write(&path, PROFILES).unwrap();
+        let profiles = ProviderProfiles::load(&path).unwrap();
+        assert_eq!(profiles.names(), vec!["cloud", "local"]);
+
+        let mut manager = LLMManager::new(reqwest::Client::new());
+
+        let (name, local) = profiles.select(None).unwrap();
+        assert_eq!(name, "local");
+        manager.apply_profile(local.clone()).await.unwrap();
+        let config = manager.get_config().await;
+        assert_eq!(config.provider, "ollama");
+        assert_eq!(config.ollama.model, "qwen3-vl:8b");
+        assert_eq!(config.ollama.min_frames_for_analysis, 3);
+
+        let (_, cloud) = profiles.select(Some("cloud")).unwrap();
+        manager.apply_profile(cloud.clone()).await.unwrap();
+        let config = manager.get_config().await;
+        assert_eq!(config.provider, "qwen");
+        assert_eq!(config.qwen.api_key, "sk-test");
+        assert_eq!(config.qwen.model, "qwen-vl-plus");
+        assert!(!config.qwen.use_video_mode);
+
+        assert!(profiles.select(Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_errors_point_at_offending_line() {
+        let toml_err = ProviderProfiles::parse(
+            "[profiles.local.ollama]\nmodel = \"qwen3-vl:8b\"\nmin_frames_for_analysis = \"three\"\n",
+            false,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(toml_err.starts_with("第 3 行"), "{}", toml_err);
+        assert!(toml_err.contains("min_frames_for_analysis = \"three\""));
+
+        let json_err = ProviderProfiles::parse(
+            "{\n  \"profiles\": {\n    \"a\": {\"gpt\": {}}\n  }\n}",
+            true,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(json_err.starts_with("第 3 行"), "{}", json_err);
+        assert!(json_err.contains("gpt"));
+
+        let default_err = ProviderProfiles::parse(
+            "default = \"x\"\n[profiles.a.claude]\nmodel = \"sonnet\"\n",
+            false,
+        )
+        .unwrap_err();
+        assert!(default_err.to_string().contains("默认配置档 x 不存在"));
+    }
+}
@@ -0,0 +1,438 @@
+// 分析调度队列 - 在 provider 前按优先级分配有限的分析并发
+//
+// 单 GPU 下大批量后台分析会让用户触发的重新分析长时间排队；
+// 这里在并发上限（信号量语义）之上按优先级排序等待者，同优先级内保持先来先服务。
+// AnalysisPipeline 把读帧、筛帧、生成视频等准备工作（I/O 密集）与模型推理（GPU 密集）
+// 分开限流，推理进行时下一个会话可以同时准备，而不是排在推理后面串行执行
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::{oneshot, Semaphore};
+use tracing::info;
+
+/// 默认同时进行的分析数（单 GPU）
+pub const DEFAULT_MAX_CONCURRENT_ANALYSES: usize = 1;
+
+/// 默认同时准备的会话数
+pub const DEFAULT_PREPARATION_CONCURRENCY: usize = 2;
+
+/// 推理并发上限：provider 的会话 id、时间窗与视频路径保存在 LLM actor 中，
+/// 按会话设置后再发起推理，并发推理会互相覆盖这些状态
+pub const MAX_INFERENCE_CONCURRENCY: usize = 1;
+
+/// 分析优先级
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalysisPriority {
+    /// 后台分析（定时会话、批量补分析）
+    #[default]
+    Background,
+    /// 用户触发的分析（重新分析、候选总结等）
+    Interactive,
+}
+
+impl AnalysisPriority {
+    /// 序列化名（持久化分析队列时使用）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Background => "background",
+            Self::Interactive => "interactive",
+        }
+    }
+}
+
+struct Waiter {
+    priority: AnalysisPriority,
+    /// 入队序号，同优先级时序号小的先执行
+    seq: u64,
+    grant: oneshot::Sender<AnalysisPermit>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    // BinaryHeap 是大顶堆：优先级高者在前，同优先级序号小者在前
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+struct QueueState {
+    available: usize,
+    next_seq: u64,
+    waiters: BinaryHeap<Waiter>,
+}
+
+struct QueueInner {
+    state: Mutex<QueueState>,
+}
+
+impl QueueInner {
+    fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+        // 持锁期间不会 panic，锁中毒时沿用内部数据
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// 归还一个名额：优先直接移交给最高优先级的等待者
+    fn release(self: &Arc<Self>) {
+        loop {
+            let waiter = {
+                let mut state = self.lock();
+                match state.waiters.pop() {
+                    Some(waiter) => waiter,
+                    None => {
+                        state.available += 1;
+                        return;
+                    }
+                }
+            };
+
+            let permit = AnalysisPermit {
+                queue: Some(self.clone()),
+            };
+            match waiter.grant.send(permit) {
+                Ok(()) => return,
+                // 等待者已取消，名额交给下一个
+                Err(mut permit) => {
+                    permit.queue = None;
+                }
+            }
+        }
+    }
+}
+
+/// 分析调度队列
+#[derive(Clone)]
+pub struct AnalysisQueue {
+    inner: Arc<QueueInner>,
+}
+
+impl AnalysisQueue {
+    pub fn new(max_concurrent: usize) -> Self {
+        Self {
+            inner: Arc::new(QueueInner {
+                state: Mutex::new(QueueState {
+                    available: max_concurrent.max(1),
+                    next_seq: 0,
+                    waiters: BinaryHeap::new(),
+                }),
+            }),
+        }
+    }
+
+    /// 申请分析名额，名额不足时按优先级排队等待
+    ///
+    /// 返回的 AnalysisPermit 析构时归还名额
+    pub async fn acquire(&self, priority: AnalysisPriority) -> AnalysisPermit {
+        let receiver = {
+            let mut state = self.inner.lock();
+            if state.available > 0 {
+                state.available -= 1;
+                return AnalysisPermit {
+                    queue: Some(self.inner.clone()),
+                };
+            }
+
+            let (grant, receiver) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiters.push(Waiter {
+                priority,
+                seq,
+                grant,
+            });
+            receiver
+        };
+
+        // 发送端只在移交名额时使用，且队列存活期间不会被丢弃
+        receiver.await.expect("分析队列已关闭")
+    }
+
+    /// 当前排队等待的分析数
+    pub fn waiting(&self) -> usize {
+        self.inner.lock().waiters.len()
+    }
+}
+
+impl Default for AnalysisQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT_ANALYSES)
+    }
+}
+
+/// 分析名额，析构时归还
+pub struct AnalysisPermit {
+    queue: Option<Arc<QueueInner>>,
+}
+
+impl Drop for AnalysisPermit {
+    fn drop(&mut self) {
+        if let Some(queue) = self.queue.take() {
+            queue.release();
+        }
+    }
+}
+
+/// 分析流水线：准备阶段与推理阶段各自限流
+#[derive(Clone)]
+pub struct AnalysisPipeline {
+    preparation: Arc<Semaphore>,
+    inference: AnalysisQueue,
+}
+
+impl AnalysisPipeline {
+    /// preparation_concurrency 为同时准备的会话数；推理并发由 inference 队列决定（与其他分析入口共享）
+    pub fn new(preparation_concurrency: usize, inference: AnalysisQueue) -> Self {
+        Self {
+            preparation: Arc::new(Semaphore::new(preparation_concurrency.max(1))),
+            inference,
+        }
+    }
+
+    /// 推理队列
+    pub fn inference_queue(&self) -> &AnalysisQueue {
+        &self.inference
+    }
+
+    /// 先在准备名额内执行 prepare，释放准备名额后再按优先级申请推理名额执行 infer
+    ///
+    /// 准备失败时不占用推理名额
+    pub async fn run<T, R, P, I, F>(
+        &self,
+        label: &str,
+        priority: AnalysisPriority,
+        prepare: P,
+        infer: I,
+    ) -> Result<R>
+    where
+        P: Future<Output = Result<T>>,
+        I: FnOnce(T) -> F,
+        F: Future<Output = Result<R>>,
+    {
+        let started = Instant::now();
+        let prepared = {
+            // 信号量不会被关闭
+            let _permit = self
+                .preparation
+                .acquire()
+                .await
+                .expect("准备阶段信号量已关闭");
+            prepare.await?
+        };
+        let prepared_at = Instant::now();
+
+        let _permit = self.inference.acquire(priority).await;
+        let inference_started = Instant::now();
+        let result = infer(prepared).await;
+        info!(
+            "{}：准备 {:.1}s，等待推理 {:.1}s，推理 {:.1}s",
+            label,
+            (prepared_at - started).as_secs_f64(),
+            (inference_started - prepared_at).as_secs_f64(),
+            inference_started.elapsed().as_secs_f64()
+        );
+        result
+    }
+}
+
+impl Default for AnalysisPipeline {
+    fn default() -> Self {
+        Self::new(DEFAULT_PREPARATION_CONCURRENCY, AnalysisQueue::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_interactive_jobs_outrank_queued_background_jobs() {
+        let queue = AnalysisQueue::new(1);
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        // 第一个后台任务占住名额，其余任务交替入队
+        let running = queue.acquire(AnalysisPriority::Background).await;
+        let jobs = [
+            ("bg-1", AnalysisPriority::Background),
+            ("ui-1", AnalysisPriority::Interactive),
+            ("bg-2", AnalysisPriority::Background),
+            ("ui-2", AnalysisPriority::Interactive),
+            ("bg-3", AnalysisPriority::Background),
+        ];
+        let mut handles = Vec::new();
+        for (name, priority) in jobs {
+            handles.push(tokio::spawn({
+                let queue = queue.clone();
+                let order = order.clone();
+                async move {
+                    let _permit = queue.acquire(priority).await;
+                    order.lock().unwrap().push(name);
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            }));
+            // 等待任务进入队列，保证入队顺序确定
+            while queue.waiting() < handles.len() {
+                tokio::task::yield_now().await;
+            }
+        }
+
+        drop(running);
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(
+            *order.lock().unwrap(),
+            vec!["ui-1", "ui-2", "bg-1", "bg-2", "bg-3"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_is_bounded() {
+        let queue = AnalysisQueue::new(2);
+        let first = queue.acquire(AnalysisPriority::Background).await;
+        let _second = queue.acquire(AnalysisPriority::Background).await;
+
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.acquire(AnalysisPriority::Interactive).await }
+        });
+        while queue.waiting() < 1 {
+            tokio::task::yield_now().await;
+        }
+        assert!(!waiting.is_finished());
+
+        drop(first);
+        let _third = waiting.await.unwrap();
+        assert_eq!(queue.waiting(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiter_passes_permit_on() {
+        let queue = AnalysisQueue::new(1);
+        let running = queue.acquire(AnalysisPriority::Background).await;
+
+        let cancelled = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.acquire(AnalysisPriority::Interactive).await }
+        });
+        while queue.waiting() < 1 {
+            tokio::task::yield_now().await;
+        }
+        cancelled.abort();
+        let _ = cancelled.await;
+
+        drop(running);
+        // 已取消的等待者不会占住名额
+        let _permit = queue.acquire(AnalysisPriority::Background).await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_preparation_overlaps_inference() {
+        // 单推理名额、两个准备名额：三个会话各准备 100ms、推理 100ms（时钟暂停，结果确定）
+        let pipeline = AnalysisPipeline::new(2, AnalysisQueue::new(1));
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        let origin = tokio::time::Instant::now();
+
+        let mut handles = Vec::new();
+        for name in ["s1", "s2", "s3"] {
+            let pipeline = pipeline.clone();
+            let spans = spans.clone();
+            handles.push(tokio::spawn(async move {
+                pipeline
+                    .run(
+                        name,
+                        AnalysisPriority::Background,
+                        async {
+                            let start = origin.elapsed();
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                            spans
+                                .lock()
+                                .unwrap()
+                                .push((name, "prepare", start, origin.elapsed()));
+                            Ok(())
+                        },
+                        |()| async {
+                            let start = origin.elapsed();
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                            spans
+                                .lock()
+                                .unwrap()
+                                .push((name, "infer", start, origin.elapsed()));
+                            Ok(())
+                        },
+                    )
+                    .await
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap().unwrap();
+        }
+
+        let spans = spans.lock().unwrap().clone();
+        let (infers, prepares): (Vec<_>, Vec<_>) =
+            spans.iter().partition(|(_, stage, _, _)| *stage == "infer");
+        // 推理互不重叠
+        let mut infer_spans: Vec<_> = infers.iter().map(|(_, _, s, e)| (*s, *e)).collect();
+        infer_spans.sort();
+        for pair in infer_spans.windows(2) {
+            assert!(pair[1].0 >= pair[0].1);
+        }
+        // 至少有一次准备与推理同时进行
+        assert!(prepares.iter().any(|(p_name, _, p_start, p_end)| {
+            infers.iter().any(|(i_name, _, i_start, i_end)| {
+                p_name != i_name && p_start < i_end && i_start < p_end
+            })
+        }));
+        // 串行执行需 600ms；流水线中 s3 的准备与 s1 的推理重叠，推理串行共 400ms
+        assert_eq!(origin.elapsed(), Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn test_failed_preparation_skips_inference() {
+        let pipeline = AnalysisPipeline::new(1, AnalysisQueue::new(1));
+        let inferred = Arc::new(Mutex::new(false));
+        let result: Result<()> = pipeline
+            .run(
+                "failing",
+                AnalysisPriority::Interactive,
+                async { Err::<(), _>(anyhow::anyhow!("读取帧失败")) },
+                |()| {
+                    let inferred = inferred.clone();
+                    async move {
+                        *inferred.lock().unwrap() = true;
+                        Ok(())
+                    }
+                },
+            )
+            .await;
+        assert!(result.is_err());
+        assert!(!*inferred.lock().unwrap());
+        // 准备与推理名额均已归还
+        let _permit = pipeline
+            .inference_queue()
+            .acquire(AnalysisPriority::Background)
+            .await;
+    }
+}
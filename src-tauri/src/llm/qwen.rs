@@ -13,6 +13,38 @@ use std::path::Path;
 use std::sync::{Arc, Mutex};
 use tracing::{debug, error, info, warn};
 
+/// 会话总结提示词（帧分析与纯文本分析共用）
+const SUMMARY_PROMPT: &str = r#"分析这些屏幕截图，总结用户的活动。返回JSON格式：
+{
+  "title": "活动标题",
+  "summary": "详细描述",
+  "tags": [{"category": "类别", "confidence": 0.9, "keywords": ["关键词"]}],
+  "key_moments": [{"time": "00:00", "description": "描述", "importance": 3}],
+  "productivity_score": 75,
+  "focus_score": 80
+}
+
+标签类别必须是以下之一（使用snake_case格式）：
+- work: 工作（专注工作、编程、文档等生产力活动）
+- meeting: 会议（视频会议、在线讨论、团队协作）
+- coding: 编程（代码编写、调试、开发环境配置）
+- research: 研究（资料查找、文献阅读、信息收集）
+- learning: 学习（在线课程、教程学习、技能提升）
+- writing: 写作（文档撰写、博客创作、笔记整理）
+- design: 设计（界面设计、图形处理、创意工作）
+- communication: 沟通（邮件、即时消息、社交互动）
+- planning: 规划（任务规划、日程安排、项目管理）
+- data_analysis: 数据分析（数据处理、报表制作、统计分析）
+- entertainment: 娱乐（游戏、视频、音乐、休闲浏览）
+- social_media: 社交媒体（社交平台浏览、内容创作与互动）
+- shopping: 购物（网上购物、商品浏览、价格比较）
+- finance: 财务（理财、账单管理、投资交易）
+- break: 休息（短暂休息、放松时间）
+- exercise: 运动（运动健身、健康管理）
+- personal: 个人事务（个人生活相关事务处理）
+- idle: 空闲（无具体活动、等待状态）
+- other: 其他（未分类的其他活动）"#;
+
 /// Qwen提供商（阿里通义千问）
 pub struct QwenProvider {
     api_key: Option<String>,
@@ -30,6 +62,8 @@ pub struct QwenProvider {
     last_call_ids: Mutex<HashMap<String, i64>>,
     /// 视频速率乘数（用于提示词）
     video_speed_multiplier: f32,
+    /// 计费单价（用于费用估算）
+    pricing: ProviderPricing,
 }
 
 impl QwenProvider {
@@ -48,6 +82,13 @@ impl QwenProvider {
             session_video_path: None,
             last_call_ids: Mutex::new(HashMap::new()),
             video_speed_multiplier: 8.0, // 默认8倍速
+            pricing: ProviderPricing {
+                currency: "CNY".to_string(),
+                per_image: 0.0,
+                per_1k_input_tokens: 0.003,
+                tokens_per_image: 1280,
+                prompt_tokens: 2000,
+            },
         }
     }
 
@@ -189,7 +230,7 @@ impl QwenProvider {
 
     /// 将图片文件转换为base64
     async fn image_to_base64(&self, path: &str) -> Result<String> {
-        let image_data = tokio::fs::read(path).await?;
+        let image_data = crate::storage::frame_pack::read_frame(path).await?;
         Ok(general_purpose::STANDARD.encode(&image_data))
     }
 
@@ -439,6 +480,47 @@ Create long, meaningful cards that represent cohesive sessions of activity, idea
         })
     }
 
+    /// 从会话总结响应中提取 JSON 并转换为 SessionSummary
+    fn parse_summary_response(response: &str) -> Result<SessionSummary> {
+        // 解析响应
+        let json_str = if let Some(start) = response.find('{') {
+            if let Some(end) = response.rfind('}') {
+                &response[start..=end]
+            } else {
+                &response[start..]
+            }
+        } else {
+            response
+        };
+
+        let parsed: serde_json::Value = serde_json::from_str(json_str)?;
+
+        // 转换为SessionSummary
+        let now = crate::storage::local_now();
+        Ok(SessionSummary {
+            title: parsed["title"].as_str().unwrap_or("未命名会话").to_string(),
+            summary: parsed["summary"].as_str().unwrap_or("").to_string(),
+            tags: vec![], // 简化处理
+            start_time: now - chrono::Duration::minutes(15),
+            end_time: now,
+            key_moments: vec![],
+            productivity_score: parsed["productivity_score"].as_f64().map(|v| v as f32),
+            focus_score: parsed["focus_score"].as_f64().map(|v| v as f32),
+            sampling_seed: None,
+            temperature: None,
+            language: None,
+            summary_auto_derived: false,
+            redacted: false,
+            idle: false,
+            recovered_stream: false,
+            low_confidence: false,
+            frame_selection: None,
+            reasoning: None,
+            served_by: None,
+            extra: HashMap::new(),
+        })
+    }
+
     /// 调用Qwen API - 支持图片base64模式
     async fn call_qwen_api(
         &self,
@@ -457,6 +539,7 @@ Create long, meaningful cards that represent cohesive sessions of activity, idea
 
         // 构建消息内容
         let mut content_parts = vec![];
+        crate::telemetry::global().add_frames_encoded(images_base64.len());
 
         // Qwen特殊处理：使用video类型来处理多张图片
         if self.use_video_mode && !images_base64.is_empty() {
@@ -564,7 +647,11 @@ Create long, meaningful cards that represent cohesive sessions of activity, idea
                 }
             }
 
-            return Err(anyhow::anyhow!("Qwen API调用失败: {}", error_text));
+            return Err(anyhow::anyhow!(
+                "Qwen API调用失败 HTTP {}: {}",
+                status_code,
+                error_text
+            ));
         }
 
         let response_text = response.text().await?;
@@ -744,7 +831,11 @@ Create long, meaningful cards that represent cohesive sessions of activity, idea
                 return Err(anyhow::anyhow!("VIDEO_TOO_SHORT: {}", error_text));
             }
 
-            return Err(anyhow::anyhow!("Qwen API调用失败: {}", error_text));
+            return Err(anyhow::anyhow!(
+                "Qwen API调用失败 HTTP {}: {}",
+                status_code,
+                error_text
+            ));
         }
 
         let response_text = response.text().await?;
@@ -990,67 +1081,27 @@ impl LLMProvider for QwenProvider {
         }
 
         // 调用API分析
-        let prompt = r#"分析这些屏幕截图，总结用户的活动。返回JSON格式：
-{
-  "title": "活动标题",
-  "summary": "详细描述",
-  "tags": [{"category": "类别", "confidence": 0.9, "keywords": ["关键词"]}],
-  "key_moments": [{"time": "00:00", "description": "描述", "importance": 3}],
-  "productivity_score": 75,
-  "focus_score": 80
-}
-
-标签类别必须是以下之一（使用snake_case格式）：
-- work: 工作（专注工作、编程、文档等生产力活动）
-- meeting: 会议（视频会议、在线讨论、团队协作）
-- coding: 编程（代码编写、调试、开发环境配置）
-- research: 研究（资料查找、文献阅读、信息收集）
-- learning: 学习（在线课程、教程学习、技能提升）
-- writing: 写作（文档撰写、博客创作、笔记整理）
-- design: 设计（界面设计、图形处理、创意工作）
-- communication: 沟通（邮件、即时消息、社交互动）
-- planning: 规划（任务规划、日程安排、项目管理）
-- data_analysis: 数据分析（数据处理、报表制作、统计分析）
-- entertainment: 娱乐（游戏、视频、音乐、休闲浏览）
-- social_media: 社交媒体（社交平台浏览、内容创作与互动）
-- shopping: 购物（网上购物、商品浏览、价格比较）
-- finance: 财务（理财、账单管理、投资交易）
-- break: 休息（短暂休息、放松时间）
-- exercise: 运动（运动健身、健康管理）
-- personal: 个人事务（个人生活相关事务处理）
-- idle: 空闲（无具体活动、等待状态）
-- other: 其他（未分类的其他活动）"#
-            .to_string();
-
         let response = self
-            .call_qwen_api(prompt, images_base64, "analyze_frames")
+            .call_qwen_api(SUMMARY_PROMPT.to_string(), images_base64, "analyze_frames")
             .await?;
 
-        // 解析响应
-        let json_str = if let Some(start) = response.find('{') {
-            if let Some(end) = response.rfind('}') {
-                &response[start..=end]
-            } else {
-                &response[start..]
-            }
-        } else {
-            &response
-        };
+        Self::parse_summary_response(&response)
+    }
 
-        let parsed: serde_json::Value = serde_json::from_str(json_str)?;
+    /// 分析纯文本会话（不发送图片）
+    async fn analyze_text(&self, content: String) -> Result<SessionSummary> {
+        if !self.is_configured() {
+            return Err(anyhow::anyhow!("Qwen API key未配置，请先配置 API key"));
+        }
 
-        // 转换为SessionSummary
-        let now = crate::storage::local_now();
-        Ok(SessionSummary {
-            title: parsed["title"].as_str().unwrap_or("未命名会话").to_string(),
-            summary: parsed["summary"].as_str().unwrap_or("").to_string(),
-            tags: vec![], // 简化处理
-            start_time: now - chrono::Duration::minutes(15),
-            end_time: now,
-            key_moments: vec![],
-            productivity_score: parsed["productivity_score"].as_f64().map(|v| v as f32),
-            focus_score: parsed["focus_score"].as_f64().map(|v| v as f32),
-        })
+        info!("Qwen开始分析纯文本会话 ({} 字符)", content.chars().count());
+
+        let prompt = build_text_session_prompt(SUMMARY_PROMPT, &content, Default::default())?;
+        let response = self
+            .call_qwen_api(prompt, Vec::new(), "analyze_text")
+            .await?;
+
+        Self::parse_summary_response(&response)
     }
 
     /// 分析视频并分段
@@ -1205,6 +1256,8 @@ impl LLMProvider for QwenProvider {
     fn configure(&mut self, config: serde_json::Value) -> Result<()> {
         info!("Qwen configure 被调用，配置内容: {:?}", config);
 
+        self.pricing.apply_config(&config);
+
         // 配置 API key（只接受非空字符串）
         if let Some(api_key) = config.get("api_key").and_then(|v| v.as_str()) {
             if !api_key.trim().is_empty() {
@@ -1258,9 +1311,18 @@ impl LLMProvider for QwenProvider {
                 "gif".to_string(),
                 "webp".to_string(),
             ],
+            audio_support: false,
+            url_context_support: false,
+            model_family: None,
+            max_images: super::profiles::DEFAULT_MAX_IMAGES,
+            tokens_per_image: DEFAULT_TOKENS_PER_IMAGE,
         }
     }
 
+    fn estimate_cost(&self, frames: usize) -> Option<CostEstimate> {
+        Some(self.pricing.estimate(frames))
+    }
+
     /// 生成每日总结文本（使用LLM）
     async fn generate_day_summary(
         &self,
@@ -1423,3 +1485,38 @@ struct QwenChoice {
 struct QwenMessage {
     content: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::{LLMManager, QwenConfig};
+
+    #[tokio::test]
+    async fn test_pricing_override_is_applied_and_stored() {
+        let mut manager = LLMManager::new(Client::new());
+        manager
+            .configure(QwenConfig {
+                api_key: "sk-test".to_string(),
+                model: "qwen-vl-max-latest".to_string(),
+                base_url: "http://127.0.0.1:1".to_string(),
+                use_video_mode: false,
+                video_path: None,
+                pricing: Some(ProviderPricing {
+                    currency: "CNY".to_string(),
+                    per_image: 0.002,
+                    per_1k_input_tokens: 0.0,
+                    tokens_per_image: 1280,
+                    prompt_tokens: 2000,
+                }),
+            })
+            .await
+            .unwrap();
+
+        let estimate = manager.estimate_cost(10).unwrap();
+        assert_eq!(estimate.currency, "CNY");
+        assert!((estimate.amount - 0.02).abs() < 1e-9);
+        // 覆盖随配置保存，重启后恢复
+        let stored = manager.get_config().await.qwen.pricing.unwrap();
+        assert!((stored.per_image - 0.002).abs() < 1e-9);
+    }
+}
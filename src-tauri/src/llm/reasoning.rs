@@ -0,0 +1,83 @@
+// 推理内容 - 分离推理模型（如 qwen3-vl 的 think 模式）在 JSON 之外输出的思考过程
+//
+// 模型可能先输出 <think>...</think> 块（开启 think 模式时服务端有时省略开始标签），或在 JSON 之前写一段分析。
+// 解析总结前始终去掉这些内容，避免其中的花括号或代码块干扰 JSON 提取；
+// 开启 capture_reasoning 时另存为总结的 reasoning 字段，便于查看模型的判断依据
+
+const THINK_OPEN: &str = "<think>";
+const THINK_CLOSE: &str = "</think>";
+
+/// 拆分模型输出，返回 (推理内容, 用于提取 JSON 的正文)；没有推理内容时正文即原文
+pub fn split_reasoning(raw: &str) -> (Option<String>, String) {
+    let mut parts: Vec<String> = Vec::new();
+    let mut body = String::new();
+    let mut rest = raw;
+
+    // 只有结束标签：之前的内容都是推理
+    if let Some(close) = rest.find(THINK_CLOSE) {
+        if !rest[..close].contains(THINK_OPEN) {
+            parts.push(rest[..close].to_string());
+            rest = &rest[close + THINK_CLOSE.len()..];
+        }
+    }
+    while let Some(open) = rest.find(THINK_OPEN) {
+        body.push_str(&rest[..open]);
+        let inner = &rest[open + THINK_OPEN.len()..];
+        match inner.find(THINK_CLOSE) {
+            Some(close) => {
+                parts.push(inner[..close].to_string());
+                rest = &inner[close + THINK_CLOSE.len()..];
+            }
+            // 未闭合（输出被截断）：其余内容都是推理
+            None => {
+                parts.push(inner.to_string());
+                rest = "";
+            }
+        }
+    }
+    body.push_str(rest);
+
+    // JSON（或代码块）之前的说明文字
+    let start = [body.find('{'), body.find("```")]
+        .into_iter()
+        .flatten()
+        .min();
+    if let Some(start) = start.filter(|&start| !body[..start].trim().is_empty()) {
+        parts.push(body[..start].to_string());
+        body.replace_range(..start, "");
+    }
+
+    let parts: Vec<&str> = parts
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect();
+    let reasoning = (!parts.is_empty()).then(|| parts.join("\n\n"));
+    (reasoning, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reasoning_is_split_from_json() {
+        let raw = "<think>\n画面里是 VS Code，示例 {\"title\": \"x\"} 只是草稿\n</think>\n{\"title\": \"编码\"}";
+        let (reasoning, body) = split_reasoning(raw);
+        assert_eq!(
+            reasoning.as_deref(),
+            Some("画面里是 VS Code，示例 {\"title\": \"x\"} 只是草稿")
+        );
+        assert_eq!(body.trim(), "{\"title\": \"编码\"}");
+
+        // 省略开始标签，以及 JSON 之前的说明文字
+        let (reasoning, body) = split_reasoning("先看截图</think>结论如下：\n```json\n{}\n```");
+        assert_eq!(reasoning.as_deref(), Some("先看截图\n\n结论如下："));
+        assert_eq!(body, "```json\n{}\n```");
+
+        // 没有推理内容时原样返回
+        let (reasoning, body) = split_reasoning("  {\"title\": \"编码\"}");
+        assert!(reasoning.is_none());
+        assert_eq!(body, "  {\"title\": \"编码\"}");
+    }
+}
@@ -0,0 +1,488 @@
+// 录制/回放 provider - 记录真实调用的输入输出，用于离线回归测试
+
+use super::plugin::*;
+use crate::storage::frame_pack;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// 录制的帧文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// 调用时传入的原始路径（回放时按此匹配）
+    pub original_path: String,
+    /// 复制到录制目录后的相对路径（读取失败时为空）
+    pub stored_path: Option<String>,
+}
+
+/// 一次录制的调用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedInteraction {
+    /// 调用的方法名（analyze_frames、analyze_text 等）
+    pub call: String,
+    /// 被录制的提供商名称
+    pub provider: String,
+    pub recorded_at: DateTime<Utc>,
+    /// 调用涉及的帧
+    #[serde(default)]
+    pub frames: Vec<RecordedFrame>,
+    /// 调用参数（帧路径、上下文、文本等）
+    pub input: Value,
+    /// 成功时的返回值
+    pub output: Option<Value>,
+    /// 失败时的错误信息
+    pub error: Option<String>,
+}
+
+/// 录制装饰器：原样转发所有调用，并把每次调用的输入输出写入录制目录
+///
+/// 写入失败只记录警告，不影响分析结果
+pub struct RecordingProvider<P: LLMProvider> {
+    inner: P,
+    dir: PathBuf,
+    seq: AtomicU64,
+}
+
+impl<P: LLMProvider> RecordingProvider<P> {
+    pub fn new(inner: P, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            inner,
+            dir: dir.into(),
+            seq: AtomicU64::new(0),
+        }
+    }
+
+    /// 取回被包装的提供商
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    async fn record<T: Serialize>(
+        &self,
+        call: &str,
+        frames: &[String],
+        input: Value,
+        result: &Result<T>,
+    ) {
+        let seq = self.seq.fetch_add(1, Ordering::SeqCst);
+        if let Err(e) = self.write_record(seq, call, frames, input, result).await {
+            warn!("录制 {} 调用失败: {}", call, e);
+        }
+    }
+
+    async fn write_record<T: Serialize>(
+        &self,
+        seq: u64,
+        call: &str,
+        frames: &[String],
+        input: Value,
+        result: &Result<T>,
+    ) -> Result<()> {
+        let frames_dir = self.dir.join("frames");
+        tokio::fs::create_dir_all(&frames_dir).await?;
+
+        let mut recorded_frames = Vec::with_capacity(frames.len());
+        for (index, frame) in frames.iter().enumerate() {
+            let stored_path = match frame_pack::read_frame(frame).await {
+                Ok(bytes) => {
+                    let name = format!("{:06}_{:04}.{}", seq, index, frame_extension(frame));
+                    tokio::fs::write(frames_dir.join(&name), bytes).await?;
+                    Some(format!("frames/{}", name))
+                }
+                Err(e) => {
+                    warn!("录制时读取帧失败 {}: {}", frame, e);
+                    None
+                }
+            };
+            recorded_frames.push(RecordedFrame {
+                original_path: frame.clone(),
+                stored_path,
+            });
+        }
+
+        let (output, error) = match result {
+            Ok(value) => (Some(serde_json::to_value(value)?), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let interaction = RecordedInteraction {
+            call: call.to_string(),
+            provider: self.inner.name().to_string(),
+            recorded_at: crate::storage::local_now(),
+            frames: recorded_frames,
+            input,
+            output,
+            error,
+        };
+
+        let path = self.dir.join(format!("{:06}_{}.json", seq, call));
+        tokio::fs::write(&path, serde_json::to_vec_pretty(&interaction)?).await?;
+        Ok(())
+    }
+}
+
+/// 帧文件扩展名（打包帧取包内文件名）
+fn frame_extension(frame: &str) -> String {
+    let name = frame_pack::split_packed_path(frame)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| frame.to_string());
+    Path::new(&name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("bin")
+        .to_string()
+}
+
+#[async_trait]
+impl<P: LLMProvider> LLMProvider for RecordingProvider<P> {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        // 透传给内部提供商，保证按具体类型向下转型的逻辑不受包装影响
+        self.inner.as_any()
+    }
+
+    async fn analyze_frames(&self, frames: Vec<String>) -> Result<SessionSummary> {
+        let result = self.inner.analyze_frames(frames.clone()).await;
+        self.record(
+            "analyze_frames",
+            &frames,
+            json!({ "frames": frames }),
+            &result,
+        )
+        .await;
+        result
+    }
+
+    async fn analyze_text(&self, content: String) -> Result<SessionSummary> {
+        let result = self.inner.analyze_text(content.clone()).await;
+        self.record("analyze_text", &[], json!({ "content": content }), &result)
+            .await;
+        result
+    }
+
+    async fn analyze_frames_with_context(
+        &self,
+        frames: Vec<String>,
+        context: AnalysisContext,
+    ) -> Result<SessionSummary> {
+        let input = json!({ "frames": frames, "context": context });
+        let result = self
+            .inner
+            .analyze_frames_with_context(frames.clone(), context)
+            .await;
+        self.record("analyze_frames_with_context", &frames, input, &result)
+            .await;
+        result
+    }
+
+    async fn refine_field(
+        &self,
+        frames: Vec<String>,
+        current: SessionSummary,
+        field: SummaryField,
+    ) -> Result<SessionSummary> {
+        let input = json!({ "frames": frames, "current": current, "field": field });
+        let result = self
+            .inner
+            .refine_field(frames.clone(), current, field)
+            .await;
+        self.record("refine_field", &frames, input, &result).await;
+        result
+    }
+
+    async fn segment_video(&self, frames: Vec<String>, duration: u32) -> Result<Vec<VideoSegment>> {
+        let result = self.inner.segment_video(frames.clone(), duration).await;
+        let input = json!({ "frames": frames, "duration": duration });
+        self.record("segment_video", &frames, input, &result).await;
+        result
+    }
+
+    async fn generate_timeline(
+        &self,
+        segments: Vec<VideoSegment>,
+        previous_cards: Option<Vec<TimelineCard>>,
+    ) -> Result<Vec<TimelineCard>> {
+        let input = json!({ "segments": segments, "previous_cards": previous_cards });
+        let result = self.inner.generate_timeline(segments, previous_cards).await;
+        self.record("generate_timeline", &[], input, &result).await;
+        result
+    }
+
+    async fn generate_day_summary(&self, date: &str, sessions: &[SessionBrief]) -> Result<String> {
+        let result = self.inner.generate_day_summary(date, sessions).await;
+        let input = json!({ "date": date, "sessions": sessions });
+        self.record("generate_day_summary", &[], input, &result)
+            .await;
+        result
+    }
+
+    fn set_session_window(&mut self, start: Option<DateTime<Utc>>, end: Option<DateTime<Utc>>) {
+        self.inner.set_session_window(start, end);
+    }
+
+    async fn on_start(&self) -> Result<()> {
+        self.inner.on_start().await
+    }
+
+    async fn on_stop(&self) -> Result<()> {
+        self.inner.on_stop().await
+    }
+
+    async fn reconnect(&self) -> Result<()> {
+        self.inner.reconnect().await
+    }
+
+    fn set_progress_sender(&mut self, sender: Option<ProgressSender>) {
+        self.inner.set_progress_sender(sender);
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn configure(&mut self, config: Value) -> Result<()> {
+        self.inner.configure(config)
+    }
+
+    fn is_configured(&self) -> bool {
+        self.inner.is_configured()
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn refresh_capabilities(&mut self) -> Result<()> {
+        self.inner.refresh_capabilities().await
+    }
+
+    fn estimate_cost(&self, frames: usize) -> Option<CostEstimate> {
+        self.inner.estimate_cost(frames)
+    }
+
+    fn last_llm_call_id(&self, call_type: &str) -> Option<i64> {
+        self.inner.last_llm_call_id(call_type)
+    }
+
+    fn last_sampling_seed(&self) -> Option<u64> {
+        self.inner.last_sampling_seed()
+    }
+}
+
+/// 回放提供商：按调用方法与参数匹配录制记录并返回录制的结果，不发起任何网络请求
+///
+/// 每条记录只使用一次，同一输入多次调用时按录制顺序依次返回
+pub struct ReplayProvider {
+    name: String,
+    interactions: Mutex<Vec<(RecordedInteraction, bool)>>,
+}
+
+impl ReplayProvider {
+    /// 加载录制目录下的全部记录（按文件名即录制顺序排列）
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("json"))
+            .collect();
+        files.sort();
+
+        let mut interactions = Vec::with_capacity(files.len());
+        for file in files {
+            let data = std::fs::read(&file)?;
+            let interaction: RecordedInteraction = serde_json::from_slice(&data)
+                .map_err(|e| anyhow!("录制文件 {} 解析失败: {}", file.display(), e))?;
+            interactions.push((interaction, false));
+        }
+        info!(
+            "已加载 {} 条录制记录: {}",
+            interactions.len(),
+            dir.display()
+        );
+
+        let name = interactions
+            .first()
+            .map(|(i, _)| i.provider.clone())
+            .unwrap_or_else(|| "replay".to_string());
+        Ok(Self {
+            name,
+            interactions: Mutex::new(interactions),
+        })
+    }
+
+    fn replay<T: DeserializeOwned>(&self, call: &str, input: Value) -> Result<T> {
+        let mut interactions = self
+            .interactions
+            .lock()
+            .map_err(|_| anyhow!("录制记录锁已损坏"))?;
+        let (interaction, used) = interactions
+            .iter_mut()
+            .find(|(i, used)| !*used && i.call == call && i.input == input)
+            .ok_or_else(|| anyhow!("没有匹配的录制记录: {} {}", call, input))?;
+        *used = true;
+
+        if let Some(error) = &interaction.error {
+            return Err(anyhow!("{}", error));
+        }
+        let output = interaction
+            .output
+            .clone()
+            .ok_or_else(|| anyhow!("录制记录缺少返回值: {}", call))?;
+        Ok(serde_json::from_value(output)?)
+    }
+}
+
+#[async_trait]
+impl LLMProvider for ReplayProvider {
+    fn as_any(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    async fn analyze_frames(&self, frames: Vec<String>) -> Result<SessionSummary> {
+        self.replay("analyze_frames", json!({ "frames": frames }))
+    }
+
+    async fn analyze_text(&self, content: String) -> Result<SessionSummary> {
+        self.replay("analyze_text", json!({ "content": content }))
+    }
+
+    async fn analyze_frames_with_context(
+        &self,
+        frames: Vec<String>,
+        context: AnalysisContext,
+    ) -> Result<SessionSummary> {
+        self.replay(
+            "analyze_frames_with_context",
+            json!({ "frames": frames, "context": context }),
+        )
+    }
+
+    async fn refine_field(
+        &self,
+        frames: Vec<String>,
+        current: SessionSummary,
+        field: SummaryField,
+    ) -> Result<SessionSummary> {
+        self.replay(
+            "refine_field",
+            json!({ "frames": frames, "current": current, "field": field }),
+        )
+    }
+
+    async fn segment_video(&self, frames: Vec<String>, duration: u32) -> Result<Vec<VideoSegment>> {
+        self.replay(
+            "segment_video",
+            json!({ "frames": frames, "duration": duration }),
+        )
+    }
+
+    async fn generate_timeline(
+        &self,
+        segments: Vec<VideoSegment>,
+        previous_cards: Option<Vec<TimelineCard>>,
+    ) -> Result<Vec<TimelineCard>> {
+        self.replay(
+            "generate_timeline",
+            json!({ "segments": segments, "previous_cards": previous_cards }),
+        )
+    }
+
+    async fn generate_day_summary(&self, date: &str, sessions: &[SessionBrief]) -> Result<String> {
+        self.replay(
+            "generate_day_summary",
+            json!({ "date": date, "sessions": sessions }),
+        )
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn configure(&mut self, _config: Value) -> Result<()> {
+        Ok(())
+    }
+
+    fn is_configured(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 返回固定结果的提供商，模拟一次真实调用
+    struct CannedProvider {
+        summary: SessionSummary,
+    }
+
+    #[async_trait]
+    impl LLMProvider for CannedProvider {
+        fn as_any(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+
+        async fn analyze_frames(&self, _frames: Vec<String>) -> Result<SessionSummary> {
+            Ok(self.summary.clone())
+        }
+
+        async fn analyze_text(&self, _content: String) -> Result<SessionSummary> {
+            Err(anyhow!("不支持文本分析"))
+        }
+
+        fn name(&self) -> &str {
+            "canned"
+        }
+
+        fn configure(&mut self, _config: Value) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_configured(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let frame = dir.path().join("frame_0001.jpg");
+        std::fs::write(&frame, b"fake-jpeg").unwrap();
+        let frames = vec![frame.to_string_lossy().to_string()];
+
+        let summary = SessionSummary {
+            title: "编写代码".to_string(),
+            summary: "实现录制回放".to_string(),
+            start_time: "2025-01-01T09:00:00Z".parse().unwrap(),
+            end_time: "2025-01-01T09:10:00Z".parse().unwrap(),
+            productivity_score: Some(80.0),
+            ..Default::default()
+        };
+        let record_dir = dir.path().join("recordings");
+        let recorder = RecordingProvider::new(CannedProvider { summary }, &record_dir);
+        let recorded = recorder.analyze_frames(frames.clone()).await.unwrap();
+        assert!(recorder.analyze_text("ls".to_string()).await.is_err());
+
+        // 帧内容被复制到录制目录
+        let copied = record_dir.join("frames/000000_0000.jpg");
+        assert_eq!(std::fs::read(copied).unwrap(), b"fake-jpeg");
+
+        let replay = ReplayProvider::load(&record_dir).unwrap();
+        assert_eq!(replay.name(), "canned");
+        let replayed = replay.analyze_frames(frames.clone()).await.unwrap();
+        assert_eq!(
+            serde_json::to_value(&replayed).unwrap(),
+            serde_json::to_value(&recorded).unwrap()
+        );
+
+        // 录制的错误原样回放；记录用完或输入不同都不匹配
+        let err = replay.analyze_text("ls".to_string()).await.unwrap_err();
+        assert!(err.to_string().contains("不支持文本分析"));
+        assert!(replay.analyze_frames(frames).await.is_err());
+        assert!(replay.analyze_frames(vec![]).await.is_err());
+    }
+}
@@ -0,0 +1,197 @@
+// 按场景采样 - 在画面切换（场景边界）之后取帧
+//
+// 等间隔采样与活动切换无关，短暂的活动可能落在两个采样点之间而被漏掉。
+// 相邻帧的感知哈希距离突然变大即为一次场景切换；每个场景取切换后的第一帧作为代表，
+// 场景数超过预算时保留持续最久的场景，预算有余时等间隔补齐。
+// 帧数很多时先等间隔取至多 budget × scan_factor 帧再计算哈希，控制解码开销
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+/// 按场景采样的配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneAwareConfig {
+    /// 相邻帧差异哈希（64 位）的汉明距离达到该值视为场景切换
+    pub threshold: u32,
+    /// 参与计算哈希的帧数上限为采样预算的倍数
+    pub scan_factor: usize,
+}
+
+impl Default for SceneAwareConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 16,
+            scan_factor: 8,
+        }
+    }
+}
+
+impl SceneAwareConfig {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(1..=64).contains(&self.threshold) {
+            return Err(anyhow::anyhow!("scene_aware.threshold 须在 1-64 之间"));
+        }
+        if self.scan_factor == 0 {
+            return Err(anyhow::anyhow!("scene_aware.scan_factor 须大于 0"));
+        }
+        Ok(())
+    }
+}
+
+/// 参与计算哈希的候选帧：帧数超过 budget × scan_factor 时等间隔抽取，保持时间顺序
+pub fn scan_candidates(frames: &[String], budget: usize, config: &SceneAwareConfig) -> Vec<String> {
+    let limit = budget.saturating_mul(config.scan_factor.max(1)).max(1);
+    if frames.len() <= limit {
+        return frames.to_vec();
+    }
+    (0..limit)
+        .map(|i| frames[i * frames.len() / limit].clone())
+        .collect()
+}
+
+/// 场景起点的帧序号：首帧总是起点，其后与上一张可计算哈希的帧距离不小于 threshold 的帧开始新场景
+///
+/// 无法计算哈希的帧归入当前场景
+pub fn scene_boundaries(hashes: &[Option<u64>], threshold: u32) -> Vec<usize> {
+    let mut boundaries = Vec::new();
+    let mut previous = None;
+    for (index, hash) in hashes.iter().enumerate() {
+        let is_cut = match (previous, hash) {
+            (Some(previous), Some(hash)) => {
+                crate::video::frame_hash::distance(previous, *hash) >= threshold
+            }
+            _ => false,
+        };
+        if index == 0 || is_cut {
+            boundaries.push(index);
+        }
+        if hash.is_some() {
+            previous = *hash;
+        }
+    }
+    boundaries
+}
+
+/// 在 budget 帧以内每个场景取切换后的第一帧，返回升序的帧序号
+pub fn select_scene_frames(hashes: &[Option<u64>], budget: usize, threshold: u32) -> Vec<usize> {
+    if hashes.len() <= budget {
+        return (0..hashes.len()).collect();
+    }
+    let boundaries = scene_boundaries(hashes, threshold);
+    // (起点, 帧数)，场景数超出预算时保留持续最久的场景
+    let mut scenes: Vec<(usize, usize)> = boundaries
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = boundaries.get(i + 1).copied().unwrap_or(hashes.len());
+            (start, end - start)
+        })
+        .collect();
+    scenes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    scenes.truncate(budget);
+    let mut selected: BTreeSet<usize> = scenes.iter().map(|(start, _)| *start).collect();
+
+    // 剩余预算等间隔补齐：按目标位置取最近的未选帧
+    let remaining = budget - selected.len();
+    for slot in 0..remaining {
+        let target = (2 * slot + 1) * hashes.len() / (2 * remaining);
+        let nearest = (0..hashes.len())
+            .filter(|index| !selected.contains(index))
+            .min_by_key(|index| index.abs_diff(target));
+        if let Some(index) = nearest {
+            selected.insert(index);
+        }
+    }
+    selected.into_iter().collect()
+}
+
+/// 按场景采样（hash 为每帧的感知哈希，计算哈希含图片解码时需在阻塞线程中调用）
+///
+/// 所有帧都无法计算哈希时返回 None，由调用方回退为普通采样
+pub fn sample_by_scene(
+    frames: &[String],
+    budget: usize,
+    config: &SceneAwareConfig,
+    hash: impl Fn(&str) -> Option<u64>,
+) -> Option<Vec<String>> {
+    if frames.len() <= budget {
+        return Some(frames.to_vec());
+    }
+    let hashes: Vec<Option<u64>> = frames.iter().map(|frame| hash(frame)).collect();
+    if hashes.iter().all(Option::is_none) {
+        return None;
+    }
+    Some(
+        select_scene_frames(&hashes, budget, config.threshold)
+            .into_iter()
+            .map(|index| frames[index].clone())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 5 个画面差异很大的场景，各场景内相邻帧只差 1 位
+    const SCENES: [(u64, usize); 5] = [
+        (0, 10),
+        (u64::MAX, 3),
+        (0x0000_0000_FFFF_FFFF, 20),
+        (0xFFFF_FFFF_0000_0000, 2),
+        (0x5555_5555_5555_5555, 8),
+    ];
+
+    fn frames() -> (Vec<String>, Vec<u64>) {
+        let hashes: Vec<u64> = SCENES
+            .iter()
+            .flat_map(|&(hash, count)| (0..count).map(move |i| hash ^ (i as u64 & 1)))
+            .collect();
+        let frames = (0..hashes.len())
+            .map(|i| format!("/frames/{i}.jpg"))
+            .collect();
+        (frames, hashes)
+    }
+
+    fn sample(budget: usize) -> Vec<usize> {
+        let (frames, hashes) = frames();
+        let hash = |frame: &str| {
+            let index: usize = frame[8..frame.len() - 4].parse().unwrap();
+            Some(hashes[index])
+        };
+        sample_by_scene(&frames, budget, &SceneAwareConfig::default(), hash)
+            .unwrap()
+            .iter()
+            .map(|frame| frame[8..frame.len() - 4].parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_one_frame_per_scene_is_selected() {
+        // 场景起点：0、10、13、33、35
+        let starts = vec![0, 10, 13, 33, 35];
+        let (_, hashes) = frames();
+        let hashes: Vec<Option<u64>> = hashes.into_iter().map(Some).collect();
+        assert_eq!(scene_boundaries(&hashes, 16), starts);
+        assert_eq!(sample(5), starts);
+
+        // 预算不足时保留持续最久的场景
+        assert_eq!(sample(3), vec![0, 13, 35]);
+        // 预算有余时每个场景仍有一帧，其余等间隔补齐
+        let sampled = sample(8);
+        assert_eq!(sampled.len(), 8);
+        assert!(starts.iter().all(|start| sampled.contains(start)));
+
+        // 无法计算哈希的帧不构成切换，全部无法计算时回退
+        let mut partial = hashes.clone();
+        partial[10] = None;
+        assert_eq!(scene_boundaries(&partial, 16), vec![0, 11, 13, 33, 35]);
+        let (frames, _) = frames();
+        assert!(sample_by_scene(&frames, 5, &SceneAwareConfig::default(), |_| None).is_none());
+        assert_eq!(
+            scan_candidates(&frames, 2, &SceneAwareConfig::default()).len(),
+            16
+        );
+    }
+}
@@ -0,0 +1,571 @@
+// 会话合并 - 把自动分段切得过碎的相邻会话合并为一个，并重新生成整体总结
+//
+// 帧按时间顺序拼接；采集间隔不同的会话先按较稀疏的间隔抽帧，避免密集的一段主导总结。
+// 帧数较多时按时间分块分别分析（map），再把各块总结交给模型合并为一份（reduce）。
+// 原始会话以 JSON 保存在合并记录中，合并后删除
+
+use super::{ActivityTag, KeyMoment, SessionSummary, TagTimeRange};
+use crate::actors::LLMHandle;
+use crate::storage::{local_now, Database, Frame, Session, SessionMergeRecord};
+use crate::time_mapper::TimeMapper;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
+use tracing::{info, warn};
+
+/// 单次分析的最大帧数，超出时分块分析后再合并
+pub const DEFAULT_CHUNK_FRAMES: usize = 120;
+
+/// 合并计划：合并后的会话、帧与原始会话记录
+#[derive(Debug, Clone)]
+pub struct MergePlan {
+    /// 合并后的会话（标题、摘要与标签在分析后填入）
+    pub session: Session,
+    /// 按时间排序并去重后的全部帧
+    pub frames: Vec<Frame>,
+    pub originals: Vec<SessionMergeRecord>,
+}
+
+/// 校验并生成合并计划
+///
+/// `neighbors` 为合并时间范围附近的其他会话：与合并范围重叠的会话必须一并选中，
+/// 否则合并会把它夹在中间
+pub fn plan_merge(
+    sessions: Vec<(Session, Vec<Frame>)>,
+    neighbors: &[Session],
+) -> Result<MergePlan> {
+    if sessions.len() < 2 {
+        return Err(anyhow!("至少需要选择两个会话才能合并"));
+    }
+    let mut ids = HashSet::new();
+    for (session, _) in &sessions {
+        let id = session.id.ok_or_else(|| anyhow!("会话缺少 ID"))?;
+        if !ids.insert(id) {
+            return Err(anyhow!("会话 {} 重复选择", id));
+        }
+    }
+
+    let start = sessions.iter().map(|(s, _)| s.start_time).min().unwrap();
+    let end = sessions.iter().map(|(s, _)| s.end_time).max().unwrap();
+    let between: Vec<String> = neighbors
+        .iter()
+        .filter(|n| n.id.is_some_and(|id| !ids.contains(&id)))
+        .filter(|n| n.start_time < end && n.end_time > start)
+        .filter_map(|n| n.id.map(|id| id.to_string()))
+        .collect();
+    if !between.is_empty() {
+        return Err(anyhow!(
+            "会话 {} 位于合并的时间范围内，请一并选择",
+            between.join(", ")
+        ));
+    }
+
+    // 设备信息取帧数最多的会话（通常是主要的采集设备）
+    let primary = sessions
+        .iter()
+        .max_by_key(|(_, frames)| frames.len())
+        .map(|(session, _)| session.clone())
+        .unwrap();
+    let devices: HashSet<_> = sessions
+        .iter()
+        .map(|(s, _)| s.device_name.clone())
+        .collect();
+    if devices.len() > 1 {
+        warn!(
+            "合并的会话来自 {} 台设备，合并后使用 {:?}",
+            devices.len(),
+            primary.device_name
+        );
+    }
+
+    // 任一原会话置顶时，合并后的会话保持置顶
+    let pinned = sessions.iter().any(|(s, _)| s.pinned);
+
+    let merged_at = local_now();
+    let mut originals = Vec::new();
+    let mut frames = Vec::new();
+    for (session, session_frames) in sessions {
+        originals.push(SessionMergeRecord {
+            id: None,
+            merged_session_id: 0,
+            original_session_id: session.id.unwrap_or_default(),
+            original_session: serde_json::to_string(&session)?,
+            frame_count: session_frames.len() as i64,
+            merged_at,
+        });
+        frames.extend(session_frames);
+    }
+    originals.sort_by_key(|o| o.original_session_id);
+
+    // 时间重叠的会话可能引用同一帧文件，只保留一次
+    frames.sort_by_key(|f| f.timestamp);
+    let mut seen = HashSet::new();
+    frames.retain(|f| seen.insert(f.file_path.clone()));
+
+    Ok(MergePlan {
+        session: Session {
+            id: None,
+            start_time: start,
+            end_time: end,
+            title: primary.title.clone(),
+            summary: String::new(),
+            video_path: None,
+            tags: "[]".to_string(),
+            created_at: None,
+            device_name: primary.device_name,
+            device_type: primary.device_type,
+            pinned,
+        },
+        frames,
+        originals,
+    })
+}
+
+/// 采集间隔：相邻帧时间差的中位数
+fn capture_interval(frames: &[Frame]) -> Option<Duration> {
+    let mut gaps: Vec<Duration> = frames
+        .windows(2)
+        .map(|pair| pair[1].timestamp - pair[0].timestamp)
+        .filter(|gap| *gap > Duration::zero())
+        .collect();
+    gaps.sort();
+    gaps.get(gaps.len() / 2).copied()
+}
+
+/// 选出送去分析的帧：按各原始会话中最稀疏的采集间隔抽帧，使每段时间的权重一致
+pub fn frames_for_analysis(frames: &[Frame], intervals: &[Option<Duration>]) -> Vec<Frame> {
+    let Some(interval) = intervals.iter().flatten().max().copied() else {
+        return frames.to_vec();
+    };
+    let mut kept: Vec<Frame> = Vec::new();
+    for frame in frames {
+        // 允许 10% 的抖动，避免采集间隔略有波动时漏掉帧
+        let due = kept.last().is_none_or(|last| {
+            (frame.timestamp - last.timestamp) * 10 >= interval * 9
+        });
+        if due {
+            kept.push(frame.clone());
+        }
+    }
+    kept
+}
+
+/// 合并各块的标签：同类别取最高置信度，关键词去重合并
+fn merge_tags(partials: &[SessionSummary]) -> Vec<ActivityTag> {
+    let mut tags: Vec<ActivityTag> = Vec::new();
+    for tag in partials.iter().flat_map(|p| &p.tags) {
+        match tags.iter_mut().find(|t| t.category == tag.category) {
+            Some(existing) => {
+                existing.confidence = existing.confidence.max(tag.confidence);
+                for keyword in &tag.keywords {
+                    if !existing.keywords.contains(keyword) {
+                        existing.keywords.push(keyword.clone());
+                    }
+                }
+            }
+            None => tags.push(tag.clone()),
+        }
+    }
+    tags
+}
+
+/// 各块的关键时刻换算为相对合并后会话开始的时间
+fn merge_key_moments(
+    chunks: &[(Vec<Frame>, SessionSummary)],
+    start: DateTime<Utc>,
+) -> Vec<KeyMoment> {
+    let mut moments = Vec::new();
+    for (frames, partial) in chunks {
+        let Some(mapper) = TimeMapper::from_frames(frames) else {
+            continue;
+        };
+        for moment in &partial.key_moments {
+            if let Some(time) = mapper.resolve(&moment.time) {
+                moments.push(KeyMoment {
+                    time: TimeMapper::format_offset(time - start),
+                    ..moment.clone()
+                });
+            }
+        }
+    }
+    moments
+}
+
+/// 各块中同类别标签的时间段换算为相对合并后会话开始的时间，替换 tags 中的时间段
+fn rebase_tag_time_ranges(
+    tags: &mut [ActivityTag],
+    chunks: &[(Vec<Frame>, SessionSummary)],
+    start: DateTime<Utc>,
+) {
+    for tag in tags.iter_mut() {
+        let mut ranges = Vec::new();
+        for (frames, partial) in chunks {
+            let Some(mapper) = TimeMapper::from_frames(frames) else {
+                continue;
+            };
+            let chunk_ranges = partial
+                .tags
+                .iter()
+                .filter(|t| t.category == tag.category)
+                .flat_map(|t| &t.time_ranges);
+            for range in chunk_ranges {
+                let resolved = mapper.resolve(&range.start).zip(mapper.resolve(&range.end));
+                if let Some((s, e)) = resolved {
+                    ranges.push(TagTimeRange {
+                        start: TimeMapper::format_offset(s - start),
+                        end: TimeMapper::format_offset(e - start),
+                    });
+                }
+            }
+        }
+        tag.time_ranges = ranges;
+    }
+}
+
+fn average(scores: impl Iterator<Item = Option<f32>>) -> Option<f32> {
+    let scores: Vec<f32> = scores.flatten().collect();
+    (!scores.is_empty()).then(|| scores.iter().sum::<f32>() / scores.len() as f32)
+}
+
+/// reduce 阶段的输入：各块总结按时间顺序排列
+pub(crate) fn build_reduce_content(partials: &[SessionSummary]) -> String {
+    let mut content =
+        String::from("以下是同一段工作按时间顺序分块分析得到的总结，请合并为一份整体总结：\n");
+    for (index, partial) in partials.iter().enumerate() {
+        let keywords: Vec<&str> = partial
+            .tags
+            .iter()
+            .flat_map(|t| t.keywords.iter().map(String::as_str))
+            .collect();
+        content.push_str(&format!(
+            "\n[第 {} 段] {}\n{}\n关键词: {}\n",
+            index + 1,
+            partial.title,
+            partial.summary,
+            keywords.join(", ")
+        ));
+    }
+    content
+}
+
+/// 分析一段帧：帧数不超过 chunk_frames 时一次分析，否则分块分析后合并
+///
+/// 关键时刻统一换算为相对 start 的时间
+pub async fn summarize_frames(
+    llm: &LLMHandle,
+    frames: Vec<Frame>,
+    chunk_frames: usize,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<SessionSummary> {
+    if frames.is_empty() {
+        return Err(anyhow!("没有可用于分析的帧"));
+    }
+    let chunk_frames = chunk_frames.max(1);
+    if frames.len() <= chunk_frames {
+        let paths = frames.iter().map(|f| f.file_path.clone()).collect();
+        let mut summary = llm.analyze_frames(paths).await?;
+        // 模型给出的时间相对第一帧，第一帧可能晚于 start
+        let chunk = [(frames, summary.clone())];
+        summary.key_moments = merge_key_moments(&chunk, start);
+        rebase_tag_time_ranges(&mut summary.tags, &chunk, start);
+        summary.start_time = start;
+        summary.end_time = end;
+        summary.clamp_tag_time_ranges();
+        return Ok(summary);
+    }
+
+    let mut chunks = Vec::new();
+    for (index, chunk) in frames.chunks(chunk_frames).enumerate() {
+        info!("合并分析：第 {} 块（{} 帧）", index + 1, chunk.len());
+        let paths = chunk.iter().map(|f| f.file_path.clone()).collect();
+        let partial = llm.analyze_frames(paths).await?;
+        chunks.push((chunk.to_vec(), partial));
+    }
+    let partials: Vec<SessionSummary> = chunks.iter().map(|(_, p)| p.clone()).collect();
+
+    let reduced = match llm.analyze_text(build_reduce_content(&partials)).await {
+        Ok(summary) => Some(summary),
+        Err(e) => {
+            warn!("合并各块总结失败，改为直接拼接: {}", e);
+            None
+        }
+    };
+    let mut summary = combine_chunks(reduced, &chunks, start);
+    summary.start_time = start;
+    summary.end_time = end;
+    summary.clamp_tag_time_ranges();
+    Ok(summary)
+}
+
+/// 由 reduce 的结果与各块总结得到整体总结；reduce 失败（None）时按时间顺序拼接各块总结
+///
+/// 文本合并看不到画面，关键时刻与评分取自各块，时间换算为相对 start
+pub(crate) fn combine_chunks(
+    reduced: Option<SessionSummary>,
+    chunks: &[(Vec<Frame>, SessionSummary)],
+    start: DateTime<Utc>,
+) -> SessionSummary {
+    let partials: Vec<SessionSummary> = chunks.iter().map(|(_, p)| p.clone()).collect();
+    let mut summary = reduced.unwrap_or_else(|| SessionSummary {
+        title: partials
+            .first()
+            .map(|p| p.title.clone())
+            .unwrap_or_default(),
+        summary: partials
+            .iter()
+            .map(|p| p.summary.trim())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ..Default::default()
+    });
+    if summary.tags.is_empty() {
+        summary.tags = merge_tags(&partials);
+    }
+    summary.key_moments = merge_key_moments(chunks, start);
+    rebase_tag_time_ranges(&mut summary.tags, chunks, start);
+    summary.productivity_score = average(partials.iter().map(|p| p.productivity_score));
+    summary.focus_score = average(partials.iter().map(|p| p.focus_score));
+    summary
+}
+
+/// 合并会话并重新分析，返回合并后的会话 ID
+pub async fn merge_sessions(
+    db: &Database,
+    llm: &LLMHandle,
+    ids: Vec<i64>,
+    chunk_frames: usize,
+) -> Result<i64> {
+    let mut sessions = Vec::new();
+    for id in &ids {
+        if db.get_frame_prune(*id).await?.is_some() {
+            return Err(anyhow!("会话 {} 的原始帧已清理，无法合并", id));
+        }
+        let detail = db.get_session_detail(*id).await?;
+        sessions.push((detail.session, detail.frames));
+    }
+
+    // 合并范围可能跨天，收集范围内每一天的会话用于检查夹在中间的会话
+    let start = sessions.iter().map(|(s, _)| s.start_time).min();
+    let end = sessions.iter().map(|(s, _)| s.end_time).max();
+    let mut neighbors = Vec::new();
+    if let (Some(start), Some(end)) = (start, end) {
+        let mut day = start.date_naive();
+        while day <= end.date_naive() {
+            neighbors.extend(db.get_sessions_by_date(&day.to_string()).await?);
+            day = day.succ_opt().ok_or_else(|| anyhow!("日期超出范围"))?;
+        }
+    }
+
+    let intervals: Vec<Option<Duration>> = sessions
+        .iter()
+        .map(|(_, frames)| capture_interval(frames))
+        .collect();
+    let mut plan = plan_merge(sessions, &neighbors)?;
+    let analysis_frames = frames_for_analysis(&plan.frames, &intervals);
+    info!(
+        "合并会话 {:?}：共 {} 帧，分析 {} 帧",
+        ids,
+        plan.frames.len(),
+        analysis_frames.len()
+    );
+
+    let summary = summarize_frames(
+        llm,
+        analysis_frames,
+        chunk_frames,
+        plan.session.start_time,
+        plan.session.end_time,
+    )
+    .await?;
+    plan.session.title = summary.title.clone();
+    plan.session.summary = summary.summary.clone();
+    plan.session.tags = serde_json::to_string(&summary.tags)?;
+
+    db.merge_sessions(&plan.session, &plan.frames, &plan.originals)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::ActivityCategory;
+
+    fn at(minutes: i64, seconds: i64) -> DateTime<Utc> {
+        "2025-01-01T09:00:00Z".parse::<DateTime<Utc>>().unwrap()
+            + Duration::minutes(minutes)
+            + Duration::seconds(seconds)
+    }
+
+    fn session(id: i64, start: DateTime<Utc>, end: DateTime<Utc>) -> Session {
+        Session {
+            id: Some(id),
+            start_time: start,
+            end_time: end,
+            title: format!("会话 {}", id),
+            summary: String::new(),
+            video_path: None,
+            tags: "[]".to_string(),
+            created_at: None,
+            device_name: Some("desk".to_string()),
+            device_type: Some("desktop".to_string()),
+            pinned: false,
+        }
+    }
+
+    fn frames(session_id: i64, start: DateTime<Utc>, count: i64, every_secs: i64) -> Vec<Frame> {
+        (0..count)
+            .map(|i| Frame {
+                id: None,
+                session_id,
+                timestamp: start + Duration::seconds(i * every_secs),
+                file_path: format!("{}_{}.jpg", session_id, i),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_merge_orders_frames_and_rejects_gaps_with_other_sessions() {
+        // 会话 2 在前、会话 1 在后，且时间有重叠并共享一帧
+        let mut late = frames(1, at(10, 0), 3, 60);
+        late.push(Frame {
+            file_path: "2_2.jpg".to_string(),
+            ..late[0].clone()
+        });
+        let early = frames(2, at(0, 0), 3, 300);
+        let plan = plan_merge(
+            vec![
+                (session(1, at(9, 0), at(15, 0)), late),
+                (session(2, at(0, 0), at(10, 30)), early.clone()),
+            ],
+            &[
+                session(1, at(9, 0), at(15, 0)),
+                session(3, at(30, 0), at(40, 0)),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            (plan.session.start_time, plan.session.end_time),
+            (at(0, 0), at(15, 0))
+        );
+        assert_eq!(plan.frames.len(), 6);
+        assert!(plan
+            .frames
+            .windows(2)
+            .all(|w| w[0].timestamp <= w[1].timestamp));
+        assert_eq!(plan.frames[0].file_path, "2_0.jpg");
+        let ids: Vec<i64> = plan
+            .originals
+            .iter()
+            .map(|o| o.original_session_id)
+            .collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(plan.originals[0].frame_count, 4);
+        assert!(plan.originals[1].original_session.contains("会话 2"));
+
+        // 夹在中间的会话未选中时拒绝合并
+        let err = plan_merge(
+            vec![
+                (session(1, at(0, 0), at(10, 0)), frames(1, at(0, 0), 2, 60)),
+                (
+                    session(3, at(30, 0), at(40, 0)),
+                    frames(3, at(30, 0), 2, 60),
+                ),
+            ],
+            &[session(2, at(12, 0), at(20, 0))],
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains('2'));
+
+        let single = vec![(session(1, at(0, 0), at(10, 0)), Vec::new())];
+        assert!(plan_merge(single, &[]).is_err());
+    }
+
+    #[test]
+    fn test_denser_capture_is_thinned_to_sparser_interval() {
+        // 会话 1 每 10 秒一帧，会话 2 每 60 秒一帧
+        let dense = frames(1, at(0, 0), 30, 10);
+        let sparse = frames(2, at(5, 0), 5, 60);
+        let intervals = [capture_interval(&dense), capture_interval(&sparse)];
+        assert_eq!(intervals[0], Some(Duration::seconds(10)));
+
+        let mut all = dense;
+        all.extend(sparse);
+        let kept = frames_for_analysis(&all, &intervals);
+        assert_eq!(kept.len(), 10);
+        assert!(kept
+            .windows(2)
+            .all(|w| w[1].timestamp - w[0].timestamp >= Duration::seconds(54)));
+
+        // 都只有一帧时无法推断间隔，全部保留
+        let single = frames(1, at(0, 0), 1, 10);
+        assert_eq!(frames_for_analysis(&single, &[None]).len(), 1);
+    }
+
+    #[test]
+    fn test_partials_combine_tags_and_rebase_key_moments() {
+        let tag = |category, confidence, keyword: &str| ActivityTag {
+            category,
+            confidence,
+            keywords: vec![keyword.to_string()],
+            subcategories: Vec::new(),
+            time_ranges: Vec::new(),
+        };
+        let moment = |time: &str| KeyMoment {
+            time: time.to_string(),
+            description: "提交代码".to_string(),
+            importance: 4,
+        };
+        let first = SessionSummary {
+            title: "写代码".to_string(),
+            summary: "实现合并".to_string(),
+            tags: vec![tag(ActivityCategory::Work, 0.6, "rust")],
+            key_moments: vec![moment("01:00")],
+            ..Default::default()
+        };
+        let mut slack = tag(ActivityCategory::Communication, 0.5, "slack");
+        slack.time_ranges = vec![TagTimeRange {
+            start: "00:10".to_string(),
+            end: "01:00".to_string(),
+        }];
+        let second = SessionSummary {
+            title: "调试".to_string(),
+            tags: vec![tag(ActivityCategory::Work, 0.9, "sqlx"), slack],
+            key_moments: vec![moment("00:30"), moment("无法解析")],
+            ..Default::default()
+        };
+
+        let tags = merge_tags(&[first.clone(), second.clone()]);
+        assert_eq!(tags.len(), 2);
+        assert_eq!(tags[0].confidence, 0.9);
+        assert_eq!(tags[0].keywords, vec!["rust", "sqlx"]);
+
+        // 第二块从 20:00 开始，其 00:30 对应合并后的 20:30
+        let chunks = vec![
+            (frames(1, at(0, 0), 3, 60), first.clone()),
+            (frames(2, at(20, 0), 3, 60), second.clone()),
+        ];
+        let times: Vec<String> = merge_key_moments(&chunks, at(0, 0))
+            .into_iter()
+            .map(|m| m.time)
+            .collect();
+        assert_eq!(times, vec!["01:00", "20:30"]);
+
+        let mut merged = tags.clone();
+        rebase_tag_time_ranges(&mut merged, &chunks, at(0, 0));
+        assert!(merged[0].time_ranges.is_empty());
+        assert_eq!(
+            merged[1].time_ranges,
+            vec![TagTimeRange {
+                start: "20:10".to_string(),
+                end: "21:00".to_string(),
+            }]
+        );
+
+        let content = build_reduce_content(&[first, second]);
+        assert!(content.contains("[第 1 段] 写代码") && content.contains("[第 2 段] 调试"));
+        assert!(content.contains("sqlx, slack"));
+    }
+}
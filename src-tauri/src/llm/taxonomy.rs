@@ -0,0 +1,336 @@
+// 分类体系 - 在固定的顶层活动类别下配置多级子类别
+//
+// 顶层仍是 ActivityCategory 的 6 个类别，未配置子类别时即为原来的扁平分类（默认分类体系），
+// 存量数据与按 category 统计的查询不受影响。模型以 work/coding/frontend 的形式输出完整路径，
+// 解析时按配置校验：顶层写入 category，其余层级写入标签的 subcategories，未知的子类别截断到最近的已知父类别。
+// 统计时按层级汇总，父类别的时长包含其全部子类别
+
+use super::plugin::{ActivityCategory, ActivityTag};
+use super::TaxonomyNode;
+use crate::storage::Session;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+
+/// 路径分隔符（解析模型输出时也接受 >）
+pub const PATH_SEPARATOR: char = '/';
+
+/// 子类别的最大层级（不含顶层类别）
+pub const MAX_SUBCATEGORY_DEPTH: usize = 4;
+
+/// 按顶层类别名组织的子类别（OllamaConfig.taxonomy）
+pub type Taxonomy = BTreeMap<String, Vec<TaxonomyNode>>;
+
+/// 顶层类别名（不区分大小写）对应的类别
+pub fn parse_category(name: &str) -> Option<ActivityCategory> {
+    serde_json::from_value(Value::String(name.trim().to_lowercase())).ok()
+}
+
+/// 顶层类别下配置的子类别
+fn subcategories_of<'a>(taxonomy: &'a Taxonomy, category: &ActivityCategory) -> &'a [TaxonomyNode] {
+    taxonomy
+        .iter()
+        .find(|(name, _)| parse_category(name).as_ref() == Some(category))
+        .map(|(_, nodes)| nodes.as_slice())
+        .unwrap_or_default()
+}
+
+/// 校验配置：键须为顶层类别，子类别名非空、不含分隔符且同级不重复，层级不超过上限
+pub fn validate_taxonomy(taxonomy: &Taxonomy) -> Result<()> {
+    fn check(parent: &str, nodes: &[TaxonomyNode], depth: usize) -> Result<()> {
+        if !nodes.is_empty() && depth > MAX_SUBCATEGORY_DEPTH {
+            return Err(anyhow!(
+                "分类 {} 的子类别超过 {} 层",
+                parent,
+                MAX_SUBCATEGORY_DEPTH
+            ));
+        }
+        let mut seen = HashSet::new();
+        for node in nodes {
+            let name = node.name.trim();
+            if name.is_empty() || name.contains([PATH_SEPARATOR, '>']) {
+                return Err(anyhow!("分类 {} 下的子类别名 {:?} 无效", parent, node.name));
+            }
+            if !seen.insert(name.to_lowercase()) {
+                return Err(anyhow!("分类 {} 下的子类别 {} 重复", parent, name));
+            }
+            check(
+                &format!("{}{}{}", parent, PATH_SEPARATOR, name),
+                &node.children,
+                depth + 1,
+            )?;
+        }
+        Ok(())
+    }
+
+    let mut seen = HashSet::new();
+    for (name, nodes) in taxonomy {
+        let Some(category) = parse_category(name) else {
+            return Err(anyhow!("未知的顶层类别 {}", name));
+        };
+        if !seen.insert(category.as_str()) {
+            return Err(anyhow!("顶层类别 {} 重复", category.as_str()));
+        }
+        check(category.as_str(), nodes, 1)?;
+    }
+    Ok(())
+}
+
+/// 按分类体系解析后的类别路径
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedPath {
+    pub category: ActivityCategory,
+    pub subcategories: Vec<String>,
+    /// 路径中含有未配置的子类别，已截断到最近的已知父类别
+    pub truncated: bool,
+}
+
+/// 解析类别路径（如 work/coding/frontend 或 work > coding）；顶层类别未知时返回 None
+pub fn resolve_path(path: &str, taxonomy: &Taxonomy) -> Option<ResolvedPath> {
+    let mut segments = path
+        .split([PATH_SEPARATOR, '>'])
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty());
+    let category = parse_category(segments.next()?)?;
+
+    let mut nodes = subcategories_of(taxonomy, &category);
+    let mut subcategories = Vec::new();
+    let mut truncated = false;
+    for segment in segments {
+        match nodes
+            .iter()
+            .find(|node| node.name.trim().eq_ignore_ascii_case(segment))
+        {
+            Some(node) => {
+                subcategories.push(node.name.trim().to_string());
+                nodes = &node.children;
+            }
+            None => {
+                truncated = true;
+                break;
+            }
+        }
+    }
+    Some(ResolvedPath {
+        category,
+        subcategories,
+        truncated,
+    })
+}
+
+/// 把模型输出中标签的类别路径拆为 category 与 subcategories（在反序列化为 SessionSummary 之前调用）
+///
+/// 顶层类别未知的标签保持原样，仍由反序列化报错；返回被截断的路径数
+pub fn normalize_tag_paths(summary: &mut Value, taxonomy: &Taxonomy) -> usize {
+    let Some(tags) = summary.get_mut("tags").and_then(Value::as_array_mut) else {
+        return 0;
+    };
+    let mut truncated = 0;
+    for tag in tags.iter_mut().filter_map(Value::as_object_mut) {
+        let Some(resolved) = tag
+            .get("category")
+            .and_then(Value::as_str)
+            .and_then(|path| resolve_path(path, taxonomy))
+        else {
+            continue;
+        };
+        if resolved.truncated {
+            truncated += 1;
+        }
+        tag.insert(
+            "category".to_string(),
+            Value::String(resolved.category.as_str().to_string()),
+        );
+        if resolved.subcategories.is_empty() {
+            tag.remove("subcategories");
+        } else {
+            tag.insert(
+                "subcategories".to_string(),
+                Value::from(resolved.subcategories),
+            );
+        }
+    }
+    truncated
+}
+
+/// 提示词中的分类体系说明；未配置子类别时返回 None（沿用扁平类别）
+pub fn build_taxonomy_note(taxonomy: &Taxonomy) -> Option<String> {
+    fn walk<'a>(prefix: &str, nodes: &'a [TaxonomyNode], paths: &mut Vec<(String, &'a str)>) {
+        for node in nodes {
+            let path = format!("{}{}{}", prefix, PATH_SEPARATOR, node.name.trim());
+            paths.push((path.clone(), node.description.trim()));
+            walk(&path, &node.children, paths);
+        }
+    }
+
+    let mut paths = Vec::new();
+    for (name, nodes) in taxonomy {
+        if let Some(category) = parse_category(name) {
+            walk(category.as_str(), nodes, &mut paths);
+        }
+    }
+    // 以层级最深的路径为例
+    let example = paths
+        .iter()
+        .map(|(path, _)| path)
+        .max_by_key(|path| path.matches(PATH_SEPARATOR).count())?;
+    let lines: Vec<String> = paths
+        .iter()
+        .map(|(path, description)| {
+            if description.is_empty() {
+                format!("- {}", path)
+            } else {
+                format!("- {}：{}", path, description)
+            }
+        })
+        .collect();
+    Some(format!(
+        "\ncategory 可以细化到下列子类别，细化时写出完整路径（如 {}），没有合适的子类别时只写顶层类别：\n{}",
+        example,
+        lines.join("\n")
+    ))
+}
+
+/// 按类别路径汇总会话时长（分钟）：会话计入首个标签（主要活动）的路径及其每一级父类别，
+/// 只统计到 depth 层（1 为顶层类别）；标签无法解析的会话计入 other
+pub fn category_minutes(sessions: &[Session], depth: usize) -> BTreeMap<String, i64> {
+    allocated_category_minutes(
+        sessions.iter().map(|session| {
+            let minutes = (session.end_time - session.start_time).num_minutes();
+            (session, minutes)
+        }),
+        depth,
+    )
+}
+
+/// 同 category_minutes，但每个会话只计入给定的分钟数（如跨午夜会话按日切分后当天的部分）
+pub fn allocated_category_minutes<'a>(
+    sessions: impl IntoIterator<Item = (&'a Session, i64)>,
+    depth: usize,
+) -> BTreeMap<String, i64> {
+    let mut totals = BTreeMap::new();
+    for (session, minutes) in sessions {
+        let path = serde_json::from_str::<Vec<ActivityTag>>(&session.tags)
+            .ok()
+            .and_then(|tags| tags.first().map(ActivityTag::path))
+            .unwrap_or_else(|| ActivityCategory::Other.as_str().to_string());
+        let minutes = minutes.max(0);
+        let segments: Vec<&str> = path.split(PATH_SEPARATOR).collect();
+        for level in 1..=depth.max(1).min(segments.len()) {
+            *totals.entry(segments[..level].join("/")).or_default() += minutes;
+        }
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration, Utc};
+
+    fn node(name: &str, children: Vec<TaxonomyNode>) -> TaxonomyNode {
+        TaxonomyNode {
+            name: name.to_string(),
+            description: String::new(),
+            children,
+        }
+    }
+
+    /// work > coding > frontend/backend，work > meeting
+    fn taxonomy() -> Taxonomy {
+        BTreeMap::from([(
+            "work".to_string(),
+            vec![
+                node(
+                    "coding",
+                    vec![node("frontend", vec![]), node("backend", vec![])],
+                ),
+                node("meeting", vec![]),
+            ],
+        )])
+    }
+
+    fn session(minutes: i64, tags: &str) -> Session {
+        let start = "2025-01-01T09:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        Session {
+            id: None,
+            start_time: start,
+            end_time: start + Duration::minutes(minutes),
+            title: String::new(),
+            summary: String::new(),
+            video_path: None,
+            tags: tags.to_string(),
+            created_at: None,
+            device_name: None,
+            device_type: None,
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn test_paths_are_validated_against_taxonomy() {
+        let taxonomy = taxonomy();
+        assert!(validate_taxonomy(&taxonomy).is_ok());
+        assert!(validate_taxonomy(&BTreeMap::from([("gaming".to_string(), vec![])])).is_err());
+        let duplicated = BTreeMap::from([(
+            "work".to_string(),
+            vec![node("coding", vec![]), node("Coding", vec![])],
+        )]);
+        assert!(validate_taxonomy(&duplicated).is_err());
+
+        let resolved = resolve_path("Work > coding > frontend", &taxonomy).unwrap();
+        assert_eq!(resolved.category, ActivityCategory::Work);
+        assert_eq!(resolved.subcategories, vec!["coding", "frontend"]);
+        assert!(!resolved.truncated);
+        // 未配置的子类别截断到最近的已知父类别
+        let resolved = resolve_path("work/coding/mobile", &taxonomy).unwrap();
+        assert_eq!(resolved.subcategories, vec!["coding"]);
+        assert!(resolved.truncated);
+        assert!(resolve_path("gaming/strategy", &taxonomy).is_none());
+
+        let mut summary = serde_json::json!({"tags": [
+            {"category": "work/coding/backend", "confidence": 0.9, "keywords": []},
+            {"category": "learning", "confidence": 0.5, "keywords": [], "subcategories": ["x"]},
+        ]});
+        assert_eq!(normalize_tag_paths(&mut summary, &taxonomy), 0);
+        let tags: Vec<ActivityTag> = serde_json::from_value(summary["tags"].clone()).unwrap();
+        assert_eq!(tags[0].path(), "work/coding/backend");
+        assert_eq!(tags[1].path(), "learning");
+        assert!(build_taxonomy_note(&taxonomy)
+            .unwrap()
+            .contains("- work/coding/frontend"));
+        assert!(build_taxonomy_note(&Taxonomy::new()).is_none());
+    }
+
+    #[test]
+    fn test_minutes_roll_up_to_parent_categories() {
+        let sessions = vec![
+            session(
+                30,
+                r#"[{"category":"work","subcategories":["coding","frontend"],"confidence":0.9,"keywords":[]}]"#,
+            ),
+            session(
+                20,
+                r#"[{"category":"work","subcategories":["meeting"],"confidence":0.9,"keywords":[]}]"#,
+            ),
+            // 旧数据没有子类别
+            session(
+                10,
+                r#"[{"category":"work","confidence":0.9,"keywords":[]}]"#,
+            ),
+            session(5, "invalid"),
+        ];
+
+        let top = category_minutes(&sessions, 1);
+        assert_eq!(
+            top,
+            BTreeMap::from([("work".to_string(), 60), ("other".to_string(), 5)])
+        );
+
+        let two_levels = category_minutes(&sessions, 2);
+        assert_eq!(two_levels["work"], 60);
+        assert_eq!(two_levels["work/coding"], 30);
+        assert_eq!(two_levels["work/meeting"], 20);
+        assert!(!two_levels.contains_key("work/coding/frontend"));
+    }
+}
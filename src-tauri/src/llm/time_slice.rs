@@ -0,0 +1,222 @@
+// 时间片分析 - 只分析会话中的一段时间（例如长会话的最后 10 分钟）
+//
+// 起止时间可以是相对会话开始的 MM:SS / HH:MM:SS，也可以是 RFC3339 时间，经由 TimeMapper 换算；
+// 按逐帧截图时间选出范围内的帧分析，返回只覆盖该时间段的总结，默认不覆盖整个会话的总结
+
+use super::session_merge::{summarize_frames, DEFAULT_CHUNK_FRAMES};
+use super::SessionSummary;
+use crate::actors::LLMHandle;
+use crate::storage::{Database, Frame, Session};
+use crate::time_mapper::TimeMapper;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use tracing::info;
+
+/// 所选时间段内没有截图帧
+///
+/// 调用方可通过 `downcast_ref::<EmptyTimeSlice>()` 识别
+#[derive(Clone, Debug, PartialEq)]
+pub struct EmptyTimeSlice {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl std::fmt::Display for EmptyTimeSlice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "所选时间段 {} - {} 内没有截图帧",
+            self.start.format("%Y-%m-%d %H:%M:%S"),
+            self.end.format("%Y-%m-%d %H:%M:%S")
+        )
+    }
+}
+
+impl std::error::Error for EmptyTimeSlice {}
+
+/// 会话中的一段时间（闭区间）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeSlice {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// 解析起止时间并校验是否位于会话范围内
+pub fn resolve_slice(session: &Session, start: &str, end: &str) -> Result<TimeSlice> {
+    let mapper = TimeMapper::from_timestamps(vec![session.start_time])
+        .ok_or_else(|| anyhow!("会话缺少开始时间"))?;
+    let resolve = |label: &str| {
+        mapper
+            .resolve(label.trim())
+            .ok_or_else(|| anyhow!("无法解析时间 {:?}，应为 MM:SS、HH:MM:SS 或 RFC3339", label))
+    };
+    let slice = TimeSlice {
+        start: resolve(start)?,
+        end: resolve(end)?,
+    };
+
+    if slice.start >= slice.end {
+        return Err(anyhow!("开始时间必须早于结束时间"));
+    }
+    if slice.start < session.start_time || slice.end > session.end_time {
+        return Err(anyhow!(
+            "时间段 {} - {} 超出会话范围 {} - {}",
+            slice.start.format("%H:%M:%S"),
+            slice.end.format("%H:%M:%S"),
+            session.start_time.format("%H:%M:%S"),
+            session.end_time.format("%H:%M:%S")
+        ));
+    }
+    Ok(slice)
+}
+
+/// 截图时间落在时间段内的帧（按时间排序）；没有帧时返回 EmptyTimeSlice
+pub fn select_frames(frames: &[Frame], slice: TimeSlice) -> Result<Vec<Frame>> {
+    let mut selected: Vec<Frame> = frames
+        .iter()
+        .filter(|f| f.timestamp >= slice.start && f.timestamp <= slice.end)
+        .cloned()
+        .collect();
+    if selected.is_empty() {
+        return Err(EmptyTimeSlice {
+            start: slice.start,
+            end: slice.end,
+        }
+        .into());
+    }
+    selected.sort_by_key(|f| f.timestamp);
+    Ok(selected)
+}
+
+/// 分析会话的一段时间，返回该时间段的总结；overwrite 为 true 时用其替换会话的标题、摘要与标签
+pub async fn analyze_time_range(
+    db: &Database,
+    llm: &LLMHandle,
+    session_id: i64,
+    start: &str,
+    end: &str,
+    overwrite: bool,
+) -> Result<SessionSummary> {
+    if db.get_frame_prune(session_id).await?.is_some() {
+        return Err(anyhow!(
+            "会话 {} 的原始帧已清理，无法按时间段分析",
+            session_id
+        ));
+    }
+    let detail = db.get_session_detail(session_id).await?;
+    let slice = resolve_slice(&detail.session, start, end)?;
+    let frames = select_frames(&detail.frames, slice)?;
+    info!(
+        "分析会话 {} 的时间段 {} - {}（{} 帧）",
+        session_id,
+        slice.start,
+        slice.end,
+        frames.len()
+    );
+
+    let summary =
+        summarize_frames(llm, frames, DEFAULT_CHUNK_FRAMES, slice.start, slice.end).await?;
+
+    if overwrite {
+        let session = &detail.session;
+        db.update_session(
+            session_id,
+            &summary.title,
+            &summary.summary,
+            session.video_path.as_deref(),
+            &serde_json::to_string(&summary.tags)?,
+        )
+        .await?;
+        info!("已用时间段总结替换会话 {} 的总结", session_id);
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        "2025-01-01T09:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::minutes(minutes)
+    }
+
+    fn session() -> Session {
+        Session {
+            id: Some(1),
+            start_time: at(0),
+            end_time: at(60),
+            title: "长会话".to_string(),
+            summary: String::new(),
+            video_path: None,
+            tags: "[]".to_string(),
+            created_at: None,
+            device_name: None,
+            device_type: None,
+            pinned: false,
+        }
+    }
+
+    #[test]
+    fn test_resolve_slice_accepts_offsets_and_rfc3339_within_bounds() {
+        let session = session();
+        assert_eq!(
+            resolve_slice(&session, "50:00", "01:00:00").unwrap(),
+            TimeSlice {
+                start: at(50),
+                end: at(60)
+            }
+        );
+        assert_eq!(
+            resolve_slice(&session, "2025-01-01T09:10:00+08:00", "20:00")
+                .unwrap()
+                .start,
+            at(10)
+        );
+
+        assert!(resolve_slice(&session, "20:00", "10:00").is_err());
+        assert!(resolve_slice(&session, "50:00", "01:10:00")
+            .unwrap_err()
+            .to_string()
+            .contains("超出会话范围"));
+        assert!(resolve_slice(&session, "最后十分钟", "60:00").is_err());
+    }
+
+    #[test]
+    fn test_select_frames_by_timestamp_and_empty_slice() {
+        let frames: Vec<Frame> = [5, 52, 51, 58, 30]
+            .iter()
+            .map(|m| Frame {
+                id: None,
+                session_id: 1,
+                timestamp: at(*m),
+                file_path: format!("{}.jpg", m),
+            })
+            .collect();
+
+        let slice = TimeSlice {
+            start: at(50),
+            end: at(60),
+        };
+        let selected: Vec<String> = select_frames(&frames, slice)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.file_path)
+            .collect();
+        assert_eq!(selected, vec!["51.jpg", "52.jpg", "58.jpg"]);
+
+        let empty = TimeSlice {
+            start: at(10),
+            end: at(20),
+        };
+        let err = select_frames(&frames, empty).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<EmptyTimeSlice>(),
+            Some(&EmptyTimeSlice {
+                start: at(10),
+                end: at(20)
+            })
+        );
+        assert!(err.to_string().contains("没有截图帧"));
+    }
+}
@@ -12,6 +12,8 @@ pub use crate::storage::{Activity, DatabaseConfig, Frame, Session, SessionDetail
 pub struct AppConfig {
     /// 数据保留天数
     pub retention_days: Option<i64>,
+    /// 存储预算（字节），0 表示不限制
+    pub max_storage_bytes: Option<u64>,
     /// LLM提供商
     pub llm_provider: Option<String>,
     /// 截屏间隔（秒）
@@ -32,6 +34,26 @@ pub struct AppConfig {
     pub database_config: Option<DatabaseConfig>,
     /// Notion 配置
     pub notion_config: Option<NotionConfig>,
+    /// 分析后帧清理配置
+    pub frame_cleanup: Option<FrameCleanupSettings>,
+    /// 帧存储配置
+    pub frame_storage: Option<FrameStorageSettings>,
+    /// 会话封面缩略图配置
+    pub session_thumbnail: Option<SessionThumbnailSettings>,
+    /// 截屏看门狗配置
+    pub capture_watchdog: Option<CaptureWatchdogSettings>,
+    /// 后台分析并发配置
+    pub analysis_concurrency: Option<AnalysisConcurrencySettings>,
+    /// 按屏幕内容变化切分会话的配置
+    pub activity_change: Option<ActivityChangeSettings>,
+    /// Prometheus 指标导出配置
+    pub metrics_exporter: Option<MetricsExporterSettings>,
+    /// Provider 配置档文件
+    pub provider_profiles: Option<ProviderProfilesSettings>,
+    /// 分析完成通知
+    pub webhook: Option<WebhookSettings>,
+    /// 深度工作识别
+    pub deep_work: Option<DeepWorkSettings>,
 }
 
 /// 日志设置
@@ -137,11 +159,243 @@ impl Default for VideoSettings {
     }
 }
 
+/// 分析后帧清理设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrameCleanupSettings {
+    /// 分析成功后是否删除会话的原始帧文件（需显式开启）
+    pub delete_frames_after_analysis: bool,
+    /// 是否保留关键时刻对应的帧
+    pub keep_key_moment_frames: bool,
+}
+
+impl Default for FrameCleanupSettings {
+    fn default() -> Self {
+        Self {
+            delete_frames_after_analysis: false,
+            keep_key_moment_frames: true,
+        }
+    }
+}
+
+/// 帧存储设置
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct FrameStorageSettings {
+    /// 分析完成后将会话的零散帧打包为单个归档文件
+    pub pack_frames_after_analysis: bool,
+}
+
+/// 会话封面的代表帧选取方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ThumbnailStrategy {
+    /// 会话首帧
+    First,
+    /// 会话中间的一帧
+    Middle,
+    /// 重要性最高的关键时刻对应的帧（没有关键时刻时取中间帧）
+    #[default]
+    KeyMoment,
+}
+
+/// 会话封面缩略图设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionThumbnailSettings {
+    /// 分析完成后是否为会话生成封面（需显式开启）
+    pub enabled: bool,
+    /// 代表帧选取方式
+    pub strategy: ThumbnailStrategy,
+    /// 封面宽度（像素）
+    pub width: u32,
+    /// 封面高度（像素）
+    pub height: u32,
+}
+
+impl Default for SessionThumbnailSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            strategy: ThumbnailStrategy::default(),
+            width: 320,
+            height: 180,
+        }
+    }
+}
+
+/// 截屏看门狗设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureWatchdogSettings {
+    /// 是否启用看门狗
+    pub enabled: bool,
+    /// 超过该时长（秒）没有新帧即视为截屏卡死
+    pub stall_threshold_secs: u64,
+    /// 检查间隔（秒）
+    pub check_interval_secs: u64,
+}
+
+impl Default for CaptureWatchdogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            stall_threshold_secs: 60,
+            check_interval_secs: 10,
+        }
+    }
+}
+
+/// 后台分析并发设置：准备（读帧、筛帧、生成视频）与推理分别限流
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnalysisConcurrencySettings {
+    /// 同时准备的会话数（I/O 密集）
+    pub preparation_concurrency: usize,
+    /// 同时进行的模型推理数（GPU 密集，与用户触发的分析共享）；
+    /// provider 按会话保存状态，目前最多为 1，超出时按 1 执行
+    pub inference_concurrency: usize,
+}
+
+impl Default for AnalysisConcurrencySettings {
+    fn default() -> Self {
+        Self {
+            preparation_concurrency: crate::llm::queue::DEFAULT_PREPARATION_CONCURRENCY,
+            inference_concurrency: crate::llm::queue::DEFAULT_MAX_CONCURRENT_ANALYSES,
+        }
+    }
+}
+
+/// 按屏幕内容变化切分会话的设置：画面或前台应用持续变化时结束当前会话并开始新会话
+///
+/// 会话仍不超过固定的会话时长，内容变化只会把会话切得更短
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivityChangeSettings {
+    /// 是否启用
+    pub enabled: bool,
+    /// 灵敏度（0-1），越高越容易判定为变化
+    pub sensitivity: f32,
+    /// 作为比较基准的最近稳定帧数（滑动窗口）
+    pub window_frames: usize,
+    /// 连续多少帧变化才确认切换（过滤弹窗等短暂变化）
+    pub confirm_frames: usize,
+    /// 会话最短时长（秒），未达到时不切分
+    pub min_session_secs: u64,
+    /// 前台应用切换也视为变化
+    pub detect_app_switch: bool,
+}
+
+impl Default for ActivityChangeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sensitivity: 0.5,
+            window_frames: 10,
+            confirm_frames: 3,
+            min_session_secs: 120,
+            detect_app_switch: true,
+        }
+    }
+}
+
+/// Prometheus 指标导出设置：在本地端口提供 GET /metrics 抓取入口（修改后重启生效）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsExporterSettings {
+    /// 是否启用
+    pub enabled: bool,
+    /// 监听地址
+    pub bind_address: String,
+}
+
+impl Default for MetricsExporterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind_address: "127.0.0.1:9464".to_string(),
+        }
+    }
+}
+
+/// Provider 配置档文件设置（启动时按文件中的配置档配置 LLM provider）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderProfilesSettings {
+    /// 配置档文件路径，相对路径相对于应用数据目录；.json 按 JSON 解析，其余按 TOML
+    pub path: String,
+    /// 启动时应用的配置档名，为空时使用文件中的 default
+    #[serde(default)]
+    pub active: Option<String>,
+}
+
+/// 分析完成通知设置：会话总结入库后向 URL 推送 JSON（尽力而为，失败不影响分析）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookSettings {
+    /// 是否启用
+    pub enabled: bool,
+    /// 接收通知的 URL
+    pub url: String,
+    /// 签名密钥，设置后请求附带 HMAC-SHA256 签名头
+    pub secret: Option<String>,
+    /// 单次请求超时（秒）
+    pub timeout_secs: u64,
+    /// 失败后的重试次数，用尽后丢弃
+    pub max_retries: u32,
+    /// 首次重试前的等待（毫秒），之后每次翻倍
+    pub retry_delay_ms: u64,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            secret: None,
+            timeout_secs: 10,
+            max_retries: 3,
+            retry_delay_ms: 1000,
+        }
+    }
+}
+
+/// 深度工作识别设置：专注度达到阈值、持续使用同一应用足够久的时段记为深度工作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeepWorkSettings {
+    /// 是否在分析完成后识别
+    pub enabled: bool,
+    /// 最短时长（分钟）
+    pub min_duration_mins: u64,
+    /// 专注度阈值（0-100）
+    pub min_focus: f32,
+    /// 可以跨过的单次打断最长时长（秒）
+    pub max_interruption_secs: i64,
+    /// 每小时允许的最多打断次数
+    pub max_switches_per_hour: f64,
+    /// 相邻帧间隔超过该值（秒）视为中断
+    pub max_gap_secs: i64,
+}
+
+impl Default for DeepWorkSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_duration_mins: 25,
+            min_focus: 70.0,
+            max_interruption_secs: 120,
+            max_switches_per_hour: 4.0,
+            max_gap_secs: 300,
+        }
+    }
+}
+
 /// 持久化的应用配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PersistedAppConfig {
     /// 数据保留天数
     pub retention_days: i64,
+    /// 存储预算（字节），超出时从最旧的未置顶会话开始淘汰；None 表示不限制
+    #[serde(default)]
+    pub max_storage_bytes: Option<u64>,
     /// LLM提供商
     pub llm_provider: String,
     /// 截屏间隔（秒）
@@ -162,12 +416,33 @@ pub struct PersistedAppConfig {
     pub database_config: Option<DatabaseConfig>,
     /// Notion 配置
     pub notion_config: Option<NotionConfig>,
+    /// 分析后帧清理配置
+    pub frame_cleanup: Option<FrameCleanupSettings>,
+    /// 帧存储配置
+    pub frame_storage: Option<FrameStorageSettings>,
+    /// 会话封面缩略图配置
+    pub session_thumbnail: Option<SessionThumbnailSettings>,
+    /// 截屏看门狗配置
+    pub capture_watchdog: Option<CaptureWatchdogSettings>,
+    /// 后台分析并发配置
+    pub analysis_concurrency: Option<AnalysisConcurrencySettings>,
+    /// 按屏幕内容变化切分会话的配置
+    pub activity_change: Option<ActivityChangeSettings>,
+    /// Prometheus 指标导出配置
+    pub metrics_exporter: Option<MetricsExporterSettings>,
+    /// Provider 配置档文件
+    pub provider_profiles: Option<ProviderProfilesSettings>,
+    /// 分析完成通知
+    pub webhook: Option<WebhookSettings>,
+    /// 深度工作识别
+    pub deep_work: Option<DeepWorkSettings>,
 }
 
 impl Default for PersistedAppConfig {
     fn default() -> Self {
         Self {
             retention_days: 7,
+            max_storage_bytes: None,
             llm_provider: "openai".to_string(),
             capture_interval: 1,
             summary_interval: 15,
@@ -178,6 +453,16 @@ impl Default for PersistedAppConfig {
             logger_settings: Some(LoggerSettings::default()),
             database_config: None,
             notion_config: Some(NotionConfig::default()),
+            frame_cleanup: Some(FrameCleanupSettings::default()),
+            frame_storage: Some(FrameStorageSettings::default()),
+            session_thumbnail: Some(SessionThumbnailSettings::default()),
+            capture_watchdog: Some(CaptureWatchdogSettings::default()),
+            analysis_concurrency: Some(AnalysisConcurrencySettings::default()),
+            activity_change: Some(ActivityChangeSettings::default()),
+            metrics_exporter: Some(MetricsExporterSettings::default()),
+            provider_profiles: None,
+            webhook: Some(WebhookSettings::default()),
+            deep_work: Some(DeepWorkSettings::default()),
         }
     }
 }
@@ -197,6 +482,15 @@ pub struct LLMProviderConfig {
     pub auth_token: String,
     #[serde(default)]
     pub codex_config: Option<serde_json::Value>,
+    /// Ollama 完整配置（含可选参数）
+    #[serde(default)]
+    pub ollama_config: Option<serde_json::Value>,
+    /// 托管 provider（Qwen / Claude）的计费单价覆盖
+    #[serde(default)]
+    pub pricing: Option<crate::llm::ProviderPricing>,
+    /// Provider 中间件（重试、限流、缓存）配置
+    #[serde(default)]
+    pub middleware: Option<crate::llm::MiddlewareConfig>,
 }
 
 /// UI设置
@@ -44,6 +44,9 @@ impl SettingsManager {
         if let Some(value) = update.retention_days {
             config.retention_days = value;
         }
+        if let Some(value) = update.max_storage_bytes {
+            config.max_storage_bytes = (value > 0).then_some(value);
+        }
         if let Some(provider) = update.llm_provider {
             config.llm_provider = provider;
         }
@@ -74,6 +77,36 @@ impl SettingsManager {
         if let Some(notion) = update.notion_config {
             config.notion_config = Some(notion);
         }
+        if let Some(cleanup) = update.frame_cleanup {
+            config.frame_cleanup = Some(cleanup);
+        }
+        if let Some(storage) = update.frame_storage {
+            config.frame_storage = Some(storage);
+        }
+        if let Some(thumbnail) = update.session_thumbnail {
+            config.session_thumbnail = Some(thumbnail);
+        }
+        if let Some(watchdog) = update.capture_watchdog {
+            config.capture_watchdog = Some(watchdog);
+        }
+        if let Some(concurrency) = update.analysis_concurrency {
+            config.analysis_concurrency = Some(concurrency);
+        }
+        if let Some(activity_change) = update.activity_change {
+            config.activity_change = Some(activity_change);
+        }
+        if let Some(metrics_exporter) = update.metrics_exporter {
+            config.metrics_exporter = Some(metrics_exporter);
+        }
+        if let Some(provider_profiles) = update.provider_profiles {
+            config.provider_profiles = Some(provider_profiles);
+        }
+        if let Some(webhook) = update.webhook {
+            config.webhook = Some(webhook);
+        }
+        if let Some(deep_work) = update.deep_work {
+            config.deep_work = Some(deep_work);
+        }
 
         self.save(&config).await?;
         Ok(config.clone())
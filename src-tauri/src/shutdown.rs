@@ -0,0 +1,167 @@
+// 退出协调器 - 应用退出时停止接收新任务、等待进行中的分析完成
+//
+// 会话在数据库的分析队列中等待，超过排空时限仍未完成的会话留在队列中，下次启动时重新分析
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+pub use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+/// 退出时等待进行中分析的默认时限
+pub const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 等待分析的会话（对应一次 SessionCompleted 事件）
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingSession {
+    /// 调度器分配的会话键（时间桶起点毫秒）
+    pub session_id: i64,
+    pub frame_count: usize,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+}
+
+/// 退出结果
+#[derive(Debug, Default)]
+pub struct ShutdownReport {
+    /// 在时限内完成的会话数
+    pub drained: usize,
+    /// 未在时限内完成、留在分析队列中的会话
+    pub requeued: Vec<PendingSession>,
+}
+
+/// 退出协调器
+#[derive(Default)]
+pub struct ShutdownCoordinator {
+    token: CancellationToken,
+    in_flight: Mutex<HashMap<i64, PendingSession>>,
+    changed: Notify,
+}
+
+impl ShutdownCoordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+
+    /// 登记开始分析的会话
+    ///
+    /// 已开始退出时不再接收新任务：会话留在分析队列中并返回 None
+    pub fn begin(self: &Arc<Self>, session: PendingSession) -> Option<InFlightGuard> {
+        if self.token.is_cancelled() {
+            info!("应用正在退出，会话 {} 留待下次启动分析", session.session_id);
+            return None;
+        }
+
+        let key = session.session_id;
+        self.lock_in_flight().insert(key, session);
+        Some(InFlightGuard {
+            coordinator: self.clone(),
+            key,
+        })
+    }
+
+    /// 开始退出：取消新任务，在时限内等待进行中的分析完成，返回未完成的会话
+    pub async fn shutdown(&self, drain_timeout: Duration) -> ShutdownReport {
+        self.token.cancel();
+        let started = self.lock_in_flight().len();
+        if started > 0 {
+            info!(
+                "等待 {} 个进行中的分析完成（最多 {:?}）",
+                started, drain_timeout
+            );
+        }
+
+        let deadline = tokio::time::Instant::now() + drain_timeout;
+        loop {
+            // 先注册等待再检查，避免检查与通知之间的竞争
+            let notified = self.changed.notified();
+            if self.lock_in_flight().is_empty() {
+                break;
+            }
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                break;
+            }
+        }
+
+        let requeued: Vec<PendingSession> = self.lock_in_flight().drain().map(|(_, s)| s).collect();
+        if !requeued.is_empty() {
+            warn!(
+                "{} 个分析未在时限内完成，下次启动时重新分析",
+                requeued.len()
+            );
+        }
+        ShutdownReport {
+            drained: started - requeued.len(),
+            requeued,
+        }
+    }
+
+    fn lock_in_flight(&self) -> std::sync::MutexGuard<'_, HashMap<i64, PendingSession>> {
+        // 持锁期间不会 panic，锁中毒时沿用内部数据
+        self.in_flight
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// 进行中分析的登记凭据，析构时（分析结束，无论成功失败）注销
+pub struct InFlightGuard {
+    coordinator: Arc<ShutdownCoordinator>,
+    key: i64,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.coordinator.lock_in_flight().remove(&self.key);
+        self.coordinator.changed.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(session_id: i64) -> PendingSession {
+        let start: DateTime<Utc> = "2025-01-01T09:00:00Z".parse().unwrap();
+        PendingSession {
+            session_id,
+            frame_count: 10,
+            window_start: start,
+            window_end: start + chrono::Duration::minutes(15),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unfinished_session_is_reported() {
+        let coordinator = Arc::new(ShutdownCoordinator::new());
+
+        // 分析未在时限内完成
+        let _guard = coordinator.begin(pending(1)).unwrap();
+        let report = coordinator.shutdown(Duration::from_millis(50)).await;
+        assert_eq!(report.drained, 0);
+        assert_eq!(report.requeued, vec![pending(1)]);
+
+        // 退出后到达的会话不再开始
+        assert!(coordinator.begin(pending(2)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_finished_analysis_is_drained() {
+        let coordinator = Arc::new(ShutdownCoordinator::new());
+
+        let guard = coordinator.begin(pending(1)).unwrap();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(guard);
+        });
+        let report = coordinator.shutdown(Duration::from_secs(5)).await;
+        assert_eq!(report.drained, 1);
+        assert!(report.requeued.is_empty());
+    }
+}
@@ -0,0 +1,214 @@
+// 分析队列持久化 - 等待分析与进行中的会话写入数据库，崩溃重启后恢复
+//
+// 会话收到完成事件即入队，开始分析时标记为进行中并累计次数，分析结束后出队；
+// 正常退出时未完成的分析恢复为等待状态，不计入次数。
+// 启动时仍在队列中的条目全部重新排队：上次进行中的条目说明分析因崩溃中断，删除残留的临时会话后重试。
+// 中断次数达到上限的条目（可能正是该会话导致崩溃）不再重试，避免启动即崩溃的循环
+
+use super::{AnalysisQueueRecord, Database};
+use crate::llm::AnalysisPriority;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use tracing::warn;
+
+/// 默认的最大分析次数（含首次），进行中被中断达到此次数后重启时不再恢复
+pub const DEFAULT_MAX_RESUME_ATTEMPTS: i64 = 3;
+
+/// 会话入队；已在队列中时（如重启后重新排队）保留已分析的次数与入队时间
+pub async fn enqueue(
+    db: &Database,
+    session_key: i64,
+    frame_count: usize,
+    window_start: DateTime<Utc>,
+    window_end: DateTime<Utc>,
+    priority: AnalysisPriority,
+) -> Result<AnalysisQueueRecord> {
+    let previous = db.get_analysis_queue_entry(session_key).await?;
+    let record = AnalysisQueueRecord {
+        session_key,
+        frame_count: frame_count as i64,
+        window_start,
+        window_end,
+        priority: priority.as_str().to_string(),
+        attempts: previous.as_ref().map_or(0, |previous| previous.attempts),
+        in_flight: false,
+        temp_session_id: None,
+        enqueued_at: previous.map_or_else(super::local_now, |previous| previous.enqueued_at),
+    };
+    db.save_analysis_queue_entry(&record).await?;
+    Ok(record)
+}
+
+/// 开始分析：标记为进行中并累计次数
+pub async fn mark_started(db: &Database, session_key: i64) -> Result<()> {
+    if let Some(mut record) = db.get_analysis_queue_entry(session_key).await? {
+        record.in_flight = true;
+        record.attempts += 1;
+        db.save_analysis_queue_entry(&record).await?;
+    }
+    Ok(())
+}
+
+/// 记录分析过程中创建的临时会话，分析被中断时重试前删除
+pub async fn attach_temp_session(
+    db: &Database,
+    session_key: i64,
+    temp_session_id: i64,
+) -> Result<()> {
+    if let Some(mut record) = db.get_analysis_queue_entry(session_key).await? {
+        record.temp_session_id = Some(temp_session_id);
+        db.save_analysis_queue_entry(&record).await?;
+    }
+    Ok(())
+}
+
+/// 正常退出时分析未完成：恢复为等待状态并撤销本次计数，临时会话留到下次启动时删除
+pub async fn release(db: &Database, session_key: i64) -> Result<()> {
+    if let Some(mut record) = db.get_analysis_queue_entry(session_key).await? {
+        if record.in_flight {
+            record.in_flight = false;
+            record.attempts = (record.attempts - 1).max(0);
+            db.save_analysis_queue_entry(&record).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 分析结束（无论成功失败）：出队
+pub async fn complete(db: &Database, session_key: i64) -> Result<()> {
+    db.delete_analysis_queue_entry(session_key).await
+}
+
+/// 启动时恢复队列，返回需要重新分析的条目（交互优先，同优先级按入队时间）
+///
+/// 删除未完成分析残留的临时会话；上次进行中的条目恢复为等待状态，分析次数已达 max_attempts 的出队放弃
+pub async fn rehydrate(db: &Database, max_attempts: i64) -> Result<Vec<AnalysisQueueRecord>> {
+    let mut resumed = Vec::new();
+    for mut record in db.list_analysis_queue().await? {
+        let changed = record.in_flight || record.temp_session_id.is_some();
+        if let Some(temp_session_id) = record.temp_session_id.take() {
+            if let Err(e) = db.delete_session(temp_session_id).await {
+                warn!("删除残留的临时会话失败 (ID={}): {}", temp_session_id, e);
+            }
+        }
+        if record.in_flight {
+            if record.attempts >= max_attempts.max(1) {
+                warn!(
+                    "会话 {} 的分析已中断 {} 次，不再自动重试",
+                    record.session_key, record.attempts
+                );
+                db.delete_analysis_queue_entry(record.session_key).await?;
+                continue;
+            }
+            record.in_flight = false;
+        }
+        if changed {
+            db.save_analysis_queue_entry(&record).await?;
+        }
+        resumed.push(record);
+    }
+    // 稳定排序，同优先级保持入队顺序
+    resumed.sort_by_key(|record| record.priority != AnalysisPriority::Interactive.as_str());
+    Ok(resumed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Session;
+
+    async fn open(path: &std::path::Path) -> Database {
+        Database::new_sqlite(path.to_str().unwrap()).await.unwrap()
+    }
+
+    async fn enqueue_at(db: &Database, session_key: i64, priority: AnalysisPriority) {
+        let start: DateTime<Utc> = "2025-01-01T09:00:00Z".parse().unwrap();
+        enqueue(
+            db,
+            session_key,
+            10,
+            start,
+            start + chrono::Duration::minutes(15),
+            priority,
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_queue_survives_restart_and_interrupted_item_is_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.db");
+        let db = open(&path).await;
+
+        enqueue_at(&db, 1, AnalysisPriority::Background).await;
+        enqueue_at(&db, 2, AnalysisPriority::Background).await;
+        enqueue_at(&db, 3, AnalysisPriority::Interactive).await;
+        // 会话 1 分析到一半时崩溃，留下临时会话
+        mark_started(&db, 1).await.unwrap();
+        let now = crate::storage::local_now();
+        let temp_session_id = db
+            .insert_session(&Session {
+                id: None,
+                start_time: now,
+                end_time: now,
+                title: "处理中...".to_string(),
+                summary: "正在分析...".to_string(),
+                video_path: None,
+                tags: "[]".to_string(),
+                created_at: Some(now),
+                device_name: None,
+                device_type: None,
+                pinned: false,
+            })
+            .await
+            .unwrap();
+        attach_temp_session(&db, 1, temp_session_id).await.unwrap();
+        drop(db);
+
+        // 模拟重启
+        let db = open(&path).await;
+        let resumed = rehydrate(&db, DEFAULT_MAX_RESUME_ATTEMPTS).await.unwrap();
+        let keys: Vec<i64> = resumed.iter().map(|record| record.session_key).collect();
+        assert_eq!(keys, vec![3, 1, 2]);
+        let interrupted = &resumed[1];
+        assert!(!interrupted.in_flight);
+        assert_eq!(interrupted.attempts, 1);
+        assert_eq!(interrupted.temp_session_id, None);
+        assert!(db.get_session(temp_session_id).await.is_err());
+
+        // 重新排队不清零次数；每次中断都计入，达到上限后放弃
+        enqueue_at(&db, 1, AnalysisPriority::Background).await;
+        for _ in 0..2 {
+            mark_started(&db, 1).await.unwrap();
+            rehydrate(&db, DEFAULT_MAX_RESUME_ATTEMPTS).await.unwrap();
+        }
+        assert!(db.get_analysis_queue_entry(1).await.unwrap().is_none());
+
+        // 正常结束的会话出队
+        complete(&db, 3).await.unwrap();
+        let keys: Vec<i64> = rehydrate(&db, DEFAULT_MAX_RESUME_ATTEMPTS)
+            .await
+            .unwrap()
+            .iter()
+            .map(|record| record.session_key)
+            .collect();
+        assert_eq!(keys, vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_graceful_shutdown_does_not_count_as_interruption() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open(&dir.path().join("data.db")).await;
+
+        enqueue_at(&db, 1, AnalysisPriority::Background).await;
+        // 每次都在退出时被打断：不计入次数，始终可以恢复
+        for _ in 0..DEFAULT_MAX_RESUME_ATTEMPTS + 1 {
+            mark_started(&db, 1).await.unwrap();
+            release(&db, 1).await.unwrap();
+            let resumed = rehydrate(&db, DEFAULT_MAX_RESUME_ATTEMPTS).await.unwrap();
+            assert_eq!(resumed.len(), 1);
+            assert_eq!(resumed[0].attempts, 0);
+        }
+    }
+}
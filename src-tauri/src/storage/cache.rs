@@ -179,6 +179,14 @@ impl DatabaseRepository for CachedRepository {
         self.inner.get_sessions_by_date(date).await
     }
 
+    async fn get_sessions_overlapping(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Session>> {
+        self.inner.get_sessions_overlapping(start, end).await
+    }
+
     async fn get_all_sessions(&self) -> Result<Vec<Session>> {
         // 全量查询不缓存
         self.inner.get_all_sessions().await
@@ -233,12 +241,43 @@ impl DatabaseRepository for CachedRepository {
         Ok(())
     }
 
+    async fn purge_session(
+        &self,
+        session_id: i64,
+    ) -> Result<std::collections::BTreeMap<String, u64>> {
+        let removed = self.inner.purge_session(session_id).await?;
+        self.invalidate_session(session_id).await;
+        Ok(removed)
+    }
+
+    async fn count_session_references(
+        &self,
+        session_id: i64,
+    ) -> Result<std::collections::BTreeMap<String, u64>> {
+        self.inner.count_session_references(session_id).await
+    }
+
     async fn delete_old_sessions(&self, cutoff_date: DateTime<Utc>) -> Result<u64> {
         let count = self.inner.delete_old_sessions(cutoff_date).await?;
         self.clear_cache().await;
         Ok(count)
     }
 
+    async fn set_session_pinned(&self, session_id: i64, pinned: bool) -> Result<()> {
+        self.inner.set_session_pinned(session_id, pinned).await?;
+        self.invalidate_session(session_id).await;
+        Ok(())
+    }
+
+    async fn list_pinned_sessions(&self) -> Result<Vec<Session>> {
+        self.inner.list_pinned_sessions().await
+    }
+
+    async fn query_sessions(&self, filter: &SessionFilter) -> Result<SessionPage> {
+        // 筛选查询不缓存
+        self.inner.query_sessions(filter).await
+    }
+
     // ========== 帧操作 ==========
 
     async fn insert_frame(&self, frame: &Frame) -> Result<i64> {
@@ -375,6 +414,169 @@ impl DatabaseRepository for CachedRepository {
         self.inner.delete_day_summary(date).await
     }
 
+    async fn save_session_analysis(&self, record: &SessionAnalysisRecord) -> Result<()> {
+        self.inner.save_session_analysis(record).await
+    }
+
+    async fn get_session_analysis(&self, session_id: i64) -> Result<Option<SessionAnalysisRecord>> {
+        self.inner.get_session_analysis(session_id).await
+    }
+
+    async fn save_frame_prune(
+        &self,
+        record: &FramePruneRecord,
+        kept_frame_ids: &[i64],
+    ) -> Result<()> {
+        self.inner.save_frame_prune(record, kept_frame_ids).await?;
+        let mut cache = self.frames_cache.write().await;
+        cache.invalidate(&record.session_id);
+        Ok(())
+    }
+
+    async fn get_frame_prune(&self, session_id: i64) -> Result<Option<FramePruneRecord>> {
+        self.inner.get_frame_prune(session_id).await
+    }
+
+    async fn save_session_thumbnail(&self, record: &SessionThumbnailRecord) -> Result<()> {
+        self.inner.save_session_thumbnail(record).await
+    }
+
+    async fn get_session_thumbnail(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionThumbnailRecord>> {
+        self.inner.get_session_thumbnail(session_id).await
+    }
+
+    async fn merge_sessions(
+        &self,
+        merged: &Session,
+        frames: &[Frame],
+        originals: &[SessionMergeRecord],
+    ) -> Result<i64> {
+        let id = self.inner.merge_sessions(merged, frames, originals).await?;
+        // 原始会话已删除，列表查询也会变化
+        self.clear_cache().await;
+        Ok(id)
+    }
+
+    async fn get_session_merges(&self, merged_session_id: i64) -> Result<Vec<SessionMergeRecord>> {
+        self.inner.get_session_merges(merged_session_id).await
+    }
+
+    async fn save_capture_gaps(&self, gaps: &[CaptureGapRecord]) -> Result<()> {
+        self.inner.save_capture_gaps(gaps).await
+    }
+
+    async fn get_capture_gaps(&self, session_id: i64) -> Result<Vec<CaptureGapRecord>> {
+        self.inner.get_capture_gaps(session_id).await
+    }
+
+    async fn save_frame_selection(&self, record: &FrameSelectionRecord) -> Result<()> {
+        self.inner.save_frame_selection(record).await
+    }
+
+    async fn get_frame_selection(&self, session_id: i64) -> Result<Option<FrameSelectionRecord>> {
+        self.inner.get_frame_selection(session_id).await
+    }
+
+    async fn save_session_extra_fields(&self, record: &SessionExtraFieldsRecord) -> Result<()> {
+        self.inner.save_session_extra_fields(record).await
+    }
+
+    async fn get_session_extra_fields(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionExtraFieldsRecord>> {
+        self.inner.get_session_extra_fields(session_id).await
+    }
+
+    async fn save_session_analysis_mode(&self, record: &SessionAnalysisModeRecord) -> Result<()> {
+        self.inner.save_session_analysis_mode(record).await
+    }
+
+    async fn get_session_analysis_mode(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionAnalysisModeRecord>> {
+        self.inner.get_session_analysis_mode(session_id).await
+    }
+
+    async fn save_session_scores(&self, record: &SessionScoresRecord) -> Result<()> {
+        self.inner.save_session_scores(record).await
+    }
+
+    async fn save_analysis_failure(&self, record: &AnalysisFailureRecord) -> Result<()> {
+        self.inner.save_analysis_failure(record).await
+    }
+
+    async fn get_analysis_failure(&self, session_id: i64) -> Result<Option<AnalysisFailureRecord>> {
+        self.inner.get_analysis_failure(session_id).await
+    }
+
+    async fn list_analysis_failures(&self) -> Result<Vec<AnalysisFailureRecord>> {
+        self.inner.list_analysis_failures().await
+    }
+
+    async fn delete_analysis_failure(&self, session_id: i64) -> Result<()> {
+        self.inner.delete_analysis_failure(session_id).await
+    }
+
+    async fn save_analysis_queue_entry(&self, record: &AnalysisQueueRecord) -> Result<()> {
+        self.inner.save_analysis_queue_entry(record).await
+    }
+
+    async fn get_analysis_queue_entry(
+        &self,
+        session_key: i64,
+    ) -> Result<Option<AnalysisQueueRecord>> {
+        self.inner.get_analysis_queue_entry(session_key).await
+    }
+
+    async fn list_analysis_queue(&self) -> Result<Vec<AnalysisQueueRecord>> {
+        self.inner.list_analysis_queue().await
+    }
+
+    async fn delete_analysis_queue_entry(&self, session_key: i64) -> Result<()> {
+        self.inner.delete_analysis_queue_entry(session_key).await
+    }
+
+    async fn replace_deep_work_stretches(
+        &self,
+        session_id: i64,
+        records: &[DeepWorkRecord],
+    ) -> Result<()> {
+        self.inner
+            .replace_deep_work_stretches(session_id, records)
+            .await
+    }
+
+    async fn get_deep_work_stretches(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DeepWorkRecord>> {
+        self.inner.get_deep_work_stretches(start, end).await
+    }
+
+    async fn save_summary_correction(&self, record: &SummaryCorrectionRecord) -> Result<()> {
+        self.inner.save_summary_correction(record).await
+    }
+
+    async fn get_summary_correction(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SummaryCorrectionRecord>> {
+        self.inner.get_summary_correction(session_id).await
+    }
+
+    async fn get_recent_summary_corrections(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<SummaryCorrectionRecord>> {
+        self.inner.get_recent_summary_corrections(limit).await
+    }
+
     async fn initialize_tables(&self) -> Result<()> {
         self.inner.initialize_tables().await
     }
@@ -383,6 +585,10 @@ impl DatabaseRepository for CachedRepository {
         self.inner.db_type()
     }
 
+    async fn close(&self) {
+        self.inner.close().await
+    }
+
     async fn migrate_timezone_to_local(&self) -> Result<(u64, u64, u64, u64, u64, u64)> {
         // 清空所有缓存，因为时间数据已改变
         self.clear_cache().await;
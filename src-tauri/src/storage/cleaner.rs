@@ -1,13 +1,15 @@
 // 存储清理模块 - 自动清理过期数据
 
-use super::Database;
+use super::{Database, Frame, FramePruneRecord, SessionPurgeManifest};
+use crate::time_mapper::TimeMapper;
 use anyhow::Result;
-use chrono::{Duration as ChronoDuration, Utc};
-use std::path::PathBuf;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tokio::time::{interval, Duration};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 /// 存储清理器
 pub struct StorageCleaner {
@@ -21,6 +23,8 @@ pub struct StorageCleaner {
     frames_dir: PathBuf,
     /// 视频文件目录
     videos_dir: PathBuf,
+    /// 存储预算（字节），None 表示不限制
+    max_storage_bytes: Arc<RwLock<Option<u64>>>,
 }
 
 impl StorageCleaner {
@@ -32,6 +36,7 @@ impl StorageCleaner {
             max_retention_days: 30,                   // 最大保留30天
             frames_dir,
             videos_dir,
+            max_storage_bytes: Arc::new(RwLock::new(None)),
         }
     }
 
@@ -58,6 +63,16 @@ impl StorageCleaner {
         *self.retention_days.read().await
     }
 
+    /// 设置存储预算（字节），None 或 0 表示不限制
+    pub async fn set_max_storage_bytes(&self, max_bytes: Option<u64>) {
+        let max_bytes = max_bytes.filter(|bytes| *bytes > 0);
+        *self.max_storage_bytes.write().await = max_bytes;
+        match max_bytes {
+            Some(bytes) => info!("存储预算已更新为: {} 字节", bytes),
+            None => info!("存储预算已关闭"),
+        }
+    }
+
     /// 启动自动清理任务
     pub async fn start_cleanup_task(self: Arc<Self>) {
         tokio::spawn(async move {
@@ -97,6 +112,9 @@ impl StorageCleaner {
         // 4. 清理孤立文件（没有数据库记录的文件）
         self.cleanup_orphaned_files().await?;
 
+        // 5. 超出存储预算时淘汰最旧的会话
+        self.enforce_storage_budget().await?;
+
         // 5. 记录清理结果
         if !failed_files.is_empty() {
             error!("清理完成，但有 {} 个文件删除失败", failed_files.len());
@@ -132,7 +150,13 @@ impl StorageCleaner {
 
             // 3. 获取所有帧文件路径
             let frames = self.db.get_frames_by_session(session_id).await?;
-            let frame_paths: Vec<String> = frames.iter().map(|f| f.file_path.clone()).collect();
+            // 归档内的帧对应同一个归档文件，只删除一次
+            let mut seen = HashSet::new();
+            let frame_paths: Vec<String> = frames
+                .iter()
+                .map(|f| super::frame_pack::storage_path(&f.file_path).to_string())
+                .filter(|path| seen.insert(path.clone()))
+                .collect();
 
             // 4. 获取视频文件路径
             let video_path = session.video_path.clone();
@@ -175,21 +199,49 @@ impl StorageCleaner {
 
     /// 清理孤立文件（数据库中没有记录的文件）
     async fn cleanup_orphaned_files(&self) -> Result<()> {
+        // 置顶会话的文件即使超过保留期限也不删除
+        let pinned_files = self.get_pinned_session_files().await?;
+
         // 清理frames目录中的孤立文件
         if self.frames_dir.exists() {
-            self.cleanup_orphaned_in_dir(&self.frames_dir).await?;
+            self.cleanup_orphaned_in_dir(&self.frames_dir, &pinned_files)
+                .await?;
         }
 
         // 清理videos目录中的孤立文件
         if self.videos_dir.exists() {
-            self.cleanup_orphaned_in_dir(&self.videos_dir).await?;
+            self.cleanup_orphaned_in_dir(&self.videos_dir, &pinned_files)
+                .await?;
         }
 
         Ok(())
     }
 
+    /// 获取置顶会话引用的文件路径（视频与帧）
+    async fn get_pinned_session_files(&self) -> Result<HashSet<PathBuf>> {
+        let mut files = HashSet::new();
+        for session in self.db.list_pinned_sessions().await? {
+            if let Some(video_path) = session.video_path.filter(|path| !path.is_empty()) {
+                files.insert(PathBuf::from(video_path));
+            }
+            let Some(session_id) = session.id else {
+                continue;
+            };
+            for frame in self.db.get_frames_by_session(session_id).await? {
+                files.insert(PathBuf::from(super::frame_pack::storage_path(
+                    &frame.file_path,
+                )));
+            }
+        }
+        Ok(files)
+    }
+
     /// 清理指定目录中的孤立文件
-    async fn cleanup_orphaned_in_dir(&self, dir: &PathBuf) -> Result<()> {
+    async fn cleanup_orphaned_in_dir(
+        &self,
+        dir: &Path,
+        pinned_files: &HashSet<PathBuf>,
+    ) -> Result<()> {
         let retention_secs = {
             let retention_days = *self.retention_days.read().await;
             let clamped_days = retention_days.max(0) as u64;
@@ -200,6 +252,9 @@ impl StorageCleaner {
 
         while let Some(entry) = entries.next_entry().await? {
             let path = entry.path();
+            if pinned_files.contains(&path) {
+                continue;
+            }
 
             // 使用异步方法获取文件元数据，而不是同步的 is_file()
             if let Ok(metadata) = tokio::fs::metadata(&path).await {
@@ -226,6 +281,115 @@ impl StorageCleaner {
         Ok(())
     }
 
+    /// 当前存储占用：帧目录与视频目录（含子目录）加上本地 SQLite 数据库
+    pub async fn storage_usage(&self) -> Result<u64> {
+        let frames_size = self.calculate_dir_size(&self.frames_dir).await?;
+        let videos_size = self.calculate_dir_size(&self.videos_dir).await?;
+        // MariaDB / PostgreSQL 不占用本机磁盘
+        let db_size = if self.db.db_type() == "sqlite" {
+            self.db.get_stats().await?.2
+        } else {
+            0
+        };
+        Ok((frames_size + videos_size + db_size).max(0) as u64)
+    }
+
+    /// 按存储预算淘汰：占用超出预算时，从最旧的未置顶会话开始依次删除帧文件，
+    /// 仍超出时再依次删除会话记录及其视频，占用降到预算以内即停止
+    pub async fn enforce_storage_budget(&self) -> Result<StorageBudgetReport> {
+        let Some(budget) = *self.max_storage_bytes.read().await else {
+            return Ok(StorageBudgetReport::default());
+        };
+        let usage_before = self.storage_usage().await?;
+        let mut report = StorageBudgetReport {
+            usage_before,
+            usage_after: usage_before,
+            ..Default::default()
+        };
+        if usage_before <= budget {
+            return Ok(report);
+        }
+        info!(
+            "存储占用 {} 字节超出预算 {} 字节，开始淘汰最旧的会话",
+            usage_before, budget
+        );
+
+        // 置顶会话与仍在进行中的会话不参与淘汰
+        let now = crate::storage::local_now();
+        let mut candidates: Vec<_> = self
+            .db
+            .get_all_sessions()
+            .await?
+            .into_iter()
+            .filter(|session| !session.pinned && session.end_time <= now)
+            .filter_map(|session| session.id.map(|id| (id, session)))
+            .collect();
+        candidates.sort_by_key(|(_, session)| session.start_time);
+
+        // 第一轮：删除帧文件，保留会话记录与总结
+        for (session_id, _) in &candidates {
+            if report.usage_after <= budget {
+                break;
+            }
+            let frames = self.db.get_frames_by_session(*session_id).await?;
+            if frames.is_empty() {
+                continue;
+            }
+            let mut seen = HashSet::new();
+            let mut freed = 0u64;
+            for path in frames
+                .iter()
+                .map(|f| super::frame_pack::storage_path(&f.file_path))
+                .filter(|path| seen.insert(path.to_string()))
+            {
+                freed += remove_file_counting(path).await;
+            }
+            self.db.delete_frames_by_session(*session_id).await?;
+            self.db.invalidate_session(*session_id).await;
+            info!(
+                "存储预算: 删除会话 {} 的 {} 帧，释放 {} 字节",
+                session_id,
+                frames.len(),
+                freed
+            );
+            report.usage_after = report.usage_after.saturating_sub(freed);
+            report.bytes_freed += freed;
+            report.frames_evicted.push(*session_id);
+        }
+
+        // 第二轮：删除会话记录及其视频
+        for (session_id, session) in &candidates {
+            if report.usage_after <= budget {
+                break;
+            }
+            let freed = match session.video_path.as_deref().filter(|p| !p.is_empty()) {
+                Some(video_path) => remove_file_counting(video_path).await,
+                None => 0,
+            };
+            self.db.delete_session(*session_id).await?;
+            info!(
+                "存储预算: 删除会话 {}（{}），释放 {} 字节",
+                session_id, session.title, freed
+            );
+            report.usage_after = report.usage_after.saturating_sub(freed);
+            report.bytes_freed += freed;
+            report.sessions_deleted.push(*session_id);
+        }
+
+        if report.usage_after > budget {
+            warn!(
+                "存储预算: 已淘汰全部可淘汰的会话，占用 {} 字节仍超出预算 {} 字节",
+                report.usage_after, budget
+            );
+        } else {
+            info!(
+                "存储预算: 淘汰完成，占用 {} -> {} 字节",
+                usage_before, report.usage_after
+            );
+        }
+        Ok(report)
+    }
+
     /// 手动触发清理
     pub async fn trigger_cleanup(&self) -> Result<()> {
         info!("手动触发存储清理");
@@ -251,19 +415,24 @@ impl StorageCleaner {
         })
     }
 
-    /// 计算目录大小
-    async fn calculate_dir_size(&self, dir: &PathBuf) -> Result<i64> {
+    /// 计算目录大小（含子目录，如帧归档目录）
+    async fn calculate_dir_size(&self, dir: &Path) -> Result<i64> {
         if !dir.exists() {
             return Ok(0);
         }
 
         let mut total_size = 0i64;
-        let mut entries = tokio::fs::read_dir(dir).await?;
-
-        while let Some(entry) = entries.next_entry().await? {
-            if let Ok(metadata) = entry.metadata().await {
-                if metadata.is_file() {
-                    total_size += metadata.len() as i64;
+        let mut pending = vec![dir.to_path_buf()];
+
+        while let Some(dir) = pending.pop() {
+            let mut entries = tokio::fs::read_dir(&dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                if let Ok(metadata) = entry.metadata().await {
+                    if metadata.is_file() {
+                        total_size += metadata.len() as i64;
+                    } else if metadata.is_dir() {
+                        pending.push(entry.path());
+                    }
                 }
             }
         }
@@ -272,6 +441,146 @@ impl StorageCleaner {
     }
 }
 
+/// 分析成功后清理会话的原始帧
+///
+/// `keep_times` 为需要保留的时间点（通常是关键时刻），每个时间点保留离它最近的一帧；
+/// 数据库中未保留的帧记录与清理记录在同一事务中写入，提交成功后再删除磁盘上的帧文件，
+/// 这样中途失败时数据库不会指向已删除的文件。
+pub async fn prune_session_frames(
+    db: &Database,
+    session_id: i64,
+    keep_times: &[DateTime<Utc>],
+) -> Result<FramePruneRecord> {
+    let frames = db.get_frames_by_session(session_id).await?;
+    let keep = select_frames_to_keep(&frames, keep_times);
+
+    // 归档内的帧无法单独删除，保留记录
+    let (kept_frames, pruned_frames): (Vec<_>, Vec<_>) =
+        frames.into_iter().enumerate().partition(|(idx, frame)| {
+            keep.contains(idx) || super::frame_pack::is_packed_path(&frame.file_path)
+        });
+    let kept_frame_ids: Vec<i64> = kept_frames.iter().filter_map(|(_, f)| f.id).collect();
+
+    let record = FramePruneRecord {
+        session_id,
+        deleted_count: pruned_frames.len() as i64,
+        kept_count: kept_frames.len() as i64,
+        pruned_at: crate::storage::local_now(),
+    };
+    db.save_frame_prune(&record, &kept_frame_ids).await?;
+    db.invalidate_session(session_id).await;
+
+    // 记录已提交，删除文件失败只会留下孤立文件，由定期清理兜底
+    for (_, frame) in pruned_frames {
+        match tokio::fs::remove_file(&frame.file_path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("删除帧文件失败 {}: {}", frame.file_path, e),
+        }
+    }
+
+    info!(
+        "会话 {} 原始帧已清理: 删除 {} 帧，保留 {} 帧",
+        session_id, record.deleted_count, record.kept_count
+    );
+    Ok(record)
+}
+
+/// 覆写文件时每次写入的字节数
+const OVERWRITE_CHUNK_BYTES: usize = 64 * 1024;
+
+/// 彻底删除会话：数据库中的全部相关记录（含人工更正）、帧文件与帧归档、视频文件
+///
+/// 先在事务中删除数据库记录，成功后再删除文件，数据库删除失败时文件保持不变。
+/// `secure` 为 true 时删除前先用零覆写文件内容；SSD 与写时复制文件系统上覆写不一定落在原位置，只能降低恢复的可能
+pub async fn purge_session(
+    db: &Database,
+    session_id: i64,
+    secure: bool,
+) -> Result<SessionPurgeManifest> {
+    let detail = db.get_session_detail(session_id).await?;
+    // 归档内的帧对应同一个归档文件，只删除一次
+    let mut seen = HashSet::new();
+    let files: Vec<String> = detail
+        .frames
+        .iter()
+        .map(|f| super::frame_pack::storage_path(&f.file_path).to_string())
+        .chain(detail.session.video_path.clone())
+        .filter(|path| seen.insert(path.clone()))
+        .collect();
+
+    let rows = db.purge_session(session_id).await?;
+    let mut manifest = SessionPurgeManifest {
+        session_id,
+        rows,
+        files_removed: Vec::new(),
+        files_failed: Vec::new(),
+        overwritten: secure,
+    };
+    for path in files {
+        match remove_file_securely(&path, secure).await {
+            Ok(()) => manifest.files_removed.push(path),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                warn!("删除会话文件失败 {}: {}", path, e);
+                manifest.files_failed.push(path);
+            }
+        }
+    }
+
+    info!(
+        "会话 {} 已彻底删除: 记录 {:?}，文件 {} 个（失败 {} 个）",
+        session_id,
+        manifest.rows,
+        manifest.files_removed.len(),
+        manifest.files_failed.len()
+    );
+    Ok(manifest)
+}
+
+/// 删除文件；overwrite 为 true 时先用零覆写全部内容并刷盘
+async fn remove_file_securely(path: &str, overwrite: bool) -> std::io::Result<()> {
+    if overwrite {
+        use tokio::io::AsyncWriteExt;
+
+        let mut file = tokio::fs::OpenOptions::new().write(true).open(path).await?;
+        let mut remaining = file.metadata().await?.len();
+        let zeros = vec![0u8; OVERWRITE_CHUNK_BYTES];
+        while remaining > 0 {
+            let n = remaining.min(zeros.len() as u64) as usize;
+            file.write_all(&zeros[..n]).await?;
+            remaining -= n as u64;
+        }
+        file.sync_all().await?;
+    }
+    tokio::fs::remove_file(path).await
+}
+
+/// 删除文件并返回释放的字节数；文件不存在或删除失败时为 0
+async fn remove_file_counting(path: &str) -> u64 {
+    let Ok(metadata) = tokio::fs::metadata(path).await else {
+        return 0;
+    };
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => metadata.len(),
+        Err(e) => {
+            warn!("存储预算: 删除文件失败 {}: {}", path, e);
+            0
+        }
+    }
+}
+
+/// 为每个保留时间点选出时间最接近的帧，返回帧下标集合
+fn select_frames_to_keep(frames: &[Frame], keep_times: &[DateTime<Utc>]) -> HashSet<usize> {
+    let Some(mapper) = TimeMapper::from_frames(frames) else {
+        return HashSet::new();
+    };
+    keep_times
+        .iter()
+        .filter_map(|time| mapper.frame_at(*time))
+        .collect()
+}
+
 /// 会话文件信息
 pub struct SessionFiles {
     pub frame_paths: Vec<String>,
@@ -289,6 +598,21 @@ pub struct CleanupResult {
     pub failed_files: Vec<(String, String)>,
 }
 
+/// 存储预算淘汰结果
+#[derive(Debug, Default)]
+pub struct StorageBudgetReport {
+    /// 淘汰前的占用（字节）
+    pub usage_before: u64,
+    /// 淘汰后的占用（字节）
+    pub usage_after: u64,
+    /// 释放的字节数
+    pub bytes_freed: u64,
+    /// 删除了帧文件的会话
+    pub frames_evicted: Vec<i64>,
+    /// 删除了记录的会话
+    pub sessions_deleted: Vec<i64>,
+}
+
 /// 存储统计信息
 #[derive(Debug, serde::Serialize)]
 pub struct StorageStats {
@@ -300,3 +624,338 @@ pub struct StorageStats {
     pub total_size: i64,
     pub retention_days: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_at(secs: i64) -> Frame {
+        Frame {
+            id: None,
+            session_id: 1,
+            timestamp: DateTime::from_timestamp(secs, 0).unwrap(),
+            file_path: format!("{}.jpg", secs),
+        }
+    }
+
+    async fn insert_old_session(db: &Database, dir: &std::path::Path, name: &str) -> i64 {
+        let start = crate::storage::local_now() - ChronoDuration::days(30);
+        let video_path = dir.join(format!("{}.mp4", name));
+        std::fs::write(&video_path, b"video").unwrap();
+        // 文件修改时间同样超过保留期限
+        std::fs::File::options()
+            .write(true)
+            .open(&video_path)
+            .unwrap()
+            .set_modified(std::time::SystemTime::now() - Duration::from_secs(30 * 86_400))
+            .unwrap();
+        db.insert_session(&crate::storage::Session {
+            id: None,
+            start_time: start,
+            end_time: start + ChronoDuration::minutes(15),
+            title: name.to_string(),
+            summary: String::new(),
+            video_path: Some(video_path.to_string_lossy().to_string()),
+            tags: "[]".to_string(),
+            created_at: None,
+            device_name: None,
+            device_type: None,
+            pinned: false,
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pinned_old_session_survives_cleanup() {
+        let dir = tempfile::tempdir().unwrap();
+        let frames_dir = dir.path().join("frames");
+        let videos_dir = dir.path().join("videos");
+        std::fs::create_dir_all(&frames_dir).unwrap();
+        std::fs::create_dir_all(&videos_dir).unwrap();
+        let db = Arc::new(
+            Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+
+        let pinned_id = insert_old_session(&db, &videos_dir, "pinned").await;
+        let expired_id = insert_old_session(&db, &videos_dir, "expired").await;
+        db.set_session_pinned(pinned_id, true).await.unwrap();
+
+        let cleaner = StorageCleaner::new(db.clone(), frames_dir, videos_dir.clone());
+        cleaner.perform_cleanup().await.unwrap();
+
+        let remaining: Vec<_> = db
+            .get_all_sessions()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|s| s.id)
+            .collect();
+        assert_eq!(remaining, vec![pinned_id]);
+        assert!(videos_dir.join("pinned.mp4").exists());
+        assert!(!videos_dir.join("expired.mp4").exists());
+        assert!(db.get_session(expired_id).await.is_err());
+
+        let pinned = db.list_pinned_sessions().await.unwrap();
+        assert_eq!(pinned.len(), 1);
+        assert!(pinned[0].pinned);
+
+        // 取消置顶后按保留期限正常清理
+        db.set_session_pinned(pinned_id, false).await.unwrap();
+        cleaner.perform_cleanup().await.unwrap();
+        assert!(db.get_all_sessions().await.unwrap().is_empty());
+        assert!(db.set_session_pinned(expired_id, true).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_storage_budget_evicts_oldest_unpinned_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let frames_dir = dir.path().join("frames");
+        let videos_dir = dir.path().join("videos");
+        std::fs::create_dir_all(&frames_dir).unwrap();
+        std::fs::create_dir_all(&videos_dir).unwrap();
+        let db = Arc::new(
+            Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+
+        // 4 个已结束的会话，从旧到新，每个会话 2 帧共 2000 字节；最旧的会话置顶
+        let now = crate::storage::local_now();
+        let mut ids = Vec::new();
+        for i in 0..4i64 {
+            let start = now - ChronoDuration::hours(10 - i);
+            let id = db
+                .insert_session(&crate::storage::Session {
+                    id: None,
+                    start_time: start,
+                    end_time: start + ChronoDuration::minutes(15),
+                    title: format!("会话 {}", i),
+                    summary: String::new(),
+                    video_path: None,
+                    tags: "[]".to_string(),
+                    created_at: None,
+                    device_name: None,
+                    device_type: None,
+                    pinned: i == 0,
+                })
+                .await
+                .unwrap();
+            let frames: Vec<Frame> = (0..2)
+                .map(|j| {
+                    let path = frames_dir.join(format!("{}_{}.jpg", i, j));
+                    std::fs::write(&path, vec![0u8; 1000]).unwrap();
+                    Frame {
+                        id: None,
+                        session_id: id,
+                        timestamp: start + ChronoDuration::seconds(j),
+                        file_path: path.to_string_lossy().to_string(),
+                    }
+                })
+                .collect();
+            db.insert_frames(&frames).await.unwrap();
+            ids.push(id);
+        }
+
+        let cleaner = StorageCleaner::new(db.clone(), frames_dir.clone(), videos_dir);
+        // 未设置预算时不淘汰
+        assert!(cleaner
+            .enforce_storage_budget()
+            .await
+            .unwrap()
+            .frames_evicted
+            .is_empty());
+
+        // 预算恰好需要释放两个会话的帧：跳过置顶会话，淘汰到预算即停止
+        let usage = cleaner.storage_usage().await.unwrap();
+        let budget = usage - 4000;
+        cleaner.set_max_storage_bytes(Some(budget)).await;
+        let report = cleaner.enforce_storage_budget().await.unwrap();
+        assert_eq!(report.frames_evicted, vec![ids[1], ids[2]]);
+        assert!(report.sessions_deleted.is_empty());
+        assert_eq!((report.bytes_freed, report.usage_after), (4000, budget));
+        assert!(cleaner.storage_usage().await.unwrap() <= budget);
+        let frame_count = |i: usize| {
+            let db = db.clone();
+            let id = ids[i];
+            async move { db.get_frames_by_session(id).await.unwrap().len() }
+        };
+        assert_eq!(frame_count(0).await, 2);
+        assert_eq!(frame_count(1).await, 0);
+        assert_eq!(frame_count(3).await, 2);
+        assert!(frames_dir.join("0_0.jpg").exists() && frames_dir.join("3_1.jpg").exists());
+        assert!(!frames_dir.join("2_0.jpg").exists());
+
+        // 预算无法满足时淘汰全部未置顶会话，置顶会话始终保留
+        cleaner.set_max_storage_bytes(Some(1)).await;
+        let report = cleaner.enforce_storage_budget().await.unwrap();
+        assert_eq!(report.frames_evicted, vec![ids[3]]);
+        assert_eq!(report.sessions_deleted, vec![ids[1], ids[2], ids[3]]);
+        let remaining: Vec<_> = db
+            .get_all_sessions()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|s| s.id)
+            .collect();
+        assert_eq!(remaining, vec![ids[0]]);
+        assert_eq!(frame_count(0).await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_purge_session_removes_every_reference() {
+        use crate::storage::{
+            LLMCallRecord, SessionAnalysisModeRecord, SessionExtraFieldsRecord,
+            SummaryCorrectionRecord,
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let purged = insert_old_session(&db, dir.path(), "purged").await;
+        let kept = insert_old_session(&db, dir.path(), "kept").await;
+        let now = Utc::now();
+        for (session_id, name) in [(purged, "purged"), (kept, "kept")] {
+            let path = dir.path().join(format!("{}.jpg", name));
+            std::fs::write(&path, b"frame").unwrap();
+            db.insert_frames(&[Frame {
+                id: None,
+                session_id,
+                timestamp: now,
+                file_path: path.to_string_lossy().to_string(),
+            }])
+            .await
+            .unwrap();
+        }
+        db.insert_llm_call(&LLMCallRecord {
+            id: None,
+            session_id: Some(purged),
+            provider: "ollama".to_string(),
+            model: "llava".to_string(),
+            call_type: "analyze_frames".to_string(),
+            request_headers: "{}".to_string(),
+            request_body: "{}".to_string(),
+            response_headers: None,
+            response_body: Some("{}".to_string()),
+            status_code: Some(200),
+            error_message: None,
+            latency_ms: Some(10),
+            token_usage: None,
+            created_at: now,
+        })
+        .await
+        .unwrap();
+        db.save_session_extra_fields(&SessionExtraFieldsRecord {
+            session_id: purged,
+            fields: "{}".to_string(),
+            created_at: now,
+        })
+        .await
+        .unwrap();
+        db.save_session_analysis_mode(&SessionAnalysisModeRecord {
+            session_id: purged,
+            mode: "work".to_string(),
+            updated_at: now,
+        })
+        .await
+        .unwrap();
+        // 人工更正不随会话级联删除，彻底删除时须一并清理
+        db.save_summary_correction(&SummaryCorrectionRecord {
+            session_id: purged,
+            description: "写代码".to_string(),
+            corrected: "{}".to_string(),
+            embedding: "[0.1]".to_string(),
+            created_at: now,
+        })
+        .await
+        .unwrap();
+        assert_eq!(db.count_session_references(purged).await.unwrap().len(), 6);
+
+        let manifest = purge_session(&db, purged, true).await.unwrap();
+        assert_eq!(manifest.rows["sessions"], 1);
+        assert_eq!(manifest.rows["frames"], 1);
+        assert_eq!(manifest.rows["summary_corrections"], 1);
+        assert_eq!(manifest.files_removed.len(), 2);
+        assert!(manifest.files_failed.is_empty());
+        assert!(!dir.path().join("purged.jpg").exists());
+        assert!(!dir.path().join("purged.mp4").exists());
+
+        let remaining = db.count_session_references(purged).await.unwrap();
+        assert!(remaining.is_empty(), "{:?}", remaining);
+        assert!(db.get_session(purged).await.is_err());
+        assert!(db.get_summary_correction(purged).await.unwrap().is_none());
+        // 其他会话不受影响
+        assert_eq!(db.get_frames_by_session(kept).await.unwrap().len(), 1);
+        assert!(dir.path().join("kept.jpg").exists());
+        assert!(purge_session(&db, purged, false).await.is_err());
+    }
+
+    #[test]
+    fn keeps_nearest_frame_for_each_moment() {
+        let frames: Vec<Frame> = [0, 10, 20, 30].into_iter().map(frame_at).collect();
+        let times = [
+            DateTime::from_timestamp(12, 0).unwrap(),
+            DateTime::from_timestamp(29, 0).unwrap(),
+        ];
+
+        let keep = select_frames_to_keep(&frames, &times);
+        assert_eq!(keep, HashSet::from([1, 3]));
+        assert!(select_frames_to_keep(&frames, &[]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn prune_removes_unkept_frames_and_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let session_id = db
+            .insert_session(&crate::storage::Session {
+                id: None,
+                start_time: DateTime::from_timestamp(0, 0).unwrap(),
+                end_time: DateTime::from_timestamp(30, 0).unwrap(),
+                title: String::new(),
+                summary: String::new(),
+                video_path: None,
+                tags: "[]".to_string(),
+                created_at: None,
+                device_name: None,
+                device_type: None,
+                pinned: false,
+            })
+            .await
+            .unwrap();
+        let frames: Vec<Frame> = [0, 10, 20, 30]
+            .into_iter()
+            .map(|secs| {
+                let path = dir.path().join(format!("{}.jpg", secs));
+                std::fs::write(&path, b"frame").unwrap();
+                Frame {
+                    session_id,
+                    file_path: path.to_str().unwrap().to_string(),
+                    ..frame_at(secs)
+                }
+            })
+            .collect();
+        db.insert_frames(&frames).await.unwrap();
+
+        let record =
+            prune_session_frames(&db, session_id, &[DateTime::from_timestamp(11, 0).unwrap()])
+                .await
+                .unwrap();
+        assert_eq!((record.deleted_count, record.kept_count), (3, 1));
+
+        // 只保留离关键时刻最近的帧，其余帧记录与文件都已删除
+        let remaining = db.get_frames_by_session(session_id).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].file_path, frames[1].file_path);
+        for (idx, frame) in frames.iter().enumerate() {
+            assert_eq!(std::path::Path::new(&frame.file_path).exists(), idx == 1);
+        }
+        let stored = db.get_frame_prune(session_id).await.unwrap().unwrap();
+        assert_eq!(stored.deleted_count, 3);
+    }
+}
@@ -26,6 +26,20 @@ pub enum DatabaseConfig {
         /// 密码
         password: String,
     },
+    /// PostgreSQL 配置（多用户服务器部署）
+    #[serde(rename = "postgres")]
+    Postgres {
+        /// 主机地址
+        host: String,
+        /// 端口
+        port: u16,
+        /// 数据库名
+        database: String,
+        /// 用户名
+        username: String,
+        /// 密码
+        password: String,
+    },
 }
 
 impl Default for DatabaseConfig {
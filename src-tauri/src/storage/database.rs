@@ -3,7 +3,10 @@
 use super::cache::CachedRepository;
 use super::config::DatabaseConfig;
 use super::models::*;
-use super::repository::{mariadb::MariaDbRepository, sqlite::SqliteRepository, DatabaseRepository};
+use super::repository::{
+    mariadb::MariaDbRepository, postgres::PostgresRepository, sqlite::SqliteRepository,
+    DatabaseRepository,
+};
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use std::sync::Arc;
@@ -29,6 +32,13 @@ impl Database {
                 username,
                 password,
             } => Self::new_mariadb(host, *port, database, username, password).await,
+            DatabaseConfig::Postgres {
+                host,
+                port,
+                database,
+                username,
+                password,
+            } => Self::new_postgres(host, *port, database, username, password).await,
         }
     }
 
@@ -60,6 +70,24 @@ impl Database {
         })
     }
 
+    /// 创建 PostgreSQL 数据库连接
+    pub async fn new_postgres(
+        host: &str,
+        port: u16,
+        database: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<Self> {
+        let postgres_repo =
+            PostgresRepository::new(host, port, database, username, password).await?;
+        let cached_repo = CachedRepository::new(Arc::new(postgres_repo));
+
+        Ok(Self {
+            repository: Arc::new(cached_repo),
+            db_type: "postgres".to_string(),
+        })
+    }
+
     // ========== 会话操作 ==========
 
     pub async fn insert_session(&self, session: &Session) -> Result<i64> {
@@ -82,6 +110,14 @@ impl Database {
         self.repository.get_sessions_by_date(date).await
     }
 
+    pub async fn get_sessions_overlapping(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Session>> {
+        self.repository.get_sessions_overlapping(start, end).await
+    }
+
     pub async fn get_all_sessions(&self) -> Result<Vec<Session>> {
         self.repository.get_all_sessions().await
     }
@@ -117,6 +153,20 @@ impl Database {
         self.repository.delete_session(session_id).await
     }
 
+    pub async fn purge_session(
+        &self,
+        session_id: i64,
+    ) -> Result<std::collections::BTreeMap<String, u64>> {
+        self.repository.purge_session(session_id).await
+    }
+
+    pub async fn count_session_references(
+        &self,
+        session_id: i64,
+    ) -> Result<std::collections::BTreeMap<String, u64>> {
+        self.repository.count_session_references(session_id).await
+    }
+
     pub async fn get_old_sessions(&self, cutoff_date: DateTime<Utc>) -> Result<Vec<Session>> {
         self.repository.get_old_sessions(cutoff_date).await
     }
@@ -125,6 +175,18 @@ impl Database {
         self.repository.delete_old_sessions(cutoff_date).await
     }
 
+    pub async fn set_session_pinned(&self, session_id: i64, pinned: bool) -> Result<()> {
+        self.repository.set_session_pinned(session_id, pinned).await
+    }
+
+    pub async fn list_pinned_sessions(&self) -> Result<Vec<Session>> {
+        self.repository.list_pinned_sessions().await
+    }
+
+    pub async fn query_sessions(&self, filter: &SessionFilter) -> Result<SessionPage> {
+        self.repository.query_sessions(filter).await
+    }
+
     // ========== 帧操作 ==========
 
     pub async fn insert_frame(&self, frame: &Frame) -> Result<i64> {
@@ -243,6 +305,11 @@ impl Database {
         &self.db_type
     }
 
+    /// 关闭数据库连接（应用退出前调用，确保写入已落盘）
+    pub async fn close(&self) {
+        self.repository.close().await
+    }
+
     pub fn is_sqlite(&self) -> bool {
         self.db_type == "sqlite"
     }
@@ -251,6 +318,10 @@ impl Database {
         self.db_type == "mariadb"
     }
 
+    pub fn is_postgres(&self) -> bool {
+        self.db_type == "postgres"
+    }
+
     // ========== 缓存管理 ==========
 
     pub async fn invalidate_session(&self, session_id: i64) {
@@ -389,6 +460,208 @@ impl Database {
         self.repository.delete_day_summary(date).await
     }
 
+    // ========== 会话分析记录操作 ==========
+
+    pub async fn save_session_analysis(&self, record: &SessionAnalysisRecord) -> Result<()> {
+        self.repository.save_session_analysis(record).await
+    }
+
+    pub async fn get_session_analysis(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionAnalysisRecord>> {
+        self.repository.get_session_analysis(session_id).await
+    }
+
+    // ========== 帧清理记录操作 ==========
+
+    pub async fn save_frame_prune(
+        &self,
+        record: &FramePruneRecord,
+        kept_frame_ids: &[i64],
+    ) -> Result<()> {
+        self.repository.save_frame_prune(record, kept_frame_ids).await
+    }
+
+    pub async fn get_frame_prune(&self, session_id: i64) -> Result<Option<FramePruneRecord>> {
+        self.repository.get_frame_prune(session_id).await
+    }
+
+    // ========== 会话封面操作 ==========
+
+    pub async fn save_session_thumbnail(&self, record: &SessionThumbnailRecord) -> Result<()> {
+        self.repository.save_session_thumbnail(record).await
+    }
+
+    pub async fn get_session_thumbnail(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionThumbnailRecord>> {
+        self.repository.get_session_thumbnail(session_id).await
+    }
+
+    // ========== 会话合并操作 ==========
+
+    pub async fn merge_sessions(
+        &self,
+        merged: &Session,
+        frames: &[Frame],
+        originals: &[SessionMergeRecord],
+    ) -> Result<i64> {
+        self.repository
+            .merge_sessions(merged, frames, originals)
+            .await
+    }
+
+    pub async fn get_session_merges(
+        &self,
+        merged_session_id: i64,
+    ) -> Result<Vec<SessionMergeRecord>> {
+        self.repository.get_session_merges(merged_session_id).await
+    }
+
+    // ========== 截屏缺口操作 ==========
+
+    pub async fn save_capture_gaps(&self, gaps: &[CaptureGapRecord]) -> Result<()> {
+        self.repository.save_capture_gaps(gaps).await
+    }
+
+    pub async fn get_capture_gaps(&self, session_id: i64) -> Result<Vec<CaptureGapRecord>> {
+        self.repository.get_capture_gaps(session_id).await
+    }
+
+    // ========== 分析所用帧操作 ==========
+
+    pub async fn save_frame_selection(&self, record: &FrameSelectionRecord) -> Result<()> {
+        self.repository.save_frame_selection(record).await
+    }
+
+    pub async fn get_frame_selection(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<FrameSelectionRecord>> {
+        self.repository.get_frame_selection(session_id).await
+    }
+
+    // ========== 总结自定义字段操作 ==========
+
+    pub async fn save_session_extra_fields(&self, record: &SessionExtraFieldsRecord) -> Result<()> {
+        self.repository.save_session_extra_fields(record).await
+    }
+
+    pub async fn get_session_extra_fields(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionExtraFieldsRecord>> {
+        self.repository.get_session_extra_fields(session_id).await
+    }
+
+    // ========== 分析模式操作 ==========
+
+    pub async fn save_session_analysis_mode(
+        &self,
+        record: &SessionAnalysisModeRecord,
+    ) -> Result<()> {
+        self.repository.save_session_analysis_mode(record).await
+    }
+
+    pub async fn get_session_analysis_mode(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionAnalysisModeRecord>> {
+        self.repository.get_session_analysis_mode(session_id).await
+    }
+
+    // ========== 会话评分操作 ==========
+
+    pub async fn save_session_scores(&self, record: &SessionScoresRecord) -> Result<()> {
+        self.repository.save_session_scores(record).await
+    }
+
+    // ========== 分析失败（死信队列）操作 ==========
+
+    pub async fn save_analysis_failure(&self, record: &AnalysisFailureRecord) -> Result<()> {
+        self.repository.save_analysis_failure(record).await
+    }
+
+    pub async fn get_analysis_failure(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<AnalysisFailureRecord>> {
+        self.repository.get_analysis_failure(session_id).await
+    }
+
+    pub async fn list_analysis_failures(&self) -> Result<Vec<AnalysisFailureRecord>> {
+        self.repository.list_analysis_failures().await
+    }
+
+    pub async fn delete_analysis_failure(&self, session_id: i64) -> Result<()> {
+        self.repository.delete_analysis_failure(session_id).await
+    }
+
+    // ========== 分析队列操作 ==========
+
+    pub async fn save_analysis_queue_entry(&self, record: &AnalysisQueueRecord) -> Result<()> {
+        self.repository.save_analysis_queue_entry(record).await
+    }
+
+    pub async fn get_analysis_queue_entry(
+        &self,
+        session_key: i64,
+    ) -> Result<Option<AnalysisQueueRecord>> {
+        self.repository.get_analysis_queue_entry(session_key).await
+    }
+
+    pub async fn list_analysis_queue(&self) -> Result<Vec<AnalysisQueueRecord>> {
+        self.repository.list_analysis_queue().await
+    }
+
+    pub async fn delete_analysis_queue_entry(&self, session_key: i64) -> Result<()> {
+        self.repository
+            .delete_analysis_queue_entry(session_key)
+            .await
+    }
+
+    // ========== 深度工作时段操作 ==========
+
+    pub async fn replace_deep_work_stretches(
+        &self,
+        session_id: i64,
+        records: &[DeepWorkRecord],
+    ) -> Result<()> {
+        self.repository
+            .replace_deep_work_stretches(session_id, records)
+            .await
+    }
+
+    pub async fn get_deep_work_stretches(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DeepWorkRecord>> {
+        self.repository.get_deep_work_stretches(start, end).await
+    }
+
+    // ========== 总结人工更正操作 ==========
+
+    pub async fn save_summary_correction(&self, record: &SummaryCorrectionRecord) -> Result<()> {
+        self.repository.save_summary_correction(record).await
+    }
+
+    pub async fn get_summary_correction(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SummaryCorrectionRecord>> {
+        self.repository.get_summary_correction(session_id).await
+    }
+
+    pub async fn get_recent_summary_corrections(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<SummaryCorrectionRecord>> {
+        self.repository.get_recent_summary_corrections(limit).await
+    }
+
     // ========== 数据库维护操作 ==========
 
     /// 迁移数据库时区：将 UTC 时间转换为本地时间
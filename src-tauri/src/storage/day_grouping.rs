@@ -0,0 +1,379 @@
+// 跨午夜会话的日期归属 - 按日汇总时决定跨越午夜的会话计入哪一天
+//
+// 原有的按日统计按开始时间分组：23:50–00:20 的会话 30 分钟全部计入前一天，查询后一天时则完全看不到它。
+// 归属策略：start_day（整段计入开始日，即原有行为）、split（在午夜切分，各天只计入各自的时长）、
+// majority_frames（整段计入帧数较多的一天，没有帧时取时长较长的一天）。
+// 时间为本地时间（类型标注为 UTC），日期直接取 date_naive()
+
+use super::{Activity, Database, Session};
+use crate::llm::KeyMoment;
+use crate::time_mapper::TimeMapper;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 跨午夜会话的归属策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MidnightPolicy {
+    /// 整段计入开始日
+    #[default]
+    StartDay,
+    /// 在午夜切分，各天计入各自的时长
+    Split,
+    /// 整段计入帧数较多的一天
+    MajorityFrames,
+}
+
+/// 会话计入某一天的部分
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DayShare {
+    pub date: NaiveDate,
+    pub minutes: i64,
+}
+
+/// 日期当天 00:00
+fn day_start(date: NaiveDate) -> DateTime<Utc> {
+    date.and_time(NaiveTime::MIN).and_utc()
+}
+
+/// 在每个午夜切分 [start, end)，返回各天的时长；不跨天时只有一段
+fn split_at_midnight(start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<(NaiveDate, Duration)> {
+    let mut parts = vec![];
+    let mut cursor = start;
+    loop {
+        let date = cursor.date_naive();
+        let Some(next) = date.succ_opt().map(day_start) else {
+            break;
+        };
+        if end <= next {
+            parts.push((date, end - cursor));
+            break;
+        }
+        parts.push((date, next - cursor));
+        cursor = next;
+    }
+    parts
+}
+
+/// 按策略计算会话计入的日期与分钟数；frame_times 只在 majority_frames 且会话跨天时使用
+pub fn day_shares(
+    session: &Session,
+    frame_times: &[DateTime<Utc>],
+    policy: MidnightPolicy,
+) -> Vec<DayShare> {
+    let total = (session.end_time - session.start_time).num_minutes().max(0);
+    let start_date = session.start_time.date_naive();
+    if session.end_time <= session.start_time {
+        return vec![DayShare {
+            date: start_date,
+            minutes: total,
+        }];
+    }
+    let parts = split_at_midnight(session.start_time, session.end_time);
+
+    let majority_date = || {
+        let mut frames_per_day: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+        for time in frame_times {
+            if parts.iter().any(|(date, _)| *date == time.date_naive()) {
+                *frames_per_day.entry(time.date_naive()).or_default() += 1;
+            }
+        }
+        // 平局时取较早的一天
+        let by_frames = frames_per_day
+            .iter()
+            .rev()
+            .max_by_key(|(_, count)| **count)
+            .map(|(date, _)| *date);
+        by_frames.or_else(|| {
+            parts
+                .iter()
+                .rev()
+                .max_by_key(|(_, duration)| *duration)
+                .map(|(date, _)| *date)
+        })
+    };
+
+    match policy {
+        MidnightPolicy::StartDay => vec![DayShare {
+            date: start_date,
+            minutes: total,
+        }],
+        MidnightPolicy::Split => parts
+            .iter()
+            .map(|(date, duration)| DayShare {
+                date: *date,
+                minutes: duration.num_minutes(),
+            })
+            .collect(),
+        MidnightPolicy::MajorityFrames => vec![DayShare {
+            date: majority_date().unwrap_or(start_date),
+            minutes: total,
+        }],
+    }
+}
+
+/// 按日汇总会话（只保留 [start, end] 内的日期，按日期倒序）；主要类别取各会话首个标签的类别
+pub fn aggregate_activities(
+    sessions: &[(Session, Vec<DayShare>)],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Vec<Activity> {
+    let mut days: BTreeMap<NaiveDate, Activity> = BTreeMap::new();
+    for (session, shares) in sessions {
+        let category = serde_json::from_str::<serde_json::Value>(&session.tags)
+            .ok()
+            .and_then(|tags| tags[0]["category"].as_str().map(str::to_string));
+        for share in shares.iter().filter(|s| s.date >= start && s.date <= end) {
+            let activity = days.entry(share.date).or_insert_with(|| Activity {
+                date: share.date.to_string(),
+                session_count: 0,
+                total_duration_minutes: 0,
+                main_categories: Vec::new(),
+            });
+            activity.session_count += 1;
+            activity.total_duration_minutes += share.minutes as i32;
+            if let Some(category) = &category {
+                if !activity.main_categories.contains(category) {
+                    activity.main_categories.push(category.clone());
+                }
+            }
+        }
+    }
+    days.into_values().rev().collect()
+}
+
+/// 按关键时刻所在的日期分组；无法解析时间的时刻归入会话开始日
+pub fn key_moments_by_day<'a>(
+    moments: &'a [KeyMoment],
+    mapper: &TimeMapper,
+) -> BTreeMap<NaiveDate, Vec<&'a KeyMoment>> {
+    let mut days: BTreeMap<NaiveDate, Vec<&KeyMoment>> = BTreeMap::new();
+    for moment in moments {
+        let date = mapper
+            .date_of(moment.time.trim())
+            .unwrap_or_else(|| mapper.start().date_naive());
+        days.entry(date).or_default().push(moment);
+    }
+    days
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|e| anyhow!("日期格式无效 {}: {}", date, e))
+}
+
+/// 查询与 [start, end] 这几天有重叠的会话，并按策略计算各自计入的日期
+async fn load_shares(
+    db: &Database,
+    start: NaiveDate,
+    end: NaiveDate,
+    policy: MidnightPolicy,
+) -> Result<Vec<(Session, Vec<DayShare>)>> {
+    let until = end.succ_opt().ok_or_else(|| anyhow!("日期超出范围"))?;
+    let sessions = db
+        .get_sessions_overlapping(day_start(start), day_start(until))
+        .await?;
+    let mut result = Vec::with_capacity(sessions.len());
+    for session in sessions {
+        let spans_midnight = session.start_time.date_naive() != session.end_time.date_naive();
+        let frame_times = match (policy, session.id) {
+            (MidnightPolicy::MajorityFrames, Some(id)) if spans_midnight => db
+                .get_frames_by_session(id)
+                .await?
+                .into_iter()
+                .map(|frame| frame.timestamp)
+                .collect(),
+            _ => Vec::new(),
+        };
+        let shares = day_shares(&session, &frame_times, policy);
+        result.push((session, shares));
+    }
+    Ok(result)
+}
+
+/// 按策略统计日期范围内的活动；start_day 沿用数据库的按开始日分组查询
+pub async fn activities(
+    db: &Database,
+    start_date: &str,
+    end_date: &str,
+    policy: MidnightPolicy,
+) -> Result<Vec<Activity>> {
+    if policy == MidnightPolicy::StartDay {
+        return db.get_activities(start_date, end_date).await;
+    }
+    let (start, end) = (parse_date(start_date)?, parse_date(end_date)?);
+    let sessions = load_shares(db, start, end, policy).await?;
+    Ok(aggregate_activities(&sessions, start, end))
+}
+
+/// 按策略计入某天的会话及其当天的分钟数（按开始时间倒序）
+pub async fn day_sessions(
+    db: &Database,
+    date: &str,
+    policy: MidnightPolicy,
+) -> Result<Vec<(Session, i64)>> {
+    let day = parse_date(date)?;
+    let mut sessions: Vec<(Session, i64)> = load_shares(db, day, day, policy)
+        .await?
+        .into_iter()
+        .filter_map(|(session, shares)| {
+            let share = shares.into_iter().find(|share| share.date == day)?;
+            Some((session, share.minutes))
+        })
+        .collect();
+    sessions.sort_by_key(|(session, _)| std::cmp::Reverse(session.start_time));
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Frame;
+
+    fn time(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    /// 23:50–00:20，每分钟一帧（前一天 10 帧，后一天 21 帧）
+    fn overnight() -> (Session, Vec<DateTime<Utc>>) {
+        let start = time("2025-01-01T23:50:00Z");
+        let session = Session {
+            id: None,
+            start_time: start,
+            end_time: start + Duration::minutes(30),
+            title: "跨午夜".to_string(),
+            summary: String::new(),
+            video_path: None,
+            tags: r#"[{"category":"work","confidence":0.9,"keywords":[]}]"#.to_string(),
+            created_at: None,
+            device_name: None,
+            device_type: None,
+            pinned: false,
+        };
+        let frames = (0..=30).map(|i| start + Duration::minutes(i)).collect();
+        (session, frames)
+    }
+
+    #[test]
+    fn test_overnight_session_under_each_policy() {
+        let (session, frames) = overnight();
+        let share = |day: &str, minutes| DayShare {
+            date: date(day),
+            minutes,
+        };
+
+        assert_eq!(
+            day_shares(&session, &frames, MidnightPolicy::StartDay),
+            vec![share("2025-01-01", 30)]
+        );
+        assert_eq!(
+            day_shares(&session, &frames, MidnightPolicy::Split),
+            vec![share("2025-01-01", 10), share("2025-01-02", 20)]
+        );
+        assert_eq!(
+            day_shares(&session, &frames, MidnightPolicy::MajorityFrames),
+            vec![share("2025-01-02", 30)]
+        );
+        // 没有帧时按时长
+        assert_eq!(
+            day_shares(&session, &[], MidnightPolicy::MajorityFrames),
+            vec![share("2025-01-02", 30)]
+        );
+
+        // 切分后两天的总时长不多算也不少算
+        let shares = day_shares(&session, &frames, MidnightPolicy::Split);
+        let activities =
+            aggregate_activities(&[(session, shares)], date("2025-01-01"), date("2025-01-02"));
+        assert_eq!(activities.len(), 2);
+        assert_eq!(activities[0].date, "2025-01-02");
+        assert_eq!(
+            activities
+                .iter()
+                .map(|a| a.total_duration_minutes)
+                .sum::<i32>(),
+            30
+        );
+        assert_eq!(activities[1].main_categories, vec!["work"]);
+    }
+
+    #[test]
+    fn test_key_moments_after_midnight_belong_to_next_day() {
+        let (session, frames) = overnight();
+        let mapper = TimeMapper::from_timestamps(frames).unwrap();
+        let moment = |time: &str| KeyMoment {
+            time: time.to_string(),
+            description: String::new(),
+            importance: 3,
+        };
+        let moments = vec![
+            moment("05:00"),
+            moment("15:00"),
+            moment("2025-01-02T00:18:00+08:00"),
+            moment("bad"),
+        ];
+        assert_eq!(mapper.date_of("09:59"), Some(date("2025-01-01")));
+        assert_eq!(mapper.date_of("10:00"), Some(date("2025-01-02")));
+
+        let days = key_moments_by_day(&moments, &mapper);
+        let times =
+            |day: &str| -> Vec<&str> { days[&date(day)].iter().map(|m| m.time.as_str()).collect() };
+        assert_eq!(times("2025-01-01"), vec!["05:00", "bad"]);
+        assert_eq!(
+            times("2025-01-02"),
+            vec!["15:00", "2025-01-02T00:18:00+08:00"]
+        );
+        assert_eq!(session.start_time, mapper.start());
+    }
+
+    #[tokio::test]
+    async fn test_day_queries_follow_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let (session, frames) = overnight();
+        let id = db.insert_session(&session).await.unwrap();
+        let frames: Vec<Frame> = frames
+            .into_iter()
+            .map(|timestamp| Frame {
+                id: None,
+                session_id: id,
+                timestamp,
+                file_path: format!("/frames/{}.jpg", timestamp.timestamp_millis()),
+            })
+            .collect();
+        db.insert_frames(&frames).await.unwrap();
+
+        for (policy, first, second, dates) in [
+            (MidnightPolicy::StartDay, Some(30), None, vec!["2025-01-01"]),
+            (
+                MidnightPolicy::Split,
+                Some(10),
+                Some(20),
+                vec!["2025-01-02", "2025-01-01"],
+            ),
+            (
+                MidnightPolicy::MajorityFrames,
+                None,
+                Some(30),
+                vec!["2025-01-02"],
+            ),
+        ] {
+            for (day, expected) in [("2025-01-01", first), ("2025-01-02", second)] {
+                let sessions = day_sessions(&db, day, policy).await.unwrap();
+                let minutes = sessions.first().map(|(_, minutes)| *minutes);
+                assert_eq!(minutes, expected, "{:?} {}", policy, day);
+            }
+            let days = activities(&db, "2025-01-01", "2025-01-02", policy)
+                .await
+                .unwrap();
+            let days: Vec<&str> = days.iter().map(|a| a.date.as_str()).collect();
+            assert_eq!(days, dates, "{:?}", policy);
+        }
+    }
+}
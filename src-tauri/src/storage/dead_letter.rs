@@ -0,0 +1,162 @@
+// 死信队列 - 多次分析失败的会话不再自动重试，留待人工查看与重新入队
+//
+// 自动分析每轮扫描未分析的视频，失败的会话原先只留下“正在分析...”的临时会话，既不再重试也看不到原因。
+// 现在每次失败记录错误与次数：次数未达上限时下一轮重试，达到上限后移入死信队列（dead_lettered_at 非空），
+// 不再自动重试；重新入队会清零次数，下一轮自动分析时再次尝试。分析成功后删除失败记录
+
+use super::{AnalysisFailureRecord, Database};
+use anyhow::{anyhow, Result};
+
+/// 默认的最大尝试次数（含首次分析）
+pub const DEFAULT_MAX_ATTEMPTS: i64 = 3;
+
+/// 错误信息的最大长度（字符），过长的错误（如完整的响应体）截断后保存
+const MAX_ERROR_CHARS: usize = 2000;
+
+/// 记录一次分析失败；失败次数达到 max_attempts 时移入死信队列
+pub async fn record_failure(
+    db: &Database,
+    session_id: i64,
+    error: &str,
+    max_attempts: i64,
+) -> Result<AnalysisFailureRecord> {
+    let now = super::local_now();
+    let last_error: String = error.chars().take(MAX_ERROR_CHARS).collect();
+    let mut record = match db.get_analysis_failure(session_id).await? {
+        Some(previous) => AnalysisFailureRecord {
+            last_error,
+            attempts: previous.attempts + 1,
+            last_failed_at: now,
+            ..previous
+        },
+        None => AnalysisFailureRecord {
+            session_id,
+            last_error,
+            attempts: 1,
+            first_failed_at: now,
+            last_failed_at: now,
+            dead_lettered_at: None,
+        },
+    };
+    if record.dead_lettered_at.is_none() && record.attempts >= max_attempts.max(1) {
+        record.dead_lettered_at = Some(now);
+    }
+    db.save_analysis_failure(&record).await?;
+    Ok(record)
+}
+
+/// 分析成功：删除失败记录
+pub async fn record_success(db: &Database, session_id: i64) -> Result<()> {
+    db.delete_analysis_failure(session_id).await
+}
+
+/// 失败过但仍可自动重试的会话（最早失败的在前）
+pub async fn retryable(db: &Database) -> Result<Vec<AnalysisFailureRecord>> {
+    let mut records: Vec<_> = db
+        .list_analysis_failures()
+        .await?
+        .into_iter()
+        .filter(|record| record.dead_lettered_at.is_none())
+        .collect();
+    records.reverse();
+    Ok(records)
+}
+
+/// 死信队列中的会话（最近失败的在前）
+pub async fn dead_letters(db: &Database) -> Result<Vec<AnalysisFailureRecord>> {
+    Ok(db
+        .list_analysis_failures()
+        .await?
+        .into_iter()
+        .filter(|record| record.dead_lettered_at.is_some())
+        .collect())
+}
+
+/// 把死信队列中的会话重新入队：清零失败次数，下一轮自动分析时重试
+pub async fn requeue(db: &Database, session_id: i64) -> Result<AnalysisFailureRecord> {
+    let record = db
+        .get_analysis_failure(session_id)
+        .await?
+        .filter(|record| record.dead_lettered_at.is_some())
+        .ok_or_else(|| anyhow!("会话 {} 不在死信队列中", session_id))?;
+    let record = AnalysisFailureRecord {
+        attempts: 0,
+        dead_lettered_at: None,
+        ..record
+    };
+    db.save_analysis_failure(&record).await?;
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::Session;
+
+    async fn database_with_session() -> (tempfile::TempDir, Database, i64) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let now = crate::storage::local_now();
+        let session_id = db
+            .insert_session(&Session {
+                id: None,
+                start_time: now,
+                end_time: now,
+                title: "视频分析中".to_string(),
+                summary: "正在分析...".to_string(),
+                video_path: Some("/videos/a.mp4".to_string()),
+                tags: "[]".to_string(),
+                created_at: Some(now),
+                device_name: None,
+                device_type: None,
+                pinned: false,
+            })
+            .await
+            .unwrap();
+        (dir, db, session_id)
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_session_is_dead_lettered_and_requeued() {
+        let (_dir, db, session_id) = database_with_session().await;
+
+        for attempt in 1..3 {
+            let record = record_failure(&db, session_id, "服务端 500", 3)
+                .await
+                .unwrap();
+            assert_eq!(record.attempts, attempt);
+            assert!(record.dead_lettered_at.is_none());
+        }
+        assert_eq!(retryable(&db).await.unwrap().len(), 1);
+
+        // 第 3 次失败后不再自动重试
+        let record = record_failure(&db, session_id, "帧无法解码", 3)
+            .await
+            .unwrap();
+        assert!(record.dead_lettered_at.is_some());
+        assert!(retryable(&db).await.unwrap().is_empty());
+        let dead = dead_letters(&db).await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(
+            (
+                dead[0].session_id,
+                dead[0].attempts,
+                dead[0].last_error.as_str()
+            ),
+            (session_id, 3, "帧无法解码")
+        );
+
+        // 重新入队后回到重试队列，成功后删除记录
+        let record = requeue(&db, session_id).await.unwrap();
+        assert_eq!(record.attempts, 0);
+        assert_eq!(record.first_failed_at, dead[0].first_failed_at);
+        assert!(dead_letters(&db).await.unwrap().is_empty());
+        assert_eq!(retryable(&db).await.unwrap()[0].session_id, session_id);
+        assert!(requeue(&db, session_id).await.is_err());
+
+        record_success(&db, session_id).await.unwrap();
+        assert!(db.get_analysis_failure(session_id).await.unwrap().is_none());
+    }
+}
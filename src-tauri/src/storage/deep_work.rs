@@ -0,0 +1,442 @@
+// 深度工作识别 - 从会话的逐帧活动中找出长时间专注于单个应用的时段
+//
+// 会话总结只给出整段的专注度评分，看不出一天里哪几段是真正不受打扰的深度工作。
+// 每帧按覆盖它的时间线卡片取所用应用与专注度：落在干扰活动内的帧记为该干扰，
+// 工作、学习类卡片取会话的专注度评分，其余类别记为 0。专注度达到阈值、应用相同的相邻帧连成一段，
+// 不超过 max_interruption_secs 的短暂打断可以跨过（计一次切换），更长的打断或没有帧的空档结束该段。
+// 时长与每小时切换次数都满足配置的时段作为派生记录保存，重新分析时整体替换。
+// 时间为本地时间（类型标注为 UTC）
+//
+// 局限：帧记录只有时间与路径，没有逐帧的前台应用；键盘、滚动等输入活动只作为
+// AnalysisContext.input_activity 汇总进提示词，不随帧保存。因此应用与专注度都取自时间线卡片，
+// 卡片粒度以下的切换识别不到。导入的视频没有帧记录，按 FALLBACK_SAMPLE_SECS 在卡片时段内取样
+
+use super::{Database, DeepWorkRecord, TimelineCardRecord};
+use crate::llm::{ActivityCategory, AppSites, Distraction};
+use crate::models::DeepWorkSettings;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+
+/// 没有帧记录时按此间隔在卡片覆盖的时段内取样（秒）
+const FALLBACK_SAMPLE_SECS: i64 = 60;
+
+/// 某一时刻的活动
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivitySample {
+    pub timestamp: DateTime<Utc>,
+    /// 前台应用；无法确定时为 None
+    pub app: Option<String>,
+    /// 专注度（0-100）；无法确定时为 None
+    pub focus: Option<f32>,
+}
+
+/// 识别出的深度工作时段
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeepWorkStretch {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub app: String,
+    /// 专注部分按时长加权的平均专注度
+    pub focus_score: f32,
+    /// 跨过的短暂打断次数
+    pub interruptions: usize,
+}
+
+impl DeepWorkStretch {
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// 相邻取样点之间的一段：同一应用、同为专注或非专注的连续取样合并为一段
+#[derive(Debug)]
+struct Run {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    app: Option<String>,
+    focused: bool,
+    /// 专注度 × 秒数之和
+    focus_weight: f64,
+}
+
+impl Run {
+    fn secs(&self) -> i64 {
+        (self.end - self.start).num_seconds()
+    }
+}
+
+/// 把取样点切成连续的段：每个取样点持续到下一个取样点，间隔超过 max_gap_secs 时在此断开
+fn runs(samples: &[ActivitySample], config: &DeepWorkSettings) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for (index, sample) in samples.iter().enumerate() {
+        let end = samples
+            .get(index + 1)
+            .map(|next| next.timestamp)
+            .filter(|next| (*next - sample.timestamp).num_seconds() <= config.max_gap_secs)
+            .unwrap_or(sample.timestamp);
+        let focus = sample.focus.unwrap_or(0.0);
+        let focused = sample.app.is_some() && focus >= config.min_focus;
+        let weight = f64::from(focus) * (end - sample.timestamp).num_seconds() as f64;
+        match runs.last_mut() {
+            Some(last)
+                if last.end == sample.timestamp
+                    && last.focused == focused
+                    && last.app == sample.app =>
+            {
+                last.end = end;
+                last.focus_weight += weight;
+            }
+            _ => runs.push(Run {
+                start: sample.timestamp,
+                end,
+                app: sample.app.clone(),
+                focused,
+                focus_weight: weight,
+            }),
+        }
+    }
+    runs
+}
+
+/// 识别深度工作时段（取样点须按时间升序）
+pub fn detect(samples: &[ActivitySample], config: &DeepWorkSettings) -> Vec<DeepWorkStretch> {
+    let runs = runs(samples, config);
+    let min_secs = config.min_duration_mins.saturating_mul(60) as i64;
+    let mut stretches = Vec::new();
+    let mut index = 0;
+    while index < runs.len() {
+        let first = &runs[index];
+        if !first.focused {
+            index += 1;
+            continue;
+        }
+        let continues = |run: &Run| run.focused && run.app == first.app;
+
+        // 向后延伸：同一应用的专注段直接相连，或中间只隔着短暂打断
+        let mut last = index;
+        let mut interruptions = 0;
+        loop {
+            let mut next = last + 1;
+            let mut interrupted = 0;
+            while next < runs.len()
+                && runs[next].start == runs[next - 1].end
+                && !continues(&runs[next])
+                && interrupted <= config.max_interruption_secs
+            {
+                interrupted += runs[next].secs();
+                next += 1;
+            }
+            let resumes = next < runs.len()
+                && runs[next].start == runs[next - 1].end
+                && continues(&runs[next])
+                && interrupted <= config.max_interruption_secs;
+            if !resumes {
+                break;
+            }
+            if next > last + 1 {
+                interruptions += 1;
+            }
+            last = next;
+        }
+
+        let (start, end) = (first.start, runs[last].end);
+        let (focus_weight, focus_secs) = runs[index..=last]
+            .iter()
+            .filter(|run| continues(run))
+            .fold((0.0, 0i64), |(weight, secs), run| {
+                (weight + run.focus_weight, secs + run.secs())
+            });
+        let secs = (end - start).num_seconds();
+        let switches_per_hour = interruptions as f64 * 3600.0 / secs.max(1) as f64;
+        if secs >= min_secs && switches_per_hour <= config.max_switches_per_hour {
+            stretches.push(DeepWorkStretch {
+                start,
+                end,
+                app: first.app.clone().unwrap_or_default(),
+                focus_score: (focus_weight / focus_secs.max(1) as f64) as f32,
+                interruptions,
+            });
+        }
+        index = last + 1;
+    }
+    stretches
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|time| time.naive_local().and_utc())
+}
+
+/// 按时间线卡片推断各时刻的活动；没有帧时间时在卡片覆盖的时段内每分钟取样
+///
+/// focus_score 为会话的专注度评分，只赋给工作、学习类卡片覆盖的时刻
+pub fn samples_from_timeline(
+    frame_times: &[DateTime<Utc>],
+    cards: &[TimelineCardRecord],
+    focus_score: Option<f32>,
+) -> Vec<ActivitySample> {
+    struct Span {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        app: Option<String>,
+        focus: Option<f32>,
+        distractions: Vec<(DateTime<Utc>, DateTime<Utc>, String)>,
+    }
+
+    let spans: Vec<Span> = cards
+        .iter()
+        .filter_map(|card| {
+            let (start, end) = (parse_time(&card.start_time)?, parse_time(&card.end_time)?);
+            let app = serde_json::from_str::<AppSites>(&card.app_sites)
+                .ok()
+                .map(|sites| sites.primary.trim().to_string())
+                .filter(|primary| !primary.is_empty());
+            let focused = matches!(
+                crate::llm::map_category(&card.category),
+                ActivityCategory::Work | ActivityCategory::Learning
+            );
+            let distractions = card
+                .distractions
+                .as_deref()
+                .and_then(|json| serde_json::from_str::<Vec<Distraction>>(json).ok())
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|d| {
+                    Some((
+                        parse_time(&d.start_time)?,
+                        parse_time(&d.end_time)?,
+                        d.title,
+                    ))
+                })
+                .collect();
+            Some(Span {
+                start,
+                end,
+                app,
+                focus: focus_score.map(|score| if focused { score } else { 0.0 }),
+                distractions,
+            })
+        })
+        .collect();
+
+    let mut times = frame_times.to_vec();
+    if times.is_empty() {
+        for span in &spans {
+            let mut time = span.start;
+            while time < span.end {
+                times.push(time);
+                time += Duration::seconds(FALLBACK_SAMPLE_SECS);
+            }
+        }
+    }
+    times.sort();
+    times.dedup();
+
+    times
+        .into_iter()
+        .map(|timestamp| {
+            let span = spans
+                .iter()
+                .find(|span| span.start <= timestamp && timestamp < span.end);
+            let distraction = span.and_then(|span| {
+                span.distractions
+                    .iter()
+                    .find(|(start, end, _)| *start <= timestamp && timestamp < *end)
+            });
+            match (span, distraction) {
+                (_, Some((_, _, title))) => ActivitySample {
+                    timestamp,
+                    app: Some(title.clone()),
+                    focus: Some(0.0),
+                },
+                (Some(span), None) => ActivitySample {
+                    timestamp,
+                    app: span.app.clone(),
+                    focus: span.focus,
+                },
+                (None, None) => ActivitySample {
+                    timestamp,
+                    app: None,
+                    focus: None,
+                },
+            }
+        })
+        .collect()
+}
+
+/// 重新识别会话的深度工作时段并替换已保存的记录，返回保存的条数
+pub async fn refresh_session(
+    db: &Database,
+    session_id: i64,
+    frame_times: &[DateTime<Utc>],
+    focus_score: Option<f32>,
+    config: &DeepWorkSettings,
+) -> Result<usize> {
+    let cards = db.get_timeline_cards_by_session(session_id).await?;
+    let samples = samples_from_timeline(frame_times, &cards, focus_score);
+    let now = super::local_now();
+    let records: Vec<DeepWorkRecord> = detect(&samples, config)
+        .into_iter()
+        .map(|stretch| DeepWorkRecord {
+            id: None,
+            session_id,
+            start_time: stretch.start,
+            end_time: stretch.end,
+            duration_secs: stretch.duration().num_seconds(),
+            app: stretch.app,
+            focus_score: f64::from(stretch.focus_score),
+            interruptions: stretch.interruptions as i64,
+            created_at: now,
+        })
+        .collect();
+    db.replace_deep_work_stretches(session_id, &records).await?;
+    Ok(records.len())
+}
+
+/// 某天（YYYY-MM-DD）的深度工作时段：与当天有重叠的全部时段，按开始时间排序
+pub async fn day_stretches(db: &Database, date: &str) -> Result<Vec<DeepWorkRecord>> {
+    let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|e| anyhow!("日期格式无效 {}: {}", date, e))?;
+    let start = date.and_time(NaiveTime::MIN).and_utc();
+    db.get_deep_work_stretches(start, start + Duration::days(1))
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(minute: i64) -> DateTime<Utc> {
+        "2025-01-01T09:00:00Z".parse::<DateTime<Utc>>().unwrap() + Duration::minutes(minute)
+    }
+
+    /// 每分钟一个取样点
+    fn sample(minute: i64, app: &str, focus: f32) -> ActivitySample {
+        ActivitySample {
+            timestamp: at(minute),
+            app: Some(app.to_string()),
+            focus: Some(focus),
+        }
+    }
+
+    #[test]
+    fn test_one_focus_stretch_is_found_amid_noise() {
+        let apps = ["Slack", "Chrome", "Mail", "Finder"];
+        let mut samples: Vec<ActivitySample> = (0..20)
+            .map(|m| sample(m, apps[m as usize % apps.len()], 40.0))
+            .collect();
+        // 09:20-10:00 在编辑器中专注，09:35 切到 Slack 1 分钟
+        samples.extend((20..60).map(|m| match m {
+            35 => sample(m, "Slack", 10.0),
+            _ => sample(m, "VS Code", 85.0),
+        }));
+        samples.extend((60..80).map(|m| sample(m, apps[m as usize % apps.len()], 50.0)));
+        // 应用相同但专注度不够的长时段不计入
+        samples.extend((80..120).map(|m| sample(m, "Chrome", 30.0)));
+
+        let stretches = detect(&samples, &DeepWorkSettings::default());
+        assert_eq!(stretches.len(), 1, "{:?}", stretches);
+        let stretch = &stretches[0];
+        assert_eq!(stretch.app, "VS Code");
+        assert_eq!((stretch.start, stretch.end), (at(20), at(60)));
+        assert_eq!(stretch.interruptions, 1);
+        assert!((stretch.focus_score - 85.0).abs() < 0.01);
+
+        // 时长阈值与专注度阈值可配置
+        let config = DeepWorkSettings {
+            min_duration_mins: 45,
+            ..Default::default()
+        };
+        assert!(detect(&samples, &config).is_empty());
+        let config = DeepWorkSettings {
+            min_focus: 90.0,
+            ..Default::default()
+        };
+        assert!(detect(&samples, &config).is_empty());
+        // 切换过于频繁时不算深度工作
+        let config = DeepWorkSettings {
+            max_switches_per_hour: 1.0,
+            ..Default::default()
+        };
+        assert!(detect(&samples, &config).is_empty());
+
+        // 没有帧的空档超过 max_gap_secs 时断开
+        samples.retain(|s| s.timestamp < at(30) || s.timestamp >= at(40));
+        assert!(detect(&samples, &DeepWorkSettings::default()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stretches_are_derived_from_timeline_and_listed_by_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let session_id = db
+            .insert_session(&crate::storage::Session {
+                id: None,
+                start_time: at(0),
+                end_time: at(60),
+                title: "编写代码".to_string(),
+                summary: String::new(),
+                video_path: None,
+                tags: "[]".to_string(),
+                created_at: Some(at(60)),
+                device_name: None,
+                device_type: None,
+                pinned: false,
+            })
+            .await
+            .unwrap();
+        let card = |start: i64, end: i64, category: &str, app: &str, distractions: &str| {
+            TimelineCardRecord {
+                id: None,
+                session_id,
+                llm_call_id: None,
+                start_time: at(start).format("%Y-%m-%dT%H:%M:%S+08:00").to_string(),
+                end_time: at(end).format("%Y-%m-%dT%H:%M:%S+08:00").to_string(),
+                category: category.to_string(),
+                subcategory: String::new(),
+                title: String::new(),
+                summary: String::new(),
+                detailed_summary: String::new(),
+                distractions: Some(distractions.to_string()),
+                app_sites: format!(r#"{{"primary":"{}","secondary":[]}}"#, app),
+                video_preview_path: None,
+                created_at: at(60),
+            }
+        };
+        let distraction = format!(
+            r#"[{{"startTime":"{}","endTime":"{}","title":"微信","summary":""}}]"#,
+            at(20).format("%Y-%m-%dT%H:%M:%S+08:00"),
+            at(21).format("%Y-%m-%dT%H:%M:%S+08:00"),
+        );
+        db.insert_timeline_cards(&[
+            card(0, 10, "entertainment", "Bilibili", "[]"),
+            card(10, 50, "coding", "VS Code", &distraction),
+            card(50, 60, "communication", "Slack", "[]"),
+        ])
+        .await
+        .unwrap();
+
+        let settings = DeepWorkSettings::default();
+        // 没有帧时按卡片每分钟取样
+        let saved = refresh_session(&db, session_id, &[], Some(80.0), &settings)
+            .await
+            .unwrap();
+        assert_eq!(saved, 1);
+        // 重新识别时替换而不是追加
+        refresh_session(&db, session_id, &[], Some(80.0), &settings)
+            .await
+            .unwrap();
+
+        let today = day_stretches(&db, "2025-01-01").await.unwrap();
+        assert_eq!(today.len(), 1);
+        assert_eq!(today[0].app, "VS Code");
+        assert_eq!((today[0].start_time, today[0].end_time), (at(10), at(50)));
+        assert_eq!(today[0].duration_secs, 40 * 60);
+        assert_eq!(today[0].interruptions, 1);
+        assert!(day_stretches(&db, "2025-01-02").await.unwrap().is_empty());
+        assert!(day_stretches(&db, "2025/01/01").await.is_err());
+    }
+}
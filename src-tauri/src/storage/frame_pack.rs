@@ -0,0 +1,349 @@
+// 帧归档模块 - 将会话的零散帧文件合并为单个归档文件
+//
+// 归档格式：魔数(8字节) + 帧数据依次拼接 + JSON 索引 + 索引偏移(u64 小端, 8字节)
+// 归档内的帧以虚拟路径 `<归档路径>#<帧文件名>` 记录在数据库中
+
+use super::{Database, Frame};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// 归档文件头魔数
+const PACK_MAGIC: &[u8; 8] = b"SAFPACK1";
+
+/// 归档文件扩展名
+const PACK_EXTENSION: &str = "pack";
+
+/// 归档存放目录（位于帧目录下）
+const PACKS_DIR_NAME: &str = "packs";
+
+/// 归档索引项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PackEntry {
+    name: String,
+    offset: u64,
+    len: u64,
+}
+
+/// 拆分虚拟路径，返回 (归档路径, 帧文件名)；普通文件路径返回 None
+pub fn split_packed_path(path: &str) -> Option<(&str, &str)> {
+    let (pack, name) = path.rsplit_once('#')?;
+    let is_pack = Path::new(pack).extension().and_then(|e| e.to_str()) == Some(PACK_EXTENSION);
+    (is_pack && !name.is_empty()).then_some((pack, name))
+}
+
+/// 是否为归档内的帧
+pub fn is_packed_path(path: &str) -> bool {
+    split_packed_path(path).is_some()
+}
+
+/// 帧实际所在的磁盘文件（归档内的帧返回归档文件本身）
+pub fn storage_path(path: &str) -> &str {
+    split_packed_path(path).map_or(path, |(pack, _)| pack)
+}
+
+/// 读取帧数据，零散文件与归档内的帧均可
+pub async fn read_frame(path: &str) -> Result<Vec<u8>> {
+    if !is_packed_path(path) {
+        return Ok(tokio::fs::read(path).await?);
+    }
+    let path = path.to_string();
+    tokio::task::spawn_blocking(move || read_frame_blocking(&path)).await?
+}
+
+/// 同步读取帧数据（用于图片解码等同步上下文）
+pub fn read_frame_blocking(path: &str) -> Result<Vec<u8>> {
+    let Some((pack, name)) = split_packed_path(path) else {
+        return Ok(std::fs::read(path)?);
+    };
+
+    let mut file = File::open(pack)?;
+    let index = read_index(&mut file)?;
+    let entry = index
+        .iter()
+        .find(|entry| entry.name == name)
+        .ok_or_else(|| anyhow!("归档 {} 中没有帧 {}", pack, name))?;
+
+    file.seek(SeekFrom::Start(entry.offset))?;
+    let mut data = vec![0u8; entry.len as usize];
+    file.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// 将帧列表中的归档帧解出到指定目录，返回可直接按文件读取的路径（零散帧保持不变）
+///
+/// 用于 FFmpeg、命令行工具等只接受真实文件路径的场景
+pub fn unpack_to_dir(frames: &[String], dir: &Path) -> Result<Vec<String>> {
+    frames
+        .iter()
+        .enumerate()
+        .map(|(idx, frame)| {
+            let Some((_, name)) = split_packed_path(frame) else {
+                return Ok(frame.clone());
+            };
+            // 加序号前缀，避免不同归档中的同名帧相互覆盖
+            let target = dir.join(format!("{idx:06}_{name}"));
+            std::fs::write(&target, read_frame_blocking(frame)?)?;
+            Ok(target.to_string_lossy().to_string())
+        })
+        .collect()
+}
+
+fn read_index(file: &mut File) -> Result<Vec<PackEntry>> {
+    let file_len = file.seek(SeekFrom::End(0))?;
+    let header_len = PACK_MAGIC.len() as u64;
+    if file_len < header_len + 8 {
+        return Err(anyhow!("帧归档文件不完整"));
+    }
+
+    let mut magic = [0u8; 8];
+    file.seek(SeekFrom::Start(0))?;
+    file.read_exact(&mut magic)?;
+    if &magic != PACK_MAGIC {
+        return Err(anyhow!("不是有效的帧归档文件"));
+    }
+
+    let mut tail = [0u8; 8];
+    file.seek(SeekFrom::End(-8))?;
+    file.read_exact(&mut tail)?;
+    let index_offset = u64::from_le_bytes(tail);
+    if index_offset < header_len || index_offset > file_len - 8 {
+        return Err(anyhow!("帧归档索引位置无效"));
+    }
+
+    let mut index = vec![0u8; (file_len - 8 - index_offset) as usize];
+    file.seek(SeekFrom::Start(index_offset))?;
+    file.read_exact(&mut index)?;
+    Ok(serde_json::from_slice(&index)?)
+}
+
+/// 将帧文件写入归档，返回每个源文件对应的虚拟路径（读取失败的源文件为 None）
+///
+/// 先写临时文件再重命名，中途失败不会留下半个归档
+pub fn write_pack(pack_path: &Path, sources: &[String]) -> Result<Vec<Option<String>>> {
+    let tmp_path = pack_path.with_extension("pack.tmp");
+    let mut writer = BufWriter::new(File::create(&tmp_path)?);
+    writer.write_all(PACK_MAGIC)?;
+
+    let mut offset = PACK_MAGIC.len() as u64;
+    let mut index = Vec::new();
+    let mut names = HashSet::new();
+    let mut packed = Vec::with_capacity(sources.len());
+    for (idx, source) in sources.iter().enumerate() {
+        let data = match std::fs::read(source) {
+            Ok(data) => data,
+            Err(e) => {
+                warn!("读取帧文件失败，跳过打包 {}: {}", source, e);
+                packed.push(None);
+                continue;
+            }
+        };
+        let file_name = Path::new(source)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| format!("{idx}"));
+        let name = if names.insert(file_name.clone()) {
+            file_name
+        } else {
+            let renamed = format!("{idx}_{file_name}");
+            names.insert(renamed.clone());
+            renamed
+        };
+
+        writer.write_all(&data)?;
+        index.push(PackEntry {
+            name: name.clone(),
+            offset,
+            len: data.len() as u64,
+        });
+        offset += data.len() as u64;
+        packed.push(Some(format!("{}#{}", pack_path.to_string_lossy(), name)));
+    }
+
+    writer.write_all(&serde_json::to_vec(&index)?)?;
+    writer.write_all(&offset.to_le_bytes())?;
+    writer
+        .into_inner()
+        .map_err(|e| e.into_error())?
+        .sync_all()?;
+    std::fs::rename(&tmp_path, pack_path)?;
+    Ok(packed)
+}
+
+/// 将会话的零散帧打包为单个归档，更新数据库中的帧路径并删除原文件
+///
+/// 归档放在帧所在目录的 packs 子目录下；返回本次打包的帧数
+pub async fn pack_session_frames(db: &Database, session_id: i64) -> Result<usize> {
+    let frames = db.get_frames_by_session(session_id).await?;
+    let sources: Vec<String> = frames
+        .iter()
+        .map(|frame| frame.file_path.clone())
+        .filter(|path| !is_packed_path(path))
+        .collect();
+    let Some(frames_dir) = sources
+        .first()
+        .and_then(|path| Path::new(path).parent())
+        .map(Path::to_path_buf)
+    else {
+        return Ok(0);
+    };
+
+    let packs_dir = frames_dir.join(PACKS_DIR_NAME);
+    tokio::fs::create_dir_all(&packs_dir).await?;
+    // 文件名带时间戳，同一会话可多次打包（如补打包新增的零散帧）
+    let pack_path: PathBuf = packs_dir.join(format!(
+        "session_{}_{}.{}",
+        session_id,
+        Utc::now().timestamp_millis(),
+        PACK_EXTENSION
+    ));
+
+    let packed = {
+        let pack_path = pack_path.clone();
+        let sources = sources.clone();
+        tokio::task::spawn_blocking(move || write_pack(&pack_path, &sources)).await??
+    };
+    let replaced: Vec<(String, String)> = sources
+        .into_iter()
+        .zip(packed)
+        .filter_map(|(source, packed)| packed.map(|packed| (source, packed)))
+        .collect();
+    if replaced.is_empty() {
+        tokio::fs::remove_file(&pack_path).await.ok();
+        return Ok(0);
+    }
+
+    let packed_paths: HashMap<&str, &str> = replaced
+        .iter()
+        .map(|(source, packed)| (source.as_str(), packed.as_str()))
+        .collect();
+    let updated: Vec<Frame> = frames
+        .into_iter()
+        .map(|frame| {
+            let file_path = packed_paths
+                .get(frame.file_path.as_str())
+                .map_or(frame.file_path.clone(), |packed| packed.to_string());
+            Frame {
+                id: None,
+                file_path,
+                ..frame
+            }
+        })
+        .collect();
+    db.delete_frames_by_session(session_id).await?;
+    db.insert_frames(&updated).await?;
+    db.invalidate_session(session_id).await;
+
+    // 数据库已指向归档后再删除原文件
+    for (source, _) in &replaced {
+        if let Err(e) = tokio::fs::remove_file(source).await {
+            warn!("删除已打包的帧文件失败 {}: {}", source, e);
+        }
+    }
+
+    info!(
+        "会话 {} 已打包 {} 帧到 {}",
+        session_id,
+        replaced.len(),
+        pack_path.display()
+    );
+    Ok(replaced.len())
+}
+
+/// 按需打包已有会话的零散帧（未指定会话时处理全部会话），返回 (会话数, 帧数)
+pub async fn pack_existing_sessions(
+    db: &Database,
+    session_ids: Option<Vec<i64>>,
+) -> Result<(usize, usize)> {
+    let session_ids = match session_ids {
+        Some(ids) => ids,
+        None => db
+            .get_all_sessions()
+            .await?
+            .into_iter()
+            .filter_map(|session| session.id)
+            .collect(),
+    };
+
+    let mut packed_sessions = 0;
+    let mut packed_frames = 0;
+    for session_id in session_ids {
+        match pack_session_frames(db, session_id).await {
+            Ok(0) => {}
+            Ok(count) => {
+                packed_sessions += 1;
+                packed_frames += count;
+            }
+            Err(e) => warn!("打包会话 {} 的帧失败: {}", session_id, e),
+        }
+    }
+
+    info!(
+        "帧打包迁移完成: {} 个会话，共 {} 帧",
+        packed_sessions, packed_frames
+    );
+    Ok((packed_sessions, packed_frames))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_frame_from_pack() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("1700000000000.jpg");
+        let second = dir.path().join("1700000001000.jpg");
+        std::fs::write(&first, b"first frame").unwrap();
+        std::fs::write(&second, b"second frame data").unwrap();
+        let missing = dir.path().join("missing.jpg");
+
+        let sources: Vec<String> = [&first, &missing, &second]
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let pack_path = dir.path().join("session_1.pack");
+        let packed = write_pack(&pack_path, &sources).unwrap();
+
+        assert!(packed[1].is_none());
+        let second_packed = packed[2].as_deref().unwrap();
+        assert!(is_packed_path(second_packed));
+        assert_eq!(storage_path(second_packed), pack_path.to_string_lossy());
+        assert_eq!(
+            read_frame_blocking(second_packed).unwrap(),
+            b"second frame data"
+        );
+        assert_eq!(
+            read_frame_blocking(packed[0].as_deref().unwrap()).unwrap(),
+            b"first frame"
+        );
+
+        let unknown = format!("{}#nope.jpg", pack_path.to_string_lossy());
+        assert!(read_frame_blocking(&unknown).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_frame_handles_loose_and_unpacked_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let loose = dir.path().join("1700000000000.jpg");
+        std::fs::write(&loose, b"loose").unwrap();
+        let loose = loose.to_string_lossy().to_string();
+        assert!(!is_packed_path(&loose));
+        assert_eq!(read_frame(&loose).await.unwrap(), b"loose");
+
+        let pack_path = dir.path().join("session_2.pack");
+        let packed = write_pack(&pack_path, std::slice::from_ref(&loose)).unwrap();
+        let packed = packed[0].clone().unwrap();
+        assert_eq!(read_frame(&packed).await.unwrap(), b"loose");
+
+        let out = tempfile::tempdir().unwrap();
+        let files = unpack_to_dir(&[packed, loose.clone()], out.path()).unwrap();
+        assert_eq!(std::fs::read(&files[0]).unwrap(), b"loose");
+        assert_eq!(files[1], loose);
+    }
+}
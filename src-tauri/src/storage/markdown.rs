@@ -0,0 +1,225 @@
+// 会话 Markdown 导出 - 便于粘贴进工作日志
+//
+// 输出只取决于输入内容（不含导出时间等易变信息），同一会话多次导出结果一致，便于版本管理
+
+use super::database::Database;
+use crate::llm::plugin::{ActivityTag, SessionSummary};
+use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+
+/// Markdown 渲染选项
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownOptions {
+    /// 会话封面（JPEG 数据），以 data URL 内嵌在标题下方
+    pub cover_jpeg: Option<Vec<u8>>,
+}
+
+/// 单行内容（标题、列表项）中的换行替换为空格
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 将会话总结渲染为 Markdown
+pub fn render_markdown(summary: &SessionSummary) -> String {
+    render_markdown_with(summary, &MarkdownOptions::default())
+}
+
+/// 按选项将会话总结渲染为 Markdown
+pub fn render_markdown_with(summary: &SessionSummary, options: &MarkdownOptions) -> String {
+    let mut sections = vec![format!("# {}", single_line(&summary.title))];
+
+    let end_format = if summary.start_time.date_naive() == summary.end_time.date_naive() {
+        "%H:%M"
+    } else {
+        "%Y-%m-%d %H:%M"
+    };
+    sections.push(format!(
+        "{} – {}",
+        summary.start_time.format("%Y-%m-%d %H:%M"),
+        summary.end_time.format(end_format)
+    ));
+
+    if let Some(cover) = &options.cover_jpeg {
+        sections.push(format!(
+            "![封面](data:image/jpeg;base64,{})",
+            general_purpose::STANDARD.encode(cover)
+        ));
+    }
+
+    let body = summary.summary.trim();
+    if !body.is_empty() {
+        sections.push(body.to_string());
+    }
+
+    if !summary.tags.is_empty() {
+        let items: Vec<String> = summary.tags.iter().map(tag_line).collect();
+        sections.push(format!("## 标签\n\n{}", items.join("\n")));
+    }
+
+    if !summary.key_moments.is_empty() {
+        let items: Vec<String> = summary
+            .key_moments
+            .iter()
+            .map(|moment| {
+                format!(
+                    "- `{}` {}（重要性 {}/5）",
+                    single_line(&moment.time),
+                    single_line(&moment.description),
+                    moment.importance
+                )
+            })
+            .collect();
+        sections.push(format!("## 关键时刻\n\n{}", items.join("\n")));
+    }
+
+    let scores: Vec<String> = [
+        ("生产力", summary.productivity_score),
+        ("专注度", summary.focus_score),
+    ]
+    .into_iter()
+    .filter_map(|(name, score)| score.map(|s| format!("| {} | {:.0} |", name, s)))
+    .collect();
+    if !scores.is_empty() {
+        sections.push(format!(
+            "## 评分\n\n| 指标 | 分数 |\n| --- | --- |\n{}",
+            scores.join("\n")
+        ));
+    }
+
+    let mut markdown = sections.join("\n\n");
+    markdown.push('\n');
+    markdown
+}
+
+fn tag_line(tag: &ActivityTag) -> String {
+    let keywords: Vec<String> = tag
+        .keywords
+        .iter()
+        .map(|k| single_line(k))
+        .filter(|k| !k.is_empty())
+        .collect();
+    let mut line = format!(
+        "- {}（{:.0}%）",
+        tag.category.to_chinese(),
+        tag.confidence * 100.0
+    );
+    if !keywords.is_empty() {
+        line.push_str(&format!("：{}", keywords.join(", ")));
+    }
+    line
+}
+
+/// 将数据库中的会话渲染为 Markdown；include_cover 时内嵌已生成的会话封面
+pub async fn render_session_markdown(
+    db: &Database,
+    session_id: i64,
+    include_cover: bool,
+) -> Result<String> {
+    let detail = db.get_session_detail(session_id).await?;
+    let summary = SessionSummary {
+        title: detail.session.title,
+        summary: detail.session.summary,
+        tags: detail.tags,
+        start_time: detail.session.start_time,
+        end_time: detail.session.end_time,
+        ..Default::default()
+    };
+    let cover_jpeg = if include_cover {
+        db.get_session_thumbnail(session_id)
+            .await?
+            .map(|record| record.image_data)
+    } else {
+        None
+    };
+    Ok(render_markdown_with(
+        &summary,
+        &MarkdownOptions { cover_jpeg },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm::plugin::{ActivityCategory, KeyMoment};
+
+    fn summary() -> SessionSummary {
+        SessionSummary {
+            title: "实现会话导出\n功能".to_string(),
+            summary: "编写 Markdown 渲染并补充测试。".to_string(),
+            tags: vec![
+                ActivityTag {
+                    category: ActivityCategory::Work,
+                    confidence: 0.9,
+                    keywords: vec!["rust".to_string(), "markdown".to_string()],
+                    subcategories: Vec::new(),
+                    time_ranges: Vec::new(),
+                },
+                ActivityTag {
+                    category: ActivityCategory::Communication,
+                    confidence: 0.25,
+                    keywords: vec![],
+                    subcategories: Vec::new(),
+                    time_ranges: Vec::new(),
+                },
+            ],
+            start_time: "2025-01-01T09:00:00Z".parse().unwrap(),
+            end_time: "2025-01-01T09:45:00Z".parse().unwrap(),
+            key_moments: vec![KeyMoment {
+                time: "12:30".to_string(),
+                description: "测试通过".to_string(),
+                importance: 4,
+            }],
+            productivity_score: Some(82.4),
+            focus_score: None,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_markdown_layout() {
+        let expected = "\
+# 实现会话导出 功能
+
+2025-01-01 09:00 – 09:45
+
+编写 Markdown 渲染并补充测试。
+
+## 标签
+
+- 工作（90%）：rust, markdown
+- 沟通（25%）
+
+## 关键时刻
+
+- `12:30` 测试通过（重要性 4/5）
+
+## 评分
+
+| 指标 | 分数 |
+| --- | --- |
+| 生产力 | 82 |
+";
+        assert_eq!(render_markdown(&summary()), expected);
+        assert_eq!(render_markdown(&summary()), render_markdown(&summary()));
+    }
+
+    #[test]
+    fn test_render_markdown_embeds_cover_and_skips_empty_sections() {
+        let options = MarkdownOptions {
+            cover_jpeg: Some(vec![0xff, 0xd8, 0xff]),
+        };
+        let markdown = render_markdown_with(&summary(), &options);
+        assert!(markdown.contains("\n\n![封面](data:image/jpeg;base64,/9j/)\n\n编写"));
+
+        let bare = SessionSummary {
+            title: "空闲".to_string(),
+            start_time: "2025-01-01T23:30:00Z".parse().unwrap(),
+            end_time: "2025-01-02T00:15:00Z".parse().unwrap(),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_markdown(&bare),
+            "# 空闲\n\n2025-01-01 23:30 – 2025-01-02 00:15\n"
+        );
+    }
+}
@@ -1,12 +1,19 @@
 // 存储模块 - 统一的数据库抽象层
 
 // 子模块
+pub mod analysis_queue;
 pub mod cache;
 pub mod cleaner;
 pub mod config;
 pub mod database;
+pub mod day_grouping;
+pub mod dead_letter;
+pub mod deep_work;
+pub mod frame_pack;
+pub mod markdown;
 pub mod models;
 pub mod repository;
+pub mod transfer;
 
 // 重新导出主要类型
 pub use cache::CachedRepository;
@@ -18,4 +25,5 @@ pub use repository::DatabaseRepository;
 
 // 重新导出具体实现（可选，用于高级用法）
 pub use repository::mariadb::MariaDbRepository;
+pub use repository::postgres::PostgresRepository;
 pub use repository::sqlite::SqliteRepository;
@@ -25,6 +25,10 @@ pub struct Session {
     pub created_at: Option<DateTime<Utc>>,
     pub device_name: Option<String>, // 设备名称
     pub device_type: Option<String>, // 设备类型(desktop, laptop, tablet等)
+    /// 置顶（收藏）的会话不会被过期清理删除
+    #[serde(default)]
+    #[sqlx(default)]
+    pub pinned: bool,
 }
 
 /// 帧数据结构
@@ -128,6 +132,196 @@ pub struct DaySummaryRecord {
     pub updated_at: DateTime<Utc>, // 更新时间
 }
 
+/// 会话分析记录：生成当前总结时实际使用的分析参数（重新分析时覆盖），用于复现分析结果
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SessionAnalysisRecord {
+    pub session_id: i64,
+    pub served_by: Option<String>, // 实际生成总结的 provider（故障转移时为后备 provider）
+    pub sampling_seed: Option<i64>, // 随机采样种子（u64 按位存为 i64），未开启随机采样时为空
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// 帧清理记录（分析成功后删除原始帧时写入）
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FramePruneRecord {
+    pub session_id: i64,
+    pub deleted_count: i64, // 已删除的帧数
+    pub kept_count: i64,    // 保留的关键时刻帧数
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub pruned_at: DateTime<Utc>, // 清理时间
+}
+
+/// 会话封面缩略图（分析完成后由代表帧生成）
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SessionThumbnailRecord {
+    pub session_id: i64,
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub frame_timestamp: DateTime<Utc>, // 代表帧的截图时间
+    pub width: i64,
+    pub height: i64,
+    #[serde(skip_serializing)]
+    pub image_data: Vec<u8>, // JPEG 数据
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// 会话合并记录：保存合并前的原始会话，合并后仍可追溯
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SessionMergeRecord {
+    pub id: Option<i64>,
+    pub merged_session_id: i64,   // 合并后的会话
+    pub original_session_id: i64, // 原始会话 ID（已删除）
+    pub original_session: String, // 原始会话的 JSON
+    pub frame_count: i64,         // 原始会话的帧数
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub merged_at: DateTime<Utc>,
+}
+
+/// 截屏缺口记录：看门狗检测到截屏卡死期间没有截图的时段
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CaptureGapRecord {
+    pub id: Option<i64>,
+    pub session_id: i64,
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub gap_start: DateTime<Utc>, // 卡死前最后一帧
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub gap_end: DateTime<Utc>, // 恢复后第一帧
+    pub restart_attempts: i64, // 恢复前重启截屏任务的次数
+}
+
+/// 分析所用帧记录：模型实际看到的帧（开启记录采样帧时写入，重新分析时覆盖）
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct FrameSelectionRecord {
+    pub session_id: i64,
+    pub total_frames: i64, // 输入帧总数
+    pub frames: String,    // 所选帧的 JSON 数组（index 为在输入帧中的序号，path 为文件路径）
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// 会话总结的自定义字段（按配置由模型输出，重新分析时覆盖）
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SessionExtraFieldsRecord {
+    pub session_id: i64,
+    pub fields: String, // 字段名 -> 值的 JSON 对象
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// 会话的分析模式（手动选择或分析时使用的模式，重新分析时沿用）
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SessionAnalysisModeRecord {
+    pub session_id: i64,
+    pub mode: String, // 模式名（如 work、study）
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 会话总结的评分（用于按评分筛选会话，重新分析时覆盖）
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SessionScoresRecord {
+    pub session_id: i64,
+    pub productivity_score: Option<f64>, // 生产力评分（0-100），模型未给出时为空
+    pub focus_score: Option<f64>,        // 专注度评分（0-100）
+    pub confidence: Option<f64>,         // 整体置信度（0-1，按标签置信度汇总），没有标签时为空
+    pub low_confidence: bool,            // 整体置信度低于阈值，待人工复核
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 分析失败记录：失败次数达到上限后移入死信队列（dead_lettered_at 非空），不再自动重试
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AnalysisFailureRecord {
+    pub session_id: i64,
+    pub last_error: String, // 最近一次失败的错误信息
+    pub attempts: i64,      // 连续失败次数（重新入队时清零）
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub first_failed_at: DateTime<Utc>,
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub last_failed_at: DateTime<Utc>,
+    #[serde(serialize_with = "serialize_datetime_as_local_option")]
+    pub dead_lettered_at: Option<DateTime<Utc>>,
+}
+
+/// 分析队列条目：等待分析与进行中的会话，持久化后崩溃重启也能恢复
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AnalysisQueueRecord {
+    pub session_key: i64, // 调度器分配的会话键（时间桶起点毫秒）
+    pub frame_count: i64,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    pub priority: String,             // 分析优先级（background / interactive）
+    pub attempts: i64,                // 已开始分析的次数
+    pub in_flight: bool,              // 是否正在分析；启动时仍为 true 说明上次分析被中断
+    pub temp_session_id: Option<i64>, // 分析过程中创建的临时会话
+    pub enqueued_at: DateTime<Utc>,
+}
+
+/// 深度工作时段：长时间专注于单个应用的时段（由会话活动派生，重新分析时覆盖）
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct DeepWorkRecord {
+    pub id: Option<i64>,
+    pub session_id: i64,
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub start_time: DateTime<Utc>,
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub end_time: DateTime<Utc>,
+    pub app: String,        // 专注使用的应用
+    pub duration_secs: i64, // 时长（含跨过的短暂打断）
+    pub focus_score: f64,   // 平均专注度（0-100）
+    pub interruptions: i64, // 跨过的短暂打断次数
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// 会话筛选条件：未设置的条件不限制；同一条件的多个取值之间为“或”，不同条件之间为“且”
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionFilter {
+    pub start_date: Option<String>, // 开始日期（含，YYYY-MM-DD），按会话开始时间
+    pub end_date: Option<String>,   // 结束日期（含）
+    pub categories: Vec<String>,    // 任一标签属于其中一个类别
+    pub keywords: Vec<String>,      // 任一标签含其中一个关键词（不区分大小写）
+    pub min_productivity: Option<f64>,
+    pub max_productivity: Option<f64>,
+    pub min_focus: Option<f64>,
+    pub max_focus: Option<f64>, // 设置了评分条件时，没有评分记录的会话不会出现在结果中
+    pub low_confidence: Option<bool>, // true 只看低置信度（待复核）的会话，false 排除它们
+    pub offset: i64,
+    pub limit: Option<i64>, // 每页条数，默认 50，最多 500
+}
+
+/// 分页的会话查询结果（按开始时间倒序）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionPage {
+    pub sessions: Vec<Session>,
+    pub total: i64, // 符合条件的会话总数
+    pub offset: i64,
+    pub limit: i64,
+}
+
+/// 会话总结的人工更正：作为后续分析的少样本示例（每个会话一条，再次更正时覆盖；会话清理后仍保留）
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SummaryCorrectionRecord {
+    pub session_id: i64,
+    pub description: String, // 模型最初给出的描述（标题、摘要与关键词）
+    pub corrected: String,   // 更正后的总结 JSON（title、summary、tags）
+    pub embedding: String,   // 原始描述的向量（JSON 数组）
+    #[serde(serialize_with = "serialize_datetime_as_local")]
+    pub created_at: DateTime<Utc>,
+}
+
+/// 彻底删除会话的清单：各表删除的行数与删除的文件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionPurgeManifest {
+    pub session_id: i64,
+    pub rows: std::collections::BTreeMap<String, u64>, // 表名 -> 删除的行数（只含非零的表）
+    pub files_removed: Vec<String>,                    // 已删除的帧文件、帧归档与视频文件
+    pub files_failed: Vec<String>,                     // 删除失败的文件（数据库记录已删除）
+    pub overwritten: bool,                             // 文件删除前已用零覆写
+}
+
 // 自定义序列化：NaiveDate -> String (YYYY-MM-DD)
 fn serialize_naive_date<S>(date: &chrono::NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
 where
@@ -1,13 +1,16 @@
 // MariaDB 数据库实现
 
-use super::DatabaseRepository;
+use super::schema::{self, Dialect, SESSION_SCOPED_TABLES};
+use super::{DatabaseRepository, PreparedFilter};
 use crate::storage::config::get_device_info;
 use crate::storage::models::*;
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use sqlx::mysql::{MySqlPool, MySqlPoolOptions};
+use sqlx::mysql::{MySql, MySqlPool, MySqlPoolOptions};
+use sqlx::QueryBuilder;
 use sqlx::Row;
+use std::collections::BTreeMap;
 use tracing::info;
 
 /// MariaDB 数据库实现
@@ -118,16 +121,7 @@ impl MariaDbRepository {
 
     /// 检查所有必要的表是否存在
     async fn check_tables_exist(&self) -> Result<bool> {
-        let tables = vec![
-            "sessions",
-            "frames",
-            "llm_calls",
-            "video_segments",
-            "timeline_cards",
-            "day_summaries",
-        ];
-
-        for table in tables {
+        for table in schema::table_names() {
             let count: i64 = sqlx::query_scalar(
                 "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = DATABASE() AND table_name = ?",
             )
@@ -149,6 +143,68 @@ impl MariaDbRepository {
     }
 }
 
+/// 按条件查询会话时选取的列（与 Session 的字段对应）
+const SESSION_COLUMNS: &str = "SELECT sessions.id, sessions.start_time, sessions.end_time, \
+    sessions.title, sessions.summary, sessions.video_path, sessions.tags, sessions.created_at, \
+    sessions.device_name, sessions.device_type, sessions.pinned";
+
+/// 追加筛选条件对应的 FROM 与 WHERE 子句（计数与分页查询共用）
+fn push_session_filter(builder: &mut QueryBuilder<'_, MySql>, filter: &PreparedFilter) {
+    builder.push(
+        " FROM sessions LEFT JOIN session_scores ON session_scores.session_id = sessions.id \
+         WHERE 1 = 1",
+    );
+    if let Some(start) = filter.start {
+        builder
+            .push(" AND sessions.start_time >= ")
+            .push_bind(start);
+    }
+    if let Some(end) = filter.end {
+        builder.push(" AND sessions.start_time < ").push_bind(end);
+    }
+    // JSON_SEARCH 的 % 与 _ 为通配符，按字面匹配时需转义
+    if !filter.categories.is_empty() {
+        builder.push(" AND (");
+        let mut values = builder.separated(" OR ");
+        for category in &filter.categories {
+            values
+                .push("JSON_SEARCH(sessions.tags, 'one', ")
+                .push_bind_unseparated(escape_json_search(category))
+                .push_unseparated(", NULL, '$[*].category') IS NOT NULL");
+        }
+        builder.push(")");
+    }
+    if !filter.keywords.is_empty() {
+        builder.push(" AND (");
+        let mut values = builder.separated(" OR ");
+        for keyword in &filter.keywords {
+            values
+                .push("JSON_SEARCH(LOWER(sessions.tags), 'one', ")
+                .push_bind_unseparated(escape_json_search(keyword))
+                .push_unseparated(", NULL, '$[*].keywords[*]') IS NOT NULL");
+        }
+        builder.push(")");
+    }
+    for (column, op, value) in &filter.score_bounds {
+        builder
+            .push(format!(" AND session_scores.{} {} ", column, op))
+            .push_bind(*value);
+    }
+    if let Some(low_confidence) = filter.low_confidence {
+        builder
+            .push(" AND COALESCE(session_scores.low_confidence, FALSE) = ")
+            .push_bind(low_confidence);
+    }
+}
+
+/// 转义 JSON_SEARCH 模式中的通配符
+fn escape_json_search(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
 #[async_trait]
 impl DatabaseRepository for MariaDbRepository {
     // ========== 会话操作 ==========
@@ -156,8 +212,8 @@ impl DatabaseRepository for MariaDbRepository {
     async fn insert_session(&self, session: &Session) -> Result<i64> {
         let result = sqlx::query(
             r#"
-            INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type, pinned)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
         )
         .bind(&session.start_time)
@@ -168,6 +224,7 @@ impl DatabaseRepository for MariaDbRepository {
         .bind(&session.tags)
         .bind(&session.device_name)
         .bind(&session.device_type)
+        .bind(session.pinned)
         .execute(&self.pool)
         .await?;
 
@@ -181,8 +238,8 @@ impl DatabaseRepository for MariaDbRepository {
         for session in sessions {
             let result = sqlx::query(
                 r#"
-                INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type)
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type, pinned)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
             )
             .bind(&session.start_time)
@@ -193,6 +250,7 @@ impl DatabaseRepository for MariaDbRepository {
             .bind(&session.tags)
             .bind(&session.device_name)
             .bind(&session.device_type)
+            .bind(session.pinned)
             .execute(&mut *tx)
             .await?;
 
@@ -207,7 +265,7 @@ impl DatabaseRepository for MariaDbRepository {
         let session = sqlx::query_as::<_, Session>(
             r#"
             SELECT id, start_time, end_time, title, summary,
-                   video_path, tags, created_at, device_name, device_type
+                   video_path, tags, created_at, device_name, device_type, pinned
             FROM sessions
             WHERE id = ?
             "#,
@@ -239,7 +297,7 @@ impl DatabaseRepository for MariaDbRepository {
         let sessions = sqlx::query_as::<_, Session>(
             r#"
             SELECT id, start_time, end_time, title, summary,
-                   video_path, tags, created_at, device_name, device_type
+                   video_path, tags, created_at, device_name, device_type, pinned
             FROM sessions
             WHERE start_time >= ? AND start_time <= ?
             ORDER BY start_time DESC
@@ -253,11 +311,33 @@ impl DatabaseRepository for MariaDbRepository {
         Ok(sessions)
     }
 
+    async fn get_sessions_overlapping(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT id, start_time, end_time, title, summary,
+                   video_path, tags, created_at, device_name, device_type, pinned
+            FROM sessions
+            WHERE start_time < ? AND end_time >= ?
+            ORDER BY start_time
+            "#,
+        )
+        .bind(end)
+        .bind(start)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
     async fn get_all_sessions(&self) -> Result<Vec<Session>> {
         let sessions = sqlx::query_as::<_, Session>(
             r#"
             SELECT id, start_time, end_time, title, summary,
-                   video_path, tags, created_at, device_name, device_type
+                   video_path, tags, created_at, device_name, device_type, pinned
             FROM sessions
             ORDER BY start_time
             "#,
@@ -343,11 +423,46 @@ impl DatabaseRepository for MariaDbRepository {
         Ok(())
     }
 
+    async fn purge_session(&self, session_id: i64) -> Result<BTreeMap<String, u64>> {
+        let mut tx = self.pool.begin().await?;
+        let mut removed = BTreeMap::new();
+        for (table, column) in SESSION_SCOPED_TABLES {
+            let sql = format!("DELETE FROM {} WHERE {} = ?", table, column);
+            let result = sqlx::query(&sql).bind(session_id).execute(&mut *tx).await?;
+            if result.rows_affected() > 0 {
+                *removed.entry(table.to_string()).or_default() += result.rows_affected();
+            }
+        }
+        // 未提交的事务在返回时回滚
+        if !removed.contains_key("sessions") {
+            return Err(anyhow::anyhow!("会话不存在: {}", session_id));
+        }
+        tx.commit().await?;
+
+        info!("彻底删除会话 {}: {:?}", session_id, removed);
+        Ok(removed)
+    }
+
+    async fn count_session_references(&self, session_id: i64) -> Result<BTreeMap<String, u64>> {
+        let mut counts = BTreeMap::new();
+        for (table, column) in SESSION_SCOPED_TABLES {
+            let sql = format!("SELECT COUNT(*) FROM {} WHERE {} = ?", table, column);
+            let count: i64 = sqlx::query_scalar(&sql)
+                .bind(session_id)
+                .fetch_one(&self.pool)
+                .await?;
+            if count > 0 {
+                *counts.entry(table.to_string()).or_default() += count as u64;
+            }
+        }
+        Ok(counts)
+    }
+
     async fn get_old_sessions(&self, cutoff_date: DateTime<Utc>) -> Result<Vec<Session>> {
         let sessions = sqlx::query_as::<_, Session>(
-            "SELECT id, start_time, end_time, title, summary, video_path, tags, created_at, device_name, device_type
+            "SELECT id, start_time, end_time, title, summary, video_path, tags, created_at, device_name, device_type, pinned
              FROM sessions
-             WHERE start_time < ?"
+             WHERE start_time < ? AND pinned = FALSE"
         )
         .bind(cutoff_date)
         .fetch_all(&self.pool)
@@ -357,7 +472,7 @@ impl DatabaseRepository for MariaDbRepository {
     }
 
     async fn delete_old_sessions(&self, cutoff_date: DateTime<Utc>) -> Result<u64> {
-        let result = sqlx::query("DELETE FROM sessions WHERE start_time < ?")
+        let result = sqlx::query("DELETE FROM sessions WHERE start_time < ? AND pinned = FALSE")
             .bind(cutoff_date)
             .execute(&self.pool)
             .await?;
@@ -371,6 +486,62 @@ impl DatabaseRepository for MariaDbRepository {
         Ok(deleted_count)
     }
 
+    async fn set_session_pinned(&self, session_id: i64, pinned: bool) -> Result<()> {
+        let result = sqlx::query("UPDATE sessions SET pinned = ? WHERE id = ?")
+            .bind(pinned)
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("会话不存在: {}", session_id));
+        }
+
+        info!("会话 {} 置顶状态: {}", session_id, pinned);
+        Ok(())
+    }
+
+    async fn list_pinned_sessions(&self) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT id, start_time, end_time, title, summary,
+                   video_path, tags, created_at, device_name, device_type, pinned
+            FROM sessions
+            WHERE pinned = TRUE
+            ORDER BY start_time DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    async fn query_sessions(&self, filter: &SessionFilter) -> Result<SessionPage> {
+        let prepared = PreparedFilter::new(filter)?;
+
+        let mut count = QueryBuilder::<MySql>::new("SELECT COUNT(*)");
+        push_session_filter(&mut count, &prepared);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut page = QueryBuilder::<MySql>::new(SESSION_COLUMNS);
+        push_session_filter(&mut page, &prepared);
+        page.push(" ORDER BY sessions.start_time DESC, sessions.id DESC LIMIT ")
+            .push_bind(prepared.limit)
+            .push(" OFFSET ")
+            .push_bind(prepared.offset);
+        let sessions = page
+            .build_query_as::<Session>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(SessionPage {
+            sessions,
+            total,
+            offset: prepared.offset,
+            limit: prepared.limit,
+        })
+    }
+
     // ========== 帧操作 ==========
 
     async fn insert_frame(&self, frame: &Frame) -> Result<i64> {
@@ -867,222 +1038,676 @@ impl DatabaseRepository for MariaDbRepository {
     // ========== 数据库初始化 ==========
 
     async fn initialize_tables(&self) -> Result<()> {
-        // 创建会话表
+        for statement in schema::create_table_statements(Dialect::MariaDb) {
+            sqlx::query(&statement).execute(&self.pool).await?;
+        }
+        // 数据库迁移: 为已存在的表补齐新增列
+        for (table, column, statement) in schema::add_column_statements(Dialect::MariaDb) {
+            let exists: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM information_schema.columns
+                 WHERE table_schema = DATABASE() AND table_name = ? AND column_name = ?",
+            )
+            .bind(table)
+            .bind(column)
+            .fetch_one(&self.pool)
+            .await?;
+            if exists == 0 {
+                info!("迁移数据库: 为 {} 添加 {} 字段", table, column);
+                sqlx::query(&statement).execute(&self.pool).await?;
+            }
+        }
+        // MySQL 不支持 CREATE INDEX IF NOT EXISTS，需要忽略已存在错误
+        for statement in schema::create_index_statements(Dialect::MariaDb) {
+            let _ = sqlx::query(&statement).execute(&self.pool).await;
+        }
+
+        info!("MariaDB 数据库表初始化完成");
+        Ok(())
+    }
+
+    async fn save_day_summary(&self, date: &str, summary: &DaySummaryRecord) -> Result<()> {
+        // 使用 REPLACE INTO 实现 upsert (MariaDB/MySQL 语法)
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS sessions (
-                id BIGINT PRIMARY KEY AUTO_INCREMENT,
-                start_time DATETIME NOT NULL,
-                end_time DATETIME NOT NULL,
-                title TEXT NOT NULL,
-                summary TEXT NOT NULL,
-                video_path TEXT,
-                tags TEXT NOT NULL,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                device_name VARCHAR(255),
-                device_type VARCHAR(50)
-            )
-        "#,
+            REPLACE INTO day_summaries (
+                date, summary_text, device_stats, parallel_work, usage_patterns,
+                active_device_count, llm_call_id, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, NOW())
+            "#,
         )
+        .bind(date)
+        .bind(&summary.summary_text)
+        .bind(&summary.device_stats)
+        .bind(&summary.parallel_work)
+        .bind(&summary.usage_patterns)
+        .bind(summary.active_device_count)
+        .bind(summary.llm_call_id)
         .execute(&self.pool)
         .await?;
 
-        // 创建帧表
+        Ok(())
+    }
+
+    async fn get_day_summary(&self, date: &str) -> Result<Option<DaySummaryRecord>> {
+        let result = sqlx::query_as::<_, DaySummaryRecord>(
+            r#"
+            SELECT * FROM day_summaries WHERE date = ?
+            "#,
+        )
+        .bind(date)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn delete_day_summary(&self, date: &str) -> Result<()> {
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS frames (
-                id BIGINT PRIMARY KEY AUTO_INCREMENT,
-                session_id BIGINT NOT NULL,
-                timestamp DATETIME NOT NULL,
-                file_path TEXT NOT NULL,
-                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
-            )
-        "#,
+            DELETE FROM day_summaries WHERE date = ?
+            "#,
         )
+        .bind(date)
         .execute(&self.pool)
         .await?;
 
-        // 创建索引（MySQL 不支持 CREATE INDEX IF NOT EXISTS，需要忽略已存在错误）
-        let _ = sqlx::query("CREATE INDEX idx_sessions_start_time ON sessions(start_time)")
-            .execute(&self.pool)
-            .await;
-        let _ = sqlx::query("CREATE INDEX idx_frames_session_id ON frames(session_id)")
-            .execute(&self.pool)
-            .await;
-        let _ =
-            sqlx::query("CREATE INDEX idx_sessions_start_end ON sessions(start_time, end_time)")
-                .execute(&self.pool)
-                .await;
-        let _ = sqlx::query(
-            "CREATE INDEX idx_frames_session_timestamp ON frames(session_id, timestamp)",
+        Ok(())
+    }
+
+    async fn save_session_analysis(&self, record: &SessionAnalysisRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            REPLACE INTO session_analyses (session_id, served_by, sampling_seed, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
         )
+        .bind(record.session_id)
+        .bind(&record.served_by)
+        .bind(record.sampling_seed)
+        .bind(record.created_at)
         .execute(&self.pool)
-        .await;
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_session_analysis(&self, session_id: i64) -> Result<Option<SessionAnalysisRecord>> {
+        let result = sqlx::query_as::<_, SessionAnalysisRecord>(
+            r#"
+            SELECT * FROM session_analyses WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_frame_prune(
+        &self,
+        record: &FramePruneRecord,
+        kept_frame_ids: &[i64],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // 删除未保留的帧记录（保留列表为空时删除该会话的全部帧）
+        let sql = if kept_frame_ids.is_empty() {
+            "DELETE FROM frames WHERE session_id = ?".to_string()
+        } else {
+            let placeholders = vec!["?"; kept_frame_ids.len()].join(", ");
+            format!(
+                "DELETE FROM frames WHERE session_id = ? AND id NOT IN ({})",
+                placeholders
+            )
+        };
+        let mut query = sqlx::query(&sql).bind(record.session_id);
+        for id in kept_frame_ids {
+            query = query.bind(id);
+        }
+        query.execute(&mut *tx).await?;
+
+        sqlx::query(
+            r#"
+            REPLACE INTO frame_prunes (session_id, deleted_count, kept_count, pruned_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(record.deleted_count)
+        .bind(record.kept_count)
+        .bind(record.pruned_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_frame_prune(&self, session_id: i64) -> Result<Option<FramePruneRecord>> {
+        let result = sqlx::query_as::<_, FramePruneRecord>(
+            r#"
+            SELECT * FROM frame_prunes WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
 
-        // 创建LLM调用记录表
+    async fn save_session_thumbnail(&self, record: &SessionThumbnailRecord) -> Result<()> {
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS llm_calls (
-                id BIGINT PRIMARY KEY AUTO_INCREMENT,
-                session_id BIGINT,
-                provider VARCHAR(100) NOT NULL,
-                model VARCHAR(100) NOT NULL,
-                call_type VARCHAR(100) NOT NULL,
-                request_headers TEXT NOT NULL,
-                request_body TEXT NOT NULL,
-                response_headers TEXT,
-                response_body TEXT,
-                status_code INT,
-                error_message TEXT,
-                latency_ms BIGINT,
-                token_usage TEXT,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            REPLACE INTO session_thumbnails (session_id, frame_timestamp, width, height, image_data, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(record.frame_timestamp)
+        .bind(record.width)
+        .bind(record.height)
+        .bind(&record.image_data)
+        .bind(record.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_session_thumbnail(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionThumbnailRecord>> {
+        let result = sqlx::query_as::<_, SessionThumbnailRecord>(
+            r#"
+            SELECT * FROM session_thumbnails WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn merge_sessions(
+        &self,
+        merged: &Session,
+        frames: &[Frame],
+        originals: &[SessionMergeRecord],
+    ) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type, pinned)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(merged.start_time)
+        .bind(merged.end_time)
+        .bind(&merged.title)
+        .bind(&merged.summary)
+        .bind(&merged.video_path)
+        .bind(&merged.tags)
+        .bind(&merged.device_name)
+        .bind(&merged.device_type)
+        .bind(merged.pinned)
+        .execute(&mut *tx)
+        .await?;
+        let merged_id = result.last_insert_id() as i64;
+
+        for frame in frames {
+            sqlx::query(
+                r#"
+                INSERT INTO frames (session_id, timestamp, file_path)
+                VALUES (?, ?, ?)
+                "#,
             )
-        "#,
+            .bind(merged_id)
+            .bind(&frame.timestamp)
+            .bind(&frame.file_path)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for original in originals {
+            sqlx::query(
+                "UPDATE session_merges SET merged_session_id = ? WHERE merged_session_id = ?",
+            )
+            .bind(merged_id)
+            .bind(original.original_session_id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(
+                r#"
+                INSERT INTO session_merges (merged_session_id, original_session_id, original_session, frame_count, merged_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(merged_id)
+            .bind(original.original_session_id)
+            .bind(&original.original_session)
+            .bind(original.frame_count)
+            .bind(original.merged_at)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("UPDATE capture_gaps SET session_id = ? WHERE session_id = ?")
+                .bind(merged_id)
+                .bind(original.original_session_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE deep_work_stretches SET session_id = ? WHERE session_id = ?")
+                .bind(merged_id)
+                .bind(original.original_session_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM sessions WHERE id = ?")
+                .bind(original.original_session_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        info!(
+            "已合并 {} 个会话为会话 {}（{} 帧）",
+            originals.len(),
+            merged_id,
+            frames.len()
+        );
+        Ok(merged_id)
+    }
+
+    async fn get_session_merges(&self, merged_session_id: i64) -> Result<Vec<SessionMergeRecord>> {
+        let records = sqlx::query_as::<_, SessionMergeRecord>(
+            r#"
+            SELECT * FROM session_merges WHERE merged_session_id = ? ORDER BY id
+            "#,
+        )
+        .bind(merged_session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn save_capture_gaps(&self, gaps: &[CaptureGapRecord]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for gap in gaps {
+            sqlx::query(
+                r#"
+                INSERT INTO capture_gaps (session_id, gap_start, gap_end, restart_attempts)
+                VALUES (?, ?, ?, ?)
+                "#,
+            )
+            .bind(gap.session_id)
+            .bind(gap.gap_start)
+            .bind(gap.gap_end)
+            .bind(gap.restart_attempts)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_capture_gaps(&self, session_id: i64) -> Result<Vec<CaptureGapRecord>> {
+        let records = sqlx::query_as::<_, CaptureGapRecord>(
+            r#"
+            SELECT * FROM capture_gaps WHERE session_id = ? ORDER BY gap_start
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn save_frame_selection(&self, record: &FrameSelectionRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            REPLACE INTO frame_selections (session_id, total_frames, frames, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
         )
+        .bind(record.session_id)
+        .bind(record.total_frames)
+        .bind(&record.frames)
+        .bind(record.created_at)
         .execute(&self.pool)
         .await?;
 
-        // 创建视频分段表
+        Ok(())
+    }
+
+    async fn get_frame_selection(&self, session_id: i64) -> Result<Option<FrameSelectionRecord>> {
+        let result = sqlx::query_as::<_, FrameSelectionRecord>(
+            r#"
+            SELECT * FROM frame_selections WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_session_extra_fields(&self, record: &SessionExtraFieldsRecord) -> Result<()> {
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS video_segments (
-                id BIGINT PRIMARY KEY AUTO_INCREMENT,
-                session_id BIGINT NOT NULL,
-                llm_call_id BIGINT,
-                start_timestamp VARCHAR(50) NOT NULL,
-                end_timestamp VARCHAR(50) NOT NULL,
-                description TEXT NOT NULL,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
-                FOREIGN KEY (llm_call_id) REFERENCES llm_calls(id) ON DELETE SET NULL
-            )
-        "#,
+            REPLACE INTO session_extra_fields (session_id, fields, created_at)
+            VALUES (?, ?, ?)
+            "#,
         )
+        .bind(record.session_id)
+        .bind(&record.fields)
+        .bind(record.created_at)
         .execute(&self.pool)
         .await?;
 
-        // 创建时间线卡片表
+        Ok(())
+    }
+
+    async fn get_session_extra_fields(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionExtraFieldsRecord>> {
+        let result = sqlx::query_as::<_, SessionExtraFieldsRecord>(
+            r#"
+            SELECT * FROM session_extra_fields WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_session_analysis_mode(&self, record: &SessionAnalysisModeRecord) -> Result<()> {
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS timeline_cards (
-                id BIGINT PRIMARY KEY AUTO_INCREMENT,
-                session_id BIGINT NOT NULL,
-                llm_call_id BIGINT,
-                start_time VARCHAR(50) NOT NULL,
-                end_time VARCHAR(50) NOT NULL,
-                category VARCHAR(100) NOT NULL,
-                subcategory VARCHAR(100) NOT NULL,
-                title TEXT NOT NULL,
-                summary TEXT NOT NULL,
-                detailed_summary TEXT NOT NULL,
-                distractions TEXT,
-                app_sites TEXT NOT NULL,
-                video_preview_path TEXT,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
-                FOREIGN KEY (llm_call_id) REFERENCES llm_calls(id) ON DELETE SET NULL
-            )
-        "#,
+            REPLACE INTO session_analysis_modes (session_id, mode, updated_at)
+            VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(&record.mode)
+        .bind(record.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_session_analysis_mode(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionAnalysisModeRecord>> {
+        let result = sqlx::query_as::<_, SessionAnalysisModeRecord>(
+            r#"
+            SELECT * FROM session_analysis_modes WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_session_scores(&self, record: &SessionScoresRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            REPLACE INTO session_scores (
+                session_id, productivity_score, focus_score, confidence, low_confidence, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?)
+            "#,
         )
+        .bind(record.session_id)
+        .bind(record.productivity_score)
+        .bind(record.focus_score)
+        .bind(record.confidence)
+        .bind(record.low_confidence)
+        .bind(record.updated_at)
         .execute(&self.pool)
         .await?;
 
-        // 创建每日总结表（缓存）
+        Ok(())
+    }
+
+    async fn save_analysis_failure(&self, record: &AnalysisFailureRecord) -> Result<()> {
         sqlx::query(
             r#"
-            CREATE TABLE IF NOT EXISTS day_summaries (
-                id BIGINT PRIMARY KEY AUTO_INCREMENT,
-                date DATE NOT NULL UNIQUE,
-                summary_text TEXT NOT NULL,
-                device_stats TEXT NOT NULL,
-                parallel_work TEXT NOT NULL,
-                usage_patterns TEXT NOT NULL,
-                active_device_count INT NOT NULL,
-                llm_call_id BIGINT,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
-                FOREIGN KEY (llm_call_id) REFERENCES llm_calls(id) ON DELETE SET NULL
-            )
-        "#,
+            REPLACE INTO analysis_failures (
+                session_id, last_error, attempts, first_failed_at, last_failed_at, dead_lettered_at
+            ) VALUES (?, ?, ?, ?, ?, ?)
+            "#,
         )
+        .bind(record.session_id)
+        .bind(&record.last_error)
+        .bind(record.attempts)
+        .bind(record.first_failed_at)
+        .bind(record.last_failed_at)
+        .bind(record.dead_lettered_at)
         .execute(&self.pool)
         .await?;
 
-        // 创建额外的索引（忽略已存在错误）
-        let _ = sqlx::query("CREATE INDEX idx_llm_calls_session_id ON llm_calls(session_id)")
-            .execute(&self.pool)
-            .await;
-        let _ = sqlx::query("CREATE INDEX idx_llm_calls_created_at ON llm_calls(created_at)")
+        Ok(())
+    }
+
+    async fn get_analysis_failure(&self, session_id: i64) -> Result<Option<AnalysisFailureRecord>> {
+        let result = sqlx::query_as::<_, AnalysisFailureRecord>(
+            r#"
+            SELECT * FROM analysis_failures WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn list_analysis_failures(&self) -> Result<Vec<AnalysisFailureRecord>> {
+        let records = sqlx::query_as::<_, AnalysisFailureRecord>(
+            r#"
+            SELECT * FROM analysis_failures ORDER BY last_failed_at DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn delete_analysis_failure(&self, session_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM analysis_failures WHERE session_id = ?")
+            .bind(session_id)
             .execute(&self.pool)
-            .await;
-        let _ =
-            sqlx::query("CREATE INDEX idx_video_segments_session_id ON video_segments(session_id)")
-                .execute(&self.pool)
-                .await;
-        let _ =
-            sqlx::query("CREATE INDEX idx_timeline_cards_session_id ON timeline_cards(session_id)")
-                .execute(&self.pool)
-                .await;
+            .await?;
 
-        info!("MariaDB 数据库表初始化完成");
         Ok(())
     }
 
-    async fn save_day_summary(&self, date: &str, summary: &DaySummaryRecord) -> Result<()> {
-        // 使用 REPLACE INTO 实现 upsert (MariaDB/MySQL 语法)
+    async fn save_analysis_queue_entry(&self, record: &AnalysisQueueRecord) -> Result<()> {
         sqlx::query(
             r#"
-            REPLACE INTO day_summaries (
-                date, summary_text, device_stats, parallel_work, usage_patterns,
-                active_device_count, llm_call_id, updated_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, NOW())
+            REPLACE INTO analysis_queue (
+                session_key, frame_count, window_start, window_end, priority, attempts, in_flight,
+                temp_session_id, enqueued_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
-        .bind(date)
-        .bind(&summary.summary_text)
-        .bind(&summary.device_stats)
-        .bind(&summary.parallel_work)
-        .bind(&summary.usage_patterns)
-        .bind(summary.active_device_count)
-        .bind(summary.llm_call_id)
+        .bind(record.session_key)
+        .bind(record.frame_count)
+        .bind(record.window_start)
+        .bind(record.window_end)
+        .bind(&record.priority)
+        .bind(record.attempts)
+        .bind(record.in_flight)
+        .bind(record.temp_session_id)
+        .bind(record.enqueued_at)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    async fn get_day_summary(&self, date: &str) -> Result<Option<DaySummaryRecord>> {
-        let result = sqlx::query_as::<_, DaySummaryRecord>(
+    async fn get_analysis_queue_entry(
+        &self,
+        session_key: i64,
+    ) -> Result<Option<AnalysisQueueRecord>> {
+        let result = sqlx::query_as::<_, AnalysisQueueRecord>(
             r#"
-            SELECT * FROM day_summaries WHERE date = ?
+            SELECT * FROM analysis_queue WHERE session_key = ?
             "#,
         )
-        .bind(date)
+        .bind(session_key)
         .fetch_optional(&self.pool)
         .await?;
 
         Ok(result)
     }
 
-    async fn delete_day_summary(&self, date: &str) -> Result<()> {
+    async fn list_analysis_queue(&self) -> Result<Vec<AnalysisQueueRecord>> {
+        let records = sqlx::query_as::<_, AnalysisQueueRecord>(
+            r#"
+            SELECT * FROM analysis_queue ORDER BY enqueued_at, session_key
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn delete_analysis_queue_entry(&self, session_key: i64) -> Result<()> {
+        sqlx::query("DELETE FROM analysis_queue WHERE session_key = ?")
+            .bind(session_key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn replace_deep_work_stretches(
+        &self,
+        session_id: i64,
+        records: &[DeepWorkRecord],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM deep_work_stretches WHERE session_id = ?")
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+        for record in records {
+            sqlx::query(
+                r#"
+                INSERT INTO deep_work_stretches (session_id, start_time, end_time, app, duration_secs, focus_score, interruptions, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(session_id)
+            .bind(record.start_time)
+            .bind(record.end_time)
+            .bind(&record.app)
+            .bind(record.duration_secs)
+            .bind(record.focus_score)
+            .bind(record.interruptions)
+            .bind(record.created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_deep_work_stretches(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DeepWorkRecord>> {
+        let records = sqlx::query_as::<_, DeepWorkRecord>(
+            r#"
+            SELECT * FROM deep_work_stretches
+            WHERE start_time < ? AND end_time > ?
+            ORDER BY start_time
+            "#,
+        )
+        .bind(end)
+        .bind(start)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn save_summary_correction(&self, record: &SummaryCorrectionRecord) -> Result<()> {
         sqlx::query(
             r#"
-            DELETE FROM day_summaries WHERE date = ?
+            REPLACE INTO summary_corrections
+                (session_id, description, corrected, embedding, created_at)
+            VALUES (?, ?, ?, ?, ?)
             "#,
         )
-        .bind(date)
+        .bind(record.session_id)
+        .bind(&record.description)
+        .bind(&record.corrected)
+        .bind(&record.embedding)
+        .bind(record.created_at)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
+    async fn get_summary_correction(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SummaryCorrectionRecord>> {
+        let result = sqlx::query_as::<_, SummaryCorrectionRecord>(
+            r#"
+            SELECT * FROM summary_corrections WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn get_recent_summary_corrections(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<SummaryCorrectionRecord>> {
+        let records = sqlx::query_as::<_, SummaryCorrectionRecord>(
+            r#"
+            SELECT * FROM summary_corrections ORDER BY created_at DESC LIMIT ?
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
     fn db_type(&self) -> &str {
         "mariadb"
     }
 
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+
     async fn migrate_timezone_to_local(&self) -> Result<(u64, u64, u64, u64, u64, u64)> {
         use chrono::Local;
 
@@ -1,12 +1,89 @@
 // Repository 抽象层 - 定义数据库操作接口
 
 pub mod mariadb;
+pub mod postgres;
+pub mod schema;
 pub mod sqlite;
 
 use super::models::*;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use std::collections::BTreeMap;
+
+/// 按条件查询会话时每页的默认条数
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+
+/// 按条件查询会话时每页的最大条数
+pub const MAX_PAGE_SIZE: i64 = 500;
+
+/// 校验并整理后的会话筛选条件，各后端据此拼接 SQL
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PreparedFilter {
+    /// 会话开始时间的范围 [start, end)
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub categories: Vec<String>,
+    /// 已转为小写
+    pub keywords: Vec<String>,
+    /// (session_scores 的列, 比较符, 值)
+    pub score_bounds: Vec<(&'static str, &'static str, f64)>,
+    pub low_confidence: Option<bool>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl PreparedFilter {
+    pub(crate) fn new(filter: &SessionFilter) -> Result<Self> {
+        let parse = |date: &Option<String>| -> Result<Option<DateTime<Utc>>> {
+            date.as_deref()
+                .map(|date| {
+                    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                        .map(|day| day.and_time(NaiveTime::MIN).and_utc())
+                        .map_err(|e| anyhow!("日期格式无效 {}: {}", date, e))
+                })
+                .transpose()
+        };
+        let non_empty = |values: &[String]| -> Vec<String> {
+            values
+                .iter()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .collect()
+        };
+        let mut score_bounds = Vec::new();
+        for (column, min, max) in [
+            (
+                "productivity_score",
+                filter.min_productivity,
+                filter.max_productivity,
+            ),
+            ("focus_score", filter.min_focus, filter.max_focus),
+        ] {
+            score_bounds.extend(min.map(|value| (column, ">=", value)));
+            score_bounds.extend(max.map(|value| (column, "<=", value)));
+        }
+        if score_bounds.iter().any(|(_, _, value)| !value.is_finite()) {
+            return Err(anyhow!("评分范围无效"));
+        }
+        Ok(Self {
+            start: parse(&filter.start_date)?,
+            end: parse(&filter.end_date)?.map(|end| end + Duration::days(1)),
+            categories: non_empty(&filter.categories),
+            keywords: non_empty(&filter.keywords)
+                .into_iter()
+                .map(|keyword| keyword.to_lowercase())
+                .collect(),
+            score_bounds,
+            low_confidence: filter.low_confidence,
+            limit: filter
+                .limit
+                .unwrap_or(DEFAULT_PAGE_SIZE)
+                .clamp(1, MAX_PAGE_SIZE),
+            offset: filter.offset.max(0),
+        })
+    }
+}
 
 /// 数据库操作接口 - 所有数据库实现必须实现此 trait
 #[async_trait]
@@ -28,6 +105,13 @@ pub trait DatabaseRepository: Send + Sync {
     /// 获取某一天的所有会话
     async fn get_sessions_by_date(&self, date: &str) -> Result<Vec<Session>>;
 
+    /// 获取与时间区间 [start, end) 有重叠的会话（包括跨越区间边界、恰好在 start 结束的会话），按开始时间排序
+    async fn get_sessions_overlapping(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Session>>;
+
     /// 获取所有会话（用于数据同步）
     async fn get_all_sessions(&self) -> Result<Vec<Session>>;
 
@@ -53,12 +137,28 @@ pub trait DatabaseRepository: Send + Sync {
     /// 删除会话
     async fn delete_session(&self, session_id: i64) -> Result<()>;
 
+    /// 彻底删除会话：在一个事务中清理 SESSION_SCOPED_TABLES 中的全部相关行
+    /// （包括不随会话级联删除的人工更正），返回各表删除的行数；会话不存在时返回错误
+    async fn purge_session(&self, session_id: i64) -> Result<BTreeMap<String, u64>>;
+
+    /// 各表中仍引用该会话的行数（只包含非零的表）
+    async fn count_session_references(&self, session_id: i64) -> Result<BTreeMap<String, u64>>;
+
     /// 获取过期会话（用于清理前获取文件路径）
     async fn get_old_sessions(&self, cutoff_date: DateTime<Utc>) -> Result<Vec<Session>>;
 
     /// 删除过期会话
     async fn delete_old_sessions(&self, cutoff_date: DateTime<Utc>) -> Result<u64>;
 
+    /// 设置会话置顶（收藏）状态，置顶会话不会被过期清理删除
+    async fn set_session_pinned(&self, session_id: i64, pinned: bool) -> Result<()>;
+
+    /// 获取所有置顶会话（按开始时间倒序）
+    async fn list_pinned_sessions(&self) -> Result<Vec<Session>>;
+
+    /// 按条件分页查询会话（按开始时间倒序），同时返回符合条件的总数
+    async fn query_sessions(&self, filter: &SessionFilter) -> Result<SessionPage>;
+
     // ========== 帧操作 ==========
 
     /// 插入单个帧
@@ -148,6 +248,159 @@ pub trait DatabaseRepository: Send + Sync {
     /// 删除某一天的总结
     async fn delete_day_summary(&self, date: &str) -> Result<()>;
 
+    // ========== 会话分析记录 ==========
+
+    /// 保存会话的分析记录（覆盖已有记录）
+    async fn save_session_analysis(&self, record: &SessionAnalysisRecord) -> Result<()>;
+
+    /// 获取会话的分析记录
+    async fn get_session_analysis(&self, session_id: i64) -> Result<Option<SessionAnalysisRecord>>;
+
+    // ========== 帧清理记录 ==========
+
+    /// 记录会话原始帧已被清理：在同一事务中删除未保留的帧记录并写入清理记录（插入或更新）
+    async fn save_frame_prune(
+        &self,
+        record: &FramePruneRecord,
+        kept_frame_ids: &[i64],
+    ) -> Result<()>;
+
+    /// 获取会话的帧清理记录
+    async fn get_frame_prune(&self, session_id: i64) -> Result<Option<FramePruneRecord>>;
+
+    // ========== 会话封面 ==========
+
+    /// 保存会话封面（插入或更新）
+    async fn save_session_thumbnail(&self, record: &SessionThumbnailRecord) -> Result<()>;
+
+    /// 获取会话封面
+    async fn get_session_thumbnail(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionThumbnailRecord>>;
+
+    // ========== 会话合并 ==========
+
+    /// 合并会话：插入合并后的会话与帧、写入原始会话的合并记录，再删除原始会话（同一事务）
+    ///
+    /// 原始会话本身由合并产生时，其合并记录改挂到新会话下。返回新会话 ID
+    async fn merge_sessions(
+        &self,
+        merged: &Session,
+        frames: &[Frame],
+        originals: &[SessionMergeRecord],
+    ) -> Result<i64>;
+
+    /// 获取会话的合并记录（合并前的原始会话）
+    async fn get_session_merges(&self, merged_session_id: i64) -> Result<Vec<SessionMergeRecord>>;
+
+    // ========== 截屏缺口 ==========
+
+    /// 保存会话的截屏缺口
+    async fn save_capture_gaps(&self, gaps: &[CaptureGapRecord]) -> Result<()>;
+
+    /// 获取会话的截屏缺口（按开始时间排序）
+    async fn get_capture_gaps(&self, session_id: i64) -> Result<Vec<CaptureGapRecord>>;
+
+    // ========== 分析所用帧 ==========
+
+    /// 保存会话分析所用的帧（覆盖已有记录）
+    async fn save_frame_selection(&self, record: &FrameSelectionRecord) -> Result<()>;
+
+    /// 获取会话分析所用的帧
+    async fn get_frame_selection(&self, session_id: i64) -> Result<Option<FrameSelectionRecord>>;
+
+    // ========== 总结自定义字段 ==========
+
+    /// 保存会话总结的自定义字段（覆盖已有记录）
+    async fn save_session_extra_fields(&self, record: &SessionExtraFieldsRecord) -> Result<()>;
+
+    /// 获取会话总结的自定义字段
+    async fn get_session_extra_fields(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionExtraFieldsRecord>>;
+
+    // ========== 分析模式 ==========
+
+    /// 保存会话的分析模式（覆盖已有记录）
+    async fn save_session_analysis_mode(&self, record: &SessionAnalysisModeRecord) -> Result<()>;
+
+    /// 获取会话的分析模式
+    async fn get_session_analysis_mode(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionAnalysisModeRecord>>;
+
+    // ========== 会话评分 ==========
+
+    /// 保存会话总结的评分（覆盖已有记录）
+    async fn save_session_scores(&self, record: &SessionScoresRecord) -> Result<()>;
+
+    // ========== 分析失败（死信队列） ==========
+
+    /// 保存分析失败记录（按 session_id 覆盖）
+    async fn save_analysis_failure(&self, record: &AnalysisFailureRecord) -> Result<()>;
+
+    /// 获取会话的分析失败记录
+    async fn get_analysis_failure(&self, session_id: i64) -> Result<Option<AnalysisFailureRecord>>;
+
+    /// 列出全部分析失败记录（按最近失败时间倒序）
+    async fn list_analysis_failures(&self) -> Result<Vec<AnalysisFailureRecord>>;
+
+    /// 删除会话的分析失败记录（分析成功后调用）
+    async fn delete_analysis_failure(&self, session_id: i64) -> Result<()>;
+
+    // ========== 分析队列 ==========
+
+    /// 保存分析队列条目（按 session_key 覆盖）
+    async fn save_analysis_queue_entry(&self, record: &AnalysisQueueRecord) -> Result<()>;
+
+    /// 获取分析队列条目
+    async fn get_analysis_queue_entry(
+        &self,
+        session_key: i64,
+    ) -> Result<Option<AnalysisQueueRecord>>;
+
+    /// 列出分析队列中的全部条目（按入队时间排序）
+    async fn list_analysis_queue(&self) -> Result<Vec<AnalysisQueueRecord>>;
+
+    /// 删除分析队列条目（分析结束后调用）
+    async fn delete_analysis_queue_entry(&self, session_key: i64) -> Result<()>;
+
+    // ========== 深度工作时段 ==========
+
+    /// 替换会话的深度工作时段（先删除该会话已有的记录）
+    async fn replace_deep_work_stretches(
+        &self,
+        session_id: i64,
+        records: &[DeepWorkRecord],
+    ) -> Result<()>;
+
+    /// 获取与 [start, end) 有重叠的深度工作时段（按开始时间排序）
+    async fn get_deep_work_stretches(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DeepWorkRecord>>;
+
+    // ========== 总结人工更正 ==========
+
+    /// 保存会话总结的人工更正（覆盖已有记录）
+    async fn save_summary_correction(&self, record: &SummaryCorrectionRecord) -> Result<()>;
+
+    /// 获取会话总结的人工更正
+    async fn get_summary_correction(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SummaryCorrectionRecord>>;
+
+    /// 获取最近的人工更正（按更正时间倒序）
+    async fn get_recent_summary_corrections(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<SummaryCorrectionRecord>>;
+
     // ========== 数据库初始化和元数据 ==========
 
     /// 初始化数据库表结构
@@ -156,6 +409,9 @@ pub trait DatabaseRepository: Send + Sync {
     /// 获取数据库类型标识
     fn db_type(&self) -> &str;
 
+    /// 关闭连接池：等待进行中的写入完成后断开连接（应用退出前调用）
+    async fn close(&self);
+
     /// 迁移时间字段：将 UTC 时间转换为本地时间格式存储
     ///
     /// 此方法用于将旧的 UTC 时间数据迁移为本地时间格式。
@@ -164,3 +420,37 @@ pub trait DatabaseRepository: Send + Sync {
     /// 返回值：(更新的会话数, 更新的帧数, 更新的 LLM 调用数, 更新的视频分段数, 更新的时间线卡片数, 更新的每日总结数)
     async fn migrate_timezone_to_local(&self) -> Result<(u64, u64, u64, u64, u64, u64)>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepared_filter_normalizes_conditions() {
+        let filter = SessionFilter {
+            end_date: Some("2025-01-31".to_string()),
+            categories: vec![" work ".to_string(), "".to_string()],
+            keywords: vec!["VS Code".to_string()],
+            min_focus: Some(60.0),
+            limit: Some(10_000),
+            offset: -5,
+            ..Default::default()
+        };
+        let prepared = PreparedFilter::new(&filter).unwrap();
+        assert_eq!(prepared.start, None);
+        assert_eq!(
+            prepared.end.unwrap().to_rfc3339(),
+            "2025-02-01T00:00:00+00:00"
+        );
+        assert_eq!(prepared.categories, vec!["work"]);
+        assert_eq!(prepared.keywords, vec!["vs code"]);
+        assert_eq!(prepared.score_bounds, vec![("focus_score", ">=", 60.0)]);
+        assert_eq!((prepared.limit, prepared.offset), (MAX_PAGE_SIZE, 0));
+
+        let bad_date = SessionFilter {
+            start_date: Some("2025/01/01".to_string()),
+            ..Default::default()
+        };
+        assert!(PreparedFilter::new(&bad_date).is_err());
+    }
+}
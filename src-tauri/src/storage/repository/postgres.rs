@@ -0,0 +1,1946 @@
+// PostgreSQL 数据库实现（多用户服务器部署）
+//
+// 时间列使用 TIMESTAMPTZ，与其他后端一致按 UTC 类型存储本地时间（见 local_now）
+
+use super::schema::{self, Dialect, SESSION_SCOPED_TABLES};
+use super::{DatabaseRepository, PreparedFilter};
+use crate::storage::config::get_device_info;
+use crate::storage::models::*;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use sqlx::postgres::{PgConnectOptions, PgPool, PgPoolOptions, Postgres};
+use sqlx::QueryBuilder;
+use sqlx::Row;
+use std::collections::BTreeMap;
+use tracing::info;
+
+/// PostgreSQL 数据库实现
+pub struct PostgresRepository {
+    pool: PgPool,
+}
+
+impl PostgresRepository {
+    /// 创建新的 PostgreSQL 数据库连接
+    pub async fn new(
+        host: &str,
+        port: u16,
+        database: &str,
+        username: &str,
+        password: &str,
+    ) -> Result<Self> {
+        info!(
+            "初始化 PostgreSQL 数据库: {}@{}:{}/{}",
+            username, host, port, database
+        );
+
+        let server_options = PgConnectOptions::new()
+            .host(host)
+            .port(port)
+            .username(username)
+            .password(password);
+
+        // 先连接到默认的 postgres 库，检查并创建数据库
+        info!("连接到 PostgreSQL 服务器检查数据库是否存在...");
+        let server_pool = PgPoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(std::time::Duration::from_secs(30))
+            .connect_with(server_options.clone().database("postgres"))
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "连接 PostgreSQL 服务器失败 ({}:{}): {}\n\n请检查：\n1. PostgreSQL 服务是否已启动\n2. 网络连接是否正常\n3. 防火墙是否阻止了端口 {}\n4. 主机地址和端口是否正确",
+                    host, port, e, port
+                )
+            })?;
+
+        let db_exists: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM pg_database WHERE datname = $1")
+                .bind(database)
+                .fetch_one(&server_pool)
+                .await?;
+
+        if db_exists == 0 {
+            info!("数据库 '{}' 不存在，正在创建...", database);
+            sqlx::query(&format!(
+                "CREATE DATABASE \"{}\" ENCODING 'UTF8'",
+                database.replace('"', "\"\"")
+            ))
+            .execute(&server_pool)
+            .await?;
+            info!("数据库 '{}' 创建成功", database);
+        } else {
+            info!("数据库 '{}' 已存在", database);
+        }
+
+        server_pool.close().await;
+
+        info!("创建 PostgreSQL 连接池...");
+        let pool = PgPoolOptions::new()
+            .max_connections(20)
+            .min_connections(2)
+            .idle_timeout(std::time::Duration::from_secs(180))
+            .max_lifetime(std::time::Duration::from_secs(1800))
+            .acquire_timeout(std::time::Duration::from_secs(30))
+            .connect_with(server_options.database(database))
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "创建 PostgreSQL 连接池失败 ({}:{}/{}): {}",
+                    host,
+                    port,
+                    database,
+                    e
+                )
+            })?;
+
+        info!("PostgreSQL 连接池创建成功");
+
+        let repo = Self { pool };
+
+        let tables_exist = repo.check_tables_exist().await?;
+        if !tables_exist {
+            info!("PostgreSQL 表不存在，开始初始化表结构");
+            repo.initialize_tables().await?;
+        } else {
+            info!("PostgreSQL 表已存在，直接使用");
+        }
+
+        Ok(repo)
+    }
+
+    /// 检查所有必要的表是否存在
+    async fn check_tables_exist(&self) -> Result<bool> {
+        for table in schema::table_names() {
+            let count: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = $1",
+            )
+            .bind(table)
+            .fetch_one(&self.pool)
+            .await?;
+
+            if count == 0 {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// 获取连接池引用
+    pub fn get_pool(&self) -> &PgPool {
+        &self.pool
+    }
+}
+
+/// 解析 YYYY-MM-DD 日期
+fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|e| anyhow!("日期格式无效 {}: {}", date, e))
+}
+
+/// 日期范围对应的时间区间 [开始日 00:00, 结束日次日 00:00)
+fn day_range(start_date: &str, end_date: &str) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let start = parse_date(start_date)?.and_time(NaiveTime::MIN).and_utc();
+    let end = parse_date(end_date)?.and_time(NaiveTime::MIN).and_utc() + Duration::days(1);
+    Ok((start, end))
+}
+
+/// 按条件查询会话时选取的列（与 Session 的字段对应）
+const SESSION_COLUMNS: &str = "SELECT sessions.id, sessions.start_time, sessions.end_time, \
+    sessions.title, sessions.summary, sessions.video_path, sessions.tags, sessions.created_at, \
+    sessions.device_name, sessions.device_type, sessions.pinned";
+
+/// 追加筛选条件对应的 FROM 与 WHERE 子句（计数与分页查询共用）
+fn push_session_filter(builder: &mut QueryBuilder<'_, Postgres>, filter: &PreparedFilter) {
+    builder.push(
+        " FROM sessions LEFT JOIN session_scores ON session_scores.session_id = sessions.id \
+         WHERE 1 = 1",
+    );
+    if let Some(start) = filter.start {
+        builder
+            .push(" AND sessions.start_time >= ")
+            .push_bind(start);
+    }
+    if let Some(end) = filter.end {
+        builder.push(" AND sessions.start_time < ").push_bind(end);
+    }
+    if !filter.categories.is_empty() {
+        builder
+            .push(
+                " AND EXISTS (SELECT 1 FROM jsonb_array_elements(sessions.tags::jsonb) AS tag \
+                 WHERE tag->>'category' = ANY(",
+            )
+            .push_bind(filter.categories.clone())
+            .push("))");
+    }
+    if !filter.keywords.is_empty() {
+        builder
+            .push(
+                " AND EXISTS (SELECT 1 FROM jsonb_array_elements(sessions.tags::jsonb) AS tag, \
+                 jsonb_array_elements_text(COALESCE(tag->'keywords', '[]'::jsonb)) AS keyword \
+                 WHERE LOWER(keyword) = ANY(",
+            )
+            .push_bind(filter.keywords.clone())
+            .push("))");
+    }
+    for (column, op, value) in &filter.score_bounds {
+        builder
+            .push(format!(" AND session_scores.{} {} ", column, op))
+            .push_bind(*value);
+    }
+    if let Some(low_confidence) = filter.low_confidence {
+        builder
+            .push(" AND COALESCE(session_scores.low_confidence, FALSE) = ")
+            .push_bind(low_confidence);
+    }
+}
+
+#[async_trait]
+impl DatabaseRepository for PostgresRepository {
+    // ========== 会话操作 ==========
+
+    async fn insert_session(&self, session: &Session) -> Result<i64> {
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type, pinned)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id
+        "#,
+        )
+        .bind(session.start_time)
+        .bind(session.end_time)
+        .bind(&session.title)
+        .bind(&session.summary)
+        .bind(&session.video_path)
+        .bind(&session.tags)
+        .bind(&session.device_name)
+        .bind(&session.device_type)
+        .bind(session.pinned)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    async fn insert_sessions(&self, sessions: &[Session]) -> Result<Vec<i64>> {
+        let mut ids = Vec::new();
+        let mut tx = self.pool.begin().await?;
+
+        for session in sessions {
+            let id: i64 = sqlx::query_scalar(
+                r#"
+                INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type, pinned)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                RETURNING id
+            "#,
+            )
+            .bind(session.start_time)
+            .bind(session.end_time)
+            .bind(&session.title)
+            .bind(&session.summary)
+            .bind(&session.video_path)
+            .bind(&session.tags)
+            .bind(&session.device_name)
+            .bind(&session.device_type)
+            .bind(session.pinned)
+            .fetch_one(&mut *tx)
+            .await?;
+
+            ids.push(id);
+        }
+
+        tx.commit().await?;
+        Ok(ids)
+    }
+
+    async fn get_session(&self, session_id: i64) -> Result<Session> {
+        let session = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT id, start_time, end_time, title, summary,
+                   video_path, tags, created_at, device_name, device_type, pinned
+            FROM sessions
+            WHERE id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(session)
+    }
+
+    async fn get_session_detail(&self, session_id: i64) -> Result<SessionDetail> {
+        let session = self.get_session(session_id).await?;
+        let frames = self.get_frames_by_session(session_id).await?;
+        let tags = serde_json::from_str(&session.tags).unwrap_or_default();
+
+        Ok(SessionDetail {
+            session,
+            frames,
+            tags,
+        })
+    }
+
+    async fn get_sessions_by_date(&self, date: &str) -> Result<Vec<Session>> {
+        let (start, end) = day_range(date, date)?;
+
+        let sessions = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT id, start_time, end_time, title, summary,
+                   video_path, tags, created_at, device_name, device_type, pinned
+            FROM sessions
+            WHERE start_time >= $1 AND start_time < $2
+            ORDER BY start_time DESC
+            "#,
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    async fn get_sessions_overlapping(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT id, start_time, end_time, title, summary,
+                   video_path, tags, created_at, device_name, device_type, pinned
+            FROM sessions
+            WHERE start_time < $1 AND end_time >= $2
+            ORDER BY start_time
+            "#,
+        )
+        .bind(end)
+        .bind(start)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    async fn get_all_sessions(&self) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT id, start_time, end_time, title, summary,
+                   video_path, tags, created_at, device_name, device_type, pinned
+            FROM sessions
+            ORDER BY start_time
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    async fn update_session(
+        &self,
+        session_id: i64,
+        title: &str,
+        summary: &str,
+        video_path: Option<&str>,
+        tags: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE sessions SET title = $1, summary = $2, video_path = $3, tags = $4 WHERE id = $5",
+        )
+        .bind(title)
+        .bind(summary)
+        .bind(video_path)
+        .bind(tags)
+        .bind(session_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_session_tags(&self, session_id: i64, tags: &str) -> Result<()> {
+        sqlx::query("UPDATE sessions SET tags = $1 WHERE id = $2")
+            .bind(tags)
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_session_video_path(&self, session_id: i64, video_path: &str) -> Result<()> {
+        sqlx::query("UPDATE sessions SET video_path = $1 WHERE id = $2")
+            .bind(video_path)
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn update_device_info_for_all_sessions(&self) -> Result<u64> {
+        let (device_name, device_type) = get_device_info();
+
+        let result = sqlx::query(
+            "UPDATE sessions SET device_name = $1, device_type = $2 WHERE device_name IS NULL OR device_type = 'desktop'",
+        )
+        .bind(&device_name)
+        .bind(&device_type)
+        .execute(&self.pool)
+        .await?;
+
+        let updated_count = result.rows_affected();
+
+        if updated_count > 0 {
+            info!(
+                "已更新 {} 条历史会话的设备信息: device_name={}, device_type={}",
+                updated_count, device_name, device_type
+            );
+        }
+
+        Ok(updated_count)
+    }
+
+    async fn delete_session(&self, session_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM sessions WHERE id = $1")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+
+        info!("删除会话: {}", session_id);
+        Ok(())
+    }
+
+    async fn purge_session(&self, session_id: i64) -> Result<BTreeMap<String, u64>> {
+        let mut tx = self.pool.begin().await?;
+        let mut removed = BTreeMap::new();
+        for (table, column) in SESSION_SCOPED_TABLES {
+            let sql = format!("DELETE FROM {} WHERE {} = $1", table, column);
+            let result = sqlx::query(&sql).bind(session_id).execute(&mut *tx).await?;
+            if result.rows_affected() > 0 {
+                *removed.entry(table.to_string()).or_default() += result.rows_affected();
+            }
+        }
+        // 未提交的事务在返回时回滚
+        if !removed.contains_key("sessions") {
+            return Err(anyhow::anyhow!("会话不存在: {}", session_id));
+        }
+        tx.commit().await?;
+
+        info!("彻底删除会话 {}: {:?}", session_id, removed);
+        Ok(removed)
+    }
+
+    async fn count_session_references(&self, session_id: i64) -> Result<BTreeMap<String, u64>> {
+        let mut counts = BTreeMap::new();
+        for (table, column) in SESSION_SCOPED_TABLES {
+            let sql = format!("SELECT COUNT(*) FROM {} WHERE {} = $1", table, column);
+            let count: i64 = sqlx::query_scalar(&sql)
+                .bind(session_id)
+                .fetch_one(&self.pool)
+                .await?;
+            if count > 0 {
+                *counts.entry(table.to_string()).or_default() += count as u64;
+            }
+        }
+        Ok(counts)
+    }
+
+    async fn get_old_sessions(&self, cutoff_date: DateTime<Utc>) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as::<_, Session>(
+            "SELECT id, start_time, end_time, title, summary, video_path, tags, created_at, device_name, device_type, pinned
+             FROM sessions
+             WHERE start_time < $1 AND NOT pinned",
+        )
+        .bind(cutoff_date)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    async fn delete_old_sessions(&self, cutoff_date: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM sessions WHERE start_time < $1 AND NOT pinned")
+            .bind(cutoff_date)
+            .execute(&self.pool)
+            .await?;
+
+        let deleted_count = result.rows_affected();
+
+        if deleted_count > 0 {
+            info!("删除了 {} 个过期会话", deleted_count);
+        }
+
+        Ok(deleted_count)
+    }
+
+    async fn set_session_pinned(&self, session_id: i64, pinned: bool) -> Result<()> {
+        let result = sqlx::query("UPDATE sessions SET pinned = $1 WHERE id = $2")
+            .bind(pinned)
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("会话不存在: {}", session_id));
+        }
+
+        info!("会话 {} 置顶状态: {}", session_id, pinned);
+        Ok(())
+    }
+
+    async fn list_pinned_sessions(&self) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT id, start_time, end_time, title, summary,
+                   video_path, tags, created_at, device_name, device_type, pinned
+            FROM sessions
+            WHERE pinned
+            ORDER BY start_time DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    async fn query_sessions(&self, filter: &SessionFilter) -> Result<SessionPage> {
+        let prepared = PreparedFilter::new(filter)?;
+
+        let mut count = QueryBuilder::<Postgres>::new("SELECT COUNT(*)");
+        push_session_filter(&mut count, &prepared);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut page = QueryBuilder::<Postgres>::new(SESSION_COLUMNS);
+        push_session_filter(&mut page, &prepared);
+        page.push(" ORDER BY sessions.start_time DESC, sessions.id DESC LIMIT ")
+            .push_bind(prepared.limit)
+            .push(" OFFSET ")
+            .push_bind(prepared.offset);
+        let sessions = page
+            .build_query_as::<Session>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(SessionPage {
+            sessions,
+            total,
+            offset: prepared.offset,
+            limit: prepared.limit,
+        })
+    }
+
+    // ========== 帧操作 ==========
+
+    async fn insert_frame(&self, frame: &Frame) -> Result<i64> {
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO frames (session_id, timestamp, file_path)
+            VALUES ($1, $2, $3)
+            RETURNING id
+        "#,
+        )
+        .bind(frame.session_id)
+        .bind(frame.timestamp)
+        .bind(&frame.file_path)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    async fn insert_frames(&self, frames: &[Frame]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        for frame in frames {
+            sqlx::query(
+                r#"
+                INSERT INTO frames (session_id, timestamp, file_path)
+                VALUES ($1, $2, $3)
+            "#,
+            )
+            .bind(frame.session_id)
+            .bind(frame.timestamp)
+            .bind(&frame.file_path)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_frames_by_session(&self, session_id: i64) -> Result<Vec<Frame>> {
+        let frames = sqlx::query_as::<_, Frame>(
+            r#"
+            SELECT id, session_id, timestamp, file_path
+            FROM frames
+            WHERE session_id = $1
+            ORDER BY timestamp
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(frames)
+    }
+
+    async fn delete_frames_by_session(&self, session_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM frames WHERE session_id = $1")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // ========== 活动统计 ==========
+
+    async fn get_activities(&self, start_date: &str, end_date: &str) -> Result<Vec<Activity>> {
+        let (start, end) = day_range(start_date, end_date)?;
+
+        // 时间按 UTC 类型存储本地时间，按 UTC 取日期即为本地日期
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                TO_CHAR(start_time AT TIME ZONE 'UTC', 'YYYY-MM-DD') AS date,
+                COUNT(*) AS session_count,
+                CAST(SUM(FLOOR(EXTRACT(EPOCH FROM (end_time - start_time)) / 60)) AS BIGINT) AS total_duration_minutes,
+                STRING_AGG(DISTINCT (tags::jsonb -> 0 ->> 'category'), ',') AS main_categories
+            FROM sessions
+            WHERE start_time >= $1 AND start_time < $2
+            GROUP BY 1
+            ORDER BY date DESC
+            "#,
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut activities = Vec::new();
+        for row in rows {
+            let date: String = row.try_get("date")?;
+            let session_count: i64 = row.try_get("session_count")?;
+            let total_duration_minutes: Option<i64> = row.try_get("total_duration_minutes")?;
+            let main_categories_str: Option<String> = row.try_get("main_categories")?;
+
+            let main_categories = main_categories_str
+                .map(|s| s.split(',').map(|s| s.to_string()).collect())
+                .unwrap_or_default();
+
+            activities.push(Activity {
+                date,
+                session_count: session_count as i32,
+                total_duration_minutes: total_duration_minutes.unwrap_or(0) as i32,
+                main_categories,
+            });
+        }
+
+        Ok(activities)
+    }
+
+    // ========== LLM 调用记录 ==========
+
+    async fn insert_llm_call(&self, record: &LLMCallRecord) -> Result<i64> {
+        // 检查 session_id 是否存在（如果不是 NULL）
+        if let Some(sid) = record.session_id {
+            let exists: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions WHERE id = $1")
+                .bind(sid)
+                .fetch_one(&self.pool)
+                .await?;
+
+            if exists == 0 {
+                return Err(anyhow!(
+                    "无法插入 LLM 调用记录：session_id {} 不存在。请先创建会话。",
+                    sid
+                ));
+            }
+        }
+
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO llm_calls (
+                session_id, provider, model, call_type,
+                request_headers, request_body, response_headers, response_body,
+                status_code, error_message, latency_ms, token_usage, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING id
+        "#,
+        )
+        .bind(record.session_id)
+        .bind(&record.provider)
+        .bind(&record.model)
+        .bind(&record.call_type)
+        .bind(&record.request_headers)
+        .bind(&record.request_body)
+        .bind(&record.response_headers)
+        .bind(&record.response_body)
+        .bind(record.status_code)
+        .bind(&record.error_message)
+        .bind(record.latency_ms)
+        .bind(&record.token_usage)
+        .bind(record.created_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    async fn get_llm_calls_by_session(&self, session_id: i64) -> Result<Vec<LLMCallRecord>> {
+        let records = sqlx::query_as::<_, LLMCallRecord>(
+            r#"
+            SELECT * FROM llm_calls
+            WHERE session_id = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn get_recent_llm_errors(&self, limit: i64) -> Result<Vec<LLMCallRecord>> {
+        let records = sqlx::query_as::<_, LLMCallRecord>(
+            r#"
+            SELECT * FROM llm_calls
+            WHERE error_message IS NOT NULL
+            ORDER BY created_at DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn delete_llm_calls_by_session(&self, session_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM llm_calls WHERE session_id = $1")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // ========== 视频分段 ==========
+
+    async fn insert_video_segment(&self, segment: &VideoSegmentRecord) -> Result<i64> {
+        // 检查 session_id 是否存在
+        let exists: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions WHERE id = $1")
+            .bind(segment.session_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        if exists == 0 {
+            return Err(anyhow!(
+                "无法插入视频分段记录：session_id {} 不存在。请先创建会话。",
+                segment.session_id
+            ));
+        }
+
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO video_segments (
+                session_id, llm_call_id, start_timestamp, end_timestamp,
+                description, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id
+        "#,
+        )
+        .bind(segment.session_id)
+        .bind(segment.llm_call_id)
+        .bind(&segment.start_timestamp)
+        .bind(&segment.end_timestamp)
+        .bind(&segment.description)
+        .bind(segment.created_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    async fn insert_video_segments(&self, segments: &[VideoSegmentRecord]) -> Result<()> {
+        if segments.is_empty() {
+            return Ok(());
+        }
+
+        // 检查所有 session_id 是否存在
+        for segment in segments {
+            let exists: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions WHERE id = $1")
+                .bind(segment.session_id)
+                .fetch_one(&self.pool)
+                .await?;
+
+            if exists == 0 {
+                return Err(anyhow!(
+                    "无法插入视频分段记录：session_id {} 不存在。请先创建会话。",
+                    segment.session_id
+                ));
+            }
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        for segment in segments {
+            sqlx::query(
+                r#"
+                INSERT INTO video_segments (
+                    session_id, llm_call_id, start_timestamp, end_timestamp,
+                    description, created_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            )
+            .bind(segment.session_id)
+            .bind(segment.llm_call_id)
+            .bind(&segment.start_timestamp)
+            .bind(&segment.end_timestamp)
+            .bind(&segment.description)
+            .bind(segment.created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_video_segments_by_session(
+        &self,
+        session_id: i64,
+    ) -> Result<Vec<VideoSegmentRecord>> {
+        let segments = sqlx::query_as::<_, VideoSegmentRecord>(
+            r#"
+            SELECT * FROM video_segments
+            WHERE session_id = $1
+            ORDER BY start_timestamp
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(segments)
+    }
+
+    async fn delete_video_segments_by_session(&self, session_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM video_segments WHERE session_id = $1")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // ========== 时间线卡片 ==========
+
+    async fn insert_timeline_card(&self, card: &TimelineCardRecord) -> Result<i64> {
+        // 检查 session_id 是否存在
+        let exists: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions WHERE id = $1")
+            .bind(card.session_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        if exists == 0 {
+            return Err(anyhow!(
+                "无法插入时间线卡片记录：session_id {} 不存在。请先创建会话。",
+                card.session_id
+            ));
+        }
+
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO timeline_cards (
+                session_id, llm_call_id, start_time, end_time,
+                category, subcategory, title, summary, detailed_summary,
+                distractions, app_sites, video_preview_path, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING id
+        "#,
+        )
+        .bind(card.session_id)
+        .bind(card.llm_call_id)
+        .bind(&card.start_time)
+        .bind(&card.end_time)
+        .bind(&card.category)
+        .bind(&card.subcategory)
+        .bind(&card.title)
+        .bind(&card.summary)
+        .bind(&card.detailed_summary)
+        .bind(&card.distractions)
+        .bind(&card.app_sites)
+        .bind(&card.video_preview_path)
+        .bind(card.created_at)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(id)
+    }
+
+    async fn insert_timeline_cards(&self, cards: &[TimelineCardRecord]) -> Result<()> {
+        if cards.is_empty() {
+            return Ok(());
+        }
+
+        // 检查所有 session_id 是否存在
+        for card in cards {
+            let exists: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions WHERE id = $1")
+                .bind(card.session_id)
+                .fetch_one(&self.pool)
+                .await?;
+
+            if exists == 0 {
+                return Err(anyhow!(
+                    "无法插入时间线卡片记录：session_id {} 不存在。请先创建会话。",
+                    card.session_id
+                ));
+            }
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        for card in cards {
+            sqlx::query(
+                r#"
+                INSERT INTO timeline_cards (
+                    session_id, llm_call_id, start_time, end_time,
+                    category, subcategory, title, summary, detailed_summary,
+                    distractions, app_sites, video_preview_path, created_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            "#,
+            )
+            .bind(card.session_id)
+            .bind(card.llm_call_id)
+            .bind(&card.start_time)
+            .bind(&card.end_time)
+            .bind(&card.category)
+            .bind(&card.subcategory)
+            .bind(&card.title)
+            .bind(&card.summary)
+            .bind(&card.detailed_summary)
+            .bind(&card.distractions)
+            .bind(&card.app_sites)
+            .bind(&card.video_preview_path)
+            .bind(card.created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_timeline_cards_by_session(
+        &self,
+        session_id: i64,
+    ) -> Result<Vec<TimelineCardRecord>> {
+        let cards = sqlx::query_as::<_, TimelineCardRecord>(
+            r#"
+            SELECT * FROM timeline_cards
+            WHERE session_id = $1
+            ORDER BY start_time
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(cards)
+    }
+
+    async fn get_recent_timeline_cards(&self, limit: i64) -> Result<Vec<TimelineCardRecord>> {
+        let cards = sqlx::query_as::<_, TimelineCardRecord>(
+            r#"
+            SELECT * FROM timeline_cards
+            ORDER BY created_at DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(cards)
+    }
+
+    async fn delete_timeline_cards_by_session(&self, session_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM timeline_cards WHERE session_id = $1")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    // ========== 统计信息 ==========
+
+    async fn get_stats(&self) -> Result<(i64, i64, i64)> {
+        let session_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sessions")
+            .fetch_one(&self.pool)
+            .await?;
+
+        let frame_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM frames")
+            .fetch_one(&self.pool)
+            .await?;
+
+        let total_size: i64 = sqlx::query_scalar("SELECT pg_database_size(current_database())")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok((session_count, frame_count, total_size))
+    }
+
+    async fn get_analyzed_video_paths(&self) -> Result<Vec<String>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT video_path
+            FROM sessions
+            WHERE video_path IS NOT NULL
+              AND summary != '{}'
+              AND summary != ''
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut paths = Vec::new();
+        for row in rows {
+            if let Ok(Some(path)) = row.try_get::<Option<String>, _>("video_path") {
+                paths.push(path);
+            }
+        }
+
+        Ok(paths)
+    }
+
+    // ========== 数据库初始化 ==========
+
+    async fn initialize_tables(&self) -> Result<()> {
+        // 新增列使用 ADD COLUMN IF NOT EXISTS，为已存在的表补齐
+        let statements = schema::create_table_statements(Dialect::Postgres)
+            .into_iter()
+            .chain(
+                schema::add_column_statements(Dialect::Postgres)
+                    .into_iter()
+                    .map(|(_, _, sql)| sql),
+            )
+            .chain(schema::create_index_statements(Dialect::Postgres));
+        for statement in statements {
+            sqlx::query(&statement).execute(&self.pool).await?;
+        }
+
+        info!("PostgreSQL 数据库表初始化完成");
+        Ok(())
+    }
+
+    async fn save_day_summary(&self, date: &str, summary: &DaySummaryRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO day_summaries (
+                date, summary_text, device_stats, parallel_work, usage_patterns,
+                active_device_count, llm_call_id, updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (date) DO UPDATE SET
+                summary_text = EXCLUDED.summary_text,
+                device_stats = EXCLUDED.device_stats,
+                parallel_work = EXCLUDED.parallel_work,
+                usage_patterns = EXCLUDED.usage_patterns,
+                active_device_count = EXCLUDED.active_device_count,
+                llm_call_id = EXCLUDED.llm_call_id,
+                updated_at = EXCLUDED.updated_at
+            "#,
+        )
+        .bind(parse_date(date)?)
+        .bind(&summary.summary_text)
+        .bind(&summary.device_stats)
+        .bind(&summary.parallel_work)
+        .bind(&summary.usage_patterns)
+        .bind(summary.active_device_count)
+        .bind(summary.llm_call_id)
+        .bind(local_now())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_day_summary(&self, date: &str) -> Result<Option<DaySummaryRecord>> {
+        let result = sqlx::query_as::<_, DaySummaryRecord>(
+            r#"
+            SELECT * FROM day_summaries WHERE date = $1
+            "#,
+        )
+        .bind(parse_date(date)?)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn delete_day_summary(&self, date: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            DELETE FROM day_summaries WHERE date = $1
+            "#,
+        )
+        .bind(parse_date(date)?)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn save_session_analysis(&self, record: &SessionAnalysisRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO session_analyses (session_id, served_by, sampling_seed, created_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (session_id) DO UPDATE SET
+                served_by = EXCLUDED.served_by,
+                sampling_seed = EXCLUDED.sampling_seed,
+                created_at = EXCLUDED.created_at
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(&record.served_by)
+        .bind(record.sampling_seed)
+        .bind(record.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_session_analysis(&self, session_id: i64) -> Result<Option<SessionAnalysisRecord>> {
+        let result = sqlx::query_as::<_, SessionAnalysisRecord>(
+            r#"
+            SELECT * FROM session_analyses WHERE session_id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_frame_prune(
+        &self,
+        record: &FramePruneRecord,
+        kept_frame_ids: &[i64],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // 删除未保留的帧记录（保留列表为空时 <> ALL 恒为真，删除该会话的全部帧）
+        sqlx::query("DELETE FROM frames WHERE session_id = $1 AND id <> ALL($2)")
+            .bind(record.session_id)
+            .bind(kept_frame_ids)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO frame_prunes (session_id, deleted_count, kept_count, pruned_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (session_id) DO UPDATE SET
+                deleted_count = EXCLUDED.deleted_count,
+                kept_count = EXCLUDED.kept_count,
+                pruned_at = EXCLUDED.pruned_at
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(record.deleted_count)
+        .bind(record.kept_count)
+        .bind(record.pruned_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_frame_prune(&self, session_id: i64) -> Result<Option<FramePruneRecord>> {
+        let result = sqlx::query_as::<_, FramePruneRecord>(
+            r#"
+            SELECT * FROM frame_prunes WHERE session_id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_session_thumbnail(&self, record: &SessionThumbnailRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO session_thumbnails (session_id, frame_timestamp, width, height, image_data, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (session_id) DO UPDATE SET
+                frame_timestamp = EXCLUDED.frame_timestamp,
+                width = EXCLUDED.width,
+                height = EXCLUDED.height,
+                image_data = EXCLUDED.image_data,
+                created_at = EXCLUDED.created_at
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(record.frame_timestamp)
+        .bind(record.width)
+        .bind(record.height)
+        .bind(&record.image_data)
+        .bind(record.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_session_thumbnail(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionThumbnailRecord>> {
+        let result = sqlx::query_as::<_, SessionThumbnailRecord>(
+            r#"
+            SELECT * FROM session_thumbnails WHERE session_id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn merge_sessions(
+        &self,
+        merged: &Session,
+        frames: &[Frame],
+        originals: &[SessionMergeRecord],
+    ) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+
+        let merged_id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type, pinned)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id
+            "#,
+        )
+        .bind(merged.start_time)
+        .bind(merged.end_time)
+        .bind(&merged.title)
+        .bind(&merged.summary)
+        .bind(&merged.video_path)
+        .bind(&merged.tags)
+        .bind(&merged.device_name)
+        .bind(&merged.device_type)
+        .bind(merged.pinned)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        for frame in frames {
+            sqlx::query(
+                r#"
+                INSERT INTO frames (session_id, timestamp, file_path)
+                VALUES ($1, $2, $3)
+                "#,
+            )
+            .bind(merged_id)
+            .bind(frame.timestamp)
+            .bind(&frame.file_path)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for original in originals {
+            sqlx::query(
+                "UPDATE session_merges SET merged_session_id = $1 WHERE merged_session_id = $2",
+            )
+            .bind(merged_id)
+            .bind(original.original_session_id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(
+                r#"
+                INSERT INTO session_merges (merged_session_id, original_session_id, original_session, frame_count, merged_at)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(merged_id)
+            .bind(original.original_session_id)
+            .bind(&original.original_session)
+            .bind(original.frame_count)
+            .bind(original.merged_at)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("UPDATE capture_gaps SET session_id = $1 WHERE session_id = $2")
+                .bind(merged_id)
+                .bind(original.original_session_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE deep_work_stretches SET session_id = $1 WHERE session_id = $2")
+                .bind(merged_id)
+                .bind(original.original_session_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM sessions WHERE id = $1")
+                .bind(original.original_session_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        info!(
+            "已合并 {} 个会话为会话 {}（{} 帧）",
+            originals.len(),
+            merged_id,
+            frames.len()
+        );
+        Ok(merged_id)
+    }
+
+    async fn get_session_merges(&self, merged_session_id: i64) -> Result<Vec<SessionMergeRecord>> {
+        let records = sqlx::query_as::<_, SessionMergeRecord>(
+            r#"
+            SELECT * FROM session_merges WHERE merged_session_id = $1 ORDER BY id
+            "#,
+        )
+        .bind(merged_session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn save_capture_gaps(&self, gaps: &[CaptureGapRecord]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for gap in gaps {
+            sqlx::query(
+                r#"
+                INSERT INTO capture_gaps (session_id, gap_start, gap_end, restart_attempts)
+                VALUES ($1, $2, $3, $4)
+                "#,
+            )
+            .bind(gap.session_id)
+            .bind(gap.gap_start)
+            .bind(gap.gap_end)
+            .bind(gap.restart_attempts)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_capture_gaps(&self, session_id: i64) -> Result<Vec<CaptureGapRecord>> {
+        let records = sqlx::query_as::<_, CaptureGapRecord>(
+            r#"
+            SELECT * FROM capture_gaps WHERE session_id = $1 ORDER BY gap_start
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn save_frame_selection(&self, record: &FrameSelectionRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO frame_selections (session_id, total_frames, frames, created_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (session_id) DO UPDATE SET
+                total_frames = EXCLUDED.total_frames,
+                frames = EXCLUDED.frames,
+                created_at = EXCLUDED.created_at
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(record.total_frames)
+        .bind(&record.frames)
+        .bind(record.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_frame_selection(&self, session_id: i64) -> Result<Option<FrameSelectionRecord>> {
+        let result = sqlx::query_as::<_, FrameSelectionRecord>(
+            r#"
+            SELECT * FROM frame_selections WHERE session_id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_session_extra_fields(&self, record: &SessionExtraFieldsRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO session_extra_fields (session_id, fields, created_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (session_id) DO UPDATE SET
+                fields = EXCLUDED.fields,
+                created_at = EXCLUDED.created_at
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(&record.fields)
+        .bind(record.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_session_extra_fields(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionExtraFieldsRecord>> {
+        let result = sqlx::query_as::<_, SessionExtraFieldsRecord>(
+            r#"
+            SELECT * FROM session_extra_fields WHERE session_id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_session_analysis_mode(&self, record: &SessionAnalysisModeRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO session_analysis_modes (session_id, mode, updated_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (session_id) DO UPDATE SET
+                mode = EXCLUDED.mode,
+                updated_at = EXCLUDED.updated_at
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(&record.mode)
+        .bind(record.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_session_analysis_mode(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionAnalysisModeRecord>> {
+        let result = sqlx::query_as::<_, SessionAnalysisModeRecord>(
+            r#"
+            SELECT * FROM session_analysis_modes WHERE session_id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_session_scores(&self, record: &SessionScoresRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO session_scores (
+                session_id, productivity_score, focus_score, confidence, low_confidence, updated_at
+            ) VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (session_id) DO UPDATE SET
+                productivity_score = EXCLUDED.productivity_score,
+                focus_score = EXCLUDED.focus_score,
+                confidence = EXCLUDED.confidence,
+                low_confidence = EXCLUDED.low_confidence,
+                updated_at = EXCLUDED.updated_at
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(record.productivity_score)
+        .bind(record.focus_score)
+        .bind(record.confidence)
+        .bind(record.low_confidence)
+        .bind(record.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn save_analysis_failure(&self, record: &AnalysisFailureRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO analysis_failures (
+                session_id, last_error, attempts, first_failed_at, last_failed_at, dead_lettered_at
+            ) VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (session_id) DO UPDATE SET
+                last_error = EXCLUDED.last_error,
+                attempts = EXCLUDED.attempts,
+                first_failed_at = EXCLUDED.first_failed_at,
+                last_failed_at = EXCLUDED.last_failed_at,
+                dead_lettered_at = EXCLUDED.dead_lettered_at
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(&record.last_error)
+        .bind(record.attempts)
+        .bind(record.first_failed_at)
+        .bind(record.last_failed_at)
+        .bind(record.dead_lettered_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_analysis_failure(&self, session_id: i64) -> Result<Option<AnalysisFailureRecord>> {
+        let result = sqlx::query_as::<_, AnalysisFailureRecord>(
+            r#"
+            SELECT * FROM analysis_failures WHERE session_id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn list_analysis_failures(&self) -> Result<Vec<AnalysisFailureRecord>> {
+        let records = sqlx::query_as::<_, AnalysisFailureRecord>(
+            r#"
+            SELECT * FROM analysis_failures ORDER BY last_failed_at DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn delete_analysis_failure(&self, session_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM analysis_failures WHERE session_id = $1")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn save_analysis_queue_entry(&self, record: &AnalysisQueueRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO analysis_queue (
+                session_key, frame_count, window_start, window_end, priority, attempts, in_flight,
+                temp_session_id, enqueued_at
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (session_key) DO UPDATE SET
+                frame_count = EXCLUDED.frame_count,
+                window_start = EXCLUDED.window_start,
+                window_end = EXCLUDED.window_end,
+                priority = EXCLUDED.priority,
+                attempts = EXCLUDED.attempts,
+                in_flight = EXCLUDED.in_flight,
+                temp_session_id = EXCLUDED.temp_session_id,
+                enqueued_at = EXCLUDED.enqueued_at
+            "#,
+        )
+        .bind(record.session_key)
+        .bind(record.frame_count)
+        .bind(record.window_start)
+        .bind(record.window_end)
+        .bind(&record.priority)
+        .bind(record.attempts)
+        .bind(record.in_flight)
+        .bind(record.temp_session_id)
+        .bind(record.enqueued_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_analysis_queue_entry(
+        &self,
+        session_key: i64,
+    ) -> Result<Option<AnalysisQueueRecord>> {
+        let result = sqlx::query_as::<_, AnalysisQueueRecord>(
+            r#"
+            SELECT * FROM analysis_queue WHERE session_key = $1
+            "#,
+        )
+        .bind(session_key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn list_analysis_queue(&self) -> Result<Vec<AnalysisQueueRecord>> {
+        let records = sqlx::query_as::<_, AnalysisQueueRecord>(
+            r#"
+            SELECT * FROM analysis_queue ORDER BY enqueued_at, session_key
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn delete_analysis_queue_entry(&self, session_key: i64) -> Result<()> {
+        sqlx::query("DELETE FROM analysis_queue WHERE session_key = $1")
+            .bind(session_key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn replace_deep_work_stretches(
+        &self,
+        session_id: i64,
+        records: &[DeepWorkRecord],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM deep_work_stretches WHERE session_id = $1")
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+        for record in records {
+            sqlx::query(
+                r#"
+                INSERT INTO deep_work_stretches (session_id, start_time, end_time, app, duration_secs, focus_score, interruptions, created_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                "#,
+            )
+            .bind(session_id)
+            .bind(record.start_time)
+            .bind(record.end_time)
+            .bind(&record.app)
+            .bind(record.duration_secs)
+            .bind(record.focus_score)
+            .bind(record.interruptions)
+            .bind(record.created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_deep_work_stretches(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DeepWorkRecord>> {
+        let records = sqlx::query_as::<_, DeepWorkRecord>(
+            r#"
+            SELECT * FROM deep_work_stretches
+            WHERE start_time < $1 AND end_time > $2
+            ORDER BY start_time
+            "#,
+        )
+        .bind(end)
+        .bind(start)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn save_summary_correction(&self, record: &SummaryCorrectionRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO summary_corrections
+                (session_id, description, corrected, embedding, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (session_id) DO UPDATE SET
+                description = EXCLUDED.description,
+                corrected = EXCLUDED.corrected,
+                embedding = EXCLUDED.embedding,
+                created_at = EXCLUDED.created_at
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(&record.description)
+        .bind(&record.corrected)
+        .bind(&record.embedding)
+        .bind(record.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_summary_correction(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SummaryCorrectionRecord>> {
+        let result = sqlx::query_as::<_, SummaryCorrectionRecord>(
+            r#"
+            SELECT * FROM summary_corrections WHERE session_id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn get_recent_summary_corrections(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<SummaryCorrectionRecord>> {
+        let records = sqlx::query_as::<_, SummaryCorrectionRecord>(
+            r#"
+            SELECT * FROM summary_corrections ORDER BY created_at DESC LIMIT $1
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    fn db_type(&self) -> &str {
+        "postgres"
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+
+    async fn migrate_timezone_to_local(&self) -> Result<(u64, u64, u64, u64, u64, u64)> {
+        use chrono::Local;
+
+        // 计算时区偏移量（小时）
+        let local_offset = Local::now().offset().local_minus_utc() / 3600;
+
+        info!(
+            "开始时区迁移：将 UTC 时间转换为本地时间（偏移 {} 小时）",
+            local_offset
+        );
+
+        let shift = |column: &str| format!("{column} = {column} + make_interval(hours => $1)");
+
+        let sessions_updated = sqlx::query(&format!(
+            "UPDATE sessions SET {}, {}, {}",
+            shift("start_time"),
+            shift("end_time"),
+            shift("created_at")
+        ))
+        .bind(local_offset)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        let frames_updated = sqlx::query(&format!("UPDATE frames SET {}", shift("timestamp")))
+            .bind(local_offset)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        let llm_calls_updated =
+            sqlx::query(&format!("UPDATE llm_calls SET {}", shift("created_at")))
+                .bind(local_offset)
+                .execute(&self.pool)
+                .await?
+                .rows_affected();
+
+        let video_segments_updated = sqlx::query(&format!(
+            "UPDATE video_segments SET {}",
+            shift("created_at")
+        ))
+        .bind(local_offset)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        let timeline_cards_updated = sqlx::query(&format!(
+            "UPDATE timeline_cards SET {}",
+            shift("created_at")
+        ))
+        .bind(local_offset)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        let day_summaries_updated = sqlx::query(&format!(
+            "UPDATE day_summaries SET {}, {}",
+            shift("created_at"),
+            shift("updated_at")
+        ))
+        .bind(local_offset)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        info!(
+            "时区迁移完成：sessions={}, frames={}, llm_calls={}, video_segments={}, timeline_cards={}, day_summaries={}",
+            sessions_updated, frames_updated, llm_calls_updated,
+            video_segments_updated, timeline_cards_updated, day_summaries_updated
+        );
+
+        Ok((
+            sessions_updated,
+            frames_updated,
+            llm_calls_updated,
+            video_segments_updated,
+            timeline_cards_updated,
+            day_summaries_updated,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day_range_covers_whole_days() {
+        let (start, end) = day_range("2025-01-01", "2025-01-03").unwrap();
+        assert_eq!(start.to_rfc3339(), "2025-01-01T00:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2025-01-04T00:00:00+00:00");
+        assert!(day_range("2025/01/01", "2025-01-03").is_err());
+    }
+
+    /// 连接 DATABASE_URL 指定的 PostgreSQL；未设置或不是 postgres 地址时跳过
+    async fn test_repository() -> Option<PostgresRepository> {
+        let url = std::env::var("DATABASE_URL").ok()?;
+        if !url.starts_with("postgres") {
+            return None;
+        }
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&url)
+            .await
+            .expect("连接 DATABASE_URL 失败");
+        Some(PostgresRepository { pool })
+    }
+
+    #[tokio::test]
+    async fn test_schema_and_frame_prune_round_trip() {
+        let Some(repo) = test_repository().await else {
+            eprintln!("未设置 PostgreSQL DATABASE_URL，跳过");
+            return;
+        };
+        // 建表可重复执行
+        repo.initialize_tables().await.unwrap();
+        repo.initialize_tables().await.unwrap();
+        assert!(repo.check_tables_exist().await.unwrap());
+
+        let start = chrono::TimeZone::with_ymd_and_hms(&Utc, 2025, 1, 1, 9, 0, 0).unwrap();
+        let session_id = repo
+            .insert_session(&Session {
+                id: None,
+                start_time: start,
+                end_time: start + Duration::minutes(15),
+                title: "编写代码".to_string(),
+                summary: "在编辑器中修改代码".to_string(),
+                video_path: None,
+                tags: "[]".to_string(),
+                created_at: None,
+                device_name: None,
+                device_type: None,
+                pinned: false,
+            })
+            .await
+            .unwrap();
+        let frames: Vec<Frame> = (0..3)
+            .map(|i| Frame {
+                id: None,
+                session_id,
+                timestamp: start + Duration::minutes(i),
+                file_path: format!("frame_{i}.jpg"),
+            })
+            .collect();
+        repo.insert_frames(&frames).await.unwrap();
+
+        let stored = repo.get_frames_by_session(session_id).await.unwrap();
+        assert_eq!(stored.len(), 3);
+        assert_eq!(stored[0].timestamp, start);
+        assert_eq!(stored[2].file_path, "frame_2.jpg");
+
+        // 清理帧与写入清理记录在同一事务中完成
+        let kept = stored[1].id.unwrap();
+        repo.save_frame_prune(
+            &FramePruneRecord {
+                session_id,
+                deleted_count: 2,
+                kept_count: 1,
+                pruned_at: start,
+            },
+            &[kept],
+        )
+        .await
+        .unwrap();
+        let remaining = repo.get_frames_by_session(session_id).await.unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].id, Some(kept));
+        let prune = repo.get_frame_prune(session_id).await.unwrap().unwrap();
+        assert_eq!(prune.deleted_count, 2);
+
+        // 保留列表为空时删除全部帧
+        repo.save_frame_prune(
+            &FramePruneRecord {
+                session_id,
+                deleted_count: 1,
+                kept_count: 0,
+                pruned_at: start,
+            },
+            &[],
+        )
+        .await
+        .unwrap();
+        assert!(repo
+            .get_frames_by_session(session_id)
+            .await
+            .unwrap()
+            .is_empty());
+
+        repo.save_session_analysis(&SessionAnalysisRecord {
+            session_id,
+            served_by: Some("qwen".to_string()),
+            sampling_seed: Some(-42),
+            created_at: start,
+        })
+        .await
+        .unwrap();
+        let analysis = repo
+            .get_session_analysis(session_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(analysis.sampling_seed, Some(-42));
+
+        repo.delete_session(session_id).await.unwrap();
+        assert!(repo.get_frame_prune(session_id).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,528 @@
+// 表结构定义 - 各数据库后端共用同一份 DDL
+//
+// 表定义中的 {id}、{bigint} 等占位符按方言替换为具体类型，新增表或列只需修改这里
+
+/// 数据库方言
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Sqlite,
+    MariaDb,
+    Postgres,
+}
+
+impl Dialect {
+    /// 占位符在该方言下对应的类型
+    fn types(self) -> [(&'static str, &'static str); 8] {
+        match self {
+            Dialect::Sqlite => [
+                ("{id}", "INTEGER PRIMARY KEY AUTOINCREMENT"),
+                ("{bigint}", "INTEGER"),
+                ("{datetime}", "DATETIME"),
+                ("{created_at}", "DATETIME DEFAULT CURRENT_TIMESTAMP"),
+                ("{updated_at}", "DATETIME DEFAULT CURRENT_TIMESTAMP"),
+                ("{blob}", "BLOB"),
+                ("{long_text}", "TEXT"),
+                ("{double}", "REAL"),
+            ],
+            Dialect::MariaDb => [
+                ("{id}", "BIGINT PRIMARY KEY AUTO_INCREMENT"),
+                ("{bigint}", "BIGINT"),
+                ("{datetime}", "DATETIME"),
+                ("{created_at}", "DATETIME DEFAULT CURRENT_TIMESTAMP"),
+                (
+                    "{updated_at}",
+                    "DATETIME DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP",
+                ),
+                ("{blob}", "MEDIUMBLOB"),
+                ("{long_text}", "LONGTEXT"),
+                ("{double}", "DOUBLE"),
+            ],
+            // 时间列按 UTC 类型存储本地时间，默认值同样取本地时间
+            Dialect::Postgres => [
+                ("{id}", "BIGSERIAL PRIMARY KEY"),
+                ("{bigint}", "BIGINT"),
+                ("{datetime}", "TIMESTAMPTZ"),
+                (
+                    "{created_at}",
+                    "TIMESTAMPTZ DEFAULT (LOCALTIMESTAMP AT TIME ZONE 'UTC')",
+                ),
+                (
+                    "{updated_at}",
+                    "TIMESTAMPTZ DEFAULT (LOCALTIMESTAMP AT TIME ZONE 'UTC')",
+                ),
+                ("{blob}", "BYTEA"),
+                ("{long_text}", "TEXT"),
+                ("{double}", "DOUBLE PRECISION"),
+            ],
+        }
+    }
+
+    /// 将表定义中的占位符替换为该方言的类型
+    fn render(self, ddl: &str) -> String {
+        let mut sql = self
+            .types()
+            .iter()
+            .fold(ddl.to_string(), |sql, (placeholder, ty)| {
+                sql.replace(placeholder, ty)
+            });
+        // SQLite 的 VARCHAR 只是 TEXT 的别名，保持与历史表结构一致
+        if self == Dialect::Sqlite {
+            while let Some(start) = sql.find("VARCHAR(") {
+                let end = sql[start..].find(')').map_or(sql.len(), |i| start + i + 1);
+                sql.replace_range(start..end, "TEXT");
+            }
+        }
+        sql
+    }
+}
+
+/// 所有后端共用的表（按外键依赖顺序排列）
+pub const TABLES: &[(&str, &str)] = &[
+    (
+        "sessions",
+        r#"
+        CREATE TABLE IF NOT EXISTS sessions (
+            id {id},
+            start_time {datetime} NOT NULL,
+            end_time {datetime} NOT NULL,
+            title TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            video_path TEXT,
+            tags TEXT NOT NULL,
+            created_at {created_at},
+            device_name VARCHAR(255),
+            device_type VARCHAR(50),
+            pinned BOOLEAN NOT NULL DEFAULT FALSE
+        )
+        "#,
+    ),
+    (
+        "frames",
+        r#"
+        CREATE TABLE IF NOT EXISTS frames (
+            id {id},
+            session_id {bigint} NOT NULL,
+            timestamp {datetime} NOT NULL,
+            file_path TEXT NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+    (
+        "llm_calls",
+        r#"
+        CREATE TABLE IF NOT EXISTS llm_calls (
+            id {id},
+            session_id {bigint},
+            provider VARCHAR(100) NOT NULL,
+            model VARCHAR(100) NOT NULL,
+            call_type VARCHAR(100) NOT NULL,
+            request_headers TEXT NOT NULL,
+            request_body TEXT NOT NULL,
+            response_headers TEXT,
+            response_body TEXT,
+            status_code INTEGER,
+            error_message TEXT,
+            latency_ms {bigint},
+            token_usage TEXT,
+            created_at {created_at},
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+    (
+        "video_segments",
+        r#"
+        CREATE TABLE IF NOT EXISTS video_segments (
+            id {id},
+            session_id {bigint} NOT NULL,
+            llm_call_id {bigint},
+            start_timestamp VARCHAR(50) NOT NULL,
+            end_timestamp VARCHAR(50) NOT NULL,
+            description TEXT NOT NULL,
+            created_at {created_at},
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+            FOREIGN KEY (llm_call_id) REFERENCES llm_calls(id) ON DELETE SET NULL
+        )
+        "#,
+    ),
+    (
+        "timeline_cards",
+        r#"
+        CREATE TABLE IF NOT EXISTS timeline_cards (
+            id {id},
+            session_id {bigint} NOT NULL,
+            llm_call_id {bigint},
+            start_time VARCHAR(50) NOT NULL,
+            end_time VARCHAR(50) NOT NULL,
+            category VARCHAR(100) NOT NULL,
+            subcategory VARCHAR(100) NOT NULL,
+            title TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            detailed_summary TEXT NOT NULL,
+            distractions TEXT,
+            app_sites TEXT NOT NULL,
+            video_preview_path TEXT,
+            created_at {created_at},
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+            FOREIGN KEY (llm_call_id) REFERENCES llm_calls(id) ON DELETE SET NULL
+        )
+        "#,
+    ),
+    (
+        "day_summaries",
+        r#"
+        CREATE TABLE IF NOT EXISTS day_summaries (
+            id {id},
+            date DATE NOT NULL UNIQUE,
+            summary_text TEXT NOT NULL,
+            device_stats TEXT NOT NULL,
+            parallel_work TEXT NOT NULL,
+            usage_patterns TEXT NOT NULL,
+            active_device_count INTEGER NOT NULL,
+            llm_call_id {bigint},
+            created_at {created_at},
+            updated_at {updated_at},
+            FOREIGN KEY (llm_call_id) REFERENCES llm_calls(id) ON DELETE SET NULL
+        )
+        "#,
+    ),
+    (
+        "session_analyses",
+        r#"
+        CREATE TABLE IF NOT EXISTS session_analyses (
+            session_id {bigint} PRIMARY KEY,
+            served_by VARCHAR(64),
+            sampling_seed {bigint},
+            created_at {datetime} NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+    (
+        "frame_prunes",
+        r#"
+        CREATE TABLE IF NOT EXISTS frame_prunes (
+            session_id {bigint} PRIMARY KEY,
+            deleted_count {bigint} NOT NULL,
+            kept_count {bigint} NOT NULL,
+            pruned_at {datetime} NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+    (
+        "session_thumbnails",
+        r#"
+        CREATE TABLE IF NOT EXISTS session_thumbnails (
+            session_id {bigint} PRIMARY KEY,
+            frame_timestamp {datetime} NOT NULL,
+            width {bigint} NOT NULL,
+            height {bigint} NOT NULL,
+            image_data {blob} NOT NULL,
+            created_at {datetime} NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+    (
+        // 合并后的会话删除时一并删除
+        "session_merges",
+        r#"
+        CREATE TABLE IF NOT EXISTS session_merges (
+            id {id},
+            merged_session_id {bigint} NOT NULL,
+            original_session_id {bigint} NOT NULL,
+            original_session {long_text} NOT NULL,
+            frame_count {bigint} NOT NULL,
+            merged_at {datetime} NOT NULL,
+            FOREIGN KEY (merged_session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+    (
+        "capture_gaps",
+        r#"
+        CREATE TABLE IF NOT EXISTS capture_gaps (
+            id {id},
+            session_id {bigint} NOT NULL,
+            gap_start {datetime} NOT NULL,
+            gap_end {datetime} NOT NULL,
+            restart_attempts {bigint} NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+    (
+        "frame_selections",
+        r#"
+        CREATE TABLE IF NOT EXISTS frame_selections (
+            session_id {bigint} PRIMARY KEY,
+            total_frames {bigint} NOT NULL,
+            frames {long_text} NOT NULL,
+            created_at {datetime} NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+    (
+        // 不随会话删除，更正作为长期的少样本示例
+        "summary_corrections",
+        r#"
+        CREATE TABLE IF NOT EXISTS summary_corrections (
+            session_id {bigint} PRIMARY KEY,
+            description TEXT NOT NULL,
+            corrected TEXT NOT NULL,
+            embedding {long_text} NOT NULL,
+            created_at {datetime} NOT NULL
+        )
+        "#,
+    ),
+    (
+        "session_extra_fields",
+        r#"
+        CREATE TABLE IF NOT EXISTS session_extra_fields (
+            session_id {bigint} PRIMARY KEY,
+            fields {long_text} NOT NULL,
+            created_at {datetime} NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+    (
+        "session_analysis_modes",
+        r#"
+        CREATE TABLE IF NOT EXISTS session_analysis_modes (
+            session_id {bigint} PRIMARY KEY,
+            mode VARCHAR(64) NOT NULL,
+            updated_at {datetime} NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+    (
+        // 按评分筛选会话
+        "session_scores",
+        r#"
+        CREATE TABLE IF NOT EXISTS session_scores (
+            session_id {bigint} PRIMARY KEY,
+            productivity_score {double},
+            focus_score {double},
+            updated_at {datetime} NOT NULL,
+            confidence {double},
+            low_confidence BOOLEAN NOT NULL DEFAULT FALSE,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+    (
+        // 分析失败记录（死信队列）
+        "analysis_failures",
+        r#"
+        CREATE TABLE IF NOT EXISTS analysis_failures (
+            session_id {bigint} PRIMARY KEY,
+            last_error TEXT NOT NULL,
+            attempts {bigint} NOT NULL,
+            first_failed_at {datetime} NOT NULL,
+            last_failed_at {datetime} NOT NULL,
+            dead_lettered_at {datetime},
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+    (
+        // 条目以调度器的会话键标识，会话记录在分析完成后才生成
+        "analysis_queue",
+        r#"
+        CREATE TABLE IF NOT EXISTS analysis_queue (
+            session_key {bigint} PRIMARY KEY,
+            frame_count {bigint} NOT NULL,
+            window_start {datetime} NOT NULL,
+            window_end {datetime} NOT NULL,
+            priority VARCHAR(32) NOT NULL,
+            attempts {bigint} NOT NULL,
+            in_flight BOOLEAN NOT NULL DEFAULT FALSE,
+            temp_session_id {bigint},
+            enqueued_at {datetime} NOT NULL
+        )
+        "#,
+    ),
+    (
+        "deep_work_stretches",
+        r#"
+        CREATE TABLE IF NOT EXISTS deep_work_stretches (
+            id {id},
+            session_id {bigint} NOT NULL,
+            start_time {datetime} NOT NULL,
+            end_time {datetime} NOT NULL,
+            app TEXT NOT NULL,
+            duration_secs {bigint} NOT NULL,
+            focus_score {double} NOT NULL,
+            interruptions {bigint} NOT NULL,
+            created_at {datetime} NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+        )
+        "#,
+    ),
+];
+
+/// 建表之后新增的列：(表名, 列名, 列定义)，初始化时为旧数据库补齐；新建的表已在 TABLES 中包含这些列
+pub const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("sessions", "pinned", "BOOLEAN NOT NULL DEFAULT FALSE"),
+    ("session_scores", "confidence", "{double}"),
+    (
+        "session_scores",
+        "low_confidence",
+        "BOOLEAN NOT NULL DEFAULT FALSE",
+    ),
+    ("session_analyses", "served_by", "VARCHAR(64)"),
+];
+
+/// 索引：(索引名, 表及列)
+pub const INDEXES: &[(&str, &str)] = &[
+    ("idx_sessions_start_time", "sessions(start_time)"),
+    ("idx_sessions_start_end", "sessions(start_time, end_time)"),
+    ("idx_frames_session_id", "frames(session_id)"),
+    (
+        "idx_frames_session_timestamp",
+        "frames(session_id, timestamp)",
+    ),
+    ("idx_llm_calls_session_id", "llm_calls(session_id)"),
+    ("idx_llm_calls_created_at", "llm_calls(created_at)"),
+    (
+        "idx_video_segments_session_id",
+        "video_segments(session_id)",
+    ),
+    (
+        "idx_timeline_cards_session_id",
+        "timeline_cards(session_id)",
+    ),
+    (
+        "idx_session_scores_productivity",
+        "session_scores(productivity_score)",
+    ),
+    ("idx_session_scores_focus", "session_scores(focus_score)"),
+];
+
+/// 保存单个会话数据的表及关联列，彻底删除会话时按顺序逐表清理（sessions 最后删除）
+///
+/// 新增与会话关联的表时须加入此列表；day_summaries 按日期汇总，不属于单个会话
+pub const SESSION_SCOPED_TABLES: &[(&str, &str)] = &[
+    ("frames", "session_id"),
+    ("video_segments", "session_id"),
+    ("timeline_cards", "session_id"),
+    ("llm_calls", "session_id"),
+    ("frame_prunes", "session_id"),
+    ("session_thumbnails", "session_id"),
+    ("session_merges", "merged_session_id"),
+    ("session_merges", "original_session_id"),
+    ("capture_gaps", "session_id"),
+    ("frame_selections", "session_id"),
+    ("summary_corrections", "session_id"),
+    ("session_extra_fields", "session_id"),
+    ("session_analysis_modes", "session_id"),
+    ("session_scores", "session_id"),
+    ("analysis_failures", "session_id"),
+    ("analysis_queue", "temp_session_id"),
+    ("deep_work_stretches", "session_id"),
+    ("session_analyses", "session_id"),
+    ("sessions", "id"),
+];
+
+/// 所有表名（服务器数据库据此判断是否需要初始化表结构）
+pub fn table_names() -> impl Iterator<Item = &'static str> {
+    TABLES.iter().map(|(name, _)| *name)
+}
+
+/// 建表语句
+pub fn create_table_statements(dialect: Dialect) -> Vec<String> {
+    TABLES.iter().map(|(_, ddl)| dialect.render(ddl)).collect()
+}
+
+/// 补齐新增列的语句：(表名, 列名, ALTER 语句)
+///
+/// 只有 PostgreSQL 支持 ADD COLUMN IF NOT EXISTS，其余方言由调用方先检查列是否存在
+pub fn add_column_statements(dialect: Dialect) -> Vec<(&'static str, &'static str, String)> {
+    let if_not_exists = match dialect {
+        Dialect::Postgres => "IF NOT EXISTS ",
+        Dialect::Sqlite | Dialect::MariaDb => "",
+    };
+    ADDED_COLUMNS
+        .iter()
+        .map(|(table, column, definition)| {
+            let sql = format!(
+                "ALTER TABLE {table} ADD COLUMN {if_not_exists}{column} {}",
+                dialect.render(definition)
+            );
+            (*table, *column, sql)
+        })
+        .collect()
+}
+
+/// 建索引语句（MySQL 不支持 CREATE INDEX IF NOT EXISTS，调用方需忽略已存在错误）
+pub fn create_index_statements(dialect: Dialect) -> Vec<String> {
+    let if_not_exists = match dialect {
+        Dialect::MariaDb => "",
+        Dialect::Sqlite | Dialect::Postgres => "IF NOT EXISTS ",
+    };
+    INDEXES
+        .iter()
+        .map(|(name, on)| format!("CREATE INDEX {if_not_exists}{name} ON {on}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statements_render_for_every_dialect() {
+        for dialect in [Dialect::Sqlite, Dialect::MariaDb, Dialect::Postgres] {
+            let statements = create_table_statements(dialect);
+            assert_eq!(statements.len(), TABLES.len());
+            for sql in &statements {
+                assert!(!sql.contains('{'), "{dialect:?} 未替换的占位符: {sql}");
+            }
+        }
+
+        let sqlite = create_table_statements(Dialect::Sqlite);
+        assert!(sqlite[0].contains("id INTEGER PRIMARY KEY AUTOINCREMENT"));
+        assert!(sqlite[0].contains("device_name TEXT,"));
+        assert!(sqlite[0].contains("pinned BOOLEAN NOT NULL DEFAULT FALSE"));
+        assert!(!sqlite.iter().any(|sql| sql.contains("VARCHAR")));
+
+        let postgres = create_table_statements(Dialect::Postgres);
+        assert!(postgres[0].contains("id BIGSERIAL PRIMARY KEY"));
+        assert!(postgres[1].contains("timestamp TIMESTAMPTZ NOT NULL"));
+
+        assert_eq!(
+            add_column_statements(Dialect::Postgres)[0].2,
+            "ALTER TABLE sessions ADD COLUMN IF NOT EXISTS pinned BOOLEAN NOT NULL DEFAULT FALSE"
+        );
+        assert_eq!(
+            add_column_statements(Dialect::Sqlite)[0].2,
+            "ALTER TABLE sessions ADD COLUMN pinned BOOLEAN NOT NULL DEFAULT FALSE"
+        );
+
+        assert_eq!(
+            create_index_statements(Dialect::MariaDb)[0],
+            "CREATE INDEX idx_sessions_start_time ON sessions(start_time)"
+        );
+        assert_eq!(
+            create_index_statements(Dialect::Sqlite)[0],
+            "CREATE INDEX IF NOT EXISTS idx_sessions_start_time ON sessions(start_time)"
+        );
+    }
+
+    #[test]
+    fn test_every_session_table_is_purged() {
+        // 除按日期汇总的表外，每张表都须在彻底删除会话时清理
+        for table in table_names().filter(|name| *name != "day_summaries") {
+            assert!(
+                SESSION_SCOPED_TABLES.iter().any(|(name, _)| *name == table),
+                "{table} 未加入 SESSION_SCOPED_TABLES"
+            );
+        }
+        assert_eq!(SESSION_SCOPED_TABLES.last(), Some(&("sessions", "id")));
+    }
+}
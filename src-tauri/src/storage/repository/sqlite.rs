@@ -1,13 +1,16 @@
 // SQLite 数据库实现
 
-use super::DatabaseRepository;
+use super::schema::{self, Dialect, SESSION_SCOPED_TABLES};
+use super::{DatabaseRepository, PreparedFilter};
 use crate::storage::config::get_device_info;
 use crate::storage::models::*;
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use sqlx::sqlite::{SqlitePool, SqlitePoolOptions};
+use sqlx::sqlite::{Sqlite, SqlitePool, SqlitePoolOptions};
+use sqlx::QueryBuilder;
 use sqlx::Row;
+use std::collections::BTreeMap;
 use tracing::info;
 
 /// SQLite 数据库实现
@@ -49,6 +52,63 @@ impl SqliteRepository {
     }
 }
 
+/// 按条件查询会话时选取的列（与 Session 的字段对应）
+const SESSION_COLUMNS: &str = "SELECT sessions.id, sessions.start_time, sessions.end_time, \
+    sessions.title, sessions.summary, sessions.video_path, sessions.tags, sessions.created_at, \
+    sessions.device_name, sessions.device_type, sessions.pinned";
+
+/// 追加筛选条件对应的 FROM 与 WHERE 子句（计数与分页查询共用）
+fn push_session_filter(builder: &mut QueryBuilder<'_, Sqlite>, filter: &PreparedFilter) {
+    builder.push(
+        " FROM sessions LEFT JOIN session_scores ON session_scores.session_id = sessions.id \
+         WHERE 1 = 1",
+    );
+    if let Some(start) = filter.start {
+        builder
+            .push(" AND sessions.start_time >= ")
+            .push_bind(start);
+    }
+    if let Some(end) = filter.end {
+        builder.push(" AND sessions.start_time < ").push_bind(end);
+    }
+    // 标签为 JSON 数组，格式异常的旧数据按没有标签处理
+    if !filter.categories.is_empty() {
+        builder.push(
+            " AND EXISTS (SELECT 1 FROM json_each(CASE WHEN json_valid(sessions.tags) \
+             THEN sessions.tags ELSE '[]' END) AS tag \
+             WHERE json_extract(tag.value, '$.category') IN (",
+        );
+        let mut values = builder.separated(", ");
+        for category in &filter.categories {
+            values.push_bind(category.clone());
+        }
+        builder.push("))");
+    }
+    if !filter.keywords.is_empty() {
+        builder.push(
+            " AND EXISTS (SELECT 1 FROM json_each(CASE WHEN json_valid(sessions.tags) \
+             THEN sessions.tags ELSE '[]' END) AS tag, \
+             json_each(tag.value, '$.keywords') AS keyword \
+             WHERE LOWER(keyword.value) IN (",
+        );
+        let mut values = builder.separated(", ");
+        for keyword in &filter.keywords {
+            values.push_bind(keyword.clone());
+        }
+        builder.push("))");
+    }
+    for (column, op, value) in &filter.score_bounds {
+        builder
+            .push(format!(" AND session_scores.{} {} ", column, op))
+            .push_bind(*value);
+    }
+    if let Some(low_confidence) = filter.low_confidence {
+        builder
+            .push(" AND COALESCE(session_scores.low_confidence, 0) = ")
+            .push_bind(low_confidence);
+    }
+}
+
 #[async_trait]
 impl DatabaseRepository for SqliteRepository {
     // ========== 会话操作 ==========
@@ -56,8 +116,8 @@ impl DatabaseRepository for SqliteRepository {
     async fn insert_session(&self, session: &Session) -> Result<i64> {
         let result = sqlx::query(
             r#"
-            INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type, pinned)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
         "#,
         )
         .bind(&session.start_time)
@@ -68,6 +128,7 @@ impl DatabaseRepository for SqliteRepository {
         .bind(&session.tags)
         .bind(&session.device_name)
         .bind(&session.device_type)
+        .bind(session.pinned)
         .execute(&self.pool)
         .await?;
 
@@ -81,8 +142,8 @@ impl DatabaseRepository for SqliteRepository {
         for session in sessions {
             let result = sqlx::query(
                 r#"
-                INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type, pinned)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
             "#,
             )
             .bind(&session.start_time)
@@ -93,6 +154,7 @@ impl DatabaseRepository for SqliteRepository {
             .bind(&session.tags)
             .bind(&session.device_name)
             .bind(&session.device_type)
+            .bind(session.pinned)
             .execute(&mut *tx)
             .await?;
 
@@ -107,7 +169,7 @@ impl DatabaseRepository for SqliteRepository {
         let session = sqlx::query_as::<_, Session>(
             r#"
             SELECT id, start_time, end_time, title, summary,
-                   video_path, tags, created_at, device_name, device_type
+                   video_path, tags, created_at, device_name, device_type, pinned
             FROM sessions
             WHERE id = ?
             "#,
@@ -136,7 +198,7 @@ impl DatabaseRepository for SqliteRepository {
         let sessions = sqlx::query_as::<_, Session>(
             r#"
             SELECT id, start_time, end_time, title, summary,
-                   video_path, tags, created_at, device_name, device_type
+                   video_path, tags, created_at, device_name, device_type, pinned
             FROM sessions
             WHERE DATE(start_time) = ?
             ORDER BY start_time DESC
@@ -149,11 +211,33 @@ impl DatabaseRepository for SqliteRepository {
         Ok(sessions)
     }
 
+    async fn get_sessions_overlapping(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT id, start_time, end_time, title, summary,
+                   video_path, tags, created_at, device_name, device_type, pinned
+            FROM sessions
+            WHERE start_time < ? AND end_time >= ?
+            ORDER BY start_time
+            "#,
+        )
+        .bind(end)
+        .bind(start)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
     async fn get_all_sessions(&self) -> Result<Vec<Session>> {
         let sessions = sqlx::query_as::<_, Session>(
             r#"
             SELECT id, start_time, end_time, title, summary,
-                   video_path, tags, created_at, device_name, device_type
+                   video_path, tags, created_at, device_name, device_type, pinned
             FROM sessions
             ORDER BY start_time
             "#,
@@ -239,11 +323,46 @@ impl DatabaseRepository for SqliteRepository {
         Ok(())
     }
 
+    async fn purge_session(&self, session_id: i64) -> Result<BTreeMap<String, u64>> {
+        let mut tx = self.pool.begin().await?;
+        let mut removed = BTreeMap::new();
+        for (table, column) in SESSION_SCOPED_TABLES {
+            let sql = format!("DELETE FROM {} WHERE {} = ?", table, column);
+            let result = sqlx::query(&sql).bind(session_id).execute(&mut *tx).await?;
+            if result.rows_affected() > 0 {
+                *removed.entry(table.to_string()).or_default() += result.rows_affected();
+            }
+        }
+        // 未提交的事务在返回时回滚
+        if !removed.contains_key("sessions") {
+            return Err(anyhow::anyhow!("会话不存在: {}", session_id));
+        }
+        tx.commit().await?;
+
+        info!("彻底删除会话 {}: {:?}", session_id, removed);
+        Ok(removed)
+    }
+
+    async fn count_session_references(&self, session_id: i64) -> Result<BTreeMap<String, u64>> {
+        let mut counts = BTreeMap::new();
+        for (table, column) in SESSION_SCOPED_TABLES {
+            let sql = format!("SELECT COUNT(*) FROM {} WHERE {} = ?", table, column);
+            let count: i64 = sqlx::query_scalar(&sql)
+                .bind(session_id)
+                .fetch_one(&self.pool)
+                .await?;
+            if count > 0 {
+                *counts.entry(table.to_string()).or_default() += count as u64;
+            }
+        }
+        Ok(counts)
+    }
+
     async fn get_old_sessions(&self, cutoff_date: DateTime<Utc>) -> Result<Vec<Session>> {
         let sessions = sqlx::query_as::<_, Session>(
-            "SELECT id, start_time, end_time, title, summary, video_path, tags, created_at, device_name, device_type
+            "SELECT id, start_time, end_time, title, summary, video_path, tags, created_at, device_name, device_type, pinned
              FROM sessions
-             WHERE start_time < ?"
+             WHERE start_time < ? AND pinned = 0"
         )
         .bind(cutoff_date)
         .fetch_all(&self.pool)
@@ -253,7 +372,7 @@ impl DatabaseRepository for SqliteRepository {
     }
 
     async fn delete_old_sessions(&self, cutoff_date: DateTime<Utc>) -> Result<u64> {
-        let result = sqlx::query("DELETE FROM sessions WHERE start_time < ?")
+        let result = sqlx::query("DELETE FROM sessions WHERE start_time < ? AND pinned = 0")
             .bind(cutoff_date)
             .execute(&self.pool)
             .await?;
@@ -267,6 +386,62 @@ impl DatabaseRepository for SqliteRepository {
         Ok(deleted_count)
     }
 
+    async fn set_session_pinned(&self, session_id: i64, pinned: bool) -> Result<()> {
+        let result = sqlx::query("UPDATE sessions SET pinned = ? WHERE id = ?")
+            .bind(pinned)
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("会话不存在: {}", session_id));
+        }
+
+        info!("会话 {} 置顶状态: {}", session_id, pinned);
+        Ok(())
+    }
+
+    async fn list_pinned_sessions(&self) -> Result<Vec<Session>> {
+        let sessions = sqlx::query_as::<_, Session>(
+            r#"
+            SELECT id, start_time, end_time, title, summary,
+                   video_path, tags, created_at, device_name, device_type, pinned
+            FROM sessions
+            WHERE pinned = 1
+            ORDER BY start_time DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(sessions)
+    }
+
+    async fn query_sessions(&self, filter: &SessionFilter) -> Result<SessionPage> {
+        let prepared = PreparedFilter::new(filter)?;
+
+        let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*)");
+        push_session_filter(&mut count, &prepared);
+        let total: i64 = count.build_query_scalar().fetch_one(&self.pool).await?;
+
+        let mut page = QueryBuilder::<Sqlite>::new(SESSION_COLUMNS);
+        push_session_filter(&mut page, &prepared);
+        page.push(" ORDER BY sessions.start_time DESC, sessions.id DESC LIMIT ")
+            .push_bind(prepared.limit)
+            .push(" OFFSET ")
+            .push_bind(prepared.offset);
+        let sessions = page
+            .build_query_as::<Session>()
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(SessionPage {
+            sessions,
+            total,
+            offset: prepared.offset,
+            limit: prepared.limit,
+        })
+    }
+
     // ========== 帧操作 ==========
 
     async fn insert_frame(&self, frame: &Frame) -> Result<i64> {
@@ -683,161 +858,26 @@ impl DatabaseRepository for SqliteRepository {
     // ========== 数据库初始化 ==========
 
     async fn initialize_tables(&self) -> Result<()> {
-        // 创建会话表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS sessions (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                start_time DATETIME NOT NULL,
-                end_time DATETIME NOT NULL,
-                title TEXT NOT NULL,
-                summary TEXT NOT NULL,
-                video_path TEXT,
-                tags TEXT NOT NULL,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                device_name TEXT,
-                device_type TEXT
-            )
-        "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // 创建帧表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS frames (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                session_id INTEGER NOT NULL,
-                timestamp DATETIME NOT NULL,
-                file_path TEXT NOT NULL,
-                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
-            )
-        "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // 创建索引
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_sessions_start_time ON sessions(start_time)")
-            .execute(&self.pool)
-            .await?;
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_frames_session_id ON frames(session_id)")
-            .execute(&self.pool)
-            .await?;
-        sqlx::query(
-            "CREATE INDEX IF NOT EXISTS idx_sessions_start_end ON sessions(start_time, end_time)",
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_frames_session_timestamp ON frames(session_id, timestamp)")
-            .execute(&self.pool)
-            .await?;
-
-        // 创建LLM调用记录表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS llm_calls (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                session_id INTEGER,
-                provider TEXT NOT NULL,
-                model TEXT NOT NULL,
-                call_type TEXT NOT NULL,
-                request_headers TEXT NOT NULL,
-                request_body TEXT NOT NULL,
-                response_headers TEXT,
-                response_body TEXT,
-                status_code INTEGER,
-                error_message TEXT,
-                latency_ms INTEGER,
-                token_usage TEXT,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
-            )
-        "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // 创建视频分段表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS video_segments (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                session_id INTEGER NOT NULL,
-                llm_call_id INTEGER,
-                start_timestamp TEXT NOT NULL,
-                end_timestamp TEXT NOT NULL,
-                description TEXT NOT NULL,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
-                FOREIGN KEY (llm_call_id) REFERENCES llm_calls(id) ON DELETE SET NULL
-            )
-        "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // 创建时间线卡片表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS timeline_cards (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                session_id INTEGER NOT NULL,
-                llm_call_id INTEGER,
-                start_time TEXT NOT NULL,
-                end_time TEXT NOT NULL,
-                category TEXT NOT NULL,
-                subcategory TEXT NOT NULL,
-                title TEXT NOT NULL,
-                summary TEXT NOT NULL,
-                detailed_summary TEXT NOT NULL,
-                distractions TEXT,
-                app_sites TEXT NOT NULL,
-                video_preview_path TEXT,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
-                FOREIGN KEY (llm_call_id) REFERENCES llm_calls(id) ON DELETE SET NULL
-            )
-        "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // 创建每日总结表（缓存）
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS day_summaries (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                date DATE NOT NULL UNIQUE,
-                summary_text TEXT NOT NULL,
-                device_stats TEXT NOT NULL,
-                parallel_work TEXT NOT NULL,
-                usage_patterns TEXT NOT NULL,
-                active_device_count INTEGER NOT NULL,
-                llm_call_id INTEGER,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (llm_call_id) REFERENCES llm_calls(id) ON DELETE SET NULL
+        for statement in schema::create_table_statements(Dialect::Sqlite) {
+            sqlx::query(&statement).execute(&self.pool).await?;
+        }
+        // 数据库迁移: 为已存在的表补齐新增列
+        for (table, column, statement) in schema::add_column_statements(Dialect::Sqlite) {
+            let exists = sqlx::query_scalar::<_, i64>(
+                "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?",
             )
-        "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // 创建额外的索引
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_llm_calls_session_id ON llm_calls(session_id)")
-            .execute(&self.pool)
-            .await?;
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_llm_calls_created_at ON llm_calls(created_at)")
-            .execute(&self.pool)
-            .await?;
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_video_segments_session_id ON video_segments(session_id)")
-            .execute(&self.pool)
-            .await?;
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_timeline_cards_session_id ON timeline_cards(session_id)")
-            .execute(&self.pool)
+            .bind(table)
+            .bind(column)
+            .fetch_one(&self.pool)
             .await?;
+            if exists == 0 {
+                info!("迁移数据库: 为 {} 添加 {} 字段", table, column);
+                sqlx::query(&statement).execute(&self.pool).await?;
+            }
+        }
+        for statement in schema::create_index_statements(Dialect::Sqlite) {
+            sqlx::query(&statement).execute(&self.pool).await?;
+        }
 
         // 数据库迁移: 为已存在的sessions表添加设备字段
         let check_device_name = sqlx::query_scalar::<_, i64>(
@@ -924,10 +964,600 @@ impl DatabaseRepository for SqliteRepository {
         Ok(())
     }
 
+    async fn save_session_analysis(&self, record: &SessionAnalysisRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO session_analyses (session_id, served_by, sampling_seed, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(&record.served_by)
+        .bind(record.sampling_seed)
+        .bind(record.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_session_analysis(&self, session_id: i64) -> Result<Option<SessionAnalysisRecord>> {
+        let result = sqlx::query_as::<_, SessionAnalysisRecord>(
+            r#"
+            SELECT * FROM session_analyses WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_frame_prune(
+        &self,
+        record: &FramePruneRecord,
+        kept_frame_ids: &[i64],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // 删除未保留的帧记录（保留列表为空时删除该会话的全部帧）
+        let sql = if kept_frame_ids.is_empty() {
+            "DELETE FROM frames WHERE session_id = ?".to_string()
+        } else {
+            let placeholders = vec!["?"; kept_frame_ids.len()].join(", ");
+            format!(
+                "DELETE FROM frames WHERE session_id = ? AND id NOT IN ({})",
+                placeholders
+            )
+        };
+        let mut query = sqlx::query(&sql).bind(record.session_id);
+        for id in kept_frame_ids {
+            query = query.bind(id);
+        }
+        query.execute(&mut *tx).await?;
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO frame_prunes (session_id, deleted_count, kept_count, pruned_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(record.deleted_count)
+        .bind(record.kept_count)
+        .bind(record.pruned_at)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get_frame_prune(&self, session_id: i64) -> Result<Option<FramePruneRecord>> {
+        let result = sqlx::query_as::<_, FramePruneRecord>(
+            r#"
+            SELECT * FROM frame_prunes WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_session_thumbnail(&self, record: &SessionThumbnailRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO session_thumbnails (session_id, frame_timestamp, width, height, image_data, created_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(record.frame_timestamp)
+        .bind(record.width)
+        .bind(record.height)
+        .bind(&record.image_data)
+        .bind(record.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_session_thumbnail(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionThumbnailRecord>> {
+        let result = sqlx::query_as::<_, SessionThumbnailRecord>(
+            r#"
+            SELECT * FROM session_thumbnails WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn merge_sessions(
+        &self,
+        merged: &Session,
+        frames: &[Frame],
+        originals: &[SessionMergeRecord],
+    ) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO sessions (start_time, end_time, title, summary, video_path, tags, device_name, device_type, pinned)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            "#,
+        )
+        .bind(merged.start_time)
+        .bind(merged.end_time)
+        .bind(&merged.title)
+        .bind(&merged.summary)
+        .bind(&merged.video_path)
+        .bind(&merged.tags)
+        .bind(&merged.device_name)
+        .bind(&merged.device_type)
+        .bind(merged.pinned)
+        .execute(&mut *tx)
+        .await?;
+        let merged_id = result.last_insert_rowid();
+
+        for frame in frames {
+            sqlx::query(
+                r#"
+                INSERT INTO frames (session_id, timestamp, file_path)
+                VALUES (?1, ?2, ?3)
+                "#,
+            )
+            .bind(merged_id)
+            .bind(&frame.timestamp)
+            .bind(&frame.file_path)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for original in originals {
+            sqlx::query(
+                "UPDATE session_merges SET merged_session_id = ? WHERE merged_session_id = ?",
+            )
+            .bind(merged_id)
+            .bind(original.original_session_id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query(
+                r#"
+                INSERT INTO session_merges (merged_session_id, original_session_id, original_session, frame_count, merged_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(merged_id)
+            .bind(original.original_session_id)
+            .bind(&original.original_session)
+            .bind(original.frame_count)
+            .bind(original.merged_at)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("UPDATE capture_gaps SET session_id = ? WHERE session_id = ?")
+                .bind(merged_id)
+                .bind(original.original_session_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE deep_work_stretches SET session_id = ? WHERE session_id = ?")
+                .bind(merged_id)
+                .bind(original.original_session_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM sessions WHERE id = ?")
+                .bind(original.original_session_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        info!(
+            "已合并 {} 个会话为会话 {}（{} 帧）",
+            originals.len(),
+            merged_id,
+            frames.len()
+        );
+        Ok(merged_id)
+    }
+
+    async fn get_session_merges(&self, merged_session_id: i64) -> Result<Vec<SessionMergeRecord>> {
+        let records = sqlx::query_as::<_, SessionMergeRecord>(
+            r#"
+            SELECT * FROM session_merges WHERE merged_session_id = ? ORDER BY id
+            "#,
+        )
+        .bind(merged_session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn save_capture_gaps(&self, gaps: &[CaptureGapRecord]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for gap in gaps {
+            sqlx::query(
+                r#"
+                INSERT INTO capture_gaps (session_id, gap_start, gap_end, restart_attempts)
+                VALUES (?, ?, ?, ?)
+                "#,
+            )
+            .bind(gap.session_id)
+            .bind(gap.gap_start)
+            .bind(gap.gap_end)
+            .bind(gap.restart_attempts)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_capture_gaps(&self, session_id: i64) -> Result<Vec<CaptureGapRecord>> {
+        let records = sqlx::query_as::<_, CaptureGapRecord>(
+            r#"
+            SELECT * FROM capture_gaps WHERE session_id = ? ORDER BY gap_start
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn save_frame_selection(&self, record: &FrameSelectionRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO frame_selections (session_id, total_frames, frames, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(record.total_frames)
+        .bind(&record.frames)
+        .bind(record.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_frame_selection(&self, session_id: i64) -> Result<Option<FrameSelectionRecord>> {
+        let result = sqlx::query_as::<_, FrameSelectionRecord>(
+            r#"
+            SELECT * FROM frame_selections WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_session_extra_fields(&self, record: &SessionExtraFieldsRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO session_extra_fields (session_id, fields, created_at)
+            VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(&record.fields)
+        .bind(record.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_session_extra_fields(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionExtraFieldsRecord>> {
+        let result = sqlx::query_as::<_, SessionExtraFieldsRecord>(
+            r#"
+            SELECT * FROM session_extra_fields WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_session_analysis_mode(&self, record: &SessionAnalysisModeRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO session_analysis_modes (session_id, mode, updated_at)
+            VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(&record.mode)
+        .bind(record.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_session_analysis_mode(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SessionAnalysisModeRecord>> {
+        let result = sqlx::query_as::<_, SessionAnalysisModeRecord>(
+            r#"
+            SELECT * FROM session_analysis_modes WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn save_session_scores(&self, record: &SessionScoresRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO session_scores (
+                session_id, productivity_score, focus_score, confidence, low_confidence, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(record.productivity_score)
+        .bind(record.focus_score)
+        .bind(record.confidence)
+        .bind(record.low_confidence)
+        .bind(record.updated_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn save_analysis_failure(&self, record: &AnalysisFailureRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO analysis_failures (
+                session_id, last_error, attempts, first_failed_at, last_failed_at, dead_lettered_at
+            ) VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(&record.last_error)
+        .bind(record.attempts)
+        .bind(record.first_failed_at)
+        .bind(record.last_failed_at)
+        .bind(record.dead_lettered_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_analysis_failure(&self, session_id: i64) -> Result<Option<AnalysisFailureRecord>> {
+        let result = sqlx::query_as::<_, AnalysisFailureRecord>(
+            r#"
+            SELECT * FROM analysis_failures WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn list_analysis_failures(&self) -> Result<Vec<AnalysisFailureRecord>> {
+        let records = sqlx::query_as::<_, AnalysisFailureRecord>(
+            r#"
+            SELECT * FROM analysis_failures ORDER BY last_failed_at DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn delete_analysis_failure(&self, session_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM analysis_failures WHERE session_id = ?")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn save_analysis_queue_entry(&self, record: &AnalysisQueueRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO analysis_queue (
+                session_key, frame_count, window_start, window_end, priority, attempts, in_flight,
+                temp_session_id, enqueued_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(record.session_key)
+        .bind(record.frame_count)
+        .bind(record.window_start)
+        .bind(record.window_end)
+        .bind(&record.priority)
+        .bind(record.attempts)
+        .bind(record.in_flight)
+        .bind(record.temp_session_id)
+        .bind(record.enqueued_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_analysis_queue_entry(
+        &self,
+        session_key: i64,
+    ) -> Result<Option<AnalysisQueueRecord>> {
+        let result = sqlx::query_as::<_, AnalysisQueueRecord>(
+            r#"
+            SELECT * FROM analysis_queue WHERE session_key = ?
+            "#,
+        )
+        .bind(session_key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn list_analysis_queue(&self) -> Result<Vec<AnalysisQueueRecord>> {
+        let records = sqlx::query_as::<_, AnalysisQueueRecord>(
+            r#"
+            SELECT * FROM analysis_queue ORDER BY enqueued_at, session_key
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn delete_analysis_queue_entry(&self, session_key: i64) -> Result<()> {
+        sqlx::query("DELETE FROM analysis_queue WHERE session_key = ?")
+            .bind(session_key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn replace_deep_work_stretches(
+        &self,
+        session_id: i64,
+        records: &[DeepWorkRecord],
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM deep_work_stretches WHERE session_id = ?")
+            .bind(session_id)
+            .execute(&mut *tx)
+            .await?;
+        for record in records {
+            sqlx::query(
+                r#"
+                INSERT INTO deep_work_stretches (session_id, start_time, end_time, app, duration_secs, focus_score, interruptions, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(session_id)
+            .bind(record.start_time)
+            .bind(record.end_time)
+            .bind(&record.app)
+            .bind(record.duration_secs)
+            .bind(record.focus_score)
+            .bind(record.interruptions)
+            .bind(record.created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_deep_work_stretches(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<DeepWorkRecord>> {
+        let records = sqlx::query_as::<_, DeepWorkRecord>(
+            r#"
+            SELECT * FROM deep_work_stretches
+            WHERE start_time < ? AND end_time > ?
+            ORDER BY start_time
+            "#,
+        )
+        .bind(end)
+        .bind(start)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    async fn save_summary_correction(&self, record: &SummaryCorrectionRecord) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO summary_corrections
+                (session_id, description, corrected, embedding, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(record.session_id)
+        .bind(&record.description)
+        .bind(&record.corrected)
+        .bind(&record.embedding)
+        .bind(record.created_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_summary_correction(
+        &self,
+        session_id: i64,
+    ) -> Result<Option<SummaryCorrectionRecord>> {
+        let result = sqlx::query_as::<_, SummaryCorrectionRecord>(
+            r#"
+            SELECT * FROM summary_corrections WHERE session_id = ?
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    async fn get_recent_summary_corrections(
+        &self,
+        limit: i64,
+    ) -> Result<Vec<SummaryCorrectionRecord>> {
+        let records = sqlx::query_as::<_, SummaryCorrectionRecord>(
+            r#"
+            SELECT * FROM summary_corrections ORDER BY created_at DESC LIMIT ?
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
     fn db_type(&self) -> &str {
         "sqlite"
     }
 
+    async fn close(&self) {
+        self.pool.close().await;
+    }
+
     async fn migrate_timezone_to_local(&self) -> Result<(u64, u64, u64, u64, u64, u64)> {
         use chrono::Local;
 
@@ -1018,3 +1648,141 @@ impl DatabaseRepository for SqliteRepository {
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    /// 每天一个会话（2025-01-01 起），标签与评分各不相同
+    async fn seeded() -> (tempfile::TempDir, SqliteRepository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = SqliteRepository::new(dir.path().join("data.db").to_str().unwrap())
+            .await
+            .unwrap();
+        let seeds = [
+            ("work", r#"["Rust","VS Code"]"#, Some((80.0, 70.0))),
+            ("work", r#"["meeting"]"#, Some((40.0, 30.0))),
+            ("learning", r#"["rust"]"#, Some((90.0, 95.0))),
+            ("entertainment", r#"["video"]"#, None),
+            ("work", r#"["review"]"#, Some((60.0, 85.0))),
+        ];
+        let first = "2025-01-01T09:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        for (day, (category, keywords, scores)) in seeds.into_iter().enumerate() {
+            let start = first + Duration::days(day as i64);
+            let session_id = repo
+                .insert_session(&Session {
+                    id: None,
+                    start_time: start,
+                    end_time: start + Duration::minutes(30),
+                    title: format!("会话 {}", day + 1),
+                    summary: String::new(),
+                    video_path: None,
+                    tags: format!(
+                        r#"[{{"category":"{}","confidence":0.9,"keywords":{}}}]"#,
+                        category, keywords
+                    ),
+                    created_at: None,
+                    device_name: None,
+                    device_type: None,
+                    pinned: false,
+                })
+                .await
+                .unwrap();
+            if let Some((productivity, focus)) = scores {
+                repo.save_session_scores(&SessionScoresRecord {
+                    session_id,
+                    productivity_score: Some(productivity),
+                    focus_score: Some(focus),
+                    confidence: Some(0.9),
+                    // 生产力评分最低的会话 2 待复核
+                    low_confidence: productivity < 50.0,
+                    updated_at: start,
+                })
+                .await
+                .unwrap();
+            }
+        }
+        (dir, repo)
+    }
+
+    fn titles(page: &SessionPage) -> Vec<&str> {
+        page.sessions.iter().map(|s| s.title.as_str()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_query_sessions_combines_filters_and_paginates() {
+        let (_dir, repo) = seeded().await;
+
+        // 无条件：按开始时间倒序
+        let page = repo
+            .query_sessions(&SessionFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(page.total, 5);
+        assert_eq!(titles(&page)[0], "会话 5");
+
+        // 日期范围包含结束日当天
+        let filter = SessionFilter {
+            start_date: Some("2025-01-02".to_string()),
+            end_date: Some("2025-01-04".to_string()),
+            ..Default::default()
+        };
+        let page = repo.query_sessions(&filter).await.unwrap();
+        assert_eq!(titles(&page), vec!["会话 4", "会话 3", "会话 2"]);
+
+        // 类别 + 评分下限
+        let filter = SessionFilter {
+            categories: vec!["work".to_string()],
+            min_productivity: Some(50.0),
+            ..Default::default()
+        };
+        let page = repo.query_sessions(&filter).await.unwrap();
+        assert_eq!(titles(&page), vec!["会话 5", "会话 1"]);
+
+        // 关键词不区分大小写；没有评分的会话不满足评分条件
+        let filter = SessionFilter {
+            keywords: vec!["RUST".to_string()],
+            max_focus: Some(90.0),
+            ..Default::default()
+        };
+        let page = repo.query_sessions(&filter).await.unwrap();
+        assert_eq!(titles(&page), vec!["会话 1"]);
+
+        // 没有符合条件的会话
+        let filter = SessionFilter {
+            categories: vec!["entertainment".to_string()],
+            min_focus: Some(0.0),
+            ..Default::default()
+        };
+        let page = repo.query_sessions(&filter).await.unwrap();
+        assert_eq!((page.total, page.sessions.len()), (0, 0));
+
+        // 低置信度（待复核）；没有评分记录的会话视为非低置信度
+        let filter = SessionFilter {
+            low_confidence: Some(true),
+            ..Default::default()
+        };
+        let page = repo.query_sessions(&filter).await.unwrap();
+        assert_eq!(titles(&page), vec!["会话 2"]);
+        let filter = SessionFilter {
+            low_confidence: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(repo.query_sessions(&filter).await.unwrap().total, 4);
+
+        // 分页：最后一页不满，越过末尾时为空但总数不变
+        let page_of = |offset| SessionFilter {
+            offset,
+            limit: Some(2),
+            ..Default::default()
+        };
+        let page = repo.query_sessions(&page_of(2)).await.unwrap();
+        assert_eq!(titles(&page), vec!["会话 3", "会话 2"]);
+        let page = repo.query_sessions(&page_of(4)).await.unwrap();
+        assert_eq!(titles(&page), vec!["会话 1"]);
+        let page = repo.query_sessions(&page_of(5)).await.unwrap();
+        assert!(page.sessions.is_empty());
+        assert_eq!((page.total, page.offset, page.limit), (5, 5, 2));
+    }
+}
@@ -0,0 +1,620 @@
+// 会话导入导出 - 在不同设备/实例之间迁移历史会话（JSON / CSV）
+
+use super::database::Database;
+use super::models::{Session, SessionExtraFieldsRecord};
+use crate::llm::plugin::{ActivityTag, SessionSummary};
+use crate::models::{DateRange, ExportFormat};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// CSV 列顺序（与 SessionExportRecord 字段一致）
+const CSV_COLUMNS: [&str; 11] = [
+    "source_id",
+    "start_time",
+    "end_time",
+    "title",
+    "summary",
+    "video_path",
+    "tags",
+    "device_name",
+    "device_type",
+    "pinned",
+    "extra",
+];
+
+/// 导出的单条会话
+///
+/// 时间按数据库原值以 RFC3339 保存，保证导入后与源实例完全一致；
+/// source_id 仅用于报告，导入时由目标数据库重新分配 id
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionExportRecord {
+    #[serde(default)]
+    pub source_id: Option<i64>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub title: String,
+    pub summary: String,
+    #[serde(default)]
+    pub video_path: Option<String>,
+    /// JSON 序列化的标签（与 sessions.tags 相同）
+    #[serde(default = "default_tags")]
+    pub tags: String,
+    #[serde(default)]
+    pub device_name: Option<String>,
+    #[serde(default)]
+    pub device_type: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+    /// 配置的自定义总结字段（CSV 中为 JSON 对象）
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extra: HashMap<String, Value>,
+}
+
+fn default_tags() -> String {
+    "[]".to_string()
+}
+
+/// 单条记录的解析结果（失败时为错误说明）
+type ParsedRecord = std::result::Result<SessionExportRecord, String>;
+
+/// 判断重复会话的稳定键：开始时间 + 结束时间 + 设备名
+type SessionKey = (DateTime<Utc>, DateTime<Utc>, Option<String>);
+
+impl SessionExportRecord {
+    fn from_session(session: &Session) -> Self {
+        Self {
+            source_id: session.id,
+            start_time: session.start_time,
+            end_time: session.end_time,
+            title: session.title.clone(),
+            summary: session.summary.clone(),
+            video_path: session.video_path.clone(),
+            tags: session.tags.clone(),
+            device_name: session.device_name.clone(),
+            device_type: session.device_type.clone(),
+            pinned: session.pinned,
+            extra: HashMap::new(),
+        }
+    }
+
+    fn to_session(&self) -> Session {
+        Session {
+            id: None,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            title: self.title.clone(),
+            summary: self.summary.clone(),
+            video_path: self.video_path.clone(),
+            tags: self.tags.clone(),
+            created_at: None,
+            device_name: self.device_name.clone(),
+            device_type: self.device_type.clone(),
+            pinned: self.pinned,
+        }
+    }
+
+    fn key(&self) -> SessionKey {
+        (self.start_time, self.end_time, self.device_name.clone())
+    }
+
+    /// 通过 SessionSummary::validate 校验，返回全部违规项
+    fn validate(&self) -> std::result::Result<(), Vec<String>> {
+        let tags: Vec<ActivityTag> = serde_json::from_str(&self.tags)
+            .map_err(|e| vec![format!("tags 不是合法的标签 JSON: {}", e)])?;
+        let summary = SessionSummary {
+            title: self.title.clone(),
+            summary: self.summary.clone(),
+            tags,
+            start_time: self.start_time,
+            end_time: self.end_time,
+            ..Default::default()
+        };
+        summary
+            .validate()
+            .map_err(|errors| errors.iter().map(|e| e.to_string()).collect())
+    }
+}
+
+/// 单条记录的导入结果
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ImportStatus {
+    /// 已导入，session_id 为目标数据库中重新分配的 id
+    Imported { session_id: i64 },
+    /// 与已有会话重复，已跳过
+    Duplicate { session_id: i64 },
+    /// 解析、校验或写入失败
+    Failed { errors: Vec<String> },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportRecordResult {
+    /// 记录在文件中的序号（从 0 开始）
+    pub index: usize,
+    /// 源实例中的会话 id
+    pub source_id: Option<i64>,
+    #[serde(flatten)]
+    pub status: ImportStatus,
+}
+
+/// 导入报告
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub records: Vec<ImportRecordResult>,
+}
+
+impl ImportReport {
+    fn push(&mut self, index: usize, source_id: Option<i64>, status: ImportStatus) {
+        match status {
+            ImportStatus::Imported { .. } => self.imported += 1,
+            ImportStatus::Duplicate { .. } => self.skipped += 1,
+            ImportStatus::Failed { .. } => self.failed += 1,
+        }
+        self.records.push(ImportRecordResult {
+            index,
+            source_id,
+            status,
+        });
+    }
+}
+
+/// 导出会话到文件，返回导出的会话数
+///
+/// date_range 按会话开始日期（YYYY-MM-DD，闭区间）过滤
+pub async fn export_sessions(
+    db: &Database,
+    path: &Path,
+    format: &ExportFormat,
+    date_range: Option<&DateRange>,
+) -> Result<usize> {
+    let records = export_records(db, date_range).await?;
+    let content = match format {
+        ExportFormat::Json => serde_json::to_string_pretty(&records)?,
+        ExportFormat::Csv => records_to_csv(&records),
+        other => return Err(anyhow!("会话导出不支持 {:?} 格式", other)),
+    };
+    tokio::fs::write(path, content).await?;
+    info!("已导出 {} 个会话到 {}", records.len(), path.display());
+    Ok(records.len())
+}
+
+async fn export_records(
+    db: &Database,
+    date_range: Option<&DateRange>,
+) -> Result<Vec<SessionExportRecord>> {
+    let sessions = db.get_all_sessions().await?;
+    let mut records = Vec::new();
+    for session in sessions.iter().filter(|s| {
+        let Some(range) = date_range else {
+            return true;
+        };
+        let date = s.start_time.format("%Y-%m-%d").to_string();
+        date >= range.start_date && date <= range.end_date
+    }) {
+        let mut record = SessionExportRecord::from_session(session);
+        if let Some(id) = session.id {
+            if let Some(extra) = db.get_session_extra_fields(id).await? {
+                record.extra = serde_json::from_str(&extra.fields).unwrap_or_default();
+            }
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// 从导出文件导入会话
+///
+/// 每条记录单独解析、校验和写入，失败只影响该条记录；
+/// 与已有会话（或文件中靠前的记录）重复时跳过
+pub async fn import_sessions(
+    db: &Database,
+    path: &Path,
+    format: &ExportFormat,
+) -> Result<ImportReport> {
+    let text = tokio::fs::read_to_string(path).await?;
+    let parsed = match format {
+        ExportFormat::Json => records_from_json(&text)?,
+        ExportFormat::Csv => records_from_csv(&text)?,
+        other => return Err(anyhow!("会话导入不支持 {:?} 格式", other)),
+    };
+
+    let mut existing: HashMap<SessionKey, i64> = db
+        .get_all_sessions()
+        .await?
+        .iter()
+        .filter_map(|s| {
+            s.id.map(|id| ((s.start_time, s.end_time, s.device_name.clone()), id))
+        })
+        .collect();
+
+    let mut report = ImportReport::default();
+    for (index, parsed) in parsed.into_iter().enumerate() {
+        let (source_id, status) = match parsed {
+            Ok(record) => (
+                record.source_id,
+                import_record(db, &record, &mut existing).await,
+            ),
+            Err(error) => (
+                None,
+                ImportStatus::Failed {
+                    errors: vec![error],
+                },
+            ),
+        };
+        if let ImportStatus::Failed { errors } = &status {
+            warn!("导入第 {} 条会话失败: {}", index, errors.join("; "));
+        }
+        report.push(index, source_id, status);
+    }
+
+    info!(
+        "会话导入完成: 导入 {}，跳过重复 {}，失败 {}",
+        report.imported, report.skipped, report.failed
+    );
+    Ok(report)
+}
+
+async fn import_record(
+    db: &Database,
+    record: &SessionExportRecord,
+    existing: &mut HashMap<SessionKey, i64>,
+) -> ImportStatus {
+    if let Err(errors) = record.validate() {
+        return ImportStatus::Failed { errors };
+    }
+    let key = record.key();
+    if let Some(&session_id) = existing.get(&key) {
+        return ImportStatus::Duplicate { session_id };
+    }
+    match db.insert_session(&record.to_session()).await {
+        Ok(session_id) => {
+            existing.insert(key, session_id);
+            if !record.extra.is_empty() {
+                let extra = SessionExtraFieldsRecord {
+                    session_id,
+                    fields: serde_json::to_string(&record.extra).unwrap_or_default(),
+                    created_at: super::models::local_now(),
+                };
+                // 会话本身已写入，自定义字段失败只记录告警
+                if let Err(e) = db.save_session_extra_fields(&extra).await {
+                    warn!("导入会话 {} 的自定义字段失败: {}", session_id, e);
+                }
+            }
+            ImportStatus::Imported { session_id }
+        }
+        Err(e) => ImportStatus::Failed {
+            errors: vec![format!("写入数据库失败: {}", e)],
+        },
+    }
+}
+
+/// 解析 JSON 数组，逐条转换（单条格式错误不影响其他记录）
+fn records_from_json(text: &str) -> Result<Vec<ParsedRecord>> {
+    let values: Vec<Value> =
+        serde_json::from_str(text).map_err(|e| anyhow!("导入文件不是合法的 JSON 数组: {}", e))?;
+    Ok(values
+        .into_iter()
+        .map(|v| serde_json::from_value(v).map_err(|e| format!("记录格式错误: {}", e)))
+        .collect())
+}
+
+/// 生成 CSV：有值的字段一律加引号，空字段表示 None，以区分 None 与空字符串
+fn records_to_csv(records: &[SessionExportRecord]) -> String {
+    fn field(value: Option<&str>) -> String {
+        value
+            .map(|v| format!("\"{}\"", v.replace('"', "\"\"")))
+            .unwrap_or_default()
+    }
+
+    let mut csv = CSV_COLUMNS.join(",");
+    csv.push('\n');
+    for record in records {
+        let source_id = record.source_id.map(|id| id.to_string());
+        let start_time = record.start_time.to_rfc3339();
+        let end_time = record.end_time.to_rfc3339();
+        let extra = (!record.extra.is_empty())
+            .then(|| serde_json::to_string(&record.extra).unwrap_or_default());
+        let fields = [
+            field(source_id.as_deref()),
+            field(Some(&start_time)),
+            field(Some(&end_time)),
+            field(Some(&record.title)),
+            field(Some(&record.summary)),
+            field(record.video_path.as_deref()),
+            field(Some(&record.tags)),
+            field(record.device_name.as_deref()),
+            field(record.device_type.as_deref()),
+            field(Some(if record.pinned { "true" } else { "false" })),
+            field(extra.as_deref()),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn records_from_csv(text: &str) -> Result<Vec<ParsedRecord>> {
+    let mut rows = parse_csv(text.trim_start_matches('\u{feff}'))?.into_iter();
+    let header: Vec<String> = rows
+        .next()
+        .ok_or_else(|| anyhow!("CSV 文件为空"))?
+        .into_iter()
+        .map(|h| h.unwrap_or_default())
+        .collect();
+    let columns: HashMap<&str, usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.trim(), i))
+        .collect();
+    for required in ["start_time", "end_time", "title", "summary"] {
+        if !columns.contains_key(required) {
+            return Err(anyhow!("CSV 缺少必需列 {}", required));
+        }
+    }
+
+    Ok(rows
+        .map(|row| {
+            if row.len() != header.len() {
+                return Err(format!(
+                    "列数不匹配: 期望 {}，实际 {}",
+                    header.len(),
+                    row.len()
+                ));
+            }
+            let get = |name: &str| columns.get(name).and_then(|&i| row[i].clone());
+            let required = |name: &str| get(name).ok_or_else(|| format!("缺少字段 {}", name));
+            let time = |name: &str| {
+                required(name)?
+                    .parse::<DateTime<Utc>>()
+                    .map_err(|e| format!("{} 时间格式错误: {}", name, e))
+            };
+            let source_id = get("source_id")
+                .map(|id| {
+                    id.parse::<i64>()
+                        .map_err(|e| format!("source_id 无效: {}", e))
+                })
+                .transpose()?;
+            let pinned = get("pinned")
+                .map(|pinned| {
+                    pinned
+                        .trim()
+                        .parse::<bool>()
+                        .map_err(|e| format!("pinned 无效: {}", e))
+                })
+                .transpose()?
+                .unwrap_or(false);
+            let extra = get("extra")
+                .map(|extra| {
+                    serde_json::from_str(&extra)
+                        .map_err(|e| format!("extra 不是合法的 JSON 对象: {}", e))
+                })
+                .transpose()?
+                .unwrap_or_default();
+            Ok(SessionExportRecord {
+                source_id,
+                start_time: time("start_time")?,
+                end_time: time("end_time")?,
+                title: required("title")?,
+                summary: required("summary")?,
+                video_path: get("video_path"),
+                tags: get("tags").unwrap_or_else(default_tags),
+                device_name: get("device_name"),
+                device_type: get("device_type"),
+                pinned,
+                extra,
+            })
+        })
+        .collect())
+}
+
+/// 解析 RFC 4180 CSV（支持引号内的逗号、换行和 "" 转义）
+///
+/// 未加引号的空字段解析为 None，加引号的字段即使为空也解析为 Some
+fn parse_csv(text: &str) -> Result<Vec<Vec<Option<String>>>> {
+    fn take_field(field: &mut String, quoted: &mut bool) -> Option<String> {
+        let value = std::mem::take(field);
+        let was_quoted = std::mem::replace(quoted, false);
+        (was_quoted || !value.is_empty()).then_some(value)
+    }
+
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_quotes = true;
+                quoted = true;
+            }
+            ',' => row.push(take_field(&mut field, &mut quoted)),
+            '\r' => {}
+            '\n' => {
+                row.push(take_field(&mut field, &mut quoted));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err(anyhow!("CSV 引号未闭合"));
+    }
+    if quoted || !field.is_empty() || !row.is_empty() {
+        row.push(take_field(&mut field, &mut quoted));
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    async fn open_db(dir: &Path, name: &str) -> Database {
+        Database::new_sqlite(dir.join(name).to_str().unwrap())
+            .await
+            .unwrap()
+    }
+
+    fn sample_sessions() -> Vec<Session> {
+        vec![
+            Session {
+                id: None,
+                start_time: utc("2025-01-01T09:00:00Z"),
+                end_time: utc("2025-01-01T09:10:00Z"),
+                title: "编写代码".to_string(),
+                summary: "实现 \"导入\", 处理逗号\n以及换行".to_string(),
+                video_path: Some("/videos/202501010900-202501010910.mp4".to_string()),
+                tags: r#"[{"category":"work","confidence":0.9,"keywords":["rust"]}]"#.to_string(),
+                created_at: None,
+                device_name: Some("laptop-a".to_string()),
+                device_type: Some("laptop".to_string()),
+                pinned: true,
+            },
+            Session {
+                id: None,
+                start_time: utc("2025-01-02T14:00:00Z"),
+                end_time: utc("2025-01-02T14:15:00Z"),
+                title: "开会".to_string(),
+                summary: String::new(),
+                video_path: Some(String::new()),
+                tags: "[]".to_string(),
+                created_at: None,
+                device_name: None,
+                device_type: None,
+                pinned: false,
+            },
+        ]
+    }
+
+    fn without_ids(records: Vec<SessionExportRecord>) -> Vec<SessionExportRecord> {
+        records
+            .into_iter()
+            .map(|r| SessionExportRecord {
+                source_id: None,
+                ..r
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_export_import_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = open_db(dir.path(), "source.db").await;
+        let mut ids = Vec::new();
+        for session in sample_sessions() {
+            ids.push(source.insert_session(&session).await.unwrap());
+        }
+        source
+            .save_session_extra_fields(&SessionExtraFieldsRecord {
+                session_id: ids[0],
+                fields: r#"{"project":"导入导出","participants":["Alice","Bob"]}"#.to_string(),
+                created_at: crate::storage::local_now(),
+            })
+            .await
+            .unwrap();
+        let expected = without_ids(export_records(&source, None).await.unwrap());
+        assert_eq!(
+            expected.iter().map(|r| r.extra.len()).collect::<Vec<_>>(),
+            vec![2, 0]
+        );
+        assert!(expected[0].pinned && !expected[1].pinned);
+
+        for (name, format) in [("json", ExportFormat::Json), ("csv", ExportFormat::Csv)] {
+            let file = dir.path().join(format!("sessions.{name}"));
+            assert_eq!(
+                export_sessions(&source, &file, &format, None)
+                    .await
+                    .unwrap(),
+                2
+            );
+
+            let target = open_db(dir.path(), &format!("target-{name}.db")).await;
+            let report = import_sessions(&target, &file, &format).await.unwrap();
+            assert_eq!((report.imported, report.skipped, report.failed), (2, 0, 0));
+            let imported = without_ids(export_records(&target, None).await.unwrap());
+            assert_eq!(imported, expected, "{name} 导入后应与源数据一致");
+
+            // 再次导入时全部视为重复
+            let report = import_sessions(&target, &file, &format).await.unwrap();
+            assert_eq!((report.imported, report.skipped, report.failed), (0, 2, 0));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_bad_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = open_db(dir.path(), "data.db").await;
+        // 目标库已有会话，占用 id 1，导入记录的 source_id 需要重新映射
+        db.insert_session(&sample_sessions()[1]).await.unwrap();
+
+        let file = dir.path().join("sessions.json");
+        let content = serde_json::json!([
+            {"source_id": 1, "start_time": "2025-01-01T09:00:00Z", "end_time": "2025-01-01T09:10:00Z",
+             "title": "有效", "summary": "ok", "tags": "[]"},
+            {"source_id": 2, "start_time": "2025-01-01T10:00:00Z", "end_time": "2025-01-01T09:00:00Z",
+             "title": "时间颠倒", "summary": "",
+             "tags": r#"[{"category":"work","confidence":2.0,"keywords":[]}]"#},
+            {"source_id": 3, "title": "缺少时间"}
+        ]);
+        std::fs::write(&file, content.to_string()).unwrap();
+
+        let report = import_sessions(&db, &file, &ExportFormat::Json)
+            .await
+            .unwrap();
+        assert_eq!((report.imported, report.skipped, report.failed), (1, 0, 2));
+        assert!(matches!(
+            report.records[0].status,
+            ImportStatus::Imported { session_id } if session_id != 1
+        ));
+        // 一次报告该记录的全部违规项
+        match &report.records[1].status {
+            ImportStatus::Failed { errors } => assert_eq!(errors.len(), 2),
+            other => panic!("应为失败: {:?}", other),
+        }
+        assert!(matches!(
+            report.records[2].status,
+            ImportStatus::Failed { .. }
+        ));
+    }
+
+    #[test]
+    fn test_parse_csv_quoting() {
+        let rows = parse_csv("a,b,c\n\"x,\"\"y\"\"\",,\"\"\r\n\"多\n行\",2,3").unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(
+            rows[1],
+            vec![Some("x,\"y\"".to_string()), None, Some(String::new())]
+        );
+        assert_eq!(rows[2][0].as_deref(), Some("多\n行"));
+        assert!(parse_csv("\"未闭合").is_err());
+    }
+}
@@ -0,0 +1,374 @@
+// 分析指标导出 - 以 Prometheus 文本格式暴露分析吞吐、失败与 provider 请求计数
+//
+// 指标由分析流水线与 provider 调用处累加，保存在进程内的全局注册表中；
+// 开启 metrics_exporter 后在本地端口提供 GET /metrics 抓取入口。
+// 与 provider 健康状态接口不同，这里只做单调递增的计数，供看板按时间序列计算速率
+
+use crate::llm::{CircuitOpen, EmptyResponse, FramesUnavailable, TooFewFrames};
+use crate::models::MetricsExporterSettings;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{debug, info, warn};
+
+/// 分析耗时直方图的桶上界（秒）
+const DURATION_BUCKETS: [f64; 10] = [
+    1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0, 1800.0,
+];
+
+/// 请求头的最大读取长度，超出后不再等待
+const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+#[derive(Debug, Default)]
+struct Histogram {
+    /// 各桶的累计计数（不含 +Inf）
+    buckets: [u64; DURATION_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if value <= bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+struct MetricsState {
+    analyses: u64,
+    /// 失败次数，按错误类型
+    failures: BTreeMap<&'static str, u64>,
+    duration: Histogram,
+    frames_encoded: u64,
+    /// provider 请求次数，按 (provider, 结果)
+    provider_requests: BTreeMap<(String, &'static str), u64>,
+}
+
+/// 分析指标注册表
+#[derive(Debug, Default)]
+pub struct AnalysisMetrics {
+    state: Mutex<MetricsState>,
+}
+
+impl AnalysisMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次完成（成功或失败）的会话分析
+    pub fn record_analysis<T>(&self, elapsed: Duration, result: &Result<T>) {
+        let mut state = self.lock();
+        state.analyses += 1;
+        state.duration.observe(elapsed.as_secs_f64());
+        if let Err(e) = result {
+            *state.failures.entry(failure_kind(e)).or_default() += 1;
+        }
+    }
+
+    /// 记录已编码并发送给模型的帧数
+    pub fn add_frames_encoded(&self, frames: usize) {
+        self.lock().frames_encoded += frames as u64;
+    }
+
+    /// 记录一次 provider 请求
+    pub fn record_provider_request(&self, provider: &str, success: bool) {
+        let outcome = if success { "success" } else { "error" };
+        *self
+            .lock()
+            .provider_requests
+            .entry((provider.to_string(), outcome))
+            .or_default() += 1;
+    }
+
+    /// 已编码的帧数
+    pub fn frames_encoded(&self) -> u64 {
+        self.lock().frames_encoded
+    }
+
+    /// Prometheus 文本格式（0.0.4）
+    pub fn render(&self) -> String {
+        let state = self.lock();
+        let mut out = String::new();
+
+        out.push_str("# HELP analyses_total 完成的会话分析次数（含失败）\n");
+        out.push_str("# TYPE analyses_total counter\n");
+        let _ = writeln!(out, "analyses_total {}", state.analyses);
+
+        out.push_str("# HELP analysis_failures_total 失败的会话分析次数，按错误类型\n");
+        out.push_str("# TYPE analysis_failures_total counter\n");
+        for (kind, count) in &state.failures {
+            let _ = writeln!(
+                out,
+                "analysis_failures_total{{kind=\"{}\"}} {}",
+                kind, count
+            );
+        }
+
+        out.push_str("# HELP analysis_duration_seconds 会话分析耗时\n");
+        out.push_str("# TYPE analysis_duration_seconds histogram\n");
+        for (bound, count) in DURATION_BUCKETS.iter().zip(state.duration.buckets) {
+            let _ = writeln!(
+                out,
+                "analysis_duration_seconds_bucket{{le=\"{}\"}} {}",
+                bound, count
+            );
+        }
+        let _ = writeln!(
+            out,
+            "analysis_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            state.duration.count
+        );
+        let _ = writeln!(out, "analysis_duration_seconds_sum {}", state.duration.sum);
+        let _ = writeln!(
+            out,
+            "analysis_duration_seconds_count {}",
+            state.duration.count
+        );
+
+        out.push_str("# HELP frames_encoded_total 编码后发送给模型的帧数\n");
+        out.push_str("# TYPE frames_encoded_total counter\n");
+        let _ = writeln!(out, "frames_encoded_total {}", state.frames_encoded);
+
+        out.push_str("# HELP provider_requests_total provider 请求次数，按 provider 与结果\n");
+        out.push_str("# TYPE provider_requests_total counter\n");
+        for ((provider, outcome), count) in &state.provider_requests {
+            let _ = writeln!(
+                out,
+                "provider_requests_total{{provider=\"{}\",outcome=\"{}\"}} {}",
+                escape_label(provider),
+                outcome,
+                count
+            );
+        }
+        out
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MetricsState> {
+        // 持锁期间不会 panic，锁中毒时沿用内部数据
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// 进程内共享的指标注册表
+pub fn global() -> &'static AnalysisMetrics {
+    static METRICS: OnceLock<AnalysisMetrics> = OnceLock::new();
+    METRICS.get_or_init(AnalysisMetrics::new)
+}
+
+/// 错误类型标签：已知的错误类型单独统计，其余按网络错误或 other 归类
+fn failure_kind(error: &anyhow::Error) -> &'static str {
+    if error.downcast_ref::<TooFewFrames>().is_some() {
+        "too_few_frames"
+    } else if error.downcast_ref::<FramesUnavailable>().is_some() {
+        "frames_unavailable"
+    } else if error.downcast_ref::<CircuitOpen>().is_some() {
+        "circuit_open"
+    } else if error.downcast_ref::<EmptyResponse>().is_some() {
+        "empty_response"
+    } else if let Some(e) = error.downcast_ref::<reqwest::Error>() {
+        if e.is_timeout() {
+            "timeout"
+        } else {
+            "request"
+        }
+    } else {
+        "other"
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// 按配置启动抓取入口；未启用时不监听端口
+pub async fn start_exporter(settings: &MetricsExporterSettings) -> Result<()> {
+    if !settings.enabled {
+        return Ok(());
+    }
+    let listener = TcpListener::bind(&settings.bind_address).await?;
+    info!("指标导出已启用: http://{}/metrics", listener.local_addr()?);
+    tokio::spawn(serve(listener, global()));
+    Ok(())
+}
+
+/// 处理抓取请求：GET /metrics 返回指标，其余路径返回 404
+async fn serve(listener: TcpListener, metrics: &'static AnalysisMetrics) {
+    loop {
+        let (mut socket, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("指标导出接受连接失败: {}", e);
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 1024];
+            let complete = |r: &[u8]| r.windows(4).any(|w| w == b"\r\n\r\n");
+            while !complete(&request) && request.len() < MAX_REQUEST_BYTES {
+                match socket.read(&mut buf).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+            let request = String::from_utf8_lossy(&request);
+            let request_line = request.lines().next().unwrap_or_default();
+            debug!("指标导出请求 {}: {}", peer, request_line);
+
+            let mut parts = request_line.split_whitespace();
+            let (status, body) = match (parts.next(), parts.next()) {
+                (Some("GET"), Some("/metrics")) => ("200 OK", metrics.render()),
+                _ => ("404 Not Found", String::new()),
+            };
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: text/plain; version=0.0.4; charset=utf-8\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            if let Err(e) = socket.write_all(response.as_bytes()).await {
+                debug!("指标导出写入响应失败: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 读取导出文本中一行计数的值（没有该行时为 0）
+    fn counter(text: &str, name: &str) -> f64 {
+        text.lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(' ')?.parse().ok())
+            .unwrap_or(0.0)
+    }
+
+    #[tokio::test]
+    async fn test_completed_analysis_bumps_counters() {
+        use crate::capture::scheduler::{SessionProcessor, SessionWindow};
+        use crate::capture::ScreenFrame;
+        use std::sync::Arc;
+
+        let content = serde_json::json!({ "title": "编写代码", "summary": "在编辑器中修改代码" });
+        let url = crate::test_server::serve_routes(vec![(
+            "/api/chat",
+            serde_json::json!({ "message": { "content": content.to_string() } }).to_string(),
+        )])
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let start = chrono::DateTime::from_timestamp_millis(1_700_000_000_000).unwrap();
+        let frames: Vec<ScreenFrame> = (0..3)
+            .map(|i| {
+                let timestamp = start + chrono::Duration::seconds(i);
+                let path = dir
+                    .path()
+                    .join(format!("{}.jpg", timestamp.timestamp_millis()));
+                image::RgbImage::from_fn(64, 48, |x, y| {
+                    image::Rgb([(x * 4) as u8, (y * 5) as u8, (i * 40) as u8])
+                })
+                .save(&path)
+                .unwrap();
+                ScreenFrame {
+                    timestamp,
+                    file_path: path.to_string_lossy().into_owned(),
+                    screen_id: 0,
+                }
+            })
+            .collect();
+        let window = SessionWindow {
+            start,
+            end: start + chrono::Duration::minutes(1),
+        };
+
+        let db = Arc::new(
+            crate::storage::Database::new_sqlite(dir.path().join("data.db").to_str().unwrap())
+                .await
+                .unwrap(),
+        );
+        let settings = Arc::new(
+            crate::settings::SettingsManager::new(dir.path().join("config.json"))
+                .await
+                .unwrap(),
+        );
+        let ollama = crate::llm::OllamaConfig {
+            base_url: url,
+            detect_idle_frames: false,
+            ..Default::default()
+        };
+        let mut manager = crate::llm::LLMManager::new(reqwest::Client::new());
+        manager.configure_ollama(ollama.clone()).await.unwrap();
+        let (actor, handle) = crate::actors::LLMManagerActor::new(manager);
+        tokio::spawn(actor.run());
+        let processor = crate::llm::LLMProcessor::new(handle.clone(), db, settings);
+
+        // 全局注册表可能被其他测试并发累加，只比较本次分析前后的增量
+        let metric = |name: &str| counter(&global().render(), name);
+        let requests = r#"provider_requests_total{provider="ollama",outcome="success"}"#;
+        let before = [
+            metric("analyses_total"),
+            metric("analysis_duration_seconds_count"),
+            metric("frames_encoded_total"),
+            metric(requests),
+        ];
+        processor
+            .process_session(frames.clone(), window.clone())
+            .await
+            .unwrap();
+        assert!(metric("analyses_total") - before[0] >= 1.0);
+        assert!(metric("analysis_duration_seconds_count") - before[1] >= 1.0);
+        // 每 30 秒采样一帧并保留最后一帧：3 帧中发送 2 帧
+        assert!(metric("frames_encoded_total") - before[2] >= 2.0);
+        assert!(metric(requests) - before[3] >= 1.0);
+
+        // 帧数不足的分析按错误类型计入失败
+        let too_few = r#"analysis_failures_total{kind="too_few_frames"}"#;
+        let failures = metric(too_few);
+        handle
+            .configure_ollama(crate::llm::OllamaConfig {
+                min_frames_for_analysis: 3,
+                ..ollama
+            })
+            .await
+            .unwrap();
+        let err = processor.process_session(frames, window).await.unwrap_err();
+        assert!(err.downcast_ref::<TooFewFrames>().is_some(), "{err}");
+        assert!(metric(too_few) - failures >= 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_exporter_serves_metrics_path() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        global().add_frames_encoded(1);
+        tokio::spawn(serve(listener, global()));
+
+        let client = reqwest::Client::new();
+        let response = client.get(format!("{url}/metrics")).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert!(response
+            .headers()
+            .get("content-type")
+            .is_some_and(|v| v.to_str().unwrap().starts_with("text/plain; version=0.0.4")));
+        let body = response.text().await.unwrap();
+        assert!(body.contains("# TYPE frames_encoded_total counter"));
+
+        let response = client.get(format!("{url}/other")).send().await.unwrap();
+        assert_eq!(response.status(), 404);
+    }
+}
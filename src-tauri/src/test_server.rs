@@ -0,0 +1,167 @@
+// 测试用的本地 HTTP 服务 - 供 provider 等模块的测试模拟远端接口
+//
+// 按 Content-Length 或分块编码读完整个请求体后再交给处理函数，避免按请求内容的结尾字符
+// 猜测请求是否结束；每个连接只处理一个请求，响应后关闭连接
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+/// 收到的请求
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// 请求头（名称为小写）
+    pub headers: Vec<(String, String)>,
+    /// 请求体（已解开分块编码）
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// 返回的响应
+#[derive(Debug, Clone)]
+pub struct Response {
+    status: String,
+    content_type: String,
+    body: String,
+}
+
+impl Response {
+    /// 200 OK 的 JSON 响应
+    pub fn ok(body: impl Into<String>) -> Self {
+        Self::with_status("200 OK", body)
+    }
+
+    /// 指定状态行（如 "429 Too Many Requests"）的 JSON 响应
+    pub fn with_status(status: &str, body: impl Into<String>) -> Self {
+        Self {
+            status: status.to_string(),
+            content_type: "application/json".to_string(),
+            body: body.into(),
+        }
+    }
+}
+
+/// 在随机端口启动服务，按处理函数的返回值响应每个请求；返回服务地址（http://127.0.0.1:端口）
+pub async fn serve<F>(handler: F) -> String
+where
+    F: FnMut(Request) -> Response + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(serve_listener(listener, handler));
+    url
+}
+
+/// 按请求路径返回固定的 200 响应，未列出的路径返回空响应体
+pub async fn serve_routes(routes: Vec<(&'static str, String)>) -> String {
+    serve(move |request| {
+        let body = routes
+            .iter()
+            .find(|(path, _)| request.path == *path)
+            .map(|(_, body)| body.clone())
+            .unwrap_or_default();
+        Response::ok(body)
+    })
+    .await
+}
+
+/// 在已绑定的端口上处理请求（需要固定地址时使用，如模拟服务重启）
+pub async fn serve_listener<F>(listener: TcpListener, mut handler: F)
+where
+    F: FnMut(Request) -> Response + Send + 'static,
+{
+    while let Ok((socket, _)) = listener.accept().await {
+        let mut reader = BufReader::new(socket);
+        let Some(request) = read_request(&mut reader).await else {
+            continue;
+        };
+        let response = handler(request);
+        let message = format!(
+            "HTTP/1.1 {}\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            response.status,
+            response.content_type,
+            response.body.len(),
+            response.body
+        );
+        let socket = reader.get_mut();
+        let _ = socket.write_all(message.as_bytes()).await;
+        let _ = socket.shutdown().await;
+    }
+}
+
+/// 读取一个完整的请求；连接提前关闭或请求格式不正确时返回 None
+async fn read_request(reader: &mut BufReader<TcpStream>) -> Option<Request> {
+    let mut line = String::new();
+    reader.read_line(&mut line).await.ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await.ok()? == 0 {
+            return None;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':')?;
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+
+    let mut request = Request {
+        method,
+        path,
+        headers,
+        body: Vec::new(),
+    };
+    let chunked = request
+        .header("transfer-encoding")
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"));
+    if chunked {
+        loop {
+            line.clear();
+            reader.read_line(&mut line).await.ok()?;
+            let size = line.trim().split(';').next()?;
+            let size = usize::from_str_radix(size, 16).ok()?;
+            if size == 0 {
+                // 跳过 trailer，读到空行为止
+                loop {
+                    line.clear();
+                    if reader.read_line(&mut line).await.ok()? == 0 || line.trim().is_empty() {
+                        break;
+                    }
+                }
+                break;
+            }
+            let start = request.body.len();
+            request.body.resize(start + size, 0);
+            reader.read_exact(&mut request.body[start..]).await.ok()?;
+            // 块末尾的 \r\n
+            line.clear();
+            reader.read_line(&mut line).await.ok()?;
+        }
+    } else if let Some(length) = request
+        .header("content-length")
+        .and_then(|v| v.parse::<usize>().ok())
+    {
+        request.body.resize(length, 0);
+        reader.read_exact(&mut request.body).await.ok()?;
+    }
+    Some(request)
+}
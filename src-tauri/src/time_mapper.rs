@@ -0,0 +1,261 @@
+// 时间映射 - 在帧序号、相对时间（MM:SS / HH:MM:SS）与绝对时间之间统一换算
+//
+// 由会话开始时间 + 采集帧率，或逐帧截图时间构造；
+// 关键时刻校验、帧查找与联系表图例都经由这里换算，避免各处各写一套时间处理
+
+use crate::storage::Frame;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+
+/// 帧与时间的对应方式
+#[derive(Debug, Clone)]
+enum FrameClock {
+    /// 固定帧率（每秒帧数，可为小数，如 29.97）
+    Fps(f64),
+    /// 逐帧截图时间，下标即帧序号
+    Timestamps(Vec<DateTime<Utc>>),
+}
+
+/// 帧序号 / 相对时间 / 绝对时间换算
+#[derive(Debug, Clone)]
+pub struct TimeMapper {
+    start: DateTime<Utc>,
+    clock: FrameClock,
+}
+
+impl TimeMapper {
+    /// 按固定帧率换算，第 0 帧位于 start
+    pub fn from_fps(start: DateTime<Utc>, fps: f64) -> Result<Self> {
+        if !fps.is_finite() || fps <= 0.0 {
+            return Err(anyhow!("帧率必须为正数: {}", fps));
+        }
+        Ok(Self {
+            start,
+            clock: FrameClock::Fps(fps),
+        })
+    }
+
+    /// 按逐帧时间换算，会话开始时间取最早的一帧；没有帧时返回 None
+    pub fn from_timestamps(timestamps: Vec<DateTime<Utc>>) -> Option<Self> {
+        let start = timestamps.iter().min().copied()?;
+        Some(Self {
+            start,
+            clock: FrameClock::Timestamps(timestamps),
+        })
+    }
+
+    /// 按数据库中帧记录的截图时间换算
+    pub fn from_frames(frames: &[Frame]) -> Option<Self> {
+        Self::from_timestamps(frames.iter().map(|f| f.timestamp).collect())
+    }
+
+    /// 会话开始时间（相对时间 00:00 对应的绝对时间）
+    pub fn start(&self) -> DateTime<Utc> {
+        self.start
+    }
+
+    /// 相对时间对应的绝对时间
+    pub fn time_at(&self, offset: Duration) -> DateTime<Utc> {
+        self.start + offset
+    }
+
+    /// 绝对时间对应的相对时间（早于会话开始时为负）
+    pub fn offset_of(&self, time: DateTime<Utc>) -> Duration {
+        time - self.start
+    }
+
+    /// 第 index 帧的相对时间（按帧率换算时四舍五入到毫秒）；超出帧范围时返回 None
+    pub fn frame_offset(&self, index: usize) -> Option<Duration> {
+        match &self.clock {
+            FrameClock::Fps(fps) => Some(Duration::milliseconds(
+                (index as f64 * 1000.0 / fps).round() as i64,
+            )),
+            FrameClock::Timestamps(timestamps) => {
+                timestamps.get(index).map(|time| *time - self.start)
+            }
+        }
+    }
+
+    /// 第 index 帧的绝对时间
+    pub fn frame_time(&self, index: usize) -> Option<DateTime<Utc>> {
+        self.frame_offset(index).map(|offset| self.time_at(offset))
+    }
+
+    /// 与相对时间最接近的帧；恰好位于两帧正中时取较早的一帧，早于第 0 帧时取第 0 帧
+    pub fn frame_at_offset(&self, offset: Duration) -> Option<usize> {
+        match &self.clock {
+            FrameClock::Fps(fps) => {
+                let position = offset.num_milliseconds() as f64 * fps / 1000.0;
+                // 四舍五入时半帧向下取，与按时间戳查找时的平局规则一致
+                Some((position - 0.5).ceil().max(0.0) as usize)
+            }
+            FrameClock::Timestamps(_) => self.frame_at(self.time_at(offset)),
+        }
+    }
+
+    /// 与绝对时间最接近的帧；距离相同时取序号较小的帧
+    pub fn frame_at(&self, time: DateTime<Utc>) -> Option<usize> {
+        match &self.clock {
+            FrameClock::Fps(_) => self.frame_at_offset(self.offset_of(time)),
+            FrameClock::Timestamps(timestamps) => timestamps
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, frame_time)| (**frame_time - time).num_milliseconds().abs())
+                .map(|(index, _)| index),
+        }
+    }
+
+    /// 解析关键时刻的相对时间：MM:SS（分钟不限）或 HH:MM:SS，各段只能是数字
+    pub fn parse_offset(label: &str) -> Option<Duration> {
+        let parts: Option<Vec<i64>> = label
+            .split(':')
+            .map(|p| {
+                p.parse::<u32>()
+                    .ok()
+                    .filter(|_| p.bytes().all(|b| b.is_ascii_digit()))
+                    .map(i64::from)
+            })
+            .collect();
+        let seconds = match parts.as_deref()? {
+            [minutes, seconds] if *seconds < 60 => minutes * 60 + seconds,
+            [hours, minutes, seconds] if *minutes < 60 && *seconds < 60 => {
+                hours * 3600 + minutes * 60 + seconds
+            }
+            _ => return None,
+        };
+        Some(Duration::seconds(seconds))
+    }
+
+    /// 格式化相对时间：不足一小时为 MM:SS，否则为 HH:MM:SS；不足一秒的部分舍去
+    pub fn format_offset(offset: Duration) -> String {
+        let sign = if offset < Duration::zero() { "-" } else { "" };
+        let total = offset.num_seconds().abs();
+        let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+        if hours > 0 {
+            format!("{}{:02}:{:02}:{:02}", sign, hours, minutes, seconds)
+        } else {
+            format!("{}{:02}:{:02}", sign, minutes, seconds)
+        }
+    }
+
+    /// 关键时刻时间对应的绝对时间：相对时间按会话开始换算，RFC3339 取其本地时间
+    ///
+    /// 数据库中的时间为本地时间（类型标注为 UTC），RFC3339 时间同样只取本地时间部分
+    pub fn resolve(&self, label: &str) -> Option<DateTime<Utc>> {
+        if let Ok(time) = DateTime::parse_from_rfc3339(label) {
+            return Some(time.naive_local().and_utc());
+        }
+        Self::parse_offset(label).map(|offset| self.time_at(offset))
+    }
+
+    /// 关键时刻所在的日期：跨午夜的会话中，相对时间越过午夜的时刻属于后一天
+    pub fn date_of(&self, label: &str) -> Option<NaiveDate> {
+        self.resolve(label).map(|time| time.date_naive())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start() -> DateTime<Utc> {
+        "2025-01-01T09:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn test_fractional_fps_round_trips_every_frame() {
+        let mapper = TimeMapper::from_fps(start(), 29.97).unwrap();
+        assert_eq!(mapper.frame_offset(1), Some(Duration::milliseconds(33)));
+        assert_eq!(mapper.frame_offset(30), Some(Duration::milliseconds(1001)));
+        for index in 0..10_000 {
+            let offset = mapper.frame_offset(index).unwrap();
+            assert_eq!(mapper.frame_at_offset(offset), Some(index), "帧 {index}");
+        }
+
+        // 低于 1 的帧率：每 4 秒一帧
+        let slow = TimeMapper::from_fps(start(), 0.25).unwrap();
+        assert_eq!(slow.frame_time(3), Some(start() + Duration::seconds(12)));
+        assert_eq!(
+            slow.frame_at(start() + Duration::milliseconds(13_999)),
+            Some(3)
+        );
+
+        assert!(TimeMapper::from_fps(start(), 0.0).is_err());
+        assert!(TimeMapper::from_fps(start(), f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_rounding_ties_and_out_of_range() {
+        // 2fps：250ms 恰在第 0、1 帧正中，取较早的一帧
+        let mapper = TimeMapper::from_fps(start(), 2.0).unwrap();
+        assert_eq!(mapper.frame_at_offset(Duration::milliseconds(249)), Some(0));
+        assert_eq!(mapper.frame_at_offset(Duration::milliseconds(250)), Some(0));
+        assert_eq!(mapper.frame_at_offset(Duration::milliseconds(251)), Some(1));
+        assert_eq!(mapper.frame_at(start() - Duration::seconds(5)), Some(0));
+
+        // 逐帧时间：同样取较早的一帧，超出范围时取最近的首尾帧
+        let frames = TimeMapper::from_timestamps(vec![
+            start(),
+            start() + Duration::seconds(2),
+            start() + Duration::seconds(6),
+        ])
+        .unwrap();
+        assert_eq!(frames.frame_at(start() + Duration::seconds(4)), Some(1));
+        assert_eq!(frames.frame_at(start() + Duration::minutes(5)), Some(2));
+        assert_eq!(frames.frame_at(start() - Duration::minutes(5)), Some(0));
+        assert_eq!(frames.frame_offset(2), Some(Duration::seconds(6)));
+        assert_eq!(frames.frame_offset(3), None);
+        assert!(TimeMapper::from_timestamps(Vec::new()).is_none());
+    }
+
+    #[test]
+    fn test_parse_and_format_offsets() {
+        assert_eq!(
+            TimeMapper::parse_offset("05:30"),
+            Some(Duration::seconds(330))
+        );
+        assert_eq!(
+            TimeMapper::parse_offset("75:00"),
+            Some(Duration::minutes(75))
+        );
+        assert_eq!(
+            TimeMapper::parse_offset("1:02:03"),
+            Some(Duration::seconds(3723))
+        );
+        for label in ["12:75", "1:60:00", "+1:00", "1:2:3:4", "上午九点", ""] {
+            assert_eq!(TimeMapper::parse_offset(label), None, "{label}");
+        }
+
+        assert_eq!(TimeMapper::format_offset(Duration::seconds(330)), "05:30");
+        assert_eq!(
+            TimeMapper::format_offset(Duration::milliseconds(59_999)),
+            "00:59"
+        );
+        assert_eq!(
+            TimeMapper::format_offset(Duration::seconds(3723)),
+            "01:02:03"
+        );
+        assert_eq!(TimeMapper::format_offset(Duration::seconds(-90)), "-01:30");
+        // 格式化后可以再解析回原值
+        let offset = Duration::seconds(4000);
+        assert_eq!(
+            TimeMapper::parse_offset(&TimeMapper::format_offset(offset)),
+            Some(offset)
+        );
+    }
+
+    #[test]
+    fn test_resolve_relative_and_absolute_labels() {
+        let mapper = TimeMapper::from_fps(start(), 1.0).unwrap();
+        assert_eq!(
+            mapper.resolve("02:15"),
+            Some(start() + Duration::seconds(135))
+        );
+        // RFC3339 取本地时间部分
+        assert_eq!(
+            mapper.resolve("2025-01-01T09:05:00+08:00"),
+            Some(start() + Duration::minutes(5))
+        );
+        assert_eq!(mapper.resolve("bad"), None);
+    }
+}
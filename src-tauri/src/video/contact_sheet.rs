@@ -0,0 +1,404 @@
+// 会话联系表 - 将采样帧排成网格导出为单张 PNG，便于快速浏览
+//
+// 每张缩略图下方标注截图时间，关键时刻对应的帧加红框和 "#序号" 标记。
+// 项目未引入字体渲染依赖，图中只用内置点阵字体绘制数字；
+// 关键时刻的说明文字（多为中文）写入同名 .txt 图例，与图中序号一一对应
+
+use crate::storage::{frame_pack, Database, Frame};
+use crate::time_mapper::TimeMapper;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use image::{imageops, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// 缩略图之间及四周的留白（像素）
+const GAP: u32 = 8;
+/// 点阵字体放大倍数
+const FONT_SCALE: u32 = 2;
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+/// 缩略图下方时间标签的高度
+const LABEL_HEIGHT: u32 = GLYPH_HEIGHT * FONT_SCALE + 6;
+/// 关键时刻红框宽度
+const MARKER_BORDER: u32 = 3;
+
+const BACKGROUND: Rgb<u8> = Rgb([32, 32, 32]);
+const PLACEHOLDER: Rgb<u8> = Rgb([80, 80, 80]);
+const TEXT: Rgb<u8> = Rgb([235, 235, 235]);
+const MARKER: Rgb<u8> = Rgb([220, 40, 40]);
+
+/// 联系表配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ContactSheetConfig {
+    /// 每行缩略图数量
+    pub columns: u32,
+    /// 最大行数；采样帧超出网格容量时均匀抽取
+    pub max_rows: Option<u32>,
+    /// 缩略图宽度（像素）
+    pub thumb_width: u32,
+    /// 缩略图高度（像素）
+    pub thumb_height: u32,
+    /// 帧采样间隔；未设置时使用分析参数中的采样间隔
+    pub sampling_interval: Option<usize>,
+}
+
+impl Default for ContactSheetConfig {
+    fn default() -> Self {
+        Self {
+            columns: 4,
+            max_rows: None,
+            thumb_width: 320,
+            thumb_height: 180,
+            sampling_interval: None,
+        }
+    }
+}
+
+impl ContactSheetConfig {
+    fn validate(&self) -> Result<()> {
+        if self.columns == 0 || self.max_rows == Some(0) {
+            return Err(anyhow!("联系表的行数和列数必须大于 0"));
+        }
+        for (name, size) in [
+            ("thumb_width", self.thumb_width),
+            ("thumb_height", self.thumb_height),
+        ] {
+            if !(32..=1920).contains(&size) {
+                return Err(anyhow!("{} 必须在 32-1920 之间: {}", name, size));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 关键时刻（取自会话的视频分段）
+struct Moment {
+    time: DateTime<Utc>,
+    description: String,
+}
+
+/// 生成会话联系表，返回 PNG 路径（图例写入同名 .txt）
+pub async fn render_contact_sheet(
+    db: &Database,
+    session_id: i64,
+    output_dir: &Path,
+    config: &ContactSheetConfig,
+) -> Result<PathBuf> {
+    config.validate()?;
+
+    let frames = db.get_frames_by_session(session_id).await?;
+    if frames.is_empty() {
+        return Err(anyhow!("会话 {} 没有可用的帧", session_id));
+    }
+
+    // 与分析时相同的采样逻辑，再按网格容量均匀抽取
+    let interval = config
+        .sampling_interval
+        .unwrap_or(crate::llm::AnalysisParams::default().frame_sampling_interval as usize);
+    let mut sampled: Vec<Frame> = crate::llm::sample_frame_indices(frames.len(), interval)
+        .into_iter()
+        .map(|i| frames[i].clone())
+        .collect();
+    if let Some(max_rows) = config.max_rows {
+        let capacity = (config.columns * max_rows) as usize;
+        if sampled.len() > capacity {
+            sampled = spread_evenly(&sampled, capacity);
+        }
+    }
+
+    // 视频分段即会话的关键时刻（与 build_session_summary 一致）
+    let mapper = TimeMapper::from_frames(&frames).ok_or_else(|| anyhow!("会话没有帧"))?;
+    let moments: Vec<Moment> = db
+        .get_video_segments_by_session(session_id)
+        .await?
+        .into_iter()
+        .filter_map(|segment| {
+            Some(Moment {
+                time: mapper.resolve(&segment.start_timestamp)?,
+                description: segment.description,
+            })
+        })
+        .collect();
+    let markers = map_moments_to_frames(&sampled, &moments);
+
+    tokio::fs::create_dir_all(output_dir).await?;
+    let png_path = output_dir.join(format!("session_{}_contact_sheet.png", session_id));
+    let legend_path = png_path.with_extension("txt");
+
+    let legend: Vec<String> = moments
+        .iter()
+        .enumerate()
+        .map(|(i, moment)| {
+            format!(
+                "#{} {} [{}] {}",
+                i + 1,
+                moment.time.format("%H:%M:%S"),
+                TimeMapper::format_offset(mapper.offset_of(moment.time)),
+                moment.description
+            )
+        })
+        .collect();
+
+    let config = config.clone();
+    let output = png_path.clone();
+    tokio::task::spawn_blocking(move || -> Result<()> {
+        let thumbnails: Vec<Option<RgbImage>> = sampled
+            .iter()
+            .map(|frame| load_thumbnail(frame, &config))
+            .collect();
+        let labels: Vec<String> = sampled
+            .iter()
+            .map(|frame| frame.timestamp.format("%H:%M:%S").to_string())
+            .collect();
+        compose_sheet(&thumbnails, &labels, &markers, &config).save(&output)?;
+        Ok(())
+    })
+    .await??;
+    tokio::fs::write(&legend_path, legend.join("\n")).await?;
+
+    info!(
+        "会话 {} 联系表已生成: {} ({} 个关键时刻)",
+        session_id,
+        png_path.display(),
+        moments.len()
+    );
+    Ok(png_path)
+}
+
+/// 从帧列表中均匀抽取 count 帧（保留首尾）
+fn spread_evenly(frames: &[Frame], count: usize) -> Vec<Frame> {
+    if count <= 1 || frames.len() <= count {
+        return frames.iter().take(count.max(1)).cloned().collect();
+    }
+    let last = frames.len() - 1;
+    (0..count)
+        .map(|i| frames[i * last / (count - 1)].clone())
+        .collect()
+}
+
+/// 每个关键时刻对应到时间最接近的采样帧，返回每帧上的关键时刻序号（从 1 开始）
+fn map_moments_to_frames(frames: &[Frame], moments: &[Moment]) -> Vec<Vec<usize>> {
+    let mut markers = vec![Vec::new(); frames.len()];
+    let Some(mapper) = TimeMapper::from_frames(frames) else {
+        return markers;
+    };
+    for (i, moment) in moments.iter().enumerate() {
+        if let Some(idx) = mapper.frame_at(moment.time) {
+            markers[idx].push(i + 1);
+        }
+    }
+    markers
+}
+
+fn load_thumbnail(frame: &Frame, config: &ContactSheetConfig) -> Option<RgbImage> {
+    match load_resized_frame(&frame.file_path, config.thumb_width, config.thumb_height) {
+        Ok(thumbnail) => Some(thumbnail),
+        Err(e) => {
+            // 缺失或损坏的帧以灰色占位，不影响整张联系表
+            warn!("联系表读取帧失败 {}: {}", frame.file_path, e);
+            None
+        }
+    }
+}
+
+/// 读取帧（含已打包的帧）并缩放为指定尺寸（阻塞调用）
+pub(crate) fn load_resized_frame(path: &str, width: u32, height: u32) -> Result<RgbImage> {
+    let bytes = frame_pack::read_frame_blocking(path)?;
+    let image = image::load_from_memory(&bytes)?;
+    Ok(image
+        .resize_exact(width, height, imageops::FilterType::Triangle)
+        .to_rgb8())
+}
+
+/// 拼接联系表：缩略图、时间标签与关键时刻标记
+fn compose_sheet(
+    thumbnails: &[Option<RgbImage>],
+    labels: &[String],
+    markers: &[Vec<usize>],
+    config: &ContactSheetConfig,
+) -> RgbImage {
+    let columns = config.columns.min(thumbnails.len().max(1) as u32);
+    let rows = (thumbnails.len() as u32).div_ceil(columns).max(1);
+    let cell_width = config.thumb_width;
+    let cell_height = config.thumb_height + LABEL_HEIGHT;
+
+    let mut sheet = RgbImage::from_pixel(
+        GAP + columns * (cell_width + GAP),
+        GAP + rows * (cell_height + GAP),
+        BACKGROUND,
+    );
+
+    for (i, thumbnail) in thumbnails.iter().enumerate() {
+        let x = GAP + (i as u32 % columns) * (cell_width + GAP);
+        let y = GAP + (i as u32 / columns) * (cell_height + GAP);
+
+        match thumbnail {
+            Some(thumbnail) => imageops::replace(&mut sheet, thumbnail, x as i64, y as i64),
+            None => fill_rect(
+                &mut sheet,
+                x,
+                y,
+                config.thumb_width,
+                config.thumb_height,
+                PLACEHOLDER,
+            ),
+        }
+
+        if let Some(label) = labels.get(i) {
+            draw_text(&mut sheet, x + 2, y + config.thumb_height + 3, label, TEXT);
+        }
+
+        let moment_ids = markers.get(i).map(Vec::as_slice).unwrap_or_default();
+        if !moment_ids.is_empty() {
+            draw_marker(&mut sheet, x, y, moment_ids, config);
+        }
+    }
+    sheet
+}
+
+/// 关键时刻标记：红框 + 左上角 "#序号" 角标
+fn draw_marker(
+    sheet: &mut RgbImage,
+    x: u32,
+    y: u32,
+    moment_ids: &[usize],
+    config: &ContactSheetConfig,
+) {
+    let (w, h) = (config.thumb_width, config.thumb_height);
+    fill_rect(sheet, x, y, w, MARKER_BORDER, MARKER);
+    fill_rect(sheet, x, y + h - MARKER_BORDER, w, MARKER_BORDER, MARKER);
+    fill_rect(sheet, x, y, MARKER_BORDER, h, MARKER);
+    fill_rect(sheet, x + w - MARKER_BORDER, y, MARKER_BORDER, h, MARKER);
+
+    let badge: Vec<String> = moment_ids.iter().map(|id| format!("#{}", id)).collect();
+    let badge = badge.join(" ");
+    let badge_width = (text_width(&badge) + 6).min(w);
+    fill_rect(sheet, x, y, badge_width, LABEL_HEIGHT, MARKER);
+    draw_text(sheet, x + 3, y + 3, &badge, TEXT);
+}
+
+fn fill_rect(sheet: &mut RgbImage, x: u32, y: u32, width: u32, height: u32, color: Rgb<u8>) {
+    for py in y..(y + height).min(sheet.height()) {
+        for px in x..(x + width).min(sheet.width()) {
+            sheet.put_pixel(px, py, color);
+        }
+    }
+}
+
+fn text_width(text: &str) -> u32 {
+    text.chars().count() as u32 * (GLYPH_WIDTH + 1) * FONT_SCALE
+}
+
+/// 用内置点阵字体绘制文本（仅支持数字、冒号、井号、连字符与空格，其余字符跳过）
+fn draw_text(sheet: &mut RgbImage, x: u32, y: u32, text: &str, color: Rgb<u8>) {
+    for (i, c) in text.chars().enumerate() {
+        let Some(rows) = glyph(c) else {
+            continue;
+        };
+        let origin_x = x + i as u32 * (GLYPH_WIDTH + 1) * FONT_SCALE;
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..GLYPH_WIDTH {
+                if bits & (1 << (GLYPH_WIDTH - 1 - col)) != 0 {
+                    fill_rect(
+                        sheet,
+                        origin_x + col * FONT_SCALE,
+                        y + row as u32 * FONT_SCALE,
+                        FONT_SCALE,
+                        FONT_SCALE,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// 5x7 点阵字形，每行低 5 位从左到右
+fn glyph(c: char) -> Option<[u8; 7]> {
+    let rows = match c {
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        ' ' => [0; 7],
+        _ => return None,
+    };
+    Some(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_at(secs: i64) -> Frame {
+        Frame {
+            id: None,
+            session_id: 1,
+            timestamp: DateTime::from_timestamp(secs, 0).unwrap(),
+            file_path: format!("{}.jpg", secs),
+        }
+    }
+
+    #[test]
+    fn test_moments_map_to_nearest_sampled_frame() {
+        let frames: Vec<Frame> = [0, 30, 60, 90].into_iter().map(frame_at).collect();
+        let moments = [
+            Moment {
+                time: DateTime::from_timestamp(35, 0).unwrap(),
+                description: "提交代码".to_string(),
+            },
+            Moment {
+                time: DateTime::from_timestamp(40, 0).unwrap(),
+                description: "发起评审".to_string(),
+            },
+            Moment {
+                time: DateTime::from_timestamp(200, 0).unwrap(),
+                description: "会议".to_string(),
+            },
+        ];
+
+        let markers = map_moments_to_frames(&frames, &moments);
+        assert_eq!(markers, vec![vec![], vec![1, 2], vec![], vec![3]]);
+
+        let spread = spread_evenly(&frames, 3);
+        let times: Vec<i64> = spread.iter().map(|f| f.timestamp.timestamp()).collect();
+        assert_eq!(times, vec![0, 30, 90]);
+    }
+
+    #[test]
+    fn test_compose_sheet_layout() {
+        let config = ContactSheetConfig {
+            columns: 2,
+            thumb_width: 40,
+            thumb_height: 30,
+            ..Default::default()
+        };
+        let thumbnail = RgbImage::from_pixel(40, 30, Rgb([0, 0, 255]));
+        let thumbnails = vec![Some(thumbnail.clone()), None, Some(thumbnail)];
+        let labels = vec!["09:00:00".to_string(); 3];
+        let markers = vec![vec![], vec![], vec![1]];
+
+        let sheet = compose_sheet(&thumbnails, &labels, &markers, &config);
+        // 3 帧 2 列 => 2 行
+        assert_eq!(sheet.width(), GAP + 2 * (40 + GAP));
+        assert_eq!(sheet.height(), GAP + 2 * (30 + LABEL_HEIGHT + GAP));
+
+        // 普通帧保持原图，缺失帧为占位色，关键时刻帧带红框
+        let second_row_y = GAP + 30 + LABEL_HEIGHT + GAP;
+        assert_eq!(*sheet.get_pixel(GAP + 20, GAP + 20), Rgb([0, 0, 255]));
+        assert_eq!(*sheet.get_pixel(GAP + 40 + GAP + 20, GAP + 20), PLACEHOLDER);
+        assert_eq!(*sheet.get_pixel(GAP + 39, second_row_y + 15), MARKER);
+    }
+}
@@ -0,0 +1,215 @@
+// 帧感知哈希 - 基于解码后的像素计算，重新编码、缩放或调整画质后仍保持接近
+//
+// dHash 比较相邻像素的明暗，计算快；pHash 取 DCT 低频分量，对画质与轻微变化更稳定。
+// 两帧的相似度以哈希的汉明距离衡量，距离不超过阈值即视为同一画面
+
+use anyhow::Result;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+
+/// 哈希算法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameHashAlgorithm {
+    /// 差异哈希
+    #[default]
+    DHash,
+    /// 感知哈希（DCT）
+    PHash,
+}
+
+/// 帧哈希配置
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrameHashConfig {
+    pub algorithm: FrameHashAlgorithm,
+    /// 视为同一画面的最大汉明距离（0-64）
+    pub max_distance: u32,
+}
+
+impl Default for FrameHashConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: FrameHashAlgorithm::DHash,
+            max_distance: 6,
+        }
+    }
+}
+
+impl FrameHashConfig {
+    /// 两个哈希是否视为同一画面
+    pub fn matches(&self, a: u64, b: u64) -> bool {
+        distance(a, b) <= self.max_distance
+    }
+}
+
+/// pHash 缩放尺寸，取其 DCT 左上角 8x8 低频分量
+const PHASH_SIZE: usize = 32;
+
+/// 两个哈希的汉明距离
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// 计算图片的 64 位哈希
+pub fn hash_image(image: &DynamicImage, algorithm: FrameHashAlgorithm) -> u64 {
+    match algorithm {
+        FrameHashAlgorithm::DHash => dhash(image),
+        FrameHashAlgorithm::PHash => phash(image),
+    }
+}
+
+/// 读取帧（支持帧包）并计算哈希（阻塞调用）
+pub fn hash_frame(path: &str, algorithm: FrameHashAlgorithm) -> Result<u64> {
+    let bytes = crate::storage::frame_pack::read_frame_blocking(path)?;
+    let image = image::load_from_memory(&bytes)?;
+    Ok(hash_image(&image, algorithm))
+}
+
+fn dhash(image: &DynamicImage) -> u64 {
+    let small = image
+        .grayscale()
+        .resize_exact(9, 8, FilterType::Triangle)
+        .to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+fn phash(image: &DynamicImage) -> u64 {
+    let small = image
+        .grayscale()
+        .resize_exact(PHASH_SIZE as u32, PHASH_SIZE as u32, FilterType::Triangle)
+        .to_luma8();
+    let pixels: Vec<f64> = small.pixels().map(|p| f64::from(p[0])).collect();
+
+    // 只需要前 8 个频率的 DCT-II 基函数
+    let mut basis = [[0f64; PHASH_SIZE]; 8];
+    for (k, row) in basis.iter_mut().enumerate() {
+        for (n, value) in row.iter_mut().enumerate() {
+            *value = (std::f64::consts::PI * (2 * n + 1) as f64 * k as f64
+                / (2 * PHASH_SIZE) as f64)
+                .cos();
+        }
+    }
+
+    let mut coefficients = [0f64; 64];
+    for (v, row_basis) in basis.iter().enumerate() {
+        for (u, column_basis) in basis.iter().enumerate() {
+            coefficients[v * 8 + u] = pixels
+                .chunks_exact(PHASH_SIZE)
+                .zip(row_basis)
+                .map(|(row, by)| {
+                    by * row
+                        .iter()
+                        .zip(column_basis)
+                        .map(|(pixel, bx)| pixel * bx)
+                        .sum::<f64>()
+                })
+                .sum();
+        }
+    }
+
+    // 以交流分量的中位数为界（直流分量只反映整体亮度，不参与取中位数）
+    let mut ac = coefficients[1..].to_vec();
+    ac.sort_by(|a, b| a.total_cmp(b));
+    let median = ac[ac.len() / 2];
+    coefficients
+        .iter()
+        .fold(0u64, |hash, &c| (hash << 1) | u64::from(c > median))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::jpeg::JpegEncoder;
+    use image::{Rgb, RgbImage};
+
+    /// 横向渐变背景上偏左的亮色圆形区域
+    ///
+    /// 不用棋盘格：其 DCT 低频分量大多接近 0，中位数附近的符号会随重新编码翻转
+    fn sample_image() -> RgbImage {
+        RgbImage::from_fn(320, 240, |x, y| {
+            let (dx, dy) = (x as f64 - 100.0, y as f64 - 90.0);
+            let value = if dx.hypot(dy) < 60.0 {
+                220
+            } else {
+                40 + (x * 2 / 5) as u8
+            };
+            Rgb([value, value, value / 2])
+        })
+    }
+
+    fn encode_jpeg(image: &RgbImage, quality: u8) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        JpegEncoder::new_with_quality(&mut bytes, quality)
+            .encode_image(image)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_reencoded_jpeg_matches_within_threshold() {
+        let dir = tempfile::tempdir().unwrap();
+        let original_path = dir.path().join("original.jpg");
+        std::fs::write(&original_path, encode_jpeg(&sample_image(), 90)).unwrap();
+
+        // 缩小一半并以低画质重新编码：文件字节完全不同，画面相同
+        let decoded = image::open(&original_path).unwrap();
+        let resized = decoded
+            .resize_exact(160, 120, FilterType::Triangle)
+            .to_rgb8();
+        let reencoded_path = dir.path().join("reencoded.jpg");
+        let reencoded = encode_jpeg(&resized, 40);
+        assert_ne!(std::fs::read(&original_path).unwrap(), reencoded);
+        std::fs::write(&reencoded_path, reencoded).unwrap();
+
+        // 左右翻转后画面不同
+        let flipped = image::imageops::flip_horizontal(&sample_image());
+        let flipped_path = dir.path().join("flipped.jpg");
+        std::fs::write(&flipped_path, encode_jpeg(&flipped, 90)).unwrap();
+
+        for algorithm in [FrameHashAlgorithm::DHash, FrameHashAlgorithm::PHash] {
+            let config = FrameHashConfig {
+                algorithm,
+                ..Default::default()
+            };
+            let hash =
+                |path: &std::path::Path| hash_frame(path.to_str().unwrap(), algorithm).unwrap();
+            let original = hash(&original_path);
+            assert!(
+                config.matches(original, hash(&reencoded_path)),
+                "{:?} 距离 {}",
+                algorithm,
+                distance(original, hash(&reencoded_path))
+            );
+            assert!(
+                !config.matches(original, hash(&flipped_path)),
+                "{:?}",
+                algorithm
+            );
+        }
+    }
+
+    #[test]
+    fn test_hash_frame_fails_on_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.jpg");
+        assert!(hash_frame(missing.to_str().unwrap(), FrameHashAlgorithm::PHash).is_err());
+        assert_eq!(
+            serde_json::from_str::<FrameHashConfig>(r#"{"algorithm":"phash"}"#).unwrap(),
+            FrameHashConfig {
+                algorithm: FrameHashAlgorithm::PHash,
+                max_distance: 6,
+            }
+        );
+    }
+}
@@ -0,0 +1,128 @@
+// 帧质量过滤 - 屏保、锁屏、关闭显示器时截到的全黑或纯色画面没有分析价值
+//
+// 按灰度均值判断是否接近全黑，按灰度标准差判断是否接近纯色，
+// 在采样前剔除这类帧，把有限的分析帧数留给真实内容
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// 帧质量过滤配置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrameQualityConfig {
+    pub enabled: bool,
+    /// 灰度均值低于该值视为全黑（0-255）
+    pub min_mean_luminance: f64,
+    /// 灰度标准差低于该值视为纯色画面（0-255）
+    pub min_std_dev: f64,
+}
+
+impl Default for FrameQualityConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_mean_luminance: 10.0,
+            min_std_dev: 3.0,
+        }
+    }
+}
+
+/// 帧的灰度统计
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameStats {
+    pub mean: f64,
+    pub std_dev: f64,
+}
+
+impl FrameQualityConfig {
+    /// 是否为全黑或纯色帧
+    pub fn is_blank(&self, stats: FrameStats) -> bool {
+        stats.mean < self.min_mean_luminance || stats.std_dev < self.min_std_dev
+    }
+}
+
+/// 读取帧（支持帧包）并计算灰度均值与标准差（阻塞调用）
+pub fn frame_stats(path: &str) -> Result<FrameStats> {
+    let bytes = crate::storage::frame_pack::read_frame_blocking(path)?;
+    let luma = image::load_from_memory(&bytes)?.to_luma8();
+    let count = (luma.width() as f64 * luma.height() as f64).max(1.0);
+    let (sum, sum_sq) = luma.pixels().fold((0f64, 0f64), |(sum, sum_sq), p| {
+        let v = f64::from(p[0]);
+        (sum + v, sum_sq + v * v)
+    });
+    let mean = sum / count;
+    let variance = (sum_sq / count - mean * mean).max(0.0);
+    Ok(FrameStats {
+        mean,
+        std_dev: variance.sqrt(),
+    })
+}
+
+/// 返回应保留的帧下标；无法读取的帧保留，交由后续流程处理（阻塞调用）
+pub fn content_frame_indices(paths: &[String], config: &FrameQualityConfig) -> Vec<usize> {
+    if !config.enabled {
+        return (0..paths.len()).collect();
+    }
+    let kept: Vec<usize> = paths
+        .iter()
+        .enumerate()
+        .filter(|(_, path)| {
+            frame_stats(path)
+                .map(|stats| !config.is_blank(stats))
+                .unwrap_or(true)
+        })
+        .map(|(index, _)| index)
+        .collect();
+    if kept.len() < paths.len() {
+        info!(
+            "帧质量过滤：{} 帧中有 {} 帧为全黑或纯色画面，已跳过",
+            paths.len(),
+            paths.len() - kept.len()
+        );
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn test_only_black_frame_is_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let black = dir.path().join("black.png");
+        RgbImage::from_pixel(64, 48, Rgb([2, 2, 2]))
+            .save(&black)
+            .unwrap();
+        let normal = dir.path().join("normal.png");
+        RgbImage::from_fn(64, 48, |x, y| {
+            let value = ((x * 4) as u8).wrapping_add((y * 2) as u8);
+            Rgb([value, 255 - value, 128])
+        })
+        .save(&normal)
+        .unwrap();
+        let white = dir.path().join("white.png");
+        RgbImage::from_pixel(64, 48, Rgb([250, 250, 250]))
+            .save(&white)
+            .unwrap();
+
+        let paths: Vec<String> = [&black, &normal, &white]
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        let config = FrameQualityConfig::default();
+        assert_eq!(content_frame_indices(&paths, &config), vec![1]);
+        assert!(frame_stats(&paths[0]).unwrap().mean < 3.0);
+
+        // 关闭后全部保留；读取失败的帧不过滤
+        let disabled = FrameQualityConfig {
+            enabled: false,
+            ..Default::default()
+        };
+        assert_eq!(content_frame_indices(&paths, &disabled), vec![0, 1, 2]);
+        let missing = vec![dir.path().join("missing.png").to_string_lossy().to_string()];
+        assert_eq!(content_frame_indices(&missing, &config), vec![0]);
+    }
+}
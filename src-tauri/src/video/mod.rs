@@ -1,7 +1,11 @@
 // 视频处理模块 - 负责将截图序列生成视频
 
+pub mod contact_sheet;
 pub mod ffmpeg_helper;
+pub mod frame_hash;
+pub mod frame_quality;
 pub mod processor;
+pub mod session_thumbnail;
 
 pub use processor::{filter_frames_by_interval, VideoConfig, VideoFormat, VideoProcessor};
 
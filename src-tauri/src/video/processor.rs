@@ -146,6 +146,18 @@ impl VideoProcessor {
             return Err(anyhow::anyhow!("没有可用的帧"));
         }
 
+        // FFmpeg 只能读取真实文件，归档内的帧先解出到临时目录（函数返回时自动删除）
+        let (_unpacked_dir, frames) = if frames
+            .iter()
+            .any(|frame| crate::storage::frame_pack::is_packed_path(frame))
+        {
+            let dir = tempfile::tempdir_in(&self.temp_dir)?;
+            let unpacked = crate::storage::frame_pack::unpack_to_dir(&frames, dir.path())?;
+            (Some(dir), unpacked)
+        } else {
+            (None, frames)
+        };
+
         // 快速检测图片分辨率：只检查前几张图片，假设所有图片分辨率相同
         // 取输入图片和配置分辨率的最大值，确保容器足够大
         let mut resolution = config.resolution;
@@ -0,0 +1,188 @@
+// 会话封面 - 分析完成后从代表帧生成小尺寸缩略图，随会话总结保存
+//
+// 代表帧可取首帧、中间帧或重要性最高的关键时刻对应的帧；
+// 原始帧已被清理或代表帧无法读取时不生成封面，前端显示为空
+
+use super::contact_sheet::load_resized_frame;
+use crate::llm::KeyMoment;
+use crate::models::{SessionThumbnailSettings, ThumbnailStrategy};
+use crate::storage::{local_now, Database, Frame, SessionThumbnailRecord};
+use crate::time_mapper::TimeMapper;
+use anyhow::{anyhow, Result};
+use image::codecs::jpeg::JpegEncoder;
+use tracing::{info, warn};
+
+/// 封面的 JPEG 质量
+const THUMBNAIL_JPEG_QUALITY: u8 = 80;
+
+/// 选出代表帧的下标；没有帧时返回 None
+///
+/// 关键时刻按重要性取最高者（相同时取较早的），时间无法解析或没有关键时刻时退回中间帧
+pub fn select_representative_frame(
+    frames: &[Frame],
+    key_moments: &[KeyMoment],
+    strategy: ThumbnailStrategy,
+) -> Option<usize> {
+    let mapper = TimeMapper::from_frames(frames)?;
+    let middle = (frames.len() - 1) / 2;
+    match strategy {
+        ThumbnailStrategy::First => Some(0),
+        ThumbnailStrategy::Middle => Some(middle),
+        ThumbnailStrategy::KeyMoment => {
+            let best = key_moments
+                .iter()
+                .filter_map(|moment| Some((moment.importance, mapper.resolve(&moment.time)?)))
+                .fold(None, |best: Option<(u8, _)>, candidate| match best {
+                    Some(best) if best.0 >= candidate.0 => Some(best),
+                    _ => Some(candidate),
+                });
+            match best {
+                Some((_, time)) => mapper.frame_at(time),
+                None => Some(middle),
+            }
+        }
+    }
+}
+
+/// 读取帧并缩放为封面尺寸，编码为 JPEG（阻塞调用）
+fn render_thumbnail(path: &str, width: u32, height: u32) -> Result<Vec<u8>> {
+    let thumbnail = load_resized_frame(path, width, height)?;
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, THUMBNAIL_JPEG_QUALITY).encode_image(&thumbnail)?;
+    Ok(bytes)
+}
+
+/// 为会话生成并保存封面，返回保存的记录；原始帧已清理或代表帧不可用时返回 None
+pub async fn generate_session_thumbnail(
+    db: &Database,
+    session_id: i64,
+    key_moments: &[KeyMoment],
+    settings: &SessionThumbnailSettings,
+) -> Result<Option<SessionThumbnailRecord>> {
+    for (name, size) in [("width", settings.width), ("height", settings.height)] {
+        if !(32..=1920).contains(&size) {
+            return Err(anyhow!("封面 {} 必须在 32-1920 之间: {}", name, size));
+        }
+    }
+
+    // 清理后只剩关键时刻帧，首帧/中间帧已不存在，不用剩余帧冒充
+    if db.get_frame_prune(session_id).await?.is_some() {
+        info!("会话 {} 的原始帧已清理，不生成封面", session_id);
+        return Ok(None);
+    }
+
+    let frames = db.get_frames_by_session(session_id).await?;
+    let Some(index) = select_representative_frame(&frames, key_moments, settings.strategy) else {
+        info!("会话 {} 没有可用的帧，不生成封面", session_id);
+        return Ok(None);
+    };
+    let frame = frames[index].clone();
+
+    let (width, height) = (settings.width, settings.height);
+    let path = frame.file_path.clone();
+    let rendered =
+        tokio::task::spawn_blocking(move || render_thumbnail(&path, width, height)).await?;
+    let image_data = match rendered {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            warn!(
+                "会话 {} 的代表帧不可用，不生成封面 {}: {}",
+                session_id, frame.file_path, e
+            );
+            return Ok(None);
+        }
+    };
+
+    let record = SessionThumbnailRecord {
+        session_id,
+        frame_timestamp: frame.timestamp,
+        width: i64::from(width),
+        height: i64::from(height),
+        image_data,
+        created_at: local_now(),
+    };
+    db.save_session_thumbnail(&record).await?;
+    info!(
+        "会话 {} 封面已生成: {:?} 帧 #{} ({} 字节)",
+        session_id,
+        settings.strategy,
+        index,
+        record.image_data.len()
+    );
+    Ok(Some(record))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Duration, Utc};
+    use image::{Rgb, RgbImage};
+
+    fn start() -> DateTime<Utc> {
+        "2025-01-01T09:00:00Z".parse().unwrap()
+    }
+
+    fn frames(count: i64) -> Vec<Frame> {
+        (0..count)
+            .map(|i| Frame {
+                id: None,
+                session_id: 1,
+                timestamp: start() + Duration::seconds(i * 10),
+                file_path: format!("{}.jpg", i),
+            })
+            .collect()
+    }
+
+    fn moment(time: &str, importance: u8) -> KeyMoment {
+        KeyMoment {
+            time: time.to_string(),
+            description: String::new(),
+            importance,
+        }
+    }
+
+    #[test]
+    fn test_representative_frame_strategies() {
+        let frames = frames(5);
+        let moments = [
+            moment("00:12", 3),
+            moment("2025-01-01T09:00:31+08:00", 5),
+            moment("00:40", 5),
+            moment("无法解析", 5),
+        ];
+        let select = |moments: &[KeyMoment], strategy| {
+            select_representative_frame(&frames, moments, strategy)
+        };
+
+        assert_eq!(select(&moments, ThumbnailStrategy::First), Some(0));
+        assert_eq!(select(&moments, ThumbnailStrategy::Middle), Some(2));
+        // 重要性相同时取较早的关键时刻，再映射到最近的帧
+        assert_eq!(select(&moments, ThumbnailStrategy::KeyMoment), Some(3));
+        // 没有可用的关键时刻时退回中间帧
+        assert_eq!(
+            select(&[moment("无法解析", 5)], ThumbnailStrategy::KeyMoment),
+            Some(2)
+        );
+        assert_eq!(
+            select_representative_frame(&[], &moments, ThumbnailStrategy::First),
+            None
+        );
+    }
+
+    #[test]
+    fn test_render_thumbnail_resizes_and_fails_on_missing_frame() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("frame.png");
+        RgbImage::from_pixel(640, 360, Rgb([10, 120, 200]))
+            .save(&path)
+            .unwrap();
+
+        let bytes = render_thumbnail(path.to_str().unwrap(), 160, 90).unwrap();
+        let thumbnail = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (160, 90));
+
+        // 帧文件已被删除时返回错误，由调用方留空封面
+        let missing = dir.path().join("missing.jpg");
+        assert!(render_thumbnail(missing.to_str().unwrap(), 160, 90).is_err());
+    }
+}
@@ -0,0 +1,208 @@
+// 分析完成通知 - 会话总结入库后向配置的 URL 推送 JSON，供 Slack、个人看板等集成使用
+//
+// 推送在后台任务中进行，失败只记录日志，不会阻塞或影响分析。请求体中的总结按脱敏规则
+// 再遮盖一次；配置了密钥时附带 HMAC-SHA256 签名头，接收方用同一密钥对请求体计算签名即可校验来源。
+// 网络错误、超时或非 2xx 响应按指数退避重试，重试用尽后丢弃
+
+use crate::llm::plugin::SessionSummary;
+use crate::llm::{redaction, RedactionConfig};
+use crate::models::WebhookSettings;
+use anyhow::{anyhow, Result};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// 签名请求头，值为 "sha256=<十六进制签名>"
+pub const SIGNATURE_HEADER: &str = "X-Screen-Analyzer-Signature";
+
+/// 推送的事件名
+const EVENT_ANALYSIS_COMPLETED: &str = "analysis.completed";
+
+/// 对请求体计算签名头的值
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC 接受任意长度的密钥");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// 构造请求体：按脱敏规则遮盖总结后序列化
+pub fn build_payload(
+    session_id: i64,
+    summary: &SessionSummary,
+    redaction: &RedactionConfig,
+) -> Result<Vec<u8>> {
+    let mut summary = summary.clone();
+    redaction::redact_summary(redaction, &mut summary);
+    Ok(serde_json::to_vec(&serde_json::json!({
+        "event": EVENT_ANALYSIS_COMPLETED,
+        "session_id": session_id,
+        "summary": summary,
+    }))?)
+}
+
+/// 发送请求，失败时按配置重试；成功时返回尝试次数
+pub async fn deliver(settings: &WebhookSettings, body: Vec<u8>) -> Result<u32> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(settings.timeout_secs.max(1)))
+        .build()?;
+    let signature = settings
+        .secret
+        .as_deref()
+        .filter(|secret| !secret.is_empty())
+        .map(|secret| sign(secret, &body));
+    let attempts = settings.max_retries.saturating_add(1);
+    let mut delay = Duration::from_millis(settings.retry_delay_ms);
+    let mut attempt = 1;
+    loop {
+        let mut request = client
+            .post(&settings.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+        if let Some(signature) = &signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(attempt),
+            Ok(response) => anyhow!("HTTP {}", response.status()),
+            Err(e) => e.into(),
+        };
+        if attempt >= attempts {
+            return Err(error.context(format!("推送 {} 次均失败", attempts)));
+        }
+        warn!(
+            "推送分析完成通知失败（第 {}/{} 次），{} 毫秒后重试: {}",
+            attempt,
+            attempts,
+            delay.as_millis(),
+            error
+        );
+        tokio::time::sleep(delay).await;
+        delay = delay.saturating_mul(2);
+        attempt += 1;
+    }
+}
+
+/// 在后台推送分析完成通知；未启用或未配置 URL 时不推送
+pub fn dispatch(
+    settings: Option<WebhookSettings>,
+    redaction: &RedactionConfig,
+    session_id: i64,
+    summary: &SessionSummary,
+) {
+    let Some(settings) = settings.filter(|s| s.enabled && !s.url.trim().is_empty()) else {
+        return;
+    };
+    let body = match build_payload(session_id, summary, redaction) {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("构造会话 {} 的分析完成通知失败: {}", session_id, e);
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        match deliver(&settings, body).await {
+            Ok(attempts) => info!(
+                "已推送会话 {} 的分析完成通知（尝试 {} 次）",
+                session_id, attempts
+            ),
+            Err(e) => warn!("会话 {} 的分析完成通知已丢弃: {:#}", session_id, e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{self, Request, Response};
+    use tokio::sync::mpsc;
+
+    /// 前 failures 次请求返回 500，之后返回 200；收到的请求发送到通道
+    async fn serve(failures: usize) -> (String, mpsc::UnboundedReceiver<Request>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut calls = 0;
+        let url = test_server::serve(move |request| {
+            calls += 1;
+            let _ = tx.send(request);
+            if calls <= failures {
+                Response::with_status("500 Internal Server Error", "")
+            } else {
+                Response::ok("")
+            }
+        })
+        .await;
+        (format!("{url}/hook"), rx)
+    }
+
+    fn settings(url: String) -> WebhookSettings {
+        WebhookSettings {
+            enabled: true,
+            url,
+            secret: Some("hook-secret".to_string()),
+            timeout_secs: 5,
+            max_retries: 2,
+            retry_delay_ms: 10,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_completed_analysis_posts_signed_summary() {
+        let (url, mut requests) = serve(1).await;
+        let summary = SessionSummary {
+            title: "回复邮件".to_string(),
+            summary: "给 alice@example.com 回复了项目进度".to_string(),
+            ..Default::default()
+        };
+        dispatch(
+            Some(settings(url)),
+            &RedactionConfig::default(),
+            42,
+            &summary,
+        );
+
+        // 第一次返回 500，重试后成功
+        let first = requests.recv().await.unwrap();
+        let request = requests.recv().await.unwrap();
+        assert_eq!(first, request);
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/hook")
+        );
+        let signature = sign("hook-secret", &request.body);
+        assert_eq!(request.header(SIGNATURE_HEADER), Some(signature.as_str()));
+
+        let payload: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(payload["event"], "analysis.completed");
+        assert_eq!(payload["session_id"], 42);
+        assert_eq!(payload["summary"]["title"], "回复邮件");
+        // 按脱敏规则遮盖后发送
+        let sent = payload["summary"]["summary"].as_str().unwrap();
+        assert!(!sent.contains("alice@example.com"), "{}", sent);
+    }
+
+    #[tokio::test]
+    async fn test_failing_webhook_is_retried_then_dropped() {
+        let (url, mut requests) = serve(usize::MAX).await;
+        let err = deliver(&settings(url), b"{}".to_vec()).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("HTTP 500"));
+        // 首次请求加 2 次重试
+        for _ in 0..3 {
+            requests.recv().await.unwrap();
+        }
+        assert!(requests.try_recv().is_err());
+
+        // 未启用时不推送
+        let (url, mut requests) = serve(0).await;
+        let mut disabled = settings(url);
+        disabled.enabled = false;
+        dispatch(
+            Some(disabled),
+            &RedactionConfig::default(),
+            1,
+            &SessionSummary::default(),
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(requests.try_recv().is_err());
+    }
+}